@@ -36,6 +36,20 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("ABI of contract ID {contract_account_id} is not available on the node")]
+    NoContractAbi {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("ABI of contract ID {contract_account_id} has hash {abi_hash}, but the contract code declares {declared_hash}")]
+    AbiHashMismatch {
+        contract_account_id: near_primitives::types::AccountId,
+        abi_hash: near_primitives::hash::CryptoHash,
+        declared_hash: near_primitives::hash::CryptoHash,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("Access key for public key {public_key} does not exist while viewing")]
     UnknownAccessKey {
         public_key: near_crypto::PublicKey,
@@ -60,6 +74,12 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Viewing the state of account {requested_account_id} took too long")]
+    QueryTimeout {
+        requested_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
 }
 
 #[derive(Debug)]
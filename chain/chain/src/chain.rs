@@ -31,8 +31,9 @@ use near_primitives::sharding::{
 use near_primitives::syncing::{
     get_num_state_parts, ReceiptProofResponse, RootProof, ShardStateSyncResponseHeader,
     ShardStateSyncResponseHeaderV1, ShardStateSyncResponseHeaderV2, StateHeaderKey, StatePartKey,
+    StateSplitProgress,
 };
-use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
+use near_primitives::transaction::{ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
     AccountId, Balance, BlockExtra, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash,
@@ -50,9 +51,11 @@ use near_store::{ColState, ColStateHeaders, ColStateParts, ShardTries, StoreUpda
 use near_primitives::state_record::StateRecord;
 
 use crate::crypto_hash_timer::CryptoHashTimer;
+use crate::gas_attribution;
 use crate::lightclient::get_epoch_block_producers_view;
 use crate::migrations::check_if_block_is_first_with_chunk_of_version;
 use crate::missing_chunks::{BlockLike, MissingChunksPool};
+use crate::state_witness::record_chunk_state_witness_size;
 use crate::store::{ChainStore, ChainStoreAccess, ChainStoreUpdate, GCMode, SavedStoreUpdate};
 use crate::types::{
     AcceptedBlock, ApplySplitStateResult, ApplySplitStateResultOrStateChanges,
@@ -67,9 +70,11 @@ use crate::{byzantine_assert, create_light_client_block_view, Doomslug};
 use crate::{metrics, DoomslugThresholdMode};
 use actix::Message;
 use delay_detector::DelayDetector;
+use near_performance_metrics::runtime_stats::ThreadPoolCounters;
 use near_primitives::shard_layout::{
     account_id_to_shard_id, account_id_to_shard_uid, ShardLayout, ShardUId,
 };
+use once_cell::sync::Lazy;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 /// Maximum number of orphans chain can store.
@@ -911,7 +916,9 @@ impl Chain {
             .map(|_| ())
     }
 
-    fn validate_block_impl(
+    /// Checks the chunk header signatures and the roots of the block.  It doesn't need the chain,
+    /// so that it can also run off the client actor thread.
+    pub fn validate_block_impl(
         runtime_adapter: &dyn RuntimeAdapter,
         genesis_block: &Block,
         block: &Block,
@@ -1076,6 +1083,26 @@ impl Chain {
         chain_update.commit()
     }
 
+    /// Processes headers like `sync_block_headers` and moves the head along with them.  Used by
+    /// header-only nodes, which never process the bodies of the blocks.
+    pub fn sync_headers_only(
+        &mut self,
+        mut headers: Vec<BlockHeader>,
+        on_challenge: &mut dyn FnMut(ChallengeBody),
+    ) -> Result<Option<Tip>, Error> {
+        headers.sort_by_key(|header| header.height());
+        self.sync_block_headers(headers.clone(), on_challenge)?;
+        let mut new_head = None;
+        for header in headers.iter() {
+            let mut chain_update = self.chain_update();
+            if let Some(tip) = chain_update.update_head_from_header(header)? {
+                new_head = Some(tip);
+            }
+            chain_update.commit()?;
+        }
+        Ok(new_head)
+    }
+
     /// Returns if given block header is on the current chain.
     pub fn is_on_current_chain(&mut self, header: &BlockHeader) -> Result<(), Error> {
         let chain_header = self.get_header_by_height(header.height())?;
@@ -1284,8 +1311,10 @@ impl Chain {
 
         match maybe_new_head {
             Ok(head) => {
+                let commit_start = Instant::now();
                 chain_update.chain_store_update.save_block_height_processed(block_height);
                 chain_update.commit()?;
+                observe_stage_time("commit", commit_start);
 
                 self.pending_states_to_patch = None;
 
@@ -2156,6 +2185,7 @@ impl Chain {
         &mut self,
         sync_hash: &CryptoHash,
         shard_id: ShardId,
+        progress: Arc<StateSplitProgress>,
         state_split_scheduler: &dyn Fn(StateSplitRequest),
     ) -> Result<(), Error> {
         let (epoch_id, next_epoch_id) = {
@@ -2176,6 +2206,7 @@ impl Chain {
             shard_uid,
             state_root: state_root,
             next_epoch_shard_layout,
+            progress,
         });
 
         Ok(())
@@ -2970,6 +3001,75 @@ impl Chain {
             .ok_or_else(|| ErrorKind::DBNotFoundErr(format!("EXECUTION OUTCOME: {}", id)).into())
     }
 
+    /// Re-applies the chunk in which the receipt with the given id was executed, on top of the
+    /// same state and with the same incoming receipts.  Returns the hash of the block containing
+    /// the chunk, the shard id and the outcome of the receipt.  Nothing is written to the store.
+    ///
+    /// Requires the state before the chunk, so unless the node is archival only works for recent
+    /// receipts.
+    pub fn replay_receipt(
+        &mut self,
+        receipt_id: &CryptoHash,
+    ) -> Result<(CryptoHash, ShardId, ExecutionOutcomeWithId), Error> {
+        let outcome = self.get_execution_outcome(receipt_id)?;
+        let block = self.get_block(&outcome.block_hash)?.clone();
+        let prev_block = self.get_block(block.header().prev_hash())?.clone();
+        let shard_id = self.runtime_adapter.account_id_to_shard_id(
+            &outcome.outcome_with_id.outcome.executor_id,
+            block.header().epoch_id(),
+        )?;
+        let chunk_header = block.chunks()[shard_id as usize].clone();
+        if chunk_header.height_included() != block.header().height() {
+            return Err(ErrorKind::Other(format!(
+                "block {} has no new chunk for shard {}",
+                block.hash(),
+                shard_id
+            ))
+            .into());
+        }
+        let prev_chunk_height_included = prev_block.chunks()[shard_id as usize].height_included();
+        let receipt_proof_response = self.store.store_update().get_incoming_receipts_for_shard(
+            shard_id,
+            *block.hash(),
+            prev_chunk_height_included,
+        )?;
+        let receipts = collect_receipts_from_response(&receipt_proof_response);
+        let chunk = self.get_chunk_clone_from_header(&chunk_header)?;
+        let is_first_block_with_chunk_of_version = check_if_block_is_first_with_chunk_of_version(
+            &mut self.store,
+            self.runtime_adapter.as_ref(),
+            prev_block.hash(),
+            shard_id,
+        )?;
+        let apply_result = self.runtime_adapter.replay_transactions(
+            shard_id,
+            &chunk_header.prev_state_root(),
+            chunk_header.height_included(),
+            block.header().raw_timestamp(),
+            prev_block.hash(),
+            block.hash(),
+            &receipts,
+            chunk.transactions(),
+            chunk_header.validator_proposals(),
+            prev_block.header().gas_price(),
+            chunk_header.gas_limit(),
+            block.header().challenges_result(),
+            *block.header().random_value(),
+            is_first_block_with_chunk_of_version,
+        )?;
+        let replayed_outcome = apply_result
+            .outcomes
+            .into_iter()
+            .find(|outcome| &outcome.id == receipt_id)
+            .ok_or_else(|| {
+                Error::from(ErrorKind::Other(format!(
+                    "receipt {} was not executed when replaying the chunk",
+                    receipt_id
+                )))
+            })?;
+        Ok((*block.hash(), shard_id, replayed_outcome))
+    }
+
     /// Retrieve the up to `max_headers_returned` headers on the main chain
     /// `hashes`: a list of block "locators". `hashes` should be ordered from older blocks to
     ///           more recent blocks. This function will find the first block in `hashes`
@@ -3134,6 +3234,8 @@ impl<'a> ChainAccess for ChainUpdate<'a> {
 pub struct SameHeightResult {
     shard_uid: ShardUId,
     gas_limit: Gas,
+    /// Number of the transactions of the chunk, whose outcomes come first.
+    num_transactions: usize,
     apply_result: ApplyTransactionResult,
     apply_split_result_or_state_changes: Option<ApplySplitStateResultOrStateChanges>,
 }
@@ -3467,6 +3569,13 @@ impl<'a> ChainUpdate<'a> {
         prev_block: &Block,
         work: Vec<Box<dyn FnOnce() -> Result<ApplyChunkResult, Error> + Send + 'static>>,
     ) -> Result<(), Error> {
+        let _span = tracing::info_span!(
+            target: "chain",
+            "apply_chunks",
+            block_hash = %block.hash(),
+            num_chunks = work.len()
+        )
+        .entered();
         let apply_results = do_apply_chunks(work);
         self.apply_chunk_postprocessing(block, prev_block, apply_results)
     }
@@ -3691,6 +3800,7 @@ impl<'a> ChainUpdate<'a> {
                     let random_seed = *block.header().random_value();
                     let height = chunk_header.height_included();
                     let prev_block_hash = chunk_header.prev_block_hash();
+                    let epoch_id = block.header().epoch_id().clone();
                     #[cfg(feature = "sandbox")]
                     let states_to_patch = self.states_to_patch.take();
 
@@ -3718,6 +3828,13 @@ impl<'a> ChainUpdate<'a> {
                             None,
                         ) {
                             Ok(apply_result) => {
+                                record_chunk_state_witness_size(
+                                    chunk.chunk_hash(),
+                                    apply_result.state_witness_size,
+                                );
+                                gas_attribution::record_outcomes(&epoch_id, &apply_result.outcomes);
+                                runtime_adapter
+                                    .update_delayed_receipts_metrics(shard_uid, &apply_result);
                                 let apply_split_result_or_state_changes =
                                     if will_shard_layout_change {
                                         Some(Self::apply_split_state_changes(
@@ -3733,6 +3850,7 @@ impl<'a> ChainUpdate<'a> {
                                 Ok(ApplyChunkResult::SameHeight(SameHeightResult {
                                     gas_limit,
                                     shard_uid,
+                                    num_transactions: chunk.transactions().len(),
                                     apply_result,
                                     apply_split_result_or_state_changes,
                                 }))
@@ -3972,6 +4090,7 @@ impl<'a> ChainUpdate<'a> {
             ApplyChunkResult::SameHeight(SameHeightResult {
                 gas_limit,
                 shard_uid,
+                num_transactions,
                 apply_result,
                 apply_split_result_or_state_changes,
             }) => {
@@ -3999,6 +4118,14 @@ impl<'a> ChainUpdate<'a> {
                     apply_result.outgoing_receipts,
                 );
                 // Save receipt and transaction results.
+                let block_height = self.chain_store_update.get_block_header(&block_hash)?.height();
+                self.chain_store_update.save_account_history(
+                    block_height,
+                    &block_hash,
+                    shard_id,
+                    &apply_result.outcomes,
+                    num_transactions,
+                );
                 self.chain_store_update.save_outcomes_with_proofs(
                     &block_hash,
                     shard_id,
@@ -4106,10 +4233,15 @@ impl<'a> ChainUpdate<'a> {
         provenance: &Provenance,
         on_challenge: &mut dyn FnMut(ChallengeBody),
     ) -> Result<Option<Tip>, Error> {
-        let _span =
-            tracing::debug_span!(target: "chain", "Process block", "#{}", block.header().height())
-                .entered();
+        let _span = tracing::info_span!(
+            target: "chain",
+            "process_block",
+            block_hash = %block.hash(),
+            height = block.header().height()
+        )
+        .entered();
         debug!(target: "chain", "Block {}, approvals: {}, me: {:?}", block.hash(), block.header().num_approvals(), me);
+        let verify_start = Instant::now();
 
         // Check that we know the epoch of the block before we try to get the header
         // (so that a block from unknown epoch doesn't get marked as an orphan)
@@ -4250,9 +4382,12 @@ impl<'a> ChainUpdate<'a> {
             }
         }
 
+        observe_stage_time("verify", verify_start);
+
         // If we have the state for shards in the next epoch already downloaded, apply the state transition
         // for these states as well
         // otherwise put the block into the permanent storage, waiting for be caught up
+        let apply_start = Instant::now();
         let apply_chunk_work = if is_caught_up {
             self.apply_chunks_preprocessing(me, block, &prev_block, ApplyChunksMode::IsCaughtUp)?
         } else {
@@ -4262,6 +4397,7 @@ impl<'a> ChainUpdate<'a> {
         };
 
         self.apply_chunks_and_process_results(block, &prev_block, apply_chunk_work)?;
+        observe_stage_time("apply", apply_start);
 
         // Verify that proposals from chunks match block header proposals.
         let block_height = block.header().height();
@@ -4305,31 +4441,53 @@ impl<'a> ChainUpdate<'a> {
         self.chain_store_update.inc_block_refcount(block.header().prev_hash())?;
 
         // Update the chain head if it's the new tip
+        let head_update_start = Instant::now();
         let res = self.update_head(block.header())?;
+        observe_stage_time("head_update", head_update_start);
 
         if res.is_some() {
-            // On the epoch switch record the epoch light client block
-            // Note that we only do it if `res.is_some()`, i.e. if the current block is the head.
-            // This is necessary because the computation of the light client block relies on
-            // `ColNextBlockHash`-es populated, and they are only populated for the canonical
-            // chain. We need to be careful to avoid a situation when the first block of the epoch
-            // never becomes a tip of the canonical chain.
-            // Presently the epoch boundary is defined by the height, and the fork choice rule
-            // is also just height, so the very first block to cross the epoch end is guaranteed
-            // to be the head of the chain, and result in the light client block produced.
-            if block.header().epoch_id() != &prev_epoch_id {
-                let prev = self.get_previous_header(block.header())?.clone();
-                if prev.last_final_block() != &CryptoHash::default() {
-                    let light_client_block = self.create_light_client_block(&prev)?;
-                    self.chain_store_update
-                        .save_epoch_light_client_block(&prev_epoch_id.0, light_client_block);
-                }
-            }
+            self.save_epoch_light_client_block_on_epoch_switch(block.header(), &prev_epoch_id)?;
         }
 
         Ok(res)
     }
 
+    /// Moves the head to the header if it's the new tip, without the body of the block.  Used
+    /// by header-only nodes.
+    fn update_head_from_header(&mut self, header: &BlockHeader) -> Result<Option<Tip>, Error> {
+        let prev_epoch_id = self.get_previous_header(header)?.epoch_id().clone();
+        let res = self.update_head(header)?;
+        if res.is_some() {
+            self.save_epoch_light_client_block_on_epoch_switch(header, &prev_epoch_id)?;
+        }
+        Ok(res)
+    }
+
+    /// On the epoch switch records the epoch light client block.  Must only be called if the
+    /// header has just become the head.
+    fn save_epoch_light_client_block_on_epoch_switch(
+        &mut self,
+        header: &BlockHeader,
+        prev_epoch_id: &EpochId,
+    ) -> Result<(), Error> {
+        // We only do it for the head. This is necessary because the computation of the light
+        // client block relies on `ColNextBlockHash`-es populated, and they are only populated for
+        // the canonical chain. We need to be careful to avoid a situation when the first block of
+        // the epoch never becomes a tip of the canonical chain.
+        // Presently the epoch boundary is defined by the height, and the fork choice rule
+        // is also just height, so the very first block to cross the epoch end is guaranteed
+        // to be the head of the chain, and result in the light client block produced.
+        if header.epoch_id() != prev_epoch_id {
+            let prev = self.get_previous_header(header)?.clone();
+            if prev.last_final_block() != &CryptoHash::default() {
+                let light_client_block = self.create_light_client_block(&prev)?;
+                self.chain_store_update
+                    .save_epoch_light_client_block(&prev_epoch_id.0, light_client_block);
+            }
+        }
+        Ok(())
+    }
+
     pub fn create_light_client_block(
         &mut self,
         header: &BlockHeader,
@@ -4591,6 +4749,9 @@ impl<'a> ChainUpdate<'a> {
     /// Directly updates the head if we've just appended a new block to it or handle
     /// the situation where the block has higher height to have a fork
     fn update_head(&mut self, header: &BlockHeader) -> Result<Option<Tip>, Error> {
+        let _span =
+            tracing::info_span!(target: "chain", "update_head", block_hash = %header.hash())
+                .entered();
         // if we made a fork with higher height than the head (which should also be true
         // when extending the head), update it
         self.update_final_head_from_block(header)?;
@@ -4738,6 +4899,7 @@ impl<'a> ChainUpdate<'a> {
         let (outcome_root, outcome_proofs) =
             ApplyTransactionResult::compute_outcomes_proof(&apply_result.outcomes);
 
+        let num_transactions = chunk.transactions().len();
         self.chain_store_update.save_chunk(chunk);
 
         self.chain_store_update.save_trie_changes(apply_result.trie_changes);
@@ -4758,6 +4920,13 @@ impl<'a> ChainUpdate<'a> {
             apply_result.outgoing_receipts,
         );
         // Saving transaction results.
+        self.chain_store_update.save_account_history(
+            block_header.height(),
+            block_header.hash(),
+            shard_id,
+            &apply_result.outcomes,
+            num_transactions,
+        );
         self.chain_store_update.save_outcomes_with_proofs(
             block_header.hash(),
             shard_id,
@@ -4834,6 +5003,16 @@ impl<'a> ChainUpdate<'a> {
         block_hash: Option<&CryptoHash>,
     ) -> Result<ChallengesResult, Error> {
         debug!(target: "chain", "Verifying challenges {:?}", challenges);
+        if !challenges.is_empty() {
+            let protocol_version = self.runtime_adapter.get_epoch_protocol_version(epoch_id)?;
+            // A challenge slashes once, so with challenges enabled a block can't include it twice.
+            if checked_feature!("protocol_feature_challenges", Challenges, protocol_version) {
+                let mut hashes = HashSet::new();
+                if !challenges.iter().all(|challenge| hashes.insert(challenge.hash)) {
+                    return Err(ErrorKind::InvalidChallenge.into());
+                }
+            }
+        }
         let mut result = vec![];
         for challenge in challenges.iter() {
             match validate_challenge(&*self.runtime_adapter, epoch_id, prev_block_hash, challenge) {
@@ -4878,10 +5057,40 @@ impl<'a> ChainUpdate<'a> {
     }
 }
 
+fn observe_stage_time(stage: &str, start: Instant) {
+    metrics::BLOCK_PROCESSING_STAGE_TIME
+        .with_label_values(&[stage])
+        .observe(start.elapsed().as_secs_f64());
+}
+
+/// Pool on which the chunks of a block are applied, one task per shard.  It is separate from the
+/// global rayon pool so that applying chunks doesn't wait for other parallel work, e.g. signature
+/// verification, and idle threads steal the shards not started yet from the busy ones.
+static APPLY_CHUNKS_POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
+    rayon::ThreadPoolBuilder::new()
+        .thread_name(|i| format!("apply_chunks_{}", i))
+        .build()
+        .expect("failed to create the apply chunks thread pool")
+});
+static APPLY_CHUNKS_POOL_COUNTERS: Lazy<Arc<ThreadPoolCounters>> =
+    Lazy::new(|| ThreadPoolCounters::new("apply_chunks"));
+
+/// Applies the chunks of a block concurrently.  Results are returned in the order of `work`, and
+/// the next block is only applied after all its chunks are, so the order of the chunks of every
+/// shard is preserved.
 pub fn do_apply_chunks(
     work: Vec<Box<dyn FnOnce() -> Result<ApplyChunkResult, Error> + Send>>,
 ) -> Vec<Result<ApplyChunkResult, Error>> {
-    work.into_par_iter().map(|task| task()).collect::<Vec<_>>()
+    let counters = &*APPLY_CHUNKS_POOL_COUNTERS;
+    counters.add_queued(work.len());
+    APPLY_CHUNKS_POOL.install(|| {
+        work.into_par_iter()
+            .map(|task| {
+                let _running = counters.start_task();
+                task()
+            })
+            .collect::<Vec<_>>()
+    })
 }
 
 pub fn collect_receipts<'a, T>(receipt_proofs: T) -> Vec<Receipt>
@@ -4943,6 +5152,8 @@ pub struct StateSplitRequest {
     pub shard_uid: ShardUId,
     pub state_root: StateRoot,
     pub next_epoch_shard_layout: ShardLayout,
+    /// Updated while the states are built, to report the progress of the split.
+    pub progress: Arc<StateSplitProgress>,
 }
 
 #[derive(Message)]
@@ -8,6 +8,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::time::Clock;
 use near_primitives::types::{AccountId, ApprovalStake, Balance, BlockHeight, BlockHeightDelta};
 use near_primitives::validator_signer::ValidatorSigner;
+use tracing::warn;
 
 /// Have that many iterations in the timer instead of `loop` to prevent potential bugs from blocking
 /// the node
@@ -310,6 +311,11 @@ impl Doomslug {
         }
     }
 
+    /// Replaces the key approvals are signed with, used when the validator key gets rotated.
+    pub fn set_signer(&mut self, signer: Arc<dyn ValidatorSigner>) {
+        self.signer = Some(signer);
+    }
+
     #[cfg(feature = "test_features")]
     pub fn adv_disable(&mut self) {
         self.threshold_mode = DoomslugThresholdMode::NoApprovals
@@ -360,6 +366,17 @@ impl Doomslug {
     /// the timers
     #[must_use]
     pub fn process_timer(&mut self, cur_time: Instant) -> Vec<Approval> {
+        self.process_timer_targets(cur_time)
+            .into_iter()
+            .filter_map(|target_height| self.create_approval(target_height))
+            .collect()
+    }
+
+    /// Same as `process_timer`, but returns the target heights of the approvals of the tip
+    /// instead of signing them, so that they can be signed by a signer which has to wait for
+    /// the signature, e.g. a remote one, off the caller's thread.
+    #[must_use]
+    pub fn process_timer_targets(&mut self, cur_time: Instant) -> Vec<BlockHeight> {
         let mut ret = vec![];
         for _ in 0..MAX_TIMER_ITERS {
             let skip_delay =
@@ -377,10 +394,7 @@ impl Doomslug {
             {
                 if tip_height >= self.largest_target_height {
                     self.largest_target_height = tip_height + 1;
-
-                    if let Some(approval) = self.create_approval(tip_height + 1) {
-                        ret.push(approval);
-                    }
+                    ret.push(tip_height + 1);
                 }
 
                 self.timer.last_endorsement_sent = cur_time;
@@ -393,9 +407,7 @@ impl Doomslug {
                 self.largest_target_height =
                     std::cmp::max(self.timer.height + 1, self.largest_target_height);
 
-                if let Some(approval) = self.create_approval(self.timer.height + 1) {
-                    ret.push(approval);
-                }
+                ret.push(self.timer.height + 1);
 
                 // Restart the timer
                 self.timer.started += skip_delay;
@@ -408,10 +420,17 @@ impl Doomslug {
         ret
     }
 
+    /// Approval of the tip for `target_height`, or `None` if we aren't a validator or the signer
+    /// failed, in which case no approval is sent for the height.
     pub fn create_approval(&self, target_height: BlockHeight) -> Option<Approval> {
-        self.signer.as_ref().map(|signer| {
-            Approval::new(self.tip.block_hash, self.tip.height, target_height, &**signer)
-        })
+        let signer = self.signer.as_ref()?;
+        match Approval::new(self.tip.block_hash, self.tip.height, target_height, &**signer) {
+            Ok(approval) => Some(approval),
+            Err(err) => {
+                warn!(target: "doomslug", "Not approving height {}: {}", target_height, err);
+                None
+            }
+        }
     }
 
     /// Determines whether a block has enough approvals to be produced.
@@ -793,7 +812,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -803,7 +822,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -813,7 +832,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -823,7 +842,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now + Duration::from_millis(100),
-                &Approval::new(hash(&[1]), 1, 4, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -833,7 +852,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[1]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -843,7 +862,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 4, &signers[0]),
+                &Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -853,7 +872,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[3]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[3]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -865,7 +884,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[1]), 1, 2, &signers[2]),
+                &Approval::new(hash(&[1]), 1, 2, &signers[2]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::ReadySince(now),
@@ -875,7 +894,7 @@ mod tests {
         assert_eq!(
             ds.on_approval_message_internal(
                 now,
-                &Approval::new(hash(&[2]), 2, 4, &signers[1]),
+                &Approval::new(hash(&[2]), 2, 4, &signers[1]).unwrap(),
                 &stakes,
             ),
             DoomslugBlockProductionReadiness::NotReady,
@@ -907,13 +926,13 @@ mod tests {
             .collect::<Vec<_>>();
         let mut tracker = DoomslugApprovalsTrackersAtHeight::new();
 
-        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]);
-        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]);
-        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]);
+        let a1_1 = Approval::new(hash(&[1]), 1, 4, &signers[0]).unwrap();
+        let a1_2 = Approval::new(hash(&[1]), 1, 4, &signers[1]).unwrap();
+        let a1_3 = Approval::new(hash(&[1]), 1, 4, &signers[2]).unwrap();
 
-        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]);
-        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]);
-        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]);
+        let a2_1 = Approval::new(hash(&[3]), 3, 4, &signers[0]).unwrap();
+        let a2_2 = Approval::new(hash(&[3]), 3, 4, &signers[1]).unwrap();
+        let a2_3 = Approval::new(hash(&[3]), 3, 4, &signers[2]).unwrap();
 
         // Process first approval, and then process it again and make sure it works
         tracker.process_approval(
@@ -0,0 +1,166 @@
+//! Optional attribution of burnt gas to accounts.
+//!
+//! When enabled, gas burnt by every execution outcome of an applied chunk is added to the account
+//! which executed it.  Only the heaviest accounts of every epoch are tracked, using the
+//! Space-Saving sketch, so memory usage does not depend on the number of active accounts.  The
+//! reported amounts may overestimate the real ones by at most the reported error.
+//!
+//! Chunks are accounted when they are applied, so chunks applied on forks which were later
+//! abandoned are accounted as well.
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use near_primitives::transaction::ExecutionOutcomeWithId;
+use near_primitives::types::{AccountId, EpochId, Gas};
+use near_primitives::views::{AccountGasView, GasAttributionView};
+use once_cell::sync::Lazy;
+
+use crate::metrics;
+
+/// Number of the most recent epochs for which the attribution is kept.
+const MAX_EPOCHS: usize = 2;
+
+/// Space-Saving sketch keeping at most `capacity` accounts with the largest gas usage.
+struct TopAccounts {
+    capacity: usize,
+    /// Estimated gas burnt by the account and the maximal overestimation of it.
+    accounts: HashMap<AccountId, (Gas, Gas)>,
+}
+
+impl TopAccounts {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, accounts: HashMap::with_capacity(capacity) }
+    }
+
+    fn add(&mut self, account_id: &AccountId, gas: Gas) {
+        if let Some((total, _)) = self.accounts.get_mut(account_id) {
+            *total = total.saturating_add(gas);
+            return;
+        }
+        if self.accounts.len() < self.capacity {
+            self.accounts.insert(account_id.clone(), (gas, 0));
+            return;
+        }
+        // Replace the lightest account.  Its gas becomes the error of the new one, since the new
+        // account could have burnt that much gas while it was not tracked.
+        let lightest = self.accounts.iter().min_by_key(|(_, (total, _))| *total);
+        if let Some((lightest_account_id, &(min_total, _))) = lightest {
+            let lightest_account_id = lightest_account_id.clone();
+            self.accounts.remove(&lightest_account_id);
+            self.accounts.insert(account_id.clone(), (min_total.saturating_add(gas), min_total));
+        }
+    }
+
+    /// Returns the tracked accounts, heaviest first.
+    fn top(&self) -> Vec<AccountGasView> {
+        let mut accounts: Vec<_> = self
+            .accounts
+            .iter()
+            .map(|(account_id, &(gas_burnt, error))| AccountGasView {
+                account_id: account_id.clone(),
+                gas_burnt,
+                error,
+            })
+            .collect();
+        accounts
+            .sort_by(|a, b| b.gas_burnt.cmp(&a.gas_burnt).then(a.account_id.cmp(&b.account_id)));
+        accounts
+    }
+}
+
+struct GasAttribution {
+    top_k: usize,
+    epochs: VecDeque<(EpochId, TopAccounts)>,
+}
+
+static GAS_ATTRIBUTION: Lazy<Mutex<Option<GasAttribution>>> = Lazy::new(|| Mutex::new(None));
+
+/// Enables the attribution, keeping `top_k` heaviest accounts of every epoch.
+pub fn enable(top_k: usize) {
+    *GAS_ATTRIBUTION.lock().unwrap() = Some(GasAttribution { top_k, epochs: VecDeque::new() });
+}
+
+/// Attributes gas burnt by the outcomes of a chunk applied in the given epoch.  Does nothing
+/// unless the attribution is enabled.
+pub fn record_outcomes(epoch_id: &EpochId, outcomes: &[ExecutionOutcomeWithId]) {
+    let mut guard = GAS_ATTRIBUTION.lock().unwrap();
+    let attribution = match guard.as_mut() {
+        Some(attribution) => attribution,
+        None => return,
+    };
+    if !attribution.epochs.iter().any(|(id, _)| id == epoch_id) {
+        if attribution.epochs.len() == MAX_EPOCHS {
+            attribution.epochs.pop_front();
+        }
+        attribution.epochs.push_back((epoch_id.clone(), TopAccounts::new(attribution.top_k)));
+    }
+    // Metrics describe the most recent epoch only.
+    let is_latest_epoch = attribution.epochs.back().map(|(id, _)| id) == Some(epoch_id);
+    let (_, top_accounts) =
+        attribution.epochs.iter_mut().find(|(id, _)| id == epoch_id).expect("inserted above");
+    for outcome in outcomes {
+        top_accounts.add(&outcome.outcome.executor_id, outcome.outcome.gas_burnt);
+    }
+
+    if is_latest_epoch {
+        metrics::ACCOUNT_GAS_BURNT.reset();
+        for account in top_accounts.top() {
+            metrics::ACCOUNT_GAS_BURNT
+                .with_label_values(&[account.account_id.as_str()])
+                .set(account.gas_burnt.min(i64::MAX as u64) as i64);
+        }
+    }
+}
+
+/// Returns the heaviest accounts of the given epoch (the most recent one by default), or `None`
+/// if the attribution is disabled or the epoch is not tracked.
+pub fn get_gas_attribution(epoch_id: Option<&EpochId>) -> Option<GasAttributionView> {
+    let guard = GAS_ATTRIBUTION.lock().unwrap();
+    let attribution = guard.as_ref()?;
+    let (epoch_id, top_accounts) = match epoch_id {
+        Some(epoch_id) => attribution.epochs.iter().find(|(id, _)| id == epoch_id)?,
+        None => attribution.epochs.back()?,
+    };
+    Some(GasAttributionView { epoch_id: epoch_id.clone(), accounts: top_accounts.top() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(name: &str) -> AccountId {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn test_top_accounts() {
+        let mut top_accounts = TopAccounts::new(2);
+        top_accounts.add(&account("alice.near"), 100);
+        top_accounts.add(&account("bob.near"), 10);
+        top_accounts.add(&account("alice.near"), 50);
+        // Evicts bob.near, the new account inherits its gas as the error.
+        top_accounts.add(&account("carol.near"), 5);
+        let top = top_accounts.top();
+        assert_eq!(top.len(), 2);
+        assert_eq!(
+            (top[0].account_id.as_str(), top[0].gas_burnt, top[0].error),
+            ("alice.near", 150, 0)
+        );
+        assert_eq!(
+            (top[1].account_id.as_str(), top[1].gas_burnt, top[1].error),
+            ("carol.near", 15, 10)
+        );
+    }
+
+    #[test]
+    fn test_heavy_account_is_kept() {
+        let mut top_accounts = TopAccounts::new(3);
+        for i in 0..100 {
+            top_accounts.add(&account(&format!("light{}.near", i)), 1);
+            top_accounts.add(&account("heavy.near"), 10);
+        }
+        let top = top_accounts.top();
+        assert_eq!(top[0].account_id.as_str(), "heavy.near");
+        assert_eq!(top[0].gas_burnt, 1000);
+    }
+}
@@ -10,10 +10,12 @@ pub use types::{Block, BlockHeader, BlockStatus, ChainGenesis, Provenance, Runti
 pub mod chain;
 pub mod crypto_hash_timer;
 mod doomslug;
+pub mod gas_attribution;
 mod lightclient;
 mod metrics;
 pub mod migrations;
 pub mod missing_chunks;
+pub mod state_witness;
 mod store;
 pub mod store_validator;
 pub mod test_utils;
@@ -1,6 +1,6 @@
 use near_metrics::{
-    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
-    IntGauge,
+    try_create_histogram, try_create_histogram_vec, try_create_int_counter, try_create_int_gauge,
+    try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter, IntGauge, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
 
@@ -19,6 +19,15 @@ pub static BLOCK_PROCESSING_TIME: Lazy<Histogram> = Lazy::new(|| {
     try_create_histogram("near_block_processing_time", "Time taken to process blocks successfully. Measures only the time taken by the successful attempts of block processing")
         .unwrap()
 });
+pub static BLOCK_PROCESSING_STAGE_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_block_processing_stage_time",
+        "Time taken by every stage of processing a block: verify, apply, head_update and commit. Measures only the stages which succeeded",
+        &["stage"],
+        None,
+    )
+    .unwrap()
+});
 pub static BLOCK_HEIGHT_HEAD: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_block_height_head", "Height of the current head of the blockchain")
         .unwrap()
@@ -42,3 +51,11 @@ pub static NUM_ORPHANS: Lazy<IntGauge> =
 pub static HEADER_HEAD_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_header_head_height", "Height of the header head").unwrap()
 });
+pub static ACCOUNT_GAS_BURNT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_account_gas_burnt",
+        "Gas burnt in the current epoch by the heaviest accounts. Collected only if gas attribution is enabled",
+        &["account_id"],
+    )
+    .unwrap()
+});
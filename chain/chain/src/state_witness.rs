@@ -0,0 +1,19 @@
+use lru::LruCache;
+use near_primitives::sharding::ChunkHash;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+// Cache with the mapping from chunk hash to the size of the state witness of the chunk, i.e. total
+// size of the distinct trie nodes and values touched while applying it.
+// Used only for debugging purposes.
+static CHUNK_STATE_WITNESS_SIZES: Lazy<Mutex<LruCache<ChunkHash, u64>>> =
+    Lazy::new(|| Mutex::new(LruCache::new(10000)));
+
+pub fn record_chunk_state_witness_size(chunk_hash: ChunkHash, size: u64) {
+    CHUNK_STATE_WITNESS_SIZES.lock().unwrap().put(chunk_hash, size);
+}
+
+pub fn get_chunk_state_witness_size(chunk_hash: &ChunkHash) -> Option<u64> {
+    CHUNK_STATE_WITNESS_SIZES.lock().unwrap().get(chunk_hash).copied()
+}
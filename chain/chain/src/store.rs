@@ -27,14 +27,15 @@ use near_primitives::transaction::{
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
-    AccountId, BlockExtra, BlockHeight, EpochId, GCCount, NumBlocks, ShardId, StateChanges,
-    StateChangesExt, StateChangesForSplitStates, StateChangesKinds, StateChangesKindsExt,
-    StateChangesRequest,
+    AccountHistoryKind, AccountId, BlockExtra, BlockHeight, EpochId, GCCount, NumBlocks,
+    RawStateChangesWithTrieKey, ShardId, StateChanges, StateChangesExt, StateChangesForSplitStates,
+    StateChangesKinds, StateChangesKindsExt, StateChangesRequest,
 };
 use near_primitives::utils::{get_block_shard_id, index_to_bytes, to_timestamp};
 use near_primitives::views::LightClientBlockView;
 use near_store::{
-    read_with_cache, ColBlock, ColBlockExtra, ColBlockHeader, ColBlockHeight, ColBlockInfo,
+    account_history_key, read_with_cache, set_account_history_entry, AccountHistoryEntry,
+    ColAccountHistory, ColBlock, ColBlockExtra, ColBlockHeader, ColBlockHeight, ColBlockInfo,
     ColBlockMerkleTree, ColBlockMisc, ColBlockOrdinal, ColBlockPerHeight, ColBlockRefCount,
     ColBlocksToCatchup, ColChallengedBlocks, ColChunkExtra, ColChunkHashesByHeight,
     ColChunkPerHeightShard, ColChunks, ColEpochLightClientBlocks, ColGCCount,
@@ -348,6 +349,9 @@ pub struct ChainStore {
     block_ordinal_to_hash: LruCache<Vec<u8>, CryptoHash>,
     /// Processed block heights.
     processed_block_heights: LruCache<Vec<u8>, ()>,
+    /// Whether the outcomes of the applied chunks are indexed by the accounts they were executed
+    /// on.
+    account_history_index: bool,
 }
 
 pub fn option_to_not_found<T>(res: io::Result<Option<T>>, field_name: &str) -> Result<T, Error> {
@@ -389,6 +393,7 @@ impl ChainStore {
             block_merkle_tree: LruCache::new(CACHE_SIZE),
             block_ordinal_to_hash: LruCache::new(CACHE_SIZE),
             processed_block_heights: LruCache::new(CACHE_SIZE),
+            account_history_index: false,
         }
     }
 
@@ -396,6 +401,16 @@ impl ChainStore {
         &self.store
     }
 
+    /// Enables the index of the transactions and receipts executed on every account, read with
+    /// `near_store::get_account_history`.
+    pub fn set_account_history_index(&mut self, enabled: bool) {
+        self.account_history_index = enabled;
+    }
+
+    pub fn account_history_index(&self) -> bool {
+        self.account_history_index
+    }
+
     pub fn store_update(&mut self) -> ChainStoreUpdate<'_> {
         ChainStoreUpdate::new(self)
     }
@@ -738,6 +753,21 @@ impl ChainStore {
                 }
                 changes
             }
+            StateChangesRequest::AccessKeyChangesByPublicKey { public_keys } => {
+                // Access keys are stored under `col::ACCESS_KEY + account_id + col::ACCESS_KEY +
+                // public_key`, so the changes of all the access keys in the block are scanned.
+                let data_key = trie_key_parsers::get_raw_prefix_for_all_access_keys();
+                let storage_key = KeyForStateChanges::new(block_hash, data_key);
+                let changes_per_key_prefix =
+                    storage_key.find_iter(&self.store).filter(|change| match change {
+                        Ok(RawStateChangesWithTrieKey {
+                            trie_key: TrieKey::AccessKey { public_key, .. },
+                            ..
+                        }) => public_keys.contains(public_key),
+                        _ => true,
+                    });
+                StateChanges::from_access_key_changes(changes_per_key_prefix)?
+            }
             StateChangesRequest::ContractCodeChanges { account_ids } => {
                 let mut changes = StateChanges::new();
                 for account_id in account_ids {
@@ -1865,6 +1895,42 @@ impl<'a> ChainStoreUpdate<'a> {
         self.chain_store_cache_update.outcome_ids.insert((*block_hash, shard_id), outcome_ids);
     }
 
+    /// Indexes the outcomes of a chunk by the accounts they were executed on, if the account
+    /// history index is enabled.  The first `num_transactions` outcomes are those of the
+    /// transactions of the chunk, the rest are those of the receipts.
+    pub fn save_account_history(
+        &mut self,
+        block_height: BlockHeight,
+        block_hash: &CryptoHash,
+        shard_id: ShardId,
+        outcomes: &[ExecutionOutcomeWithId],
+        num_transactions: usize,
+    ) {
+        if !self.chain_store.account_history_index {
+            return;
+        }
+        let mut store_update = self.store().store_update();
+        for (i, outcome_with_id) in outcomes.iter().enumerate() {
+            let kind = if i < num_transactions {
+                AccountHistoryKind::Transaction
+            } else {
+                AccountHistoryKind::Receipt
+            };
+            set_account_history_entry(
+                &mut store_update,
+                &outcome_with_id.outcome.executor_id,
+                &AccountHistoryEntry {
+                    block_height,
+                    block_hash: *block_hash,
+                    shard_id,
+                    id: outcome_with_id.id,
+                    kind,
+                },
+            );
+        }
+        self.merge(store_update);
+    }
+
     pub fn save_trie_changes(&mut self, trie_changes: WrappedTrieChanges) {
         self.trie_changes.push(trie_changes);
     }
@@ -2077,6 +2143,7 @@ impl<'a> ChainStoreUpdate<'a> {
                                 tries
                                     .revert_insertions(&trie_changes, shard_uid, &mut store_update)
                                     .map(|_| {
+                                        // Compaction only collects the rows of canonical blocks.
                                         self.gc_col(
                                             ColTrieChanges,
                                             &get_block_shard_uid(&block_hash, &shard_uid),
@@ -2097,7 +2164,7 @@ impl<'a> ChainStoreUpdate<'a> {
                                 tries
                                     .apply_deletions(&trie_changes, shard_uid, &mut store_update)
                                     .map(|_| {
-                                        self.gc_col(
+                                        self.gc_col_by_compaction(
                                             ColTrieChanges,
                                             &get_block_shard_uid(&block_hash, &shard_uid),
                                         );
@@ -2149,7 +2216,13 @@ impl<'a> ChainStoreUpdate<'a> {
         // gc ColChunkExtra based on shard_uid since it's indexed by shard_uid in the storage
         for shard_uid in self.get_shard_uids_to_gc(runtime_adapter, &block_hash) {
             let block_shard_uid = get_block_shard_uid(&block_hash, &shard_uid);
-            self.gc_col(ColChunkExtra, &block_shard_uid);
+            if let GCMode::Canonical(_) = gc_mode {
+                self.gc_col_by_compaction(ColChunkExtra, &block_shard_uid);
+            } else {
+                // Compaction only collects the rows of canonical blocks, and the tail is reset by
+                // state sync, so it doesn't cover the blocks cleared here.
+                self.gc_col(ColChunkExtra, &block_shard_uid);
+            }
         }
 
         // 3. Delete block_hash-indexed data
@@ -2302,6 +2375,17 @@ impl<'a> ChainStoreUpdate<'a> {
                 self.chain_store.get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id)?;
             for outcome_id in outcome_ids {
                 let mut outcomes_with_id = self.chain_store.get_outcomes_by_id(&outcome_id)?;
+                if self.chain_store.account_history_index {
+                    for outcome in outcomes_with_id.iter().filter(|o| &o.block_hash == block_hash) {
+                        let key = account_history_key(
+                            &outcome.outcome_with_id.outcome.executor_id,
+                            block.header().height(),
+                            block_hash,
+                            &outcome_id,
+                        );
+                        self.gc_col(ColAccountHistory, &key);
+                    }
+                }
                 outcomes_with_id.retain(|outcome| &outcome.block_hash != block_hash);
                 if outcomes_with_id.is_empty() {
                     self.gc_col(ColTransactionResult, &outcome_id.as_ref().into());
@@ -2429,6 +2513,9 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::ColHeaderHashesByHeight => {
                 store_update.delete(col, key);
             }
+            DBCol::ColAccountHistory => {
+                store_update.delete(col, key);
+            }
             DBCol::ColDbVersion
             | DBCol::ColBlockMisc
             | DBCol::ColGCCount
@@ -2448,7 +2535,9 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::_ColLastBlockWithNewChunk
             | DBCol::_ColTransactionRefCount
             | DBCol::ColStateChangesForSplitStates
-            | DBCol::ColCachedContractCode => {
+            | DBCol::ColCachedContractCode
+            | DBCol::ColContractAccounts
+            | DBCol::ColStateWriteIntents => {
                 unreachable!();
             }
         }
@@ -2456,6 +2545,18 @@ impl<'a> ChainStoreUpdate<'a> {
         self.merge(store_update);
     }
 
+    /// Like `gc_col`, but if the store removes the rows of the column once the GC tail passes
+    /// them, leaves the row to compaction rather than deleting it.
+    fn gc_col_by_compaction(&mut self, col: DBCol, key: &Vec<u8>) {
+        if !self.store().gc_by_compaction() {
+            return self.gc_col(col, key);
+        }
+        if col == DBCol::ColChunkExtra {
+            self.chain_store.chunk_extras.pop(key);
+        }
+        self.inc_gc(col);
+    }
+
     /// Merge another StoreUpdate into this one
     pub fn merge(&mut self, store_update: StoreUpdate) {
         self.store_updates.push(store_update);
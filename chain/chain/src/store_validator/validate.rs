@@ -524,12 +524,31 @@ pub(crate) fn canonical_prev_block_validity(
     Ok(())
 }
 
+/// Whether the block is garbage collected while its rows are left to compaction, see
+/// `Store::gc_by_compaction`.
+fn garbage_collected_by_compaction(
+    sv: &StoreValidator,
+    block_hash: &CryptoHash,
+) -> Result<bool, StoreValidatorError> {
+    if !sv.store.gc_by_compaction() {
+        return Ok(false);
+    }
+    let exists = unwrap_or_err!(
+        sv.store.exists(ColBlock, block_hash.as_ref()),
+        "Can't check whether Block exists"
+    );
+    Ok(!exists)
+}
+
 pub(crate) fn trie_changes_chunk_extra_exists(
     sv: &mut StoreValidator,
     (block_hash, shard_uid): &(CryptoHash, ShardUId),
     trie_changes: &TrieChanges,
 ) -> Result<(), StoreValidatorError> {
     let new_root = trie_changes.new_root;
+    if garbage_collected_by_compaction(sv, block_hash)? {
+        return Ok(());
+    }
     // 1. Block with `block_hash` should be available
     let block = unwrap_or_err_db!(
         sv.store.get_ser::<Block>(ColBlock, block_hash.as_ref()),
@@ -752,6 +771,9 @@ pub(crate) fn chunk_extra_block_exists(
     (block_hash, _shard_uid): &(CryptoHash, ShardUId),
     _chunk_extra: &ChunkExtra,
 ) -> Result<(), StoreValidatorError> {
+    if garbage_collected_by_compaction(sv, block_hash)? {
+        return Ok(());
+    }
     unwrap_or_err_db!(
         sv.store.get_ser::<Block>(ColBlock, block_hash.as_ref()),
         "Can't get Block from DB"
@@ -14,16 +14,19 @@ use near_pool::types::PoolIterator;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::block_header::{Approval, ApprovalInner};
 use near_primitives::challenge::ChallengesResult;
+use near_primitives::contract::ContractCode;
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::{hash, CryptoHash};
-use near_primitives::receipt::{ActionReceipt, Receipt, ReceiptEnum};
+use near_primitives::receipt::{ActionReceipt, DelayedReceiptIndices, Receipt, ReceiptEnum};
+use near_primitives::runtime::config::RuntimeConfig;
 use near_primitives::serialize::to_base;
 use near_primitives::shard_layout;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::sharding::ChunkHash;
 use near_primitives::state_record::StateRecord;
+use near_primitives::syncing::StateSplitProgress;
 use near_primitives::transaction::{
     Action, ExecutionMetadata, ExecutionOutcome, ExecutionOutcomeWithId, ExecutionStatus,
     SignedTransaction, TransferAction,
@@ -36,8 +39,9 @@ use near_primitives::types::{
 use near_primitives::validator_signer::InMemoryValidatorSigner;
 use near_primitives::version::{ProtocolVersion, PROTOCOL_VERSION};
 use near_primitives::views::{
-    AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochValidatorInfo,
-    QueryRequest, QueryResponse, QueryResponseKind, ViewStateResult,
+    AccessKeyInfoView, AccessKeyList, CallResult, ContractCodeView, EpochRewardProjectionView,
+    EpochValidatorInfo, EpochValidatorProjectionView, ProtocolVersionVotesView, QueryRequest,
+    QueryResponse, QueryResponseKind, ViewStateResult,
 };
 use near_store::test_utils::create_test_store;
 use near_store::{
@@ -829,6 +833,10 @@ impl RuntimeAdapter for KeyValueRuntime {
             total_balance_burnt: 0,
             proof: None,
             processed_delayed_receipts: vec![],
+            state_witness_size: 0,
+            delayed_receipts_count: 0,
+            delayed_receipts_gas_added: 0,
+            delayed_receipts_gas_removed: 0,
         })
     }
 
@@ -921,6 +929,13 @@ impl RuntimeAdapter for KeyValueRuntime {
                 block_height,
                 block_hash: *block_hash,
             }),
+            QueryRequest::ViewAbi { account_id } => {
+                Err(near_chain_primitives::error::QueryError::NoContractAbi {
+                    contract_account_id: account_id.clone(),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
         }
     }
 
@@ -973,6 +988,22 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(())
     }
 
+    fn precompile_contracts(
+        &self,
+        _epoch_id: &EpochId,
+        _contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn precompile_contracts_in_background(
+        &self,
+        _epoch_id: &EpochId,
+        _contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn get_state_root_node(
         &self,
         _shard_id: ShardId,
@@ -1086,6 +1117,16 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(PROTOCOL_VERSION)
     }
 
+    fn get_delayed_receipts(
+        &self,
+        _shard_uid: ShardUId,
+        _state_root: &StateRoot,
+        _from_index: Option<u64>,
+        _limit: u64,
+    ) -> Result<(DelayedReceiptIndices, Vec<(u64, Receipt)>), Error> {
+        Ok((DelayedReceiptIndices::default(), vec![]))
+    }
+
     fn get_validator_info(
         &self,
         _epoch_id: ValidatorInfoIdentifier,
@@ -1102,6 +1143,56 @@ impl RuntimeAdapter for KeyValueRuntime {
         })
     }
 
+    fn get_validator_projection(
+        &self,
+        _last_block_hash: &CryptoHash,
+    ) -> Result<EpochValidatorProjectionView, Error> {
+        Ok(EpochValidatorProjectionView {
+            epoch_height: 2,
+            seat_price: 0,
+            validators: vec![],
+            fishermen: vec![],
+            kickout: vec![],
+        })
+    }
+
+    fn get_protocol_version_votes(
+        &self,
+        _last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, Error> {
+        Ok(ProtocolVersionVotesView {
+            epoch_height: 1,
+            protocol_version: PROTOCOL_VERSION,
+            next_epoch_protocol_version: PROTOCOL_VERSION,
+            total_stake: 0,
+            threshold_stake: 0,
+            versions: vec![],
+            validators: vec![],
+            upgrade: None,
+        })
+    }
+
+    fn get_reward_projection(
+        &self,
+        _last_block_hash: &CryptoHash,
+    ) -> Result<EpochRewardProjectionView, Error> {
+        Ok(EpochRewardProjectionView {
+            epoch_height: 1,
+            protocol_version: PROTOCOL_VERSION,
+            total_supply: 0,
+            epoch_duration_ns: 0,
+            minted_amount: 0,
+            protocol_treasury_reward: 0,
+            burnt_amount: 0,
+            inflation_rate: 0.0,
+            validators: vec![],
+        })
+    }
+
+    fn get_epoch_info_after_next(&self, _last_block_hash: &CryptoHash) -> Result<EpochInfo, Error> {
+        Ok(EpochInfo::default())
+    }
+
     fn compare_epoch_id(
         &self,
         epoch_id: &EpochId,
@@ -1163,6 +1254,10 @@ impl RuntimeAdapter for KeyValueRuntime {
         unreachable!("get_protocol_config should not be called in KeyValueRuntime");
     }
 
+    fn get_runtime_config(&self, _protocol_version: ProtocolVersion) -> Arc<RuntimeConfig> {
+        Arc::new(RuntimeConfig::test())
+    }
+
     fn get_prev_epoch_id_from_prev_block(
         &self,
         prev_block_hash: &CryptoHash,
@@ -1201,6 +1296,7 @@ impl RuntimeAdapter for KeyValueRuntime {
         _shard_uid: ShardUId,
         _state_root: &StateRoot,
         _next_epoch_shard_layout: &ShardLayout,
+        _progress: &StateSplitProgress,
     ) -> Result<HashMap<ShardUId, StateRoot>, Error> {
         Ok(HashMap::new())
     }
@@ -116,7 +116,8 @@ fn build_chain_with_orhpans() {
         &*signer,
         *last_block.header().next_bp_hash(),
         CryptoHash::default(),
-    );
+    )
+    .unwrap();
     assert_eq!(chain.process_block_test(&None, block).unwrap_err().kind(), ErrorKind::Orphan);
     assert_eq!(
         chain.process_block_test(&None, blocks.pop().unwrap()).unwrap_err().kind(),
@@ -25,3 +25,29 @@ fn chain_sync_headers() {
         .unwrap();
     assert_eq!(chain.header_head().unwrap().height, 4);
 }
+
+#[test]
+fn chain_sync_headers_only() {
+    init_test_logger();
+    let (mut chain, _, bls_signer) = setup();
+    let mut blocks = vec![chain.get_block(&chain.genesis().hash().clone()).unwrap().clone()];
+    let mut block_merkle_tree = PartialMerkleTree::default();
+    for i in 0..4 {
+        blocks.push(Block::empty_with_block_merkle_tree(
+            &blocks[i],
+            &*bls_signer,
+            &mut block_merkle_tree,
+        ));
+    }
+    let tip = chain
+        .sync_headers_only(
+            blocks[1..].iter().map(|block| block.header().clone()).collect(),
+            &mut |_| panic!("Unexpected"),
+        )
+        .unwrap();
+    assert_eq!(tip.unwrap().last_block_hash, *blocks[4].hash());
+    assert_eq!(chain.head().unwrap().height, 4);
+    assert_eq!(chain.header_head().unwrap().height, 4);
+    // The bodies are never stored.
+    assert!(!chain.block_exists(blocks[4].hash()).unwrap());
+}
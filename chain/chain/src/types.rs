@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use chrono::DateTime;
@@ -13,12 +14,14 @@ use near_pool::types::PoolIterator;
 pub use near_primitives::block::{Block, BlockHeader, Tip};
 use near_primitives::challenge::{ChallengesResult, SlashedValidator};
 use near_primitives::checked_feature;
+use near_primitives::contract::ContractCode;
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath};
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
+use near_primitives::runtime::config::RuntimeConfig;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
 use near_primitives::transaction::{ExecutionOutcomeWithId, SignedTransaction};
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
@@ -30,13 +33,17 @@ use near_primitives::version::{
     ProtocolVersion, MIN_GAS_PRICE_NEP_92, MIN_GAS_PRICE_NEP_92_FIX, MIN_PROTOCOL_VERSION_NEP_92,
     MIN_PROTOCOL_VERSION_NEP_92_FIX,
 };
-use near_primitives::views::{EpochValidatorInfo, QueryRequest, QueryResponse};
+use near_primitives::views::{
+    EpochRewardProjectionView, EpochValidatorInfo, EpochValidatorProjectionView,
+    ProtocolVersionVotesView, QueryRequest, QueryResponse,
+};
 use near_store::{PartialStorage, ShardTries, Store, StoreUpdate, Trie, WrappedTrieChanges};
 
 use crate::DoomslugThresholdMode;
 use near_primitives::epoch_manager::ShardConfig;
 use near_primitives::shard_layout::{ShardLayout, ShardUId};
 use near_primitives::state_record::StateRecord;
+use near_primitives::syncing::StateSplitProgress;
 
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub enum BlockStatus {
@@ -104,6 +111,14 @@ pub struct ApplyTransactionResult {
     pub total_balance_burnt: Balance,
     pub proof: Option<PartialStorage>,
     pub processed_delayed_receipts: Vec<Receipt>,
+    /// Total size of the distinct trie nodes and values touched while applying the chunk.
+    pub state_witness_size: u64,
+    /// Number of receipts in the delayed receipts queue after the chunk was applied.
+    pub delayed_receipts_count: u64,
+    /// Prepaid gas of the receipts the chunk added to the delayed receipts queue.
+    pub delayed_receipts_gas_added: Gas,
+    /// Prepaid gas of the receipts the chunk removed from the delayed receipts queue.
+    pub delayed_receipts_gas_removed: Gas,
 }
 
 impl ApplyTransactionResult {
@@ -162,6 +177,9 @@ pub struct BlockEconomicsConfig {
     min_gas_price: Balance,
     max_gas_price: Balance,
     genesis_protocol_version: ProtocolVersion,
+    /// Gas price enforced for all new blocks, set through the sandbox RPC.
+    #[cfg(feature = "sandbox")]
+    sandbox_gas_price_override: Option<Balance>,
 }
 
 impl BlockEconomicsConfig {
@@ -169,6 +187,10 @@ impl BlockEconomicsConfig {
     const MAX_GAS_MULTIPLIER: u128 = 20;
     /// Compute min gas price according to protocol version and genesis protocol version.
     pub fn min_gas_price(&self, protocol_version: ProtocolVersion) -> Balance {
+        #[cfg(feature = "sandbox")]
+        if let Some(gas_price) = self.sandbox_gas_price_override {
+            return gas_price;
+        }
         if self.genesis_protocol_version < MIN_PROTOCOL_VERSION_NEP_92 {
             if protocol_version >= MIN_PROTOCOL_VERSION_NEP_92_FIX {
                 MIN_GAS_PRICE_NEP_92_FIX
@@ -189,6 +211,10 @@ impl BlockEconomicsConfig {
     }
 
     pub fn max_gas_price(&self, protocol_version: ProtocolVersion) -> Balance {
+        #[cfg(feature = "sandbox")]
+        if let Some(gas_price) = self.sandbox_gas_price_override {
+            return gas_price;
+        }
         if checked_feature!("stable", CapMaxGasPrice, protocol_version) {
             std::cmp::min(
                 self.max_gas_price,
@@ -202,6 +228,14 @@ impl BlockEconomicsConfig {
     pub fn gas_price_adjustment_rate(&self, _protocol_version: ProtocolVersion) -> Rational {
         self.gas_price_adjustment_rate
     }
+
+    /// Forces the gas price of all blocks produced and accepted from now on to be `gas_price`
+    /// (since both min and max gas price are equal to it), or restores the normal gas price
+    /// computation if `None`.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_set_gas_price_override(&mut self, gas_price: Option<Balance>) {
+        self.sandbox_gas_price_override = gas_price;
+    }
 }
 
 impl From<&ChainGenesis> for BlockEconomicsConfig {
@@ -211,6 +245,8 @@ impl From<&ChainGenesis> for BlockEconomicsConfig {
             min_gas_price: chain_genesis.min_gas_price,
             max_gas_price: chain_genesis.max_gas_price,
             genesis_protocol_version: chain_genesis.protocol_version,
+            #[cfg(feature = "sandbox")]
+            sandbox_gas_price_override: None,
         }
     }
 }
@@ -641,6 +677,46 @@ pub trait RuntimeAdapter: Send + Sync {
         states_to_patch: Option<Vec<StateRecord>>,
     ) -> Result<ApplyTransactionResult, Error>;
 
+    /// Applies a new chunk again to find out how its receipts and transactions were executed.
+    /// Unlike `apply_transactions`, it has no side effects such as updating the metrics, and
+    /// storage errors are returned rather than treated as fatal.
+    fn replay_transactions(
+        &self,
+        shard_id: ShardId,
+        state_root: &StateRoot,
+        height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        receipts: &[Receipt],
+        transactions: &[SignedTransaction],
+        last_validator_proposals: ValidatorStakeIter,
+        gas_price: Balance,
+        gas_limit: Gas,
+        challenges_result: &ChallengesResult,
+        random_seed: CryptoHash,
+        is_first_block_with_chunk_of_version: bool,
+    ) -> Result<ApplyTransactionResult, Error> {
+        self.apply_transactions(
+            shard_id,
+            state_root,
+            height,
+            block_timestamp,
+            prev_block_hash,
+            block_hash,
+            receipts,
+            transactions,
+            last_validator_proposals,
+            gas_price,
+            gas_limit,
+            challenges_result,
+            random_seed,
+            true,
+            is_first_block_with_chunk_of_version,
+            None,
+        )
+    }
+
     fn check_state_transition(
         &self,
         partial_storage: PartialStorage,
@@ -674,11 +750,56 @@ pub trait RuntimeAdapter: Send + Sync {
         request: &QueryRequest,
     ) -> Result<QueryResponse, near_chain_primitives::error::QueryError>;
 
+    /// Returns indices of the delayed receipts queue of the shard and up to `limit` receipts from
+    /// the queue (along with their indices), starting at `from_index` or at the head of the queue.
+    fn get_delayed_receipts(
+        &self,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+        from_index: Option<u64>,
+        limit: u64,
+    ) -> Result<(DelayedReceiptIndices, Vec<(u64, Receipt)>), Error>;
+
+    /// Updates the delayed receipts metrics of the shard with the result of applying a chunk.
+    /// Only called for the chunks applied while processing blocks, so that replaying or
+    /// re-applying chunks doesn't change the metrics.
+    fn update_delayed_receipts_metrics(
+        &self,
+        _shard_uid: ShardUId,
+        _apply_result: &ApplyTransactionResult,
+    ) {
+    }
+
     fn get_validator_info(
         &self,
         epoch_id: ValidatorInfoIdentifier,
     ) -> Result<EpochValidatorInfo, Error>;
 
+    /// Projects the validator set of the epoch after next as if the epoch of the given block
+    /// ended at it, using the proposals and block production stats collected so far.
+    fn get_validator_projection(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochValidatorProjectionView, Error>;
+
+    /// Protocol versions voted for by the block producers of the epoch of the given block so far.
+    fn get_protocol_version_votes(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, Error>;
+
+    /// Rewards of the epoch of the given block, projected from the block production stats so
+    /// far.
+    fn get_reward_projection(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochRewardProjectionView, Error>;
+
+    /// Info of the epoch after the next one, decided at the end of the epoch whose last block is
+    /// given.  Besides its validators, it holds the kickouts, rewards and stake changes of the
+    /// ended epoch.
+    fn get_epoch_info_after_next(&self, last_block_hash: &CryptoHash) -> Result<EpochInfo, Error>;
+
     /// Get the part of the state from given state root.
     /// `block_hash` is a block whose `prev_state_root` is `state_root`
     fn obtain_state_part(
@@ -713,6 +834,7 @@ pub trait RuntimeAdapter: Send + Sync {
         shard_uid: ShardUId,
         state_root: &StateRoot,
         next_epoch_shard_layout: &ShardLayout,
+        progress: &StateSplitProgress,
     ) -> Result<HashMap<ShardUId, StateRoot>, Error>;
 
     /// Should be executed after accepting all the parts to set up a new state.
@@ -726,6 +848,22 @@ pub trait RuntimeAdapter: Send + Sync {
         epoch_id: &EpochId,
     ) -> Result<(), Error>;
 
+    /// Compiles given contracts and puts them into the compiled contract cache, so that applying
+    /// chunks which call them later does not have to wait for compilation.
+    fn precompile_contracts(
+        &self,
+        epoch_id: &EpochId,
+        contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error>;
+
+    /// Same as `precompile_contracts`, but only schedules the compilation and returns without
+    /// waiting for it.
+    fn precompile_contracts_in_background(
+        &self,
+        epoch_id: &EpochId,
+        contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error>;
+
     /// Returns StateRootNode of a state.
     /// `block_hash` is a block whose `prev_state_root` is `state_root`
     /// Panics if requested hash is not in storage.
@@ -758,6 +896,9 @@ pub trait RuntimeAdapter: Send + Sync {
 
     fn get_protocol_config(&self, epoch_id: &EpochId) -> Result<ProtocolConfig, Error>;
 
+    /// Get the runtime config which is in effect at the given protocol version.
+    fn get_runtime_config(&self, protocol_version: ProtocolVersion) -> Arc<RuntimeConfig>;
+
     /// Get previous epoch id by hash of previous block.
     fn get_prev_epoch_id_from_prev_block(
         &self,
@@ -779,7 +920,7 @@ pub struct LatestKnown {
 }
 
 /// Either an epoch id or latest block hash
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum ValidatorInfoIdentifier {
     EpochId(EpochId),
     BlockHash(CryptoHash),
@@ -825,7 +966,8 @@ mod tests {
             KeyType::ED25519,
             "other2",
         );
-        let approvals = vec![Some(Approval::new(*b1.hash(), 1, 2, &other_signer).signature)];
+        let approvals =
+            vec![Some(Approval::new(*b1.hash(), 1, 2, &other_signer).unwrap().signature)];
         let b2 = Block::empty_with_approvals(
             &b1,
             2,
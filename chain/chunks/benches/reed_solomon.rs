@@ -0,0 +1,77 @@
+#[macro_use]
+extern crate bencher;
+
+use bencher::{black_box, Bencher};
+use near_primitives::sharding::ReedSolomonWrapper;
+
+/// Parts of a chunk with 100 block producers, a third of which are data parts.
+const DATA_PARTS: usize = 33;
+const PARITY_PARTS: usize = 67;
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+fn data_parts() -> Vec<Option<Box<[u8]>>> {
+    let part_size = (CHUNK_SIZE + DATA_PARTS - 1) / DATA_PARTS;
+    (0..DATA_PARTS)
+        .map(|i| Some((0..part_size).map(|j| (i * 31 + j) as u8).collect::<Vec<_>>().into()))
+        .chain((0..PARITY_PARTS).map(|_| None))
+        .collect()
+}
+
+fn encode(bench: &mut Bencher) {
+    let rs = ReedSolomonWrapper::new(DATA_PARTS, PARITY_PARTS);
+    let parts = data_parts();
+    bench.iter(|| {
+        let mut parts = parts.clone();
+        rs.encode(&mut parts).unwrap();
+        black_box(parts)
+    });
+}
+
+fn encode_by_reconstruct(bench: &mut Bencher) {
+    let mut rs = ReedSolomonWrapper::new(DATA_PARTS, PARITY_PARTS);
+    let parts = data_parts();
+    bench.iter(|| {
+        let mut parts = parts.clone();
+        rs.reconstruct(&mut parts).unwrap();
+        black_box(parts)
+    });
+}
+
+/// Decodes a chunk from the parity parts and the first data parts, as a node which received
+/// just enough parts does.
+fn decode(bench: &mut Bencher) {
+    let mut rs = ReedSolomonWrapper::new(DATA_PARTS, PARITY_PARTS);
+    let mut encoded = data_parts();
+    rs.encode(&mut encoded).unwrap();
+    let received: Vec<_> = encoded
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| if i < DATA_PARTS / 2 || i >= DATA_PARTS * 2 { part } else { None })
+        .collect();
+    bench.iter(|| {
+        let mut parts = received.clone();
+        rs.reconstruct(&mut parts).unwrap();
+        black_box(parts)
+    });
+}
+
+/// Same as `decode`, but with a new instance for every chunk, which recomputes the decoding
+/// matrix every time.
+fn decode_new_instance(bench: &mut Bencher) {
+    let mut encoded = data_parts();
+    ReedSolomonWrapper::new(DATA_PARTS, PARITY_PARTS).encode(&mut encoded).unwrap();
+    let received: Vec<_> = encoded
+        .into_iter()
+        .enumerate()
+        .map(|(i, part)| if i < DATA_PARTS / 2 || i >= DATA_PARTS * 2 { part } else { None })
+        .collect();
+    bench.iter(|| {
+        let mut rs = ReedSolomonWrapper::new(DATA_PARTS, PARITY_PARTS);
+        let mut parts = received.clone();
+        rs.reconstruct(&mut parts).unwrap();
+        black_box(parts)
+    });
+}
+
+benchmark_group!(benches, encode, encode_by_reconstruct, decode, decode_new_instance);
+benchmark_main!(benches);
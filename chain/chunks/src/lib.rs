@@ -99,16 +99,17 @@ use near_network::types::{
 };
 use near_pool::{PoolIteratorWrapper, TransactionPool};
 use near_primitives::block::Tip;
+use near_primitives::contract::ContractCode;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{merklize, verify_path, MerklePath};
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, EncodedShardChunkBody, PartialEncodedChunk,
     PartialEncodedChunkPart, PartialEncodedChunkV1, PartialEncodedChunkV2, ReceiptList,
     ReceiptProof, ReedSolomonWrapper, ShardChunk, ShardChunkHeader, ShardProof,
 };
 use near_primitives::time::Clock;
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::transaction::{Action, DeployContractAction, SignedTransaction};
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
     AccountId, Balance, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash, ShardId, StateRoot,
@@ -136,6 +137,8 @@ const CHUNK_PRODUCER_BLACKLIST_SIZE: usize = 100;
 pub const CHUNK_REQUEST_RETRY_MS: u64 = 100;
 pub const CHUNK_REQUEST_SWITCH_TO_OTHERS_MS: u64 = 400;
 pub const CHUNK_REQUEST_SWITCH_TO_FULL_FETCH_MS: u64 = 3_000;
+/// Upper bound of the delay between retries of a chunk request, which doubles with every retry.
+const CHUNK_REQUEST_MAX_RETRY_DELAY_MS: u64 = 1_600;
 const CHUNK_REQUEST_RETRY_MAX_MS: u64 = 1_000_000;
 const CHUNK_FORWARD_CACHE_SIZE: usize = 1000;
 const ACCEPTING_SEAL_PERIOD_MS: i64 = 30_000;
@@ -177,10 +180,15 @@ struct ChunkRequestInfo {
     shard_id: ShardId,
     added: Instant,
     last_requested: Instant,
+    // number of times the request was retried
+    attempts: u32,
+    // delay after `last_requested` before the request is retried
+    retry_delay: Duration,
 }
 
 struct RequestPool {
     retry_duration: Duration,
+    max_retry_delay: Duration,
     switch_to_others_duration: Duration,
     switch_to_full_fetch_duration: Duration,
     max_duration: Duration,
@@ -190,12 +198,14 @@ struct RequestPool {
 impl RequestPool {
     pub fn new(
         retry_duration: Duration,
+        max_retry_delay: Duration,
         switch_to_others_duration: Duration,
         switch_to_full_fetch_duration: Duration,
         max_duration: Duration,
     ) -> Self {
         Self {
             retry_duration,
+            max_retry_delay,
             switch_to_others_duration,
             switch_to_full_fetch_duration,
             max_duration,
@@ -227,8 +237,14 @@ impl RequestPool {
                 removed_requests.insert(chunk_hash.clone());
                 continue;
             }
-            if chunk_request.last_requested.elapsed() > self.retry_duration {
+            if chunk_request.last_requested.elapsed() > chunk_request.retry_delay {
                 chunk_request.last_requested = Clock::instant();
+                chunk_request.attempts += 1;
+                chunk_request.retry_delay = Self::retry_delay(
+                    self.retry_duration,
+                    self.max_retry_delay,
+                    chunk_request.attempts,
+                );
                 requests.push((chunk_hash.clone(), chunk_request.clone()));
             }
         }
@@ -237,6 +253,14 @@ impl RequestPool {
         }
         requests
     }
+
+    /// Delay before the retry following the given number of attempts: the retry duration doubled
+    /// with every attempt up to `max_retry_delay`, randomized by up to a quarter either way so
+    /// that the nodes missing the same chunk don't retry in lockstep.
+    fn retry_delay(retry_duration: Duration, max_retry_delay: Duration, attempts: u32) -> Duration {
+        let delay = retry_duration.saturating_mul(1 << attempts.min(16)).min(max_retry_delay);
+        delay.mul_f64(rand::thread_rng().gen_range(0.75, 1.25))
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -500,6 +524,7 @@ impl ShardsManager {
             encoded_chunks: EncodedChunksCache::new(),
             requested_partial_encoded_chunks: RequestPool::new(
                 Duration::from_millis(CHUNK_REQUEST_RETRY_MS),
+                Duration::from_millis(CHUNK_REQUEST_MAX_RETRY_DELAY_MS),
                 Duration::from_millis(CHUNK_REQUEST_SWITCH_TO_OTHERS_MS),
                 Duration::from_millis(CHUNK_REQUEST_SWITCH_TO_FULL_FETCH_MS),
                 Duration::from_millis(CHUNK_REQUEST_RETRY_MAX_MS),
@@ -521,6 +546,11 @@ impl ShardsManager {
         self.tx_pools.get_mut(&shard_id).map(|pool| pool.pool_iterator())
     }
 
+    /// Number of transactions in the pool of the shard.
+    pub fn pool_len(&self, shard_id: ShardId) -> usize {
+        self.tx_pools.get(&shard_id).map_or(0, |pool| pool.len())
+    }
+
     pub fn cares_about_shard_this_or_next_epoch(
         &self,
         account_id: Option<&AccountId>,
@@ -541,6 +571,7 @@ impl ShardsManager {
         force_request_full: bool,
         request_own_parts_from_others: bool,
         request_from_archival: bool,
+        attempt: u32,
     ) -> Result<(), near_chain::Error> {
         let mut bp_to_parts = HashMap::<_, Vec<u64>>::new();
 
@@ -574,12 +605,18 @@ impl ShardsManager {
         // which will be passed to PeerManagerActor. PeerManagerActor will try to request either
         // from the target account or any eligible peer of the node (See comments in
         // AccountIdOrPeerTrackingShard for when target account is used or peer is used)
+        //
+        // Every other retry rotates the targets, so that an unresponsive chunk producer or part
+        // owner doesn't hold the chunk up: the parts and receipts are requested from a random
+        // block producer tracking the shard, preferably through any peer tracking the shard.
 
         let me = self.me.as_ref();
+        let rotate = attempt % 2 == 1;
         // A account that is either the original chunk producer or a random block producer tracking
         // the shard
         let shard_representative_target = if !request_own_parts_from_others
             && !request_from_archival
+            && !rotate
             && Some(chunk_producer_account_id) != me
         {
             Some(chunk_producer_account_id.clone())
@@ -612,7 +649,7 @@ impl ShardsManager {
                     let part_owner =
                         self.runtime_adapter.get_part_owner(ancestor_hash, part_ord)?;
 
-                    if Some(&part_owner) == me {
+                    if Some(&part_owner) == me || rotate {
                         // If missing own part, request it from the chunk producer / node tracking shard
                         shard_representative_target.clone()
                     } else {
@@ -651,7 +688,9 @@ impl ShardsManager {
                 };
                 let target = AccountIdOrPeerTrackingShard {
                     account_id: target_account,
-                    prefer_peer: request_from_archival || rand::thread_rng().gen::<bool>(),
+                    prefer_peer: request_from_archival
+                        || rotate
+                        || rand::thread_rng().gen::<bool>(),
                     shard_id,
                     only_archival: request_from_archival,
                     min_height: height.saturating_sub(CHUNK_REQUEST_PEER_HORIZON),
@@ -780,6 +819,8 @@ impl ShardsManager {
                 shard_id,
                 last_requested: Clock::instant(),
                 added: Clock::instant(),
+                attempts: 0,
+                retry_delay: Duration::from_millis(CHUNK_REQUEST_RETRY_MS),
             },
         );
 
@@ -806,6 +847,7 @@ impl ShardsManager {
                     false,
                     old_block,
                     fetch_from_archival,
+                    0,
                 );
                 if let Err(err) = request_result {
                     error!(target: "chunks", "Error during requesting partial encoded chunk: {}", err);
@@ -899,6 +941,8 @@ impl ShardsManager {
             });
             let old_block = header_head.last_block_hash != chunk_request.prev_block_hash
                 && header_head.prev_block_hash != chunk_request.prev_block_hash;
+            let targets = if chunk_request.attempts % 2 == 1 { "rotated" } else { "same" };
+            metrics::PARTIAL_ENCODED_CHUNK_REQUEST_RETRIES.with_label_values(&[targets]).inc();
 
             match self.request_partial_encoded_chunk(
                 chunk_request.height,
@@ -911,6 +955,7 @@ impl ShardsManager {
                     || chunk_request.added.elapsed()
                         > self.requested_partial_encoded_chunks.switch_to_others_duration,
                 fetch_from_archival,
+                chunk_request.attempts,
             ) {
                 Ok(()) => {}
                 Err(err) => {
@@ -1219,9 +1264,9 @@ impl ShardsManager {
         }
 
         let mut content = EncodedShardChunkBody { parts };
-        if let Err(err) = content.reconstruct(rs) {
+        if let Err(err) = content.encode(rs) {
             warn!(target: "chunks",
-                   "Not sending {}, failed to reconstruct RS parity parts: {}",
+                   "Not sending {}, failed to encode RS parity parts: {}",
                    request.chunk_hash.0, err);
             return None;
         }
@@ -1582,6 +1627,15 @@ impl ShardsManager {
     ) -> Result<ProcessPartialEncodedChunkResult, Error> {
         let header = &partial_encoded_chunk.header;
         let chunk_hash = header.chunk_hash();
+        // Chunks are correlated with the blocks they build on.
+        let _span = tracing::info_span!(
+            target: "chunks",
+            "process_partial_encoded_chunk",
+            chunk_hash = %chunk_hash.0,
+            prev_block_hash = %header.prev_block_hash(),
+            shard_id = header.shard_id()
+        )
+        .entered();
         debug!(target: "chunks", "process partial encoded chunk {:?} height {} shard {}, me: {:?}",
                chunk_hash, header.height_created(), header.shard_id(), self.me);
         // Verify the partial encoded chunk is valid and worth processing
@@ -2061,6 +2115,8 @@ impl ShardsManager {
                 &mut store_update,
             )?;
 
+            self.precompile_chunk_contracts(&shard_chunk);
+
             // Decoded a valid chunk, store it in the permanent store
             store_update.save_chunk(shard_chunk);
             store_update.commit()?;
@@ -2079,6 +2135,43 @@ impl ShardsManager {
         }
     }
 
+    /// Starts compiling contracts deployed in the chunk in the background, so that the
+    /// compilation overlaps with waiting for the rest of the block instead of delaying the
+    /// application of the chunk.
+    fn precompile_chunk_contracts(&self, chunk: &ShardChunk) {
+        let receipt_actions = chunk.receipts().iter().flat_map(|receipt| match &receipt.receipt {
+            ReceiptEnum::Action(action_receipt) => action_receipt.actions.as_slice(),
+            ReceiptEnum::Data(_) => &[][..],
+        });
+        let contract_codes: Vec<ContractCode> = chunk
+            .transactions()
+            .iter()
+            .flat_map(|tx| tx.transaction.actions.iter())
+            .chain(receipt_actions)
+            .filter_map(|action| match action {
+                Action::DeployContract(DeployContractAction { code }) => {
+                    Some(ContractCode::new(code.clone(), None))
+                }
+                _ => None,
+            })
+            .collect();
+        if contract_codes.is_empty() {
+            return;
+        }
+        let epoch_id = match self.runtime_adapter.get_epoch_id_from_prev_block(chunk.prev_block()) {
+            Ok(epoch_id) => epoch_id,
+            Err(err) => {
+                debug!(target: "chunks", "Not precompiling contracts of chunk {:?}: {}", chunk.chunk_hash(), err);
+                return;
+            }
+        };
+        let num_contracts = contract_codes.len() as u64;
+        match self.runtime_adapter.precompile_contracts_in_background(&epoch_id, contract_codes) {
+            Ok(()) => metrics::PRECOMPILED_CHUNK_CONTRACTS.inc_by(num_contracts),
+            Err(err) => warn!(target: "chunks", "Failed to precompile contracts: {}", err),
+        }
+    }
+
     pub fn create_and_persist_partial_chunk(
         &mut self,
         encoded_chunk: &EncodedShardChunk,
@@ -2232,6 +2325,8 @@ mod test {
                 shard_id: 0,
                 added: added,
                 last_requested: added,
+                attempts: 0,
+                retry_delay: Duration::from_millis(CHUNK_REQUEST_RETRY_MS),
             },
         );
         std::thread::sleep(Duration::from_millis(2 * CHUNK_REQUEST_RETRY_MS));
@@ -2255,6 +2350,100 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_chunk_request_retry_delay() {
+        let retry = Duration::from_millis(CHUNK_REQUEST_RETRY_MS);
+        let max = Duration::from_millis(CHUNK_REQUEST_MAX_RETRY_DELAY_MS);
+        for attempts in 0..40 {
+            let delay = RequestPool::retry_delay(retry, max, attempts);
+            let expected = retry.saturating_mul(1 << attempts.min(16)).min(max);
+            assert!(delay >= expected.mul_f64(0.75) && delay <= expected.mul_f64(1.25));
+        }
+    }
+
+    /// retries should alternate between the part owners and a single block producer tracking the
+    /// shard, which is preferably reached through any peer tracking the shard
+    #[test]
+    fn test_chunk_request_retry_rotates_targets() {
+        let runtime_adapter = Arc::new(KeyValueRuntime::new_with_validators(
+            create_test_store(),
+            vec![vec![
+                "test".parse().unwrap(),
+                "test1".parse().unwrap(),
+                "test2".parse().unwrap(),
+                "test3".parse().unwrap(),
+            ]],
+            1,
+            1,
+            5,
+        ));
+        let network_adapter = Arc::new(MockPeerManagerAdapter::default());
+        let me: AccountId = "test".parse().unwrap();
+        let mut shards_manager = ShardsManager::new(
+            Some(me.clone()),
+            runtime_adapter.clone(),
+            network_adapter.clone(),
+            TEST_SEED,
+        );
+        let chunk_hash = ChunkHash(hash(&[1]));
+        let num_total_parts = runtime_adapter.num_total_parts() as u64;
+
+        let mut request_with_attempt = |attempt| {
+            shards_manager
+                .request_partial_encoded_chunk(
+                    1,
+                    &CryptoHash::default(),
+                    0,
+                    &chunk_hash,
+                    false,
+                    false,
+                    false,
+                    attempt,
+                )
+                .unwrap();
+            network_adapter
+                .requests
+                .write()
+                .unwrap()
+                .drain(..)
+                .map(|request| match request.as_network_requests() {
+                    NetworkRequests::PartialEncodedChunkRequest { target, request, .. } => {
+                        (target, request)
+                    }
+                    other => panic!("unexpected network request {:?}", other),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for attempt in 0..4 {
+            let requests = request_with_attempt(attempt);
+            let mut part_ords: Vec<u64> =
+                requests.iter().flat_map(|(_, request)| request.part_ords.clone()).collect();
+            part_ords.sort();
+            assert_eq!(part_ords, (0..num_total_parts).collect::<Vec<_>>());
+            for (target, _) in &requests {
+                assert!(target.account_id.is_some());
+                assert_ne!(target.account_id.as_ref(), Some(&me));
+            }
+            if attempt % 2 == 0 {
+                // Parts are requested from their owners.
+                for (target, request) in &requests {
+                    let part_owner = runtime_adapter
+                        .get_part_owner(&CryptoHash::default(), request.part_ords[0])
+                        .unwrap();
+                    if part_owner != me {
+                        assert_eq!(target.account_id, Some(part_owner));
+                    }
+                }
+                assert!(requests.len() > 1);
+            } else {
+                // Everything is requested from one target through any peer tracking the shard.
+                assert_eq!(requests.len(), 1);
+                assert!(requests[0].0.prefer_peer);
+            }
+        }
+    }
+
     #[test]
     #[cfg_attr(not(feature = "expensive_tests"), ignore)]
     fn test_seal_removal() {
@@ -2312,6 +2501,8 @@ mod test {
                 shard_id: header.shard_id(),
                 last_requested: Clock::instant(),
                 added: Clock::instant(),
+                attempts: 0,
+                retry_delay: Duration::from_millis(CHUNK_REQUEST_RETRY_MS),
             },
         );
         shards_manager
@@ -2323,6 +2514,7 @@ mod test {
                 false,
                 false,
                 false,
+                0,
             )
             .unwrap();
         let partial_encoded_chunk1 =
@@ -18,3 +18,22 @@ pub static PARTIAL_ENCODED_CHUNK_REQUEST_PROCESSING_TIME: Lazy<near_metrics::His
         )
         .unwrap()
     });
+
+pub static PRECOMPILED_CHUNK_CONTRACTS: Lazy<near_metrics::IntCounter> = Lazy::new(|| {
+    near_metrics::try_create_int_counter(
+        "near_precompiled_chunk_contracts_total",
+        "Number of contracts deployed in decoded chunks which were sent for compilation before the chunk is applied",
+    )
+    .unwrap()
+});
+
+pub static PARTIAL_ENCODED_CHUNK_REQUEST_RETRIES: Lazy<near_metrics::IntCounterVec> = Lazy::new(
+    || {
+        near_metrics::try_create_int_counter_vec(
+            "near_partial_encoded_chunk_request_retries_total",
+            "Number of retried partial encoded chunk requests, by whether they were sent to the same targets or rotated to others",
+            &["targets"],
+        )
+        .unwrap()
+    },
+);
@@ -5,24 +5,32 @@ use std::sync::Arc;
 use actix::Message;
 use chrono::DateTime;
 use near_primitives::time::Utc;
+use tokio::sync::broadcast;
 
 use near_chain_configs::ProtocolConfigView;
-use near_network_primitives::types::{AccountOrPeerIdOrHash, KnownProducer, PeerInfo};
+use near_network_primitives::types::{
+    AccountOrPeerIdOrHash, KnownProducer, PeerClientVersion, PeerInfo,
+};
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::sharding::ChunkHash;
+use near_primitives::syncing::StateSplitProgress;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockReference, EpochHeight, EpochId, EpochReference, MaybeBlockId,
+    ProtocolVersion, ProtocolVersionReference, ShardId, TransactionOrReceiptId,
 };
 use near_primitives::utils::generate_random_string;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    FinalExecutionOutcomeViewEnum, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
-    QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView, StateChangesRequestView,
-    StateChangesView,
+    AccountHistoryView, AccountsByCodeHashView, BlockView, ChunkView, ContractLogView,
+    DelayedReceiptsView, EpochRewardProjectionView, EpochSummaryView, EpochValidatorInfo,
+    EpochValidatorProjectionView, ExecutionOutcomeWithIdView, FeeEstimateView,
+    FinalExecutionOutcomeViewEnum, GasAttributionView, GasPriceView, LightClientBlockLiteView,
+    LightClientBlockView, MaintenanceWindowsView, MissedProductionView, ProducerScheduleView,
+    ProtocolVersionVotesView, QueryRequest, QueryResponse, ReceiptTraceView, ReceiptView,
+    RuntimeConfigDiffView, StateChangesKindsView, StateChangesRequestView, StateChangesView,
+    ValidatorEventsView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 
@@ -109,7 +117,7 @@ pub enum ShardSyncStatus {
     StateDownloadApplying,
     StateDownloadComplete,
     StateSplitScheduling,
-    StateSplitApplying,
+    StateSplitApplying(Arc<StateSplitProgress>),
     StateSyncDone,
 }
 
@@ -326,12 +334,32 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("ABI of contract ID {contract_account_id} is not available on the node at block #{block_height}")]
+    NoContractAbi {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("ABI of contract ID {contract_account_id} has hash {abi_hash}, but the contract code declares {declared_hash}")]
+    AbiHashMismatch {
+        contract_account_id: near_primitives::types::AccountId,
+        abi_hash: near_primitives::hash::CryptoHash,
+        declared_hash: near_primitives::hash::CryptoHash,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("State of contract {contract_account_id} is too large to be viewed")]
     TooLargeContractState {
         contract_account_id: near_primitives::types::AccountId,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Viewing the state of contract {contract_account_id} took too long")]
+    QueryTimeout {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("Access key for public key {public_key} has never been observed on the node at block #{block_height}")]
     UnknownAccessKey {
         public_key: near_crypto::PublicKey,
@@ -490,9 +518,22 @@ impl From<near_chain_primitives::Error> for GetGasPriceError {
     }
 }
 
+/// A connected peer along with the software it runs.
+#[derive(Clone, Debug)]
+pub struct ConnectedPeerInfo {
+    pub peer_info: PeerInfo,
+    /// Height of the head of the peer.
+    pub height: BlockHeight,
+    pub protocol_version: ProtocolVersion,
+    /// Version of the client the peer runs, unless it's too old to send it.
+    pub client_version: Option<PeerClientVersion>,
+    /// How long the peer has been connected.
+    pub connection_age: std::time::Duration,
+}
+
 #[derive(Debug)]
 pub struct NetworkInfoResponse {
-    pub connected_peers: Vec<PeerInfo>,
+    pub connected_peers: Vec<ConnectedPeerInfo>,
     pub num_connected_peers: usize,
     pub peer_max_count: u32,
     pub sent_bytes_per_sec: u64,
@@ -545,6 +586,125 @@ impl Message for GetValidatorInfo {
     type Result = Result<EpochValidatorInfo, GetValidatorInfoError>;
 }
 
+/// Projection of the validators of the epoch after next as of the latest known block.
+pub struct GetValidatorProjection;
+
+impl Message for GetValidatorProjection {
+    type Result = Result<EpochValidatorProjectionView, GetValidatorInfoError>;
+}
+
+/// Protocol versions voted for by the block producers of the epoch as of the latest known block.
+pub struct GetProtocolVersionVotes;
+
+impl Message for GetProtocolVersionVotes {
+    type Result = Result<ProtocolVersionVotesView, GetValidatorInfoError>;
+}
+
+/// Rewards of the current epoch projected from the block and chunk production so far.
+pub struct GetRewardProjection;
+
+impl Message for GetRewardProjection {
+    type Result = Result<EpochRewardProjectionView, GetValidatorInfoError>;
+}
+
+/// Upcoming heights of the current epoch at which the validator produces neither blocks nor chunks.
+pub struct GetMaintenanceWindows {
+    pub account_id: AccountId,
+}
+
+impl Message for GetMaintenanceWindows {
+    type Result = Result<MaintenanceWindowsView, GetValidatorInfoError>;
+}
+
+/// Block producer of each height and chunk producer of each height and shard of the epoch.
+pub struct GetProducerSchedule {
+    pub epoch_reference: EpochReference,
+}
+
+impl Message for GetProducerSchedule {
+    type Result = Result<ProducerScheduleView, GetValidatorInfoError>;
+}
+
+/// Events concerning the validator run by the node, starting from the given event id.
+pub struct GetValidatorEvents {
+    pub from_id: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetValidatorEventsError {
+    #[error("Validator events are disabled on this node")]
+    Disabled,
+}
+
+impl Message for GetValidatorEvents {
+    type Result = Result<ValidatorEventsView, GetValidatorEventsError>;
+}
+
+/// Blocks and chunks the validator run by the node missed, starting from the given miss id.
+pub struct GetMissedProduction {
+    pub from_id: u64,
+}
+
+impl Message for GetMissedProduction {
+    type Result = Vec<MissedProductionView>;
+}
+
+/// Summary of the epoch with the given height, or of the latest epoch which ended.
+pub struct GetEpochSummary {
+    pub epoch_height: Option<EpochHeight>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetEpochSummaryError {
+    #[error("Summary of the epoch is unavailable on this node")]
+    Unavailable,
+}
+
+impl Message for GetEpochSummary {
+    type Result = Result<EpochSummaryView, GetEpochSummaryError>;
+}
+
+/// Current gas price along with the fullness of the latest blocks and of the chunks of the shard,
+/// to estimate the gas price a transaction will be charged at.
+pub struct GetFeeEstimate {
+    pub shard_id: ShardId,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetFeeEstimateError {
+    #[error("There are no fully synchronized blocks yet")]
+    NoSyncedBlocks,
+    #[error("Shard id {shard_id} does not exist")]
+    InvalidShardId { shard_id: ShardId },
+}
+
+impl Message for GetFeeEstimate {
+    type Result = Result<FeeEstimateView, GetFeeEstimateError>;
+}
+
+/// Subscribes to the log lines of contract executions matching the `contract_logs` config.
+pub struct SubscribeContractLogs;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SubscribeContractLogsError {
+    #[error("Contract logs are disabled on this node")]
+    Disabled,
+}
+
+impl Message for SubscribeContractLogs {
+    type Result = Result<broadcast::Receiver<ContractLogView>, SubscribeContractLogsError>;
+}
+
+/// Makes the client drop all further network messages and stop syncing and producing blocks,
+/// then flushes the store to disk.  As messages are handled one at a time, the response also means
+/// that the block being applied, if any, has been persisted.  Used to shut the node down
+/// gracefully.
+pub struct StopProcessingBlocks;
+
+impl Message for StopProcessingBlocks {
+    type Result = Result<(), String>;
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum GetValidatorInfoError {
     #[error("IO Error: {0}")]
@@ -791,18 +951,192 @@ impl Message for GetReceipt {
     type Result = Result<Option<ReceiptView>, GetReceiptError>;
 }
 
+/// Pages through the delayed receipts queue of a shard.
+pub struct GetDelayedReceipts {
+    pub block_reference: BlockReference,
+    pub shard_id: ShardId,
+    /// Index of the first returned receipt.  Defaults to the head of the queue.
+    pub from_index: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetDelayedReceiptsError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("Block either has never been observed on the node or has been garbage collected: {0}")]
+    UnknownBlock(String),
+    #[error("Shard id {0} does not exist")]
+    InvalidShardId(ShardId),
+    #[error("Shard {0} is not tracked by this node")]
+    UnavailableShard(ShardId),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetDelayedReceiptsError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error.kind() {
+            near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
+            near_chain_primitives::ErrorKind::DBNotFoundErr(s) => Self::UnknownBlock(s),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+impl Message for GetDelayedReceipts {
+    type Result = Result<DelayedReceiptsView, GetDelayedReceiptsError>;
+}
+
+/// Accounts with the contract code of the given hash deployed as of the given block, found with
+/// the index of the accounts to which the code was ever deployed.  Accounts in shards not tracked
+/// by the node are skipped.
+pub struct GetAccountsByCodeHash {
+    pub block_reference: BlockReference,
+    pub code_hash: CryptoHash,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetAccountsByCodeHashError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("Block either has never been observed on the node or has been garbage collected: {0}")]
+    UnknownBlock(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetAccountsByCodeHashError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error.kind() {
+            near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
+            near_chain_primitives::ErrorKind::DBNotFoundErr(s) => Self::UnknownBlock(s),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+impl Message for GetAccountsByCodeHash {
+    type Result = Result<AccountsByCodeHashView, GetAccountsByCodeHashError>;
+}
+
+/// Transactions signed by the account and receipts received by it on the canonical chain,
+/// newest first, read from the account history index.
+pub struct GetAccountHistory {
+    pub account_id: AccountId,
+    /// Cursor returned with the previous page.  Defaults to the newest entry.
+    pub cursor: Option<Vec<u8>>,
+    pub limit: Option<u64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetAccountHistoryError {
+    #[error("Account history index is disabled on this node")]
+    Disabled,
+    #[error("IO Error: {0}")]
+    IOError(String),
+    // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
+    // expected cases, we cannot statically guarantee that no other errors will be returned
+    // in the future.
+    // TODO #3851: Remove this variant once we can exhaustively match all the underlying errors
+    #[error("It is a bug if you receive this error type, please, report this incident: https://github.com/near/nearcore/issues/new/choose. Details: {0}")]
+    Unreachable(String),
+}
+
+impl From<near_chain_primitives::Error> for GetAccountHistoryError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error.kind() {
+            near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
+            _ => Self::Unreachable(error.to_string()),
+        }
+    }
+}
+
+impl Message for GetAccountHistory {
+    type Result = Result<AccountHistoryView, GetAccountHistoryError>;
+}
+
+/// Accounts which burnt the most gas in the given epoch (the most recent one by default).
+pub struct GetGasAttribution {
+    pub epoch_id: Option<EpochId>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GetGasAttributionError {
+    #[error("Gas attribution is disabled on this node")]
+    Disabled,
+    #[error("No gas attribution was collected for the requested epoch")]
+    UnknownEpoch,
+}
+
+impl Message for GetGasAttribution {
+    type Result = Result<GasAttributionView, GetGasAttributionError>;
+}
+
+/// Replays the receipt recording its host function calls and storage accesses.
+pub struct TraceReceipt {
+    pub receipt_id: CryptoHash,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum TraceReceiptError {
+    #[error("IO Error: {0}")]
+    IOError(String),
+    #[error("Execution outcome of receipt {0} is unknown to this node")]
+    UnknownReceipt(near_primitives::hash::CryptoHash),
+    #[error("Receipt tracing is only available on archival nodes")]
+    NotArchival,
+    #[error("Receipt tracing requires a node built with the `receipt_trace` feature")]
+    NotEnabled,
+    #[error("Failed to replay the receipt: {0}")]
+    ReplayFailed(String),
+}
+
+impl From<near_chain_primitives::Error> for TraceReceiptError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error.kind() {
+            near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
+            _ => Self::ReplayFailed(error.to_string()),
+        }
+    }
+}
+
+impl Message for TraceReceipt {
+    type Result = Result<ReceiptTraceView, TraceReceiptError>;
+}
+
 pub struct GetProtocolConfig(pub BlockReference);
 
 impl Message for GetProtocolConfig {
     type Result = Result<ProtocolConfigView, GetProtocolConfigError>;
 }
 
+/// Diff of runtime configs between two protocol versions.
+pub struct GetProtocolConfigDiff {
+    pub from: ProtocolVersionReference,
+    pub to: ProtocolVersionReference,
+}
+
+impl Message for GetProtocolConfigDiff {
+    type Result = Result<RuntimeConfigDiffView, GetProtocolConfigError>;
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum GetProtocolConfigError {
     #[error("IO Error: {0}")]
     IOError(String),
     #[error("Block has never been observed: {0}")]
     UnknownBlock(String),
+    #[error("Epoch is unknown to this node: {0}")]
+    UnknownEpoch(String),
     // NOTE: Currently, the underlying errors are too broad, and while we tried to handle
     // expected cases, we cannot statically guarantee that no other errors will be returned
     // in the future.
@@ -816,6 +1150,9 @@ impl From<near_chain_primitives::Error> for GetProtocolConfigError {
         match error.kind() {
             near_chain_primitives::ErrorKind::IOErr(s) => Self::IOError(s),
             near_chain_primitives::ErrorKind::DBNotFoundErr(s) => Self::UnknownBlock(s),
+            near_chain_primitives::ErrorKind::EpochOutOfBounds(epoch_id) => {
+                Self::UnknownEpoch(format!("{:?}", epoch_id))
+            }
             _ => Self::Unreachable(error.to_string()),
         }
     }
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use near_primitives::block::{Approval, ApprovalInner};
+use near_primitives::types::{AccountId, BlockHeight};
+
+use crate::metrics;
+
+/// How many verified approvals are remembered to drop the copies arriving later.
+const SEEN_APPROVALS_CACHE_SIZE: usize = 4096;
+
+/// Drops the approvals the node already received, which arrive again when routed over several
+/// paths, before verifying their signatures again, and coalesces the approvals the node sends to
+/// the same block producer into one message.
+pub(crate) struct ApprovalAggregation {
+    /// Approvals received and verified, by target height, approved block and approver.
+    seen: lru::LruCache<(BlockHeight, ApprovalInner, AccountId), ()>,
+    /// Approvals waiting to be sent, by the block producer they're sent to.
+    pending: HashMap<AccountId, Vec<Approval>>,
+}
+
+impl ApprovalAggregation {
+    pub fn new() -> Self {
+        Self { seen: lru::LruCache::new(SEEN_APPROVALS_CACHE_SIZE), pending: HashMap::new() }
+    }
+
+    /// Whether the approval was already received and verified.
+    pub fn is_duplicate(&mut self, approval: &Approval) -> bool {
+        let duplicate = self.seen.contains(&key(approval));
+        if duplicate {
+            metrics::APPROVALS_DUPLICATE_DROPPED_TOTAL.inc();
+        }
+        duplicate
+    }
+
+    /// Remembers an approval whose signature is valid.  Approvals are remembered only once
+    /// verified, so that invalid copies can't get the valid one dropped.
+    pub fn record_verified(&mut self, approval: &Approval) {
+        self.seen.put(key(approval), ());
+    }
+
+    pub fn queue(&mut self, target: AccountId, approval: Approval) {
+        self.pending.entry(target).or_default().push(approval);
+    }
+
+    /// Takes the approvals waiting to be sent, grouped by the block producer they're sent to.
+    pub fn take_pending(&mut self) -> Vec<(AccountId, Vec<Approval>)> {
+        self.pending.drain().collect()
+    }
+}
+
+fn key(approval: &Approval) -> (BlockHeight, ApprovalInner, AccountId) {
+    (approval.target_height, approval.inner.clone(), approval.account_id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, Signature};
+    use near_primitives::block::{Approval, ApprovalInner};
+    use near_primitives::hash::CryptoHash;
+
+    use super::ApprovalAggregation;
+
+    fn approval(account_id: &str, target_height: u64) -> Approval {
+        Approval {
+            inner: ApprovalInner::Endorsement(CryptoHash::default()),
+            target_height,
+            signature: Signature::empty(KeyType::ED25519),
+            account_id: account_id.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_approval_aggregation() {
+        let mut aggregation = ApprovalAggregation::new();
+        assert!(!aggregation.is_duplicate(&approval("test0", 10)));
+        aggregation.record_verified(&approval("test0", 10));
+        assert!(aggregation.is_duplicate(&approval("test0", 10)));
+        assert!(!aggregation.is_duplicate(&approval("test0", 11)));
+        assert!(!aggregation.is_duplicate(&approval("test1", 10)));
+
+        aggregation.queue("test1".parse().unwrap(), approval("test0", 10));
+        aggregation.queue("test1".parse().unwrap(), approval("test0", 11));
+        aggregation.queue("test2".parse().unwrap(), approval("test0", 12));
+        let mut pending: Vec<_> = aggregation
+            .take_pending()
+            .into_iter()
+            .map(|(target, approvals)| (target.to_string(), approvals.len()))
+            .collect();
+        pending.sort();
+        assert_eq!(pending, vec![("test1".to_string(), 2), ("test2".to_string(), 1)]);
+        assert!(aggregation.take_pending().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::block::{ApprovalInner, BlockHeader};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, NumBlocks};
+
+/// How many heights of sent approvals are kept while waiting for the blocks they were sent for.
+const SENT_APPROVALS_CACHE_SIZE: usize = 64;
+
+/// Number of approvals of the node included in and excluded from the blocks of a producer.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ApprovalInclusion {
+    pub included: NumBlocks,
+    pub excluded: NumBlocks,
+}
+
+/// Tracks whether the approvals sent by the node end up in the blocks they were sent for, per
+/// producer of those blocks, in the current epoch.  Tells approvals which don't reach the block
+/// producers apart from producers which don't include them.
+pub(crate) struct ApprovalParticipation {
+    /// Approvals sent to other block producers, keyed by their target height.
+    sent: lru::LruCache<BlockHeight, ApprovalInner>,
+    epoch_id: EpochId,
+    per_producer: HashMap<AccountId, ApprovalInclusion>,
+}
+
+impl ApprovalParticipation {
+    pub fn new() -> Self {
+        Self {
+            sent: lru::LruCache::new(SENT_APPROVALS_CACHE_SIZE),
+            epoch_id: EpochId::default(),
+            per_producer: HashMap::new(),
+        }
+    }
+
+    pub fn record_sent(&mut self, inner: ApprovalInner, target_height: BlockHeight) {
+        self.sent.put(target_height, inner);
+    }
+
+    /// Whether an approval was sent for the given block: for its height, endorsing its parent or
+    /// skipping from the height of its parent.
+    pub fn approval_sent_for(&mut self, header: &BlockHeader, prev_height: BlockHeight) -> bool {
+        let inner = ApprovalInner::new(header.prev_hash(), prev_height, header.height());
+        self.sent.pop(&header.height()).map_or(false, |sent| sent == inner)
+    }
+
+    pub fn record_block(&mut self, epoch_id: &EpochId, producer: AccountId, included: bool) {
+        if epoch_id != &self.epoch_id {
+            self.epoch_id = epoch_id.clone();
+            self.per_producer.clear();
+        }
+        let inclusion = self.per_producer.entry(producer).or_default();
+        if included {
+            inclusion.included += 1;
+        } else {
+            inclusion.excluded += 1;
+        }
+    }
+
+    /// Inclusion of the approvals of the node in the blocks of the given producer in the current
+    /// epoch.
+    pub fn get(&self, producer: &AccountId) -> ApprovalInclusion {
+        self.per_producer.get(producer).copied().unwrap_or_default()
+    }
+
+    /// Inclusion of the approvals of the node in the current epoch, per producer.
+    pub fn current_epoch(&self) -> (EpochId, Vec<(AccountId, ApprovalInclusion)>) {
+        let per_producer = self
+            .per_producer
+            .iter()
+            .map(|(producer, inclusion)| (producer.clone(), *inclusion))
+            .collect();
+        (self.epoch_id.clone(), per_producer)
+    }
+
+    /// Restores the inclusion of the approvals of the node in the given epoch, as returned by
+    /// `current_epoch` before a restart.
+    pub fn restore(
+        &mut self,
+        epoch_id: EpochId,
+        per_producer: Vec<(AccountId, ApprovalInclusion)>,
+    ) {
+        self.epoch_id = epoch_id;
+        self.per_producer = per_producer.into_iter().collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::hash;
+    use near_primitives::types::EpochId;
+
+    use super::{ApprovalInclusion, ApprovalParticipation};
+
+    #[test]
+    fn test_approval_participation() {
+        let mut participation = ApprovalParticipation::new();
+        let epoch1 = EpochId(hash(b"epoch1"));
+        participation.record_block(&epoch1, "test1".parse().unwrap(), true);
+        participation.record_block(&epoch1, "test1".parse().unwrap(), false);
+        participation.record_block(&epoch1, "test1".parse().unwrap(), true);
+        assert_eq!(
+            participation.get(&"test1".parse().unwrap()),
+            ApprovalInclusion { included: 2, excluded: 1 }
+        );
+        assert_eq!(participation.get(&"test2".parse().unwrap()), ApprovalInclusion::default());
+
+        // Reset at the start of a new epoch.
+        participation.record_block(&EpochId(hash(b"epoch2")), "test2".parse().unwrap(), false);
+        assert_eq!(participation.get(&"test1".parse().unwrap()), ApprovalInclusion::default());
+        assert_eq!(
+            participation.get(&"test2".parse().unwrap()),
+            ApprovalInclusion { included: 0, excluded: 1 }
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Signing of approvals and produced blocks for signers which have to wait for the signature,
+//! e.g. on a remote signing service, see [`ValidatorSigner::is_remote`].  The client actor hands
+//! what it has to sign to a thread of its own, which sends the result back to the actor as a
+//! [`BackgroundSigned`] message, so that the actor keeps processing blocks in the meantime.
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+
+use actix::{Addr, Message};
+
+use near_crypto::vrf::{Proof, Value};
+use near_crypto::{KeyType, PublicKey, Signature};
+use near_primitives::block::{Approval, ApprovalInner, Block, BlockHeader};
+use near_primitives::challenge::ChallengeBody;
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::telemetry::TelemetryInfo;
+use near_primitives::types::{AccountId, BlockHeight, EpochId};
+use near_primitives::validator_signer::{SignerError, ValidatorSigner};
+
+use crate::ClientActor;
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) enum BackgroundSigned {
+    /// Approval of the block `parent_hash`.
+    Approval { parent_hash: CryptoHash, approval: Result<Approval, SignerError> },
+    /// VRF output for producing the block at `next_height` on top of `prev_hash`.
+    Vrf {
+        next_height: BlockHeight,
+        prev_hash: CryptoHash,
+        vrf: Result<(Value, Proof), SignerError>,
+    },
+    /// Produced block, which is only published once the signature of its header is set.
+    Block { block: Block, signature: Result<Signature, SignerError> },
+}
+
+type Job = Box<dyn FnOnce() -> BackgroundSigned + Send>;
+
+pub(crate) struct BackgroundSigner {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl BackgroundSigner {
+    pub(crate) fn new(client_addr: Addr<ClientActor>) -> Self {
+        let (jobs, jobs_rx) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("background_signer".to_string())
+            .spawn(move || {
+                for job in jobs_rx {
+                    client_addr.do_send(job());
+                }
+            })
+            .expect("Failed to start the background signer thread");
+        Self { jobs }
+    }
+
+    pub(crate) fn sign_approval(
+        &self,
+        signer: Arc<dyn ValidatorSigner>,
+        parent_hash: CryptoHash,
+        parent_height: BlockHeight,
+        target_height: BlockHeight,
+    ) {
+        self.spawn(move || BackgroundSigned::Approval {
+            parent_hash,
+            approval: Approval::new(parent_hash, parent_height, target_height, &*signer),
+        });
+    }
+
+    /// Computes the VRF output of the block at `next_height` from the random value of the
+    /// previous block, which the block can't be built without.
+    pub(crate) fn compute_vrf(
+        &self,
+        signer: Arc<dyn ValidatorSigner>,
+        next_height: BlockHeight,
+        prev_hash: CryptoHash,
+        prev_random_value: CryptoHash,
+    ) {
+        self.spawn(move || BackgroundSigned::Vrf {
+            next_height,
+            prev_hash,
+            vrf: signer.compute_vrf_with_proof(prev_random_value.as_ref()),
+        });
+    }
+
+    /// Signs the header of a block built with [`UnsignedBlockSigner`].
+    pub(crate) fn sign_block(&self, signer: Arc<dyn ValidatorSigner>, block: Block) {
+        self.spawn(move || {
+            let header = block.header();
+            let signature = signer
+                .sign_block_header_parts(
+                    *header.prev_hash(),
+                    &header.inner_lite_bytes(),
+                    &header.inner_rest_bytes(),
+                )
+                .map(|(_, signature)| signature);
+            BackgroundSigned::Block { block, signature }
+        });
+    }
+
+    fn spawn(&self, job: impl FnOnce() -> BackgroundSigned + Send + 'static) {
+        // The thread only stops when the actor does.
+        let _ = self.jobs.send(Box::new(job));
+    }
+}
+
+/// Signer for building a block on the client actor thread out of the VRF output computed in the
+/// background.  The header is left with an empty signature, which is set once
+/// [`BackgroundSigner::sign_block`] has signed it, before the block is processed or published.
+pub(crate) struct UnsignedBlockSigner {
+    pub(crate) signer: Arc<dyn ValidatorSigner>,
+    pub(crate) vrf: (Value, Proof),
+}
+
+impl ValidatorSigner for UnsignedBlockSigner {
+    fn validator_id(&self) -> &AccountId {
+        self.signer.validator_id()
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.signer.public_key()
+    }
+
+    fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
+        self.signer.sign_telemetry(info)
+    }
+
+    fn sign_block_header_parts(
+        &self,
+        prev_hash: CryptoHash,
+        inner_lite: &[u8],
+        inner_rest: &[u8],
+    ) -> Result<(CryptoHash, Signature), SignerError> {
+        let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
+        Ok((hash, Signature::empty(KeyType::ED25519)))
+    }
+
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, SignerError> {
+        self.signer.sign_chunk_hash(chunk_hash)
+    }
+
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, SignerError> {
+        self.signer.sign_approval(inner, target_height)
+    }
+
+    fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
+        self.signer.sign_challenge(challenge_body)
+    }
+
+    fn sign_account_announce(
+        &self,
+        account_id: &AccountId,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> Signature {
+        self.signer.sign_account_announce(account_id, peer_id, epoch_id)
+    }
+
+    fn compute_vrf_with_proof(&self, _data: &[u8]) -> Result<(Value, Proof), SignerError> {
+        Ok(self.vrf.clone())
+    }
+
+    fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
+        self.signer.write_to_file(path)
+    }
+}
@@ -0,0 +1,65 @@
+//! Verification of the blocks received from peers ahead of their processing.  The client actor
+//! hands every received block to a thread of its own, which checks the chunk header signatures
+//! and the roots of the block and sends it back to the actor as a [`VerifiedBlock`] message.  The
+//! verification of a block thus overlaps with the apply and commit of the previous one on the
+//! actor thread.
+use std::sync::{mpsc, Arc};
+use std::time::Instant;
+
+use actix::{Addr, Message};
+
+use near_chain::{Chain, RuntimeAdapter};
+use near_primitives::block::Block;
+use near_primitives::network::PeerId;
+use near_primitives::utils::MaybeValidated;
+
+use crate::metrics;
+use crate::ClientActor;
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct VerifiedBlock {
+    /// Marked as valid if it passed the verification.  A block which failed it is processed as
+    /// usual, which verifies it again and handles the error.
+    pub block: MaybeValidated<Block>,
+    pub peer_id: PeerId,
+    pub was_requested: bool,
+}
+
+pub(crate) struct BlockVerifier {
+    blocks: mpsc::Sender<(Block, PeerId, bool)>,
+}
+
+impl BlockVerifier {
+    pub(crate) fn new(
+        client_addr: Addr<ClientActor>,
+        runtime_adapter: Arc<dyn RuntimeAdapter>,
+        genesis_block: Block,
+    ) -> Self {
+        let (blocks, blocks_rx) = mpsc::channel::<(Block, PeerId, bool)>();
+        // A single thread keeps the blocks in the order they were received in, so that the
+        // children don't get to the actor before their parents and end up as orphans.
+        std::thread::Builder::new()
+            .name("block_verifier".to_string())
+            .spawn(move || {
+                for (block, peer_id, was_requested) in blocks_rx {
+                    let start = Instant::now();
+                    let block = MaybeValidated::from(block);
+                    if Chain::validate_block_impl(&*runtime_adapter, &genesis_block, &block).is_ok()
+                    {
+                        block.mark_as_valid();
+                    }
+                    metrics::BLOCK_BACKGROUND_VERIFICATION_TIME
+                        .observe(start.elapsed().as_secs_f64());
+                    client_addr.do_send(VerifiedBlock { block, peer_id, was_requested });
+                }
+            })
+            .expect("Failed to start the block verifier thread");
+        Self { blocks }
+    }
+
+    pub(crate) fn verify(&self, block: Block, peer_id: PeerId, was_requested: bool) {
+        // The thread only stops when the actor does.
+        let _ = self.blocks.send((block, peer_id, was_requested));
+    }
+}
@@ -13,20 +13,24 @@ use near_chain::chain::{
     OrphanMissingChunks, StateSplitRequest, TX_ROUTING_HEIGHT_HORIZON,
 };
 use near_chain::test_utils::format_hash;
-use near_chain::types::{AcceptedBlock, LatestKnown};
+use near_chain::types::{AcceptedBlock, LatestKnown, ValidatorInfoIdentifier};
+use near_chain::validate::validate_challenge;
 use near_chain::{
     BlockStatus, Chain, ChainGenesis, ChainStoreAccess, Doomslug, DoomslugThresholdMode, ErrorKind,
     Provenance, RuntimeAdapter,
 };
 use near_chain_configs::ClientConfig;
 use near_chunks::{ProcessPartialEncodedChunkResult, ShardsManager};
+use near_crypto::Signature;
 use near_network::types::{
     FullPeerInfo, NetworkClientResponses, NetworkRequests, PeerManagerAdapter,
 };
 use near_primitives::block::{Approval, ApprovalInner, ApprovalMessage, Block, BlockHeader, Tip};
 use near_primitives::challenge::{Challenge, ChallengeBody};
+use near_primitives::checked_feature;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath};
+use near_primitives::network::PeerId;
 use near_primitives::receipt::Receipt;
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, PartialEncodedChunkV2, ReedSolomonWrapper,
@@ -38,9 +42,20 @@ use near_primitives::types::{AccountId, ApprovalStake, BlockHeight, EpochId, Num
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::{to_timestamp, MaybeValidated};
 use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::views::{MissedProductionReason, QueryRequest, QueryResponseKind};
 
+use crate::approval_aggregation::ApprovalAggregation;
+use crate::approval_participation::ApprovalParticipation;
 use crate::chunks_delay_tracker::ChunksDelayTracker;
+use crate::contract_logs::ContractLogs;
+use crate::epoch_summary::{summarize_epoch, EpochSummaries};
+use crate::fee_estimation::FeeHistory;
+use crate::key_rotation::KeyRotation;
+use crate::missed_production::MissedProduction;
+use crate::shadow_validation::ShadowValidator;
 use crate::sync::{BlockSync, EpochSync, HeaderSync, StateSync, StateSyncResult};
+use crate::validator_events::ValidatorEvents;
+use crate::validator_stats::{Production, ValidatorStats};
 use crate::{metrics, SyncStatus};
 use near_client_primitives::types::{Error, ShardSyncDownload, ShardSyncStatus};
 use near_network::types::PeerManagerMessageRequest;
@@ -103,6 +118,29 @@ pub struct Client {
     last_time_head_progress_made: Instant,
     /// Keeps track of when the latest blocks and chunks were received.
     chunks_delay_tracker: ChunksDelayTracker,
+    /// Set in shadow validation mode, in which case `validator_signer` is not set.
+    shadow_validator: Option<ShadowValidator>,
+    /// Rotation of the validator key, until the next key is in use.
+    key_rotation: Option<KeyRotation>,
+    /// Set when `validator_signer` has been switched to the next key, until the client actor has
+    /// picked it up, see [`Client::take_validator_signer_rotated`].
+    validator_signer_rotated: bool,
+    /// Set if events are reported when the validator is at risk of being kicked out.
+    pub(crate) validator_events: Option<ValidatorEvents>,
+    /// Set if log lines of contract executions are streamed to subscribers.
+    pub(crate) contract_logs: Option<ContractLogs>,
+    /// Inclusion of the approvals sent by the node in the blocks of other producers.
+    pub(crate) approval_participation: ApprovalParticipation,
+    /// Received approvals, to drop their copies, and approvals waiting to be sent.
+    approval_aggregation: ApprovalAggregation,
+    /// Blocks and chunks the validator was to produce but didn't.
+    pub(crate) missed_production: MissedProduction,
+    /// Summaries of the latest epochs.
+    pub(crate) epoch_summaries: EpochSummaries,
+    /// Production stats of the validator which outlive restarts.
+    pub(crate) validator_stats: ValidatorStats,
+    /// Gas prices and fullness of the latest blocks, for fee estimates.
+    pub(crate) fee_history: FeeHistory,
 }
 
 impl Client {
@@ -120,7 +158,35 @@ impl Client {
         } else {
             DoomslugThresholdMode::NoApprovals
         };
-        let chain = Chain::new(runtime_adapter.clone(), &chain_genesis, doomslug_threshold_mode)?;
+        let mut chain =
+            Chain::new(runtime_adapter.clone(), &chain_genesis, doomslug_threshold_mode)?;
+        let mut fee_history = FeeHistory::new(&chain_genesis);
+        if let Err(err) = fee_history.load(&mut chain) {
+            warn!(target: "client", "Failed to load the fee history: {}", err);
+        }
+        chain.mut_store().set_account_history_index(config.account_history_index);
+        // In shadow validation mode the node must behave as a non-validator, so the signer is
+        // only ever used to produce blocks and chunks which are not sent anywhere.
+        let (validator_signer, shadow_validator) = if config.shadow_validation {
+            (None, validator_signer.map(ShadowValidator::new))
+        } else {
+            (validator_signer, None)
+        };
+        let key_rotation = match (&config.key_rotation, &validator_signer) {
+            (Some(key_rotation), Some(signer)) => {
+                Some(KeyRotation::new(key_rotation, signer.validator_id())?)
+            }
+            _ => None,
+        };
+        let validator_events = config.validator_events.clone().map(ValidatorEvents::new);
+        let contract_logs = config.contract_logs.clone().map(|contract_logs| {
+            ContractLogs::new(contract_logs, chain.store().store().clone(), chain_genesis.height)
+        });
+        let validator_stats =
+            ValidatorStats::load(chain.store().store(), config.uptime_tracking_start_epoch_height);
+        if let Some(top_k) = config.gas_attribution_top_k {
+            near_chain::gas_attribution::enable(top_k);
+        }
         let shards_mgr = ShardsManager::new(
             validator_signer.as_ref().map(|x| x.validator_id().clone()),
             runtime_adapter.clone(),
@@ -167,7 +233,7 @@ impl Client {
             validator_signer.clone(),
             doomslug_threshold_mode,
         );
-        Ok(Self {
+        let mut client = Self {
             #[cfg(feature = "test_features")]
             adv_produce_blocks: false,
             #[cfg(feature = "test_features")]
@@ -191,7 +257,23 @@ impl Client {
             rebroadcasted_blocks: lru::LruCache::new(NUM_REBROADCAST_BLOCKS),
             last_time_head_progress_made: Clock::instant(),
             chunks_delay_tracker: Default::default(),
-        })
+            shadow_validator,
+            key_rotation,
+            validator_signer_rotated: false,
+            validator_events,
+            contract_logs,
+            approval_participation: ApprovalParticipation::new(),
+            approval_aggregation: ApprovalAggregation::new(),
+            missed_production: MissedProduction::default(),
+            epoch_summaries: EpochSummaries::default(),
+            validator_stats,
+            fee_history,
+        };
+        client.validator_stats.restore_approvals(&mut client.approval_participation);
+        // The key might have been rotated before the restart.
+        let head = client.chain.head()?;
+        client.switch_validator_key_if_rotated(&head)?;
+        Ok(client)
     }
 
     // Checks if it's been at least `stall_timeout` since the last time the head was updated, or
@@ -332,13 +414,23 @@ impl Client {
     /// Produce block if we are block producer for given `next_height` block height.
     /// Either returns produced block (not applied) or error.
     pub fn produce_block(&mut self, next_height: BlockHeight) -> Result<Option<Block>, Error> {
-        let known_height = self.chain.mut_store().get_latest_known()?.height;
-
         let validator_signer = self
             .validator_signer
             .as_ref()
             .ok_or_else(|| Error::BlockProducer("Called without block producer info.".to_string()))?
             .clone();
+        self.produce_block_with_signer(next_height, &*validator_signer)
+    }
+
+    /// Same as `produce_block`, but signs with the given signer instead of the validator signer
+    /// of the client, e.g. with one holding the VRF output computed in the background.
+    pub(crate) fn produce_block_with_signer(
+        &mut self,
+        next_height: BlockHeight,
+        validator_signer: &dyn ValidatorSigner,
+    ) -> Result<Option<Block>, Error> {
+        let known_height = self.chain.mut_store().get_latest_known()?.height;
+
         let head = self.chain.head()?;
         assert_eq!(
             head.epoch_id,
@@ -355,8 +447,6 @@ impl Client {
         let prev_hash = head.last_block_hash;
         let prev_height = head.height;
         let prev_prev_hash = *prev.prev_hash();
-        let prev_epoch_id = prev.epoch_id().clone();
-        let prev_next_bp_hash = *prev.next_bp_hash();
 
         // Check and update the doomslug tip here. This guarantees that our endorsement will be in the
         // doomslug witness. Have to do it before checking the ability to produce a block.
@@ -382,6 +472,11 @@ impl Client {
         let validator_pk = validator_stake.take_public_key();
         if validator_pk != validator_signer.public_key() {
             debug!(target: "client", "Local validator key {} does not match expected validator key {}, skipping block production", validator_signer.public_key(), validator_pk);
+            self.missed_production.record_failure(
+                next_height,
+                None,
+                MissedProductionReason::SignerError,
+            );
             #[cfg(not(feature = "test_features"))]
             return Ok(None);
             #[cfg(feature = "test_features")]
@@ -402,12 +497,6 @@ impl Client {
 
         let mut approvals_map = self.doomslug.remove_witness(&prev_hash, prev_height, next_height);
 
-        // At this point, the previous epoch hash must be available
-        let epoch_id = self
-            .runtime_adapter
-            .get_epoch_id_from_prev_block(&head.last_block_hash)
-            .expect("Epoch hash should exist at this point");
-
         let approvals = self
             .runtime_adapter
             .get_epoch_block_approvers_ordered(&prev_hash)?
@@ -423,9 +512,61 @@ impl Client {
 
         debug_assert_eq!(approvals_map.len(), 0);
 
+        let block = match self.build_block(
+            prev_hash,
+            next_height,
+            new_chunks,
+            approvals,
+            validator_signer,
+        ) {
+            Ok(block) => block,
+            Err(Error::BlockProducer(err)) => {
+                warn!(target: "client", "Skipping block production at height {}: {}", next_height, err);
+                self.missed_production.record_failure(
+                    next_height,
+                    None,
+                    MissedProductionReason::SignerError,
+                );
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Update latest known even before returning block out, to prevent race conditions.
+        self.chain.mut_store().save_latest_known(LatestKnown {
+            height: next_height,
+            seen: to_timestamp(Clock::utc()),
+        })?;
+
+        metrics::BLOCK_PRODUCED_TOTAL.inc();
+
+        Ok(Some(block))
+    }
+
+    /// Builds a block at `next_height` on top of `prev_hash` out of the given new chunks and
+    /// approvals, without checking that we are its producer and without storing anything.
+    /// Fails with `Error::BlockProducer` only if the signer fails.
+    fn build_block(
+        &mut self,
+        prev_hash: CryptoHash,
+        next_height: BlockHeight,
+        new_chunks: HashMap<ShardId, ShardChunkHeader>,
+        approvals: Vec<Option<Signature>>,
+        validator_signer: &dyn ValidatorSigner,
+    ) -> Result<Block, Error> {
+        let prev = self.chain.get_block_header(&prev_hash)?;
+        let prev_epoch_id = prev.epoch_id().clone();
+        let prev_next_bp_hash = *prev.next_bp_hash();
+
+        // At this point, the previous epoch hash must be available
+        let epoch_id = self
+            .runtime_adapter
+            .get_epoch_id_from_prev_block(&prev_hash)
+            .expect("Epoch hash should exist at this point");
+
         let next_epoch_id = self
             .runtime_adapter
-            .get_next_epoch_id_from_prev_block(&head.last_block_hash)
+            .get_next_epoch_id_from_prev_block(&prev_hash)
             .expect("Epoch hash should exist at this point");
 
         let protocol_version = self.runtime_adapter.get_epoch_protocol_version(&epoch_id)?;
@@ -465,32 +606,35 @@ impl Client {
 
         let prev_header = &prev_block.header();
 
-        let next_epoch_id =
-            self.runtime_adapter.get_next_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let next_epoch_id = self.runtime_adapter.get_next_epoch_id_from_prev_block(&prev_hash)?;
 
-        let minted_amount =
-            if self.runtime_adapter.is_next_block_epoch_start(&head.last_block_hash)? {
-                Some(self.runtime_adapter.get_epoch_minted_amount(&next_epoch_id)?)
-            } else {
-                None
-            };
+        let minted_amount = if self.runtime_adapter.is_next_block_epoch_start(&prev_hash)? {
+            Some(self.runtime_adapter.get_epoch_minted_amount(&next_epoch_id)?)
+        } else {
+            None
+        };
 
-        let epoch_sync_data_hash =
-            if self.runtime_adapter.is_next_block_epoch_start(&head.last_block_hash)? {
-                Some(self.runtime_adapter.get_epoch_sync_data_hash(
-                    prev_block.hash(),
-                    &epoch_id,
-                    &next_epoch_id,
-                )?)
-            } else {
-                None
-            };
+        let epoch_sync_data_hash = if self.runtime_adapter.is_next_block_epoch_start(&prev_hash)? {
+            Some(self.runtime_adapter.get_epoch_sync_data_hash(
+                prev_block.hash(),
+                &epoch_id,
+                &next_epoch_id,
+            )?)
+        } else {
+            None
+        };
 
-        // Get all the current challenges.
-        // TODO(2445): Enable challenges when they are working correctly.
-        // let challenges = self.challenges.drain().map(|(_, challenge)| challenge).collect();
         let this_epoch_protocol_version =
             self.runtime_adapter.get_epoch_protocol_version(&epoch_id)?;
+        let challenges = if checked_feature!(
+            "protocol_feature_challenges",
+            Challenges,
+            this_epoch_protocol_version
+        ) {
+            self.challenges_to_include(&epoch_id, &prev_hash)
+        } else {
+            vec![]
+        };
         let next_epoch_protocol_version =
             self.runtime_adapter.get_epoch_protocol_version(&next_epoch_id)?;
 
@@ -510,21 +654,14 @@ impl Client {
             max_gas_price,
             minted_amount,
             prev_block_extra.challenges_result,
-            vec![],
-            &*validator_signer,
+            challenges,
+            validator_signer,
             next_bp_hash,
             block_merkle_root,
-        );
-
-        // Update latest known even before returning block out, to prevent race conditions.
-        self.chain.mut_store().save_latest_known(LatestKnown {
-            height: next_height,
-            seen: to_timestamp(Clock::utc()),
-        })?;
+        )
+        .map_err(|err| Error::BlockProducer(err.to_string()))?;
 
-        metrics::BLOCK_PRODUCED_TOTAL.inc();
-
-        Ok(Some(block))
+        Ok(block)
     }
 
     pub fn produce_chunk(
@@ -548,6 +685,30 @@ impl Client {
             return Ok(None);
         }
 
+        let (encoded_chunk, merkle_paths, outgoing_receipts) = self.build_chunk(
+            prev_block_hash,
+            epoch_id,
+            last_header,
+            next_height,
+            shard_id,
+            &*validator_signer,
+        )?;
+
+        metrics::CHUNK_PRODUCED_TOTAL.inc();
+        Ok(Some((encoded_chunk, merkle_paths, outgoing_receipts)))
+    }
+
+    /// Builds a chunk at `next_height` on top of `prev_block_hash` out of the transactions in the
+    /// pool, without checking that we are its producer.
+    fn build_chunk(
+        &mut self,
+        prev_block_hash: CryptoHash,
+        epoch_id: &EpochId,
+        last_header: ShardChunkHeader,
+        next_height: BlockHeight,
+        shard_id: ShardId,
+        validator_signer: &dyn ValidatorSigner,
+    ) -> Result<(EncodedShardChunk, Vec<MerklePath>, Vec<Receipt>), Error> {
         if self.runtime_adapter.is_next_block_epoch_start(&prev_block_hash)? {
             let prev_prev_hash = *self.chain.get_block_header(&prev_block_hash)?.prev_hash();
             if !self.chain.prev_block_is_caught_up(&prev_prev_hash, &prev_block_hash)? {
@@ -617,7 +778,7 @@ impl Client {
             &outgoing_receipts,
             outgoing_receipts_root,
             tx_root,
-            &*validator_signer,
+            validator_signer,
             &mut self.rs,
             protocol_version,
         )?;
@@ -633,28 +794,32 @@ impl Client {
             encoded_chunk.chunk_hash().0,
         );
 
-        metrics::CHUNK_PRODUCED_TOTAL.inc();
-        Ok(Some((encoded_chunk, merkle_paths, outgoing_receipts)))
+        Ok((encoded_chunk, merkle_paths, outgoing_receipts))
     }
 
-    /// Prepares an ordered list of valid transactions from the pool up the limits.
+    /// Prepares an ordered list of valid transactions from the pool up the limits, lowered by the
+    /// local caps of the produced chunks if configured.
     fn prepare_transactions(
         &mut self,
         shard_id: ShardId,
         chunk_extra: &ChunkExtra,
         prev_block_header: &BlockHeader,
     ) -> Result<Vec<SignedTransaction>, Error> {
-        let Self { chain, shards_mgr, runtime_adapter, .. } = self;
+        let Self { chain, shards_mgr, runtime_adapter, config, .. } = self;
+        let gas_limit = match config.produced_chunk_gas_limit {
+            Some(gas_cap) if gas_cap < chunk_extra.gas_limit() => gas_cap,
+            _ => chunk_extra.gas_limit(),
+        };
 
         let next_epoch_id =
             runtime_adapter.get_epoch_id_from_prev_block(prev_block_header.hash())?;
         let protocol_version = runtime_adapter.get_epoch_protocol_version(&next_epoch_id)?;
 
-        let transactions = if let Some(mut iter) = shards_mgr.get_pool_iterator(shard_id) {
+        let mut transactions = if let Some(mut iter) = shards_mgr.get_pool_iterator(shard_id) {
             let transaction_validity_period = chain.transaction_validity_period;
             runtime_adapter.prepare_transactions(
                 prev_block_header.gas_price(),
-                chunk_extra.gas_limit(),
+                gas_limit,
                 &next_epoch_id,
                 shard_id,
                 *chunk_extra.state_root(),
@@ -681,9 +846,49 @@ impl Client {
         // Reintroduce valid transactions back to the pool. They will be removed when the chunk is
         // included into the block.
         shards_mgr.reintroduce_transactions(shard_id, &transactions);
+        // Dropping the last transactions keeps the rest valid, as every account's transactions
+        // are ordered by nonce and were verified in order.
+        match config.produced_chunk_max_transactions {
+            Some(max_transactions) if transactions.len() > max_transactions => {
+                transactions.truncate(max_transactions);
+                metrics::PRODUCED_CHUNK_CAP_BINDING_TOTAL
+                    .with_label_values(&["transactions"])
+                    .inc();
+            }
+            // Transactions left in the pool might have fit into the gas limit of the chunk.
+            _ if gas_limit < chunk_extra.gas_limit()
+                && shards_mgr.pool_len(shard_id) > transactions.len() =>
+            {
+                metrics::PRODUCED_CHUNK_CAP_BINDING_TOTAL.with_label_values(&["gas"]).inc();
+            }
+            _ => {}
+        }
         Ok(transactions)
     }
 
+    /// Current challenges which can be included in the block on top of `prev_hash`: the valid
+    /// ones, which slash the producers of the challenged blocks and chunks, and the malicious ones,
+    /// which slash the challengers.  Challenges which can't be verified, e.g. because their
+    /// blocks are gone or their challengers aren't validators anymore, are dropped.
+    fn challenges_to_include(
+        &mut self,
+        epoch_id: &EpochId,
+        prev_hash: &CryptoHash,
+    ) -> Vec<Challenge> {
+        let runtime_adapter = &*self.runtime_adapter;
+        self.challenges.retain(|_, challenge| {
+            match validate_challenge(runtime_adapter, epoch_id, prev_hash, challenge) {
+                Ok(_) => true,
+                Err(err) if err.kind() == ErrorKind::MaliciousChallenge => true,
+                Err(err) => {
+                    warn!(target: "client", "Dropping challenge {}: {}", challenge.hash, err);
+                    false
+                }
+            }
+        });
+        self.challenges.values().cloned().collect()
+    }
+
     pub fn send_challenges(&mut self, challenges: Vec<ChallengeBody>) {
         if let Some(validator_signer) = &self.validator_signer {
             for body in challenges {
@@ -746,34 +951,20 @@ impl Client {
             )
         };
 
-        // Send out challenges that accumulated via on_challenge.
-        self.send_challenges(challenges);
-
-        // Send out challenge if the block was found to be invalid.
-        if let Some(validator_signer) = self.validator_signer.as_ref() {
-            match &result {
-                Err(e) => match e.kind() {
-                    near_chain::ErrorKind::InvalidChunkProofs(chunk_proofs) => {
-                        self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkProofs(*chunk_proofs),
-                                &**validator_signer,
-                            )),
-                        ));
-                    }
-                    near_chain::ErrorKind::InvalidChunkState(chunk_state) => {
-                        self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::Challenge(Challenge::produce(
-                                ChallengeBody::ChunkState(*chunk_state),
-                                &**validator_signer,
-                            )),
-                        ));
-                    }
-                    _ => {}
-                },
+        // Challenge the block if it was found to be invalid.
+        if let Err(e) = &result {
+            match e.kind() {
+                near_chain::ErrorKind::InvalidChunkProofs(chunk_proofs) => {
+                    challenges.push(ChallengeBody::ChunkProofs(*chunk_proofs));
+                }
+                near_chain::ErrorKind::InvalidChunkState(chunk_state) => {
+                    challenges.push(ChallengeBody::ChunkState(*chunk_state));
+                }
                 _ => {}
             }
         }
+        // Send out challenges that accumulated via on_challenge.
+        self.send_challenges(challenges);
 
         if let Ok(Some(_)) = result {
             self.last_time_head_progress_made = Clock::instant();
@@ -915,7 +1106,11 @@ impl Client {
         headers: Vec<BlockHeader>,
     ) -> Result<(), near_chain::Error> {
         let mut challenges = vec![];
-        self.chain.sync_block_headers(headers, &mut |challenge| challenges.push(challenge))?;
+        if self.config.header_only {
+            self.chain.sync_headers_only(headers, &mut |challenge| challenges.push(challenge))?;
+        } else {
+            self.chain.sync_block_headers(headers, &mut |challenge| challenges.push(challenge))?;
+        }
         self.send_challenges(challenges);
         Ok(())
     }
@@ -960,6 +1155,8 @@ impl Client {
         Ok(())
     }
 
+    /// Sends the approval to the next block producer, or collects it if that's the node.  With
+    /// `approval_batching` the approval is only queued until `flush_approvals`.
     pub fn send_approval(
         &mut self,
         parent_hash: &CryptoHash,
@@ -972,15 +1169,97 @@ impl Client {
             self.collect_block_approval(&approval, ApprovalType::SelfApproval);
         } else {
             debug!(target: "client", "Sending an approval {:?} from {} to {} for {}", approval.inner, approval.account_id, next_block_producer, approval.target_height);
-            let approval_message = ApprovalMessage::new(approval, next_block_producer);
-            self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
-                NetworkRequests::Approval { approval_message },
-            ));
+            self.approval_participation.record_sent(approval.inner.clone(), approval.target_height);
+            if self.config.approval_batching {
+                self.approval_aggregation.queue(next_block_producer, approval);
+            } else {
+                let approval_message = ApprovalMessage::new(approval, next_block_producer);
+                self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::Approval { approval_message },
+                ));
+            }
         }
 
         Ok(())
     }
 
+    /// Sends the approvals queued by `send_approval`, those to the same block producer in one
+    /// message.
+    pub fn flush_approvals(&mut self) {
+        for (target, mut approvals) in self.approval_aggregation.take_pending() {
+            let request = if approvals.len() == 1 {
+                let approval = approvals.pop().unwrap();
+                NetworkRequests::Approval {
+                    approval_message: ApprovalMessage::new(approval, target),
+                }
+            } else {
+                debug!(target: "client", "Sending {} approvals to {} at once", approvals.len(), target);
+                metrics::APPROVALS_BATCHED_TOTAL.inc_by(approvals.len() as u64);
+                NetworkRequests::Approvals { target, approvals }
+            };
+            self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(request));
+        }
+    }
+
+    /// Records whether the approval sent by the node for the block is included in it.
+    fn check_approval_inclusion(&mut self, header: &BlockHeader) -> Result<(), Error> {
+        let account_id = match &self.validator_signer {
+            Some(signer) => signer.validator_id().clone(),
+            None => return Ok(()),
+        };
+        let prev_height = self.chain.get_block_header(header.prev_hash())?.height();
+        if !self.approval_participation.approval_sent_for(header, prev_height) {
+            return Ok(());
+        }
+        let approvers =
+            self.runtime_adapter.get_epoch_block_approvers_ordered(header.prev_hash())?;
+        let included =
+            approvers.iter().zip(header.approvals().iter()).any(|((approver, _), signature)| {
+                approver.account_id == account_id && signature.is_some()
+            });
+        let producer =
+            self.runtime_adapter.get_block_producer(header.epoch_id(), header.height())?;
+        self.approval_participation.record_block(header.epoch_id(), producer, included);
+        Ok(())
+    }
+
+    /// Accounts for the blocks the validator was to produce at the heights the block skipped and
+    /// for its chunks the block doesn't include.
+    fn check_missed_production(
+        &mut self,
+        block: &Block,
+        provenance: Provenance,
+    ) -> Result<(), Error> {
+        let account_id = match &self.validator_signer {
+            Some(signer) => signer.validator_id().clone(),
+            None => return Ok(()),
+        };
+        let header = block.header();
+        let syncing = provenance == Provenance::SYNC || self.sync_status.is_syncing();
+        let prev_height = self.chain.get_block_header(header.prev_hash())?.height();
+        for height in prev_height + 1..header.height() {
+            if self.runtime_adapter.get_block_producer(header.epoch_id(), height)? == account_id {
+                self.missed_production.block_missed(height, syncing);
+            }
+        }
+        for (shard_id, chunk) in block.chunks().iter().enumerate() {
+            if chunk.height_included() == header.height() {
+                continue;
+            }
+            let shard_id = shard_id as ShardId;
+            let chunk_producer = self.runtime_adapter.get_chunk_producer(
+                header.epoch_id(),
+                header.height(),
+                shard_id,
+            )?;
+            if chunk_producer == account_id {
+                self.missed_production.chunk_missed(header.height(), shard_id, syncing);
+            }
+        }
+        self.missed_production.prune(header.height());
+        Ok(())
+    }
+
     /// Gets called when block got accepted.
     /// Send updates over network, update tx pool and notify ourselves if it's time to produce next block.
     /// Blocks are passed in no particular order.
@@ -993,6 +1272,285 @@ impl Client {
         self.on_block_accepted_with_optional_chunk_produce(block_hash, status, provenance, false);
     }
 
+    /// Submits the staking transaction with the next validator key from the configured epoch on,
+    /// again until it is included, and switches to the next key as soon as it is the key of the
+    /// validator for the next block.
+    fn check_key_rotation(&mut self) -> Result<(), Error> {
+        let head = self.chain.head()?;
+        if self.key_rotation.is_none() || self.switch_validator_key_if_rotated(&head)? {
+            return Ok(());
+        }
+        if self.sync_status.is_syncing() {
+            return Ok(());
+        }
+        let epoch_height =
+            self.runtime_adapter.get_epoch_height_from_prev_block(&head.last_block_hash)?;
+        let (account_id, account_public_key) = match self.key_rotation.as_ref() {
+            Some(key_rotation) if key_rotation.should_check(epoch_height) => {
+                (key_rotation.next_signer.validator_id().clone(), key_rotation.account_public_key())
+            }
+            _ => return Ok(()),
+        };
+
+        let nonce = match self.query_at_head(
+            &head,
+            &account_id,
+            QueryRequest::ViewAccessKey {
+                account_id: account_id.clone(),
+                public_key: account_public_key,
+            },
+        )? {
+            QueryResponseKind::AccessKey(access_key) => access_key.nonce,
+            _ => return Err(Error::Other("unexpected response to ViewAccessKey".to_string())),
+        };
+        let key_rotation = match self.key_rotation.as_mut() {
+            Some(key_rotation) => key_rotation,
+            None => return Ok(()),
+        };
+        if !key_rotation.should_submit(head.height, nonce) {
+            if key_rotation.staking_tx_included() {
+                info!(target: "client", "Transaction staking with the next validator key of {} has been included", account_id);
+            }
+            return Ok(());
+        }
+        let stake = match self.query_at_head(
+            &head,
+            &account_id,
+            QueryRequest::ViewAccount { account_id: account_id.clone() },
+        )? {
+            QueryResponseKind::ViewAccount(account) => account.locked,
+            _ => return Err(Error::Other("unexpected response to ViewAccount".to_string())),
+        };
+        let tx = match self.key_rotation.as_mut() {
+            Some(key_rotation) => key_rotation.staking_transaction(
+                nonce + 1,
+                stake,
+                head.height,
+                head.last_block_hash,
+            ),
+            None => return Ok(()),
+        };
+        info!(target: "client", "Submitting transaction {} staking {} with the next validator key of {}", tx.get_hash(), stake, account_id);
+        self.process_tx(tx, false, false);
+        Ok(())
+    }
+
+    /// Reports events if the validator missed too many blocks or chunks in the epoch so far or
+    /// would be kicked out if the epoch ended at the head.  Computing the stats and the
+    /// projection locks the epoch manager, so it is done periodically rather than on every head.
+    pub(crate) fn check_validator_events(&mut self) -> Result<(), Error> {
+        let account_id = match (&self.validator_events, &self.validator_signer) {
+            (Some(_), Some(signer)) => signer.validator_id().clone(),
+            _ => return Ok(()),
+        };
+        if self.sync_status.is_syncing() {
+            return Ok(());
+        }
+        let head = self.chain.head()?;
+        let epoch_start_height =
+            self.runtime_adapter.get_epoch_start_height(&head.last_block_hash)?;
+        let min_blocks =
+            self.config.validator_events.as_ref().map_or(0, |config| config.min_blocks);
+        if head.height + 1 < epoch_start_height + min_blocks {
+            if let Some(validator_events) = self.validator_events.as_mut() {
+                validator_events.skip_check();
+            }
+            return Ok(());
+        }
+        let validator_info = self
+            .runtime_adapter
+            .get_validator_info(ValidatorInfoIdentifier::BlockHash(head.last_block_hash))?;
+        let projection = self.runtime_adapter.get_validator_projection(&head.last_block_hash)?;
+        let stats = validator_info.current_validators.iter().find(|v| v.account_id == account_id);
+        let kickout =
+            projection.kickout.iter().find(|k| k.account_id == account_id).map(|k| &k.reason);
+        if let Some(validator_events) = self.validator_events.as_mut() {
+            validator_events.check(
+                &account_id,
+                validator_info.epoch_height,
+                head.height,
+                stats,
+                kickout,
+            );
+        }
+        Ok(())
+    }
+
+    /// Summarizes the previous epoch if the new head is the first block of an epoch.
+    fn check_epoch_summary(&mut self, block: &Block) -> Result<(), Error> {
+        if self.sync_status.is_syncing()
+            || !self.runtime_adapter.is_next_block_epoch_start(block.header().prev_hash())?
+        {
+            return Ok(());
+        }
+        let last_block_header = self.chain.get_block_header(block.header().prev_hash())?.clone();
+        // Genesis is not an epoch of its own.
+        if last_block_header.height() == self.chain.genesis().height() {
+            return Ok(());
+        }
+        let account_id = self.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+        let summary =
+            summarize_epoch(&*self.runtime_adapter, account_id.as_ref(), &last_block_header)?;
+        if let Some(validator) = &summary.validator {
+            self.validator_stats.epoch_ended(
+                summary.epoch_height,
+                Production {
+                    num_produced_blocks: validator.num_produced_blocks,
+                    num_expected_blocks: validator.num_expected_blocks,
+                    num_produced_chunks: validator.num_produced_chunks,
+                    num_expected_chunks: validator.num_expected_chunks,
+                },
+            );
+            self.save_validator_stats();
+        }
+        self.epoch_summaries.add(summary);
+        Ok(())
+    }
+
+    /// Saves the production stats of the validator, along with the inclusion of its approvals
+    /// so far, so that they survive a restart.
+    pub(crate) fn save_validator_stats(&mut self) {
+        if self.validator_signer.is_none() {
+            return;
+        }
+        self.validator_stats.save_approvals(&self.approval_participation);
+        if let Err(err) = self.validator_stats.save(self.chain.store().store()) {
+            warn!(target: "client", "Failed to save the validator stats: {}", err);
+        }
+    }
+
+    /// Streams the matching logs of the executions included in the new head.  On reorgs, the
+    /// logs of the blocks which are no longer on the canonical chain are not retracted.
+    fn record_contract_logs(&self, block: &Block) {
+        if let Some(contract_logs) = &self.contract_logs {
+            contract_logs.record_block(*block.hash());
+        }
+    }
+
+    /// Starts signing with the next validator key if it is the key of the validator for the block
+    /// following `head`.  Returns whether the key has been switched.
+    fn switch_validator_key_if_rotated(&mut self, head: &Tip) -> Result<bool, Error> {
+        let next_signer = match self.key_rotation.as_ref() {
+            Some(key_rotation) => key_rotation.next_signer.clone(),
+            None => return Ok(false),
+        };
+        let epoch_id = self.runtime_adapter.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let validator = match self.runtime_adapter.get_validator_by_account_id(
+            &epoch_id,
+            &head.last_block_hash,
+            next_signer.validator_id(),
+        ) {
+            Ok((validator, _)) => validator,
+            // Not a validator in this epoch.
+            Err(_) => return Ok(false),
+        };
+        if validator.public_key() != &next_signer.public_key() {
+            return Ok(false);
+        }
+        info!(target: "client", "Switching to the next validator key {} of {}", next_signer.public_key(), next_signer.validator_id());
+        self.doomslug.set_signer(next_signer.clone());
+        self.validator_signer = Some(next_signer);
+        self.key_rotation = None;
+        self.validator_signer_rotated = true;
+        Ok(true)
+    }
+
+    /// Returns whether `validator_signer` has been switched to the next key since the last call.
+    pub(crate) fn take_validator_signer_rotated(&mut self) -> bool {
+        std::mem::take(&mut self.validator_signer_rotated)
+    }
+
+    /// Executes a view query against the state of the shard of `account_id` at `head`.
+    fn query_at_head(
+        &mut self,
+        head: &Tip,
+        account_id: &AccountId,
+        request: QueryRequest,
+    ) -> Result<QueryResponseKind, Error> {
+        let header = self.chain.get_block_header(&head.last_block_hash)?.clone();
+        let shard_id = self.runtime_adapter.account_id_to_shard_id(account_id, &head.epoch_id)?;
+        let shard_uid = self.runtime_adapter.shard_id_to_uid(shard_id, &head.epoch_id)?;
+        let state_root =
+            *self.chain.get_chunk_extra(&head.last_block_hash, &shard_uid)?.state_root();
+        self.runtime_adapter
+            .query(
+                shard_uid,
+                &state_root,
+                header.height(),
+                header.raw_timestamp(),
+                header.prev_hash(),
+                header.hash(),
+                header.epoch_id(),
+                &request,
+            )
+            .map(|response| response.kind)
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
+    /// In shadow validation mode, compares the accepted block with the block, chunks and approval
+    /// produced locally for its height, then produces the ones for the next height.  Nothing
+    /// produced here is ever broadcast.
+    fn shadow_validate(&mut self, block: &Block) {
+        let signer = match self.shadow_validator.as_mut() {
+            Some(shadow_validator) => {
+                shadow_validator.check_block(block);
+                shadow_validator.signer.clone()
+            }
+            None => return,
+        };
+        if let Err(err) = self.shadow_produce(block, &*signer) {
+            debug!(target: "client", "Shadow validation: failed to produce on top of {}: {}", block.hash(), err);
+        }
+    }
+
+    fn shadow_produce(&mut self, block: &Block, signer: &dyn ValidatorSigner) -> Result<(), Error> {
+        let prev_hash = *block.hash();
+        let next_height = block.header().height() + 1;
+        let approval = Approval::new(prev_hash, block.header().height(), next_height, signer)
+            .map_err(|err| Error::BlockProducer(err.to_string()))?;
+
+        let epoch_id = self.runtime_adapter.get_epoch_id_from_prev_block(&prev_hash)?;
+        let mut new_chunks = HashMap::new();
+        for shard_id in 0..self.runtime_adapter.num_shards(&epoch_id)? {
+            let shard_uid = self.runtime_adapter.shard_id_to_uid(shard_id, &epoch_id)?;
+            // Chunks can only be produced for the shards whose state we track.
+            if self.chain.get_chunk_extra(&prev_hash, &shard_uid).is_err() {
+                continue;
+            }
+            let timer = metrics::SHADOW_VALIDATION_PRODUCTION_TIME
+                .with_label_values(&["chunk"])
+                .start_timer();
+            let last_header =
+                Chain::get_prev_chunk_header(&*self.runtime_adapter, block, shard_id)?;
+            let (encoded_chunk, _, _) =
+                self.build_chunk(prev_hash, &epoch_id, last_header, next_height, shard_id, signer)?;
+            timer.observe_duration();
+            new_chunks.insert(shard_id, encoded_chunk.cloned_header());
+        }
+
+        let timer =
+            metrics::SHADOW_VALIDATION_PRODUCTION_TIME.with_label_values(&["block"]).start_timer();
+        let num_approvers =
+            self.runtime_adapter.get_epoch_block_approvers_ordered(&prev_hash)?.len();
+        let shadow_block = self.build_block(
+            prev_hash,
+            next_height,
+            new_chunks.clone(),
+            vec![None; num_approvers],
+            signer,
+        )?;
+        timer.observe_duration();
+
+        if let Some(shadow_validator) = self.shadow_validator.as_mut() {
+            shadow_validator.record_approval(approval);
+            for (_, chunk_header) in new_chunks {
+                shadow_validator.record_chunk(chunk_header);
+            }
+            shadow_validator.record_block(shadow_block.header().clone());
+        }
+        Ok(())
+    }
+
     pub fn record_accepted_block(&mut self, block_hash: &CryptoHash, chunks: &[ChunkHash]) {
         self.chunks_delay_tracker.finish_block_processing(block_hash, chunks);
     }
@@ -1019,6 +1577,15 @@ impl Client {
 
         let _ = self.check_and_update_doomslug_tip();
 
+        if let Err(err) = self.check_approval_inclusion(block.header()) {
+            debug!(target: "client", "Failed to check approval inclusion in block {}: {}", block_hash, err);
+        }
+        if !matches!(status, BlockStatus::Fork) {
+            if let Err(err) = self.check_missed_production(&block, provenance) {
+                debug!(target: "client", "Failed to check missed production in block {}: {}", block_hash, err);
+            }
+        }
+
         // If we produced the block, then it should have already been broadcasted.
         // If received the block from another node then broadcast "header first" to minimize network traffic.
         if provenance == Provenance::NONE {
@@ -1039,6 +1606,19 @@ impl Client {
         }
 
         if status.is_new_head() {
+            if let Err(err) = self.check_key_rotation() {
+                error!(target: "client", "Failed to rotate validator key: {}", err);
+            }
+            if let Err(err) = self.check_epoch_summary(&block) {
+                error!(target: "client", "Failed to summarize the epoch before block {}: {}", block_hash, err);
+            }
+            self.record_contract_logs(&block);
+            self.fee_history.record_block(&block);
+            // Challenges included in the canonical chain have been acted upon, so they aren't
+            // included again.
+            for challenge in block.challenges().iter() {
+                self.challenges.remove(&challenge.hash);
+            }
             self.shards_mgr.update_largest_seen_height(block.header().height());
             let last_final_block = block.header().last_final_block();
             let last_finalized_height = if last_final_block == &CryptoHash::default() {
@@ -1164,24 +1744,40 @@ impl Client {
                             block.header().height() + 1,
                             shard_id,
                         ) {
-                            Ok(Some((encoded_chunk, merkle_paths, receipts))) => self
-                                .shards_mgr
-                                .distribute_encoded_chunk(
-                                    encoded_chunk,
-                                    merkle_paths,
-                                    receipts,
-                                    self.chain.mut_store(),
-                                )
-                                .expect("Failed to process produced chunk"),
+                            Ok(Some((encoded_chunk, merkle_paths, receipts))) => {
+                                self.missed_production
+                                    .record_produced_chunk(block.header().height() + 1, shard_id);
+                                self.shards_mgr
+                                    .distribute_encoded_chunk(
+                                        encoded_chunk,
+                                        merkle_paths,
+                                        receipts,
+                                        self.chain.mut_store(),
+                                    )
+                                    .expect("Failed to process produced chunk")
+                            }
                             Ok(None) => {}
                             Err(err) => {
                                 error!(target: "client", "Error producing chunk {:?}", err);
+                                self.missed_production.record_failure(
+                                    block.header().height() + 1,
+                                    Some(shard_id),
+                                    MissedProductionReason::Error,
+                                );
                             }
                         }
                     }
                 }
             }
         }
+        if self.shadow_validator.is_some()
+            && provenance != Provenance::SYNC
+            && !self.sync_status.is_syncing()
+            && !matches!(status, BlockStatus::Fork)
+        {
+            self.shadow_validate(&block);
+        }
+
         for accepted_block in self.check_incomplete_chunks(block.hash()) {
             self.on_block_accepted_with_optional_chunk_produce(
                 accepted_block.hash,
@@ -1315,6 +1911,32 @@ impl Client {
         }
     }
 
+    /// Collects approvals received in one message. Returns false if the message carries more
+    /// approvals than there are block producers in the epoch of the head, in which case none of
+    /// them is collected. Copies for the same approver and target height are verified once.
+    pub fn collect_block_approvals(&mut self, approvals: Vec<Approval>, peer_id: PeerId) -> bool {
+        let num_block_producers = match self.chain.head().and_then(|head| {
+            self.runtime_adapter
+                .get_epoch_block_producers_ordered(&head.epoch_id, &head.last_block_hash)
+        }) {
+            Ok(block_producers) => block_producers.len(),
+            Err(err) => {
+                debug!(target: "client", "Dropping {} approvals: {}", approvals.len(), err);
+                return true;
+            }
+        };
+        if approvals.len() > num_block_producers {
+            return false;
+        }
+        let mut seen = HashSet::new();
+        for approval in approvals {
+            if seen.insert((approval.account_id.clone(), approval.target_height)) {
+                self.collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id.clone()));
+            }
+        }
+        true
+    }
+
     /// Collects block approvals. Returns false if block approval is invalid.
     ///
     /// We send the approval to doomslug given the epoch of the current tip iff:
@@ -1330,6 +1952,14 @@ impl Client {
     pub fn collect_block_approval(&mut self, approval: &Approval, approval_type: ApprovalType) {
         let Approval { inner, account_id, target_height, signature } = approval;
 
+        // The same approval often arrives over several routes; its copies are dropped before
+        // verifying the signature again.
+        if let ApprovalType::PeerApproval(_) = approval_type {
+            if self.approval_aggregation.is_duplicate(approval) {
+                return;
+            }
+        }
+
         let parent_hash = match inner {
             ApprovalInner::Endorsement(parent_hash) => *parent_hash,
             ApprovalInner::Skip(parent_height) => {
@@ -1382,7 +2012,7 @@ impl Client {
                 Approval::get_data_for_sig(inner, *target_height).as_ref(),
                 signature,
             ) {
-                Ok(true) => {}
+                Ok(true) => self.approval_aggregation.record_verified(approval),
                 _ => return,
             }
         }
@@ -1423,17 +2053,18 @@ impl Client {
         self.doomslug.on_approval_message(Clock::instant(), approval, &block_producer_stakes);
     }
 
-    /// Forwards given transaction to upcoming validators.
+    /// Forwards given transaction to the chunk producers of the shard at the upcoming
+    /// `tx_routing_height_horizon` heights and at twice as far, of this epoch and of the next one
+    /// near the boundary.
     fn forward_tx(&self, epoch_id: &EpochId, tx: &SignedTransaction) -> Result<(), Error> {
         let shard_id =
             self.runtime_adapter.account_id_to_shard_id(&tx.transaction.signer_id, epoch_id)?;
         let head = self.chain.head()?;
         let maybe_next_epoch_id = self.get_next_epoch_id_if_at_boundary(&head)?;
 
+        let routing_horizon = self.config.tx_routing_height_horizon;
         let mut validators = HashSet::new();
-        for horizon in
-            (2..=TX_ROUTING_HEIGHT_HORIZON).chain(vec![TX_ROUTING_HEIGHT_HORIZON * 2].into_iter())
-        {
+        for horizon in (2..=routing_horizon).chain(vec![routing_horizon * 2].into_iter()) {
             let validator =
                 self.chain.find_chunk_producer_for_forwarding(epoch_id, shard_id, horizon)?;
             validators.insert(validator);
@@ -1466,6 +2097,7 @@ impl Client {
             self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
                 NetworkRequests::ForwardTx(validator, tx.clone()),
             ));
+            metrics::TRANSACTIONS_FORWARDED_TOTAL.inc();
         }
 
         Ok(())
@@ -1496,7 +2128,7 @@ impl Client {
                 + self.config.epoch_length;
 
         let epoch_boundary_possible =
-            head.height + TX_ROUTING_HEIGHT_HORIZON >= next_epoch_estimated_height;
+            head.height + self.config.tx_routing_height_horizon >= next_epoch_estimated_height;
         if epoch_boundary_possible {
             Ok(Some(self.runtime_adapter.get_next_epoch_id_from_prev_block(&head.last_block_hash)?))
         } else {
@@ -1772,29 +2404,40 @@ impl Client {
     }
 
     /// When accepting challenge, we verify that it's valid given signature with current validators.
-    pub fn process_challenge(&mut self, _challenge: Challenge) -> Result<(), Error> {
-        // TODO(2445): Enable challenges when they are working correctly.
-        //        if self.challenges.contains_key(&challenge.hash) {
-        //            return Ok(());
-        //        }
-        //        debug!(target: "client", "Received challenge: {:?}", challenge);
-        //        let head = self.chain.head()?;
-        //        if self.runtime_adapter.verify_validator_or_fisherman_signature(
-        //            &head.epoch_id,
-        //            &head.prev_block_hash,
-        //            &challenge.account_id,
-        //            challenge.hash.as_ref(),
-        //            &challenge.signature,
-        //        )? {
-        //            // If challenge is not double sign, we should process it right away to invalidate the chain.
-        //            match challenge.body {
-        //                ChallengeBody::BlockDoubleSign(_) => {}
-        //                _ => {
-        //                    self.chain.process_challenge(&challenge);
-        //                }
-        //            }
-        //            self.challenges.insert(challenge.hash, challenge);
-        //        }
+    /// Accepted challenges are gossiped further and included in the blocks the node produces.
+    pub fn process_challenge(&mut self, challenge: Challenge) -> Result<(), Error> {
+        if self.challenges.contains_key(&challenge.hash) {
+            return Ok(());
+        }
+        let head = self.chain.head()?;
+        let protocol_version = self.runtime_adapter.get_epoch_protocol_version(&head.epoch_id)?;
+        if !checked_feature!("protocol_feature_challenges", Challenges, protocol_version) {
+            return Ok(());
+        }
+        debug!(target: "client", "Received challenge: {:?}", challenge);
+        if !self.runtime_adapter.verify_validator_or_fisherman_signature(
+            &head.epoch_id,
+            &head.prev_block_hash,
+            &challenge.account_id,
+            challenge.hash.as_ref(),
+            &challenge.signature,
+        )? {
+            return Err(ErrorKind::InvalidChallenge.into());
+        }
+        let kind = match challenge.body {
+            ChallengeBody::BlockDoubleSign(_) => "block_double_sign",
+            ChallengeBody::ChunkProofs(_) => "chunk_proofs",
+            ChallengeBody::ChunkState(_) => "chunk_state",
+        };
+        // If challenge is not double sign, we should process it right away to invalidate the chain.
+        if !matches!(challenge.body, ChallengeBody::BlockDoubleSign(_)) {
+            self.chain.process_challenge(&challenge);
+        }
+        metrics::CHALLENGES_RECEIVED_TOTAL.with_label_values(&[kind]).inc();
+        self.challenges.insert(challenge.hash, challenge.clone());
+        self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::Challenge(challenge),
+        ));
         Ok(())
     }
 
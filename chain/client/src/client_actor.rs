@@ -1,12 +1,18 @@
 //! Client actor orchestrates Client and facilitates network connection.
 
+use crate::background_signer::{BackgroundSigned, BackgroundSigner, UnsignedBlockSigner};
+use crate::block_verifier::{BlockVerifier, VerifiedBlock};
 use crate::client::Client;
-use crate::info::{get_validator_epoch_stats, InfoHelper, ValidatorInfoHelper};
+use crate::info::{
+    get_validator_epoch_stats, InfoHelper, ValidatorInfoHelper, ValidatorProductionStats,
+    ValidatorUptime,
+};
 use crate::metrics::PARTIAL_ENCODED_CHUNK_RESPONSE_DELAY;
 use crate::sync::{StateSync, StateSyncResult};
+use crate::validator_stats::Production;
 use crate::{metrics, StatusResponse};
 use actix::dev::SendError;
-use actix::{Actor, Addr, Arbiter, AsyncContext, Context, Handler, Message};
+use actix::{Actor, Addr, Arbiter, AsyncContext, Context, Handler, Message, MessageResult};
 use actix_rt::ArbiterHandle;
 use borsh::BorshSerialize;
 use chrono::DateTime;
@@ -15,6 +21,7 @@ use near_chain::chain::{
     BlockCatchUpResponse, StateSplitRequest, StateSplitResponse,
 };
 use near_chain::crypto_hash_timer::CryptoHashTimer;
+use near_chain::state_witness::get_chunk_state_witness_size;
 use near_chain::test_utils::format_hash;
 use near_chain::types::{AcceptedBlock, ValidatorInfoIdentifier};
 use near_chain::{
@@ -23,39 +30,47 @@ use near_chain::{
 };
 use near_chain_configs::ClientConfig;
 use near_client_primitives::types::{
-    Error, GetNetworkInfo, NetworkInfoResponse, ShardSyncDownload, ShardSyncStatus, Status,
-    StatusError, StatusSyncInfo, SyncStatus,
+    ConnectedPeerInfo, Error, GetEpochSummary, GetEpochSummaryError, GetFeeEstimate,
+    GetFeeEstimateError, GetMissedProduction, GetNetworkInfo, GetValidatorEvents,
+    GetValidatorEventsError, NetworkInfoResponse, ShardSyncDownload, ShardSyncStatus, Status,
+    StatusError, StatusSyncInfo, StopProcessingBlocks, SubscribeContractLogs,
+    SubscribeContractLogsError, SyncStatus,
 };
+use near_crypto::vrf::{Proof, Value};
 use near_network::types::{
     NetworkClientMessages, NetworkClientResponses, NetworkInfo, NetworkRequests,
     PeerManagerAdapter, PeerManagerMessageRequest,
 };
 use near_network_primitives::types::ReasonForBan;
 use near_performance_metrics;
+use near_performance_metrics::actor_metrics::{spawn_mailbox_probes, MailboxProbe};
 use near_performance_metrics_macros::{perf, perf_with_debug};
+use near_primitives::block::Tip;
 use near_primitives::block_header::ApprovalType;
 use near_primitives::epoch_manager::RngSeed;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::syncing::StatePartKey;
 use near_primitives::time::{Clock, Utc};
-use near_primitives::types::BlockHeight;
+use near_primitives::types::{BlockHeight, EpochHeight, ShardId};
 use near_primitives::unwrap_or_return;
 use near_primitives::utils::{from_timestamp, MaybeValidated};
-use near_primitives::validator_signer::ValidatorSigner;
+use near_primitives::validator_signer::{SignerError, ValidatorSigner};
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{
-    DebugBlockStatus, DebugChunkStatus, DetailedDebugStatus, ValidatorInfo,
+    ContractLogView, DebugBlockStatus, DebugChunkStatus, DetailedDebugStatus, EpochSummaryView,
+    FeeEstimateView, MissedProductionReason, MissedProductionView, ValidatorEventsView,
+    ValidatorInfo,
 };
 use near_store::db::DBCol::ColStateParts;
 use near_telemetry::TelemetryActor;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 use tracing::{debug, error, info, trace, warn};
 
 /// Multiplier on `max_block_time` to wait until deciding that chain stalled.
@@ -87,11 +102,20 @@ pub struct ClientActor {
 
     // Last time when log_summary method was called.
     log_summary_timer_next_attempt: DateTime<Utc>,
+    validator_events_next_attempt: DateTime<Utc>,
 
     block_production_started: bool,
     doomslug_timer_next_attempt: DateTime<Utc>,
     chunk_request_retry_next_attempt: DateTime<Utc>,
     sync_started: bool,
+    /// Set once the node is shutting down, see [`StopProcessingBlocks`].
+    stopped_processing_blocks: bool,
+    /// Signs approvals and produced blocks off the actor thread if the signer is remote.
+    background_signer: Option<BackgroundSigner>,
+    /// Height of the block whose production waits for the background signer.
+    block_signing_height: Option<BlockHeight>,
+    /// Verifies the received blocks while the actor processes the previous ones.
+    block_verifier: BlockVerifier,
     state_parts_task_scheduler: Box<dyn Fn(ApplyStatePartsRequest)>,
     block_catch_up_scheduler: Box<dyn Fn(BlockCatchUpRequest)>,
     state_split_scheduler: Box<dyn Fn(StateSplitRequest)>,
@@ -151,10 +175,13 @@ impl ClientActor {
         );
         wait_until_genesis(&chain_genesis.time);
         if let Some(vs) = &validator_signer {
-            info!(target: "client", "Starting validator node: {}", vs.validator_id());
+            if config.shadow_validation {
+                info!(target: "client", "Starting shadow validator node: {}", vs.validator_id());
+            } else {
+                info!(target: "client", "Starting validator node: {}", vs.validator_id());
+            }
         }
-        let info_helper = InfoHelper::new(telemetry_actor, &config, validator_signer.clone());
-        let client = Client::new(
+        let mut client = Client::new(
             config,
             chain_genesis,
             runtime_adapter,
@@ -163,6 +190,20 @@ impl ClientActor {
             enable_doomslug,
             rng_seed,
         )?;
+        let info_helper =
+            InfoHelper::new(telemetry_actor, &client.config, client.validator_signer.clone());
+        // The key might have been rotated while creating the client.
+        client.take_validator_signer_rotated();
+        let background_signer = client
+            .validator_signer
+            .as_ref()
+            .filter(|signer| signer.is_remote())
+            .map(|_| BackgroundSigner::new(ctx.address()));
+        let block_verifier = BlockVerifier::new(
+            ctx.address(),
+            client.runtime_adapter.clone(),
+            client.chain.genesis_block().clone(),
+        );
 
         let now = Utc::now();
         Ok(ClientActor {
@@ -180,15 +221,21 @@ impl ClientActor {
                 sent_bytes_per_sec: 0,
                 known_producers: vec![],
                 peer_counter: 0,
+                port_mapping: None,
             },
             last_validator_announce_time: None,
             info_helper,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
+            validator_events_next_attempt: now,
             block_production_started: false,
             doomslug_timer_next_attempt: now,
             chunk_request_retry_next_attempt: now,
             sync_started: false,
+            stopped_processing_blocks: false,
+            background_signer,
+            block_signing_height: None,
+            block_verifier,
             state_parts_task_scheduler: create_sync_job_scheduler::<ApplyStatePartsRequest>(
                 sync_jobs_actor_addr.clone(),
             ),
@@ -244,6 +291,8 @@ impl Actor for ClientActor {
 
         // Start catchup job.
         self.catchup(ctx);
+
+        spawn_mailbox_probes(ctx.address());
     }
 }
 
@@ -252,6 +301,9 @@ impl Handler<NetworkClientMessages> for ClientActor {
 
     #[perf_with_debug]
     fn handle(&mut self, msg: NetworkClientMessages, ctx: &mut Context<Self>) -> Self::Result {
+        if self.stopped_processing_blocks {
+            return NetworkClientResponses::NoResponse;
+        }
         self.check_triggers(ctx);
 
         let _d = delay_detector::DelayDetector::new(|| {
@@ -363,6 +415,14 @@ impl ClientActor {
                             NetworkClientResponses::AdvResult(store_validator.tests_done())
                         }
                     }
+                    near_network_primitives::types::NetworkAdversarialMessage::AdvSetStoreFaults(read_delay_ms, read_error_percent) => {
+                        info!(target: "adversary", "Delaying store reads by {} ms, failing {}% of them", read_delay_ms, read_error_percent);
+                        self.client.chain.store().store().faults().set(
+                            Duration::from_millis(read_delay_ms),
+                            read_error_percent,
+                        );
+                        NetworkClientResponses::NoResponse
+                    }
                     _ => panic!("invalid adversary message"),
                 };
             }
@@ -384,12 +444,29 @@ impl ClientActor {
                         self.fastforward_delta = Some(delta_height);
                         NetworkClientResponses::NoResponse
                     }
+                    near_network_primitives::types::NetworkSandboxMessage::SandboxSetTimestamp(timestamp) => {
+                        Clock::sandbox_set_utc(near_primitives::utils::from_timestamp(timestamp));
+                        NetworkClientResponses::NoResponse
+                    }
+                    near_network_primitives::types::NetworkSandboxMessage::SandboxSetGasPrice(gas_price) => {
+                        self.client.chain.block_economics_config.sandbox_set_gas_price_override(gas_price);
+                        NetworkClientResponses::NoResponse
+                    }
                 };
             }
             NetworkClientMessages::Transaction { transaction, is_forwarded, check_only } => {
                 self.client.process_tx(transaction, is_forwarded, check_only)
             }
             NetworkClientMessages::Block(block, peer_id, was_requested) => {
+                if self.client.config.header_only {
+                    // Only the header is kept; the chunks of the block are never requested.
+                    if self.receive_headers(vec![block.header().clone()], peer_id) {
+                        return NetworkClientResponses::NoResponse;
+                    }
+                    return NetworkClientResponses::Ban {
+                        ban_reason: ReasonForBan::BadBlockHeader,
+                    };
+                }
                 let blocks_at_height = self
                     .client
                     .chain
@@ -445,6 +522,15 @@ impl ClientActor {
                 self.client.collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id));
                 NetworkClientResponses::NoResponse
             }
+            NetworkClientMessages::BlockApprovals(approvals, peer_id) => {
+                debug!(target: "client", "Receive {} approvals from peer {:?}", approvals.len(), peer_id);
+                if self.client.collect_block_approvals(approvals, peer_id) {
+                    NetworkClientResponses::NoResponse
+                } else {
+                    warn!(target: "client", "Banning node for sending more approvals than there are block producers");
+                    NetworkClientResponses::Ban { ban_reason: ReasonForBan::Abusive }
+                }
+            }
             NetworkClientMessages::StateResponse(state_response_info) => {
                 let shard_id = state_response_info.shard_id();
                 let hash = state_response_info.sync_hash();
@@ -748,6 +834,7 @@ impl Handler<Status> for ClientActor {
                         gas_used: chunk.gas_used(),
                         processing_time_ms: CryptoHashTimer::get_timer_value(chunk.chunk_hash().0)
                             .map(|s| s.as_millis() as u64),
+                        state_witness_size: get_chunk_state_witness_size(&chunk.chunk_hash()),
                     })
                     .collect();
 
@@ -798,10 +885,92 @@ impl Handler<Status> for ClientActor {
             },
             validator_account_id,
             detailed_debug_status,
+            port_mapping: self.network_info.port_mapping.clone(),
         })
     }
 }
 
+impl Handler<GetValidatorEvents> for ClientActor {
+    type Result = Result<ValidatorEventsView, GetValidatorEventsError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetValidatorEvents, _ctx: &mut Context<Self>) -> Self::Result {
+        if self.client.validator_events.is_none() {
+            return Err(GetValidatorEventsError::Disabled);
+        }
+        self.check_validator_events();
+        match &self.client.validator_events {
+            Some(validator_events) => Ok(validator_events.view(msg.from_id)),
+            None => Err(GetValidatorEventsError::Disabled),
+        }
+    }
+}
+
+impl Handler<GetMissedProduction> for ClientActor {
+    type Result = MessageResult<GetMissedProduction>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetMissedProduction, _ctx: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.client.missed_production.misses_from(msg.from_id))
+    }
+}
+
+impl Handler<GetEpochSummary> for ClientActor {
+    type Result = Result<EpochSummaryView, GetEpochSummaryError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetEpochSummary, _ctx: &mut Context<Self>) -> Self::Result {
+        self.client
+            .epoch_summaries
+            .get(msg.epoch_height)
+            .cloned()
+            .ok_or(GetEpochSummaryError::Unavailable)
+    }
+}
+
+impl Handler<GetFeeEstimate> for ClientActor {
+    type Result = Result<FeeEstimateView, GetFeeEstimateError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetFeeEstimate, _ctx: &mut Context<Self>) -> Self::Result {
+        self.client.fee_history.estimate(msg.shard_id)
+    }
+}
+
+impl Handler<SubscribeContractLogs> for ClientActor {
+    type Result = Result<broadcast::Receiver<ContractLogView>, SubscribeContractLogsError>;
+
+    #[perf]
+    fn handle(&mut self, _msg: SubscribeContractLogs, _ctx: &mut Context<Self>) -> Self::Result {
+        match &self.client.contract_logs {
+            Some(contract_logs) => Ok(contract_logs.subscribe()),
+            None => Err(SubscribeContractLogsError::Disabled),
+        }
+    }
+}
+
+impl Handler<StopProcessingBlocks> for ClientActor {
+    type Result = Result<(), String>;
+
+    #[perf]
+    fn handle(&mut self, _msg: StopProcessingBlocks, _ctx: &mut Context<Self>) -> Self::Result {
+        info!(target: "client", "Stopped processing blocks, flushing the store");
+        self.stopped_processing_blocks = true;
+        if let Some(db) = self.client.chain.store().store().get_rocksdb() {
+            db.flush().map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Handler<MailboxProbe> for ClientActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: MailboxProbe, _ctx: &mut Context<Self>) {
+        msg.observe();
+    }
+}
+
 impl Handler<GetNetworkInfo> for ClientActor {
     type Result = Result<NetworkInfoResponse, String>;
 
@@ -812,7 +981,13 @@ impl Handler<GetNetworkInfo> for ClientActor {
 
         Ok(NetworkInfoResponse {
             connected_peers: (self.network_info.connected_peers.iter())
-                .map(|fpi| fpi.peer_info.clone())
+                .map(|fpi| ConnectedPeerInfo {
+                    peer_info: fpi.peer_info.clone(),
+                    height: fpi.chain_info.height,
+                    protocol_version: fpi.protocol_version,
+                    client_version: fpi.client_version.clone(),
+                    connection_age: fpi.connection_established_time.elapsed(),
+                })
                 .collect(),
             num_connected_peers: self.network_info.num_connected_peers,
             peer_max_count: self.network_info.peer_max_count,
@@ -936,7 +1111,18 @@ impl ClientActor {
                     if let Err(err) = self.produce_block(height) {
                         // If there is an error, report it and let it retry on the next loop step.
                         error!(target: "client", "Block production failed: {}", err);
+                        self.client.missed_production.record_failure(
+                            height,
+                            None,
+                            MissedProductionReason::Error,
+                        );
                     }
+                } else if !have_all_chunks {
+                    self.client.missed_production.record_failure(
+                        height,
+                        None,
+                        MissedProductionReason::MissingChunkParts,
+                    );
                 }
             }
         }
@@ -960,8 +1146,14 @@ impl ClientActor {
         let _d = delay_detector::DelayDetector::new(|| "client triggers".into());
 
         let mut delay = Duration::from_secs(1);
+        if self.stopped_processing_blocks {
+            return delay;
+        }
         let now = Utc::now();
 
+        if self.client.take_validator_signer_rotated() {
+            self.on_validator_signer_rotated(ctx);
+        }
         if self.sync_started {
             self.doomslug_timer_next_attempt = self.run_timer(
                 self.client.config.doosmslug_step_period,
@@ -998,6 +1190,24 @@ impl ClientActor {
             )
         }
 
+        if let Some(check_period) =
+            self.client.config.validator_events.as_ref().map(|config| config.check_period)
+        {
+            self.validator_events_next_attempt = self.run_timer(
+                check_period,
+                self.validator_events_next_attempt,
+                ctx,
+                |act, _ctx| act.check_validator_events(),
+            );
+            delay = core::cmp::min(
+                delay,
+                self.validator_events_next_attempt
+                    .signed_duration_since(now)
+                    .to_std()
+                    .unwrap_or(delay),
+            );
+        }
+
         self.log_summary_timer_next_attempt = self.run_timer(
             self.client.config.log_summary_period,
             self.log_summary_timer_next_attempt,
@@ -1031,6 +1241,12 @@ impl ClientActor {
         )
     }
 
+    fn check_validator_events(&mut self) {
+        if let Err(err) = self.client.check_validator_events() {
+            error!(target: "client", "Failed to check validator events: {}", err);
+        }
+    }
+
     fn try_handle_block_production(&mut self) {
         match self.handle_block_production() {
             Ok(()) => {}
@@ -1042,7 +1258,12 @@ impl ClientActor {
 
     fn try_doomslug_timer(&mut self, _: &mut Context<ClientActor>) {
         let _ = self.client.check_and_update_doomslug_tip();
-        let approvals = self.client.doomslug.process_timer(Clock::instant());
+        // A remote signer signs the approvals in the background, they are sent once signed.
+        let (approvals, approval_targets) = if self.background_signer.is_some() {
+            (vec![], self.client.doomslug.process_timer_targets(Clock::instant()))
+        } else {
+            (self.client.doomslug.process_timer(Clock::instant()), vec![])
+        };
 
         // Important to save the largest approval target height before sending approvals, so
         // that if the node crashes in the meantime, we cannot get slashed on recovery
@@ -1063,42 +1284,131 @@ impl ClientActor {
                             error!("Error while sending an approval {:?}", e);
                         }
                     }
+                    if let (Some(background_signer), Some(signer)) =
+                        (&self.background_signer, &self.client.validator_signer)
+                    {
+                        let (parent_hash, parent_height) = self.client.doomslug.get_tip();
+                        for target_height in approval_targets {
+                            background_signer.sign_approval(
+                                signer.clone(),
+                                parent_hash,
+                                parent_height,
+                                target_height,
+                            );
+                        }
+                    }
+                    self.client.flush_approvals();
                 }
             }
             Err(e) => error!("Error while committing largest skipped height {:?}", e),
         };
     }
 
+    /// Starts using the validator key the client has switched to for telemetry and, depending on
+    /// whether the new key is remote, for signing in the background.
+    fn on_validator_signer_rotated(&mut self, ctx: &mut Context<ClientActor>) {
+        let signer = self.client.validator_signer.clone();
+        let is_remote = signer.as_ref().map_or(false, |signer| signer.is_remote());
+        if is_remote && self.background_signer.is_none() {
+            self.background_signer = Some(BackgroundSigner::new(ctx.address()));
+        } else if !is_remote {
+            // Whatever has been handed to the background signer is still sent back once signed.
+            self.background_signer = None;
+        }
+        self.info_helper.set_validator_signer(signer);
+    }
+
     /// Produce block if we are block producer for given `next_height` height.
     /// Can return error, should be called with `produce_block` to handle errors and reschedule.
     fn produce_block(&mut self, next_height: BlockHeight) -> Result<(), Error> {
-        match self.client.produce_block(next_height) {
-            Ok(Some(block)) => {
-                let peer_id = self.node_id.clone();
-                // We’ve produced the block so that counts as validated block.
-                let block = MaybeValidated::from_validated(block);
-                let res = self.process_block(block, Provenance::PRODUCED, &peer_id);
-                match &res {
-                    Ok(_) => Ok(()),
-                    Err(e) => match e.kind() {
-                        near_chain::ErrorKind::ChunksMissing(_) => {
-                            // missing chunks were already handled in Client::process_block, we don't need to
-                            // do anything here
-                            Ok(())
-                        }
-                        _ => {
-                            error!(target: "client", "Failed to process freshly produced block: {:?}", res);
-                            byzantine_assert!(false);
-                            res.map_err(|err| err.into())
-                        }
-                    },
-                }
+        if let Some(background_signer) = &self.background_signer {
+            // The block is built once its VRF output is computed in the background, one at a time.
+            if self.block_signing_height.is_some() {
+                return Ok(());
             }
+            let signer = self.client.validator_signer.clone().ok_or_else(|| {
+                Error::BlockProducer("Called without block producer info.".to_string())
+            })?;
+            let head = self.client.chain.head()?;
+            let prev_random_value =
+                *self.client.chain.get_block_header(&head.last_block_hash)?.random_value();
+            self.block_signing_height = Some(next_height);
+            background_signer.compute_vrf(
+                signer,
+                next_height,
+                head.last_block_hash,
+                prev_random_value,
+            );
+            return Ok(());
+        }
+        match self.client.produce_block(next_height) {
+            Ok(Some(block)) => self.process_produced_block(block),
             Ok(None) => Ok(()),
             Err(err) => Err(err),
         }
     }
 
+    /// Builds the block at `next_height` with the VRF output computed by the background signer
+    /// and hands it back to sign the header.
+    fn produce_block_with_vrf(
+        &mut self,
+        next_height: BlockHeight,
+        prev_hash: CryptoHash,
+        vrf: Result<(Value, Proof), SignerError>,
+    ) -> Result<(), Error> {
+        self.block_signing_height = None;
+        let vrf = match vrf {
+            Ok(vrf) => vrf,
+            Err(err) => {
+                warn!(target: "client", "Skipping block production at height {}: {}", next_height, err);
+                self.client.missed_production.record_failure(
+                    next_height,
+                    None,
+                    MissedProductionReason::SignerError,
+                );
+                return Ok(());
+            }
+        };
+        let signer = match self.client.validator_signer.clone() {
+            Some(signer) => signer,
+            None => return Ok(()),
+        };
+        // The head moved on in the meantime, production is retried on top of the new one.
+        if self.client.chain.head()?.last_block_hash != prev_hash {
+            return Ok(());
+        }
+        let unsigned_signer = UnsignedBlockSigner { signer: signer.clone(), vrf };
+        if let Some(block) = self.client.produce_block_with_signer(next_height, &unsigned_signer)? {
+            if let Some(background_signer) = &self.background_signer {
+                self.block_signing_height = Some(next_height);
+                background_signer.sign_block(signer, block);
+            }
+        }
+        Ok(())
+    }
+
+    fn process_produced_block(&mut self, block: Block) -> Result<(), Error> {
+        let peer_id = self.node_id.clone();
+        // We’ve produced the block so that counts as validated block.
+        let block = MaybeValidated::from_validated(block);
+        let res = self.process_block(block, Provenance::PRODUCED, &peer_id);
+        match &res {
+            Ok(_) => Ok(()),
+            Err(e) => match e.kind() {
+                near_chain::ErrorKind::ChunksMissing(_) => {
+                    // missing chunks were already handled in Client::process_block, we don't need to
+                    // do anything here
+                    Ok(())
+                }
+                _ => {
+                    error!(target: "client", "Failed to process freshly produced block: {:?}", res);
+                    byzantine_assert!(false);
+                    res.map_err(|err| err.into())
+                }
+            },
+        }
+    }
+
     /// Process all blocks that were accepted by calling other relevant services.
     fn process_accepted_blocks(&mut self, accepted_blocks: Vec<AcceptedBlock>) {
         for accepted_block in accepted_blocks {
@@ -1116,13 +1426,21 @@ impl ClientActor {
             let chunks = block.chunks();
             for (chunk, &included) in chunks.iter().zip(block.header().chunk_mask().iter()) {
                 if included {
-                    self.info_helper.chunk_processed(chunk.shard_id(), chunk.gas_used());
+                    self.info_helper.chunk_processed(
+                        chunk.shard_id(),
+                        chunk.gas_used(),
+                        chunk.gas_limit(),
+                    );
                 } else {
                     self.info_helper.chunk_skipped(chunk.shard_id());
                 }
             }
 
-            self.info_helper.block_processed(gas_used, chunks_in_block as u64);
+            self.info_helper.block_processed(
+                gas_used,
+                chunks_in_block as u64,
+                block.header().gas_price(),
+            );
             self.check_send_announce_account(last_final_hash);
         }
     }
@@ -1186,6 +1504,14 @@ impl ClientActor {
     /// Processes received block. Ban peer if the block header is invalid or the block is ill-formed.
     fn receive_block(&mut self, block: Block, peer_id: PeerId, was_requested: bool) {
         let hash = *block.hash();
+        let _span = tracing::info_span!(
+            target: "client",
+            "receive_block",
+            block_hash = %hash,
+            height = block.header().height(),
+            %peer_id
+        )
+        .entered();
         debug!(target: "client", "{:?} Received block {} <- {} at {} from {}, requested: {}", self.client.validator_signer.as_ref().map(|vs| vs.validator_id()), hash, block.header().prev_hash(), block.header().height(), peer_id, was_requested);
         let head = unwrap_or_return!(self.client.chain.head());
         let is_syncing = self.client.sync_status.is_syncing();
@@ -1198,10 +1524,21 @@ impl ClientActor {
             debug!(target: "client", "dropping block {} that is too far behind. Block height {} current tail height {}", block.hash(), block.header().height(), tail);
             return;
         }
+        self.block_verifier.verify(block, peer_id, was_requested);
+    }
+
+    /// Processes a received block once [`BlockVerifier`] has verified it.
+    fn process_received_block(
+        &mut self,
+        block: MaybeValidated<Block>,
+        peer_id: PeerId,
+        was_requested: bool,
+    ) {
+        let hash = *block.hash();
         let prev_hash = *block.header().prev_hash();
         let provenance =
             if was_requested { near_chain::Provenance::SYNC } else { near_chain::Provenance::NONE };
-        match self.process_block(block.into(), provenance, &peer_id) {
+        match self.process_block(block, provenance, &peer_id) {
             Ok(_) => {}
             Err(ref err) if err.is_bad_data() => {
                 warn!(target: "client", "receive bad block: {}", err);
@@ -1426,6 +1763,9 @@ impl ClientActor {
     /// Runs itself iff it was not ran as reaction for message with results of
     /// finishing state part job
     fn sync(&mut self, ctx: &mut Context<ClientActor>) {
+        if self.stopped_processing_blocks {
+            return;
+        }
         let _d = delay_detector::DelayDetector::new(|| "client sync".into());
         // Macro to schedule to call this function later if error occurred.
         macro_rules! unwrap_or_run_later (($obj: expr) => (match $obj {
@@ -1476,6 +1816,8 @@ impl ClientActor {
 
             // Sync state if already running sync state or if block sync is too far.
             let sync_state = match self.client.sync_status {
+                // Header-only nodes are done once the headers are synced.
+                _ if self.client.config.header_only => false,
                 SyncStatus::StateSync(_, _) => true,
                 _ if header_head.height
                     >= highest_height
@@ -1622,12 +1964,22 @@ impl ClientActor {
         };
 
         let epoch_identifier = ValidatorInfoIdentifier::BlockHash(head.last_block_hash);
-        let validator_epoch_stats = self
-            .client
-            .runtime_adapter
-            .get_validator_info(epoch_identifier)
-            .map(get_validator_epoch_stats)
-            .unwrap_or_default();
+        let epoch_height =
+            self.client.runtime_adapter.get_epoch_height_from_prev_block(&head.prev_block_hash);
+        let validator_epoch_stats =
+            match self.client.runtime_adapter.get_validator_info(epoch_identifier) {
+                Ok(info) => {
+                    if let Ok(epoch_height) = epoch_height {
+                        self.info_helper.epoch_kickouts(epoch_height, &info.prev_epoch_kickout);
+                    }
+                    get_validator_epoch_stats(info, &self.client.approval_participation)
+                }
+                Err(_) => vec![],
+            };
+        let validator_uptime =
+            self.validator_uptime(epoch_height.as_ref().ok().copied(), &validator_epoch_stats);
+        self.client.save_validator_stats();
+        let delayed_receipts = self.delayed_receipts_counts(&head);
         self.info_helper.info(
             self.client.chain.store().get_genesis_height(),
             &head,
@@ -1636,18 +1988,71 @@ impl ClientActor {
             &self.network_info,
             validator_info,
             validator_epoch_stats,
-            self.client
-                .runtime_adapter
-                .get_epoch_height_from_prev_block(&head.prev_block_hash)
-                .unwrap_or(0),
+            validator_uptime,
+            epoch_height.unwrap_or(0),
             self.client
                 .runtime_adapter
                 .get_protocol_upgrade_block_height(head.last_block_hash)
                 .unwrap_or(None)
                 .unwrap_or(0),
+            self.client.runtime_adapter.get_protocol_version_votes(&head.last_block_hash).ok(),
             self.client.chain.store().get_store_statistics(),
+            delayed_receipts,
         );
     }
+
+    /// Uptime of the validator run by the node since the tracking start, including the current
+    /// epoch, and the height of the first epoch counted.
+    fn validator_uptime(
+        &self,
+        epoch_height: Option<EpochHeight>,
+        validator_epoch_stats: &[ValidatorProductionStats],
+    ) -> Option<ValidatorUptime> {
+        let account_id = self.client.validator_signer.as_ref()?.validator_id();
+        let current = validator_epoch_stats
+            .iter()
+            .find(|stats| &stats.account_id == account_id)
+            .map(|stats| Production {
+                num_produced_blocks: stats.num_produced_blocks,
+                num_expected_blocks: stats.num_expected_blocks,
+                num_produced_chunks: stats.num_produced_chunks,
+                num_expected_chunks: stats.num_expected_chunks,
+            })
+            .unwrap_or_default();
+        let (since_epoch_height, production) =
+            self.client.validator_stats.cumulative(epoch_height?, &current);
+        Some(ValidatorUptime {
+            account_id: account_id.clone(),
+            since_epoch_height,
+            uptime: production.uptime(),
+        })
+    }
+
+    /// Number of delayed receipts of each shard tracked by the node as of the head.
+    fn delayed_receipts_counts(&mut self, head: &Tip) -> BTreeMap<ShardId, u64> {
+        let mut counts = BTreeMap::new();
+        let num_shards =
+            unwrap_or_return!(self.client.runtime_adapter.num_shards(&head.epoch_id), counts);
+        for shard_id in 0..num_shards {
+            let shard_uid =
+                match self.client.runtime_adapter.shard_id_to_uid(shard_id, &head.epoch_id) {
+                    Ok(shard_uid) => shard_uid,
+                    Err(_) => continue,
+                };
+            // Shards not tracked have no chunk extra.
+            let state_root =
+                match self.client.chain.get_chunk_extra(&head.last_block_hash, &shard_uid) {
+                    Ok(chunk_extra) => *chunk_extra.state_root(),
+                    Err(_) => continue,
+                };
+            if let Ok((indices, _)) =
+                self.client.runtime_adapter.get_delayed_receipts(shard_uid, &state_root, None, 0)
+            {
+                counts.insert(shard_id, indices.next_available_index - indices.first_index);
+            }
+        }
+        counts
+    }
 }
 
 impl Drop for ClientActor {
@@ -1705,6 +2110,66 @@ impl Handler<ApplyStatePartsRequest> for SyncJobsActor {
     }
 }
 
+impl Handler<VerifiedBlock> for ClientActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: VerifiedBlock, ctx: &mut Self::Context) -> Self::Result {
+        if self.stopped_processing_blocks {
+            return;
+        }
+        self.check_triggers(ctx);
+        self.process_received_block(msg.block, msg.peer_id, msg.was_requested);
+    }
+}
+
+impl Handler<BackgroundSigned> for ClientActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: BackgroundSigned, _: &mut Self::Context) -> Self::Result {
+        match msg {
+            BackgroundSigned::Approval { parent_hash, approval } => match approval {
+                Ok(approval) => {
+                    if let Err(err) = self.client.send_approval(&parent_hash, approval) {
+                        error!("Error while sending an approval {:?}", err);
+                    }
+                    self.client.flush_approvals();
+                }
+                Err(err) => warn!(target: "client", "Not sending an approval: {}", err),
+            },
+            BackgroundSigned::Vrf { next_height, prev_hash, vrf } => {
+                if let Err(err) = self.produce_block_with_vrf(next_height, prev_hash, vrf) {
+                    error!(target: "client", "Block production failed: {}", err);
+                    self.client.missed_production.record_failure(
+                        next_height,
+                        None,
+                        MissedProductionReason::Error,
+                    );
+                }
+            }
+            BackgroundSigned::Block { mut block, signature } => {
+                self.block_signing_height = None;
+                let height = block.header().height();
+                match signature {
+                    Ok(signature) => {
+                        block.set_signature(signature);
+                        if let Err(err) = self.process_produced_block(block) {
+                            error!(target: "client", "Block production failed: {}", err);
+                        }
+                    }
+                    Err(err) => {
+                        warn!(target: "client", "Skipping block production at height {}: {}", height, err);
+                        self.client.missed_production.record_failure(
+                            height,
+                            None,
+                            MissedProductionReason::SignerError,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl Handler<ApplyStatePartsResponse> for ClientActor {
     type Result = ();
 
@@ -1760,6 +2225,7 @@ impl Handler<StateSplitRequest> for SyncJobsActor {
             msg.shard_uid,
             &msg.state_root,
             &msg.next_epoch_shard_layout,
+            &msg.progress,
         );
 
         self.client_addr.do_send(StateSplitResponse {
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::mpsc;
+
+use near_chain::{ChainStore, ChainStoreAccess, Error};
+use near_chain_configs::ContractLogsConfig;
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::views::ContractLogView;
+use near_store::Store;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::metrics;
+
+/// How many logs a subscriber may fall behind before it's disconnected.
+const CONTRACT_LOGS_CHANNEL_CAPACITY: usize = 4096;
+
+/// Streams the log lines of contract executions matching the configured prefixes and accounts to
+/// subscribers, and optionally to a file, as the blocks including the executions become the head.
+/// The outcomes are read and the logs written on a thread of its own, off the block processing
+/// path of the client.
+pub(crate) struct ContractLogs {
+    sender: broadcast::Sender<ContractLogView>,
+    blocks: mpsc::Sender<CryptoHash>,
+}
+
+impl ContractLogs {
+    pub fn new(config: ContractLogsConfig, store: Store, genesis_height: BlockHeight) -> Self {
+        let (sender, _) = broadcast::channel(CONTRACT_LOGS_CHANNEL_CAPACITY);
+        let (blocks, blocks_rx) = mpsc::channel();
+        let mut recorder = ContractLogsRecorder::new(config, sender.clone());
+        let mut chain_store = ChainStore::new(store, genesis_height);
+        std::thread::Builder::new()
+            .name("contract_logs".to_string())
+            .spawn(move || {
+                // Stops once the client is dropped.
+                for block_hash in blocks_rx {
+                    if let Err(err) = recorder.record_block_from_store(&mut chain_store, block_hash)
+                    {
+                        warn!(target: "client", "Failed to record contract logs of block {}: {}", block_hash, err);
+                    }
+                }
+            })
+            .expect("Failed to start the contract logs thread");
+        Self { sender, blocks }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ContractLogView> {
+        self.sender.subscribe()
+    }
+
+    /// Streams the matching logs of the executions included in the given block in the background.
+    pub fn record_block(&self, block_hash: CryptoHash) {
+        // The thread only stops when this is dropped.
+        let _ = self.blocks.send(block_hash);
+    }
+}
+
+struct ContractLogsRecorder {
+    config: ContractLogsConfig,
+    sender: broadcast::Sender<ContractLogView>,
+    /// Opened lazily so that a misconfigured path doesn't prevent the node from starting.
+    file: Option<BufWriter<File>>,
+}
+
+impl ContractLogsRecorder {
+    fn new(config: ContractLogsConfig, sender: broadcast::Sender<ContractLogView>) -> Self {
+        Self { config, sender, file: None }
+    }
+
+    fn record_block_from_store(
+        &mut self,
+        chain_store: &mut ChainStore,
+        block_hash: CryptoHash,
+    ) -> Result<(), Error> {
+        let block = chain_store.get_block(&block_hash)?;
+        let block_height = block.header().height();
+        let shard_ids: Vec<_> = block.chunks().iter().map(|chunk| chunk.shard_id()).collect();
+        let mut outcomes = HashMap::new();
+        for shard_id in shard_ids {
+            let mut shard_outcomes = vec![];
+            for id in chain_store.get_outcomes_by_block_hash_and_shard_id(&block_hash, shard_id)? {
+                let mut outcomes_of_id = chain_store.get_outcomes_by_id(&id)?;
+                outcomes_of_id.retain(|outcome| outcome.block_hash == block_hash);
+                shard_outcomes.extend(outcomes_of_id);
+            }
+            outcomes.insert(shard_id, shard_outcomes);
+        }
+        self.record_block(block_hash, block_height, &outcomes);
+        Ok(())
+    }
+
+    /// Streams the matching logs among the outcomes of the given block, per shard.
+    fn record_block(
+        &mut self,
+        block_hash: CryptoHash,
+        block_height: BlockHeight,
+        outcomes: &HashMap<ShardId, Vec<ExecutionOutcomeWithIdAndProof>>,
+    ) {
+        let mut shard_ids: Vec<_> = outcomes.keys().copied().collect();
+        shard_ids.sort();
+        for shard_id in shard_ids {
+            for outcome in &outcomes[&shard_id] {
+                let outcome_with_id = &outcome.outcome_with_id;
+                if !self.config.account_ids.is_empty()
+                    && !self.config.account_ids.contains(&outcome_with_id.outcome.executor_id)
+                {
+                    continue;
+                }
+                for log in &outcome_with_id.outcome.logs {
+                    if !self.config.prefixes.iter().any(|prefix| log.starts_with(prefix)) {
+                        continue;
+                    }
+                    self.record(ContractLogView {
+                        block_height,
+                        block_hash,
+                        shard_id,
+                        id: outcome_with_id.id,
+                        executor_id: outcome_with_id.outcome.executor_id.clone(),
+                        log: log.clone(),
+                    });
+                }
+            }
+        }
+        if let (Some(file), Some(path)) = (&mut self.file, &self.config.output_file) {
+            if let Err(err) = file.flush() {
+                warn!(target: "client", "Failed to write contract logs to {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    fn record(&mut self, log: ContractLogView) {
+        metrics::CONTRACT_LOGS_TOTAL.inc();
+        if let Some(path) = &self.config.output_file {
+            if self.file.is_none() {
+                match OpenOptions::new().create(true).append(true).open(path) {
+                    Ok(file) => self.file = Some(BufWriter::new(file)),
+                    Err(err) => {
+                        warn!(target: "client", "Failed to open {} for contract logs: {}", path.display(), err)
+                    }
+                }
+            }
+            if let Some(file) = &mut self.file {
+                if let Err(err) = writeln!(file, "{}", serde_json::to_string(&log).unwrap()) {
+                    warn!(target: "client", "Failed to write contract log to {}: {}", path.display(), err);
+                }
+            }
+        }
+        // There being no subscribers is not an error.
+        let _ = self.sender.send(log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use near_chain_configs::ContractLogsConfig;
+    use near_primitives::hash::hash;
+    use near_primitives::transaction::{
+        ExecutionOutcome, ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof,
+    };
+
+    use super::ContractLogsRecorder;
+
+    fn outcome(id: &[u8], executor_id: &str, logs: &[&str]) -> ExecutionOutcomeWithIdAndProof {
+        ExecutionOutcomeWithIdAndProof {
+            proof: vec![],
+            block_hash: hash(b"block"),
+            outcome_with_id: ExecutionOutcomeWithId {
+                id: hash(id),
+                outcome: ExecutionOutcome {
+                    logs: logs.iter().map(|log| log.to_string()).collect(),
+                    executor_id: executor_id.parse().unwrap(),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_contract_logs() {
+        let output_file = tempfile::NamedTempFile::new().unwrap();
+        let (sender, mut receiver) = tokio::sync::broadcast::channel(16);
+        let mut contract_logs = ContractLogsRecorder::new(
+            ContractLogsConfig {
+                prefixes: vec!["EVENT_JSON:".to_string()],
+                account_ids: vec!["token.near".parse().unwrap()],
+                output_file: Some(output_file.path().to_path_buf()),
+            },
+            sender,
+        );
+        let outcomes = HashMap::from([
+            (
+                0,
+                vec![
+                    outcome(b"r1", "token.near", &["EVENT_JSON:{}", "Transfer 1 to alice.near"]),
+                    outcome(b"r2", "other.near", &["EVENT_JSON:{}"]),
+                ],
+            ),
+            (1, vec![outcome(b"r3", "token.near", &["EVENT_JSON:{\"event\":\"ft_mint\"}"])]),
+        ]);
+        contract_logs.record_block(hash(b"block"), 10, &outcomes);
+
+        let first = receiver.try_recv().unwrap();
+        assert_eq!(
+            (first.shard_id, first.id, first.log.as_str()),
+            (0, hash(b"r1"), "EVENT_JSON:{}")
+        );
+        let second = receiver.try_recv().unwrap();
+        assert_eq!(
+            (second.shard_id, second.id, second.log.as_str()),
+            (1, hash(b"r3"), "EVENT_JSON:{\"event\":\"ft_mint\"}")
+        );
+        assert!(receiver.try_recv().is_err());
+
+        let written = std::fs::read_to_string(output_file.path()).unwrap();
+        assert_eq!(written.lines().count(), 2);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(written.lines().next().unwrap()).unwrap()
+                ["log"],
+            "EVENT_JSON:{}"
+        );
+    }
+}
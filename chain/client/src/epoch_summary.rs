@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+
+use near_chain::types::ValidatorInfoIdentifier;
+use near_chain::{Error, RuntimeAdapter};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::epoch_manager::epoch_info::EpochInfo;
+use near_primitives::types::{AccountId, EpochHeight, NumBlocks, ShardId};
+use near_primitives::views::{
+    CurrentEpochValidatorInfo, EpochSummarySeatView, EpochSummaryValidatorView, EpochSummaryView,
+    StakeChangeView,
+};
+use tracing::info;
+
+/// How many of the latest summaries are kept for `EXPERIMENTAL_epoch_summary`.
+const MAX_EPOCH_SUMMARIES: usize = 16;
+
+/// Summaries of the latest epochs, taken as the next epochs start.  The summaries of the epochs
+/// which ended before the node started aren't available.
+#[derive(Default)]
+pub(crate) struct EpochSummaries {
+    summaries: VecDeque<EpochSummaryView>,
+}
+
+impl EpochSummaries {
+    pub fn add(&mut self, summary: EpochSummaryView) {
+        let votes: Vec<_> = summary
+            .protocol_version_votes
+            .iter()
+            .map(|votes| format!("{}: {}", votes.protocol_version, votes.stake))
+            .collect();
+        match &summary.validator {
+            Some(validator) => info!(
+                target: "client",
+                "Epoch {} ended at {}: {} produced {}/{} blocks and {}/{} chunks, uptime {:.2}%, reward {}, stake {}, next seat {:?}, protocol version votes [{}], {} stake changes",
+                summary.epoch_height, summary.last_block_height, validator.account_id,
+                validator.num_produced_blocks, validator.num_expected_blocks,
+                validator.num_produced_chunks, validator.num_expected_chunks,
+                validator.uptime * 100.0, validator.reward, validator.stake, validator.next_seat,
+                votes.join(", "), summary.stake_changes.len()
+            ),
+            None => info!(
+                target: "client",
+                "Epoch {} ended at {}: protocol version votes [{}], {} stake changes",
+                summary.epoch_height, summary.last_block_height, votes.join(", "),
+                summary.stake_changes.len()
+            ),
+        }
+        if self.summaries.len() == MAX_EPOCH_SUMMARIES {
+            self.summaries.pop_front();
+        }
+        self.summaries.push_back(summary);
+    }
+
+    /// Summary of the epoch with the given height, or of the latest epoch.
+    pub fn get(&self, epoch_height: Option<EpochHeight>) -> Option<&EpochSummaryView> {
+        match epoch_height {
+            Some(epoch_height) => {
+                self.summaries.iter().find(|summary| summary.epoch_height == epoch_height)
+            }
+            None => self.summaries.back(),
+        }
+    }
+}
+
+/// Summarizes the epoch which ended with the given block, for the validator with the given
+/// account if the node runs one.
+pub(crate) fn summarize_epoch(
+    runtime_adapter: &dyn RuntimeAdapter,
+    account_id: Option<&AccountId>,
+    last_block_header: &BlockHeader,
+) -> Result<EpochSummaryView, Error> {
+    let epoch_id = last_block_header.epoch_id().clone();
+    let validator_info =
+        runtime_adapter.get_validator_info(ValidatorInfoIdentifier::EpochId(epoch_id.clone()))?;
+    let votes = runtime_adapter.get_protocol_version_votes(last_block_header.hash())?;
+    // The end of the epoch decides the epoch after next, whose info holds the outcome.
+    let epoch_info_after_next =
+        runtime_adapter.get_epoch_info_after_next(last_block_header.hash())?;
+    let validator = account_id.map(|account_id| {
+        summarize_validator(
+            account_id,
+            validator_info.current_validators.iter().find(|v| &v.account_id == account_id),
+            &epoch_info_after_next,
+        )
+    });
+    Ok(EpochSummaryView {
+        epoch_id: epoch_id.clone(),
+        epoch_height: validator_info.epoch_height,
+        protocol_version: runtime_adapter.get_epoch_protocol_version(&epoch_id)?,
+        start_height: validator_info.epoch_start_height,
+        last_block_height: last_block_header.height(),
+        validator,
+        protocol_version_votes: votes.versions,
+        protocol_upgrade: votes.upgrade,
+        stake_changes: stake_changes(&validator_info.current_validators, &epoch_info_after_next),
+    })
+}
+
+/// `stats` are those of the validator in the ended epoch, if it had a seat, and
+/// `epoch_info_after_next` the info decided at its end.
+fn summarize_validator(
+    account_id: &AccountId,
+    stats: Option<&CurrentEpochValidatorInfo>,
+    epoch_info_after_next: &EpochInfo,
+) -> EpochSummaryValidatorView {
+    let next_seat = match epoch_info_after_next.get_validator_id(account_id) {
+        Some(validator_id) => EpochSummarySeatView::Seated {
+            stake: epoch_info_after_next.validator_stake(*validator_id),
+            shards: (epoch_info_after_next.chunk_producers_settlement().iter().enumerate())
+                .filter(|(_, producers)| producers.contains(validator_id))
+                .map(|(shard_id, _)| shard_id as ShardId)
+                .collect(),
+        },
+        None => match epoch_info_after_next.validator_kickout().get(account_id) {
+            Some(reason) => EpochSummarySeatView::KickedOut { reason: reason.clone() },
+            None => EpochSummarySeatView::NotSeated,
+        },
+    };
+    let (produced_blocks, expected_blocks) =
+        stats.map_or((0, 0), |stats| (stats.num_produced_blocks, stats.num_expected_blocks));
+    let (produced_chunks, expected_chunks) =
+        stats.map_or((0, 0), |stats| (stats.num_produced_chunks, stats.num_expected_chunks));
+    EpochSummaryValidatorView {
+        account_id: account_id.clone(),
+        stake: stats.map_or(0, |stats| stats.stake),
+        num_produced_blocks: produced_blocks,
+        num_expected_blocks: expected_blocks,
+        num_produced_chunks: produced_chunks,
+        num_expected_chunks: expected_chunks,
+        uptime: uptime(&[(produced_blocks, expected_blocks), (produced_chunks, expected_chunks)]),
+        reward: epoch_info_after_next.validator_reward().get(account_id).copied().unwrap_or(0),
+        next_seat,
+    }
+}
+
+/// Accounts whose stake locked for the epoch after next differs from their stake in the ended
+/// epoch, ordered by account.
+fn stake_changes(
+    current_validators: &[CurrentEpochValidatorInfo],
+    epoch_info_after_next: &EpochInfo,
+) -> Vec<StakeChangeView> {
+    epoch_info_after_next
+        .stake_change()
+        .iter()
+        .filter_map(|(account_id, new_stake)| {
+            let stake = current_validators
+                .iter()
+                .find(|v| &v.account_id == account_id)
+                .map_or(0, |v| v.stake);
+            (stake != *new_stake).then(|| StakeChangeView {
+                account_id: account_id.clone(),
+                stake,
+                new_stake: *new_stake,
+            })
+        })
+        .collect()
+}
+
+/// Average of the produced shares of the given kinds, skipping those with nothing expected.
+pub(crate) fn uptime(stats: &[(NumBlocks, NumBlocks)]) -> f64 {
+    let shares: Vec<f64> = stats
+        .iter()
+        .filter(|(_, expected)| *expected > 0)
+        .map(|(produced, expected)| *produced as f64 / *expected as f64)
+        .collect();
+    if shares.is_empty() {
+        return 1.0;
+    }
+    shares.iter().sum::<f64>() / shares.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::epoch_manager::epoch_info::{EpochInfo, EpochInfoV2};
+    use near_primitives::types::validator_stake::ValidatorStake;
+    use near_primitives::types::{EpochId, ValidatorKickoutReason};
+    use near_primitives::views::{
+        CurrentEpochValidatorInfo, EpochSummarySeatView, EpochSummaryView, StakeChangeView,
+    };
+
+    use super::{stake_changes, summarize_validator, EpochSummaries, MAX_EPOCH_SUMMARIES};
+
+    #[test]
+    fn test_epoch_summary() {
+        let account_id = "test0".parse().unwrap();
+        let public_key = near_crypto::PublicKey::empty(near_crypto::KeyType::ED25519);
+        let current_validators = vec![CurrentEpochValidatorInfo {
+            account_id: "test0".parse().unwrap(),
+            public_key: public_key.clone(),
+            is_slashed: false,
+            stake: 100,
+            shards: vec![0],
+            num_produced_blocks: 9,
+            num_expected_blocks: 10,
+            num_produced_chunks: 7,
+            num_expected_chunks: 10,
+        }];
+        let kickout = ValidatorKickoutReason::NotEnoughChunks { produced: 7, expected: 10 };
+        let epoch_info_after_next = EpochInfo::V2(EpochInfoV2 {
+            stake_change: vec![("test0".parse().unwrap(), 0), ("test1".parse().unwrap(), 50)]
+                .into_iter()
+                .collect(),
+            validator_reward: vec![("test0".parse().unwrap(), 5)].into_iter().collect(),
+            validator_kickout: vec![("test0".parse().unwrap(), kickout.clone())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        });
+        let summary =
+            summarize_validator(&account_id, current_validators.first(), &epoch_info_after_next);
+        assert_eq!((summary.stake, summary.reward), (100, 5));
+        assert!((summary.uptime - 0.8).abs() < 1e-9);
+        assert_eq!(summary.next_seat, EpochSummarySeatView::KickedOut { reason: kickout });
+        assert_eq!(
+            stake_changes(&current_validators, &epoch_info_after_next),
+            vec![
+                StakeChangeView { account_id: "test0".parse().unwrap(), stake: 100, new_stake: 0 },
+                StakeChangeView { account_id: "test1".parse().unwrap(), stake: 0, new_stake: 50 },
+            ]
+        );
+
+        let epoch_info_after_next = EpochInfo::V2(EpochInfoV2 {
+            validators: vec![ValidatorStake::new_v1(account_id.clone(), public_key, 105)],
+            validator_to_index: vec![(account_id.clone(), 0)].into_iter().collect(),
+            chunk_producers_settlement: vec![vec![], vec![0]],
+            stake_change: vec![("test0".parse().unwrap(), 105)].into_iter().collect(),
+            ..Default::default()
+        });
+        let summary =
+            summarize_validator(&account_id, current_validators.first(), &epoch_info_after_next);
+        assert_eq!(summary.next_seat, EpochSummarySeatView::Seated { stake: 105, shards: vec![1] });
+        let summary = summarize_validator(&account_id, None, &EpochInfo::default());
+        assert_eq!((summary.stake, summary.uptime, summary.reward), (0, 1.0, 0));
+        assert_eq!(summary.next_seat, EpochSummarySeatView::NotSeated);
+
+        let mut summaries = EpochSummaries::default();
+        for epoch_height in 0..MAX_EPOCH_SUMMARIES as u64 + 2 {
+            summaries.add(EpochSummaryView {
+                epoch_id: EpochId::default(),
+                epoch_height,
+                protocol_version: 1,
+                start_height: epoch_height * 10,
+                last_block_height: epoch_height * 10 + 9,
+                validator: None,
+                protocol_version_votes: vec![],
+                protocol_upgrade: None,
+                stake_changes: vec![],
+            });
+        }
+        assert!(summaries.get(Some(1)).is_none());
+        assert_eq!(summaries.get(Some(2)).unwrap().start_height, 20);
+        assert_eq!(summaries.get(None).unwrap().epoch_height, MAX_EPOCH_SUMMARIES as u64 + 1);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use near_chain::{Chain, ChainGenesis, ErrorKind};
+use near_client_primitives::types::GetFeeEstimateError;
+use near_primitives::block::Block;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{Balance, BlockHeight, BlockHeightDelta, Gas, ShardId};
+use near_primitives::views::{FeeEstimateView, FeePercentileView};
+use num_rational::Rational;
+
+/// How many of the latest blocks the estimates are based on.
+const MAX_BLOCKS: usize = 200;
+/// Number of blocks the gas price multipliers cover the growth of the gas price for, i.e. the
+/// delay before a transaction is included which the multipliers allow for.
+const HORIZON: BlockHeightDelta = 10;
+const PERCENTILES: [u8; 3] = [50, 90, 99];
+
+struct BlockFees {
+    height: BlockHeight,
+    hash: CryptoHash,
+    gas_price: Balance,
+    gas_used: Gas,
+    gas_limit: Gas,
+    /// Gas used and gas limit of the chunk of each shard, `None` if the chunk is missing.
+    chunks: Vec<Option<(Gas, Gas)>>,
+}
+
+/// Sliding window of the gas price and the fullness of the latest blocks on the canonical chain,
+/// from which the fees of transactions are estimated.
+pub(crate) struct FeeHistory {
+    blocks: VecDeque<BlockFees>,
+    gas_price_adjustment_rate: Rational,
+    min_gas_price: Balance,
+    max_gas_price: Balance,
+}
+
+impl FeeHistory {
+    pub fn new(chain_genesis: &ChainGenesis) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            gas_price_adjustment_rate: chain_genesis.gas_price_adjustment_rate,
+            min_gas_price: chain_genesis.min_gas_price,
+            max_gas_price: chain_genesis.max_gas_price,
+        }
+    }
+
+    /// Fills the window with the blocks up to the head, so that estimates are available right
+    /// after a restart.  Stops at the first block which isn't stored, e.g. one before the state
+    /// the node synced to.
+    pub fn load(&mut self, chain: &mut Chain) -> Result<(), near_chain::Error> {
+        let genesis_height = chain.genesis().height();
+        let mut block_hash = chain.head()?.last_block_hash;
+        let mut blocks = vec![];
+        while blocks.len() < MAX_BLOCKS {
+            let block = match chain.get_block(&block_hash) {
+                Ok(block) => block.clone(),
+                Err(err) if matches!(err.kind(), ErrorKind::DBNotFoundErr(_)) => break,
+                Err(err) => return Err(err),
+            };
+            if block.header().height() == genesis_height {
+                break;
+            }
+            block_hash = *block.header().prev_hash();
+            blocks.push(block);
+        }
+        for block in blocks.iter().rev() {
+            self.record_block(block);
+        }
+        Ok(())
+    }
+
+    /// Records a block which became the head.  The blocks which aren't its ancestors, i.e. were
+    /// on the chain before a reorg, are dropped.
+    pub fn record_block(&mut self, block: &Block) {
+        let height = block.header().height();
+        let prev_hash = block.header().prev_hash();
+        while self.blocks.back().map_or(false, |fees| &fees.hash != prev_hash) {
+            self.blocks.pop_back();
+        }
+        let chunks = block
+            .chunks()
+            .iter()
+            .map(|chunk| {
+                if chunk.height_included() == height {
+                    Some((chunk.gas_used(), chunk.gas_limit()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        self.blocks.push_back(BlockFees {
+            height,
+            hash: *block.hash(),
+            gas_price: block.header().gas_price(),
+            gas_used: Block::compute_gas_used(block.chunks().iter(), height),
+            gas_limit: Block::compute_gas_limit(block.chunks().iter(), height),
+            chunks,
+        });
+        if self.blocks.len() > MAX_BLOCKS {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Estimates the fees for the transactions of the shard.
+    pub fn estimate(&self, shard_id: ShardId) -> Result<FeeEstimateView, GetFeeEstimateError> {
+        let latest = self.blocks.back().ok_or(GetFeeEstimateError::NoSyncedBlocks)?;
+        if shard_id as usize >= latest.chunks.len() {
+            return Err(GetFeeEstimateError::InvalidShardId { shard_id });
+        }
+        let mut block_fullness: Vec<f64> =
+            self.blocks.iter().map(|fees| fullness(fees.gas_used, fees.gas_limit)).collect();
+        let mut chunk_fullness: Vec<f64> = self
+            .blocks
+            .iter()
+            .filter_map(|fees| fees.chunks.get(shard_id as usize).copied().flatten())
+            .map(|(gas_used, gas_limit)| fullness(gas_used, gas_limit))
+            .collect();
+        block_fullness.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        chunk_fullness.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentiles = PERCENTILES
+            .iter()
+            .map(|&percentile| {
+                let block_fullness = nearest_rank(&block_fullness, percentile);
+                FeePercentileView {
+                    percentile,
+                    block_fullness,
+                    chunk_fullness: nearest_rank(&chunk_fullness, percentile),
+                    gas_price_multiplier: self
+                        .gas_price_multiplier(latest.gas_price, block_fullness),
+                }
+            })
+            .collect();
+        Ok(FeeEstimateView {
+            block_height: latest.height,
+            block_hash: latest.hash,
+            gas_price: latest.gas_price,
+            shard_id,
+            num_blocks: self.blocks.len() as u64,
+            horizon: HORIZON,
+            percentiles,
+        })
+    }
+
+    /// Ratio of the gas price after `HORIZON` blocks as full as given to the current one.
+    fn gas_price_multiplier(&self, gas_price: Balance, block_fullness: f64) -> f64 {
+        if gas_price == 0 {
+            return 1.0;
+        }
+        // Fullness in millionths, so that the integer formula of the protocol can be used.
+        let gas_limit = 1_000_000;
+        let gas_used = (block_fullness * gas_limit as f64).round() as Gas;
+        let mut future_gas_price = gas_price;
+        for _ in 0..HORIZON {
+            future_gas_price = Block::compute_new_gas_price(
+                future_gas_price,
+                gas_used,
+                gas_limit,
+                self.gas_price_adjustment_rate,
+                self.min_gas_price,
+                self.max_gas_price,
+            );
+        }
+        (future_gas_price as f64 / gas_price as f64).max(1.0)
+    }
+}
+
+fn fullness(gas_used: Gas, gas_limit: Gas) -> f64 {
+    if gas_limit == 0 {
+        0.0
+    } else {
+        gas_used as f64 / gas_limit as f64
+    }
+}
+
+/// Percentile of the sorted values by the nearest-rank method, 0 if there are none.
+fn nearest_rank(sorted: &[f64], percentile: u8) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (usize::from(percentile) * sorted.len() + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use num_rational::Rational;
+
+    use super::{nearest_rank, FeeHistory, HORIZON};
+
+    #[test]
+    fn test_nearest_rank() {
+        let values: Vec<f64> = (1..=10).map(|i| i as f64 / 10.0).collect();
+        assert_eq!(nearest_rank(&values, 50), 0.5);
+        assert_eq!(nearest_rank(&values, 90), 0.9);
+        assert_eq!(nearest_rank(&values, 99), 1.0);
+        assert_eq!(nearest_rank(&values[..1], 50), 0.1);
+        assert_eq!(nearest_rank(&[], 50), 0.0);
+    }
+
+    #[test]
+    fn test_gas_price_multiplier() {
+        let history = FeeHistory {
+            blocks: Default::default(),
+            gas_price_adjustment_rate: Rational::new_raw(1, 100),
+            min_gas_price: 100_000_000,
+            max_gas_price: 10_000_000_000_000_000_000_000,
+        };
+        let gas_price = 100_000_000;
+        // Full blocks raise the price by 1% each.
+        let multiplier = history.gas_price_multiplier(gas_price, 1.0);
+        assert!((multiplier - 1.01f64.powi(HORIZON as i32)).abs() < 1e-6, "{}", multiplier);
+        // Half full blocks keep it, and emptier blocks never bring the multiplier below 1.
+        assert_eq!(history.gas_price_multiplier(gas_price, 0.5), 1.0);
+        assert_eq!(history.gas_price_multiplier(gas_price, 0.0), 1.0);
+    }
+}
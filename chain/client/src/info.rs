@@ -1,26 +1,34 @@
+use crate::approval_participation::ApprovalParticipation;
 use crate::{metrics, rocksdb_metrics, SyncStatus};
 use actix::Addr;
 use near_chain_configs::{ClientConfig, LogSummaryStyle};
 use near_client_primitives::types::ShardSyncStatus;
+use near_metrics::push::StatsdExporter;
 use near_network::types::NetworkInfo;
 use near_primitives::block::Tip;
 use near_primitives::network::PeerId;
 use near_primitives::serialize::to_base;
+use near_primitives::syncing::StateSplitProgress;
 use near_primitives::telemetry::{
-    TelemetryAgentInfo, TelemetryChainInfo, TelemetryInfo, TelemetrySystemInfo,
+    TelemetryAgentInfo, TelemetryChainInfo, TelemetryInfo, TelemetryShardInfo, TelemetrySystemInfo,
 };
 use near_primitives::time::{Clock, Instant};
-use near_primitives::types::{AccountId, BlockHeight, EpochHeight, Gas, NumBlocks, ShardId};
+use near_primitives::types::{
+    AccountId, Balance, BlockHeight, EpochHeight, Gas, NumBlocks, ShardId, ValidatorKickoutReason,
+};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::{Version, DB_VERSION, PROTOCOL_VERSION};
-use near_primitives::views::{CurrentEpochValidatorInfo, EpochValidatorInfo, ValidatorKickoutView};
+use near_primitives::views::{
+    CurrentEpochValidatorInfo, EpochValidatorInfo, ProtocolVersionVotesView, ValidatorKickoutView,
+};
 use near_store::db::StoreStatistics;
 use near_telemetry::{telemetry, TelemetryActor};
 use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write;
 use std::sync::Arc;
 use sysinfo::{get_current_pid, set_open_files_limit, Pid, ProcessExt, System, SystemExt};
-use tracing::info;
+use tracing::{info, warn};
 
 const TERAGAS: f64 = 1_000_000_000_000_f64;
 
@@ -29,6 +37,13 @@ pub struct ValidatorInfoHelper {
     pub num_validators: usize,
 }
 
+/// Uptime of the validator run by the node since the tracking start.
+pub struct ValidatorUptime {
+    pub account_id: AccountId,
+    pub since_epoch_height: EpochHeight,
+    pub uptime: f64,
+}
+
 /// A helper that prints information about current chain and reports to telemetry.
 pub struct InfoHelper {
     /// Nearcore agent (executable) version
@@ -51,6 +66,14 @@ pub struct InfoHelper {
     telemetry_actor: Addr<TelemetryActor>,
     /// Log coloring enabled
     log_summary_style: LogSummaryStyle,
+    /// Height of the last epoch whose kickouts were logged.
+    kickouts_logged_epoch_height: Option<EpochHeight>,
+    /// Gas prices of the blocks processed before and at the end of the period.
+    gas_prices: Option<(Balance, Balance)>,
+    /// Gas used and gas limit of the chunks of each shard in the blocks processed during period.
+    shards_gas: BTreeMap<ShardId, (Gas, Gas)>,
+    /// Pushes the metrics to a StatsD agent at the end of every period, if configured.
+    metrics_exporter: Option<StatsdExporter>,
 }
 
 impl InfoHelper {
@@ -60,6 +83,15 @@ impl InfoHelper {
         validator_signer: Option<Arc<dyn ValidatorSigner>>,
     ) -> Self {
         set_open_files_limit(0);
+        let metrics_exporter = client_config.metrics_push.as_ref().and_then(|config| {
+            match StatsdExporter::new(&config.address, &config.prefix) {
+                Ok(exporter) => Some(exporter),
+                Err(err) => {
+                    warn!(target: "stats", "Failed to set up pushing metrics to {}: {}", config.address, err);
+                    None
+                }
+            }
+        });
         InfoHelper {
             nearcore_version: client_config.version.clone(),
             sys: System::new(),
@@ -71,23 +103,64 @@ impl InfoHelper {
             telemetry_actor,
             validator_signer,
             log_summary_style: client_config.log_summary_style,
+            kickouts_logged_epoch_height: None,
+            gas_prices: None,
+            shards_gas: BTreeMap::new(),
+            metrics_exporter,
         }
     }
 
-    pub fn chunk_processed(&mut self, shard_id: ShardId, gas_used: Gas) {
+    /// Replaces the key telemetry is signed with, used when the validator key gets rotated.
+    pub fn set_validator_signer(&mut self, validator_signer: Option<Arc<dyn ValidatorSigner>>) {
+        self.validator_signer = validator_signer;
+    }
+
+    /// Logs the validators kicked out at the start of the epoch, once per epoch.
+    pub fn epoch_kickouts(&mut self, epoch_height: EpochHeight, kickouts: &[ValidatorKickoutView]) {
+        if self.kickouts_logged_epoch_height == Some(epoch_height) {
+            return;
+        }
+        self.kickouts_logged_epoch_height = Some(epoch_height);
+        if kickouts.is_empty() {
+            info!(target: "stats", "Epoch #{} started, no validators kicked out", epoch_height);
+            return;
+        }
+        let kickouts_log = kickouts
+            .iter()
+            .map(|kickout| format!("{} ({})", kickout.account_id, display_kickout(kickout)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        info!(
+            target: "stats",
+            "Epoch #{} started, {} validator{} kicked out: {}",
+            epoch_height,
+            kickouts.len(),
+            if kickouts.len() == 1 { "" } else { "s" },
+            kickouts_log
+        );
+    }
+
+    pub fn chunk_processed(&mut self, shard_id: ShardId, gas_used: Gas, gas_limit: Gas) {
         metrics::TGAS_USAGE_HIST
             .with_label_values(&[&format!("{}", shard_id)])
             .observe(gas_used as f64 / TERAGAS);
+        let shard_gas = self.shards_gas.entry(shard_id).or_default();
+        shard_gas.0 += gas_used;
+        shard_gas.1 += gas_limit;
     }
 
     pub fn chunk_skipped(&mut self, shard_id: ShardId) {
         metrics::CHUNK_SKIPPED_TOTAL.with_label_values(&[&format!("{}", shard_id)]).inc();
     }
 
-    pub fn block_processed(&mut self, gas_used: Gas, num_chunks: u64) {
+    pub fn block_processed(&mut self, gas_used: Gas, num_chunks: u64, gas_price: Balance) {
         self.num_blocks_processed += 1;
         self.num_chunks_in_blocks_processed += num_chunks;
         self.gas_used += gas_used;
+        self.gas_prices = Some(match self.gas_prices {
+            Some((start_gas_price, _)) => (start_gas_price, gas_price),
+            None => (gas_price, gas_price),
+        });
     }
 
     pub fn info(
@@ -99,9 +172,12 @@ impl InfoHelper {
         network_info: &NetworkInfo,
         validator_info: Option<ValidatorInfoHelper>,
         validator_epoch_stats: Vec<ValidatorProductionStats>,
+        validator_uptime: Option<ValidatorUptime>,
         epoch_height: EpochHeight,
         protocol_upgrade_block_height: BlockHeight,
+        protocol_version_votes: Option<ProtocolVersionVotesView>,
         statistics: Option<StoreStatistics>,
+        delayed_receipts: BTreeMap<ShardId, u64>,
     ) {
         let use_colour = matches!(self.log_summary_style, LogSummaryStyle::Colored);
         let paint = |colour: ansi_term::Colour, text: Option<String>| match text {
@@ -115,12 +191,22 @@ impl InfoHelper {
         let sync_status_log = Some(display_sync_status(sync_status, head, genesis_height));
 
         let validator_info_log = validator_info.as_ref().map(|info| {
-            format!(
+            let mut log = format!(
                 " {}{} validator{}",
                 if info.is_validator { "Validator | " } else { "" },
                 info.num_validators,
                 s(info.num_validators)
-            )
+            );
+            if let Some(uptime) = validator_uptime.as_ref().filter(|_| info.is_validator) {
+                write!(
+                    log,
+                    " | Uptime {:.2}% since epoch #{}",
+                    uptime.uptime * 100.,
+                    uptime.since_epoch_height
+                )
+                .unwrap();
+            }
+            log
         });
 
         let network_info_log = Some(format!(
@@ -179,6 +265,17 @@ impl InfoHelper {
         (metrics::AVG_TGAS_USAGE.set((avg_gas_used as f64 / TERAGAS).round() as i64));
         (metrics::EPOCH_HEIGHT.set(epoch_height as i64));
         (metrics::PROTOCOL_UPGRADE_BLOCK_HEIGHT.set(protocol_upgrade_block_height as i64));
+        if let Some(votes) = protocol_version_votes {
+            // Versions nobody votes for anymore shouldn't linger.
+            metrics::PROTOCOL_VERSION_VOTES_STAKE_RATIO.reset();
+            for version in &votes.versions {
+                metrics::PROTOCOL_VERSION_VOTES_STAKE_RATIO
+                    .with_label_values(&[&version.protocol_version.to_string()])
+                    .set(version.stake as f64 / votes.total_stake.max(1) as f64);
+            }
+            metrics::PROTOCOL_UPGRADE_EPOCH_HEIGHT
+                .set(votes.upgrade.map(|upgrade| upgrade.epoch_height as i64).unwrap_or_default());
+        }
         (metrics::NODE_PROTOCOL_VERSION.set(PROTOCOL_VERSION as i64));
         (metrics::NODE_DB_VERSION.set(DB_VERSION as i64));
 
@@ -199,12 +296,30 @@ impl InfoHelper {
             (metrics::VALIDATORS_CHUNKS_EXPECTED
                 .with_label_values(&[stats.account_id.as_str()])
                 .set(stats.num_expected_chunks as i64));
+            (metrics::VALIDATORS_APPROVALS_INCLUDED
+                .with_label_values(&[stats.account_id.as_str()])
+                .set(stats.num_approvals_included as i64));
+            (metrics::VALIDATORS_APPROVALS_EXCLUDED
+                .with_label_values(&[stats.account_id.as_str()])
+                .set(stats.num_approvals_excluded as i64));
         }
 
+        if let Some(uptime) = validator_uptime {
+            metrics::VALIDATOR_CUMULATIVE_UPTIME_RATIO
+                .with_label_values(&[uptime.account_id.as_str()])
+                .set(uptime.uptime);
+        }
+
+        let shards = self.congestion(delayed_receipts);
+        let gas_price = self.gas_prices.map(|(_, gas_price)| gas_price);
+
         self.started = Clock::instant();
         self.num_blocks_processed = 0;
         self.num_chunks_in_blocks_processed = 0;
         self.gas_used = 0;
+        // The next period's trajectory starts from the last price of this one.
+        self.gas_prices = gas_price.map(|gas_price| (gas_price, gas_price));
+        self.shards_gas.clear();
 
         let info = TelemetryInfo {
             agent: TelemetryAgentInfo {
@@ -226,6 +341,8 @@ impl InfoHelper {
                 latest_block_hash: to_base(&head.last_block_hash),
                 latest_block_height: head.height,
                 num_peers: network_info.num_connected_peers,
+                gas_price,
+                shards,
             },
         };
         // Sign telemetry if there is a signer present.
@@ -235,6 +352,100 @@ impl InfoHelper {
             serde_json::to_value(&info).expect("Telemetry must serialize to json")
         };
         telemetry(&self.telemetry_actor, content);
+
+        if let Some(exporter) = &mut self.metrics_exporter {
+            if let Err(err) = exporter.push() {
+                warn!(target: "stats", "Failed to push metrics: {}", err);
+            }
+        }
+    }
+
+    /// Logs and exports the gas price trajectory over the period and the fullness and delayed
+    /// receipts backlog of each shard.
+    fn congestion(&self, delayed_receipts: BTreeMap<ShardId, u64>) -> Vec<TelemetryShardInfo> {
+        let shard_ids =
+            self.shards_gas.keys().chain(delayed_receipts.keys()).copied().collect::<BTreeSet<_>>();
+        let shards = shard_ids
+            .into_iter()
+            .map(|shard_id| {
+                let block_fullness = self
+                    .shards_gas
+                    .get(&shard_id)
+                    .map(|&(gas_used, gas_limit)| gas_used as f64 / gas_limit.max(1) as f64);
+                if let Some(block_fullness) = block_fullness {
+                    metrics::BLOCK_FULLNESS_RATIO
+                        .with_label_values(&[&shard_id.to_string()])
+                        .set(block_fullness);
+                }
+                TelemetryShardInfo {
+                    shard_id,
+                    block_fullness,
+                    delayed_receipts: delayed_receipts.get(&shard_id).copied(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut log = String::new();
+        if let Some((start_gas_price, gas_price)) = self.gas_prices {
+            metrics::GAS_PRICE.set(gas_price.min(i64::MAX as u128) as i64);
+            write!(
+                log,
+                "Gas price {} ({:+.2}%)",
+                gas_price,
+                (gas_price as f64 / start_gas_price.max(1) as f64 - 1.) * 100.
+            )
+            .unwrap();
+        }
+        if shards.iter().any(|shard| shard.block_fullness.is_some()) {
+            log.push_str(" | Fullness");
+            for shard in &shards {
+                if let Some(block_fullness) = shard.block_fullness {
+                    write!(log, " {}: {:.0}%", shard.shard_id, block_fullness * 100.).unwrap();
+                }
+            }
+        }
+        if shards.iter().any(|shard| shard.delayed_receipts.is_some()) {
+            log.push_str(" | Delayed receipts");
+            for shard in &shards {
+                if let Some(count) = shard.delayed_receipts {
+                    write!(log, " {}: {}", shard.shard_id, count).unwrap();
+                }
+            }
+        }
+        if !log.is_empty() {
+            info!(target: "stats", "{}", log.trim_start_matches(" | "));
+        }
+        shards
+    }
+}
+
+fn display_kickout(kickout: &ValidatorKickoutView) -> String {
+    let produced = |kind: &str, produced: NumBlocks, expected: NumBlocks| {
+        let mut text = format!(
+            "produced {}/{} {} ({:.1}%)",
+            produced,
+            expected,
+            kind,
+            produced as f64 * 100. / expected.max(1) as f64
+        );
+        if let Some(threshold) = kickout.produced_threshold_percent {
+            write!(text, ", below {}%", threshold).unwrap();
+        }
+        text
+    };
+    match kickout.reason {
+        ValidatorKickoutReason::Slashed => "slashed".to_string(),
+        ValidatorKickoutReason::NotEnoughBlocks { produced: p, expected } => {
+            produced("blocks", p, expected)
+        }
+        ValidatorKickoutReason::NotEnoughChunks { produced: p, expected } => {
+            produced("chunks", p, expected)
+        }
+        ValidatorKickoutReason::Unstaked => "unstaked".to_string(),
+        ValidatorKickoutReason::NotEnoughStake { stake, threshold } => {
+            format!("stake {} below seat price {}", stake, threshold)
+        }
+        ValidatorKickoutReason::DidNotGetASeat => "didn’t get a seat".to_string(),
     }
 }
 
@@ -285,7 +496,7 @@ fn display_sync_status(
             for (shard_id, shard_status) in shard_statuses {
                 write!(
                     res,
-                    "[{}: {}]",
+                    "[{}: {}",
                     shard_id,
                     match shard_status.status {
                         ShardSyncStatus::StateDownloadHeader => "header",
@@ -294,11 +505,15 @@ fn display_sync_status(
                         ShardSyncStatus::StateDownloadApplying => "applying",
                         ShardSyncStatus::StateDownloadComplete => "download complete",
                         ShardSyncStatus::StateSplitScheduling => "split scheduling",
-                        ShardSyncStatus::StateSplitApplying => "split applying",
+                        ShardSyncStatus::StateSplitApplying(_) => "split applying",
                         ShardSyncStatus::StateSyncDone => "done",
                     }
                 )
                 .unwrap();
+                if let ShardSyncStatus::StateSplitApplying(progress) = &shard_status.status {
+                    res.push_str(&display_state_split_progress(*shard_id, progress));
+                }
+                res.push(']');
             }
             res
         }
@@ -306,6 +521,34 @@ fn display_sync_status(
     }
 }
 
+/// Formats the progress of building the states of the shards split from the given shard and
+/// exports it via metrics.
+fn display_state_split_progress(shard_id: ShardId, progress: &StateSplitProgress) -> String {
+    let shard_label = shard_id.to_string();
+    let (parts_done, num_parts) = progress.parts();
+    metrics::STATE_SPLIT_PARTS_DONE.with_label_values(&[&shard_label]).set(parts_done as i64);
+    metrics::STATE_SPLIT_PARTS_TOTAL.with_label_values(&[&shard_label]).set(num_parts as i64);
+    let eta = progress.eta();
+    metrics::STATE_SPLIT_ETA_SECONDS
+        .with_label_values(&[&shard_label])
+        .set(eta.map_or(-1, |eta| eta.as_secs() as i64));
+    let mut res = if num_parts == 0 {
+        String::new()
+    } else {
+        format!(" {:.2}%", (parts_done * 100) as f64 / num_parts as f64)
+    };
+    if let Some(eta) = eta {
+        write!(res, " ETA {}s", eta.as_secs()).unwrap();
+    }
+    for (shard_uid, bytes) in progress.child_bytes() {
+        metrics::STATE_SPLIT_CHILD_SHARD_BYTES
+            .with_label_values(&[&shard_uid.shard_id.to_string()])
+            .set(bytes as i64);
+        write!(res, " {}: {}", shard_uid.shard_id, pretty_bytes(bytes)).unwrap();
+    }
+    res
+}
+
 const KILOBYTE: u64 = 1024;
 const MEGABYTE: u64 = KILOBYTE * 1024;
 const GIGABYTE: u64 = MEGABYTE * 1024;
@@ -349,13 +592,16 @@ fn gas_used_per_sec(num: u64) -> String {
     }
 }
 
-/// Number of blocks and chunks produced and expected by a certain validator.
+/// Number of blocks and chunks produced and expected by a certain validator, and number of
+/// approvals of this node included in and excluded from the blocks it produced.
 pub struct ValidatorProductionStats {
     pub account_id: AccountId,
     pub num_produced_blocks: NumBlocks,
     pub num_expected_blocks: NumBlocks,
     pub num_produced_chunks: NumBlocks,
     pub num_expected_chunks: NumBlocks,
+    pub num_approvals_included: NumBlocks,
+    pub num_approvals_excluded: NumBlocks,
 }
 
 impl ValidatorProductionStats {
@@ -366,15 +612,23 @@ impl ValidatorProductionStats {
             num_expected_blocks: 0,
             num_produced_chunks: 0,
             num_expected_chunks: 0,
+            num_approvals_included: 0,
+            num_approvals_excluded: 0,
         }
     }
-    pub fn validator(info: CurrentEpochValidatorInfo) -> Self {
+    pub fn validator(
+        info: CurrentEpochValidatorInfo,
+        approval_participation: &ApprovalParticipation,
+    ) -> Self {
+        let approvals = approval_participation.get(&info.account_id);
         Self {
             account_id: info.account_id,
             num_produced_blocks: info.num_produced_blocks,
             num_expected_blocks: info.num_expected_blocks,
             num_produced_chunks: info.num_produced_chunks,
             num_expected_chunks: info.num_expected_chunks,
+            num_approvals_included: approvals.included,
+            num_approvals_excluded: approvals.excluded,
         }
     }
 }
@@ -382,6 +636,7 @@ impl ValidatorProductionStats {
 /// Converts EpochValidatorInfo into a vector of ValidatorProductionStats.
 pub fn get_validator_epoch_stats(
     current_validator_epoch_info: EpochValidatorInfo,
+    approval_participation: &ApprovalParticipation,
 ) -> Vec<ValidatorProductionStats> {
     let mut stats = vec![];
     // Record kickouts to replace latest stats of kicked out validators with zeros.
@@ -389,7 +644,7 @@ pub fn get_validator_epoch_stats(
         stats.push(ValidatorProductionStats::kickout(kickout));
     }
     for validator in current_validator_epoch_info.current_validators {
-        stats.push(ValidatorProductionStats::validator(validator));
+        stats.push(ValidatorProductionStats::validator(validator, approval_participation));
     }
     stats
 }
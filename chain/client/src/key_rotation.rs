@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use near_chain_configs::KeyRotationConfig;
+use near_client_primitives::types::Error;
+use near_crypto::{InMemorySigner, PublicKey, Signer};
+use near_primitives::hash::CryptoHash;
+use near_primitives::transaction::{Action, SignedTransaction, StakeAction, Transaction};
+use near_primitives::types::{
+    AccountId, Balance, BlockHeight, BlockHeightDelta, EpochHeight, Nonce,
+};
+use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+
+/// Number of blocks after which the staking transaction is submitted again if it hasn't been
+/// included yet.
+const STAKING_TX_RESUBMIT_BLOCKS: BlockHeightDelta = 20;
+
+/// Staking transaction submitted with the next validator key.
+struct StakingTransaction {
+    nonce: Nonce,
+    /// Height of the head when the transaction was submitted.
+    height: BlockHeight,
+}
+
+/// State of an automatic rotation of the validator key, see [`KeyRotationConfig`].
+pub(crate) struct KeyRotation {
+    /// Validator key to switch to.
+    pub next_signer: Arc<dyn ValidatorSigner>,
+    /// Full access key of the validator account, signs the staking transaction.
+    account_signer: InMemorySigner,
+    /// Height of the epoch from which on the staking transaction is submitted.
+    epoch_height: EpochHeight,
+    /// Last submitted staking transaction, until it is included.
+    staking_tx: Option<StakingTransaction>,
+    staking_tx_included: bool,
+}
+
+impl KeyRotation {
+    pub fn new(config: &KeyRotationConfig, validator_id: &AccountId) -> Result<Self, Error> {
+        let next_signer = InMemoryValidatorSigner::from_file(&config.next_validator_key_file);
+        if next_signer.validator_id() != validator_id {
+            return Err(Error::Other(format!(
+                "next validator key is for {}, expected {}",
+                next_signer.validator_id(),
+                validator_id
+            )));
+        }
+        let account_signer = InMemorySigner::from_file(&config.account_key_file);
+        if &account_signer.account_id != validator_id {
+            return Err(Error::Other(format!(
+                "account key for staking is for {}, expected {}",
+                account_signer.account_id, validator_id
+            )));
+        }
+        Ok(Self {
+            next_signer: Arc::new(next_signer),
+            account_signer,
+            epoch_height: config.epoch_height,
+            staking_tx: None,
+            staking_tx_included: false,
+        })
+    }
+
+    pub fn account_public_key(&self) -> PublicKey {
+        self.account_signer.public_key()
+    }
+
+    /// Whether the inclusion of the staking transaction has to be checked in the epoch with the
+    /// given height.
+    pub fn should_check(&self, epoch_height: EpochHeight) -> bool {
+        !self.staking_tx_included && epoch_height >= self.epoch_height
+    }
+
+    /// Whether the staking transaction has to be submitted (again) at the given height, given the
+    /// nonce of the account key at that height.  Marks the transaction as included once its nonce
+    /// has been used.
+    pub fn should_submit(&mut self, height: BlockHeight, nonce: Nonce) -> bool {
+        match &self.staking_tx {
+            Some(tx) if nonce >= tx.nonce => {
+                self.staking_tx_included = true;
+                false
+            }
+            Some(tx) => height >= tx.height + STAKING_TX_RESUBMIT_BLOCKS,
+            None => true,
+        }
+    }
+
+    pub fn staking_tx_included(&self) -> bool {
+        self.staking_tx_included
+    }
+
+    /// Creates the transaction which keeps the given stake of the validator and changes its key
+    /// to the next one, to be submitted at the given height.
+    pub fn staking_transaction(
+        &mut self,
+        nonce: Nonce,
+        stake: Balance,
+        height: BlockHeight,
+        block_hash: CryptoHash,
+    ) -> SignedTransaction {
+        self.staking_tx = Some(StakingTransaction { nonce, height });
+        let transaction = Transaction {
+            signer_id: self.account_signer.account_id.clone(),
+            public_key: self.account_signer.public_key(),
+            nonce,
+            receiver_id: self.account_signer.account_id.clone(),
+            block_hash,
+            actions: vec![Action::Stake(StakeAction {
+                stake,
+                public_key: self.next_signer.public_key(),
+            })],
+        };
+        let signature = self.account_signer.sign(transaction.get_hash_and_size().0.as_ref());
+        SignedTransaction::new(signature, transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::KeyRotationConfig;
+    use near_crypto::{InMemorySigner, KeyType, Signer};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::transaction::{Action, StakeAction};
+    use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+
+    use super::KeyRotation;
+
+    #[test]
+    fn test_staking_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let account_id = "test0".parse().unwrap();
+        let next_signer =
+            InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "next");
+        next_signer.write_to_file(&dir.path().join("next_validator_key.json")).unwrap();
+        let account_signer =
+            InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+        account_signer.write_to_file(&dir.path().join("account_key.json")).unwrap();
+        let config = KeyRotationConfig {
+            next_validator_key_file: dir.path().join("next_validator_key.json"),
+            account_key_file: dir.path().join("account_key.json"),
+            epoch_height: 5,
+        };
+        assert!(KeyRotation::new(&config, &"test1".parse().unwrap()).is_err());
+
+        let mut rotation = KeyRotation::new(&config, &account_id).unwrap();
+        assert!(!rotation.should_check(4));
+        assert!(rotation.should_check(5));
+        assert!(rotation.should_submit(100, 10));
+        let tx = rotation.staking_transaction(11, 100, 100, CryptoHash::default());
+        // Not included yet, resubmitted after a while.
+        assert!(!rotation.should_submit(101, 10));
+        assert!(rotation.should_submit(120, 10));
+        rotation.staking_transaction(11, 100, 120, CryptoHash::default());
+        assert!(!rotation.should_submit(121, 11));
+        assert!(rotation.staking_tx_included());
+        assert!(!rotation.should_check(6));
+
+        assert_eq!(tx.transaction.signer_id, account_id);
+        assert_eq!(tx.transaction.public_key, account_signer.public_key);
+        assert_eq!(tx.transaction.nonce, 11);
+        assert_eq!(
+            tx.transaction.actions,
+            vec![Action::Stake(StakeAction { stake: 100, public_key: next_signer.public_key() })]
+        );
+        assert!(tx.signature.verify(tx.get_hash().as_ref(), &account_signer.public_key));
+    }
+}
@@ -1,10 +1,15 @@
 pub use near_client_primitives::types::{
-    Error, GetBlock, GetBlockHash, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree,
-    GetChunk, GetExecutionOutcome, GetExecutionOutcomeResponse, GetExecutionOutcomesForBlock,
-    GetGasPrice, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt,
-    GetStateChanges, GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    Error, GetAccountHistory, GetAccountsByCodeHash, GetBlock, GetBlockError, GetBlockHash,
+    GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk, GetDelayedReceipts,
+    GetEpochSummary, GetExecutionOutcome, GetExecutionOutcomeResponse,
+    GetExecutionOutcomesForBlock, GetFeeEstimate, GetGasAttribution, GetGasPrice,
+    GetMaintenanceWindows, GetMissedProduction, GetNetworkInfo, GetNextLightClientBlock,
+    GetProducerSchedule, GetProtocolConfig, GetProtocolConfigDiff, GetProtocolVersionVotes,
+    GetReceipt, GetRewardProjection, GetStateChanges, GetStateChangesInBlock,
+    GetStateChangesWithCauseInBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetValidatorEvents, GetValidatorInfo, GetValidatorOrdered, GetValidatorProjection, Query,
+    QueryError, Status, StatusResponse, StopProcessingBlocks, SubscribeContractLogs,
+    SubscribeContractLogsError, SyncStatus, TraceReceipt, TxStatus, TxStatusError,
 };
 
 pub use crate::client::Client;
@@ -13,14 +18,29 @@ pub use crate::client_actor::{start_client, ClientActor};
 pub use crate::view_client::AdversarialControls;
 pub use crate::view_client::{start_view_client, ViewClientActor};
 
+mod approval_aggregation;
+mod approval_participation;
+mod background_signer;
+mod block_verifier;
 mod chunks_delay_tracker;
 mod client;
 mod client_actor;
+mod contract_logs;
+mod epoch_summary;
+mod fee_estimation;
 mod info;
+mod key_rotation;
 mod metrics;
+mod missed_production;
 mod rocksdb_metrics;
+mod shadow_validation;
+pub mod simulator;
+mod state_parts_cache;
 pub mod sync;
 pub mod test_utils;
 #[cfg(test)]
 mod tests;
+mod validator_events;
+mod validator_stats;
+mod view_cache;
 mod view_client;
@@ -1,7 +1,7 @@
 use near_metrics::{
-    try_create_histogram, try_create_histogram_vec, try_create_int_counter,
-    try_create_int_counter_vec, try_create_int_gauge, Histogram, HistogramVec, IntCounter,
-    IntCounterVec, IntGauge, IntGaugeVec,
+    try_create_gauge_vec, try_create_histogram, try_create_histogram_vec, try_create_int_counter,
+    try_create_int_counter_vec, try_create_int_gauge, GaugeVec, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
 };
 use once_cell::sync::Lazy;
 
@@ -55,6 +55,13 @@ pub static MEMORY_USAGE: Lazy<IntGauge> = Lazy::new(|| {
 pub static GC_TIME: Lazy<Histogram> = Lazy::new(|| {
     try_create_histogram("near_gc_time", "Time taken to do garbage collection").unwrap()
 });
+pub static BLOCK_BACKGROUND_VERIFICATION_TIME: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_block_background_verification_time",
+        "Time taken to verify the chunk header signatures and the roots of a received block before it is processed",
+    )
+    .unwrap()
+});
 pub static AVG_TGAS_USAGE: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_chunk_tgas_used",
@@ -74,6 +81,17 @@ pub static TGAS_USAGE_HIST: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static GAS_PRICE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_gas_price", "Gas price of the last processed block").unwrap()
+});
+pub static BLOCK_FULLNESS_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_block_fullness_ratio",
+        "Gas used by the chunks of the shard in the blocks processed since the last summary, relative to their gas limit",
+        &["shard_id"],
+    )
+    .unwrap()
+});
 pub static BLOCK_CHUNKS_REQUESTED_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_block_chunks_request_delay_seconds",
@@ -108,6 +126,22 @@ pub static VALIDATORS_CHUNKS_EXPECTED: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static VALIDATORS_APPROVALS_INCLUDED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_validators_approvals_included",
+        "Number of approvals of this node included in the blocks produced by a validator in the current epoch",
+        &["account_id"],
+    )
+    .unwrap()
+});
+pub static VALIDATORS_APPROVALS_EXCLUDED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_validators_approvals_excluded",
+        "Number of approvals of this node missing from the blocks produced by a validator in the current epoch",
+        &["account_id"],
+    )
+    .unwrap()
+});
 pub static VALIDATORS_BLOCKS_PRODUCED: Lazy<IntGaugeVec> = Lazy::new(|| {
     near_metrics::try_create_int_gauge_vec(
         "near_validators_blocks_produced",
@@ -124,6 +158,38 @@ pub static VALIDATORS_BLOCKS_EXPECTED: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static STATE_SPLIT_PARTS_DONE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_state_split_parts_done",
+        "Number of parts of the parent shard state already split into the child shards",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+pub static STATE_SPLIT_PARTS_TOTAL: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_state_split_parts_total",
+        "Number of parts of the parent shard state to split into the child shards",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+pub static STATE_SPLIT_ETA_SECONDS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_state_split_eta_seconds",
+        "Estimated time until the parent shard state is split, -1 if unknown",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+pub static STATE_SPLIT_CHILD_SHARD_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_state_split_child_shard_bytes",
+        "Bytes of keys and values written to a child shard while splitting the parent shard state",
+        &["shard_id"],
+    )
+    .unwrap()
+});
 pub static SYNC_STATUS: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_sync_status", "Node sync status").unwrap());
 pub static EPOCH_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
@@ -137,6 +203,21 @@ pub static PROTOCOL_UPGRADE_BLOCK_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static PROTOCOL_UPGRADE_EPOCH_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_protocol_upgrade_epoch_height",
+        "Height of the first epoch of the decided or projected protocol upgrade",
+    )
+    .unwrap()
+});
+pub static PROTOCOL_VERSION_VOTES_STAKE_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_protocol_version_votes_stake_ratio",
+        "Share of the block producer stake of the epoch voting for a protocol version",
+        &["protocol_version"],
+    )
+    .unwrap()
+});
 pub static NODE_PROTOCOL_VERSION: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_node_protocol_version", "Max protocol version supported by the node")
         .unwrap()
@@ -176,3 +257,149 @@ pub static CLIENT_MESSAGES_PROCESSING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static SHADOW_VALIDATION_CHECKS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_shadow_validation_checks_total",
+        "Number of blocks, chunks and approvals produced in shadow validation mode which were compared with the published ones",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static SHADOW_VALIDATION_DIVERGENCES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_shadow_validation_divergences_total",
+        "Number of fields of blocks, chunks and approvals produced in shadow validation mode which differ from the published ones",
+        &["kind", "field"],
+    )
+    .unwrap()
+});
+pub static VALIDATOR_EVENTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_validator_events_total",
+        "Number of events reported because the validator of this node missed blocks or chunks or was projected to be kicked out",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static MISSED_PRODUCTION_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_missed_production_total",
+        "Number of blocks and chunks the validator of this node was scheduled to produce but didn't, by reason",
+        &["kind", "reason"],
+    )
+    .unwrap()
+});
+pub static PRODUCED_CHUNK_CAP_BINDING_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_produced_chunk_cap_binding_total",
+        "Number of produced chunks which left transactions out because of the local gas or transaction count cap",
+        &["cap"],
+    )
+    .unwrap()
+});
+pub static SHADOW_VALIDATION_PRODUCTION_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_shadow_validation_production_time_seconds",
+        "Time taken to produce blocks and chunks in shadow validation mode",
+        &["kind"],
+        Some(prometheus::exponential_buckets(0.001, 1.6, 20).unwrap()),
+    )
+    .unwrap()
+});
+pub static VIEW_CACHE_HITS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_view_client_cache_hits_total",
+        "Number of block, chunk and validators requests to the view client answered from its cache",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static VIEW_CACHE_MISSES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_view_client_cache_misses_total",
+        "Number of block, chunk and validators requests to the view client not found in its cache",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static VIEW_QUERY_TIMEOUTS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_view_client_query_timeouts_total",
+        "Number of view_state queries cancelled because they ran for longer than view_query_timeout",
+    )
+    .unwrap()
+});
+pub static CONTRACT_LOGS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_contract_logs_total",
+        "Number of log lines of contract executions matching the contract_logs config",
+    )
+    .unwrap()
+});
+pub static STATE_PART_SERVE_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_state_part_serve_latency_seconds",
+        "Time to obtain a state part requested by a node doing state sync, by whether it was found in memory, on disk or had to be generated",
+        &["source"],
+        Some(prometheus::exponential_buckets(0.0001, 2.0, 20).unwrap()),
+    )
+    .unwrap()
+});
+pub static STATE_PARTS_CACHE_MEMORY_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_state_parts_cache_memory_bytes",
+        "Total size of the state parts kept in memory for serving state sync requests",
+    )
+    .unwrap()
+});
+pub static STATE_PARTS_PRECOMPUTED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_state_parts_precomputed_total",
+        "Number of state parts generated ahead of state sync requests",
+    )
+    .unwrap()
+});
+pub static STATE_PARTS_PRECOMPUTED_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_state_parts_precomputed_bytes",
+        "Total size of the state parts stored for the sync hash of the current epoch by the precomputation",
+    )
+    .unwrap()
+});
+pub static TRANSACTIONS_FORWARDED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_transactions_forwarded_total",
+        "Number of messages forwarding a transaction to an upcoming chunk producer",
+    )
+    .unwrap()
+});
+pub static APPROVALS_DUPLICATE_DROPPED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_approvals_duplicate_dropped_total",
+        "Number of received approvals dropped because the same approval was already received",
+    )
+    .unwrap()
+});
+pub static APPROVALS_BATCHED_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_approvals_batched_total",
+        "Number of approvals sent together with other approvals to the same block producer",
+    )
+    .unwrap()
+});
+pub static CHALLENGES_RECEIVED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_challenges_received_total",
+        "Number of challenges received from the network with a valid signature, by kind",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub static VALIDATOR_CUMULATIVE_UPTIME_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_validator_cumulative_uptime_ratio",
+        "Share of the blocks and chunks the validator of the node produced since the tracking start, kept across restarts",
+        &["account_id"],
+    )
+    .unwrap()
+});
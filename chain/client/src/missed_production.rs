@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::views::{MissedProductionReason, MissedProductionView};
+use tracing::warn;
+
+use crate::metrics;
+
+/// How many of the latest misses are kept for `EXPERIMENTAL_missed_production`.
+const MAX_MISSES: usize = 1024;
+
+/// Tracks the blocks and chunks the validator run by the node was scheduled to produce but
+/// didn't.  Failed production attempts are recorded as they happen; the misses are accounted
+/// for once a block shows that the slot passed: the heights it skipped and the chunks it doesn't
+/// include.
+#[derive(Default)]
+pub(crate) struct MissedProduction {
+    /// Why producing the block at a height, or the chunk of a shard, failed.
+    failures: HashMap<(BlockHeight, Option<ShardId>), MissedProductionReason>,
+    /// Chunks which were produced, to tell those which didn't make it into the block.
+    produced_chunks: HashSet<(BlockHeight, ShardId)>,
+    misses: VecDeque<MissedProductionView>,
+    next_id: u64,
+}
+
+impl MissedProduction {
+    /// Records why producing the block at the height, or the chunk of the shard, failed.  A later
+    /// failure overrides an earlier one.
+    pub fn record_failure(
+        &mut self,
+        height: BlockHeight,
+        shard_id: Option<ShardId>,
+        reason: MissedProductionReason,
+    ) {
+        self.failures.insert((height, shard_id), reason);
+    }
+
+    pub fn record_produced_chunk(&mut self, height: BlockHeight, shard_id: ShardId) {
+        self.produced_chunks.insert((height, shard_id));
+    }
+
+    /// Accounts for the block at the height, which the chain skipped.
+    pub fn block_missed(&mut self, height: BlockHeight, syncing: bool) {
+        let reason = match self.failures.get(&(height, None)) {
+            Some(reason) => *reason,
+            None if syncing => MissedProductionReason::NotSynced,
+            None => MissedProductionReason::Timeout,
+        };
+        self.record(height, None, reason);
+    }
+
+    /// Accounts for the chunk of the shard at the height, which the block at the height doesn't
+    /// include.
+    pub fn chunk_missed(&mut self, height: BlockHeight, shard_id: ShardId, syncing: bool) {
+        let reason = match self.failures.get(&(height, Some(shard_id))) {
+            Some(reason) => *reason,
+            None if self.produced_chunks.contains(&(height, shard_id)) => {
+                MissedProductionReason::Timeout
+            }
+            None if syncing => MissedProductionReason::NotSynced,
+            // The previous block was never accepted, so the chunk wasn't even attempted.
+            None => MissedProductionReason::MissingChunkParts,
+        };
+        self.record(height, Some(shard_id), reason);
+    }
+
+    /// Forgets the attempts for the heights up to the given one, which are accounted for.
+    pub fn prune(&mut self, height: BlockHeight) {
+        self.failures.retain(|(failure_height, _), _| *failure_height > height);
+        self.produced_chunks.retain(|(chunk_height, _)| *chunk_height > height);
+    }
+
+    fn record(
+        &mut self,
+        height: BlockHeight,
+        shard_id: Option<ShardId>,
+        reason: MissedProductionReason,
+    ) {
+        let kind = if shard_id.is_some() { "chunk" } else { "block" };
+        warn!(target: "client", "Missed producing the {} at height {} for shard {:?}: {:?}", kind, height, shard_id, reason);
+        metrics::MISSED_PRODUCTION_TOTAL.with_label_values(&[kind, reason_label(reason)]).inc();
+        if self.misses.len() == MAX_MISSES {
+            self.misses.pop_front();
+        }
+        self.misses.push_back(MissedProductionView { id: self.next_id, height, shard_id, reason });
+        self.next_id += 1;
+    }
+
+    /// Misses with id at least `from_id` which are still kept.
+    pub fn misses_from(&self, from_id: u64) -> Vec<MissedProductionView> {
+        self.misses.iter().filter(|miss| miss.id >= from_id).cloned().collect()
+    }
+}
+
+fn reason_label(reason: MissedProductionReason) -> &'static str {
+    match reason {
+        MissedProductionReason::NotSynced => "not_synced",
+        MissedProductionReason::MissingChunkParts => "missing_chunk_parts",
+        MissedProductionReason::Timeout => "timeout",
+        MissedProductionReason::SignerError => "signer_error",
+        MissedProductionReason::Error => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::views::MissedProductionReason;
+
+    use super::MissedProduction;
+
+    #[test]
+    fn test_missed_production() {
+        let mut missed = MissedProduction::default();
+        missed.record_failure(10, None, MissedProductionReason::MissingChunkParts);
+        missed.record_produced_chunk(11, 0);
+        missed.record_failure(11, Some(1), MissedProductionReason::Error);
+        missed.block_missed(10, false);
+        missed.block_missed(12, true);
+        missed.chunk_missed(11, 0, false);
+        missed.chunk_missed(11, 1, false);
+        missed.chunk_missed(11, 2, false);
+        missed.prune(12);
+        missed.chunk_missed(11, 1, false);
+
+        let misses: Vec<_> = missed
+            .misses_from(0)
+            .into_iter()
+            .map(|miss| (miss.id, miss.height, miss.shard_id, miss.reason))
+            .collect();
+        assert_eq!(
+            misses,
+            vec![
+                (0, 10, None, MissedProductionReason::MissingChunkParts),
+                (1, 12, None, MissedProductionReason::NotSynced),
+                (2, 11, Some(0), MissedProductionReason::Timeout),
+                (3, 11, Some(1), MissedProductionReason::Error),
+                (4, 11, Some(2), MissedProductionReason::MissingChunkParts),
+                // The failure is forgotten once accounted for.
+                (5, 11, Some(1), MissedProductionReason::MissingChunkParts),
+            ]
+        );
+        assert_eq!(missed.misses_from(4).len(), 2);
+    }
+}
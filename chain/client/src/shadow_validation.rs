@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use near_primitives::block::{Approval, ApprovalInner, Block, BlockHeader};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ShardChunkHeader;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::validator_signer::ValidatorSigner;
+use tracing::{debug, warn};
+
+use crate::metrics;
+
+/// How many heights of locally produced blocks, chunks and approvals are kept while waiting for
+/// the published ones.
+const SHADOW_CACHE_SIZE: usize = 32;
+
+/// Keeps blocks, chunks and approvals produced by a node in shadow validation mode until the
+/// ones published by the network for the same heights arrive, and reports divergences between
+/// them via metrics.
+///
+/// Only the fields which are fully determined by the previous block are compared: transactions
+/// and the set of chunks included depend on the mempool and timing of the producer, so they are
+/// expected to differ.
+pub(crate) struct ShadowValidator {
+    /// Key used to sign what is produced locally.  Nothing signed with it is ever sent out.
+    pub signer: Arc<dyn ValidatorSigner>,
+    /// Blocks produced locally, keyed by the hash of their previous block.
+    blocks: lru::LruCache<CryptoHash, BlockHeader>,
+    /// Chunks produced locally, keyed by the hash of their previous block and shard id.
+    chunks: lru::LruCache<(CryptoHash, ShardId), ShardChunkHeader>,
+    /// Approvals produced locally, keyed by their target height.
+    approvals: lru::LruCache<BlockHeight, Approval>,
+}
+
+impl ShadowValidator {
+    pub fn new(signer: Arc<dyn ValidatorSigner>) -> Self {
+        Self {
+            signer,
+            blocks: lru::LruCache::new(SHADOW_CACHE_SIZE),
+            chunks: lru::LruCache::new(SHADOW_CACHE_SIZE * 16),
+            approvals: lru::LruCache::new(SHADOW_CACHE_SIZE),
+        }
+    }
+
+    pub fn record_block(&mut self, header: BlockHeader) {
+        self.blocks.put(*header.prev_hash(), header);
+    }
+
+    pub fn record_chunk(&mut self, header: ShardChunkHeader) {
+        self.chunks.put((header.prev_block_hash(), header.shard_id()), header);
+    }
+
+    pub fn record_approval(&mut self, approval: Approval) {
+        self.approvals.put(approval.target_height, approval);
+    }
+
+    /// Compares the block accepted from the network, and the chunks it includes, with the ones
+    /// produced locally on top of the same previous block.
+    pub fn check_block(&mut self, block: &Block) {
+        let header = block.header();
+        if let Some(approval) = self.approvals.pop(&header.height()) {
+            let fields = approval_divergences(&approval, header);
+            report("approval", header.height(), &fields);
+        }
+        if let Some(expected) = self.blocks.pop(header.prev_hash()) {
+            let fields = block_divergences(&expected, header);
+            report("block", header.height(), &fields);
+        }
+        for chunk in block.chunks().iter() {
+            if chunk.height_included() != header.height() {
+                continue;
+            }
+            if let Some(expected) = self.chunks.pop(&(*header.prev_hash(), chunk.shard_id())) {
+                let fields = chunk_divergences(&expected, chunk);
+                report("chunk", header.height(), &fields);
+            }
+        }
+    }
+}
+
+fn report(kind: &str, height: BlockHeight, fields: &[&str]) {
+    metrics::SHADOW_VALIDATION_CHECKS_TOTAL.with_label_values(&[kind]).inc();
+    if fields.is_empty() {
+        debug!(target: "client", "Shadow validation: {} at height {} matches", kind, height);
+        return;
+    }
+    warn!(target: "client", "Shadow validation: {} at height {} diverges in {:?}", kind, height, fields);
+    for field in fields {
+        metrics::SHADOW_VALIDATION_DIVERGENCES_TOTAL.with_label_values(&[kind, field]).inc();
+    }
+}
+
+/// The approval diverges if it endorses a block other than the parent of the published block.
+fn approval_divergences(approval: &Approval, header: &BlockHeader) -> Vec<&'static str> {
+    match approval.inner {
+        ApprovalInner::Endorsement(parent_hash) if parent_hash != *header.prev_hash() => {
+            vec!["parent_hash"]
+        }
+        _ => vec![],
+    }
+}
+
+fn block_divergences(expected: &BlockHeader, actual: &BlockHeader) -> Vec<&'static str> {
+    let mut fields = vec![];
+    if expected.epoch_id() != actual.epoch_id() {
+        fields.push("epoch_id");
+    }
+    if expected.next_epoch_id() != actual.next_epoch_id() {
+        fields.push("next_epoch_id");
+    }
+    if expected.next_bp_hash() != actual.next_bp_hash() {
+        fields.push("next_bp_hash");
+    }
+    if expected.block_merkle_root() != actual.block_merkle_root() {
+        fields.push("block_merkle_root");
+    }
+    if expected.block_ordinal() != actual.block_ordinal() {
+        fields.push("block_ordinal");
+    }
+    if expected.epoch_sync_data_hash() != actual.epoch_sync_data_hash() {
+        fields.push("epoch_sync_data_hash");
+    }
+    fields
+}
+
+fn chunk_divergences(expected: &ShardChunkHeader, actual: &ShardChunkHeader) -> Vec<&'static str> {
+    let mut fields = vec![];
+    if expected.prev_state_root() != actual.prev_state_root() {
+        fields.push("prev_state_root");
+    }
+    if expected.outcome_root() != actual.outcome_root() {
+        fields.push("outcome_root");
+    }
+    if expected.gas_used() != actual.gas_used() {
+        fields.push("gas_used");
+    }
+    if expected.gas_limit() != actual.gas_limit() {
+        fields.push("gas_limit");
+    }
+    if expected.balance_burnt() != actual.balance_burnt() {
+        fields.push("balance_burnt");
+    }
+    if expected.outgoing_receipts_root() != actual.outgoing_receipts_root() {
+        fields.push("outgoing_receipts_root");
+    }
+    if !expected.validator_proposals().eq(actual.validator_proposals()) {
+        fields.push("validator_proposals");
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::KeyType;
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::sharding::{ShardChunkHeader, ShardChunkHeaderV3};
+    use near_primitives::types::{Balance, Gas};
+    use near_primitives::validator_signer::InMemoryValidatorSigner;
+
+    use super::chunk_divergences;
+
+    fn chunk(
+        signer: &InMemoryValidatorSigner,
+        state_root: CryptoHash,
+        gas_used: Gas,
+        balance_burnt: Balance,
+        tx_root: CryptoHash,
+    ) -> ShardChunkHeader {
+        ShardChunkHeader::V3(
+            ShardChunkHeaderV3::new(
+                hash(b"prev"),
+                state_root,
+                CryptoHash::default(),
+                CryptoHash::default(),
+                0,
+                1,
+                0,
+                gas_used,
+                1000,
+                balance_burnt,
+                CryptoHash::default(),
+                tx_root,
+                vec![],
+                signer,
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_chunk_divergences() {
+        let validator =
+            InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+        let shadow =
+            InMemoryValidatorSigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let actual = chunk(&validator, hash(b"state"), 10, 1, hash(b"txs"));
+
+        // Signer and transactions are not expected to match.
+        let expected = chunk(&shadow, hash(b"state"), 10, 1, CryptoHash::default());
+        assert!(chunk_divergences(&expected, &actual).is_empty());
+
+        let expected = chunk(&shadow, hash(b"other state"), 10, 2, hash(b"txs"));
+        assert_eq!(chunk_divergences(&expected, &actual), ["prev_state_root", "balance_burnt"]);
+    }
+}
@@ -0,0 +1,543 @@
+//! Deterministic simulation of a set of validators on a virtual clock.
+//!
+//! The clients run in the thread of the test, without actix.  The simulator plays the part of the
+//! actors and of the network: it fires the block production and doomslug timers of the clients
+//! and delivers the messages they send each other after a latency chosen by a seeded random
+//! number generator.  All of it is driven by an event queue ordered by virtual time, and the
+//! clock of the clients (`near_primitives::time::Clock`) returns the time of the event being
+//! processed, so a run is reproducible given the seed and takes no real time.
+//!
+//! Only the messages needed for block production are delivered: blocks, approvals and chunk
+//! parts, requests and forwards.  The rest, e.g. sync requests, is dropped.
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::debug;
+
+use near_chain::test_utils::KeyValueRuntime;
+use near_chain::types::AcceptedBlock;
+use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
+use near_chain_configs::ClientConfig;
+use near_crypto::{InMemorySigner, KeyType};
+use near_network::test_utils::MockPeerManagerAdapter;
+use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
+use near_primitives::block::{ApprovalType, Block};
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::network::PeerId;
+use near_primitives::sharding::PartialEncodedChunk;
+use near_primitives::time::{Clock, Instant, MockClockGuard, Utc};
+use near_primitives::types::{AccountId, BlockHeight, NumSeats};
+use near_primitives::utils::MaybeValidated;
+use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+use near_store::test_utils::create_test_store;
+
+use crate::{Client, Error, SyncStatus};
+
+/// How the messages sent from one client to another are delayed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkLatency {
+    /// A latency chosen at random between the minimum and maximum latency of the simulator.
+    Random,
+    /// A fixed latency.
+    Fixed(Duration),
+    /// The messages are dropped.
+    Down,
+}
+
+enum Event {
+    BlockProduction(usize),
+    DoomslugTimer(usize),
+    Deliver { from: usize, to: usize, request: NetworkRequests },
+}
+
+/// An event with the virtual time at which it happens.  Events at the same time happen in the
+/// order they were scheduled in.
+struct ScheduledEvent {
+    time: Duration,
+    seq: u64,
+    event: Event,
+}
+
+impl PartialEq for ScheduledEvent {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl Eq for ScheduledEvent {}
+
+impl PartialOrd for ScheduledEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledEvent {
+    /// Reversed, so that the max-heap pops the earliest event first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.seq).cmp(&(self.time, self.seq))
+    }
+}
+
+/// A builder for the [`Simulator`].
+pub struct SimulatorBuilder {
+    num_validators: usize,
+    seed: u64,
+    epoch_length: u64,
+    min_block_production_delay: Duration,
+    max_block_production_delay: Duration,
+    min_latency: Duration,
+    max_latency: Duration,
+}
+
+impl SimulatorBuilder {
+    /// Sets the number of validators, each run by its own client.  Defaults to four.
+    pub fn validators(mut self, num_validators: usize) -> Self {
+        assert!(num_validators > 0);
+        self.num_validators = num_validators;
+        self
+    }
+
+    /// Sets the seed of the random number generator choosing the latencies.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn epoch_length(mut self, epoch_length: u64) -> Self {
+        self.epoch_length = epoch_length;
+        self
+    }
+
+    /// Sets the minimum and maximum block production delays of the clients, which also determine
+    /// the doomslug timeouts.  Default to 100ms and 200ms.
+    pub fn block_production_delays(mut self, min: Duration, max: Duration) -> Self {
+        self.min_block_production_delay = min;
+        self.max_block_production_delay = max;
+        self
+    }
+
+    /// Sets the range of the random latencies of the links.  Defaults to 10ms to 50ms.
+    pub fn latencies(mut self, min: Duration, max: Duration) -> Self {
+        assert!(min <= max);
+        self.min_latency = min;
+        self.max_latency = max;
+        self
+    }
+
+    pub fn build(self) -> Simulator {
+        // The clock has to be mocked before the clients are created, since they read it.
+        let clock = MockClockGuard::default();
+        let start_instant = Instant::now();
+        let start_utc = Utc.timestamp(1_600_000_000, 0);
+        clock.set_virtual_time(start_instant, start_utc);
+
+        let mut chain_genesis = ChainGenesis::test();
+        chain_genesis.epoch_length = self.epoch_length;
+        let validators: Vec<AccountId> =
+            (0..self.num_validators).map(|i| format!("test{}", i).parse().unwrap()).collect();
+        let mut clients = vec![];
+        let mut network_adapters = vec![];
+        let mut peer_ids = vec![];
+        let mut route_backs = vec![];
+        for account_id in validators.iter() {
+            let network_adapter = Arc::new(MockPeerManagerAdapter::default());
+            let runtime_adapter = Arc::new(KeyValueRuntime::new_with_validators(
+                create_test_store(),
+                vec![validators.clone()],
+                1,
+                1,
+                chain_genesis.epoch_length,
+            ));
+            let validator_signer: Arc<dyn ValidatorSigner> =
+                Arc::new(InMemoryValidatorSigner::from_seed(
+                    account_id.clone(),
+                    KeyType::ED25519,
+                    account_id.as_ref(),
+                ));
+            let mut config = ClientConfig::test(
+                true,
+                self.min_block_production_delay.as_millis() as u64,
+                self.max_block_production_delay.as_millis() as u64,
+                validators.len() as NumSeats,
+                false,
+                true,
+            );
+            config.epoch_length = chain_genesis.epoch_length;
+            let mut client = Client::new(
+                config,
+                chain_genesis.clone(),
+                runtime_adapter,
+                network_adapter.clone(),
+                Some(validator_signer),
+                true,
+                [3; 32],
+            )
+            .unwrap();
+            client.sync_status = SyncStatus::NoSync;
+            clients.push(client);
+            network_adapters.push(network_adapter);
+            peer_ids.push(PeerId::new(
+                InMemorySigner::from_seed(
+                    account_id.clone(),
+                    KeyType::ED25519,
+                    account_id.as_ref(),
+                )
+                .public_key,
+            ));
+            route_backs.push(hash(account_id.as_ref().as_bytes()));
+        }
+
+        let mut simulator = Simulator {
+            clients,
+            network_adapters,
+            peer_ids,
+            route_backs,
+            account_to_client_index: validators
+                .into_iter()
+                .enumerate()
+                .map(|(index, account_id)| (account_id, index))
+                .collect(),
+            clock,
+            start_instant,
+            start_utc,
+            now: Duration::default(),
+            rng: StdRng::seed_from_u64(self.seed),
+            min_latency: self.min_latency,
+            max_latency: self.max_latency,
+            links: HashMap::new(),
+            events: BinaryHeap::new(),
+            next_seq: 0,
+        };
+        for id in 0..simulator.clients.len() {
+            let config = &simulator.clients[id].config;
+            let (block_production_delay, doomslug_step_period) =
+                (config.block_production_tracking_delay, config.doosmslug_step_period);
+            simulator.schedule(block_production_delay, Event::BlockProduction(id));
+            simulator.schedule(doomslug_step_period, Event::DoomslugTimer(id));
+        }
+        simulator
+    }
+}
+
+/// Validators running on a virtual clock, see the module documentation.
+pub struct Simulator {
+    pub clients: Vec<Client>,
+    network_adapters: Vec<Arc<MockPeerManagerAdapter>>,
+    peer_ids: Vec<PeerId>,
+    /// The `route_back` of the chunk part requests sent by each client, identifying the client
+    /// to which the responses are delivered.
+    route_backs: Vec<CryptoHash>,
+    account_to_client_index: HashMap<AccountId, usize>,
+    clock: MockClockGuard,
+    start_instant: Instant,
+    start_utc: DateTime<Utc>,
+    /// The virtual time elapsed since the start of the simulation.
+    now: Duration,
+    rng: StdRng,
+    min_latency: Duration,
+    max_latency: Duration,
+    links: HashMap<(usize, usize), LinkLatency>,
+    events: BinaryHeap<ScheduledEvent>,
+    next_seq: u64,
+}
+
+impl Simulator {
+    pub fn builder() -> SimulatorBuilder {
+        SimulatorBuilder {
+            num_validators: 4,
+            seed: 0,
+            epoch_length: 5,
+            min_block_production_delay: Duration::from_millis(100),
+            max_block_production_delay: Duration::from_millis(200),
+            min_latency: Duration::from_millis(10),
+            max_latency: Duration::from_millis(50),
+        }
+    }
+
+    /// Returns the virtual time elapsed since the start of the simulation.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    /// Sets how the messages sent by the client `from` to the client `to` are delayed.
+    pub fn set_link(&mut self, from: usize, to: usize, latency: LinkLatency) {
+        self.links.insert((from, to), latency);
+    }
+
+    /// Sets how all the messages sent to and by the client `id` are delayed.
+    pub fn set_links_of(&mut self, id: usize, latency: LinkLatency) {
+        for other in 0..self.clients.len() {
+            if other != id {
+                self.set_link(id, other, latency);
+                self.set_link(other, id, latency);
+            }
+        }
+    }
+
+    /// Processes the events up to `duration` of virtual time from now.
+    pub fn run_for(&mut self, duration: Duration) {
+        let until = self.now + duration;
+        while self.events.peek().map_or(false, |event| event.time <= until) {
+            self.step();
+        }
+        self.set_now(until);
+    }
+
+    /// Processes the events until `condition` holds or `timeout` of virtual time passes.  Returns
+    /// whether the condition holds.
+    pub fn run_until<F: FnMut(&mut Simulator) -> bool>(
+        &mut self,
+        mut condition: F,
+        timeout: Duration,
+    ) -> bool {
+        let until = self.now + timeout;
+        while !condition(self) {
+            if self.events.peek().map_or(true, |event| event.time > until) {
+                self.set_now(until);
+                return false;
+            }
+            self.step();
+        }
+        true
+    }
+
+    /// Returns the height of the head of the client `id`.
+    pub fn head_height(&self, id: usize) -> BlockHeight {
+        self.clients[id].chain.head().unwrap().height
+    }
+
+    /// Returns the height of the last final block of the client `id`.
+    pub fn final_height(&self, id: usize) -> BlockHeight {
+        self.clients[id].chain.final_head().unwrap().height
+    }
+
+    fn set_now(&mut self, now: Duration) {
+        self.now = now;
+        self.clock.set_virtual_time(
+            self.start_instant + now,
+            self.start_utc + chrono::Duration::from_std(now).unwrap(),
+        );
+    }
+
+    fn schedule(&mut self, delay: Duration, event: Event) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.push(ScheduledEvent { time: self.now + delay, seq, event });
+    }
+
+    fn step(&mut self) {
+        let ScheduledEvent { time, event, .. } = self.events.pop().unwrap();
+        self.set_now(time);
+        match event {
+            Event::BlockProduction(id) => {
+                if let Err(err) = self.handle_block_production(id) {
+                    debug!(target: "simulator", "Client {} failed to produce a block: {}", id, err);
+                }
+                let delay = self.clients[id].config.block_production_tracking_delay;
+                self.schedule(delay, Event::BlockProduction(id));
+            }
+            Event::DoomslugTimer(id) => {
+                self.handle_doomslug_timer(id);
+                let delay = self.clients[id].config.doosmslug_step_period;
+                self.schedule(delay, Event::DoomslugTimer(id));
+            }
+            Event::Deliver { from, to, request } => self.deliver(from, to, request),
+        }
+        self.send_network_requests();
+    }
+
+    /// Mirrors `ClientActor::handle_block_production`.
+    fn handle_block_production(&mut self, id: usize) -> Result<(), Error> {
+        let client = &mut self.clients[id];
+        let _ = client.check_and_update_doomslug_tip();
+        let head = client.chain.head()?;
+        let latest_known = client.chain.mut_store().get_latest_known()?;
+        let epoch_id =
+            client.runtime_adapter.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        for height in
+            latest_known.height + 1..=client.doomslug.get_largest_height_crossing_threshold()
+        {
+            let client = &mut self.clients[id];
+            let block_producer = client.runtime_adapter.get_block_producer(&epoch_id, height)?;
+            if client.validator_signer.as_ref().map(|signer| signer.validator_id())
+                != Some(&block_producer)
+            {
+                continue;
+            }
+            let num_chunks = client.shards_mgr.num_chunks_for_block(&head.last_block_hash);
+            let have_all_chunks = head.height == 0
+                || num_chunks == client.runtime_adapter.num_shards(&epoch_id).unwrap();
+            if client.doomslug.ready_to_produce_block(Clock::instant(), height, have_all_chunks) {
+                if let Some(block) = client.produce_block(height)? {
+                    self.network_adapters[id].do_send(PeerManagerMessageRequest::NetworkRequests(
+                        NetworkRequests::Block { block: block.clone() },
+                    ));
+                    self.process_block(id, block, Provenance::PRODUCED);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Mirrors `ClientActor::try_doomslug_timer`.
+    fn handle_doomslug_timer(&mut self, id: usize) {
+        let client = &mut self.clients[id];
+        let _ = client.check_and_update_doomslug_tip();
+        let approvals = client.doomslug.process_timer(Clock::instant());
+        let mut chain_store_update = client.chain.mut_store().store_update();
+        chain_store_update.save_largest_target_height(client.doomslug.get_largest_target_height());
+        chain_store_update.commit().unwrap();
+        let head = client.chain.head().unwrap();
+        if client.is_validator(&head.epoch_id, &head.last_block_hash)
+            || client.is_validator(&head.next_epoch_id, &head.last_block_hash)
+        {
+            for approval in approvals {
+                let tip = client.doomslug.get_tip().0;
+                if let Err(err) = client.send_approval(&tip, approval) {
+                    debug!(target: "simulator", "Client {} failed to send an approval: {}", id, err);
+                }
+            }
+            client.flush_approvals();
+        }
+    }
+
+    fn process_block(&mut self, id: usize, block: Block, provenance: Provenance) {
+        let (accepted_blocks, result) =
+            self.clients[id].process_block(MaybeValidated::from(block), provenance);
+        if let Err(err) = result {
+            debug!(target: "simulator", "Client {} didn't accept a block: {}", id, err);
+        }
+        self.on_blocks_accepted(id, accepted_blocks);
+    }
+
+    fn on_blocks_accepted(&mut self, id: usize, accepted_blocks: Vec<AcceptedBlock>) {
+        for block in accepted_blocks {
+            self.clients[id].on_block_accepted(block.hash, block.status, block.provenance);
+        }
+    }
+
+    fn deliver(&mut self, from: usize, to: usize, request: NetworkRequests) {
+        let client = &mut self.clients[to];
+        let accepted_blocks = match request {
+            NetworkRequests::Block { block } => {
+                self.process_block(to, block, Provenance::NONE);
+                return;
+            }
+            NetworkRequests::Approval { approval_message } => {
+                client.collect_block_approval(
+                    &approval_message.approval,
+                    ApprovalType::PeerApproval(self.peer_ids[from].clone()),
+                );
+                return;
+            }
+            NetworkRequests::Approvals { approvals, .. } => {
+                for approval in approvals {
+                    client.collect_block_approval(
+                        &approval,
+                        ApprovalType::PeerApproval(self.peer_ids[from].clone()),
+                    );
+                }
+                return;
+            }
+            NetworkRequests::PartialEncodedChunkMessage { partial_encoded_chunk, .. } => client
+                .process_partial_encoded_chunk(MaybeValidated::from(PartialEncodedChunk::from(
+                    partial_encoded_chunk,
+                ))),
+            NetworkRequests::PartialEncodedChunkForward { forward, .. } => {
+                client.process_partial_encoded_chunk_forward(forward)
+            }
+            NetworkRequests::PartialEncodedChunkRequest { request, .. } => {
+                // The response is sent through the network adapter of the client.
+                client.shards_mgr.process_partial_encoded_chunk_request(
+                    request,
+                    self.route_backs[from],
+                    client.chain.mut_store(),
+                    &mut client.rs,
+                );
+                return;
+            }
+            NetworkRequests::PartialEncodedChunkResponse { response, .. } => {
+                client.process_partial_encoded_chunk_response(response)
+            }
+            _ => return,
+        };
+        match accepted_blocks {
+            Ok(accepted_blocks) => self.on_blocks_accepted(to, accepted_blocks),
+            Err(err) => {
+                debug!(target: "simulator", "Client {} failed to process a chunk: {}", to, err)
+            }
+        }
+    }
+
+    /// Schedules the delivery of the messages the clients sent since the last call.
+    fn send_network_requests(&mut self) {
+        for from in 0..self.clients.len() {
+            while let Some(request) = self.network_adapters[from].pop() {
+                let request = match request {
+                    PeerManagerMessageRequest::NetworkRequests(request) => request,
+                    _ => continue,
+                };
+                let recipients: Vec<usize> = match &request {
+                    NetworkRequests::Block { .. } => {
+                        (0..self.clients.len()).filter(|&to| to != from).collect()
+                    }
+                    NetworkRequests::Approval { approval_message } => self
+                        .account_to_client_index
+                        .get(&approval_message.target)
+                        .into_iter()
+                        .copied()
+                        .collect(),
+                    NetworkRequests::Approvals { target, .. } => {
+                        self.account_to_client_index.get(target).into_iter().copied().collect()
+                    }
+                    NetworkRequests::PartialEncodedChunkMessage { account_id, .. }
+                    | NetworkRequests::PartialEncodedChunkForward { account_id, .. } => {
+                        self.account_to_client_index.get(account_id).into_iter().copied().collect()
+                    }
+                    NetworkRequests::PartialEncodedChunkRequest { target, .. } => target
+                        .account_id
+                        .as_ref()
+                        .and_then(|account_id| self.account_to_client_index.get(account_id))
+                        .into_iter()
+                        .copied()
+                        .collect(),
+                    NetworkRequests::PartialEncodedChunkResponse { route_back, .. } => self
+                        .route_backs
+                        .iter()
+                        .position(|hash| hash == route_back)
+                        .into_iter()
+                        .collect(),
+                    _ => vec![],
+                };
+                for to in recipients {
+                    if let Some(latency) = self.latency(from, to) {
+                        let request = request.clone();
+                        self.schedule(latency, Event::Deliver { from, to, request });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the latency of a message sent from the client `from` to the client `to`, or `None`
+    /// if it's dropped.
+    fn latency(&mut self, from: usize, to: usize) -> Option<Duration> {
+        if from == to {
+            return Some(Duration::default());
+        }
+        match self.links.get(&(from, to)).copied().unwrap_or(LinkLatency::Random) {
+            LinkLatency::Random => Some(Duration::from_nanos(self.rng.gen_range(
+                self.min_latency.as_nanos() as u64,
+                self.max_latency.as_nanos() as u64 + 1,
+            ))),
+            LinkLatency::Fixed(latency) => Some(latency),
+            LinkLatency::Down => None,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use tracing::{error, info, warn};
+
+use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, RuntimeAdapter};
+use near_primitives::hash::CryptoHash;
+use near_primitives::syncing::{get_num_state_parts, StatePartKey};
+use near_primitives::types::{BlockHeight, ShardId};
+use near_store::ColStateParts;
+
+use crate::metrics;
+use crate::sync::StateSync;
+
+/// How often the precomputation checks whether a new epoch has started.
+const PRECOMPUTE_CHECK_PERIOD: Duration = Duration::from_secs(10);
+
+/// Recently served state parts within a memory budget, shared by all instances of
+/// ViewClientActor.
+pub struct StatePartsCache {
+    parts: lru::LruCache<(CryptoHash, ShardId, u64), Vec<u8>>,
+    size: u64,
+    memory_budget_bytes: u64,
+}
+
+impl StatePartsCache {
+    pub fn new(memory_budget_bytes: u64) -> Self {
+        Self { parts: lru::LruCache::unbounded(), size: 0, memory_budget_bytes }
+    }
+
+    pub fn get(
+        &mut self,
+        sync_hash: CryptoHash,
+        shard_id: ShardId,
+        part_id: u64,
+    ) -> Option<Vec<u8>> {
+        self.parts.get(&(sync_hash, shard_id, part_id)).cloned()
+    }
+
+    /// Keeps the part, evicting the least recently served ones to stay within the budget.
+    pub fn put(&mut self, sync_hash: CryptoHash, shard_id: ShardId, part_id: u64, part: Vec<u8>) {
+        let part_size = part.len() as u64;
+        if part_size > self.memory_budget_bytes {
+            return;
+        }
+        if let Some(old_part) = self.parts.put((sync_hash, shard_id, part_id), part) {
+            self.size -= old_part.len() as u64;
+        }
+        self.size += part_size;
+        while self.size > self.memory_budget_bytes {
+            let (_, evicted) = self.parts.pop_lru().expect("the size of the parts is positive");
+            self.size -= evicted.len() as u64;
+        }
+        metrics::STATE_PARTS_CACHE_MEMORY_BYTES.set(self.size as i64);
+    }
+}
+
+/// Starts a thread which generates the state parts of the tracked shards for the sync hash of
+/// the current epoch as soon as the epoch starts, so that state sync requests are answered from
+/// disk.
+pub fn start_state_parts_precompute(
+    runtime_adapter: Arc<dyn RuntimeAdapter>,
+    chain_genesis: ChainGenesis,
+    disk_budget_bytes: u64,
+) {
+    thread::Builder::new()
+        .name("state_parts_precompute".to_string())
+        .spawn(move || {
+            let mut chain = match Chain::new_for_view_client(
+                runtime_adapter.clone(),
+                &chain_genesis,
+                DoomslugThresholdMode::TwoThirds,
+            ) {
+                Ok(chain) => chain,
+                Err(err) => {
+                    error!(target: "sync", "Failed to start state parts precomputation: {}", err);
+                    return;
+                }
+            };
+            let mut done_sync_hash = None;
+            loop {
+                if let Err(err) = precompute_state_parts(
+                    &mut chain,
+                    &*runtime_adapter,
+                    disk_budget_bytes,
+                    &mut done_sync_hash,
+                ) {
+                    warn!(target: "sync", "Failed to precompute state parts: {}", err);
+                }
+                thread::sleep(PRECOMPUTE_CHECK_PERIOD);
+            }
+        })
+        .expect("Failed to spawn state parts precomputation thread");
+}
+
+/// Generates the missing parts of the tracked shards for the sync hash of the epoch of the head,
+/// unless done for that sync hash already, after deleting the parts of previous sync hashes.
+fn precompute_state_parts(
+    chain: &mut Chain,
+    runtime_adapter: &dyn RuntimeAdapter,
+    disk_budget_bytes: u64,
+    done_sync_hash: &mut Option<CryptoHash>,
+) -> Result<(), near_chain::Error> {
+    let head = chain.head()?;
+    let sync_hash = StateSync::get_epoch_start_sync_hash(chain, &head.last_block_hash)?;
+    if *done_sync_hash == Some(sync_hash) || &sync_hash == chain.genesis().hash() {
+        return Ok(());
+    }
+    let sync_block = chain.get_block(&sync_hash)?.clone();
+    delete_previous_state_parts(chain, sync_block.header().height())?;
+
+    // The parts contain the state as of the last block of the previous epoch.
+    let sync_prev_header = chain.get_block_header(sync_block.header().prev_hash())?.clone();
+    let mut total_size = 0;
+    let mut num_precomputed = 0;
+    'shards: for shard_id in 0..sync_block.chunks().len() as ShardId {
+        if !runtime_adapter.cares_about_shard(None, sync_prev_header.prev_hash(), shard_id, false) {
+            continue;
+        }
+        let header = chain.get_state_response_header(shard_id, sync_hash)?;
+        let num_parts = get_num_state_parts(header.state_root_node().memory_usage);
+        for part_id in 0..num_parts {
+            if total_size >= disk_budget_bytes {
+                warn!(target: "sync", "State parts for {} exceed the disk budget of {} bytes, the remaining parts will be generated on request", sync_hash, disk_budget_bytes);
+                break 'shards;
+            }
+            let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
+            let part = match chain.store().store().get(ColStateParts, &key)? {
+                Some(part) => part,
+                None => {
+                    let part = chain.get_state_response_part(shard_id, part_id, sync_hash)?;
+                    metrics::STATE_PARTS_PRECOMPUTED_TOTAL.inc();
+                    num_precomputed += 1;
+                    part
+                }
+            };
+            total_size += part.len() as u64;
+            metrics::STATE_PARTS_PRECOMPUTED_BYTES.set(total_size as i64);
+        }
+    }
+    info!(target: "sync", "Precomputed {} state parts for {}, {} bytes of parts are stored", num_precomputed, sync_hash, total_size);
+    *done_sync_hash = Some(sync_hash);
+    Ok(())
+}
+
+/// Deletes the stored state parts of sync hashes below the given height.  Parts of unknown or
+/// higher blocks may be the ones being downloaded by this node.
+fn delete_previous_state_parts(
+    chain: &mut Chain,
+    sync_height: BlockHeight,
+) -> Result<(), near_chain::Error> {
+    let store = chain.store().store().clone();
+    let mut store_update = store.store_update();
+    let mut num_deleted = 0;
+    for (key, _) in store.iter(ColStateParts) {
+        let StatePartKey(sync_hash, _, _) = StatePartKey::try_from_slice(&key)?;
+        match chain.get_block_header(&sync_hash) {
+            Ok(header) if header.height() < sync_height => {
+                store_update.delete(ColStateParts, &key);
+                num_deleted += 1;
+            }
+            _ => {}
+        }
+    }
+    store_update.commit()?;
+    if num_deleted > 0 {
+        info!(target: "sync", "Deleted {} state parts of previous epochs", num_deleted);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::hash;
+
+    use super::StatePartsCache;
+
+    #[test]
+    fn test_state_parts_cache() {
+        let sync_hash = hash(b"sync");
+        let mut cache = StatePartsCache::new(10);
+        cache.put(sync_hash, 0, 0, vec![0; 4]);
+        cache.put(sync_hash, 0, 1, vec![1; 4]);
+        assert_eq!(cache.get(sync_hash, 0, 0), Some(vec![0; 4]));
+        // Evicts part 1, which was served less recently.
+        cache.put(sync_hash, 1, 0, vec![2; 4]);
+        assert_eq!(cache.get(sync_hash, 0, 1), None);
+        assert_eq!(cache.get(sync_hash, 0, 0), Some(vec![0; 4]));
+        assert_eq!(cache.get(sync_hash, 1, 0), Some(vec![2; 4]));
+        // Parts larger than the budget aren't kept.
+        cache.put(sync_hash, 1, 1, vec![3; 11]);
+        assert_eq!(cache.get(sync_hash, 1, 1), None);
+        assert_eq!(cache.get(sync_hash, 0, 0), Some(vec![0; 4]));
+    }
+}
@@ -18,7 +18,7 @@ use near_network::types::{FullPeerInfo, NetworkRequests, NetworkResponses, PeerM
 use near_primitives::block::Tip;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
-use near_primitives::syncing::get_num_state_parts;
+use near_primitives::syncing::{get_num_state_parts, StateSplitProgress};
 use near_primitives::time::{Clock, Utc};
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{
@@ -552,8 +552,7 @@ impl BlockSync {
             let (height, hash) = request;
             let request_from_archival = self.archive && height < gc_stop_height;
             let peer = if request_from_archival {
-                let archival_peer_iter =
-                    highest_height_peers.iter().filter(|p| p.chain_info.archival);
+                let archival_peer_iter = highest_height_peers.iter().filter(|p| p.is_archival());
                 archival_peer_iter.choose(&mut rand::thread_rng())
             } else {
                 let peer_iter = highest_height_peers.iter();
@@ -864,18 +863,20 @@ impl StateSync {
                 }
                 ShardSyncStatus::StateSplitScheduling => {
                     debug_assert!(split_states);
+                    let progress = Arc::new(StateSplitProgress::new());
                     chain.build_state_for_split_shards_preprocessing(
                         &sync_hash,
                         shard_id,
+                        progress.clone(),
                         state_split_scheduler,
                     )?;
                     debug!(target: "sync", "State sync split scheduled: me {:?}, shard = {}, hash = {}", me, shard_id, sync_hash);
                     *shard_sync_download = ShardSyncDownload {
                         downloads: vec![],
-                        status: ShardSyncStatus::StateSplitApplying,
+                        status: ShardSyncStatus::StateSplitApplying(progress),
                     };
                 }
-                ShardSyncStatus::StateSplitApplying => {
+                ShardSyncStatus::StateSplitApplying(_) => {
                     debug_assert!(split_states);
                     let result = self.split_state_roots.remove(&shard_id);
                     if let Some(state_roots) = result {
@@ -1013,9 +1014,18 @@ impl StateSync {
         }
     }
 
+    /// Number of parts requested from `target` for `shard_id` which haven't arrived yet.
+    fn pending_parts(&self, target: &AccountOrPeerIdOrHash, shard_id: ShardId) -> u64 {
+        self.last_part_id_requested
+            .get(&(target.clone(), shard_id))
+            .map_or(0, |request| request.missing_parts as u64)
+    }
+
     /// Find possible targets to download state from.
-    /// Candidates are validators at current epoch and peers at highest height.
-    /// Only select candidates that we have no pending request currently ongoing.
+    /// Candidates are the peers at highest height which advertised they serve the state of the
+    /// shard for the sync hash or, if none of them can take more requests, validators at current
+    /// epoch and peers at highest height tracking the shard.
+    /// Only select candidates with less than `MAX_STATE_PART_REQUEST` parts pending.
     fn possible_targets(
         &mut self,
         me: &Option<AccountId>,
@@ -1028,6 +1038,14 @@ impl StateSync {
         // Remove candidates from pending list if request expired due to timeout
         self.last_part_id_requested.retain(|_, request| !request.expired());
 
+        let serving_peers = peers_serving_state(highest_height_peers, sync_hash, shard_id)
+            .into_iter()
+            .filter(|candidate| self.pending_parts(candidate, shard_id) < MAX_STATE_PART_REQUEST)
+            .collect::<Vec<_>>();
+        if !serving_peers.is_empty() {
+            return Ok(serving_peers);
+        }
+
         let prev_block_hash = chain.get_block_header(&sync_hash)?.prev_hash();
         let epoch_hash = runtime_adapter.get_epoch_id_from_prev_block(prev_block_hash)?;
 
@@ -1052,15 +1070,13 @@ impl StateSync {
                 }
             })
             .chain(highest_height_peers.iter().filter_map(|peer| {
-                if peer.chain_info.tracked_shards.contains(&shard_id) {
+                if peer.chain_info.tracked_shards.contains(&shard_id) && peer.serves_state_sync() {
                     Some(AccountOrPeerIdOrHash::PeerId(peer.peer_info.id.clone()))
                 } else {
                     None
                 }
             }))
-            .filter(|candidate| {
-                !self.last_part_id_requested.contains_key(&(candidate.clone(), shard_id))
-            })
+            .filter(|candidate| self.pending_parts(candidate, shard_id) < MAX_STATE_PART_REQUEST)
             .collect::<Vec<_>>())
     }
 
@@ -1117,8 +1133,23 @@ impl StateSync {
                 );
             }
             ShardSyncStatus::StateDownloadParts => {
-                let possible_targets_sampler =
-                    SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+                let num_parts_to_request = new_shard_sync_download
+                    .downloads
+                    .iter()
+                    .filter(|download| download.run_me.load(Ordering::SeqCst))
+                    .count();
+                let limit = part_request_limit(num_parts_to_request, possible_targets.len());
+                // Never keep more than `MAX_STATE_PART_REQUEST` parts in flight per peer.
+                let limits = possible_targets
+                    .iter()
+                    .map(|target| {
+                        limit.min(
+                            MAX_STATE_PART_REQUEST
+                                .saturating_sub(self.pending_parts(target, shard_id)),
+                        )
+                    })
+                    .collect();
+                let possible_targets_sampler = SamplerLimited::new(possible_targets, limits);
 
                 // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
                 // Parts are ordered such that its index match its part_id.
@@ -1221,12 +1252,39 @@ impl StateSync {
     }
 }
 
+/// Peers which advertised they serve the state of the shard for the sync hash.
+fn peers_serving_state(
+    peers: &[FullPeerInfo],
+    sync_hash: CryptoHash,
+    shard_id: ShardId,
+) -> Vec<AccountOrPeerIdOrHash> {
+    peers
+        .iter()
+        .filter(|peer| {
+            peer.servable_state
+                .iter()
+                .any(|info| info.sync_hash == sync_hash && info.shard_ids.contains(&shard_id))
+        })
+        .map(|peer| AccountOrPeerIdOrHash::PeerId(peer.peer_info.id.clone()))
+        .collect()
+}
+
+/// Maximum number of parts requested from each target, so that the parts are spread evenly
+/// across the targets.
+fn part_request_limit(num_parts: usize, num_targets: usize) -> u64 {
+    if num_targets == 0 {
+        return 0;
+    }
+    let parts_per_target = (num_parts + num_targets - 1) / num_targets;
+    MAX_STATE_PART_REQUEST.min(parts_per_target as u64)
+}
+
 /// Create an abstract collection of elements to be shuffled.
-/// Each element will appear in the shuffled output exactly `limit` times.
+/// Each element will appear in the shuffled output exactly as many times as its limit.
 /// Use it as an iterator to access the shuffled collection.
 ///
 /// ```rust,ignore
-/// let sampler = SamplerLimited::new(vec![1, 2, 3], 2);
+/// let sampler = SamplerLimited::new(vec![1, 2, 3], vec![2, 2, 2]);
 ///
 /// let res = sampler.collect::<Vec<_>>();
 ///
@@ -1247,13 +1305,10 @@ struct SamplerLimited<T> {
 }
 
 impl<T> SamplerLimited<T> {
-    fn new(data: Vec<T>, limit: u64) -> Self {
-        if limit == 0 {
-            Self { data: vec![], limit: vec![] }
-        } else {
-            let len = data.len();
-            Self { data, limit: vec![limit; len] }
-        }
+    fn new(data: Vec<T>, limits: Vec<u64>) -> Self {
+        let (data, limit) =
+            data.into_iter().zip(limits.into_iter()).filter(|(_, limit)| *limit > 0).unzip();
+        Self { data, limit }
     }
 }
 
@@ -1297,7 +1352,8 @@ mod test {
 
     use super::*;
     use crate::test_utils::TestEnv;
-    use near_network_primitives::types::{PartialEdgeInfo, PeerInfo};
+    use near_network_primitives::types::{PartialEdgeInfo, PeerInfo, ServableStateInfo};
+    use near_primitives::hash::hash;
     use near_primitives::merkle::PartialMerkleTree;
     use near_primitives::types::EpochId;
     use near_primitives::utils::MaybeValidated;
@@ -1381,6 +1437,11 @@ mod test {
                 archival: false,
             },
             partial_edge_info: PartialEdgeInfo::default(),
+            servable_state: vec![],
+            protocol_version: PROTOCOL_VERSION,
+            client_version: None,
+            capabilities: None,
+            connection_established_time: Clock::instant(),
         };
         let head = chain.head().unwrap();
         assert!(header_sync
@@ -1431,6 +1492,11 @@ mod test {
                 },
                 chain_info: Default::default(),
                 partial_edge_info: Default::default(),
+                servable_state: vec![],
+                protocol_version: PROTOCOL_VERSION,
+                client_version: None,
+                capabilities: None,
+                connection_established_time: Clock::instant(),
             });
             header_sync.syncing_peer.as_mut().unwrap().chain_info.height = highest_height;
         };
@@ -1469,6 +1535,7 @@ mod test {
                             current_height,
                             &signer,
                         )
+                        .unwrap()
                         .signature
                     })
                 })
@@ -1502,7 +1569,8 @@ mod test {
                 &*signers[3],
                 *last_block.header().next_bp_hash(),
                 block_merkle_tree.root(),
-            );
+            )
+            .unwrap();
             block_merkle_tree.insert(*block.hash());
 
             all_blocks.push(block);
@@ -1593,6 +1661,11 @@ mod test {
                 },
                 chain_info: Default::default(),
                 partial_edge_info: Default::default(),
+                servable_state: vec![],
+                protocol_version: PROTOCOL_VERSION,
+                client_version: None,
+                capabilities: None,
+                connection_established_time: Clock::instant(),
             })
             .collect()
     }
@@ -1700,4 +1773,48 @@ mod test {
             blocks.iter().take(MAX_BLOCK_REQUESTS).map(|b| *b.hash()).collect::<HashSet<_>>()
         );
     }
+
+    #[test]
+    fn test_peers_serving_state() {
+        let sync_hash = hash(b"sync");
+        let mut peer_infos = create_peer_infos(3);
+        for peer in peer_infos.iter_mut() {
+            peer.peer_info = PeerInfo::random();
+        }
+        peer_infos[0].servable_state = vec![ServableStateInfo { sync_hash, shard_ids: vec![0, 1] }];
+        peer_infos[1].servable_state =
+            vec![ServableStateInfo { sync_hash: hash(b"other"), shard_ids: vec![0] }];
+        peer_infos[2].servable_state = vec![ServableStateInfo { sync_hash, shard_ids: vec![1] }];
+
+        let serving = |shard_id| peers_serving_state(&peer_infos, sync_hash, shard_id);
+        assert_eq!(
+            serving(0),
+            vec![AccountOrPeerIdOrHash::PeerId(peer_infos[0].peer_info.id.clone())]
+        );
+        assert_eq!(
+            serving(1),
+            vec![
+                AccountOrPeerIdOrHash::PeerId(peer_infos[0].peer_info.id.clone()),
+                AccountOrPeerIdOrHash::PeerId(peer_infos[2].peer_info.id.clone()),
+            ]
+        );
+        assert!(serving(2).is_empty());
+    }
+
+    #[test]
+    fn test_part_request_limit() {
+        assert_eq!(part_request_limit(10, 0), 0);
+        assert_eq!(part_request_limit(10, 3), 4);
+        assert_eq!(part_request_limit(9, 3), 3);
+        assert_eq!(part_request_limit(1000, 2), MAX_STATE_PART_REQUEST);
+    }
+
+    #[test]
+    fn test_sampler_limited_per_target() {
+        let res = SamplerLimited::new(vec![1, 2, 3], vec![2, 0, 3]).collect::<Vec<_>>();
+        assert_eq!(res.len(), 5);
+        assert_eq!(res.iter().filter(|v| **v == 1).count(), 2);
+        assert_eq!(res.iter().filter(|v| **v == 2).count(), 0);
+        assert_eq!(res.iter().filter(|v| **v == 3).count(), 3);
+    }
 }
@@ -579,6 +579,11 @@ pub fn setup_mock_all_validators(
                                     archival: true,
                                 },
                                 partial_edge_info: PartialEdgeInfo::default(),
+                                servable_state: vec![],
+                                protocol_version: PROTOCOL_VERSION,
+                                client_version: None,
+                                capabilities: None,
+                                connection_established_time: Clock::instant(),
                             })
                             .collect();
                         let peers2 = peers.clone();
@@ -591,6 +596,7 @@ pub fn setup_mock_all_validators(
                             received_bytes_per_sec: 0,
                             known_producers: vec![],
                             peer_counter: 0,
+                            port_mapping: None,
                         };
                         client_addr.do_send(NetworkClientMessages::NetworkInfo(info));
                     }
@@ -1669,7 +1675,8 @@ pub fn create_chunk(
         &*client.validator_signer.as_ref().unwrap().clone(),
         *last_block.header().next_bp_hash(),
         block_merkle_tree.root(),
-    );
+    )
+    .unwrap();
     (chunk, merkle_paths, receipts, block)
 }
 
@@ -1711,6 +1718,7 @@ pub fn run_catchup(
                 msg.shard_uid,
                 &msg.state_root,
                 &msg.next_epoch_shard_layout,
+                &msg.progress,
             );
             if let Some((sync, _, _)) = client.catchup_state_syncs.get_mut(&msg.sync_hash) {
                 // We are doing catchup
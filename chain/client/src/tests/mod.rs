@@ -4,3 +4,4 @@ mod chunks_management;
 mod consensus;
 mod cross_shard_tx;
 mod query_client;
+mod simulation;
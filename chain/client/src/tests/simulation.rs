@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use crate::simulator::{LinkLatency, Simulator};
+use near_primitives::hash::CryptoHash;
+
+fn run_and_get_heads(seed: u64) -> Vec<CryptoHash> {
+    let mut sim = Simulator::builder().seed(seed).build();
+    sim.run_for(Duration::from_secs(5));
+    sim.clients.iter().map(|client| client.chain.head().unwrap().last_block_hash).collect()
+}
+
+/// Two runs with the same seed produce the same chain.
+#[test]
+fn test_simulation_is_deterministic() {
+    let heads = run_and_get_heads(42);
+    assert_ne!(heads[0], CryptoHash::default());
+    assert_eq!(heads, run_and_get_heads(42));
+}
+
+/// Without latencies, the blocks are produced as fast as the minimum block production delay
+/// allows, and no faster.
+#[test]
+fn test_simulation_block_production_delay() {
+    let mut sim = Simulator::builder()
+        .block_production_delays(Duration::from_millis(100), Duration::from_millis(200))
+        .latencies(Duration::ZERO, Duration::ZERO)
+        .build();
+    sim.run_for(Duration::from_secs(10));
+    let height = sim.head_height(0);
+    assert!(50 <= height && height <= 100, "{}", height);
+    assert!(sim.final_height(0) + 2 >= height);
+}
+
+/// The heights of a validator cut off from the others are skipped after the doomslug timeouts,
+/// and the others keep finalizing blocks.
+#[test]
+fn test_simulation_skips_unreachable_validator() {
+    let mut sim = Simulator::builder().validators(4).seed(7).build();
+    sim.set_links_of(1, LinkLatency::Down);
+    assert!(sim.run_until(|sim| sim.final_height(0) >= 20, Duration::from_secs(60)));
+    // Without approvals from the others, the validator can't produce blocks either.
+    assert_eq!(sim.head_height(1), 0);
+    for id in [0, 2, 3] {
+        assert!(sim.head_height(id) >= 20);
+    }
+}
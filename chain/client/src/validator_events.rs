@@ -0,0 +1,186 @@
+use std::collections::{HashSet, VecDeque};
+
+use actix::{Actor, Addr};
+use near_chain_configs::ValidatorEventsConfig;
+use near_primitives::types::{
+    AccountId, BlockHeight, EpochHeight, NumBlocks, ValidatorKickoutReason,
+};
+use near_primitives::views::{
+    CurrentEpochValidatorInfo, ValidatorEventKind, ValidatorEventView, ValidatorEventsView,
+};
+use near_telemetry::{TelemetryActor, TelemetryConfig};
+use tracing::warn;
+
+use crate::metrics;
+
+/// How many of the latest events are kept for `EXPERIMENTAL_validator_events`.
+const MAX_VALIDATOR_EVENTS: usize = 1024;
+
+/// Reports events when the validator run by the node misses blocks or chunks beyond the warning
+/// thresholds or is projected to be kicked out, so that operators learn about it before the end
+/// of the epoch.  Every kind of event is reported at most once per epoch.
+pub(crate) struct ValidatorEvents {
+    config: ValidatorEventsConfig,
+    events: VecDeque<ValidatorEventView>,
+    next_id: u64,
+    /// Epoch of the last check and kinds of events reported in it.
+    epoch_height: EpochHeight,
+    reported: HashSet<&'static str>,
+    /// Set if the last check was skipped as the epoch didn't span enough blocks yet.
+    insufficient_data: bool,
+    /// Posts the events to `webhook_url`, started lazily as it needs a running actix system.
+    webhook: Option<Addr<TelemetryActor>>,
+}
+
+impl ValidatorEvents {
+    pub fn new(config: ValidatorEventsConfig) -> Self {
+        Self {
+            config,
+            events: VecDeque::new(),
+            next_id: 0,
+            epoch_height: 0,
+            reported: HashSet::new(),
+            insufficient_data: false,
+            webhook: None,
+        }
+    }
+
+    /// Checks the production stats of the validator in the current epoch so far and the kickout
+    /// it would get if the epoch ended now.
+    pub fn check(
+        &mut self,
+        account_id: &AccountId,
+        epoch_height: EpochHeight,
+        block_height: BlockHeight,
+        stats: Option<&CurrentEpochValidatorInfo>,
+        kickout: Option<&ValidatorKickoutReason>,
+    ) {
+        self.insufficient_data = false;
+        if epoch_height != self.epoch_height {
+            self.epoch_height = epoch_height;
+            self.reported.clear();
+        }
+        let mut kinds = vec![];
+        if let Some(stats) = stats {
+            let (produced, expected) = (stats.num_produced_blocks, stats.num_expected_blocks);
+            if below_threshold(produced, expected, self.config.block_warning_threshold) {
+                kinds.push((
+                    "missing_blocks",
+                    ValidatorEventKind::MissingBlocks { produced, expected },
+                ));
+            }
+            let (produced, expected) = (stats.num_produced_chunks, stats.num_expected_chunks);
+            if below_threshold(produced, expected, self.config.chunk_warning_threshold) {
+                kinds.push((
+                    "missing_chunks",
+                    ValidatorEventKind::MissingChunks { produced, expected },
+                ));
+            }
+        }
+        if let Some(reason) = kickout {
+            kinds.push(("kickout", ValidatorEventKind::Kickout { reason: reason.clone() }));
+        }
+        for (label, kind) in kinds {
+            if !self.reported.insert(label) {
+                continue;
+            }
+            let event = ValidatorEventView {
+                id: self.next_id,
+                account_id: account_id.clone(),
+                epoch_height,
+                block_height,
+                kind,
+            };
+            self.next_id += 1;
+            self.record(label, event);
+        }
+    }
+
+    fn record(&mut self, label: &str, event: ValidatorEventView) {
+        warn!(target: "client", "Validator {} in epoch {} at height {}: {:?}", event.account_id, event.epoch_height, event.block_height, event.kind);
+        metrics::VALIDATOR_EVENTS_TOTAL.with_label_values(&[label]).inc();
+        if let Some(url) = &self.config.webhook_url {
+            let webhook = self.webhook.get_or_insert_with(|| {
+                TelemetryActor::new(TelemetryConfig { endpoints: vec![url.clone()] }).start()
+            });
+            near_telemetry::telemetry(webhook, serde_json::to_value(&event).unwrap());
+        }
+        if self.events.len() == MAX_VALIDATOR_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Records that the stats weren't checked as the epoch of the head is too short so far.
+    pub fn skip_check(&mut self) {
+        self.insufficient_data = true;
+    }
+
+    /// Events with id at least `from_id` which are still kept.
+    pub fn view(&self, from_id: u64) -> ValidatorEventsView {
+        ValidatorEventsView {
+            events: self.events.iter().filter(|event| event.id >= from_id).cloned().collect(),
+            insufficient_data: self.insufficient_data,
+        }
+    }
+}
+
+fn below_threshold(produced: NumBlocks, expected: NumBlocks, threshold: u8) -> bool {
+    produced * 100 < expected * threshold as NumBlocks
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::ValidatorEventsConfig;
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::types::ValidatorKickoutReason;
+    use near_primitives::views::{CurrentEpochValidatorInfo, ValidatorEventKind};
+
+    use super::ValidatorEvents;
+
+    fn stats(produced_blocks: u64, produced_chunks: u64) -> CurrentEpochValidatorInfo {
+        CurrentEpochValidatorInfo {
+            account_id: "test0".parse().unwrap(),
+            public_key: PublicKey::empty(KeyType::ED25519),
+            is_slashed: false,
+            stake: 100,
+            shards: vec![0],
+            num_produced_blocks: produced_blocks,
+            num_expected_blocks: 10,
+            num_produced_chunks: produced_chunks,
+            num_expected_chunks: 10,
+        }
+    }
+
+    #[test]
+    fn test_validator_events() {
+        let mut events = ValidatorEvents::new(ValidatorEventsConfig::default());
+        let account_id = "test0".parse().unwrap();
+        events.skip_check();
+        assert!(events.view(0).insufficient_data);
+        events.check(&account_id, 1, 10, Some(&stats(10, 8)), None);
+        assert!(events.view(0).events.is_empty());
+        assert!(!events.view(0).insufficient_data);
+
+        events.check(&account_id, 1, 11, Some(&stats(9, 7)), None);
+        let kinds: Vec<_> = events.view(0).events.into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ValidatorEventKind::MissingBlocks { produced: 9, expected: 10 },
+                ValidatorEventKind::MissingChunks { produced: 7, expected: 10 }
+            ]
+        );
+
+        // Reported once per epoch.
+        let kickout = ValidatorKickoutReason::NotEnoughBlocks { produced: 5, expected: 10 };
+        events.check(&account_id, 1, 12, Some(&stats(5, 7)), Some(&kickout));
+        let new_events = events.view(2).events;
+        assert_eq!(new_events.len(), 1);
+        assert_eq!(new_events[0].id, 2);
+        assert_eq!(new_events[0].kind, ValidatorEventKind::Kickout { reason: kickout });
+
+        events.check(&account_id, 2, 20, Some(&stats(0, 0)), None);
+        assert_eq!(events.view(3).events.len(), 2);
+    }
+}
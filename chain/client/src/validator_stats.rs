@@ -0,0 +1,153 @@
+use std::io;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::types::{AccountId, EpochHeight, EpochId, NumBlocks};
+use near_store::{DBCol, Store, VALIDATOR_STATS_KEY};
+use tracing::warn;
+
+use crate::approval_participation::{ApprovalInclusion, ApprovalParticipation};
+use crate::epoch_summary::uptime;
+
+/// Number of blocks and chunks the validator produced and was expected to produce.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Production {
+    pub num_produced_blocks: NumBlocks,
+    pub num_expected_blocks: NumBlocks,
+    pub num_produced_chunks: NumBlocks,
+    pub num_expected_chunks: NumBlocks,
+}
+
+impl Production {
+    fn add(&mut self, other: &Production) {
+        self.num_produced_blocks += other.num_produced_blocks;
+        self.num_expected_blocks += other.num_expected_blocks;
+        self.num_produced_chunks += other.num_produced_chunks;
+        self.num_expected_chunks += other.num_expected_chunks;
+    }
+
+    pub fn uptime(&self) -> f64 {
+        uptime(&[
+            (self.num_produced_blocks, self.num_expected_blocks),
+            (self.num_produced_chunks, self.num_expected_chunks),
+        ])
+    }
+}
+
+/// Production stats of the validator run by the node, kept in `ColBlockMisc` so that they
+/// outlive restarts: the production since the tracking start and the inclusion of its approvals
+/// in the current epoch.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, PartialEq)]
+pub(crate) struct ValidatorStats {
+    /// Height of the first epoch counted, once known.
+    tracking_start: Option<EpochHeight>,
+    /// Height of the last ended epoch counted.
+    last_counted_epoch: Option<EpochHeight>,
+    /// Production in the ended epochs since the tracking start.
+    production: Production,
+    approvals_epoch_id: EpochId,
+    approvals: Vec<(AccountId, ApprovalInclusion)>,
+}
+
+impl ValidatorStats {
+    /// Loads the stats saved before the restart.  They're started over if the configured
+    /// tracking start changed.
+    pub fn load(store: &Store, tracking_start: Option<EpochHeight>) -> Self {
+        let stats = store.get_ser::<Self>(DBCol::ColBlockMisc, VALIDATOR_STATS_KEY).unwrap_or_else(
+            |err| {
+                warn!(target: "client", "Failed to load the validator stats, starting over: {}", err);
+                None
+            },
+        );
+        match stats {
+            Some(stats)
+                if tracking_start.map_or(true, |start| stats.tracking_start == Some(start)) =>
+            {
+                stats
+            }
+            _ => Self { tracking_start, ..Self::default() },
+        }
+    }
+
+    pub fn save(&self, store: &Store) -> io::Result<()> {
+        let mut store_update = store.store_update();
+        store_update.set_ser(DBCol::ColBlockMisc, VALIDATOR_STATS_KEY, self)?;
+        store_update.commit()
+    }
+
+    /// Counts the production of the validator in the ended epoch, unless the epoch is before the
+    /// tracking start or already counted.
+    pub fn epoch_ended(&mut self, epoch_height: EpochHeight, production: Production) {
+        let start = *self.tracking_start.get_or_insert(epoch_height);
+        if epoch_height < start
+            || self.last_counted_epoch.map_or(false, |last| epoch_height <= last)
+        {
+            return;
+        }
+        self.last_counted_epoch = Some(epoch_height);
+        self.production.add(&production);
+    }
+
+    /// Height of the first epoch counted and the production since then, including the production
+    /// so far in the current epoch.
+    pub fn cumulative(
+        &self,
+        epoch_height: EpochHeight,
+        current: &Production,
+    ) -> (EpochHeight, Production) {
+        let start = self.tracking_start.unwrap_or(epoch_height);
+        let mut production = self.production;
+        if epoch_height >= start && self.last_counted_epoch.map_or(true, |last| epoch_height > last)
+        {
+            production.add(current);
+        }
+        (start, production)
+    }
+
+    pub fn save_approvals(&mut self, approval_participation: &ApprovalParticipation) {
+        let (epoch_id, approvals) = approval_participation.current_epoch();
+        self.approvals_epoch_id = epoch_id;
+        self.approvals = approvals;
+    }
+
+    pub fn restore_approvals(&self, approval_participation: &mut ApprovalParticipation) {
+        approval_participation.restore(self.approvals_epoch_id.clone(), self.approvals.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_store::test_utils::create_test_store;
+
+    use super::{Production, ValidatorStats};
+
+    fn production(produced: u64, expected: u64) -> Production {
+        Production {
+            num_produced_blocks: produced,
+            num_expected_blocks: expected,
+            num_produced_chunks: produced,
+            num_expected_chunks: expected,
+        }
+    }
+
+    #[test]
+    fn test_validator_stats() {
+        let store = create_test_store();
+        let mut stats = ValidatorStats::load(&store, Some(3));
+        stats.epoch_ended(2, production(0, 10));
+        stats.epoch_ended(3, production(9, 10));
+        // Epochs can be summarized again after a restart.
+        stats.epoch_ended(3, production(9, 10));
+        stats.save(&store).unwrap();
+
+        let mut stats = ValidatorStats::load(&store, None);
+        assert_eq!(stats.cumulative(4, &production(1, 10)), (3, production(10, 20)));
+        stats.epoch_ended(4, production(1, 10));
+        assert_eq!(stats.cumulative(4, &production(1, 10)), (3, production(10, 20)));
+        assert!((stats.cumulative(5, &production(0, 0)).1.uptime() - 0.5).abs() < 1e-9);
+        stats.save(&store).unwrap();
+
+        // A different tracking start starts the count over.
+        let stats = ValidatorStats::load(&store, Some(5));
+        assert_eq!(stats.cumulative(5, &production(3, 4)), (5, production(3, 4)));
+    }
+}
@@ -0,0 +1,88 @@
+use std::hash::Hash;
+
+use near_chain::types::ValidatorInfoIdentifier;
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::types::ShardId;
+use near_primitives::views::{BlockView, ChunkView, EpochValidatorInfo};
+
+use crate::metrics;
+
+/// How many views of each kind are kept.
+const VIEW_CACHE_SIZE: usize = 128;
+
+/// Chunk requests which can be answered from the cache.
+#[derive(Hash, PartialEq, Eq)]
+pub enum ChunkKey {
+    ChunkHash(ChunkHash),
+    BlockShard(CryptoHash, ShardId),
+}
+
+/// Recently served block, chunk and validator views, shared by all instances of
+/// ViewClientActor.
+///
+/// Requests by height or finality are resolved to block hashes against the current head before
+/// looking up the cache, and validators of the ongoing epoch are keyed by the hash of the header
+/// head.  The cached views never change for these keys, so a new head invalidates the entries
+/// which depend on it simply by making requests resolve to different keys.
+pub struct ViewCache {
+    blocks: lru::LruCache<CryptoHash, BlockView>,
+    chunks: lru::LruCache<ChunkKey, ChunkView>,
+    validators: lru::LruCache<ValidatorInfoIdentifier, EpochValidatorInfo>,
+}
+
+impl ViewCache {
+    pub fn new() -> Self {
+        Self {
+            blocks: lru::LruCache::new(VIEW_CACHE_SIZE),
+            chunks: lru::LruCache::new(VIEW_CACHE_SIZE),
+            validators: lru::LruCache::new(VIEW_CACHE_SIZE),
+        }
+    }
+
+    pub fn get_block(&mut self, block_hash: &CryptoHash) -> Option<BlockView> {
+        lookup(&mut self.blocks, "block", block_hash)
+    }
+
+    pub fn put_block(&mut self, block_hash: CryptoHash, view: BlockView) {
+        self.blocks.put(block_hash, view);
+    }
+
+    pub fn get_chunk(&mut self, key: &ChunkKey) -> Option<ChunkView> {
+        lookup(&mut self.chunks, "chunk", key)
+    }
+
+    pub fn put_chunk(&mut self, key: ChunkKey, view: ChunkView) {
+        self.chunks.put(key, view);
+    }
+
+    pub fn get_validators(
+        &mut self,
+        identifier: &ValidatorInfoIdentifier,
+    ) -> Option<EpochValidatorInfo> {
+        lookup(&mut self.validators, "validators", identifier)
+    }
+
+    pub fn put_validators(
+        &mut self,
+        identifier: ValidatorInfoIdentifier,
+        view: EpochValidatorInfo,
+    ) {
+        self.validators.put(identifier, view);
+    }
+}
+
+fn lookup<K: Hash + Eq, V: Clone>(
+    cache: &mut lru::LruCache<K, V>,
+    kind: &str,
+    key: &K,
+) -> Option<V> {
+    let view = cache.get(key).cloned();
+    let counter = if view.is_some() {
+        &metrics::VIEW_CACHE_HITS_TOTAL
+    } else {
+        &metrics::VIEW_CACHE_MISSES_TOTAL
+    };
+    counter.with_label_values(&[kind]).inc();
+    view
+}
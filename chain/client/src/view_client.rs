@@ -5,12 +5,15 @@ use near_primitives::time::Clock;
 use std::cmp::Ordering;
 use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
+use std::ops::Range;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use actix::{Actor, Addr, Handler, SyncArbiter, SyncContext};
+use borsh::BorshSerialize;
 use tracing::{debug, error, info, trace, warn};
 
+use near_chain::gas_attribution;
 use near_chain::types::ValidatorInfoIdentifier;
 use near_chain::{
     get_epoch_block_producers_view, Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode,
@@ -18,46 +21,61 @@ use near_chain::{
 };
 use near_chain_configs::{ClientConfig, ProtocolConfigView};
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockHash, GetBlockProof, GetBlockProofError,
-    GetBlockProofResponse, GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome,
-    GetExecutionOutcomeError, GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError,
-    GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigError, GetReceipt,
-    GetReceiptError, GetStateChangesError, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
-    TxStatus, TxStatusError,
+    Error, GetAccountHistory, GetAccountHistoryError, GetAccountsByCodeHash,
+    GetAccountsByCodeHashError, GetBlock, GetBlockError, GetBlockHash, GetBlockProof,
+    GetBlockProofError, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunkError,
+    GetDelayedReceipts, GetDelayedReceiptsError, GetExecutionOutcome, GetExecutionOutcomeError,
+    GetExecutionOutcomesForBlock, GetGasAttribution, GetGasAttributionError, GetGasPrice,
+    GetGasPriceError, GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigDiff,
+    GetProtocolConfigError, GetReceipt, GetReceiptError, GetStateChangesError,
+    GetStateChangesWithCauseInBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetValidatorInfoError, Query, QueryError, TraceReceipt, TraceReceiptError, TxStatus,
+    TxStatusError,
 };
 use near_network::types::{NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest};
 #[cfg(feature = "test_features")]
 use near_network_primitives::types::NetworkAdversarialMessage;
 use near_network_primitives::types::{
-    NetworkViewClientMessages, NetworkViewClientResponses, ReasonForBan, StateResponseInfo,
-    StateResponseInfoV1, StateResponseInfoV2,
+    NetworkViewClientMessages, NetworkViewClientResponses, ReasonForBan, ServableStateInfo,
+    StateResponseInfo, StateResponseInfoV1, StateResponseInfoV2,
 };
+use near_performance_metrics::actor_metrics::{spawn_mailbox_probes, MailboxProbe};
 use near_performance_metrics_macros::{perf, perf_with_debug};
 use near_primitives::block::{Block, BlockHeader, GenesisId, Tip};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, PartialMerkleTree};
 use near_primitives::network::AnnounceAccount;
+use near_primitives::receipt_trace;
+use near_primitives::serialize::to_base;
 use near_primitives::sharding::ShardChunk;
 use near_primitives::syncing::{
     ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV1,
-    ShardStateSyncResponseV2,
+    ShardStateSyncResponseV2, StatePartKey,
 };
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochId, EpochReference, Finality,
-    MaybeBlockId, ShardId, TransactionOrReceiptId,
+    MaybeBlockId, ProtocolVersion, ProtocolVersionReference, ShardId, TransactionOrReceiptId,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView,
-    StateChangesView,
+    AccountHistoryEntryView, AccountHistoryView, AccountsByCodeHashView, BlockView, ChunkView,
+    DelayedReceiptView, DelayedReceiptsView, EpochRewardProjectionView, EpochValidatorInfo,
+    EpochValidatorProjectionView, ExecutionOutcomeWithIdView, FinalExecutionOutcomeView,
+    FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasAttributionView, GasPriceView,
+    HeightProducersView, LightClientBlockView, MaintenanceWindowsView, ProducerScheduleView,
+    ProtocolVersionVotesView, QueryRequest, QueryResponse, QueryResponseKind, ReceiptTraceView,
+    ReceiptView, RuntimeConfigDiffView, StateChangesKindsView, StateChangesView,
 };
+use near_store::ColStateParts;
 
+use crate::metrics;
+use crate::state_parts_cache::{start_state_parts_precompute, StatePartsCache};
+use crate::sync::StateSync;
+use crate::view_cache::{ChunkKey, ViewCache};
 use crate::{
-    sync, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
+    sync, GetChunk, GetExecutionOutcomeResponse, GetMaintenanceWindows, GetNextLightClientBlock,
+    GetProducerSchedule, GetProtocolVersionVotes, GetRewardProjection, GetStateChanges,
+    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, GetValidatorProjection,
 };
 
 /// Max number of queries that we keep.
@@ -67,6 +85,16 @@ const REQUEST_WAIT_TIME: u64 = 1000;
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
+/// Number of delayed receipts returned by default when paging through the queue.
+const DELAYED_RECEIPTS_DEFAULT_LIMIT: u64 = 100;
+/// Max number of delayed receipts returned at once.
+const DELAYED_RECEIPTS_MAX_LIMIT: u64 = 1000;
+
+/// Number of account history entries returned by default when paging through the history.
+const ACCOUNT_HISTORY_DEFAULT_LIMIT: u64 = 100;
+/// Max number of account history entries returned at once.
+const ACCOUNT_HISTORY_MAX_LIMIT: u64 = 1000;
+
 /// Request and response manager across all instances of ViewClientActor.
 pub struct ViewClientRequestManager {
     /// Transaction query that needs to be forwarded to other shards
@@ -101,6 +129,8 @@ pub struct ViewClientActor {
     network_adapter: Arc<dyn PeerManagerAdapter>,
     pub config: ClientConfig,
     request_manager: Arc<RwLock<ViewClientRequestManager>>,
+    view_cache: Arc<Mutex<ViewCache>>,
+    state_parts_cache: Arc<Mutex<StatePartsCache>>,
     state_request_cache: Arc<Mutex<VecDeque<Instant>>>,
 }
 
@@ -127,6 +157,8 @@ impl ViewClientActor {
         network_adapter: Arc<dyn PeerManagerAdapter>,
         config: ClientConfig,
         request_manager: Arc<RwLock<ViewClientRequestManager>>,
+        view_cache: Arc<Mutex<ViewCache>>,
+        state_parts_cache: Arc<Mutex<StatePartsCache>>,
         #[cfg(feature = "test_features")] adv: Arc<RwLock<AdversarialControls>>,
     ) -> Result<Self, Error> {
         // TODO: should we create shared ChainStore that is passed to both Client and ViewClient?
@@ -144,6 +176,8 @@ impl ViewClientActor {
             network_adapter,
             config,
             request_manager,
+            view_cache,
+            state_parts_cache,
             state_request_cache: Arc::new(Mutex::new(VecDeque::default())),
         })
     }
@@ -195,6 +229,18 @@ impl ViewClientActor {
         }
     }
 
+    fn get_protocol_version_by_reference(
+        &self,
+        reference: ProtocolVersionReference,
+    ) -> Result<ProtocolVersion, near_chain::Error> {
+        match reference {
+            ProtocolVersionReference::EpochId(epoch_id) => {
+                self.runtime_adapter.get_epoch_protocol_version(&epoch_id)
+            }
+            ProtocolVersionReference::ProtocolVersion(protocol_version) => Ok(protocol_version),
+        }
+    }
+
     fn handle_query(&mut self, msg: Query) -> Result<QueryResponse, QueryError> {
         let header = match msg.block_reference {
             BlockReference::BlockId(BlockId::Height(block_height)) => {
@@ -246,6 +292,7 @@ impl ViewClientActor {
             QueryRequest::ViewAccessKeyList { account_id, .. } => account_id,
             QueryRequest::CallFunction { account_id, .. } => account_id,
             QueryRequest::ViewCode { account_id, .. } => account_id,
+            QueryRequest::ViewAbi { account_id, .. } => account_id,
         };
         let shard_id =
             self.runtime_adapter
@@ -313,6 +360,24 @@ impl ViewClientActor {
                     block_height,
                     block_hash,
                 } => QueryError::NoContractCode { contract_account_id, block_height, block_hash },
+                near_chain::near_chain_primitives::error::QueryError::NoContractAbi {
+                    contract_account_id,
+                    block_height,
+                    block_hash,
+                } => QueryError::NoContractAbi { contract_account_id, block_height, block_hash },
+                near_chain::near_chain_primitives::error::QueryError::AbiHashMismatch {
+                    contract_account_id,
+                    abi_hash,
+                    declared_hash,
+                    block_height,
+                    block_hash,
+                } => QueryError::AbiHashMismatch {
+                    contract_account_id,
+                    abi_hash,
+                    declared_hash,
+                    block_height,
+                    block_hash,
+                },
                 near_chain::near_chain_primitives::error::QueryError::UnknownAccessKey {
                     public_key,
                     block_height,
@@ -336,6 +401,18 @@ impl ViewClientActor {
                     block_height,
                     block_hash,
                 },
+                near_chain::near_chain_primitives::error::QueryError::QueryTimeout {
+                    requested_account_id,
+                    block_height,
+                    block_hash,
+                } => {
+                    metrics::VIEW_QUERY_TIMEOUTS_TOTAL.inc();
+                    QueryError::QueryTimeout {
+                        contract_account_id: requested_account_id,
+                        block_height,
+                        block_hash,
+                    }
+                }
             }),
         }
     }
@@ -519,6 +596,70 @@ impl ViewClientActor {
         cache.push_back(now);
         true
     }
+
+    /// Looks up the state part in memory and on disk before generating it, and records the
+    /// latency by where the part was found.
+    fn get_state_response_part(
+        &mut self,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<Vec<u8>, near_chain::Error> {
+        let start = Clock::instant();
+        let cached = self
+            .state_parts_cache
+            .lock()
+            .expect(POISONED_LOCK_ERR)
+            .get(sync_hash, shard_id, part_id);
+        let (part, source) = match cached {
+            Some(part) => (part, "memory"),
+            None => {
+                let key = StatePartKey(sync_hash, shard_id, part_id).try_to_vec()?;
+                let source = if self.chain.store().store().exists(ColStateParts, &key)? {
+                    "disk"
+                } else {
+                    "generated"
+                };
+                let part = self.chain.get_state_response_part(shard_id, part_id, sync_hash)?;
+                self.state_parts_cache.lock().expect(POISONED_LOCK_ERR).put(
+                    sync_hash,
+                    shard_id,
+                    part_id,
+                    part.clone(),
+                );
+                (part, source)
+            }
+        };
+        metrics::STATE_PART_SERVE_LATENCY
+            .with_label_values(&[source])
+            .observe((Clock::instant() - start).as_secs_f64());
+        Ok(part)
+    }
+
+    /// Returns the shards of which this node has the state for the sync hash of the current
+    /// epoch, i.e. the ones it has the state of as of the last block of the previous epoch.
+    fn get_servable_state(&mut self) -> Result<Vec<ServableStateInfo>, near_chain::Error> {
+        let head = self.chain.head()?;
+        let sync_hash =
+            StateSync::get_epoch_start_sync_hash(&mut self.chain, &head.last_block_hash)?;
+        if &sync_hash == self.chain.genesis().hash() {
+            return Ok(vec![]);
+        }
+        let sync_header = self.chain.get_block_header(&sync_hash)?.clone();
+        let sync_prev_header = self.chain.get_block_header(sync_header.prev_hash())?.clone();
+        let num_shards = self.runtime_adapter.num_shards(sync_prev_header.epoch_id())?;
+        let shard_ids = (0..num_shards)
+            .filter(|&shard_id| {
+                self.runtime_adapter.cares_about_shard(
+                    self.validator_account_id.as_ref(),
+                    sync_prev_header.prev_hash(),
+                    shard_id,
+                    true,
+                )
+            })
+            .collect();
+        Ok(vec![ServableStateInfo { sync_hash, shard_ids }])
+    }
 }
 
 impl Actor for ViewClientActor {
@@ -534,39 +675,49 @@ impl Handler<Query> for ViewClientActor {
     }
 }
 
+impl Handler<MailboxProbe> for ViewClientActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: MailboxProbe, _: &mut Self::Context) {
+        msg.observe();
+    }
+}
+
 /// Handles retrieving block from the chain.
 impl Handler<GetBlock> for ViewClientActor {
     type Result = Result<BlockView, GetBlockError>;
 
     #[perf]
     fn handle(&mut self, msg: GetBlock, _: &mut Self::Context) -> Self::Result {
-        let block = match msg.0 {
-            BlockReference::Finality(finality) => {
-                let block_hash = self.get_block_hash_by_finality(&finality)?;
-                self.chain.get_block(&block_hash).map(Clone::clone)
-            }
+        let block_hash = match msg.0 {
+            BlockReference::Finality(finality) => self.get_block_hash_by_finality(&finality)?,
             BlockReference::BlockId(BlockId::Height(height)) => {
-                self.chain.get_block_by_height(height).map(Clone::clone)
-            }
-            BlockReference::BlockId(BlockId::Hash(hash)) => {
-                self.chain.get_block(&hash).map(Clone::clone)
+                self.chain.get_block_hash_by_height(height)?
             }
+            BlockReference::BlockId(BlockId::Hash(hash)) => hash,
             BlockReference::SyncCheckpoint(sync_checkpoint) => {
                 if let Some(block_hash) =
                     self.get_block_hash_by_sync_checkpoint(&sync_checkpoint)?
                 {
-                    self.chain.get_block(&block_hash).map(Clone::clone)
+                    block_hash
                 } else {
                     return Err(GetBlockError::NotSyncedYet);
                 }
             }
-        }?;
+        };
+        if let Some(view) = self.view_cache.lock().expect(POISONED_LOCK_ERR).get_block(&block_hash)
+        {
+            return Ok(view);
+        }
+        let block = self.chain.get_block(&block_hash)?.clone();
 
         let block_author = self
             .runtime_adapter
             .get_block_producer(block.header().epoch_id(), block.header().height())?;
 
-        Ok(BlockView::from_author_block(block_author, block))
+        let view = BlockView::from_author_block(block_author, block);
+        self.view_cache.lock().expect(POISONED_LOCK_ERR).put_block(block_hash, view.clone());
+        Ok(view)
     }
 }
 
@@ -634,15 +785,21 @@ impl Handler<GetChunk> for ViewClientActor {
             })
         };
 
-        let chunk = match msg {
-            GetChunk::ChunkHash(chunk_hash) => self.chain.get_chunk(&chunk_hash)?.clone(),
-            GetChunk::BlockHash(block_hash, shard_id) => {
-                let block = self.chain.get_block(&block_hash)?.clone();
-                get_chunk_from_block(block, shard_id, &mut self.chain)?
-            }
+        let key = match msg {
+            GetChunk::ChunkHash(chunk_hash) => ChunkKey::ChunkHash(chunk_hash),
+            GetChunk::BlockHash(block_hash, shard_id) => ChunkKey::BlockShard(block_hash, shard_id),
             GetChunk::Height(height, shard_id) => {
-                let block = self.chain.get_block_by_height(height)?.clone();
-                get_chunk_from_block(block, shard_id, &mut self.chain)?
+                ChunkKey::BlockShard(self.chain.get_block_hash_by_height(height)?, shard_id)
+            }
+        };
+        if let Some(view) = self.view_cache.lock().expect(POISONED_LOCK_ERR).get_chunk(&key) {
+            return Ok(view);
+        }
+        let chunk = match &key {
+            ChunkKey::ChunkHash(chunk_hash) => self.chain.get_chunk(chunk_hash)?.clone(),
+            ChunkKey::BlockShard(block_hash, shard_id) => {
+                let block = self.chain.get_block(block_hash)?.clone();
+                get_chunk_from_block(block, *shard_id, &mut self.chain)?
             }
         };
 
@@ -655,7 +812,9 @@ impl Handler<GetChunk> for ViewClientActor {
             chunk_inner.shard_id(),
         )?;
 
-        Ok(ChunkView::from_author_chunk(author, chunk))
+        let view = ChunkView::from_author_chunk(author, chunk);
+        self.view_cache.lock().expect(POISONED_LOCK_ERR).put_chunk(key, view.clone());
+        Ok(view)
     }
 }
 
@@ -706,12 +865,185 @@ impl Handler<GetValidatorInfo> for ViewClientActor {
                 ValidatorInfoIdentifier::BlockHash(self.chain.header_head()?.last_block_hash)
             }
         };
+        if let Some(view) =
+            self.view_cache.lock().expect(POISONED_LOCK_ERR).get_validators(&epoch_identifier)
+        {
+            return Ok(view);
+        }
+        let view = self.runtime_adapter.get_validator_info(epoch_identifier.clone())?;
+        self.view_cache
+            .lock()
+            .expect(POISONED_LOCK_ERR)
+            .put_validators(epoch_identifier, view.clone());
+        Ok(view)
+    }
+}
+
+impl Handler<GetValidatorProjection> for ViewClientActor {
+    type Result = Result<EpochValidatorProjectionView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(&mut self, _msg: GetValidatorProjection, _: &mut Self::Context) -> Self::Result {
+        // use header head because this is latest from the perspective of epoch manager
+        let last_block_hash = self.chain.header_head()?.last_block_hash;
+        self.runtime_adapter
+            .get_validator_projection(&last_block_hash)
+            .map_err(GetValidatorInfoError::from)
+    }
+}
+
+impl Handler<GetProtocolVersionVotes> for ViewClientActor {
+    type Result = Result<ProtocolVersionVotesView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(&mut self, _msg: GetProtocolVersionVotes, _: &mut Self::Context) -> Self::Result {
+        // use header head because this is latest from the perspective of epoch manager
+        let last_block_hash = self.chain.header_head()?.last_block_hash;
         self.runtime_adapter
-            .get_validator_info(epoch_identifier)
+            .get_protocol_version_votes(&last_block_hash)
             .map_err(GetValidatorInfoError::from)
     }
 }
 
+impl Handler<GetRewardProjection> for ViewClientActor {
+    type Result = Result<EpochRewardProjectionView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(&mut self, _msg: GetRewardProjection, _: &mut Self::Context) -> Self::Result {
+        // use header head because this is latest from the perspective of epoch manager
+        let last_block_hash = self.chain.header_head()?.last_block_hash;
+        self.runtime_adapter
+            .get_reward_projection(&last_block_hash)
+            .map_err(GetValidatorInfoError::from)
+    }
+}
+
+impl Handler<GetMaintenanceWindows> for ViewClientActor {
+    type Result = Result<MaintenanceWindowsView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetMaintenanceWindows, _: &mut Self::Context) -> Self::Result {
+        let head = self.chain.head()?;
+        let next_epoch_start_height =
+            self.runtime_adapter.get_epoch_start_height(&head.last_block_hash)?
+                + self.chain.epoch_length;
+        let num_shards = self.runtime_adapter.num_shards(&head.epoch_id)?;
+        let account_id = msg.account_id;
+        let windows = maintenance_windows(head.height + 1..next_epoch_start_height, |height| {
+            if self.runtime_adapter.get_block_producer(&head.epoch_id, height)? == account_id {
+                return Ok(true);
+            }
+            for shard_id in 0..num_shards {
+                if self.runtime_adapter.get_chunk_producer(&head.epoch_id, height, shard_id)?
+                    == account_id
+                {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })?;
+        Ok(MaintenanceWindowsView {
+            account_id,
+            head_height: head.height,
+            next_epoch_start_height,
+            windows,
+        })
+    }
+}
+
+impl Handler<GetProducerSchedule> for ViewClientActor {
+    type Result = Result<ProducerScheduleView, GetValidatorInfoError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetProducerSchedule, _: &mut Self::Context) -> Self::Result {
+        // use header head because this is latest from the perspective of epoch manager
+        let tip = self.chain.header_head()?;
+        let current_start_height =
+            self.runtime_adapter.get_epoch_start_height(&tip.last_block_hash)?;
+        let (epoch_id, start_height) = match msg.epoch_reference {
+            EpochReference::Latest => (tip.epoch_id, current_start_height),
+            EpochReference::EpochId(id) if id == tip.epoch_id => (id, current_start_height),
+            EpochReference::EpochId(id) if id == tip.next_epoch_id => {
+                (id, current_start_height + self.chain.epoch_length)
+            }
+            EpochReference::EpochId(id) => {
+                let block_hash = self.get_block_hash_in_past_epoch(&id)?;
+                (id, self.runtime_adapter.get_epoch_start_height(&block_hash)?)
+            }
+            EpochReference::BlockId(block_id) => {
+                let header = match block_id {
+                    BlockId::Hash(h) => self.chain.get_block_header(&h)?.clone(),
+                    BlockId::Height(h) => self.chain.get_header_by_height(h)?.clone(),
+                };
+                let start_height = self.runtime_adapter.get_epoch_start_height(header.hash())?;
+                (header.epoch_id().clone(), start_height)
+            }
+        };
+        let num_shards = self.runtime_adapter.num_shards(&epoch_id)?;
+        let mut heights = vec![];
+        for height in start_height..start_height + self.chain.epoch_length {
+            let block_producer = self.runtime_adapter.get_block_producer(&epoch_id, height)?;
+            let chunk_producers = (0..num_shards)
+                .map(|shard_id| {
+                    self.runtime_adapter.get_chunk_producer(&epoch_id, height, shard_id)
+                })
+                .collect::<Result<_, _>>()?;
+            heights.push(HeightProducersView { height, block_producer, chunk_producers });
+        }
+        Ok(ProducerScheduleView { epoch_id, start_height, heights })
+    }
+}
+
+impl ViewClientActor {
+    /// Returns the hash of a block on the canonical chain in the given epoch, which must have
+    /// ended.
+    fn get_block_hash_in_past_epoch(
+        &mut self,
+        epoch_id: &EpochId,
+    ) -> Result<CryptoHash, near_chain::Error> {
+        if self.chain.genesis().epoch_id() == epoch_id {
+            return Ok(*self.chain.genesis().hash());
+        }
+        // The id of an epoch is the hash of the last block of the epoch before the previous one,
+        // and the epoch starts at least `epoch_length` heights after the previous one.
+        let prev_epoch_block_hash = *self.chain.mut_store().get_next_block_hash(&epoch_id.0)?;
+        let prev_epoch_start_height = self.chain.get_block_header(&prev_epoch_block_hash)?.height();
+        let epoch_length = self.chain.epoch_length;
+        for height in
+            prev_epoch_start_height + epoch_length..=prev_epoch_start_height + 2 * epoch_length
+        {
+            if let Ok(header) = self.chain.get_header_by_height(height) {
+                if header.epoch_id() == epoch_id {
+                    return Ok(*header.hash());
+                }
+            }
+        }
+        Err(ErrorKind::EpochOutOfBounds(epoch_id.clone()).into())
+    }
+}
+
+/// Splits `heights` into the maximal ranges of heights at which `is_producing` is false.
+fn maintenance_windows(
+    heights: Range<BlockHeight>,
+    mut is_producing: impl FnMut(BlockHeight) -> Result<bool, near_chain::Error>,
+) -> Result<Vec<Range<BlockHeight>>, near_chain::Error> {
+    let mut windows = vec![];
+    let mut window_start = None;
+    for height in heights.clone() {
+        if is_producing(height)? {
+            if let Some(start) = window_start.take() {
+                windows.push(start..height);
+            }
+        } else if window_start.is_none() {
+            window_start = Some(height);
+        }
+    }
+    if let Some(start) = window_start {
+        windows.push(start..heights.end);
+    }
+    Ok(windows)
+}
+
 impl Handler<GetValidatorOrdered> for ViewClientActor {
     type Result = Result<Vec<ValidatorStakeView>, GetValidatorInfoError>;
 
@@ -975,6 +1307,229 @@ impl Handler<GetReceipt> for ViewClientActor {
     }
 }
 
+impl Handler<TraceReceipt> for ViewClientActor {
+    type Result = Result<ReceiptTraceView, TraceReceiptError>;
+
+    #[perf]
+    fn handle(&mut self, msg: TraceReceipt, _: &mut Self::Context) -> Self::Result {
+        if !cfg!(feature = "receipt_trace") {
+            return Err(TraceReceiptError::NotEnabled);
+        }
+        if !self.config.archive {
+            return Err(TraceReceiptError::NotArchival);
+        }
+        if let Err(err) = self.chain.get_execution_outcome(&msg.receipt_id) {
+            return Err(match err.kind() {
+                ErrorKind::DBNotFoundErr(_) => TraceReceiptError::UnknownReceipt(msg.receipt_id),
+                _ => err.into(),
+            });
+        }
+        let chain = &mut self.chain;
+        let (result, events) =
+            receipt_trace::trace_receipt(msg.receipt_id, || chain.replay_receipt(&msg.receipt_id));
+        let (block_hash, shard_id, outcome) = result?;
+        Ok(ReceiptTraceView {
+            receipt_id: msg.receipt_id,
+            block_hash,
+            shard_id,
+            outcome: outcome.outcome.into(),
+            events: events.unwrap_or_default(),
+        })
+    }
+}
+
+impl Handler<GetDelayedReceipts> for ViewClientActor {
+    type Result = Result<DelayedReceiptsView, GetDelayedReceiptsError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetDelayedReceipts, _: &mut Self::Context) -> Self::Result {
+        let block_hash = match msg.block_reference {
+            BlockReference::Finality(finality) => self.get_block_hash_by_finality(&finality)?,
+            BlockReference::BlockId(BlockId::Height(height)) => {
+                *self.chain.get_header_by_height(height)?.hash()
+            }
+            BlockReference::BlockId(BlockId::Hash(hash)) => hash,
+            BlockReference::SyncCheckpoint(sync_checkpoint) => {
+                match self.get_block_hash_by_sync_checkpoint(&sync_checkpoint)? {
+                    Some(block_hash) => block_hash,
+                    None => {
+                        return Err(GetDelayedReceiptsError::UnknownBlock(format!(
+                            "{:?}",
+                            sync_checkpoint
+                        )))
+                    }
+                }
+            }
+        };
+        let epoch_id = self.chain.get_block_header(&block_hash)?.epoch_id().clone();
+        if msg.shard_id >= self.runtime_adapter.num_shards(&epoch_id)? {
+            return Err(GetDelayedReceiptsError::InvalidShardId(msg.shard_id));
+        }
+        let shard_uid = self.runtime_adapter.shard_id_to_uid(msg.shard_id, &epoch_id)?;
+        let state_root = match self.chain.get_chunk_extra(&block_hash, &shard_uid) {
+            Ok(chunk_extra) => *chunk_extra.state_root(),
+            Err(err) => {
+                return Err(match err.kind() {
+                    ErrorKind::DBNotFoundErr(_) => {
+                        GetDelayedReceiptsError::UnavailableShard(msg.shard_id)
+                    }
+                    _ => err.into(),
+                })
+            }
+        };
+        let limit =
+            msg.limit.unwrap_or(DELAYED_RECEIPTS_DEFAULT_LIMIT).min(DELAYED_RECEIPTS_MAX_LIMIT);
+        let (indices, receipts) = self.runtime_adapter.get_delayed_receipts(
+            shard_uid,
+            &state_root,
+            msg.from_index,
+            limit,
+        )?;
+        Ok(DelayedReceiptsView {
+            block_hash,
+            shard_id: msg.shard_id,
+            first_index: indices.first_index,
+            next_available_index: indices.next_available_index,
+            receipts: receipts
+                .into_iter()
+                .map(|(index, receipt)| DelayedReceiptView { index, receipt: receipt.into() })
+                .collect(),
+        })
+    }
+}
+
+impl Handler<GetAccountsByCodeHash> for ViewClientActor {
+    type Result = Result<AccountsByCodeHashView, GetAccountsByCodeHashError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetAccountsByCodeHash, _: &mut Self::Context) -> Self::Result {
+        let block_hash = match msg.block_reference {
+            BlockReference::Finality(finality) => self.get_block_hash_by_finality(&finality)?,
+            BlockReference::BlockId(BlockId::Height(height)) => {
+                *self.chain.get_header_by_height(height)?.hash()
+            }
+            BlockReference::BlockId(BlockId::Hash(hash)) => hash,
+            BlockReference::SyncCheckpoint(sync_checkpoint) => {
+                match self.get_block_hash_by_sync_checkpoint(&sync_checkpoint)? {
+                    Some(block_hash) => block_hash,
+                    None => {
+                        return Err(GetAccountsByCodeHashError::UnknownBlock(format!(
+                            "{:?}",
+                            sync_checkpoint
+                        )))
+                    }
+                }
+            }
+        };
+        let header = self.chain.get_block_header(&block_hash)?.clone();
+        let candidates =
+            near_store::get_contract_accounts(&self.runtime_adapter.get_store(), &msg.code_hash)
+                .map_err(|err| GetAccountsByCodeHashError::IOError(err.to_string()))?;
+        // The index keeps the accounts the code was ever deployed to, so the current code of each
+        // of them is checked.
+        let mut account_ids = vec![];
+        for account_id in candidates {
+            let shard_id =
+                self.runtime_adapter.account_id_to_shard_id(&account_id, header.epoch_id())?;
+            let shard_uid = self.runtime_adapter.shard_id_to_uid(shard_id, header.epoch_id())?;
+            let state_root = match self.chain.get_chunk_extra(&block_hash, &shard_uid) {
+                Ok(chunk_extra) => *chunk_extra.state_root(),
+                Err(err) => match err.kind() {
+                    ErrorKind::DBNotFoundErr(_) => continue,
+                    _ => return Err(err.into()),
+                },
+            };
+            let response = self.runtime_adapter.query(
+                shard_uid,
+                &state_root,
+                header.height(),
+                header.raw_timestamp(),
+                header.prev_hash(),
+                header.hash(),
+                header.epoch_id(),
+                &QueryRequest::ViewAccount { account_id: account_id.clone() },
+            );
+            match response {
+                Ok(QueryResponse { kind: QueryResponseKind::ViewAccount(account), .. }) => {
+                    if account.code_hash == msg.code_hash {
+                        account_ids.push(account_id);
+                    }
+                }
+                Ok(_) => {}
+                // Deleted since the code was deployed.
+                Err(near_chain::near_chain_primitives::error::QueryError::UnknownAccount {
+                    ..
+                }) => {}
+                Err(err) => return Err(GetAccountsByCodeHashError::Unreachable(err.to_string())),
+            }
+        }
+        Ok(AccountsByCodeHashView {
+            block_hash,
+            block_height: header.height(),
+            code_hash: msg.code_hash,
+            account_ids,
+        })
+    }
+}
+
+impl Handler<GetAccountHistory> for ViewClientActor {
+    type Result = Result<AccountHistoryView, GetAccountHistoryError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetAccountHistory, _: &mut Self::Context) -> Self::Result {
+        if !self.config.account_history_index {
+            return Err(GetAccountHistoryError::Disabled);
+        }
+        let limit =
+            msg.limit.unwrap_or(ACCOUNT_HISTORY_DEFAULT_LIMIT).min(ACCOUNT_HISTORY_MAX_LIMIT);
+        let page = near_store::get_account_history(
+            &self.runtime_adapter.get_store(),
+            &msg.account_id,
+            msg.cursor.as_deref(),
+            limit as usize,
+        )
+        .map_err(|err| GetAccountHistoryError::IOError(err.to_string()))?;
+        // The index has the entries of the blocks on forks as well, which are skipped, so a page
+        // may have fewer entries than the limit even if there are more.
+        let mut entries = vec![];
+        for entry in page.entries {
+            match self.chain.mut_store().get_block_hash_by_height(entry.block_height) {
+                Ok(block_hash) if block_hash == entry.block_hash => {}
+                Ok(_) => continue,
+                Err(err) => match err.kind() {
+                    ErrorKind::DBNotFoundErr(_) => continue,
+                    _ => return Err(err.into()),
+                },
+            }
+            entries.push(AccountHistoryEntryView {
+                block_height: entry.block_height,
+                block_hash: entry.block_hash,
+                shard_id: entry.shard_id,
+                id: entry.id,
+                kind: entry.kind,
+            });
+        }
+        Ok(AccountHistoryView {
+            account_id: msg.account_id,
+            entries,
+            next_cursor: page.next_cursor.map(to_base),
+        })
+    }
+}
+
+impl Handler<GetGasAttribution> for ViewClientActor {
+    type Result = Result<GasAttributionView, GetGasAttributionError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetGasAttribution, _: &mut Self::Context) -> Self::Result {
+        if self.config.gas_attribution_top_k.is_none() {
+            return Err(GetGasAttributionError::Disabled);
+        }
+        gas_attribution::get_gas_attribution(msg.epoch_id.as_ref())
+            .ok_or(GetGasAttributionError::UnknownEpoch)
+    }
+}
+
 impl Handler<GetBlockProof> for ViewClientActor {
     type Result = Result<GetBlockProofResponse, GetBlockProofError>;
 
@@ -1022,6 +1577,23 @@ impl Handler<GetProtocolConfig> for ViewClientActor {
     }
 }
 
+impl Handler<GetProtocolConfigDiff> for ViewClientActor {
+    type Result = Result<RuntimeConfigDiffView, GetProtocolConfigError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetProtocolConfigDiff, _: &mut Self::Context) -> Self::Result {
+        let from_protocol_version = self.get_protocol_version_by_reference(msg.from)?;
+        let to_protocol_version = self.get_protocol_version_by_reference(msg.to)?;
+        let from_config = self.runtime_adapter.get_runtime_config(from_protocol_version);
+        let to_config = self.runtime_adapter.get_runtime_config(to_protocol_version);
+        Ok(RuntimeConfigDiffView {
+            from_protocol_version,
+            to_protocol_version,
+            changes: from_config.diff(&to_config),
+        })
+    }
+}
+
 impl Handler<NetworkViewClientMessages> for ViewClientActor {
     type Result = NetworkViewClientResponses;
 
@@ -1282,9 +1854,7 @@ impl Handler<NetworkViewClientMessages> for ViewClientActor {
                 trace!(target: "sync", "Computing state request part {} {} {}", shard_id, sync_hash, part_id);
                 let state_response = match self.chain.check_sync_hash_validity(&sync_hash) {
                     Ok(true) => {
-                        let part = match self
-                            .chain
-                            .get_state_response_part(shard_id, part_id, sync_hash)
+                        let part = match self.get_state_response_part(shard_id, part_id, sync_hash)
                         {
                             Ok(part) => Some((part_id, part)),
                             Err(e) => {
@@ -1354,6 +1924,13 @@ impl Handler<NetworkViewClientMessages> for ViewClientActor {
 
                 NetworkViewClientResponses::AnnounceAccount(filtered_announce_accounts)
             }
+            NetworkViewClientMessages::GetServableState => match self.get_servable_state() {
+                Ok(servable_state) => NetworkViewClientResponses::ServableState(servable_state),
+                Err(err) => {
+                    debug!(target: "sync", "Cannot get the servable state: {}", err);
+                    NetworkViewClientResponses::NoResponse
+                }
+            },
             NetworkViewClientMessages::EpochSyncRequest { epoch_id: _epoch_id } => {
                 // TODO #3488
                 NetworkViewClientResponses::NoResponse
@@ -1388,13 +1965,28 @@ pub fn start_view_client(
     #[cfg(feature = "test_features")] adv: Arc<RwLock<AdversarialControls>>,
 ) -> Addr<ViewClientActor> {
     let request_manager = Arc::new(RwLock::new(ViewClientRequestManager::new()));
-    SyncArbiter::start(config.view_client_threads, move || {
+    let view_cache = Arc::new(Mutex::new(ViewCache::new()));
+    let state_parts_cache = Arc::new(Mutex::new(StatePartsCache::new(
+        config.state_parts_cache.as_ref().map_or(0, |config| config.memory_budget_bytes),
+    )));
+    if let Some(state_parts_cache_config) =
+        config.state_parts_cache.as_ref().filter(|config| config.precompute)
+    {
+        start_state_parts_precompute(
+            runtime_adapter.clone(),
+            chain_genesis.clone(),
+            state_parts_cache_config.disk_budget_bytes,
+        );
+    }
+    let view_client_addr = SyncArbiter::start(config.view_client_threads, move || {
         // ViewClientActor::start_in_arbiter(&Arbiter::current(), move |_ctx| {
         let validator_account_id1 = validator_account_id.clone();
         let runtime_adapter1 = runtime_adapter.clone();
         let network_adapter1 = network_adapter.clone();
         let config1 = config.clone();
         let request_manager1 = request_manager.clone();
+        let view_cache1 = view_cache.clone();
+        let state_parts_cache1 = state_parts_cache.clone();
         ViewClientActor::new(
             validator_account_id1,
             &chain_genesis,
@@ -1402,9 +1994,27 @@ pub fn start_view_client(
             network_adapter1,
             config1,
             request_manager1,
+            view_cache1,
+            state_parts_cache1,
             #[cfg(feature = "test_features")]
             adv.clone(),
         )
         .unwrap()
-    })
+    });
+    spawn_mailbox_probes(view_client_addr.clone());
+    view_client_addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::maintenance_windows;
+
+    #[test]
+    fn test_maintenance_windows() {
+        let producing = [12, 13, 17];
+        let windows = maintenance_windows(10..20, |height| Ok(producing.contains(&height)));
+        assert_eq!(windows.unwrap(), vec![10..12, 14..17, 18..20]);
+        let windows = maintenance_windows(12..14, |height| Ok(producing.contains(&height)));
+        assert_eq!(windows.unwrap(), vec![]);
+    }
 }
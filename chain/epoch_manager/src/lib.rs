@@ -18,7 +18,10 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolVersion, UPGRADABILITY_FIX_PROTOCOL_VERSION};
 use near_primitives::views::{
-    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+    CurrentEpochValidatorInfo, EpochRewardProjectionView, EpochValidatorInfo,
+    EpochValidatorProjectionView, NextEpochValidatorInfo, ProtocolUpgradeView,
+    ProtocolVersionStakeView, ProtocolVersionVoteView, ProtocolVersionVotesView,
+    ValidatorKickoutView, ValidatorRewardProjectionView,
 };
 use near_store::{ColBlockInfo, ColEpochInfo, ColEpochStart, Store, StoreUpdate};
 
@@ -27,6 +30,7 @@ pub use crate::reward_calculator::RewardCalculator;
 use crate::types::EpochInfoAggregator;
 pub use crate::types::RngSeed;
 
+use crate::reward_calculator::NUM_NS_IN_SECOND;
 pub use crate::reward_calculator::NUM_SECONDS_IN_A_YEAR;
 use near_chain::types::{BlockHeaderInfo, ValidatorInfoIdentifier};
 use near_chain_configs::GenesisConfig;
@@ -321,10 +325,13 @@ impl EpochManager {
         (validator_kickout, validator_block_chunk_stats)
     }
 
+    /// Gathers proposals, kickouts and production stats of the epoch ending with the given block.
+    /// With `copy_only` the cached epoch info aggregator is kept, as the epoch doesn't really end.
     fn collect_blocks_info(
         &mut self,
         last_block_info: &BlockInfo,
         last_block_hash: &CryptoHash,
+        copy_only: bool,
     ) -> Result<EpochSummary, EpochError> {
         let epoch_info = self.get_epoch_info(last_block_info.epoch_id())?.clone();
         let next_epoch_id = self.get_next_epoch_id(last_block_hash)?;
@@ -338,42 +345,22 @@ impl EpochManager {
         } = self.get_and_update_epoch_info_aggregator(
             last_block_info.epoch_id(),
             last_block_hash,
-            false,
+            copy_only,
         )?;
         let mut proposals = vec![];
         let mut validator_kickout = HashMap::new();
 
         // Next protocol version calculation.
         // Implements https://github.com/nearprotocol/NEPs/pull/64/files#diff-45f773511fe4321b446c3c4226324873R76
-        let mut versions = HashMap::new();
-        for (validator_id, version) in version_tracker.iter() {
-            let stake = epoch_info.validator_stake(*validator_id);
-            *versions.entry(version).or_insert(0) += stake;
-        }
-        let total_block_producer_stake: u128 = epoch_info
-            .block_producers_settlement()
-            .iter()
-            .collect::<HashSet<_>>()
-            .iter()
-            .map(|&id| epoch_info.validator_stake(*id))
-            .sum();
-
-        let protocol_version =
-            if epoch_info.protocol_version() >= UPGRADABILITY_FIX_PROTOCOL_VERSION {
-                next_epoch_info.protocol_version()
-            } else {
-                epoch_info.protocol_version()
-            };
-
-        let config = self.config.for_protocol_version(protocol_version);
-        let next_version = if let Some((&version, stake)) =
+        let (versions, total_block_producer_stake) =
+            protocol_version_stakes(&epoch_info, &version_tracker);
+        let protocol_version = upgraded_protocol_version(&epoch_info, &next_epoch_info);
+        let threshold_stake =
+            self.protocol_upgrade_threshold_stake(protocol_version, total_block_producer_stake);
+        let next_version = if let Some((version, stake)) =
             versions.into_iter().max_by(|left, right| left.1.cmp(&right.1))
         {
-            if stake
-                > (total_block_producer_stake
-                    * *config.protocol_upgrade_stake_threshold.numer() as u128)
-                    / *config.protocol_upgrade_stake_threshold.denom() as u128
-            {
+            if stake > threshold_stake {
                 version
             } else {
                 protocol_version
@@ -435,7 +422,7 @@ impl EpochManager {
         last_block_hash: &CryptoHash,
         rng_seed: RngSeed,
     ) -> Result<(), EpochError> {
-        let epoch_summary = self.collect_blocks_info(block_info, last_block_hash)?;
+        let epoch_summary = self.collect_blocks_info(block_info, last_block_hash, false)?;
         let epoch_info = self.get_epoch_info(block_info.epoch_id())?;
         let epoch_protocol_version = epoch_info.protocol_version();
         let validator_stake =
@@ -1130,38 +1117,10 @@ impl EpochManager {
             }
         };
 
+        let config = self.config.for_protocol_version(cur_epoch_info.protocol_version()).clone();
         let next_epoch_info = self.get_epoch_info(&next_epoch_id)?;
-        let mut next_validator_to_shard = (0..next_epoch_info.validators_len())
-            .map(|_| HashSet::default())
-            .collect::<Vec<HashSet<ShardId>>>();
-        for (shard_id, validators) in
-            next_epoch_info.chunk_producers_settlement().iter().enumerate()
-        {
-            for validator_id in validators {
-                next_validator_to_shard[*validator_id as usize].insert(shard_id as u64);
-            }
-        }
-        let next_validators = next_epoch_info
-            .validators_iter()
-            .enumerate()
-            .map(|(validator_id, info)| {
-                let mut shards = next_validator_to_shard[validator_id]
-                    .clone()
-                    .into_iter()
-                    .collect::<Vec<ShardId>>();
-                shards.sort();
-                let (account_id, public_key, stake) = info.destructure();
-                NextEpochValidatorInfo { account_id, public_key, stake, shards }
-            })
-            .collect();
-        let prev_epoch_kickout = next_epoch_info
-            .validator_kickout()
-            .clone()
-            .into_iter()
-            .collect::<BTreeMap<_, _>>()
-            .into_iter()
-            .map(|(account_id, reason)| ValidatorKickoutView { account_id, reason })
-            .collect();
+        let next_validators = validators_with_shards(next_epoch_info);
+        let prev_epoch_kickout = kickout_view(next_epoch_info.validator_kickout(), &config);
 
         Ok(EpochValidatorInfo {
             current_validators,
@@ -1175,6 +1134,237 @@ impl EpochManager {
         })
     }
 
+    /// Projects the validators which would be selected for the epoch after the next one if the
+    /// epoch of the given block ended with it, from the proposals and block and chunk production
+    /// stats gathered so far.  Rewards of the epoch are not known yet, so they are not included
+    /// in the projected stakes.
+    pub fn get_validator_projection(
+        &mut self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochValidatorProjectionView, EpochError> {
+        let block_info = self.get_block_info(last_block_hash)?.clone();
+        let epoch_summary = self.collect_blocks_info(&block_info, last_block_hash, true)?;
+        let epoch_protocol_version = self.get_epoch_info(block_info.epoch_id())?.protocol_version();
+        let next_epoch_id = self.get_next_epoch_id(last_block_hash)?;
+        let next_epoch_info = self.get_epoch_info(&next_epoch_id)?.clone();
+        let EpochSummary { all_proposals, validator_kickout, next_version, .. } = epoch_summary;
+        let epoch_info = match proposals_to_epoch_info(
+            self.config.for_protocol_version(next_version),
+            [0; 32],
+            &next_epoch_info,
+            all_proposals,
+            validator_kickout,
+            HashMap::new(),
+            0,
+            next_version,
+            epoch_protocol_version,
+        ) {
+            Ok(epoch_info) => epoch_info,
+            // Same as in `finalize_epoch`, the validators of the next epoch would stay.
+            Err(EpochError::ThresholdError { .. })
+            | Err(EpochError::NotEnoughValidators { .. }) => {
+                let mut epoch_info = next_epoch_info;
+                *epoch_info.epoch_height_mut() += 1;
+                epoch_info
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(EpochValidatorProjectionView {
+            epoch_height: epoch_info.epoch_height(),
+            seat_price: epoch_info.seat_price(),
+            validators: validators_with_shards(&epoch_info),
+            fishermen: epoch_info.fishermen_iter().map(Into::into).collect(),
+            kickout: kickout_view(
+                epoch_info.validator_kickout(),
+                self.config.for_protocol_version(epoch_protocol_version),
+            ),
+        })
+    }
+
+    /// Breaks down the protocol versions voted for by the block producers of the epoch of the
+    /// given block, as advertised in the blocks they produced so far, and the upgrade they lead
+    /// to.
+    pub fn get_protocol_version_votes(
+        &mut self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, EpochError> {
+        let block_info = self.get_block_info(last_block_hash)?.clone();
+        let epoch_info = self.get_epoch_info(block_info.epoch_id())?.clone();
+        let next_epoch_id = self.get_next_epoch_id(last_block_hash)?;
+        let next_epoch_info = self.get_epoch_info(&next_epoch_id)?.clone();
+        let EpochInfoAggregator { version_tracker, .. } = self
+            .get_and_update_epoch_info_aggregator(block_info.epoch_id(), last_block_hash, true)?;
+        let (stakes, total_stake) = protocol_version_stakes(&epoch_info, &version_tracker);
+        let protocol_version = upgraded_protocol_version(&epoch_info, &next_epoch_info);
+        let threshold_stake = self.protocol_upgrade_threshold_stake(protocol_version, total_stake);
+
+        let mut versions = stakes
+            .into_iter()
+            .map(|(protocol_version, stake)| ProtocolVersionStakeView {
+                protocol_version,
+                stake,
+                num_validators: version_tracker
+                    .values()
+                    .filter(|&&version| version == protocol_version)
+                    .count() as u64,
+            })
+            .collect::<Vec<_>>();
+        versions.sort_by(|left, right| right.protocol_version.cmp(&left.protocol_version));
+        let mut validators = epoch_info
+            .block_producers_settlement()
+            .iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|&validator_id| {
+                let validator = epoch_info.get_validator(validator_id);
+                ProtocolVersionVoteView {
+                    account_id: validator.account_id().clone(),
+                    stake: validator.stake(),
+                    protocol_version: version_tracker.get(&validator_id).copied(),
+                }
+            })
+            .collect::<Vec<_>>();
+        validators.sort_by(|left, right| {
+            right.stake.cmp(&left.stake).then_with(|| left.account_id.cmp(&right.account_id))
+        });
+
+        let epoch_length =
+            self.config.for_protocol_version(epoch_info.protocol_version()).epoch_length;
+        let next_epoch_start_height =
+            self.get_block_info(block_info.epoch_first_block())?.height() + epoch_length;
+        let voted_version = versions
+            .iter()
+            .find(|version| version.stake > threshold_stake)
+            .map(|version| version.protocol_version);
+        let upgrade = if next_epoch_info.protocol_version() != epoch_info.protocol_version() {
+            Some(ProtocolUpgradeView {
+                protocol_version: next_epoch_info.protocol_version(),
+                epoch_height: next_epoch_info.epoch_height(),
+                block_height: next_epoch_start_height,
+            })
+        } else {
+            voted_version.filter(|&version| version != protocol_version).map(|version| {
+                ProtocolUpgradeView {
+                    protocol_version: version,
+                    epoch_height: next_epoch_info.epoch_height() + 1,
+                    block_height: next_epoch_start_height + epoch_length,
+                }
+            })
+        };
+        Ok(ProtocolVersionVotesView {
+            epoch_height: epoch_info.epoch_height(),
+            protocol_version: epoch_info.protocol_version(),
+            next_epoch_protocol_version: next_epoch_info.protocol_version(),
+            total_stake,
+            threshold_stake,
+            versions,
+            validators,
+            upgrade,
+        })
+    }
+
+    /// Projects the rewards of the epoch of the given block the same way as `finalize_epoch`
+    /// computes them, from the block and chunk production stats gathered so far.  The duration
+    /// of the epoch and the fees burnt are extrapolated to the end of the epoch from the blocks
+    /// so far.
+    pub fn get_reward_projection(
+        &mut self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochRewardProjectionView, EpochError> {
+        let block_info = self.get_block_info(last_block_hash)?.clone();
+        let EpochSummary { validator_block_chunk_stats, .. } =
+            self.collect_blocks_info(&block_info, last_block_hash, true)?;
+        let epoch_info = self.get_epoch_info(block_info.epoch_id())?.clone();
+        let protocol_version = epoch_info.protocol_version();
+        let validator_stake =
+            epoch_info.validators_iter().map(|r| r.account_and_stake()).collect::<HashMap<_, _>>();
+
+        let epoch_length = self.config.for_protocol_version(protocol_version).epoch_length;
+        let epoch_start_height = self.get_block_info(block_info.epoch_first_block())?.height();
+        let last_epoch_last_block_hash =
+            *self.get_block_info(block_info.epoch_first_block())?.prev_hash();
+        let last_block_in_last_epoch = self.get_block_info(&last_epoch_last_block_hash)?.clone();
+        // The epoch ends with the first block at or after its last height.
+        let elapsed_heights = block_info.height() - last_block_in_last_epoch.height();
+        let epoch_heights = (epoch_start_height + epoch_length - 1)
+            .saturating_sub(last_block_in_last_epoch.height())
+            .max(elapsed_heights);
+        let extrapolate = |amount: u128| {
+            (U256::from(amount) * U256::from(epoch_heights) / U256::from(elapsed_heights)).as_u128()
+        };
+        let epoch_duration = extrapolate(
+            block_info
+                .timestamp_nanosec()
+                .saturating_sub(last_block_in_last_epoch.timestamp_nanosec())
+                .into(),
+        ) as u64;
+        let total_supply = *block_info.total_supply();
+        let burnt_amount = extrapolate(
+            (last_block_in_last_epoch.total_supply() + epoch_info.minted_amount())
+                .saturating_sub(total_supply),
+        );
+
+        let stats = validator_block_chunk_stats.clone();
+        let (rewards, minted_amount) = self.reward_calculator.calculate_reward(
+            validator_block_chunk_stats,
+            &validator_stake,
+            total_supply,
+            protocol_version,
+            self.genesis_protocol_version,
+            epoch_duration,
+        );
+        let protocol_treasury_reward =
+            rewards.get(&self.reward_calculator.protocol_treasury_account).copied().unwrap_or(0);
+        let epochs_per_year = (self.reward_calculator.num_seconds_per_year as f64)
+            * (NUM_NS_IN_SECOND as f64)
+            / (epoch_duration.max(1) as f64);
+        let mut validators = stats
+            .into_iter()
+            .map(|(account_id, stats)| {
+                let stake = validator_stake[&account_id];
+                let reward = rewards.get(&account_id).copied().unwrap_or(0);
+                ValidatorRewardProjectionView {
+                    num_produced_blocks: stats.block_stats.produced,
+                    num_expected_blocks: stats.block_stats.expected,
+                    num_produced_chunks: stats.chunk_stats.produced,
+                    num_expected_chunks: stats.chunk_stats.expected,
+                    reward,
+                    apy: reward as f64 / stake.max(1) as f64 * epochs_per_year,
+                    stake,
+                    account_id,
+                }
+            })
+            .collect::<Vec<_>>();
+        validators.sort_by(|left, right| {
+            right.stake.cmp(&left.stake).then_with(|| left.account_id.cmp(&right.account_id))
+        });
+        let inflation_rate = (minted_amount as f64 - burnt_amount as f64)
+            / total_supply.max(1) as f64
+            * epochs_per_year;
+        Ok(EpochRewardProjectionView {
+            epoch_height: epoch_info.epoch_height(),
+            protocol_version,
+            total_supply,
+            epoch_duration_ns: epoch_duration,
+            minted_amount,
+            protocol_treasury_reward,
+            burnt_amount,
+            inflation_rate,
+            validators,
+        })
+    }
+
+    /// Stake a protocol version needs more than to be adopted when upgrading from the given one.
+    fn protocol_upgrade_threshold_stake(
+        &self,
+        protocol_version: ProtocolVersion,
+        total_block_producer_stake: Balance,
+    ) -> Balance {
+        let config = self.config.for_protocol_version(protocol_version);
+        (total_block_producer_stake * *config.protocol_upgrade_stake_threshold.numer() as u128)
+            / *config.protocol_upgrade_stake_threshold.denom() as u128
+    }
+
     /// Compare two epoch ids based on their start height. This works because finality gadget
     /// guarantees that we cannot have two different epochs on two forks
     pub fn compare_epoch_id(
@@ -1554,6 +1744,88 @@ impl EpochManager {
     }
 }
 
+/// Stake of the block producers of the epoch per protocol version they voted for, and the total
+/// stake of the block producers.
+fn protocol_version_stakes(
+    epoch_info: &EpochInfo,
+    version_tracker: &HashMap<ValidatorId, ProtocolVersion>,
+) -> (HashMap<ProtocolVersion, Balance>, Balance) {
+    let mut versions = HashMap::new();
+    for (validator_id, version) in version_tracker.iter() {
+        let stake = epoch_info.validator_stake(*validator_id);
+        *versions.entry(*version).or_insert(0) += stake;
+    }
+    let total_block_producer_stake: u128 = epoch_info
+        .block_producers_settlement()
+        .iter()
+        .collect::<HashSet<_>>()
+        .iter()
+        .map(|&id| epoch_info.validator_stake(*id))
+        .sum();
+    (versions, total_block_producer_stake)
+}
+
+/// Protocol version the votes of the block producers of the epoch upgrade from.
+fn upgraded_protocol_version(
+    epoch_info: &EpochInfo,
+    next_epoch_info: &EpochInfo,
+) -> ProtocolVersion {
+    if epoch_info.protocol_version() >= UPGRADABILITY_FIX_PROTOCOL_VERSION {
+        next_epoch_info.protocol_version()
+    } else {
+        epoch_info.protocol_version()
+    }
+}
+
+/// Validators of the epoch with the shards they produce chunks for.
+fn validators_with_shards(epoch_info: &EpochInfo) -> Vec<NextEpochValidatorInfo> {
+    let mut validator_to_shard = (0..epoch_info.validators_len())
+        .map(|_| HashSet::default())
+        .collect::<Vec<HashSet<ShardId>>>();
+    for (shard_id, validators) in epoch_info.chunk_producers_settlement().iter().enumerate() {
+        for validator_id in validators {
+            validator_to_shard[*validator_id as usize].insert(shard_id as u64);
+        }
+    }
+    epoch_info
+        .validators_iter()
+        .enumerate()
+        .map(|(validator_id, info)| {
+            let mut shards =
+                validator_to_shard[validator_id].clone().into_iter().collect::<Vec<ShardId>>();
+            shards.sort();
+            let (account_id, public_key, stake) = info.destructure();
+            NextEpochValidatorInfo { account_id, public_key, stake, shards }
+        })
+        .collect()
+}
+
+/// Kickouts sorted by account id, with the production thresholds of the config they were
+/// checked against.
+fn kickout_view(
+    kickout: &HashMap<AccountId, ValidatorKickoutReason>,
+    config: &EpochConfig,
+) -> Vec<ValidatorKickoutView> {
+    kickout
+        .clone()
+        .into_iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(account_id, reason)| {
+            let produced_threshold_percent = match reason {
+                ValidatorKickoutReason::NotEnoughBlocks { .. } => {
+                    Some(config.block_producer_kickout_threshold)
+                }
+                ValidatorKickoutReason::NotEnoughChunks { .. } => {
+                    Some(config.chunk_producer_kickout_threshold)
+                }
+                _ => None,
+            };
+            ValidatorKickoutView { account_id, reason, produced_threshold_percent }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests2 {
     use num_rational::Rational;
@@ -1938,6 +2210,55 @@ mod tests2 {
         check_reward(epoch_info, vec![("test2".parse().unwrap(), 0), ("near".parse().unwrap(), 0)]);
     }
 
+    /// Projection at the last block of an epoch matches the validators actually selected.
+    #[test]
+    fn test_validator_projection() {
+        let amount_staked = 1_000_000;
+        let validators = vec![
+            ("test1".parse().unwrap(), amount_staked),
+            ("test2".parse().unwrap(), amount_staked),
+        ];
+        let epoch_length = 10;
+        let mut epoch_manager =
+            setup_default_epoch_manager(validators, epoch_length, 1, 2, 0, 90, 60);
+        let h = hash_range((3 * epoch_length) as usize);
+
+        record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+        let mut prev_block = h[0];
+        let init_epoch_id = epoch_manager.get_epoch_id_from_prev_block(&prev_block).unwrap();
+        let mut test2_kicked_out = false;
+        for (i, curr_block) in h.iter().enumerate().skip(1) {
+            let height = i as u64;
+            let epoch_id = epoch_manager.get_epoch_id_from_prev_block(&prev_block).unwrap();
+            let block_producer = epoch_manager.get_block_producer_info(&epoch_id, height).unwrap();
+            if block_producer.account_id().as_ref() == "test2" && epoch_id == init_epoch_id {
+                // test2 skips its blocks in the first epoch
+                continue;
+            }
+            record_block(&mut epoch_manager, prev_block, *curr_block, height, vec![]);
+            prev_block = *curr_block;
+            if !epoch_manager.is_next_block_epoch_start(curr_block).unwrap() {
+                continue;
+            }
+            let projection = epoch_manager.get_validator_projection(curr_block).unwrap();
+            let epoch_info = epoch_manager.get_epoch_info(&EpochId(*curr_block)).unwrap().clone();
+            assert_eq!(projection.epoch_height, epoch_info.epoch_height());
+            assert_eq!(
+                projection.validators.iter().map(|v| v.account_id.clone()).collect::<Vec<_>>(),
+                epoch_info.validators_iter().map(|v| v.take_account_id()).collect::<Vec<_>>()
+            );
+            let config = epoch_manager.config.for_protocol_version(epoch_info.protocol_version());
+            assert_eq!(projection.kickout, kickout_view(epoch_info.validator_kickout(), config));
+            for kickout in &projection.kickout {
+                if let ValidatorKickoutReason::NotEnoughBlocks { .. } = kickout.reason {
+                    assert_eq!(kickout.produced_threshold_percent, Some(90));
+                }
+            }
+            test2_kicked_out |= projection.kickout.iter().any(|k| k.account_id.as_ref() == "test2");
+        }
+        assert!(test2_kicked_out);
+    }
+
     #[test]
     fn test_validator_unstake() {
         let store = create_test_store();
@@ -2347,6 +2668,66 @@ mod tests2 {
         assert_eq!(epoch_info.minted_amount(), inflation);
     }
 
+    /// Projection during the epoch and at its last block matches the rewards actually given.
+    #[test]
+    fn test_reward_projection() {
+        let stake_amount = 1_000_000;
+        let validators =
+            vec![("test1".parse().unwrap(), 110), ("test2".parse().unwrap(), stake_amount)];
+        let epoch_length = 2;
+        let total_supply = validators.iter().map(|(_, stake)| stake).sum();
+        let reward_calculator = RewardCalculator {
+            max_inflation_rate: Rational::new(5, 100),
+            num_blocks_per_year: 50,
+            epoch_length,
+            protocol_reward_rate: Rational::new(1, 10),
+            protocol_treasury_account: "near".parse().unwrap(),
+            online_min_threshold: Rational::new(90, 100),
+            online_max_threshold: Rational::new(99, 100),
+            num_seconds_per_year: 50,
+        };
+        let mut epoch_manager =
+            setup_epoch_manager(validators, epoch_length, 1, 1, 0, 90, 60, 100, reward_calculator);
+        let h = hash_range(5);
+        record_with_block_info(
+            &mut epoch_manager,
+            block_info(
+                h[0],
+                0,
+                0,
+                Default::default(),
+                Default::default(),
+                h[0],
+                vec![true],
+                total_supply,
+            ),
+        );
+        record_with_block_info(
+            &mut epoch_manager,
+            block_info(h[1], 1, 1, h[0], h[0], h[1], vec![true], total_supply),
+        );
+        let mid_epoch_projection = epoch_manager.get_reward_projection(&h[1]).unwrap();
+        record_with_block_info(
+            &mut epoch_manager,
+            block_info(h[2], 2, 2, h[1], h[1], h[1], vec![true], total_supply),
+        );
+        let last_block_projection = epoch_manager.get_reward_projection(&h[2]).unwrap();
+
+        let epoch_info = epoch_manager.get_epoch_info(&EpochId(h[2])).unwrap();
+        for projection in [mid_epoch_projection, last_block_projection] {
+            assert_eq!(projection.epoch_duration_ns, epoch_length * NUM_NS_IN_SECOND);
+            assert_eq!(projection.burnt_amount, 0);
+            assert_eq!(projection.minted_amount, epoch_info.minted_amount());
+            assert_eq!(projection.protocol_treasury_reward, epoch_info.validator_reward()["near"]);
+            assert_eq!(projection.validators.len(), 1);
+            let test2 = &projection.validators[0];
+            assert_eq!(test2.account_id.as_ref(), "test2");
+            assert_eq!(test2.reward, epoch_info.validator_reward()["test2"]);
+            // 25 epochs of 2 seconds per year of 50 seconds.
+            assert_eq!(test2.apy, test2.reward as f64 / stake_amount as f64 * 25.0);
+        }
+    }
+
     #[test]
     fn test_validator_reward_weight_by_stake() {
         let stake_amount1 = 1_000_000;
@@ -3782,6 +4163,55 @@ mod tests2 {
         );
     }
 
+    #[test]
+    fn test_protocol_version_votes() {
+        let store = create_test_store();
+        let config = epoch_config(2, 1, 2, 0, 90, 60, 0, None);
+        let amount_staked = 1_000_000;
+        let validators = vec![
+            stake("test1".parse().unwrap(), amount_staked),
+            stake("test2".parse().unwrap(), amount_staked),
+        ];
+        let mut epoch_manager =
+            EpochManager::new(store, config, 0, default_reward_calculator(), validators).unwrap();
+        let h = hash_range(8);
+        record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+        let mut block_info1 =
+            block_info(h[1], 1, 1, h[0], h[0], h[0], vec![], DEFAULT_TOTAL_SUPPLY);
+        set_block_info_protocol_version(&mut block_info1, 0);
+        epoch_manager.record_block_info(block_info1, [0; 32]).unwrap();
+
+        let epoch_id = epoch_manager.get_epoch_id(&h[1]).unwrap();
+        let block_producer = epoch_manager.get_block_producer_info(&epoch_id, 1).unwrap();
+        let votes = epoch_manager.get_protocol_version_votes(&h[1]).unwrap();
+        assert_eq!(votes.protocol_version, 0);
+        assert_eq!(votes.total_stake, 2 * amount_staked);
+        assert_eq!(votes.threshold_stake, 2 * amount_staked * 80 / 100);
+        assert_eq!(
+            votes.versions,
+            vec![ProtocolVersionStakeView {
+                protocol_version: 0,
+                stake: amount_staked,
+                num_validators: 1
+            }]
+        );
+        assert_eq!(votes.validators.len(), 2);
+        for vote in &votes.validators {
+            let expected =
+                if &vote.account_id == block_producer.account_id() { Some(0) } else { None };
+            assert_eq!(vote.protocol_version, expected);
+        }
+        assert_eq!(votes.upgrade, None);
+
+        let mut upgrades = vec![];
+        for i in 2..6 {
+            record_block(&mut epoch_manager, h[i - 1], h[i], i as u64, vec![]);
+            upgrades.extend(epoch_manager.get_protocol_version_votes(&h[i]).unwrap().upgrade);
+        }
+        assert!(!upgrades.is_empty());
+        assert!(upgrades.iter().all(|upgrade| upgrade.protocol_version == PROTOCOL_VERSION));
+    }
+
     #[test]
     fn test_protocol_version_switch_with_shard_layout_change() {
         let store = create_test_store();
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 pub use near_primitives::hash::CryptoHash;
 pub use near_primitives::{types, views};
 
+pub mod proto;
+
 /// Resulting struct represents block with chunks
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StreamerMessage {
@@ -0,0 +1,1155 @@
+//! Protobuf representation of [`StreamerMessage`](crate::StreamerMessage), for consumers which
+//! decode the stream in other languages.  The schema is in `proto/streamer_message.proto`; the
+//! structs here mirror it field by field and must be kept in sync with it.
+use near_crypto::PublicKey;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::from_base64;
+use near_primitives::types::Balance;
+use near_primitives::views;
+
+/// Version of the schema, bumped on changes which old consumers can't decode.
+pub const STREAMER_MESSAGE_VERSION: u32 = 1;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Empty {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StreamerMessage {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(message, optional, tag = "2")]
+    pub block: Option<Block>,
+    #[prost(message, repeated, tag = "3")]
+    pub shards: Vec<Shard>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Block {
+    #[prost(string, tag = "1")]
+    pub author: String,
+    #[prost(message, optional, tag = "2")]
+    pub header: Option<BlockHeader>,
+    #[prost(message, repeated, tag = "3")]
+    pub chunks: Vec<ChunkHeader>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlockHeader {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+    #[prost(uint64, optional, tag = "2")]
+    pub prev_height: Option<u64>,
+    #[prost(bytes, tag = "3")]
+    pub epoch_id: Vec<u8>,
+    #[prost(bytes, tag = "4")]
+    pub next_epoch_id: Vec<u8>,
+    #[prost(bytes, tag = "5")]
+    pub hash: Vec<u8>,
+    #[prost(bytes, tag = "6")]
+    pub prev_hash: Vec<u8>,
+    #[prost(bytes, tag = "7")]
+    pub prev_state_root: Vec<u8>,
+    #[prost(bytes, tag = "8")]
+    pub chunk_receipts_root: Vec<u8>,
+    #[prost(bytes, tag = "9")]
+    pub chunk_headers_root: Vec<u8>,
+    #[prost(bytes, tag = "10")]
+    pub chunk_tx_root: Vec<u8>,
+    #[prost(bytes, tag = "11")]
+    pub outcome_root: Vec<u8>,
+    #[prost(uint64, tag = "12")]
+    pub chunks_included: u64,
+    #[prost(bytes, tag = "13")]
+    pub challenges_root: Vec<u8>,
+    #[prost(uint64, tag = "14")]
+    pub timestamp_nanosec: u64,
+    #[prost(bytes, tag = "15")]
+    pub random_value: Vec<u8>,
+    #[prost(message, repeated, tag = "16")]
+    pub validator_proposals: Vec<ValidatorStake>,
+    #[prost(bool, repeated, tag = "17")]
+    pub chunk_mask: Vec<bool>,
+    #[prost(string, tag = "18")]
+    pub gas_price: String,
+    #[prost(uint64, optional, tag = "19")]
+    pub block_ordinal: Option<u64>,
+    #[prost(string, tag = "20")]
+    pub total_supply: String,
+    #[prost(message, repeated, tag = "21")]
+    pub challenges_result: Vec<SlashedValidator>,
+    #[prost(bytes, tag = "22")]
+    pub last_final_block: Vec<u8>,
+    #[prost(bytes, tag = "23")]
+    pub last_ds_final_block: Vec<u8>,
+    #[prost(bytes, tag = "24")]
+    pub next_bp_hash: Vec<u8>,
+    #[prost(bytes, tag = "25")]
+    pub block_merkle_root: Vec<u8>,
+    #[prost(bytes, optional, tag = "26")]
+    pub epoch_sync_data_hash: Option<Vec<u8>>,
+    #[prost(message, repeated, tag = "27")]
+    pub approvals: Vec<Approval>,
+    #[prost(string, tag = "28")]
+    pub signature: String,
+    #[prost(uint32, tag = "29")]
+    pub latest_protocol_version: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Approval {
+    #[prost(string, optional, tag = "1")]
+    pub signature: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValidatorStake {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(string, tag = "2")]
+    pub public_key: String,
+    #[prost(string, tag = "3")]
+    pub stake: String,
+    #[prost(bool, tag = "4")]
+    pub is_chunk_only: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SlashedValidator {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(bool, tag = "2")]
+    pub is_double_sign: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ChunkHeader {
+    #[prost(bytes, tag = "1")]
+    pub chunk_hash: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub prev_block_hash: Vec<u8>,
+    #[prost(bytes, tag = "3")]
+    pub outcome_root: Vec<u8>,
+    #[prost(bytes, tag = "4")]
+    pub prev_state_root: Vec<u8>,
+    #[prost(bytes, tag = "5")]
+    pub encoded_merkle_root: Vec<u8>,
+    #[prost(uint64, tag = "6")]
+    pub encoded_length: u64,
+    #[prost(uint64, tag = "7")]
+    pub height_created: u64,
+    #[prost(uint64, tag = "8")]
+    pub height_included: u64,
+    #[prost(uint64, tag = "9")]
+    pub shard_id: u64,
+    #[prost(uint64, tag = "10")]
+    pub gas_used: u64,
+    #[prost(uint64, tag = "11")]
+    pub gas_limit: u64,
+    #[prost(string, tag = "12")]
+    pub balance_burnt: String,
+    #[prost(bytes, tag = "13")]
+    pub outgoing_receipts_root: Vec<u8>,
+    #[prost(bytes, tag = "14")]
+    pub tx_root: Vec<u8>,
+    #[prost(message, repeated, tag = "15")]
+    pub validator_proposals: Vec<ValidatorStake>,
+    #[prost(string, tag = "16")]
+    pub signature: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Shard {
+    #[prost(uint64, tag = "1")]
+    pub shard_id: u64,
+    #[prost(message, optional, tag = "2")]
+    pub chunk: Option<Chunk>,
+    #[prost(message, repeated, tag = "3")]
+    pub receipt_execution_outcomes: Vec<ExecutionOutcomeWithReceipt>,
+    #[prost(message, repeated, tag = "4")]
+    pub state_changes: Vec<StateChangeWithCause>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Chunk {
+    #[prost(string, tag = "1")]
+    pub author: String,
+    #[prost(message, optional, tag = "2")]
+    pub header: Option<ChunkHeader>,
+    #[prost(message, repeated, tag = "3")]
+    pub transactions: Vec<TransactionWithOutcome>,
+    #[prost(message, repeated, tag = "4")]
+    pub receipts: Vec<Receipt>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TransactionWithOutcome {
+    #[prost(message, optional, tag = "1")]
+    pub transaction: Option<SignedTransaction>,
+    #[prost(message, optional, tag = "2")]
+    pub outcome: Option<ExecutionOutcomeWithId>,
+    #[prost(message, optional, tag = "3")]
+    pub receipt: Option<Receipt>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SignedTransaction {
+    #[prost(string, tag = "1")]
+    pub signer_id: String,
+    #[prost(string, tag = "2")]
+    pub public_key: String,
+    #[prost(uint64, tag = "3")]
+    pub nonce: u64,
+    #[prost(string, tag = "4")]
+    pub receiver_id: String,
+    #[prost(message, repeated, tag = "5")]
+    pub actions: Vec<Action>,
+    #[prost(string, tag = "6")]
+    pub signature: String,
+    #[prost(bytes, tag = "7")]
+    pub hash: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Action {
+    #[prost(oneof = "action::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8")]
+    pub kind: Option<action::Kind>,
+}
+
+pub mod action {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        CreateAccount(super::Empty),
+        #[prost(message, tag = "2")]
+        DeployContract(super::DeployContract),
+        #[prost(message, tag = "3")]
+        FunctionCall(super::FunctionCall),
+        #[prost(message, tag = "4")]
+        Transfer(super::Transfer),
+        #[prost(message, tag = "5")]
+        Stake(super::Stake),
+        #[prost(message, tag = "6")]
+        AddKey(super::AddKey),
+        #[prost(message, tag = "7")]
+        DeleteKey(super::DeleteKey),
+        #[prost(message, tag = "8")]
+        DeleteAccount(super::DeleteAccount),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeployContract {
+    /// Hash of the deployed code, as views only carry the hash.
+    #[prost(bytes, tag = "1")]
+    pub code: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FunctionCall {
+    #[prost(string, tag = "1")]
+    pub method_name: String,
+    #[prost(bytes, tag = "2")]
+    pub args: Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub gas: u64,
+    #[prost(string, tag = "4")]
+    pub deposit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Transfer {
+    #[prost(string, tag = "1")]
+    pub deposit: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Stake {
+    #[prost(string, tag = "1")]
+    pub stake: String,
+    #[prost(string, tag = "2")]
+    pub public_key: String,
+    #[prost(bool, tag = "3")]
+    pub is_chunk_only: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AddKey {
+    #[prost(string, tag = "1")]
+    pub public_key: String,
+    #[prost(message, optional, tag = "2")]
+    pub access_key: Option<AccessKey>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteKey {
+    #[prost(string, tag = "1")]
+    pub public_key: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeleteAccount {
+    #[prost(string, tag = "1")]
+    pub beneficiary_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccessKey {
+    #[prost(uint64, tag = "1")]
+    pub nonce: u64,
+    #[prost(oneof = "access_key::Permission", tags = "2, 3")]
+    pub permission: Option<access_key::Permission>,
+}
+
+pub mod access_key {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Permission {
+        #[prost(message, tag = "2")]
+        FunctionCall(super::FunctionCallPermission),
+        #[prost(message, tag = "3")]
+        FullAccess(super::Empty),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FunctionCallPermission {
+    #[prost(string, optional, tag = "1")]
+    pub allowance: Option<String>,
+    #[prost(string, tag = "2")]
+    pub receiver_id: String,
+    #[prost(string, repeated, tag = "3")]
+    pub method_names: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Receipt {
+    #[prost(string, tag = "1")]
+    pub predecessor_id: String,
+    #[prost(string, tag = "2")]
+    pub receiver_id: String,
+    #[prost(bytes, tag = "3")]
+    pub receipt_id: Vec<u8>,
+    #[prost(oneof = "receipt::Receipt", tags = "4, 5")]
+    pub receipt: Option<receipt::Receipt>,
+}
+
+pub mod receipt {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Receipt {
+        #[prost(message, tag = "4")]
+        Action(super::ActionReceipt),
+        #[prost(message, tag = "5")]
+        Data(super::DataReceipt),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActionReceipt {
+    #[prost(string, tag = "1")]
+    pub signer_id: String,
+    #[prost(string, tag = "2")]
+    pub signer_public_key: String,
+    #[prost(string, tag = "3")]
+    pub gas_price: String,
+    #[prost(message, repeated, tag = "4")]
+    pub output_data_receivers: Vec<DataReceiver>,
+    #[prost(bytes, repeated, tag = "5")]
+    pub input_data_ids: Vec<Vec<u8>>,
+    #[prost(message, repeated, tag = "6")]
+    pub actions: Vec<Action>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DataReceiver {
+    #[prost(bytes, tag = "1")]
+    pub data_id: Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub receiver_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DataReceipt {
+    #[prost(bytes, tag = "1")]
+    pub data_id: Vec<u8>,
+    #[prost(bytes, optional, tag = "2")]
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExecutionOutcomeWithReceipt {
+    #[prost(message, optional, tag = "1")]
+    pub execution_outcome: Option<ExecutionOutcomeWithId>,
+    #[prost(message, optional, tag = "2")]
+    pub receipt: Option<Receipt>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExecutionOutcomeWithId {
+    #[prost(message, repeated, tag = "1")]
+    pub proof: Vec<MerklePathItem>,
+    #[prost(bytes, tag = "2")]
+    pub block_hash: Vec<u8>,
+    #[prost(bytes, tag = "3")]
+    pub id: Vec<u8>,
+    #[prost(message, optional, tag = "4")]
+    pub outcome: Option<ExecutionOutcome>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MerklePathItem {
+    #[prost(bytes, tag = "1")]
+    pub hash: Vec<u8>,
+    #[prost(enumeration = "merkle_path_item::Direction", tag = "2")]
+    pub direction: i32,
+}
+
+pub mod merkle_path_item {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum Direction {
+        Left = 0,
+        Right = 1,
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExecutionOutcome {
+    #[prost(string, repeated, tag = "1")]
+    pub logs: Vec<String>,
+    #[prost(bytes, repeated, tag = "2")]
+    pub receipt_ids: Vec<Vec<u8>>,
+    #[prost(uint64, tag = "3")]
+    pub gas_burnt: u64,
+    #[prost(string, tag = "4")]
+    pub tokens_burnt: String,
+    #[prost(string, tag = "5")]
+    pub executor_id: String,
+    #[prost(oneof = "execution_outcome::Status", tags = "6, 7, 8, 9")]
+    pub status: Option<execution_outcome::Status>,
+    #[prost(message, optional, tag = "10")]
+    pub metadata: Option<ExecutionMetadata>,
+}
+
+pub mod execution_outcome {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Status {
+        #[prost(message, tag = "6")]
+        Unknown(super::Empty),
+        /// JSON of the `TxExecutionError`, as in the JSON stream.
+        #[prost(string, tag = "7")]
+        Failure(String),
+        #[prost(bytes, tag = "8")]
+        SuccessValue(Vec<u8>),
+        #[prost(bytes, tag = "9")]
+        SuccessReceiptId(Vec<u8>),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ExecutionMetadata {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(message, repeated, tag = "2")]
+    pub gas_profile: Vec<CostGasUsed>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CostGasUsed {
+    #[prost(string, tag = "1")]
+    pub cost_category: String,
+    #[prost(string, tag = "2")]
+    pub cost: String,
+    #[prost(uint64, tag = "3")]
+    pub gas_used: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StateChangeWithCause {
+    #[prost(message, optional, tag = "1")]
+    pub cause: Option<StateChangeCause>,
+    #[prost(oneof = "state_change_with_cause::Value", tags = "2, 3, 4, 5, 6, 7, 8, 9")]
+    pub value: Option<state_change_with_cause::Value>,
+}
+
+pub mod state_change_with_cause {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(message, tag = "2")]
+        AccountUpdate(super::AccountUpdate),
+        #[prost(message, tag = "3")]
+        AccountDeletion(super::AccountDeletion),
+        #[prost(message, tag = "4")]
+        AccessKeyUpdate(super::AccessKeyUpdate),
+        #[prost(message, tag = "5")]
+        AccessKeyDeletion(super::AccessKeyDeletion),
+        #[prost(message, tag = "6")]
+        DataUpdate(super::DataUpdate),
+        #[prost(message, tag = "7")]
+        DataDeletion(super::DataDeletion),
+        #[prost(message, tag = "8")]
+        ContractCodeUpdate(super::ContractCodeUpdate),
+        #[prost(message, tag = "9")]
+        ContractCodeDeletion(super::ContractCodeDeletion),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StateChangeCause {
+    #[prost(oneof = "state_change_cause::Kind", tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11")]
+    pub kind: Option<state_change_cause::Kind>,
+}
+
+pub mod state_change_cause {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        NotWritableToDisk(super::Empty),
+        #[prost(message, tag = "2")]
+        InitialState(super::Empty),
+        #[prost(bytes, tag = "3")]
+        TransactionProcessing(Vec<u8>),
+        #[prost(bytes, tag = "4")]
+        ActionReceiptProcessingStarted(Vec<u8>),
+        #[prost(bytes, tag = "5")]
+        ActionReceiptGasReward(Vec<u8>),
+        #[prost(bytes, tag = "6")]
+        ReceiptProcessing(Vec<u8>),
+        #[prost(bytes, tag = "7")]
+        PostponedReceipt(Vec<u8>),
+        #[prost(message, tag = "8")]
+        UpdatedDelayedReceipts(super::Empty),
+        #[prost(message, tag = "9")]
+        ValidatorAccountsUpdate(super::Empty),
+        #[prost(message, tag = "10")]
+        Migration(super::Empty),
+        #[prost(message, tag = "11")]
+        Resharding(super::Empty),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccountUpdate {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+    #[prost(string, tag = "3")]
+    pub locked: String,
+    #[prost(bytes, tag = "4")]
+    pub code_hash: Vec<u8>,
+    #[prost(uint64, tag = "5")]
+    pub storage_usage: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccountDeletion {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccessKeyUpdate {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(string, tag = "2")]
+    pub public_key: String,
+    #[prost(message, optional, tag = "3")]
+    pub access_key: Option<AccessKey>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AccessKeyDeletion {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(string, tag = "2")]
+    pub public_key: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DataUpdate {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(bytes, tag = "2")]
+    pub key: Vec<u8>,
+    #[prost(bytes, tag = "3")]
+    pub value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DataDeletion {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(bytes, tag = "2")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContractCodeUpdate {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+    #[prost(bytes, tag = "2")]
+    pub code: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ContractCodeDeletion {
+    #[prost(string, tag = "1")]
+    pub account_id: String,
+}
+
+fn hash_bytes(hash: &CryptoHash) -> Vec<u8> {
+    hash.0.to_vec()
+}
+
+fn balance(balance: &Balance) -> String {
+    balance.to_string()
+}
+
+fn public_key(public_key: &PublicKey) -> String {
+    public_key.to_string()
+}
+
+/// Views hold code, arguments and return values base64-encoded, and always validly so.
+fn store_bytes(value: &impl AsRef<[u8]>) -> Vec<u8> {
+    value.as_ref().to_vec()
+}
+
+fn base64_bytes(value: &str) -> Vec<u8> {
+    from_base64(value).unwrap_or_default()
+}
+
+impl From<&crate::StreamerMessage> for StreamerMessage {
+    fn from(message: &crate::StreamerMessage) -> Self {
+        Self {
+            version: STREAMER_MESSAGE_VERSION,
+            block: Some((&message.block).into()),
+            shards: message.shards.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&views::BlockView> for Block {
+    fn from(block: &views::BlockView) -> Self {
+        Self {
+            author: block.author.to_string(),
+            header: Some((&block.header).into()),
+            chunks: block.chunks.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&views::BlockHeaderView> for BlockHeader {
+    fn from(header: &views::BlockHeaderView) -> Self {
+        Self {
+            height: header.height,
+            prev_height: header.prev_height,
+            epoch_id: hash_bytes(&header.epoch_id),
+            next_epoch_id: hash_bytes(&header.next_epoch_id),
+            hash: hash_bytes(&header.hash),
+            prev_hash: hash_bytes(&header.prev_hash),
+            prev_state_root: hash_bytes(&header.prev_state_root),
+            chunk_receipts_root: hash_bytes(&header.chunk_receipts_root),
+            chunk_headers_root: hash_bytes(&header.chunk_headers_root),
+            chunk_tx_root: hash_bytes(&header.chunk_tx_root),
+            outcome_root: hash_bytes(&header.outcome_root),
+            chunks_included: header.chunks_included,
+            challenges_root: hash_bytes(&header.challenges_root),
+            timestamp_nanosec: header.timestamp_nanosec,
+            random_value: hash_bytes(&header.random_value),
+            validator_proposals: header.validator_proposals.iter().map(Into::into).collect(),
+            chunk_mask: header.chunk_mask.clone(),
+            gas_price: balance(&header.gas_price),
+            block_ordinal: header.block_ordinal,
+            total_supply: balance(&header.total_supply),
+            challenges_result: header
+                .challenges_result
+                .iter()
+                .map(|slashed| SlashedValidator {
+                    account_id: slashed.account_id.to_string(),
+                    is_double_sign: slashed.is_double_sign,
+                })
+                .collect(),
+            last_final_block: hash_bytes(&header.last_final_block),
+            last_ds_final_block: hash_bytes(&header.last_ds_final_block),
+            next_bp_hash: hash_bytes(&header.next_bp_hash),
+            block_merkle_root: hash_bytes(&header.block_merkle_root),
+            epoch_sync_data_hash: header.epoch_sync_data_hash.as_ref().map(hash_bytes),
+            approvals: header
+                .approvals
+                .iter()
+                .map(|approval| Approval {
+                    signature: approval.as_ref().map(|signature| signature.to_string()),
+                })
+                .collect(),
+            signature: header.signature.to_string(),
+            latest_protocol_version: header.latest_protocol_version,
+        }
+    }
+}
+
+impl From<&views::validator_stake_view::ValidatorStakeView> for ValidatorStake {
+    fn from(stake: &views::validator_stake_view::ValidatorStakeView) -> Self {
+        let stake = stake.clone().into_validator_stake();
+        let is_chunk_only = stake.is_chunk_only();
+        let (account_id, key, stake) = stake.destructure();
+        Self {
+            account_id: account_id.to_string(),
+            public_key: public_key(&key),
+            stake: balance(&stake),
+            is_chunk_only,
+        }
+    }
+}
+
+impl From<&views::ChunkHeaderView> for ChunkHeader {
+    fn from(header: &views::ChunkHeaderView) -> Self {
+        Self {
+            chunk_hash: hash_bytes(&header.chunk_hash),
+            prev_block_hash: hash_bytes(&header.prev_block_hash),
+            outcome_root: hash_bytes(&header.outcome_root),
+            prev_state_root: hash_bytes(&header.prev_state_root),
+            encoded_merkle_root: hash_bytes(&header.encoded_merkle_root),
+            encoded_length: header.encoded_length,
+            height_created: header.height_created,
+            height_included: header.height_included,
+            shard_id: header.shard_id,
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            balance_burnt: balance(&header.balance_burnt),
+            outgoing_receipts_root: hash_bytes(&header.outgoing_receipts_root),
+            tx_root: hash_bytes(&header.tx_root),
+            validator_proposals: header.validator_proposals.iter().map(Into::into).collect(),
+            signature: header.signature.to_string(),
+        }
+    }
+}
+
+impl From<&crate::IndexerShard> for Shard {
+    fn from(shard: &crate::IndexerShard) -> Self {
+        Self {
+            shard_id: shard.shard_id,
+            chunk: shard.chunk.as_ref().map(Into::into),
+            receipt_execution_outcomes: shard
+                .receipt_execution_outcomes
+                .iter()
+                .map(|outcome| ExecutionOutcomeWithReceipt {
+                    execution_outcome: Some((&outcome.execution_outcome).into()),
+                    receipt: Some((&outcome.receipt).into()),
+                })
+                .collect(),
+            state_changes: shard.state_changes.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&crate::IndexerChunkView> for Chunk {
+    fn from(chunk: &crate::IndexerChunkView) -> Self {
+        Self {
+            author: chunk.author.to_string(),
+            header: Some((&chunk.header).into()),
+            transactions: chunk
+                .transactions
+                .iter()
+                .map(|transaction| TransactionWithOutcome {
+                    transaction: Some((&transaction.transaction).into()),
+                    outcome: Some((&transaction.outcome.execution_outcome).into()),
+                    receipt: transaction.outcome.receipt.as_ref().map(Into::into),
+                })
+                .collect(),
+            receipts: chunk.receipts.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&views::SignedTransactionView> for SignedTransaction {
+    fn from(transaction: &views::SignedTransactionView) -> Self {
+        Self {
+            signer_id: transaction.signer_id.to_string(),
+            public_key: public_key(&transaction.public_key),
+            nonce: transaction.nonce,
+            receiver_id: transaction.receiver_id.to_string(),
+            actions: transaction.actions.iter().map(Into::into).collect(),
+            signature: transaction.signature.to_string(),
+            hash: hash_bytes(&transaction.hash),
+        }
+    }
+}
+
+impl From<&views::ActionView> for Action {
+    fn from(action: &views::ActionView) -> Self {
+        let kind = match action {
+            views::ActionView::CreateAccount => action::Kind::CreateAccount(Empty {}),
+            views::ActionView::DeployContract { code } => {
+                action::Kind::DeployContract(DeployContract { code: base64_bytes(code) })
+            }
+            views::ActionView::FunctionCall { method_name, args, gas, deposit } => {
+                action::Kind::FunctionCall(FunctionCall {
+                    method_name: method_name.clone(),
+                    args: base64_bytes(args),
+                    gas: *gas,
+                    deposit: balance(deposit),
+                })
+            }
+            views::ActionView::Transfer { deposit } => {
+                action::Kind::Transfer(Transfer { deposit: balance(deposit) })
+            }
+            views::ActionView::Stake { stake, public_key: key } => action::Kind::Stake(Stake {
+                stake: balance(stake),
+                public_key: public_key(key),
+                is_chunk_only: false,
+            }),
+            views::ActionView::AddKey { public_key: key, access_key } => {
+                action::Kind::AddKey(AddKey {
+                    public_key: public_key(key),
+                    access_key: Some(access_key.into()),
+                })
+            }
+            views::ActionView::DeleteKey { public_key: key } => {
+                action::Kind::DeleteKey(DeleteKey { public_key: public_key(key) })
+            }
+            views::ActionView::DeleteAccount { beneficiary_id } => {
+                action::Kind::DeleteAccount(DeleteAccount {
+                    beneficiary_id: beneficiary_id.to_string(),
+                })
+            }
+            #[cfg(feature = "protocol_feature_chunk_only_producers")]
+            views::ActionView::StakeChunkOnly { stake, public_key: key } => {
+                action::Kind::Stake(Stake {
+                    stake: balance(stake),
+                    public_key: public_key(key),
+                    is_chunk_only: true,
+                })
+            }
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl From<&views::AccessKeyView> for AccessKey {
+    fn from(access_key: &views::AccessKeyView) -> Self {
+        let permission = match &access_key.permission {
+            views::AccessKeyPermissionView::FunctionCall {
+                allowance,
+                receiver_id,
+                method_names,
+            } => access_key::Permission::FunctionCall(FunctionCallPermission {
+                allowance: allowance.as_ref().map(balance),
+                receiver_id: receiver_id.clone(),
+                method_names: method_names.clone(),
+            }),
+            views::AccessKeyPermissionView::FullAccess => {
+                access_key::Permission::FullAccess(Empty {})
+            }
+        };
+        Self { nonce: access_key.nonce, permission: Some(permission) }
+    }
+}
+
+impl From<&views::ReceiptView> for Receipt {
+    fn from(receipt: &views::ReceiptView) -> Self {
+        let inner = match &receipt.receipt {
+            views::ReceiptEnumView::Action {
+                signer_id,
+                signer_public_key,
+                gas_price,
+                output_data_receivers,
+                input_data_ids,
+                actions,
+            } => receipt::Receipt::Action(ActionReceipt {
+                signer_id: signer_id.to_string(),
+                signer_public_key: public_key(signer_public_key),
+                gas_price: balance(gas_price),
+                output_data_receivers: output_data_receivers
+                    .iter()
+                    .map(|receiver| DataReceiver {
+                        data_id: hash_bytes(&receiver.data_id),
+                        receiver_id: receiver.receiver_id.to_string(),
+                    })
+                    .collect(),
+                input_data_ids: input_data_ids.iter().map(hash_bytes).collect(),
+                actions: actions.iter().map(Into::into).collect(),
+            }),
+            views::ReceiptEnumView::Data { data_id, data } => receipt::Receipt::Data(DataReceipt {
+                data_id: hash_bytes(data_id),
+                data: data.clone(),
+            }),
+        };
+        Self {
+            predecessor_id: receipt.predecessor_id.to_string(),
+            receiver_id: receipt.receiver_id.to_string(),
+            receipt_id: hash_bytes(&receipt.receipt_id),
+            receipt: Some(inner),
+        }
+    }
+}
+
+impl From<&views::ExecutionOutcomeWithIdView> for ExecutionOutcomeWithId {
+    fn from(outcome: &views::ExecutionOutcomeWithIdView) -> Self {
+        Self {
+            proof: outcome
+                .proof
+                .iter()
+                .map(|item| MerklePathItem {
+                    hash: hash_bytes(&item.hash),
+                    direction: match item.direction {
+                        near_primitives::merkle::Direction::Left => {
+                            merkle_path_item::Direction::Left
+                        }
+                        near_primitives::merkle::Direction::Right => {
+                            merkle_path_item::Direction::Right
+                        }
+                    } as i32,
+                })
+                .collect(),
+            block_hash: hash_bytes(&outcome.block_hash),
+            id: hash_bytes(&outcome.id),
+            outcome: Some((&outcome.outcome).into()),
+        }
+    }
+}
+
+impl From<&views::ExecutionOutcomeView> for ExecutionOutcome {
+    fn from(outcome: &views::ExecutionOutcomeView) -> Self {
+        let status = match &outcome.status {
+            views::ExecutionStatusView::Unknown => execution_outcome::Status::Unknown(Empty {}),
+            views::ExecutionStatusView::Failure(error) => execution_outcome::Status::Failure(
+                serde_json::to_string(error).unwrap_or_else(|_| error.to_string()),
+            ),
+            views::ExecutionStatusView::SuccessValue(value) => {
+                execution_outcome::Status::SuccessValue(base64_bytes(value))
+            }
+            views::ExecutionStatusView::SuccessReceiptId(receipt_id) => {
+                execution_outcome::Status::SuccessReceiptId(hash_bytes(receipt_id))
+            }
+        };
+        Self {
+            logs: outcome.logs.clone(),
+            receipt_ids: outcome.receipt_ids.iter().map(hash_bytes).collect(),
+            gas_burnt: outcome.gas_burnt,
+            tokens_burnt: balance(&outcome.tokens_burnt),
+            executor_id: outcome.executor_id.to_string(),
+            status: Some(status),
+            metadata: Some(ExecutionMetadata {
+                version: outcome.metadata.version,
+                gas_profile: outcome
+                    .metadata
+                    .gas_profile
+                    .iter()
+                    .flatten()
+                    .map(|cost| CostGasUsed {
+                        cost_category: cost.cost_category.clone(),
+                        cost: cost.cost.clone(),
+                        gas_used: cost.gas_used,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+impl From<&views::StateChangeWithCauseView> for StateChangeWithCause {
+    fn from(change: &views::StateChangeWithCauseView) -> Self {
+        use state_change_cause::Kind;
+        use state_change_with_cause::Value;
+
+        let cause = match &change.cause {
+            views::StateChangeCauseView::NotWritableToDisk => Kind::NotWritableToDisk(Empty {}),
+            views::StateChangeCauseView::InitialState => Kind::InitialState(Empty {}),
+            views::StateChangeCauseView::TransactionProcessing { tx_hash } => {
+                Kind::TransactionProcessing(hash_bytes(tx_hash))
+            }
+            views::StateChangeCauseView::ActionReceiptProcessingStarted { receipt_hash } => {
+                Kind::ActionReceiptProcessingStarted(hash_bytes(receipt_hash))
+            }
+            views::StateChangeCauseView::ActionReceiptGasReward { receipt_hash } => {
+                Kind::ActionReceiptGasReward(hash_bytes(receipt_hash))
+            }
+            views::StateChangeCauseView::ReceiptProcessing { receipt_hash } => {
+                Kind::ReceiptProcessing(hash_bytes(receipt_hash))
+            }
+            views::StateChangeCauseView::PostponedReceipt { receipt_hash } => {
+                Kind::PostponedReceipt(hash_bytes(receipt_hash))
+            }
+            views::StateChangeCauseView::UpdatedDelayedReceipts => {
+                Kind::UpdatedDelayedReceipts(Empty {})
+            }
+            views::StateChangeCauseView::ValidatorAccountsUpdate => {
+                Kind::ValidatorAccountsUpdate(Empty {})
+            }
+            views::StateChangeCauseView::Migration => Kind::Migration(Empty {}),
+            views::StateChangeCauseView::Resharding => Kind::Resharding(Empty {}),
+        };
+        let value = match &change.value {
+            views::StateChangeValueView::AccountUpdate { account_id, account } => {
+                Value::AccountUpdate(AccountUpdate {
+                    account_id: account_id.to_string(),
+                    amount: balance(&account.amount),
+                    locked: balance(&account.locked),
+                    code_hash: hash_bytes(&account.code_hash),
+                    storage_usage: account.storage_usage,
+                })
+            }
+            views::StateChangeValueView::AccountDeletion { account_id } => {
+                Value::AccountDeletion(AccountDeletion { account_id: account_id.to_string() })
+            }
+            views::StateChangeValueView::AccessKeyUpdate {
+                account_id,
+                public_key: key,
+                access_key,
+            } => Value::AccessKeyUpdate(AccessKeyUpdate {
+                account_id: account_id.to_string(),
+                public_key: public_key(key),
+                access_key: Some(access_key.into()),
+            }),
+            views::StateChangeValueView::AccessKeyDeletion { account_id, public_key: key } => {
+                Value::AccessKeyDeletion(AccessKeyDeletion {
+                    account_id: account_id.to_string(),
+                    public_key: public_key(key),
+                })
+            }
+            views::StateChangeValueView::DataUpdate { account_id, key, value } => {
+                Value::DataUpdate(DataUpdate {
+                    account_id: account_id.to_string(),
+                    key: store_bytes(key),
+                    value: store_bytes(value),
+                })
+            }
+            views::StateChangeValueView::DataDeletion { account_id, key } => {
+                Value::DataDeletion(DataDeletion {
+                    account_id: account_id.to_string(),
+                    key: store_bytes(key),
+                })
+            }
+            views::StateChangeValueView::ContractCodeUpdate { account_id, code } => {
+                Value::ContractCodeUpdate(ContractCodeUpdate {
+                    account_id: account_id.to_string(),
+                    code: code.clone(),
+                })
+            }
+            views::StateChangeValueView::ContractCodeDeletion { account_id } => {
+                Value::ContractCodeDeletion(ContractCodeDeletion {
+                    account_id: account_id.to_string(),
+                })
+            }
+        };
+        Self { cause: Some(StateChangeCause { kind: Some(cause) }), value: Some(value) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, PublicKey};
+    use near_primitives::hash::hash;
+    use near_primitives::views;
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_receipt_round_trip() {
+        let receipt = views::ReceiptView {
+            predecessor_id: "alice.near".parse().unwrap(),
+            receiver_id: "token.near".parse().unwrap(),
+            receipt_id: hash(b"receipt"),
+            receipt: views::ReceiptEnumView::Action {
+                signer_id: "alice.near".parse().unwrap(),
+                signer_public_key: PublicKey::empty(KeyType::ED25519),
+                gas_price: 100_000_000,
+                output_data_receivers: vec![],
+                input_data_ids: vec![hash(b"data")],
+                actions: vec![views::ActionView::FunctionCall {
+                    method_name: "ft_transfer".to_string(),
+                    args: "e30=".to_string(),
+                    gas: 30_000_000_000_000,
+                    deposit: 1,
+                }],
+            },
+        };
+        let encoded = Receipt::from(&receipt).encode_to_vec();
+        let decoded = Receipt::decode(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.receipt_id, hash(b"receipt").0.to_vec());
+        let action_receipt = match decoded.receipt {
+            Some(receipt::Receipt::Action(action_receipt)) => action_receipt,
+            other => panic!("unexpected receipt {:?}", other),
+        };
+        assert_eq!(action_receipt.gas_price, "100000000");
+        assert_eq!(
+            action_receipt.actions[0].kind,
+            Some(action::Kind::FunctionCall(FunctionCall {
+                method_name: "ft_transfer".to_string(),
+                args: b"{}".to_vec(),
+                gas: 30_000_000_000_000,
+                deposit: "1".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_state_change_round_trip() {
+        let change = views::StateChangeWithCauseView {
+            cause: views::StateChangeCauseView::ReceiptProcessing { receipt_hash: hash(b"r") },
+            value: views::StateChangeValueView::DataUpdate {
+                account_id: "token.near".parse().unwrap(),
+                key: b"key".to_vec().into(),
+                value: b"value".to_vec().into(),
+            },
+        };
+        let encoded = StateChangeWithCause::from(&change).encode_to_vec();
+        let decoded = StateChangeWithCause::decode(encoded.as_slice()).unwrap();
+        assert_eq!(
+            decoded.cause.unwrap().kind,
+            Some(state_change_cause::Kind::ReceiptProcessing(hash(b"r").0.to_vec()))
+        );
+        assert_eq!(
+            decoded.value,
+            Some(state_change_with_cause::Value::DataUpdate(DataUpdate {
+                account_id: "token.near".to_string(),
+                key: b"key".to_vec(),
+                value: b"value".to_vec(),
+            }))
+        );
+    }
+
+    /// Messages of the current schema must decode with an older one which only knows some of the
+    /// fields, and the other way around.
+    #[test]
+    fn test_schema_compatibility() {
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct OldStreamerMessage {
+            #[prost(uint32, tag = "1")]
+            version: u32,
+            #[prost(message, repeated, tag = "3")]
+            shards: Vec<OldShard>,
+        }
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct OldShard {
+            #[prost(uint64, tag = "1")]
+            shard_id: u64,
+        }
+
+        let message = StreamerMessage {
+            version: STREAMER_MESSAGE_VERSION,
+            block: Some(Block { author: "test.near".to_string(), header: None, chunks: vec![] }),
+            shards: vec![Shard {
+                shard_id: 3,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: vec![StateChangeWithCause {
+                    cause: Some(StateChangeCause {
+                        kind: Some(state_change_cause::Kind::Migration(Empty {})),
+                    }),
+                    value: None,
+                }],
+            }],
+        };
+        let decoded = OldStreamerMessage::decode(message.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded.version, STREAMER_MESSAGE_VERSION);
+        assert_eq!(decoded.shards, vec![OldShard { shard_id: 3 }]);
+
+        let decoded = StreamerMessage::decode(decoded.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded.version, STREAMER_MESSAGE_VERSION);
+        assert_eq!(decoded.block, None);
+        assert_eq!(decoded.shards[0].shard_id, 3);
+        assert_eq!(decoded.shards[0].state_changes, vec![]);
+    }
+}
@@ -0,0 +1,134 @@
+//! Publisher of the stream of `StreamerMessage`s to S3-compatible storage in the layout of NEAR
+//! Lake: for every block, `<height>/shard_<shard_id>.json` for each of its shards followed by
+//! `<height>/block.json`, with the height zero-padded to 12 digits so that keys sort by height.
+//!
+//! Blocks are uploaded one at a time in the order they are streamed and every object is retried
+//! until it's uploaded, so once `block.json` of a block exists all the objects of it and of the
+//! blocks streamed before it exist as well.
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Context;
+use aws_sdk_s3::{ByteStream, Endpoint, Region};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+
+use crate::{metrics, StreamerMessage, INDEXER};
+
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Configuration of the publisher, read from the `"lake"` section of `config.json`.
+/// Credentials are taken from the environment, as for the AWS CLI.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LakeConfig {
+    /// Bucket to upload the blocks to
+    pub bucket: String,
+    /// Region of the bucket
+    pub region: String,
+    /// Endpoint of S3-compatible storage other than AWS, e.g. `https://storage.googleapis.com`
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+impl LakeConfig {
+    /// Reads the `"lake"` section of `config.json` in the given `home_dir`, if any.
+    pub fn from_home_dir(home_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = home_dir.join(nearcore::config::CONFIG_FILENAME);
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        match config.get_mut("lake").map(serde_json::Value::take) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(lake) => serde_json::from_value(lake)
+                .map(Some)
+                .with_context(|| format!("Invalid \"lake\" section in {}", path.display())),
+        }
+    }
+}
+
+/// Uploads the blocks of `stream` as they come until it ends.
+pub async fn publish(config: LakeConfig, mut stream: mpsc::Receiver<StreamerMessage>) {
+    let client = s3_client(&config).await;
+    info!(target: INDEXER, "Publishing blocks to bucket {}...", config.bucket);
+    let mut last_published: Option<(BlockHeight, CryptoHash)> = None;
+    while let Some(streamer_message) = stream.recv().await {
+        let header = &streamer_message.block.header;
+        if let Some((last_height, last_hash)) = last_published {
+            if header.prev_hash != last_hash {
+                metrics::LAKE_GAPS_TOTAL.inc();
+                warn!(
+                    target: INDEXER,
+                    "Block #{} doesn't follow the last published block #{}, some blocks are missing",
+                    header.height,
+                    last_height,
+                );
+            }
+        }
+        publish_block(&client, &config.bucket, &streamer_message).await;
+        metrics::LAKE_LAST_PUBLISHED_BLOCK_HEIGHT.set(header.height as i64);
+        last_published = Some((header.height, header.hash));
+    }
+}
+
+async fn s3_client(config: &LakeConfig) -> aws_sdk_s3::Client {
+    let shared_config =
+        aws_config::from_env().region(Region::new(config.region.clone())).load().await;
+    let mut s3_config = aws_sdk_s3::config::Builder::from(&shared_config);
+    if let Some(endpoint) = &config.endpoint {
+        let uri = endpoint.parse().expect("Invalid endpoint of the \"lake\" config");
+        s3_config = s3_config.endpoint_resolver(Endpoint::immutable(uri));
+    }
+    aws_sdk_s3::Client::from_conf(s3_config.build())
+}
+
+async fn publish_block(
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    streamer_message: &StreamerMessage,
+) {
+    let prefix = format!("{:0>12}", streamer_message.block.header.height);
+    for shard in &streamer_message.shards {
+        let key = format!("{}/shard_{}.json", prefix, shard.shard_id);
+        put_object(client, bucket, &key, serde_json::to_vec(shard).unwrap()).await;
+    }
+    let key = format!("{}/block.json", prefix);
+    put_object(client, bucket, &key, serde_json::to_vec(&streamer_message.block).unwrap()).await;
+}
+
+/// Uploads the object, retrying with exponential backoff until it succeeds.
+async fn put_object(client: &aws_sdk_s3::Client, bucket: &str, key: &str, body: Vec<u8>) {
+    let mut backoff = INITIAL_RETRY_BACKOFF;
+    loop {
+        let timer = metrics::LAKE_UPLOAD_LATENCY.start_timer();
+        let result = client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .content_type("application/json")
+            .body(ByteStream::from(body.clone()))
+            .send()
+            .await;
+        match result {
+            Ok(_) => {
+                timer.observe_duration();
+                return;
+            }
+            Err(err) => {
+                timer.stop_and_discard();
+                metrics::LAKE_UPLOAD_RETRIES_TOTAL.inc();
+                warn!(
+                    target: INDEXER,
+                    "Failed to upload {}, retrying in {:?}: {}", key, backoff, err
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = std::cmp::min(backoff * 2, MAX_RETRY_BACKOFF);
+            }
+        }
+    }
+}
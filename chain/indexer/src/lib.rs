@@ -5,7 +5,7 @@ use tokio::sync::mpsc;
 
 use near_chain_configs::GenesisValidationMode;
 pub use near_primitives;
-use near_primitives::types::Gas;
+use near_primitives::types::{AccountId, BlockHeight, Gas};
 pub use nearcore::{get_default_home, init_configs, NearConfig};
 
 pub use near_indexer_primitives::{
@@ -14,6 +14,10 @@ pub use near_indexer_primitives::{
     StreamerMessage,
 };
 
+#[cfg(feature = "lake")]
+pub mod lake;
+#[cfg(feature = "lake")]
+mod metrics;
 mod streamer;
 
 pub const INDEXER: &str = "indexer";
@@ -42,6 +46,14 @@ pub struct InitConfigArgs {
     pub download_config: bool,
     /// Specify a custom download URL for the config file.
     pub download_config_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis file.
+    pub download_genesis_sha256: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded config file.
+    pub download_config_sha256: Option<String>,
+    /// Download genesis records, kept in a separate file, from the given URL.
+    pub download_records_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis records file.
+    pub download_records_sha256: Option<String>,
     /// Specify the boot nodes to bootstrap the network
     pub boot_nodes: Option<String>,
     /// Specify a custom max_gas_burnt_view limit.
@@ -77,6 +89,30 @@ pub struct IndexerConfig {
     pub sync_mode: SyncModeEnum,
     /// Whether await for node to be synced or not
     pub await_for_node_synced: AwaitForNodeSyncedEnum,
+    /// Contract data changes to stream, all of them if `None`
+    pub contract_data_filter: Option<Vec<ContractDataPattern>>,
+}
+
+/// Configuration of [`backfill`]
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    /// Path to `home_dir` of an archival node where configs and the database can be found
+    pub home_dir: std::path::PathBuf,
+    /// Height of the first block to stream
+    pub start_block_height: BlockHeight,
+    /// Height of the last block to stream (inclusive)
+    pub end_block_height: BlockHeight,
+    /// Contract data changes to stream, all of them if `None`
+    pub contract_data_filter: Option<Vec<ContractDataPattern>>,
+}
+
+/// Contract data (storage) keys of an account starting with the given prefix, e.g. the balances
+/// of a fungible token contract.  Used to keep only the `DataUpdate` and `DataDeletion` state
+/// changes which are of interest, as there may be lots of them in every block.
+#[derive(Debug, Clone)]
+pub struct ContractDataPattern {
+    pub account_id: AccountId,
+    pub key_prefix: Vec<u8>,
 }
 
 /// This is the core component, which handles `nearcore` and internal `streamer`.
@@ -137,6 +173,28 @@ impl Indexer {
     }
 }
 
+/// Streams `StreamerMessage`s of the historical blocks in the given height range out of the
+/// database of an archival node, opened read-only, as fast as it can be read.  Unlike
+/// [`Indexer::streamer`], neither the client nor the network is started, so the node owning the
+/// database may keep running.  The stream ends after the last block of the range.
+pub fn backfill(
+    backfill_config: BackfillConfig,
+) -> Result<mpsc::Receiver<StreamerMessage>, anyhow::Error> {
+    let near_config =
+        nearcore::config::load_config(&backfill_config.home_dir, GenesisValidationMode::Full);
+    let view_client = nearcore::start_read_only_view_client(&backfill_config.home_dir, near_config)
+        .with_context(|| "start_read_only_view_client")?;
+    let (sender, receiver) = mpsc::channel(100);
+    actix::spawn(streamer::backfill(
+        view_client,
+        backfill_config.start_block_height,
+        backfill_config.end_block_height,
+        backfill_config.contract_data_filter,
+        sender,
+    ));
+    Ok(receiver)
+}
+
 /// Function that initializes configs for the node which
 /// accepts `InitConfigWrapper` and calls original `init_configs` from `neard`
 pub fn indexer_init_configs(
@@ -153,9 +211,14 @@ pub fn indexer_init_configs(
         params.genesis.as_deref(),
         params.download_genesis,
         params.download_genesis_url.as_deref(),
+        params.download_genesis_sha256.as_deref(),
+        params.download_records_url.as_deref(),
+        params.download_records_sha256.as_deref(),
         params.download_config,
         params.download_config_url.as_deref(),
+        params.download_config_sha256.as_deref(),
         params.boot_nodes.as_deref(),
         params.max_gas_burnt_view,
+        &Default::default(),
     )
 }
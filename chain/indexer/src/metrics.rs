@@ -0,0 +1,34 @@
+use near_metrics::{
+    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
+    IntGauge,
+};
+use once_cell::sync::Lazy;
+
+pub static LAKE_LAST_PUBLISHED_BLOCK_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_indexer_lake_last_published_block_height",
+        "Height of the last block uploaded to the lake bucket",
+    )
+    .unwrap()
+});
+pub static LAKE_GAPS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_indexer_lake_gaps_total",
+        "Number of times a block uploaded to the lake bucket didn't follow the previous one",
+    )
+    .unwrap()
+});
+pub static LAKE_UPLOAD_RETRIES_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_indexer_lake_upload_retries_total",
+        "Number of failed uploads to the lake bucket which were retried",
+    )
+    .unwrap()
+});
+pub static LAKE_UPLOAD_LATENCY: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_indexer_lake_upload_latency_sec",
+        "Time taken to upload an object to the lake bucket",
+    )
+    .unwrap()
+});
@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use actix::Addr;
 use async_recursion::async_recursion;
+use futures::StreamExt;
 use rocksdb::DB;
 use tokio::sync::mpsc;
 use tokio::time;
@@ -13,16 +14,17 @@ use near_indexer_primitives::{
     StreamerMessage,
 };
 use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
 use near_primitives::views;
 
-use crate::{AwaitForNodeSyncedEnum, IndexerConfig};
+use crate::{AwaitForNodeSyncedEnum, ContractDataPattern, IndexerConfig};
 
 use self::errors::FailedToFetchData;
 use self::fetchers::{
     fetch_block_by_hash, fetch_block_by_height, fetch_block_chunks, fetch_latest_block,
     fetch_outcomes, fetch_state_changes, fetch_status,
 };
-use self::utils::convert_transactions_sir_into_local_receipts;
+use self::utils::{convert_transactions_sir_into_local_receipts, filter_contract_data_changes};
 use crate::streamer::fetchers::fetch_protocol_config;
 use crate::INDEXER;
 
@@ -32,6 +34,9 @@ mod utils;
 
 const INTERVAL: Duration = Duration::from_millis(500);
 
+/// Number of blocks fetched concurrently while backfilling.
+const BACKFILL_CONCURRENCY: usize = 16;
+
 /// Blocks #47317863 and #47317864 with restored receipts.
 const PROBLEMATIC_BLOKS: [CryptoHash; 2] = [
     CryptoHash(
@@ -68,6 +73,7 @@ fn test_problematic_blocks_hash() {
 async fn build_streamer_message(
     client: &Addr<near_client::ViewClientActor>,
     block: views::BlockView,
+    contract_data_filter: Option<&[ContractDataPattern]>,
 ) -> Result<StreamerMessage, FailedToFetchData> {
     let chunks = fetch_block_chunks(&client, &block).await?;
 
@@ -87,7 +93,10 @@ async fn build_streamer_message(
             shard_id,
             chunk: None,
             receipt_execution_outcomes: vec![],
-            state_changes: state_changes.remove(&shard_id).unwrap_or_default(),
+            state_changes: filter_contract_data_changes(
+                state_changes.remove(&shard_id).unwrap_or_default(),
+                contract_data_filter,
+            ),
         })
         .collect::<Vec<_>>();
 
@@ -339,7 +348,12 @@ pub(crate) async fn start(
         );
         for block_height in start_syncing_block_height..=latest_block_height {
             if let Ok(block) = fetch_block_by_height(&view_client, block_height).await {
-                let response = build_streamer_message(&view_client, block).await;
+                let response = build_streamer_message(
+                    &view_client,
+                    block,
+                    indexer_config.contract_data_filter.as_deref(),
+                )
+                .await;
 
                 match response {
                     Ok(streamer_message) => {
@@ -366,3 +380,53 @@ pub(crate) async fn start(
         }
     }
 }
+
+/// Function that streams the blocks in the given height range as fast as the view client can
+/// fetch them, a few blocks at a time, keeping them in order.  Heights without a block are
+/// skipped.
+pub(crate) async fn backfill(
+    view_client: Addr<near_client::ViewClientActor>,
+    start_block_height: BlockHeight,
+    end_block_height: BlockHeight,
+    contract_data_filter: Option<Vec<ContractDataPattern>>,
+    blocks_sink: mpsc::Sender<StreamerMessage>,
+) {
+    info!(
+        target: INDEXER,
+        "Backfilling blocks #{} to #{}...", start_block_height, end_block_height
+    );
+    let contract_data_filter = contract_data_filter.as_deref();
+    let mut streamer_messages = futures::stream::iter(start_block_height..=end_block_height)
+        .map(|block_height| {
+            let view_client = view_client.clone();
+            async move {
+                let block = fetch_block_by_height(&view_client, block_height).await.ok()?;
+                match build_streamer_message(&view_client, block, contract_data_filter).await {
+                    Ok(streamer_message) => Some(streamer_message),
+                    Err(err) => {
+                        debug!(
+                            target: INDEXER,
+                            "Missing data, skipping block #{}...", block_height
+                        );
+                        debug!(target: INDEXER, "{:#?}", err);
+                        None
+                    }
+                }
+            }
+        })
+        .buffered(BACKFILL_CONCURRENCY);
+    while let Some(streamer_message) = streamer_messages.next().await {
+        let streamer_message = match streamer_message {
+            Some(streamer_message) => streamer_message,
+            None => continue,
+        };
+        if blocks_sink.send(streamer_message).await.is_err() {
+            info!(
+                target: INDEXER,
+                "Unable to send StreamerMessage to listener, listener doesn't listen. terminating..."
+            );
+            return;
+        }
+    }
+    info!(target: INDEXER, "Backfilled blocks #{} to #{}", start_block_height, end_block_height);
+}
@@ -4,6 +4,8 @@ use near_indexer_primitives::IndexerTransactionWithOutcome;
 use near_primitives::views;
 use node_runtime::config::tx_cost;
 
+use crate::ContractDataPattern;
+
 use super::errors::FailedToFetchData;
 use super::fetchers::fetch_block_by_hash;
 
@@ -66,3 +68,84 @@ pub(crate) async fn convert_transactions_sir_into_local_receipts(
 
     Ok(local_receipts)
 }
+
+/// Drops the contract data changes which don't match any of the patterns, if given.
+pub(crate) fn filter_contract_data_changes(
+    state_changes: views::StateChangesView,
+    contract_data_filter: Option<&[ContractDataPattern]>,
+) -> views::StateChangesView {
+    let patterns = match contract_data_filter {
+        Some(patterns) => patterns,
+        None => return state_changes,
+    };
+    state_changes
+        .into_iter()
+        .filter(|state_change| {
+            let (account_id, key) = match &state_change.value {
+                views::StateChangeValueView::DataUpdate { account_id, key, .. }
+                | views::StateChangeValueView::DataDeletion { account_id, key } => {
+                    (account_id, key)
+                }
+                _ => return true,
+            };
+            let key: &[u8] = key.as_ref();
+            patterns.iter().any(|pattern| {
+                &pattern.account_id == account_id && key.starts_with(&pattern.key_prefix)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::views::{
+        StateChangeCauseView, StateChangeValueView, StateChangeWithCauseView,
+    };
+
+    use crate::ContractDataPattern;
+
+    use super::filter_contract_data_changes;
+
+    fn data_update(account_id: &str, key: &[u8]) -> StateChangeWithCauseView {
+        StateChangeWithCauseView {
+            cause: StateChangeCauseView::ReceiptProcessing { receipt_hash: CryptoHash::default() },
+            value: StateChangeValueView::DataUpdate {
+                account_id: account_id.parse().unwrap(),
+                key: key.to_vec().into(),
+                value: b"value".to_vec().into(),
+            },
+        }
+    }
+
+    fn changes() -> Vec<StateChangeWithCauseView> {
+        vec![
+            data_update("token.near", b"balances:alice.near"),
+            data_update("token.near", b"metadata"),
+            data_update("other.near", b"balances:alice.near"),
+            StateChangeWithCauseView {
+                cause: StateChangeCauseView::InitialState,
+                value: StateChangeValueView::AccountDeletion {
+                    account_id: "token.near".parse().unwrap(),
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn test_filter_contract_data_changes() {
+        assert_eq!(filter_contract_data_changes(changes(), None).len(), 4);
+
+        let patterns = [ContractDataPattern {
+            account_id: "token.near".parse().unwrap(),
+            key_prefix: b"balances:".to_vec(),
+        }];
+        let filtered = filter_contract_data_changes(changes(), Some(&patterns));
+        assert_eq!(filtered.len(), 2);
+        assert!(matches!(
+            &filtered[0].value,
+            StateChangeValueView::DataUpdate { key, .. } if AsRef::<[u8]>::as_ref(key) == b"balances:alice.near"
+        ));
+        assert!(matches!(&filtered[1].value, StateChangeValueView::AccountDeletion { .. }));
+    }
+}
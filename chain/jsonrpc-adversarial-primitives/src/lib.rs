@@ -34,6 +34,20 @@ pub struct SetAdvOptionsRequest {
     pub disable_edge_pruning: Option<bool>,
 }
 
+#[cfg_attr(feature = "ser_de", derive(Deserialize))]
+pub struct SetNetworkFaultsRequest {
+    pub peer_id: PeerId,
+    /// Percentage of the messages to the peer to drop.
+    pub drop_percent: u8,
+}
+
+#[cfg_attr(feature = "ser_de", derive(Deserialize))]
+pub struct SetStoreFaultsRequest {
+    pub read_delay_ms: u64,
+    /// Percentage of the store reads which fail with a transient error.
+    pub read_error_percent: u8,
+}
+
 #[cfg_attr(feature = "ser_de", derive(Deserialize))]
 pub struct StartRoutingTableSyncRequest {
     pub peer_id: PeerId,
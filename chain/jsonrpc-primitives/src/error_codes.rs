@@ -0,0 +1,142 @@
+//! Machine-readable codes of the errors returned by the JSON RPC.
+//!
+//! Every error with a `cause` carries an `error_code` next to it, looked up by the name of the
+//! cause, so that clients can branch on the code and on whether retrying may help instead of
+//! parsing the messages.  A cause name means the same thing for every method, e.g.
+//! `UNKNOWN_BLOCK` is returned by `block`, `chunk`, `query` and the others alike, so it has a
+//! single code.
+//!
+//! The codes are grouped by the thousands:
+//! * 1xxx: the request is invalid,
+//! * 2xxx: the requested block, chunk, epoch or shard isn't available,
+//! * 3xxx: the requested account or contract data isn't available,
+//! * 4xxx: the requested transaction or receipt isn't available or can't be processed,
+//! * 5xxx: the node doesn't serve the request,
+//! * 9xxx: the node failed to process the request.
+//!
+//! Codes are never reused or changed.  Adding codes bumps [`RPC_ERROR_CODES_VERSION`], so that
+//! clients can tell whether the node may return codes they don't know about.
+use serde::{Deserialize, Serialize};
+
+/// Version of the table of error codes.
+pub const RPC_ERROR_CODES_VERSION: u32 = 3;
+
+/// Code, name, whether the error is retriable.  An error is retriable when the same request to
+/// the same node may succeed later.
+const ERROR_CODES: &[(u32, &str, bool)] = &[
+    (1001, "PARSE_ERROR", false),
+    (1002, "METHOD_NOT_FOUND", false),
+    (1003, "INVALID_CURSOR", false),
+    (2001, "UNKNOWN_BLOCK", false),
+    (2002, "NOT_SYNCED_YET", true),
+    (2003, "NO_SYNCED_BLOCKS", true),
+    (2004, "GARBAGE_COLLECTED_BLOCK", false),
+    (2005, "UNKNOWN_EPOCH", false),
+    (2006, "EPOCH_OUT_OF_BOUNDS", false),
+    (2007, "UNKNOWN_CHUNK", false),
+    (2008, "INVALID_SHARD_ID", false),
+    (2009, "UNAVAILABLE_SHARD", false),
+    (2010, "NODE_IS_SYNCING", true),
+    (2011, "NO_NEW_BLOCKS", true),
+    (2012, "INCONSISTENT_STATE", true),
+    (2013, "NOT_CONFIRMED", true),
+    (3001, "INVALID_ACCOUNT", false),
+    (3002, "UNKNOWN_ACCOUNT", false),
+    (3003, "NO_CONTRACT_CODE", false),
+    (3004, "TOO_LARGE_CONTRACT_STATE", false),
+    (3005, "UNKNOWN_ACCESS_KEY", false),
+    (3006, "CONTRACT_EXECUTION_ERROR", false),
+    (3007, "QUERY_TIMEOUT", true),
+    (3008, "NO_CONTRACT_ABI", false),
+    (3009, "ABI_HASH_MISMATCH", false),
+    (4001, "INVALID_TRANSACTION", false),
+    (4002, "DOES_NOT_TRACK_SHARD", false),
+    (4003, "REQUEST_ROUTED", true),
+    (4004, "UNKNOWN_TRANSACTION", true),
+    (4005, "TIMEOUT_ERROR", true),
+    (4006, "UNKNOWN_RECEIPT", true),
+    (4007, "UNKNOWN_TRANSACTION_OR_RECEIPT", true),
+    (4008, "TRACE_NOT_AVAILABLE", false),
+    (5001, "NOT_AVAILABLE", false),
+    (5002, "INVALID_DIRECTIVE", false),
+    (5003, "VALIDATOR_INFO_UNAVAILABLE", true),
+    (5004, "VALIDATOR_EVENTS_DISABLED", false),
+    (5005, "EPOCH_SUMMARY_UNAVAILABLE", false),
+    (5006, "UNSUPPORTED_METHOD", false),
+    (5007, "TOO_MANY_JOBS", true),
+    (5008, "UNKNOWN_JOB", false),
+    (9001, "INTERNAL_ERROR", true),
+];
+
+/// Machine-readable description of an RPC error.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RpcErrorCode {
+    /// Name of the cause of the error, e.g. `UNKNOWN_BLOCK`.
+    pub name: String,
+    pub code: u32,
+    /// Whether the same request to the same node may succeed later.
+    pub retriable: bool,
+    /// Version of the table of error codes the node uses, see [`RPC_ERROR_CODES_VERSION`].
+    pub version: u32,
+}
+
+impl RpcErrorCode {
+    /// The code of the cause with the given name, if it has one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ERROR_CODES.iter().find(|(_, code_name, _)| *code_name == name).map(
+            |&(code, name, retriable)| RpcErrorCode {
+                name: name.to_string(),
+                code,
+                retriable,
+                version: RPC_ERROR_CODES_VERSION,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{RpcErrorCode, ERROR_CODES};
+    use crate::errors::RpcError;
+    use crate::types::blocks::RpcBlockError;
+    use crate::types::query::RpcQueryError;
+
+    #[test]
+    fn test_error_codes() {
+        let codes: HashSet<_> = ERROR_CODES.iter().map(|(code, _, _)| code).collect();
+        let names: HashSet<_> = ERROR_CODES.iter().map(|(_, name, _)| name).collect();
+        assert_eq!(codes.len(), ERROR_CODES.len());
+        assert_eq!(names.len(), ERROR_CODES.len());
+        assert!(RpcErrorCode::from_name("NO_SUCH_ERROR").is_none());
+
+        let error_code = |error: RpcError| {
+            let error_code = error.error_code.unwrap();
+            (error_code.name, error_code.code, error_code.retriable)
+        };
+        assert_eq!(
+            error_code(RpcBlockError::NotSyncedYet.into()),
+            ("NOT_SYNCED_YET".to_string(), 2002, true)
+        );
+        assert_eq!(
+            error_code(
+                RpcQueryError::UnknownAccount {
+                    requested_account_id: "test".parse().unwrap(),
+                    block_height: 1,
+                    block_hash: Default::default(),
+                }
+                .into()
+            ),
+            ("UNKNOWN_ACCOUNT".to_string(), 3002, false)
+        );
+        assert_eq!(
+            error_code(RpcBlockError::InternalError { error_message: "error".to_string() }.into()),
+            ("INTERNAL_ERROR".to_string(), 9001, true)
+        );
+        assert_eq!(
+            error_code(RpcError::method_not_found("no_such_method".to_string())),
+            ("METHOD_NOT_FOUND".to_string(), 1002, false)
+        );
+    }
+}
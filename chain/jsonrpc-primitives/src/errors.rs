@@ -5,6 +5,8 @@ use serde_json::{to_value, Value};
 
 use near_primitives::errors::{InvalidTxError, TxExecutionError};
 
+use crate::error_codes::RpcErrorCode;
+
 #[derive(Serialize)]
 pub struct RpcParseError(pub String);
 
@@ -16,6 +18,9 @@ pub struct RpcParseError(pub String);
 pub struct RpcError {
     #[serde(flatten)]
     pub error_struct: Option<RpcErrorKind>,
+    /// Code of the cause in `error_struct`, unless it has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<RpcErrorCode>,
     /// Deprecated please use the `error_struct` instead
     pub code: i64,
     /// Deprecated please use the `error_struct` instead
@@ -53,7 +58,7 @@ impl RpcError {
     ///
     /// Mostly for completeness, doesn't do anything but filling in the corresponding fields.
     pub fn new(code: i64, message: String, data: Option<Value>) -> Self {
-        RpcError { code, message, data, error_struct: None }
+        RpcError { code, message, data, error_struct: None, error_code: None }
     }
 
     /// Create an Invalid Param error.
@@ -90,6 +95,7 @@ impl RpcError {
             error_struct: Some(RpcErrorKind::RequestValidationError(
                 RpcRequestValidationErrorKind::ParseError { error_message: e },
             )),
+            error_code: RpcErrorCode::from_name("PARSE_ERROR"),
         }
     }
 
@@ -120,6 +126,7 @@ impl RpcError {
                 "name": "INTERNAL_ERROR",
                 "info": serde_json::json!({"error_message": info})
             }))),
+            error_code: RpcErrorCode::from_name("INTERNAL_ERROR"),
         }
     }
 
@@ -128,6 +135,7 @@ impl RpcError {
             code: -32_000,
             message: "Server error".to_owned(),
             data: error_data,
+            error_code: error_struct["name"].as_str().and_then(RpcErrorCode::from_name),
             error_struct: Some(RpcErrorKind::HandlerError(error_struct)),
         }
     }
@@ -141,6 +149,7 @@ impl RpcError {
             error_struct: Some(RpcErrorKind::RequestValidationError(
                 RpcRequestValidationErrorKind::MethodNotFound { method_name: method },
             )),
+            error_code: RpcErrorCode::from_name("METHOD_NOT_FOUND"),
         }
     }
 }
@@ -1,3 +1,4 @@
+pub mod error_codes;
 pub mod errors;
 pub mod message;
 pub(crate) mod metrics;
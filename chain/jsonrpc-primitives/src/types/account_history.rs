@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAccountHistoryRequest {
+    pub account_id: near_primitives::types::AccountId,
+    /// `next_cursor` of the previous page.  Defaults to the newest entry.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+impl RpcAccountHistoryRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcAccountHistoryRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAccountHistoryResponse {
+    #[serde(flatten)]
+    pub account_history_view: near_primitives::views::AccountHistoryView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcAccountHistoryError {
+    #[error("Account history is not available on this node: {error_message}")]
+    NotAvailable { error_message: String },
+    #[error("Cursor {cursor} is not a cursor returned by EXPERIMENTAL_account_history")]
+    InvalidCursor { cursor: String },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::GetAccountHistoryError> for RpcAccountHistoryError {
+    fn from(error: near_client_primitives::types::GetAccountHistoryError) -> Self {
+        match error {
+            near_client_primitives::types::GetAccountHistoryError::Disabled => {
+                Self::NotAvailable { error_message: error.to_string() }
+            }
+            near_client_primitives::types::GetAccountHistoryError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            near_client_primitives::types::GetAccountHistoryError::Unreachable(
+                ref error_message,
+            ) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", &error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcAccountHistoryError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcAccountHistoryError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcAccountHistoryError> for crate::errors::RpcError {
+    fn from(error: RpcAccountHistoryError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcAccountHistoryError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
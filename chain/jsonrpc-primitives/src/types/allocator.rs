@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAllocatorStatsRequest {
+    /// Also dumps the heap profile of the node to a file, which requires the node to be built
+    /// with the heap profiling of the allocator and started with it enabled.
+    #[serde(default)]
+    pub dump_heap_profile: bool,
+}
+
+impl RpcAllocatorStatsRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcAllocatorStatsRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAllocatorStatsResponse {
+    #[serde(flatten)]
+    pub allocator_stats: AllocatorStats,
+    /// Path of the heap profile on the node, if one was dumped.
+    pub heap_profile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AllocatorStats {
+    pub allocator: String,
+    pub allocated: u64,
+    pub active: u64,
+    pub metadata: u64,
+    pub resident: u64,
+    pub mapped: u64,
+    pub retained: u64,
+    /// Share of the active bytes which aren't allocated, i.e. lost to fragmentation.
+    pub fragmentation: f64,
+    pub arenas: Vec<ArenaStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ArenaStats {
+    pub index: u32,
+    /// Number of threads assigned to the arena.
+    pub threads: u32,
+    pub active: u64,
+    /// Bytes in the unused pages which weren't returned to the OS yet.
+    pub dirty: u64,
+    pub muzzy: u64,
+    pub resident: u64,
+    pub mapped: u64,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcAllocatorStatsError {
+    #[error("Allocator statistics are not available on this node: {error_message}")]
+    NotAvailable { error_message: String },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcAllocatorStatsError> for crate::errors::RpcError {
+    fn from(error: RpcAllocatorStatsError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcAllocatorStatsError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -22,6 +22,26 @@ pub struct RpcProtocolConfigResponse {
     pub config_view: near_chain_configs::ProtocolConfigView,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcProtocolConfigDiffRequest {
+    pub from: near_primitives::types::ProtocolVersionReference,
+    pub to: near_primitives::types::ProtocolVersionReference,
+}
+
+impl RpcProtocolConfigDiffRequest {
+    pub fn parse(
+        value: Option<Value>,
+    ) -> Result<RpcProtocolConfigDiffRequest, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcProtocolConfigDiffRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcProtocolConfigDiffResponse {
+    #[serde(flatten)]
+    pub config_diff_view: near_primitives::views::RuntimeConfigDiffView,
+}
+
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcProtocolConfigError {
@@ -30,6 +50,8 @@ pub enum RpcProtocolConfigError {
         #[serde(skip_serializing)]
         error_message: String,
     },
+    #[error("Epoch is unknown to this node: {error_message}")]
+    UnknownEpoch { error_message: String },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
 }
@@ -40,6 +62,9 @@ impl From<near_client_primitives::types::GetProtocolConfigError> for RpcProtocol
             near_client_primitives::types::GetProtocolConfigError::UnknownBlock(error_message) => {
                 Self::UnknownBlock { error_message }
             }
+            near_client_primitives::types::GetProtocolConfigError::UnknownEpoch(error_message) => {
+                Self::UnknownEpoch { error_message }
+            }
             near_client_primitives::types::GetProtocolConfigError::IOError(error_message) => {
                 Self::InternalError { error_message }
             }
@@ -68,7 +93,10 @@ impl From<RpcProtocolConfigError> for crate::errors::RpcError {
             RpcProtocolConfigError::UnknownBlock { error_message } => {
                 Some(Value::String(format!("Block Not Found: {}", error_message)))
             }
-            RpcProtocolConfigError::InternalError { .. } => Some(Value::String(error.to_string())),
+            RpcProtocolConfigError::UnknownEpoch { .. }
+            | RpcProtocolConfigError::InternalError { .. } => {
+                Some(Value::String(error.to_string()))
+            }
         };
 
         let error_data_value = match serde_json::to_value(error) {
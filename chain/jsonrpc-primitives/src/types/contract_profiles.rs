@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcContractProfilesResponse {
+    /// Profiles of the contract calls made since the previous request, oldest first.
+    pub profiles: Vec<ContractCallProfileView>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractCallProfileView {
+    pub code_hash: near_primitives::hash::CryptoHash,
+    pub method_name: String,
+    /// Wall time of the call, the only part of the profile which isn't deterministic.
+    pub wall_time_us: u64,
+    pub burnt_gas: near_primitives::types::Gas,
+    /// Number of calls of every host function, including the `gas` function charging for the
+    /// wasm instructions.
+    pub host_function_calls: BTreeMap<String, u64>,
+    pub gas_profile: Vec<near_primitives::views::CostGasUsed>,
+    /// Executed wasm instructions and their gas by opcode.  Empty for the contracts prepared
+    /// under the old protocol versions, which the profiler doesn't instrument.
+    pub opcodes: BTreeMap<String, OpcodeProfileView>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OpcodeProfileView {
+    pub count: u64,
+    pub gas: near_primitives::types::Gas,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcContractProfilesError {
+    #[error("Contract profiles are not available on this node: {error_message}")]
+    NotAvailable { error_message: String },
+}
+
+impl From<RpcContractProfilesError> for crate::errors::RpcError {
+    fn from(error: RpcContractProfilesError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcContractProfilesError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
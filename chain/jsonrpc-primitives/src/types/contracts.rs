@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAccountsByCodeHashRequest {
+    #[serde(flatten)]
+    pub block_reference: near_primitives::types::BlockReference,
+    pub code_hash: near_primitives::hash::CryptoHash,
+}
+
+impl RpcAccountsByCodeHashRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcAccountsByCodeHashRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcAccountsByCodeHashResponse {
+    #[serde(flatten)]
+    pub accounts_by_code_hash_view: near_primitives::views::AccountsByCodeHashView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcAccountsByCodeHashError {
+    #[error("Block not found: {error_message}")]
+    UnknownBlock {
+        #[serde(skip_serializing)]
+        error_message: String,
+    },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::GetAccountsByCodeHashError>
+    for RpcAccountsByCodeHashError
+{
+    fn from(error: near_client_primitives::types::GetAccountsByCodeHashError) -> Self {
+        match error {
+            near_client_primitives::types::GetAccountsByCodeHashError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            near_client_primitives::types::GetAccountsByCodeHashError::UnknownBlock(
+                error_message,
+            ) => Self::UnknownBlock { error_message },
+            near_client_primitives::types::GetAccountsByCodeHashError::Unreachable(
+                ref error_message,
+            ) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", &error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcAccountsByCodeHashError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcAccountsByCodeHashError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcAccountsByCodeHashError> for crate::errors::RpcError {
+    fn from(error: RpcAccountsByCodeHashError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcAccountsByCodeHashError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcGasAttributionRequest {
+    /// Epoch to report. Defaults to the most recent epoch with applied chunks.
+    #[serde(default)]
+    pub epoch_id: Option<near_primitives::types::EpochId>,
+}
+
+impl RpcGasAttributionRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcGasAttributionRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcGasAttributionResponse {
+    #[serde(flatten)]
+    pub gas_attribution_view: near_primitives::views::GasAttributionView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcGasAttributionError {
+    #[error("Gas attribution is not available on this node: {error_message}")]
+    NotAvailable { error_message: String },
+    #[error("No gas attribution was collected for the requested epoch")]
+    UnknownEpoch,
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::GetGasAttributionError> for RpcGasAttributionError {
+    fn from(error: near_client_primitives::types::GetGasAttributionError) -> Self {
+        match error {
+            near_client_primitives::types::GetGasAttributionError::Disabled => {
+                Self::NotAvailable { error_message: error.to_string() }
+            }
+            near_client_primitives::types::GetGasAttributionError::UnknownEpoch => {
+                Self::UnknownEpoch
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcGasAttributionError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcGasAttributionError> for crate::errors::RpcError {
+    fn from(error: RpcGasAttributionError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcGasAttributionError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
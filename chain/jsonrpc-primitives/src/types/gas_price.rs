@@ -83,3 +83,66 @@ impl RpcGasPriceRequest {
             .map(|(block_id,)| RpcGasPriceRequest { block_id })
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcFeeEstimateRequest {
+    pub shard_id: near_primitives::types::ShardId,
+}
+
+impl RpcFeeEstimateRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcFeeEstimateRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcFeeEstimateResponse {
+    #[serde(flatten)]
+    pub fee_estimate_view: near_primitives::views::FeeEstimateView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcFeeEstimateError {
+    #[error("There are no fully synchronized blocks on the node yet")]
+    NoSyncedBlocks,
+    #[error("Shard id {shard_id} does not exist")]
+    InvalidShardId { shard_id: near_primitives::types::ShardId },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::GetFeeEstimateError> for RpcFeeEstimateError {
+    fn from(error: near_client_primitives::types::GetFeeEstimateError) -> Self {
+        match error {
+            near_client_primitives::types::GetFeeEstimateError::NoSyncedBlocks => {
+                Self::NoSyncedBlocks
+            }
+            near_client_primitives::types::GetFeeEstimateError::InvalidShardId { shard_id } => {
+                Self::InvalidShardId { shard_id }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcFeeEstimateError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcFeeEstimateError> for crate::errors::RpcError {
+    fn from(error: RpcFeeEstimateError) -> Self {
+        let error_data = Some(Value::String(error.to_string()));
+        let error_data_value = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcFeeEstimateError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(error_data, error_data_value)
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Identifier of a request scheduled for background execution.  Random, so that clients cannot
+/// poll the results of jobs submitted by others.
+pub type JobId = String;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RpcSubmitJobRequest {
+    /// Name of the RPC method to execute in the background.
+    pub method: String,
+    /// Parameters of the RPC method, in the same format as for a direct call.
+    #[serde(default)]
+    pub params: Option<Value>,
+}
+
+impl RpcSubmitJobRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        Ok(crate::utils::parse_params::<RpcSubmitJobRequest>(value)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcSubmitJobResponse {
+    pub job_id: JobId,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcPollResultRequest {
+    pub job_id: JobId,
+}
+
+impl RpcPollResultRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        Ok(crate::utils::parse_params::<RpcPollResultRequest>(value)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RpcPollResultResponse {
+    /// The job is queued or still being executed.
+    Pending,
+    /// The job has finished, `result` is what a direct call of the method would return.
+    Completed { result: Value },
+    /// The job has finished with an error, `error` is what a direct call of the method would
+    /// return.
+    Failed { error: crate::errors::RpcError },
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcJobError {
+    #[error("Method {method_name} cannot be executed as a background job")]
+    UnsupportedMethod { method_name: String },
+    #[error("Too many jobs are pending on this node. Try again later")]
+    TooManyJobs,
+    #[error("Job {job_id} is unknown or its result has already expired")]
+    UnknownJob { job_id: JobId },
+}
+
+impl From<RpcJobError> for crate::errors::RpcError {
+    fn from(error: RpcJobError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcJobError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
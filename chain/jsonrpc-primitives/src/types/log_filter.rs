@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+fn default_duration_secs() -> u64 {
+    600
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcLogFilterRequest {
+    /// Replaces the log filter directives of the node, in the `RUST_LOG` format, e.g.
+    /// `info,network=debug`.
+    #[serde(default)]
+    pub directives: Option<String>,
+    /// Directives applied on top of the others for `duration_secs`, in the same format.
+    #[serde(default)]
+    pub temporary_directives: Option<String>,
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,
+}
+
+impl RpcLogFilterRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcLogFilterRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcLogFilterResponse {
+    /// Log filter directives in effect, in the `RUST_LOG` format.
+    pub directives: String,
+    pub temporary_directives: Vec<RpcTemporaryLogDirective>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcTemporaryLogDirective {
+    pub directive: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcLogFilterError {
+    #[error("The log filter can't be changed on this node: {error_message}")]
+    NotAvailable { error_message: String },
+    #[error("Invalid log filter directive: {error_message}")]
+    InvalidDirective { error_message: String },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcLogFilterError> for crate::errors::RpcError {
+    fn from(error: RpcLogFilterError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcLogFilterError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
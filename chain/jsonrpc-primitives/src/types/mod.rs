@@ -1,9 +1,16 @@
+pub mod account_history;
+pub mod allocator;
 pub mod blocks;
 pub mod changes;
 pub mod chunks;
 pub mod config;
+pub mod contract_profiles;
+pub mod contracts;
+pub mod gas_attribution;
 pub mod gas_price;
+pub mod jobs;
 pub mod light_client;
+pub mod log_filter;
 pub mod network_info;
 pub mod query;
 pub mod receipts;
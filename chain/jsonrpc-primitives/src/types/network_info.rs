@@ -1,6 +1,7 @@
-use near_network_primitives::types::{KnownProducer, PeerInfo};
+use near_client_primitives::types::ConnectedPeerInfo;
+use near_network_primitives::types::KnownProducer;
 use near_primitives::network::PeerId;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, BlockHeight, ProtocolVersion};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
@@ -9,6 +10,19 @@ pub struct RpcPeerInfo {
     pub id: PeerId,
     pub addr: Option<SocketAddr>,
     pub account_id: Option<AccountId>,
+    /// The fields below are missing in the responses of older nodes.
+    pub height: Option<BlockHeight>,
+    pub protocol_version: Option<ProtocolVersion>,
+    /// Version of the client the peer runs, unless it's too old to send it.
+    pub client_version: Option<RpcPeerClientVersion>,
+    /// Seconds since the connection to the peer was established.
+    pub connection_age_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcPeerClientVersion {
+    pub version: String,
+    pub build: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -36,9 +50,20 @@ pub enum RpcNetworkInfoError {
     InternalError { error_message: String },
 }
 
-impl From<PeerInfo> for RpcPeerInfo {
-    fn from(peer_info: PeerInfo) -> Self {
-        Self { id: peer_info.id, addr: peer_info.addr, account_id: peer_info.account_id }
+impl From<ConnectedPeerInfo> for RpcPeerInfo {
+    fn from(peer: ConnectedPeerInfo) -> Self {
+        Self {
+            id: peer.peer_info.id,
+            addr: peer.peer_info.addr,
+            account_id: peer.peer_info.account_id,
+            height: Some(peer.height),
+            protocol_version: Some(peer.protocol_version),
+            client_version: peer.client_version.map(|client_version| RpcPeerClientVersion {
+                version: client_version.version,
+                build: client_version.build,
+            }),
+            connection_age_secs: Some(peer.connection_age.as_secs()),
+        }
     }
 }
 
@@ -48,12 +48,32 @@ pub enum RpcQueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("ABI of contract ID {contract_account_id} is not available on the node")]
+    NoContractAbi {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("ABI of contract ID {contract_account_id} has hash {abi_hash}, but the contract code declares {declared_hash}")]
+    AbiHashMismatch {
+        contract_account_id: near_primitives::types::AccountId,
+        abi_hash: near_primitives::hash::CryptoHash,
+        declared_hash: near_primitives::hash::CryptoHash,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("State of contract {contract_account_id} is too large to be viewed")]
     TooLargeContractState {
         contract_account_id: near_primitives::types::AccountId,
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Viewing the state of contract {contract_account_id} took too long")]
+    QueryTimeout {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("Access key for public key {public_key} has never been observed on the node")]
     UnknownAccessKey {
         public_key: near_crypto::PublicKey,
@@ -87,6 +107,7 @@ pub enum QueryResponseKind {
     CallResult(near_primitives::views::CallResult),
     AccessKey(near_primitives::views::AccessKeyView),
     AccessKeyList(near_primitives::views::AccessKeyList),
+    ViewAbi(near_primitives::views::ContractAbiView),
 }
 
 impl RpcQueryRequest {
@@ -198,6 +219,24 @@ impl From<near_client_primitives::types::QueryError> for RpcQueryError {
                 block_height,
                 block_hash,
             } => Self::NoContractCode { contract_account_id, block_height, block_hash },
+            near_client_primitives::types::QueryError::NoContractAbi {
+                contract_account_id,
+                block_height,
+                block_hash,
+            } => Self::NoContractAbi { contract_account_id, block_height, block_hash },
+            near_client_primitives::types::QueryError::AbiHashMismatch {
+                contract_account_id,
+                abi_hash,
+                declared_hash,
+                block_height,
+                block_hash,
+            } => Self::AbiHashMismatch {
+                contract_account_id,
+                abi_hash,
+                declared_hash,
+                block_height,
+                block_hash,
+            },
             near_client_primitives::types::QueryError::UnknownAccessKey {
                 public_key,
                 block_height,
@@ -220,6 +259,11 @@ impl From<near_client_primitives::types::QueryError> for RpcQueryError {
                 block_height,
                 block_hash,
             } => Self::TooLargeContractState { contract_account_id, block_height, block_hash },
+            near_client_primitives::types::QueryError::QueryTimeout {
+                contract_account_id,
+                block_height,
+                block_hash,
+            } => Self::QueryTimeout { contract_account_id, block_height, block_hash },
         }
     }
 }
@@ -255,6 +299,9 @@ impl From<near_primitives::views::QueryResponseKind> for QueryResponseKind {
             near_primitives::views::QueryResponseKind::AccessKeyList(access_key_list) => {
                 Self::AccessKeyList(access_key_list)
             }
+            near_primitives::views::QueryResponseKind::ViewAbi(contract_abi_view) => {
+                Self::ViewAbi(contract_abi_view)
+            }
         }
     }
 }
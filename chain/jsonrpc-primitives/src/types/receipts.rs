@@ -80,3 +80,161 @@ impl From<RpcReceiptError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcReceiptTraceRequest {
+    #[serde(flatten)]
+    pub receipt_reference: ReceiptReference,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcReceiptTraceResponse {
+    #[serde(flatten)]
+    pub receipt_trace_view: near_primitives::views::ReceiptTraceView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcReceiptTraceError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+    #[error("Execution outcome of receipt {receipt_id} is unknown to this node")]
+    UnknownReceipt { receipt_id: near_primitives::hash::CryptoHash },
+    #[error("Receipt tracing is not available on this node: {error_message}")]
+    TraceNotAvailable { error_message: String },
+}
+
+impl RpcReceiptTraceRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        let receipt_reference = crate::utils::parse_params::<ReceiptReference>(value)?;
+        Ok(Self { receipt_reference })
+    }
+}
+
+impl From<near_client_primitives::types::TraceReceiptError> for RpcReceiptTraceError {
+    fn from(error: near_client_primitives::types::TraceReceiptError) -> Self {
+        match error {
+            near_client_primitives::types::TraceReceiptError::IOError(error_message)
+            | near_client_primitives::types::TraceReceiptError::ReplayFailed(error_message) => {
+                Self::InternalError { error_message }
+            }
+            near_client_primitives::types::TraceReceiptError::UnknownReceipt(hash) => {
+                Self::UnknownReceipt { receipt_id: hash }
+            }
+            near_client_primitives::types::TraceReceiptError::NotArchival
+            | near_client_primitives::types::TraceReceiptError::NotEnabled => {
+                Self::TraceNotAvailable { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcReceiptTraceError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcReceiptTraceError> for crate::errors::RpcError {
+    fn from(error: RpcReceiptTraceError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcReceiptTraceError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcDelayedReceiptsRequest {
+    #[serde(flatten)]
+    pub block_reference: near_primitives::types::BlockReference,
+    pub shard_id: near_primitives::types::ShardId,
+    /// Index of the first returned receipt.  Defaults to the head of the queue.
+    #[serde(default)]
+    pub from_index: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+impl RpcDelayedReceiptsRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcDelayedReceiptsRequest>(value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcDelayedReceiptsResponse {
+    #[serde(flatten)]
+    pub delayed_receipts_view: near_primitives::views::DelayedReceiptsView,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcDelayedReceiptsError {
+    #[error("Block not found: {error_message}")]
+    UnknownBlock {
+        #[serde(skip_serializing)]
+        error_message: String,
+    },
+    #[error("Shard id {shard_id} does not exist")]
+    InvalidShardId { shard_id: near_primitives::types::ShardId },
+    #[error("Shard {shard_id} is not tracked by this node")]
+    UnavailableShard { shard_id: near_primitives::types::ShardId },
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_client_primitives::types::GetDelayedReceiptsError> for RpcDelayedReceiptsError {
+    fn from(error: near_client_primitives::types::GetDelayedReceiptsError) -> Self {
+        match error {
+            near_client_primitives::types::GetDelayedReceiptsError::IOError(error_message) => {
+                Self::InternalError { error_message }
+            }
+            near_client_primitives::types::GetDelayedReceiptsError::UnknownBlock(error_message) => {
+                Self::UnknownBlock { error_message }
+            }
+            near_client_primitives::types::GetDelayedReceiptsError::InvalidShardId(shard_id) => {
+                Self::InvalidShardId { shard_id }
+            }
+            near_client_primitives::types::GetDelayedReceiptsError::UnavailableShard(shard_id) => {
+                Self::UnavailableShard { shard_id }
+            }
+            near_client_primitives::types::GetDelayedReceiptsError::Unreachable(
+                ref error_message,
+            ) => {
+                tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", &error_message);
+                crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
+                    .with_label_values(&["RpcDelayedReceiptsError"])
+                    .inc();
+                Self::InternalError { error_message: error.to_string() }
+            }
+        }
+    }
+}
+
+impl From<actix::MailboxError> for RpcDelayedReceiptsError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcDelayedReceiptsError> for crate::errors::RpcError {
+    fn from(error: RpcDelayedReceiptsError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcDelayedReceiptsError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -1,5 +1,5 @@
 use near_primitives::state_record::StateRecord;
-use near_primitives::types::BlockHeightDelta;
+use near_primitives::types::{Balance, BlockHeightDelta};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -86,3 +86,91 @@ impl From<RpcSandboxFastForwardError> for crate::errors::RpcError {
         Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
     }
 }
+
+#[derive(Deserialize, Serialize)]
+pub struct RpcSandboxSetTimestampRequest {
+    /// Unix timestamp in nanoseconds the next produced block should have.
+    #[serde(with = "near_primitives::serialize::u64_dec_format")]
+    pub timestamp: u64,
+}
+
+impl RpcSandboxSetTimestampRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        Ok(crate::utils::parse_params::<RpcSandboxSetTimestampRequest>(value)?)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RpcSandboxSetTimestampResponse {}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxSetTimestampError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<actix::MailboxError> for RpcSandboxSetTimestampError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcSandboxSetTimestampError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxSetTimestampError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxSetTimestampError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RpcSandboxSetGasPriceRequest {
+    /// Gas price of all new blocks, `null` restores the regular gas price computation.
+    #[serde(default, with = "near_primitives::serialize::option_u128_dec_format")]
+    pub gas_price: Option<Balance>,
+}
+
+impl RpcSandboxSetGasPriceRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        Ok(crate::utils::parse_params::<RpcSandboxSetGasPriceRequest>(value)?)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RpcSandboxSetGasPriceResponse {}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcSandboxSetGasPriceError {
+    #[error("The node reached its limits. Try again later. More details: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<actix::MailboxError> for RpcSandboxSetGasPriceError {
+    fn from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl From<RpcSandboxSetGasPriceError> for crate::errors::RpcError {
+    fn from(error: RpcSandboxSetGasPriceError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcSandboxSetGasPriceError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
@@ -6,6 +6,55 @@ use near_primitives::types::AccountId;
 #[derive(Debug, Clone)]
 pub struct RpcBroadcastTransactionRequest {
     pub signed_transaction: near_primitives::transaction::SignedTransaction,
+    /// Whether to validate the transaction against the state before sending it and return the
+    /// rejection instead of sending an invalid transaction.
+    pub preflight: bool,
+    /// Finality of the blocks executing the transaction and its receipts which
+    /// `broadcast_tx_commit` waits for.
+    pub finality: near_primitives::types::Finality,
+    /// How long `broadcast_tx_commit` waits, instead of the polling timeout of the node.
+    pub timeout: Option<std::time::Duration>,
+}
+
+/// Options of the `broadcast_tx_*` methods, passed as the optional second positional parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RpcBroadcastTransactionOptions {
+    /// Validate the nonce, the balance for gas and deposit and the access key restrictions of the
+    /// transaction before sending it.  Nodes which don't track the shard of the signer can't do
+    /// it and reject the transaction with `DOES_NOT_TRACK_SHARD`.
+    #[serde(default)]
+    pub preflight: bool,
+    /// Finality of the blocks executing the transaction and its receipts which
+    /// `broadcast_tx_commit` waits for, `optimistic` by default.
+    #[serde(default)]
+    pub finality: Option<near_primitives::types::Finality>,
+    /// How long `broadcast_tx_commit` waits in seconds, up to the maximum polling timeout of the
+    /// node.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Progress of a transaction sent over the `/tx_commit` WebSocket.  `COMPLETED` and `FAILED` are
+/// the last messages of the stream.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcTransactionProgress {
+    /// The node accepted the transaction and either added it to its pool or routed it.
+    Sent {
+        transaction_hash: near_primitives::hash::CryptoHash,
+    },
+    /// The transaction and its receipts were executed, but not all in blocks with the requested
+    /// finality yet.
+    Executed {
+        execution_status: near_primitives::views::FinalExecutionStatus,
+    },
+    /// The transaction and its receipts were executed in blocks with the requested finality.
+    Completed {
+        final_execution_outcome: near_primitives::views::FinalExecutionOutcomeViewEnum,
+    },
+    Failed {
+        error: crate::errors::RpcError,
+    },
 }
 
 #[derive(Debug)]
@@ -55,8 +104,25 @@ pub struct RpcBroadcastTxSyncResponse {
 
 impl RpcBroadcastTransactionRequest {
     pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
-        let signed_transaction = crate::utils::parse_signed_transaction(value)?;
-        Ok(Self { signed_transaction })
+        if let Ok((encoded, options)) =
+            crate::utils::parse_params::<(String, RpcBroadcastTransactionOptions)>(value.clone())
+        {
+            let signed_transaction = crate::utils::decode_signed_transaction(&encoded)?;
+            Ok(Self {
+                signed_transaction,
+                preflight: options.preflight,
+                finality: options.finality.unwrap_or(near_primitives::types::Finality::None),
+                timeout: options.timeout_secs.map(std::time::Duration::from_secs),
+            })
+        } else {
+            let signed_transaction = crate::utils::parse_signed_transaction(value)?;
+            Ok(Self {
+                signed_transaction,
+                preflight: false,
+                finality: near_primitives::types::Finality::None,
+                timeout: None,
+            })
+        }
     }
 }
 
@@ -11,6 +11,10 @@ pub enum RpcValidatorError {
     UnknownEpoch,
     #[error("Validator info unavailable")]
     ValidatorInfoUnavailable,
+    #[error("Validator events are disabled on this node")]
+    ValidatorEventsDisabled,
+    #[error("Summary of the epoch is unavailable on this node")]
+    EpochSummaryUnavailable,
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
 }
@@ -32,6 +36,79 @@ pub struct RpcValidatorResponse {
     pub validator_info: near_primitives::views::EpochValidatorInfo,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcValidatorEventsRequest {
+    /// Id of the first event to return, events with smaller ids are skipped.
+    #[serde(default)]
+    pub from_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcValidatorEventsResponse {
+    #[serde(flatten)]
+    pub validator_events: near_primitives::views::ValidatorEventsView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMissedProductionRequest {
+    /// Id of the first miss to return, misses with smaller ids are skipped.
+    #[serde(default)]
+    pub from_id: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMissedProductionResponse {
+    pub misses: Vec<near_primitives::views::MissedProductionView>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcEpochSummaryRequest {
+    /// Height of the epoch to summarize, the latest epoch which ended if not set.
+    #[serde(default)]
+    pub epoch_height: Option<near_primitives::types::EpochHeight>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcEpochSummaryResponse {
+    #[serde(flatten)]
+    pub summary: near_primitives::views::EpochSummaryView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcValidatorsProjectionResponse {
+    #[serde(flatten)]
+    pub projection: near_primitives::views::EpochValidatorProjectionView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcProtocolVersionVotesResponse {
+    #[serde(flatten)]
+    pub votes: near_primitives::views::ProtocolVersionVotesView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcRewardProjectionResponse {
+    #[serde(flatten)]
+    pub projection: near_primitives::views::EpochRewardProjectionView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcProducerScheduleResponse {
+    #[serde(flatten)]
+    pub schedule: near_primitives::views::ProducerScheduleView,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMaintenanceWindowsRequest {
+    pub account_id: near_primitives::types::AccountId,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcMaintenanceWindowsResponse {
+    #[serde(flatten)]
+    pub maintenance_windows: near_primitives::views::MaintenanceWindowsView,
+}
+
 impl From<near_client_primitives::types::GetValidatorInfoError> for RpcValidatorError {
     fn from(error: near_client_primitives::types::GetValidatorInfoError) -> Self {
         match error {
@@ -57,12 +134,56 @@ impl From<near_client_primitives::types::GetValidatorInfoError> for RpcValidator
     }
 }
 
+impl From<near_client_primitives::types::GetValidatorEventsError> for RpcValidatorError {
+    fn from(error: near_client_primitives::types::GetValidatorEventsError) -> Self {
+        match error {
+            near_client_primitives::types::GetValidatorEventsError::Disabled => {
+                Self::ValidatorEventsDisabled
+            }
+        }
+    }
+}
+
+impl From<near_client_primitives::types::GetEpochSummaryError> for RpcValidatorError {
+    fn from(error: near_client_primitives::types::GetEpochSummaryError) -> Self {
+        match error {
+            near_client_primitives::types::GetEpochSummaryError::Unavailable => {
+                Self::EpochSummaryUnavailable
+            }
+        }
+    }
+}
+
 impl From<actix::MailboxError> for RpcValidatorError {
     fn from(error: actix::MailboxError) -> Self {
         Self::InternalError { error_message: error.to_string() }
     }
 }
 
+impl RpcValidatorEventsRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcValidatorEventsRequest>(value)
+    }
+}
+
+impl RpcMissedProductionRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcMissedProductionRequest>(value)
+    }
+}
+
+impl RpcEpochSummaryRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcEpochSummaryRequest>(value)
+    }
+}
+
+impl RpcMaintenanceWindowsRequest {
+    pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
+        crate::utils::parse_params::<RpcMaintenanceWindowsRequest>(value)
+    }
+}
+
 impl RpcValidatorRequest {
     pub fn parse(value: Option<Value>) -> Result<Self, crate::errors::RpcParseError> {
         let epoch_reference = if let Ok((block_id,)) =
@@ -92,6 +213,8 @@ impl From<RpcValidatorError> for crate::errors::RpcError {
             RpcValidatorError::ValidatorInfoUnavailable => {
                 Some(Value::String(format!("Validator info unavailable")))
             }
+            RpcValidatorError::ValidatorEventsDisabled => Some(Value::String(error.to_string())),
+            RpcValidatorError::EpochSummaryUnavailable => Some(Value::String(error.to_string())),
             RpcValidatorError::InternalError { .. } => Some(Value::String(error.to_string())),
         };
 
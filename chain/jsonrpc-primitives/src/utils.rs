@@ -18,7 +18,13 @@ pub(crate) fn parse_signed_transaction(
     value: Option<Value>,
 ) -> Result<near_primitives::transaction::SignedTransaction, crate::errors::RpcParseError> {
     let (encoded,) = crate::utils::parse_params::<(String,)>(value.clone())?;
-    let bytes = near_primitives_core::serialize::from_base64(&encoded)
+    decode_signed_transaction(&encoded)
+}
+
+pub(crate) fn decode_signed_transaction(
+    encoded: &str,
+) -> Result<near_primitives::transaction::SignedTransaction, crate::errors::RpcParseError> {
+    let bytes = near_primitives_core::serialize::from_base64(encoded)
         .map_err(|err| crate::errors::RpcParseError(err.to_string()))?;
     Ok(near_primitives::transaction::SignedTransaction::try_from_slice(&bytes).map_err(|err| {
         crate::errors::RpcParseError(format!("Failed to decode transaction: {}", err))
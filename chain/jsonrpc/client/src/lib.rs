@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use awc::{Client, Connector};
+use borsh::BorshDeserialize;
 use futures::{future, future::LocalBoxFuture, FutureExt, TryFutureExt};
 use serde::Deserialize;
 use serde::Serialize;
@@ -10,13 +11,20 @@ use near_jsonrpc_primitives::message::{from_slice, Message};
 use near_jsonrpc_primitives::types::changes::{
     RpcStateChangesInBlockByTypeRequest, RpcStateChangesInBlockByTypeResponse,
 };
-use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
+use near_jsonrpc_primitives::types::network_info::RpcNetworkInfoResponse;
+use near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionOptions;
+use near_jsonrpc_primitives::types::validator::{
+    RpcEpochSummaryRequest, RpcEpochSummaryResponse, RpcMaintenanceWindowsRequest,
+    RpcMaintenanceWindowsResponse, RpcMissedProductionRequest, RpcMissedProductionResponse,
+    RpcValidatorEventsRequest, RpcValidatorEventsResponse, RpcValidatorsOrderedRequest,
+};
 use near_primitives::hash::CryptoHash;
 use near_primitives::types::{AccountId, BlockId, BlockReference, MaybeBlockId, ShardId};
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, FinalExecutionOutcomeView, GasPriceView,
-    StatusResponse,
+    BlockView, ChunkView, EpochRewardProjectionView, EpochValidatorInfo,
+    EpochValidatorProjectionView, FinalExecutionOutcomeView, GasPriceView, ProducerScheduleView,
+    ProtocolVersionVotesView, StatusResponse,
 };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -72,6 +80,56 @@ where
         .boxed_local()
 }
 
+/// Prepare a `RPCRequest` which asks for the Borsh-encoded response.  Errors are still returned
+/// in JSON.
+fn call_method_borsh<P, R>(
+    client: &Client,
+    server_addr: &str,
+    method: &str,
+    params: P,
+) -> RpcRequest<R>
+where
+    P: Serialize,
+    R: BorshDeserialize + 'static,
+{
+    let request =
+        Message::request(method.to_string(), Some(serde_json::to_value(&params).unwrap()));
+    client
+        .post(server_addr)
+        .insert_header(("Content-Type", "application/json"))
+        .insert_header(("Accept", "application/borsh"))
+        .send_json(&request)
+        .map_err(|err| RpcError::new_internal_error(None, format!("{:?}", err)))
+        .and_then(|mut response| {
+            let is_borsh = response
+                .headers()
+                .get("Content-Type")
+                .map_or(false, |content_type| content_type == "application/borsh");
+            response.body().limit(PAYLOAD_LIMIT).map(move |body| {
+                let bytes = body.map_err(|err| {
+                    RpcError::parse_error(format!("Failed to retrieve payload: {:?}", err))
+                })?;
+                if is_borsh {
+                    return R::try_from_slice(&bytes).map_err(|err| {
+                        RpcError::parse_error(format!("Failed to parse: {:?}", err))
+                    });
+                }
+                match from_slice(&bytes) {
+                    Ok(Message::Response(resp)) => Err(resp.result.err().unwrap_or_else(|| {
+                        RpcError::parse_error("Expected a Borsh-encoded response".to_string())
+                    })),
+                    Ok(_) => {
+                        Err(RpcError::parse_error(format!("Failed to parse JSON RPC response")))
+                    }
+                    Err(err) => {
+                        Err(RpcError::parse_error(format!("Error {:?} in {:?}", err, bytes)))
+                    }
+                }
+            })
+        })
+        .boxed_local()
+}
+
 /// Prepare a `HttpRequest` with a given client, server address and parameters.
 fn call_http_get<R, P>(
     client: &Client,
@@ -186,11 +244,23 @@ jsonrpc_client!(pub struct JsonRpcClient {
     pub fn broadcast_tx_async(&self, tx: String) -> RpcRequest<String>;
     pub fn broadcast_tx_commit(&self, tx: String) -> RpcRequest<FinalExecutionOutcomeView>;
     pub fn status(&self) -> RpcRequest<StatusResponse>;
+    pub fn network_info(&self) -> RpcRequest<RpcNetworkInfoResponse>;
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_check_tx(&self, tx: String) -> RpcRequest<serde_json::Value>;
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_genesis_config(&self) -> RpcRequest<serde_json::Value>;
     #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_validators_projection(&self) -> RpcRequest<EpochValidatorProjectionView>;
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_protocol_version_votes(&self) -> RpcRequest<ProtocolVersionVotesView>;
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_reward_projection(&self) -> RpcRequest<EpochRewardProjectionView>;
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_producer_schedule(
+        &self,
+        block_id: MaybeBlockId,
+    ) -> RpcRequest<ProducerScheduleView>;
+    #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_broadcast_tx_sync(&self, tx: String) -> RpcRequest<serde_json::Value>;
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_tx_status(&self, tx: String) -> RpcRequest<serde_json::Value>;
@@ -227,6 +297,39 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "block", request)
     }
 
+    /// Same as `block`, but the block is transferred in Borsh instead of JSON.
+    pub fn block_borsh(&self, request: BlockReference) -> RpcRequest<BlockView> {
+        call_method_borsh(&self.client, &self.server_addr, "block", request)
+    }
+
+    /// Same as `chunk`, but the chunk is transferred in Borsh instead of JSON.
+    pub fn chunk_borsh(&self, id: ChunkId) -> RpcRequest<ChunkView> {
+        call_method_borsh(&self.client, &self.server_addr, "chunk", [id])
+    }
+
+    /// Same as `broadcast_tx_async`, but the node validates the transaction before sending it.
+    pub fn broadcast_tx_async_with_preflight(&self, tx: String) -> RpcRequest<String> {
+        let options = RpcBroadcastTransactionOptions { preflight: true, ..Default::default() };
+        call_method(&self.client, &self.server_addr, "broadcast_tx_async", (tx, options))
+    }
+
+    /// Same as `broadcast_tx_commit`, but the node validates the transaction before sending it.
+    pub fn broadcast_tx_commit_with_preflight(
+        &self,
+        tx: String,
+    ) -> RpcRequest<FinalExecutionOutcomeView> {
+        let options = RpcBroadcastTransactionOptions { preflight: true, ..Default::default() };
+        self.broadcast_tx_commit_with_options(tx, options)
+    }
+
+    pub fn broadcast_tx_commit_with_options(
+        &self,
+        tx: String,
+        options: RpcBroadcastTransactionOptions,
+    ) -> RpcRequest<FinalExecutionOutcomeView> {
+        call_method(&self.client, &self.server_addr, "broadcast_tx_commit", (tx, options))
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_changes(
         &self,
@@ -243,6 +346,38 @@ impl JsonRpcClient {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validators_ordered", request)
     }
 
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_validator_events(
+        &self,
+        request: RpcValidatorEventsRequest,
+    ) -> RpcRequest<RpcValidatorEventsResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_validator_events", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_missed_production(
+        &self,
+        request: RpcMissedProductionRequest,
+    ) -> RpcRequest<RpcMissedProductionResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_missed_production", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_epoch_summary(
+        &self,
+        request: RpcEpochSummaryRequest,
+    ) -> RpcRequest<RpcEpochSummaryResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_epoch_summary", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_maintenance_windows(
+        &self,
+        request: RpcMaintenanceWindowsRequest,
+    ) -> RpcRequest<RpcMaintenanceWindowsResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_maintenance_windows", request)
+    }
+
     #[allow(non_snake_case)]
     pub fn EXPERIMENTAL_receipt(
         &self,
@@ -258,6 +393,39 @@ impl JsonRpcClient {
     ) -> RpcRequest<near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse> {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_protocol_config", request)
     }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_protocol_config_diff(
+        &self,
+        request: near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_protocol_config_diff", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_fee_estimate(
+        &self,
+        request: near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_fee_estimate", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_account_history(
+        &self,
+        request: near_jsonrpc_primitives::types::account_history::RpcAccountHistoryRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse>
+    {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_account_history", request)
+    }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_accounts_by_code_hash(
+        &self,
+        request: near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_accounts_by_code_hash", request)
+    }
 }
 
 fn create_client() -> Client {
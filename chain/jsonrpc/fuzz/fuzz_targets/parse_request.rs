@@ -0,0 +1,55 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use near_jsonrpc_primitives::message::{from_slice, Message};
+use near_jsonrpc_primitives::types::{
+    blocks, changes, chunks, config, contracts, gas_attribution, gas_price, jobs, light_client,
+    log_filter, query, receipts, sandbox, transactions, validator,
+};
+
+/// Runs the params through the parser of each request type.  Parsing mustn't panic, whether the
+/// params are valid for the type or not.
+macro_rules! parse_all {
+    ($params:expr, $($request:ty),* $(,)?) => {
+        $(let _ = <$request>::parse($params.clone());)*
+    };
+}
+
+// Doesn't need a running node, unlike `fuzz_target_1`, so it covers the parsing much faster.
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(Message::Request(request)) = from_slice(bytes) {
+        parse_all!(
+            request.params,
+            blocks::RpcBlockRequest,
+            changes::RpcStateChangesInBlockRequest,
+            changes::RpcStateChangesInBlockByTypeRequest,
+            chunks::RpcChunkRequest,
+            config::RpcProtocolConfigRequest,
+            config::RpcProtocolConfigDiffRequest,
+            contracts::RpcAccountsByCodeHashRequest,
+            gas_attribution::RpcGasAttributionRequest,
+            gas_price::RpcGasPriceRequest,
+            jobs::RpcSubmitJobRequest,
+            jobs::RpcPollResultRequest,
+            light_client::RpcLightClientExecutionProofRequest,
+            light_client::RpcLightClientNextBlockRequest,
+            log_filter::RpcLogFilterRequest,
+            query::RpcQueryRequest,
+            receipts::RpcReceiptRequest,
+            receipts::RpcReceiptTraceRequest,
+            receipts::RpcDelayedReceiptsRequest,
+            sandbox::RpcSandboxPatchStateRequest,
+            sandbox::RpcSandboxFastForwardRequest,
+            sandbox::RpcSandboxSetTimestampRequest,
+            sandbox::RpcSandboxSetGasPriceRequest,
+            transactions::RpcBroadcastTransactionRequest,
+            transactions::RpcTransactionStatusCommonRequest,
+            validator::RpcValidatorRequest,
+            validator::RpcValidatorEventsRequest,
+            validator::RpcMissedProductionRequest,
+            validator::RpcEpochSummaryRequest,
+            validator::RpcMaintenanceWindowsRequest,
+            validator::RpcValidatorsOrderedRequest,
+        );
+    }
+});
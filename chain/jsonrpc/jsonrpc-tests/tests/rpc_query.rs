@@ -13,7 +13,9 @@ use near_logger_utils::init_test_logger;
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_primitives::account::{AccessKey, AccessKeyPermission};
 use near_primitives::hash::CryptoHash;
-use near_primitives::types::{BlockId, BlockReference, EpochId, ShardId, SyncCheckpoint};
+use near_primitives::types::{
+    AccountId, BlockId, BlockReference, EpochId, ShardId, SyncCheckpoint,
+};
 use near_primitives::views::QueryRequest;
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
@@ -48,6 +50,26 @@ fn test_block_by_id_hash() {
     });
 }
 
+/// Retrieve blocks and chunks encoded in Borsh
+#[test]
+fn test_block_and_chunk_borsh() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let block = client.block(BlockReference::BlockId(BlockId::Height(0))).await.unwrap();
+        let borsh_block =
+            client.block_borsh(BlockReference::BlockId(BlockId::Height(0))).await.unwrap();
+        assert_eq!(borsh_block.author, block.author);
+        assert_eq!(borsh_block.header.hash, block.header.hash);
+        assert_eq!(borsh_block.chunks.len(), block.chunks.len());
+
+        let chunk_id = ChunkId::Hash(block.chunks[0].chunk_hash);
+        let chunk = client.chunk_borsh(chunk_id).await.unwrap();
+        assert_eq!(chunk.header.chunk_hash, block.chunks[0].chunk_hash);
+
+        let err = client.block_borsh(BlockReference::BlockId(BlockId::Height(100))).await;
+        assert!(err.is_err());
+    });
+}
+
 /// Retrieve blocks via json rpc
 #[test]
 fn test_block_query() {
@@ -466,6 +488,21 @@ fn test_validators_ordered() {
     });
 }
 
+#[test]
+fn test_producer_schedule() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let schedule = client.EXPERIMENTAL_producer_schedule(None).await.unwrap();
+        assert!(!schedule.heights.is_empty());
+        let validators: Vec<AccountId> = vec!["test1".parse().unwrap(), "test2".parse().unwrap()];
+        for (i, producers) in schedule.heights.iter().enumerate() {
+            assert_eq!(producers.height, schedule.start_height + i as u64);
+            assert!(validators.contains(&producers.block_producer));
+            assert_eq!(producers.chunk_producers.len(), 1);
+            assert!(validators.contains(&producers.chunk_producers[0]));
+        }
+    });
+}
+
 /// Retrieve genesis config via JSON RPC.
 /// WARNING: Be mindful about changing genesis structure as it is part of the public protocol!
 #[test]
@@ -7,12 +7,13 @@ use futures::{future, FutureExt, TryFutureExt};
 use near_actix_test_utils::run_actix;
 use near_crypto::{InMemorySigner, KeyType};
 use near_jsonrpc::client::new_client;
+use near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionOptions;
 use near_logger_utils::{init_integration_logger, init_test_logger};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::serialize::{to_base, to_base64};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::BlockReference;
+use near_primitives::types::{BlockId, BlockReference, Finality};
 use near_primitives::views::FinalExecutionStatus;
 
 use near_jsonrpc_tests::{self as test_utils, test_with_client};
@@ -224,3 +225,88 @@ fn test_check_invalid_tx() {
         }
     });
 }
+
+/// Test that a transaction which can't pay for its deposit is rejected before being sent.
+#[test]
+fn test_send_tx_async_with_preflight_not_enough_balance() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            u128::MAX / 2,
+            block_hash,
+        );
+        let bytes = tx.try_to_vec().unwrap();
+        match client.broadcast_tx_async_with_preflight(to_base64(&bytes)).await {
+            Err(e) => {
+                let data = e.data.unwrap();
+                assert!(
+                    data["TxExecutionError"]["InvalidTxError"]["NotEnoughBalance"].is_object(),
+                    "{}",
+                    data
+                );
+            }
+            Ok(_) => panic!("transaction should not be sent"),
+        }
+    });
+}
+
+/// Test that a valid transaction passes the preflight validation and gets executed.
+#[test]
+fn test_send_tx_commit_with_preflight() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            100,
+            block_hash,
+        );
+        let bytes = tx.try_to_vec().unwrap();
+        let result = client.broadcast_tx_commit_with_preflight(to_base64(&bytes)).await.unwrap();
+        assert_eq!(result.status, FinalExecutionStatus::SuccessValue(to_base64(&[])));
+    });
+}
+
+/// Test that `broadcast_tx_commit` waits for the blocks executing the transaction to be final.
+#[test]
+fn test_send_tx_commit_final() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::send_money(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            100,
+            block_hash,
+        );
+        let bytes = tx.try_to_vec().unwrap();
+        let options = RpcBroadcastTransactionOptions {
+            finality: Some(Finality::Final),
+            timeout_secs: Some(30),
+            ..Default::default()
+        };
+        let result =
+            client.broadcast_tx_commit_with_options(to_base64(&bytes), options).await.unwrap();
+        assert_eq!(result.status, FinalExecutionStatus::SuccessValue(to_base64(&[])));
+        let final_height =
+            client.block(BlockReference::Finality(Finality::Final)).await.unwrap().header.height;
+        for outcome in std::iter::once(&result.transaction_outcome).chain(&result.receipts_outcome)
+        {
+            let block = client
+                .block(BlockReference::BlockId(BlockId::Hash(outcome.block_hash)))
+                .await
+                .unwrap();
+            assert!(block.header.height <= final_height);
+        }
+    });
+}
@@ -0,0 +1,80 @@
+//! `/contract_logs` WebSocket, which streams the log lines of contract executions matching the
+//! `contract_logs` config of the node as JSON text messages, one per log line.
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tracing::debug;
+
+use near_client::{SubscribeContractLogs, SubscribeContractLogsError};
+use near_primitives::views::ContractLogView;
+
+use crate::JsonRpcHandler;
+
+pub(crate) async fn contract_logs_handler(
+    handler: web::Data<JsonRpcHandler>,
+    request: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, HttpError> {
+    match handler.client_addr.send(SubscribeContractLogs).await {
+        Ok(Ok(receiver)) => {
+            ws::start(ContractLogsSession { receiver: Some(receiver) }, &request, stream)
+        }
+        Ok(Err(err @ SubscribeContractLogsError::Disabled)) => {
+            Ok(HttpResponse::NotFound().body(err.to_string()))
+        }
+        Err(_) => Ok(HttpResponse::ServiceUnavailable().finish()),
+    }
+}
+
+struct ContractLogsSession {
+    /// Taken once the session starts.
+    receiver: Option<broadcast::Receiver<ContractLogView>>,
+}
+
+impl Actor for ContractLogsSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if let Some(receiver) = self.receiver.take() {
+            ctx.add_stream(BroadcastStream::new(receiver));
+        }
+    }
+}
+
+impl StreamHandler<Result<ContractLogView, BroadcastStreamRecvError>> for ContractLogsSession {
+    fn handle(
+        &mut self,
+        log: Result<ContractLogView, BroadcastStreamRecvError>,
+        ctx: &mut Self::Context,
+    ) {
+        match log {
+            Ok(log) => ctx.text(serde_json::to_string(&log).unwrap()),
+            // Subscribers which can't keep up are disconnected rather than silently missing logs.
+            Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                debug!(target: "jsonrpc", "Contract logs subscriber missed {} logs", missed);
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Again,
+                    description: Some(format!("Missed {} logs", missed)),
+                }));
+                ctx.stop();
+            }
+        }
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ContractLogsSession {
+    fn handle(&mut self, message: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match message {
+            Ok(ws::Message::Ping(payload)) => ctx.pong(&payload),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => {}
+            Err(_) => ctx.stop(),
+        }
+    }
+}
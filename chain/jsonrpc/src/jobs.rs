@@ -0,0 +1,202 @@
+//! Background execution of heavy RPC requests.
+//!
+//! Some requests (e.g. `EXPERIMENTAL_changes_in_block` on a large block) take a long time to
+//! process.  Instead of keeping the HTTP connection busy, clients may submit such a request as a
+//! job, get its id back immediately and later poll for the result.  Number of jobs executed at the
+//! same time is bounded so that heavy requests cannot starve the light ones.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::jobs::{JobId, RpcPollResultResponse};
+
+/// Methods which may be submitted as background jobs.
+pub(crate) const JOB_METHODS: &[&str] =
+    &["EXPERIMENTAL_changes", "EXPERIMENTAL_changes_in_block", "query", "validators"];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RpcJobsConfig {
+    /// Maximum number of jobs executed at the same time.  The remaining ones wait in a queue.
+    pub max_concurrent_jobs: usize,
+    /// Maximum number of jobs (pending or finished) tracked at the same time.  Submitting a job
+    /// above this limit fails.
+    pub max_jobs: usize,
+    /// How long the result of a finished job is kept around waiting to be polled.
+    pub result_ttl: Duration,
+}
+
+impl Default for RpcJobsConfig {
+    fn default() -> Self {
+        Self { max_concurrent_jobs: 2, max_jobs: 100, result_ttl: Duration::from_secs(60) }
+    }
+}
+
+enum JobState {
+    Pending,
+    Finished { finished_at: Instant, result: Result<Value, RpcError> },
+}
+
+/// Keeps track of submitted jobs and their results.  Shared between all HTTP workers.
+pub(crate) struct JobRegistry {
+    config: RpcJobsConfig,
+    semaphore: Semaphore,
+    state: Mutex<JobRegistryState>,
+}
+
+#[derive(Default)]
+struct JobRegistryState {
+    jobs: HashMap<JobId, JobState>,
+}
+
+/// A registered job which hasn't finished yet.  Dropping it without calling
+/// [`PendingJob::finish`], e.g. because the job was cancelled or panicked, releases its entry so
+/// that it doesn't count against `max_jobs` forever.
+pub(crate) struct PendingJob {
+    registry: Arc<JobRegistry>,
+    job_id: JobId,
+    finished: bool,
+}
+
+impl PendingJob {
+    pub(crate) fn job_id(&self) -> &JobId {
+        &self.job_id
+    }
+
+    pub(crate) fn finish(mut self, result: Result<Value, RpcError>) {
+        let mut state = self.registry.state.lock().unwrap();
+        state.jobs.insert(
+            self.job_id.clone(),
+            JobState::Finished { finished_at: Instant::now(), result },
+        );
+        self.finished = true;
+    }
+}
+
+impl Drop for PendingJob {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.registry.state.lock().unwrap().jobs.remove(&self.job_id);
+        }
+    }
+}
+
+impl JobRegistry {
+    pub(crate) fn new(config: RpcJobsConfig) -> Arc<Self> {
+        Arc::new(Self {
+            semaphore: Semaphore::new(config.max_concurrent_jobs),
+            config,
+            state: Default::default(),
+        })
+    }
+
+    /// Registers a new pending job.  Returns `None` if there are too many jobs already.
+    pub(crate) fn register(self: &Arc<Self>) -> Option<PendingJob> {
+        let mut state = self.state.lock().unwrap();
+        self.remove_expired(&mut state);
+        if state.jobs.len() >= self.config.max_jobs {
+            return None;
+        }
+        let job_id = loop {
+            let job_id = format!("{:032x}", rand::thread_rng().gen::<u128>());
+            if !state.jobs.contains_key(&job_id) {
+                break job_id;
+            }
+        };
+        state.jobs.insert(job_id.clone(), JobState::Pending);
+        Some(PendingJob { registry: self.clone(), job_id, finished: false })
+    }
+
+    /// Waits until the job is allowed to run.  The job must hold the permit while it executes.
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        // The semaphore is never closed.
+        self.semaphore.acquire().await.unwrap()
+    }
+
+    /// Returns the state of the job or `None` if it is unknown or its result has expired.
+    pub(crate) fn poll(&self, job_id: &JobId) -> Option<RpcPollResultResponse> {
+        let mut state = self.state.lock().unwrap();
+        self.remove_expired(&mut state);
+        state.jobs.get(job_id).map(|job| match job {
+            JobState::Pending => RpcPollResultResponse::Pending,
+            JobState::Finished { result: Ok(result), .. } => {
+                RpcPollResultResponse::Completed { result: result.clone() }
+            }
+            JobState::Finished { result: Err(error), .. } => {
+                RpcPollResultResponse::Failed { error: error.clone() }
+            }
+        })
+    }
+
+    fn remove_expired(&self, state: &mut JobRegistryState) {
+        let result_ttl = self.config.result_ttl;
+        state.jobs.retain(|_, job| match job {
+            JobState::Pending => true,
+            JobState::Finished { finished_at, .. } => finished_at.elapsed() < result_ttl,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle() {
+        let registry = JobRegistry::new(RpcJobsConfig::default());
+        let job = registry.register().unwrap();
+        let job_id = job.job_id().clone();
+        assert!(matches!(registry.poll(&job_id), Some(RpcPollResultResponse::Pending)));
+        job.finish(Ok(Value::Bool(true)));
+        assert!(matches!(
+            registry.poll(&job_id),
+            Some(RpcPollResultResponse::Completed { result: Value::Bool(true) })
+        ));
+        assert!(registry.poll(&"0".repeat(32)).is_none());
+    }
+
+    #[test]
+    fn test_max_jobs() {
+        let registry = JobRegistry::new(RpcJobsConfig { max_jobs: 1, ..Default::default() });
+        let job = registry.register().unwrap();
+        assert!(registry.register().is_none());
+        job.finish(Ok(Value::Null));
+        // Finished jobs count against the limit until their results expire.
+        assert!(registry.register().is_none());
+    }
+
+    #[test]
+    fn test_result_expiration() {
+        let registry =
+            JobRegistry::new(RpcJobsConfig { result_ttl: Duration::ZERO, ..Default::default() });
+        let job = registry.register().unwrap();
+        let job_id = job.job_id().clone();
+        job.finish(Ok(Value::Null));
+        assert!(registry.poll(&job_id).is_none());
+    }
+
+    #[test]
+    fn test_dropped_job_is_released() {
+        let registry = JobRegistry::new(RpcJobsConfig { max_jobs: 1, ..Default::default() });
+        let job = registry.register().unwrap();
+        let job_id = job.job_id().clone();
+        drop(job);
+        assert!(registry.poll(&job_id).is_none());
+        assert!(registry.register().is_some());
+    }
+
+    #[test]
+    fn test_job_ids_are_random() {
+        let registry = JobRegistry::new(RpcJobsConfig::default());
+        let first = registry.register().unwrap();
+        let second = registry.register().unwrap();
+        assert_eq!(first.job_id().len(), 32);
+        assert_ne!(first.job_id(), second.job_id());
+    }
+}
@@ -1,10 +1,15 @@
 #![doc = include_str!("../README.md")]
 
+use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use actix::Addr;
 use actix_cors::Cors;
-use actix_web::{get, http, middleware, web, App, Error as HttpError, HttpResponse, HttpServer};
+use actix_web::{
+    get, http, middleware, web, App, Error as HttpError, HttpRequest, HttpResponse, HttpServer,
+};
+use borsh::BorshSerialize;
 use futures::Future;
 use futures::FutureExt;
 use prometheus;
@@ -15,29 +20,44 @@ use tracing::info;
 
 use near_chain_configs::GenesisConfig;
 use near_client::{
-    ClientActor, GetBlock, GetBlockProof, GetChunk, GetExecutionOutcome, GetGasPrice,
-    GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, Query, Status, TxStatus,
-    TxStatusError, ViewClientActor,
+    ClientActor, GetAccountHistory, GetAccountsByCodeHash, GetBlock, GetBlockError, GetBlockProof,
+    GetChunk, GetDelayedReceipts, GetEpochSummary, GetExecutionOutcome, GetFeeEstimate,
+    GetGasAttribution, GetGasPrice, GetMaintenanceWindows, GetMissedProduction, GetNetworkInfo,
+    GetNextLightClientBlock, GetProducerSchedule, GetProtocolConfig, GetProtocolConfigDiff,
+    GetProtocolVersionVotes, GetReceipt, GetRewardProjection, GetStateChanges,
+    GetStateChangesInBlock, GetValidatorEvents, GetValidatorInfo, GetValidatorOrdered,
+    GetValidatorProjection, Query, Status, TraceReceipt, TxStatus, TxStatusError, ViewClientActor,
 };
 pub use near_jsonrpc_client as client;
-use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::errors::{RpcError, RpcErrorKind, RpcRequestValidationErrorKind};
 use near_jsonrpc_primitives::message::{Message, Request};
 use near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse;
 use near_metrics::{Encoder, TextEncoder};
 use near_network::types::{NetworkClientMessages, NetworkClientResponses};
 use near_primitives::hash::CryptoHash;
-use near_primitives::serialize::BaseEncode;
+use near_primitives::serialize::{from_base, BaseEncode};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::AccountId;
-use near_primitives::views::FinalExecutionOutcomeViewEnum;
+use near_primitives::types::{AccountId, BlockId, BlockReference, Finality};
+use near_primitives::views::{FinalExecutionOutcomeViewEnum, FinalExecutionStatus};
 
+mod contract_logs;
+mod jobs;
 mod metrics;
+mod tx_commit;
+
+pub use jobs::RpcJobsConfig;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub struct RpcPollingConfig {
     pub polling_interval: Duration,
     pub polling_timeout: Duration,
+    /// Upper bound of the timeout requested by `broadcast_tx_commit` callers.
+    #[serde(default = "default_max_polling_timeout")]
+    pub max_polling_timeout: Duration,
+}
+
+fn default_max_polling_timeout() -> Duration {
+    Duration::from_secs(60)
 }
 
 impl Default for RpcPollingConfig {
@@ -45,6 +65,7 @@ impl Default for RpcPollingConfig {
         Self {
             polling_interval: Duration::from_millis(500),
             polling_timeout: Duration::from_secs(10),
+            max_polling_timeout: default_max_polling_timeout(),
         }
     }
 }
@@ -78,6 +99,9 @@ pub struct RpcConfig {
     // We disable it by default, as some of those endpoints might be quite CPU heavy.
     #[serde(default = "default_enable_debug_rpc")]
     pub enable_debug_rpc: bool,
+    // Limits for heavy requests executed in the background via `EXPERIMENTAL_submit_job`.
+    #[serde(default)]
+    pub jobs_config: RpcJobsConfig,
 }
 
 impl Default for RpcConfig {
@@ -89,6 +113,7 @@ impl Default for RpcConfig {
             polling_config: Default::default(),
             limits_config: Default::default(),
             enable_debug_rpc: false,
+            jobs_config: Default::default(),
         }
     }
 }
@@ -203,12 +228,57 @@ fn process_query_response(
     }
 }
 
+/// Content type of Borsh-encoded responses.
+const BORSH_CONTENT_TYPE: &str = "application/borsh";
+
+/// Methods which can respond with the Borsh encoding of the primitive view instead of JSON.
+const BORSH_METHODS: &[&str] = &["block", "chunk", "tx"];
+
+/// Whether the client asked for a Borsh-encoded response, either with `Accept: application/borsh`
+/// or with `?encoding=borsh` in the URL.
+fn wants_borsh(http_request: &HttpRequest) -> bool {
+    let accept = http_request.headers().get(http::header::ACCEPT).and_then(|v| v.to_str().ok());
+    accept.map_or(false, |accept| {
+        accept.split(',').any(|media_type| media_type.trim().starts_with(BORSH_CONTENT_TYPE))
+    }) || http_request.query_string().split('&').any(|param| param == "encoding=borsh")
+}
+
+/// Returns the method name to be used as a metrics label.
+///
+/// Unknown methods are collapsed into a single label so that arbitrary user input cannot blow up
+/// the cardinality of the per-method metrics.
+fn method_label<'a>(method: &'a str, response: &Result<Value, RpcError>) -> &'a str {
+    match response {
+        Err(err) if err.code == -32_601 => "UNSUPPORTED_METHOD",
+        _ => method,
+    }
+}
+
+/// Returns the structured error name (e.g. `UNKNOWN_BLOCK`) to be used as a metrics label.
+fn error_kind_label(err: &RpcError) -> &str {
+    match &err.error_struct {
+        Some(RpcErrorKind::RequestValidationError(
+            RpcRequestValidationErrorKind::MethodNotFound { .. },
+        )) => "METHOD_NOT_FOUND",
+        Some(RpcErrorKind::RequestValidationError(RpcRequestValidationErrorKind::ParseError {
+            ..
+        })) => "PARSE_ERROR",
+        Some(RpcErrorKind::HandlerError(error_struct)) => {
+            error_struct["name"].as_str().unwrap_or("HANDLER_ERROR")
+        }
+        Some(RpcErrorKind::InternalError(_)) => "INTERNAL_ERROR",
+        None => "UNKNOWN_ERROR",
+    }
+}
+
+#[derive(Clone)]
 struct JsonRpcHandler {
     client_addr: Addr<ClientActor>,
     view_client_addr: Addr<ViewClientActor>,
     polling_config: RpcPollingConfig,
-    genesis_config: GenesisConfig,
+    genesis_config: Arc<GenesisConfig>,
     enable_debug_rpc: bool,
+    jobs: Arc<jobs::JobRegistry>,
     #[cfg(feature = "test_features")]
     peer_manager_addr: Addr<near_network::PeerManagerActor>,
     #[cfg(feature = "test_features")]
@@ -232,34 +302,60 @@ impl JsonRpcHandler {
     // `process_request_internal`.
     async fn process_request(&self, request: Request) -> Result<Value, RpcError> {
         let timer = Instant::now();
+        let _in_flight = metrics::InFlightRequestGuard::new();
 
         let request_method = request.method.clone();
-        let response = self.process_request_internal(request).await;
-
-        let request_method = if let Err(err) = &response {
-            if err.code == -32_601 {
-                "UNSUPPORTED_METHOD"
-            } else {
-                &request_method
-            }
+        let response = if request.method == "EXPERIMENTAL_submit_job" {
+            self.submit_job(request)
         } else {
-            &request_method
+            self.process_request_internal(request).await
         };
 
-        metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[request_method]).inc();
-        metrics::RPC_PROCESSING_TIME
-            .with_label_values(&[request_method])
-            .observe(timer.elapsed().as_secs_f64());
+        let request_method = method_label(&request_method, &response);
+        observe_request(request_method, timer, response.as_ref().err());
+        response
+    }
+
+    /// Processes a request for one of `BORSH_METHODS` and returns the Borsh encoding of the
+    /// view it responds with.  Errors are still reported in JSON.
+    async fn process_borsh_request(&self, request: Request) -> Result<Vec<u8>, RpcError> {
+        let timer = Instant::now();
+        let _in_flight = metrics::InFlightRequestGuard::new();
 
-        if let Err(err) = &response {
-            metrics::RPC_ERROR_COUNT
-                .with_label_values(&[request_method, &err.code.to_string()])
-                .inc();
-        }
+        let request_method = request.method.clone();
+        let response = self.process_borsh_request_internal(request).await;
 
+        observe_request(&request_method, timer, response.as_ref().err());
         response
     }
 
+    // Processes the Borsh request but doesn't update any metrics.
+    async fn process_borsh_request_internal(&self, request: Request) -> Result<Vec<u8>, RpcError> {
+        match request.method.as_ref() {
+            "block" => {
+                let rpc_block_request =
+                    near_jsonrpc_primitives::types::blocks::RpcBlockRequest::parse(request.params)?;
+                let block = self.block(rpc_block_request).await?;
+                block.block_view.try_to_vec()
+            }
+            "chunk" => {
+                let rpc_chunk_request =
+                    near_jsonrpc_primitives::types::chunks::RpcChunkRequest::parse(request.params)?;
+                let chunk = self.chunk(rpc_chunk_request).await?;
+                chunk.chunk_view.try_to_vec()
+            }
+            "tx" => {
+                let rpc_transaction_status_common_request =
+                    near_jsonrpc_primitives::types::transactions::RpcTransactionStatusCommonRequest::parse(request.params)?;
+                let rpc_transaction_response =
+                    self.tx_status_common(rpc_transaction_status_common_request, false).await?;
+                rpc_transaction_response.final_execution_outcome.try_to_vec()
+            }
+            _ => return Err(RpcError::method_not_found(request.method)),
+        }
+        .map_err(|err| RpcError::serialization_error(err.to_string()))
+    }
+
     // Processes the request but doesn't update any metrics.
     async fn process_request_internal(&self, request: Request) -> Result<Value, RpcError> {
         #[cfg(feature = "test_features")]
@@ -295,6 +391,24 @@ impl JsonRpcHandler {
                             .map_err(|err| RpcError::serialization_error(err.to_string())),
                     )
                 }
+                "adv_set_network_faults" => {
+                    let params = parse_params::<
+                        near_jsonrpc_adversarial_primitives::SetNetworkFaultsRequest,
+                    >(params)?;
+                    self.peer_manager_addr
+                        .send(near_network::types::PeerManagerMessageRequest::SetNetworkFaults(
+                            near_network::test_utils::SetNetworkFaults {
+                                peer_id: params.peer_id,
+                                drop_percent: params.drop_percent,
+                            },
+                        ))
+                        .await?;
+                    Some(
+                        serde_json::to_value(())
+                            .map_err(|err| RpcError::serialization_error(err.to_string())),
+                    )
+                }
+                "adv_set_store_faults" => Some(self.adv_set_store_faults(params).await),
                 #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
                 "adv_set_routing_table" => {
                     let request =
@@ -393,7 +507,7 @@ impl JsonRpcHandler {
                     near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionRequest::parse(
                         request.params,
                     )?;
-                let transaction_hash = self.send_tx_async(rpc_transaction_request).await;
+                let transaction_hash = self.send_tx_async(rpc_transaction_request).await?;
                 serde_json::to_value((&transaction_hash).to_base())
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
@@ -402,7 +516,7 @@ impl JsonRpcHandler {
                     near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionRequest::parse(
                         request.params,
                     )?;
-                let send_tx_response = self.send_tx_commit(rpc_transaction_request).await?;
+                let send_tx_response = self.send_tx_commit(rpc_transaction_request, None).await?;
                 serde_json::to_value(send_tx_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
@@ -512,11 +626,25 @@ impl JsonRpcHandler {
                 serde_json::to_value(broadcast_tx_sync_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_debug_threads" => {
+                let stats = self.debug_threads().await;
+                serde_json::to_value(stats)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_genesis_config" => {
                 let genesis_config = self.genesis_config().await;
                 serde_json::to_value(genesis_config)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_log_filter" => {
+                let rpc_log_filter_request =
+                    near_jsonrpc_primitives::types::log_filter::RpcLogFilterRequest::parse(
+                        request.params,
+                    )?;
+                let log_filter = self.log_filter(rpc_log_filter_request).await?;
+                serde_json::to_value(log_filter)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_light_client_proof" => {
                 let rpc_light_client_execution_proof_request = near_jsonrpc_primitives::types::light_client::RpcLightClientExecutionProofRequest::parse(request.params)?;
                 let rpc_light_client_execution_proof_response = self
@@ -525,6 +653,15 @@ impl JsonRpcHandler {
                 serde_json::to_value(rpc_light_client_execution_proof_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_poll_result" => {
+                let rpc_poll_result_request =
+                    near_jsonrpc_primitives::types::jobs::RpcPollResultRequest::parse(
+                        request.params,
+                    )?;
+                let poll_result = self.poll_result(rpc_poll_result_request)?;
+                serde_json::to_value(poll_result)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_protocol_config" => {
                 let rpc_protocol_config_request =
                     near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest::parse(
@@ -534,6 +671,67 @@ impl JsonRpcHandler {
                 serde_json::to_value(config)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_protocol_config_diff" => {
+                let rpc_protocol_config_diff_request =
+                    near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffRequest::parse(
+                        request.params,
+                    )?;
+                let config_diff =
+                    self.protocol_config_diff(rpc_protocol_config_diff_request).await?;
+                serde_json::to_value(config_diff)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_delayed_receipts" => {
+                let rpc_delayed_receipts_request =
+                    near_jsonrpc_primitives::types::receipts::RpcDelayedReceiptsRequest::parse(
+                        request.params,
+                    )?;
+                let delayed_receipts = self.delayed_receipts(rpc_delayed_receipts_request).await?;
+                serde_json::to_value(delayed_receipts)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_account_history" => {
+                let rpc_account_history_request =
+                    near_jsonrpc_primitives::types::account_history::RpcAccountHistoryRequest::parse(
+                        request.params,
+                    )?;
+                let account_history = self.account_history(rpc_account_history_request).await?;
+                serde_json::to_value(account_history)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_accounts_by_code_hash" => {
+                let rpc_accounts_by_code_hash_request =
+                    near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashRequest::parse(
+                        request.params,
+                    )?;
+                let accounts =
+                    self.accounts_by_code_hash(rpc_accounts_by_code_hash_request).await?;
+                serde_json::to_value(accounts)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_allocator_stats" => {
+                let rpc_allocator_stats_request =
+                    near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsRequest::parse(
+                        request.params,
+                    )?;
+                let allocator_stats = self.allocator_stats(rpc_allocator_stats_request).await?;
+                serde_json::to_value(allocator_stats)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_contract_profiles" => {
+                let contract_profiles = self.contract_profiles().await?;
+                serde_json::to_value(contract_profiles)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_gas_attribution" => {
+                let rpc_gas_attribution_request =
+                    near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionRequest::parse(
+                        request.params,
+                    )?;
+                let gas_attribution = self.gas_attribution(rpc_gas_attribution_request).await?;
+                serde_json::to_value(gas_attribution)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_receipt" => {
                 let rpc_receipt_request =
                     near_jsonrpc_primitives::types::receipts::RpcReceiptRequest::parse(
@@ -543,6 +741,15 @@ impl JsonRpcHandler {
                 serde_json::to_value(receipt)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_receipt_trace" => {
+                let rpc_receipt_trace_request =
+                    near_jsonrpc_primitives::types::receipts::RpcReceiptTraceRequest::parse(
+                        request.params,
+                    )?;
+                let receipt_trace = self.receipt_trace(rpc_receipt_trace_request).await?;
+                serde_json::to_value(receipt_trace)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             "EXPERIMENTAL_tx_status" => {
                 let rpc_transaction_status_common_request = near_jsonrpc_primitives::types::transactions::RpcTransactionStatusCommonRequest::parse(request.params)?;
                 let rpc_transaction_response =
@@ -559,6 +766,76 @@ impl JsonRpcHandler {
                 serde_json::to_value(validators)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            "EXPERIMENTAL_validator_events" => {
+                let rpc_validator_events_request =
+                    near_jsonrpc_primitives::types::validator::RpcValidatorEventsRequest::parse(
+                        request.params,
+                    )?;
+                let events = self.validator_events(rpc_validator_events_request).await?;
+                serde_json::to_value(events)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_missed_production" => {
+                let rpc_missed_production_request =
+                    near_jsonrpc_primitives::types::validator::RpcMissedProductionRequest::parse(
+                        request.params,
+                    )?;
+                let misses = self.missed_production(rpc_missed_production_request).await?;
+                serde_json::to_value(misses)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_fee_estimate" => {
+                let rpc_fee_estimate_request =
+                    near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateRequest::parse(
+                        request.params,
+                    )?;
+                let fee_estimate = self.fee_estimate(rpc_fee_estimate_request).await?;
+                serde_json::to_value(fee_estimate)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_epoch_summary" => {
+                let rpc_epoch_summary_request =
+                    near_jsonrpc_primitives::types::validator::RpcEpochSummaryRequest::parse(
+                        request.params,
+                    )?;
+                let summary = self.epoch_summary(rpc_epoch_summary_request).await?;
+                serde_json::to_value(summary)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_maintenance_windows" => {
+                let rpc_maintenance_windows_request =
+                    near_jsonrpc_primitives::types::validator::RpcMaintenanceWindowsRequest::parse(
+                        request.params,
+                    )?;
+                let maintenance_windows =
+                    self.maintenance_windows(rpc_maintenance_windows_request).await?;
+                serde_json::to_value(maintenance_windows)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_validators_projection" => {
+                let projection = self.validators_projection().await?;
+                serde_json::to_value(projection)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_protocol_version_votes" => {
+                let votes = self.protocol_version_votes().await?;
+                serde_json::to_value(votes)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_reward_projection" => {
+                let projection = self.reward_projection().await?;
+                serde_json::to_value(projection)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            "EXPERIMENTAL_producer_schedule" => {
+                let rpc_validator_request =
+                    near_jsonrpc_primitives::types::validator::RpcValidatorRequest::parse(
+                        request.params,
+                    )?;
+                let schedule = self.producer_schedule(rpc_validator_request).await?;
+                serde_json::to_value(schedule)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             #[cfg(feature = "sandbox")]
             "sandbox_patch_state" => {
                 let sandbox_patch_state_request =
@@ -581,24 +858,116 @@ impl JsonRpcHandler {
                 serde_json::to_value(sandbox_fast_forward_response)
                     .map_err(|err| RpcError::serialization_error(err.to_string()))
             }
+            #[cfg(feature = "sandbox")]
+            "sandbox_set_timestamp" => {
+                let sandbox_set_timestamp_request =
+                    near_jsonrpc_primitives::types::sandbox::RpcSandboxSetTimestampRequest::parse(
+                        request.params,
+                    )?;
+                let sandbox_set_timestamp_response =
+                    self.sandbox_set_timestamp(sandbox_set_timestamp_request).await?;
+                serde_json::to_value(sandbox_set_timestamp_response)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
+            #[cfg(feature = "sandbox")]
+            "sandbox_set_gas_price" => {
+                let sandbox_set_gas_price_request =
+                    near_jsonrpc_primitives::types::sandbox::RpcSandboxSetGasPriceRequest::parse(
+                        request.params,
+                    )?;
+                let sandbox_set_gas_price_response =
+                    self.sandbox_set_gas_price(sandbox_set_gas_price_request).await?;
+                serde_json::to_value(sandbox_set_gas_price_response)
+                    .map_err(|err| RpcError::serialization_error(err.to_string()))
+            }
             _ => Err(RpcError::method_not_found(request.method.clone())),
         };
 
         response
     }
 
+    /// Schedules the wrapped request for execution in the background and returns the id of the
+    /// job which can be later passed to `EXPERIMENTAL_poll_result`.
+    ///
+    /// This is intentionally not part of `process_request_internal` so that jobs cannot submit
+    /// other jobs.
+    fn submit_job(&self, request: Request) -> Result<Value, RpcError> {
+        let rpc_submit_job_request =
+            near_jsonrpc_primitives::types::jobs::RpcSubmitJobRequest::parse(
+                request.params.clone(),
+            )?;
+        if !jobs::JOB_METHODS.contains(&rpc_submit_job_request.method.as_str()) {
+            return Err(near_jsonrpc_primitives::types::jobs::RpcJobError::UnsupportedMethod {
+                method_name: rpc_submit_job_request.method,
+            }
+            .into());
+        }
+        let job = self
+            .jobs
+            .register()
+            .ok_or(near_jsonrpc_primitives::types::jobs::RpcJobError::TooManyJobs)?;
+        let job_id = job.job_id().clone();
+
+        let mut job_request = request;
+        job_request.method = rpc_submit_job_request.method;
+        job_request.params = rpc_submit_job_request.params;
+        let handler = self.clone();
+        actix::spawn(async move {
+            let _permit = handler.jobs.acquire().await;
+            let result = handler.process_request_internal(job_request).await;
+            job.finish(result);
+        });
+
+        serde_json::to_value(near_jsonrpc_primitives::types::jobs::RpcSubmitJobResponse { job_id })
+            .map_err(|err| RpcError::serialization_error(err.to_string()))
+    }
+
+    fn poll_result(
+        &self,
+        request_data: near_jsonrpc_primitives::types::jobs::RpcPollResultRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::jobs::RpcPollResultResponse,
+        near_jsonrpc_primitives::types::jobs::RpcJobError,
+    > {
+        self.jobs.poll(&request_data.job_id).ok_or(
+            near_jsonrpc_primitives::types::jobs::RpcJobError::UnknownJob {
+                job_id: request_data.job_id,
+            },
+        )
+    }
+
     async fn send_tx_async(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionRequest,
-    ) -> CryptoHash {
+    ) -> Result<CryptoHash, near_jsonrpc_primitives::types::transactions::RpcTransactionError> {
         let tx = request_data.signed_transaction;
+        if request_data.preflight {
+            self.preflight_tx(&tx).await?;
+        }
         let hash = tx.get_hash().clone();
         self.client_addr.do_send(NetworkClientMessages::Transaction {
             transaction: tx,
             is_forwarded: false,
             check_only: false, // if we set true here it will not actually send the transaction
         });
-        hash
+        Ok(hash)
+    }
+
+    /// Validates the transaction against the state of the head, including the nonce, the balance
+    /// for gas and deposit and the access key restrictions.  Fails if the node doesn't track the
+    /// shard of the signer, as the transaction can't be validated then.
+    async fn preflight_tx(
+        &self,
+        tx: &SignedTransaction,
+    ) -> Result<(), near_jsonrpc_primitives::types::transactions::RpcTransactionError> {
+        match self.send_tx(tx.clone(), true).await? {
+            NetworkClientResponses::ValidTx => Ok(()),
+            network_client_responses => Err(
+                near_jsonrpc_primitives::types::transactions::RpcTransactionError::from_network_client_responses(
+                    network_client_responses
+                )
+            ),
+        }
     }
 
     async fn tx_exists(
@@ -649,6 +1018,25 @@ impl JsonRpcHandler {
         &self,
         tx_info: near_jsonrpc_primitives::types::transactions::TransactionInfo,
         fetch_receipt: bool,
+    ) -> Result<FinalExecutionOutcomeViewEnum, TxStatusError> {
+        let result = self.tx_status_fetch_internal(tx_info.clone(), fetch_receipt).await;
+        if let Err(TxStatusError::TimeoutError) = result {
+            metrics::RPC_TIMEOUT_TOTAL.inc();
+            tracing::warn!(
+                target: "jsonrpc", "Timeout: tx_status_fetch method. tx_info {:?} fetch_receipt {:?}",
+                tx_info,
+                fetch_receipt,
+            );
+        }
+        result
+    }
+
+    // Fetches the status of the transaction but doesn't report timeouts, which are expected
+    // while polling.
+    async fn tx_status_fetch_internal(
+        &self,
+        tx_info: near_jsonrpc_primitives::types::transactions::TransactionInfo,
+        fetch_receipt: bool,
     ) -> Result<FinalExecutionOutcomeViewEnum, TxStatusError> {
         let (tx_hash, account_id) = match &tx_info {
             near_jsonrpc_primitives::types::transactions::TransactionInfo::Transaction(tx) => {
@@ -689,36 +1077,60 @@ impl JsonRpcHandler {
             }
         })
         .await
-        .map_err(|_| {
-            metrics::RPC_TIMEOUT_TOTAL.inc();
-            tracing::warn!(
-                target: "jsonrpc", "Timeout: tx_status_fetch method. tx_info {:?} fetch_receipt {:?}",
-                tx_info,
-                fetch_receipt,
-            );
-            TxStatusError::TimeoutError
-        })?
+        .map_err(|_| TxStatusError::TimeoutError)?
     }
 
+    /// Polls the status of the transaction until it's executed and, unless `finality` is
+    /// optimistic, until the blocks executing it and its receipts have the given finality.
     async fn tx_polling(
         &self,
         tx_info: near_jsonrpc_primitives::types::transactions::TransactionInfo,
+        finality: &Finality,
+        wait_timeout: Duration,
+        progress: Option<
+            &tokio::sync::mpsc::UnboundedSender<
+                near_jsonrpc_primitives::types::transactions::RpcTransactionProgress,
+            >,
+        >,
     ) -> Result<
         near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
-        timeout(self.polling_config.polling_timeout, async {
+        timeout(wait_timeout, async {
+            let mut reported_execution = false;
             loop {
-                match self.tx_status_fetch(tx_info.clone(), false).await {
+                match self.tx_status_fetch_internal(tx_info.clone(), false).await {
                     Ok(tx_status) => {
-                        break Ok(
-                            near_jsonrpc_primitives::types::transactions::RpcTransactionResponse {
-                                final_execution_outcome: tx_status,
-                            },
-                        )
+                        if self.tx_has_finality(&tx_status, finality).await? {
+                            break Ok(
+                                near_jsonrpc_primitives::types::transactions::RpcTransactionResponse {
+                                    final_execution_outcome: tx_status,
+                                },
+                            );
+                        }
+                        if let (Some(progress), false) = (progress, reported_execution) {
+                            let execution_status = match tx_status {
+                                FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome) => {
+                                    outcome.status
+                                }
+                                FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(
+                                    outcome,
+                                ) => outcome.final_outcome.status,
+                            };
+                            if !matches!(
+                                execution_status,
+                                FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started
+                            ) {
+                                let _ = progress.send(near_jsonrpc_primitives::types::transactions::RpcTransactionProgress::Executed {
+                                    execution_status,
+                                });
+                                reported_execution = true;
+                            }
+                        }
                     }
-                    // If transaction is missing, keep polling.
-                    Err(TxStatusError::MissingTransaction(_)) => {}
+                    // If transaction is missing, keep polling.  Fetching the status may time out
+                    // sooner than the caller wants to wait.
+                    Err(TxStatusError::MissingTransaction(_)) | Err(TxStatusError::TimeoutError) => {}
                     // If we hit any other error, we return to the user.
                     Err(err) => {
                         break Err(err.into());
@@ -738,6 +1150,71 @@ impl JsonRpcHandler {
         })?
     }
 
+    /// Whether the transaction and its receipts were executed in blocks of the canonical chain
+    /// with the given finality.
+    async fn tx_has_finality(
+        &self,
+        tx_status: &FinalExecutionOutcomeViewEnum,
+        finality: &Finality,
+    ) -> Result<bool, near_jsonrpc_primitives::types::transactions::RpcTransactionError> {
+        if finality == &Finality::None {
+            return Ok(true);
+        }
+        let outcome = match tx_status {
+            FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome) => outcome,
+            FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome) => {
+                &outcome.final_outcome
+            }
+        };
+        if matches!(
+            outcome.status,
+            FinalExecutionStatus::NotStarted | FinalExecutionStatus::Started
+        ) {
+            return Ok(false);
+        }
+        let final_height =
+            self.get_block(BlockReference::Finality(finality.clone())).await?.header.height;
+        let block_hashes: HashSet<CryptoHash> = std::iter::once(&outcome.transaction_outcome)
+            .chain(outcome.receipts_outcome.iter())
+            .map(|outcome| outcome.block_hash)
+            .collect();
+        for block_hash in block_hashes {
+            let height = self
+                .get_block(BlockReference::BlockId(BlockId::Hash(block_hash)))
+                .await?
+                .header
+                .height;
+            if height > final_height {
+                return Ok(false);
+            }
+            match self.view_client_addr.send(GetBlock(BlockReference::BlockId(BlockId::Height(height)))).await? {
+                Ok(block) if block.header.hash == block_hash => {}
+                // The block isn't on the canonical chain, so the transaction status will change.
+                Ok(_) | Err(GetBlockError::UnknownBlock { .. }) => return Ok(false),
+                Err(err) => {
+                    return Err(near_jsonrpc_primitives::types::transactions::RpcTransactionError::InternalError {
+                        debug_info: err.to_string(),
+                    })
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    async fn get_block(
+        &self,
+        block_reference: BlockReference,
+    ) -> Result<
+        near_primitives::views::BlockView,
+        near_jsonrpc_primitives::types::transactions::RpcTransactionError,
+    > {
+        self.view_client_addr.send(GetBlock(block_reference)).await?.map_err(|err| {
+            near_jsonrpc_primitives::types::transactions::RpcTransactionError::InternalError {
+                debug_info: err.to_string(),
+            }
+        })
+    }
+
     /// Send a transaction idempotently (subsequent send of the same transaction will not cause
     /// any new side-effects and the result will be the same unless we garbage collected it
     /// already).
@@ -782,6 +1259,9 @@ impl JsonRpcHandler {
         near_jsonrpc_primitives::types::transactions::RpcBroadcastTxSyncResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
+        if request_data.preflight {
+            self.preflight_tx(&request_data.signed_transaction).await?;
+        }
         match self.send_tx(request_data.clone().signed_transaction, false).await? {
             NetworkClientResponses::ValidTx => {
                 Ok(near_jsonrpc_primitives::types::transactions::RpcBroadcastTxSyncResponse {
@@ -825,27 +1305,31 @@ impl JsonRpcHandler {
         }
     }
 
+    /// Sends the transaction unless it was sent already and waits for it to be executed with the
+    /// requested finality, reporting the progress if requested.
     async fn send_tx_commit(
         &self,
         request_data: near_jsonrpc_primitives::types::transactions::RpcBroadcastTransactionRequest,
+        progress: Option<
+            &tokio::sync::mpsc::UnboundedSender<
+                near_jsonrpc_primitives::types::transactions::RpcTransactionProgress,
+            >,
+        >,
     ) -> Result<
         near_jsonrpc_primitives::types::transactions::RpcTransactionResponse,
         near_jsonrpc_primitives::types::transactions::RpcTransactionError,
     > {
         let tx = request_data.signed_transaction;
-        match self
-            .tx_status_fetch(
-                near_jsonrpc_primitives::types::transactions::TransactionInfo::Transaction(
-                    tx.clone(),
-                ),
-                false,
-            )
-            .await
-        {
-            Ok(outcome) => {
-                return Ok(near_jsonrpc_primitives::types::transactions::RpcTransactionResponse {
-                    final_execution_outcome: outcome,
-                });
+        let finality = request_data.finality;
+        let wait_timeout =
+            request_data.timeout.map_or(self.polling_config.polling_timeout, |requested| {
+                requested.min(self.polling_config.max_polling_timeout)
+            });
+        let tx_info =
+            near_jsonrpc_primitives::types::transactions::TransactionInfo::Transaction(tx.clone());
+        match self.tx_status_fetch(tx_info.clone(), false).await {
+            Ok(_) => {
+                return self.tx_polling(tx_info, &finality, wait_timeout, progress).await;
             }
             Err(TxStatusError::InvalidTx(invalid_tx_error)) => {
                 return Err(near_jsonrpc_primitives::types::transactions::RpcTransactionError::InvalidTransaction {
@@ -854,9 +1338,17 @@ impl JsonRpcHandler {
             }
             _ => {}
         }
+        if request_data.preflight {
+            self.preflight_tx(&tx).await?;
+        }
         match self.send_tx(tx.clone(), false).await? {
             NetworkClientResponses::ValidTx | NetworkClientResponses::RequestRouted => {
-                self.tx_polling(near_jsonrpc_primitives::types::transactions::TransactionInfo::Transaction(tx)).await
+                if let Some(progress) = progress {
+                    let _ = progress.send(near_jsonrpc_primitives::types::transactions::RpcTransactionProgress::Sent {
+                        transaction_hash: tx.get_hash(),
+                    });
+                }
+                self.tx_polling(tx_info, &finality, wait_timeout, progress).await
             }
             network_client_response=> {
                 Err(
@@ -912,6 +1404,71 @@ impl JsonRpcHandler {
         &self.genesis_config
     }
 
+    /// Statistics of the threads, async runtimes and thread pools of the node.  Doesn't go
+    /// through any actor, so it answers even if they are blocked.
+    pub async fn debug_threads(&self) -> near_performance_metrics::runtime_stats::RuntimeStats {
+        near_performance_metrics::runtime_stats::snapshot()
+    }
+
+    /// Changes the log filter directives of the node, for good or until they expire, and returns
+    /// the ones in effect.  Exposed only when debug RPC is enabled.
+    async fn log_filter(
+        &self,
+        request_data: near_jsonrpc_primitives::types::log_filter::RpcLogFilterRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::log_filter::RpcLogFilterResponse,
+        near_jsonrpc_primitives::types::log_filter::RpcLogFilterError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::log_filter::RpcLogFilterError::NotAvailable {
+                    error_message: "debug RPC is disabled".to_string(),
+                },
+            );
+        }
+        let into_rpc_error = |err: near_o11y::LogFilterError| match err {
+            near_o11y::LogFilterError::NoSubscriber => {
+                near_jsonrpc_primitives::types::log_filter::RpcLogFilterError::NotAvailable {
+                    error_message: err.to_string(),
+                }
+            }
+            near_o11y::LogFilterError::InvalidDirective(_) => {
+                near_jsonrpc_primitives::types::log_filter::RpcLogFilterError::InvalidDirective {
+                    error_message: err.to_string(),
+                }
+            }
+            near_o11y::LogFilterError::Reload(_) => {
+                near_jsonrpc_primitives::types::log_filter::RpcLogFilterError::InternalError {
+                    error_message: err.to_string(),
+                }
+            }
+        };
+        if let Some(directives) = &request_data.directives {
+            near_o11y::set_log_filter(directives).map_err(into_rpc_error)?;
+        }
+        if let Some(directives) = &request_data.temporary_directives {
+            near_o11y::add_temporary_log_filter(
+                directives,
+                Duration::from_secs(request_data.duration_secs),
+            )
+            .map_err(into_rpc_error)?;
+        }
+        let status = near_o11y::log_filter().map_err(into_rpc_error)?;
+        Ok(near_jsonrpc_primitives::types::log_filter::RpcLogFilterResponse {
+            directives: status.directives,
+            temporary_directives: status
+                .temporary_directives
+                .into_iter()
+                .map(|(directive, expires_in)| {
+                    near_jsonrpc_primitives::types::log_filter::RpcTemporaryLogDirective {
+                        directive,
+                        expires_in_secs: expires_in.as_secs(),
+                    }
+                })
+                .collect(),
+        })
+    }
+
     pub async fn protocol_config(
         &self,
         request_data: near_jsonrpc_primitives::types::config::RpcProtocolConfigRequest,
@@ -926,6 +1483,24 @@ impl JsonRpcHandler {
         Ok(RpcProtocolConfigResponse { config_view })
     }
 
+    /// Returns the runtime config parameters which differ between two protocol versions, each
+    /// given either directly or by an epoch in which it was active.
+    pub async fn protocol_config_diff(
+        &self,
+        request_data: near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffResponse,
+        near_jsonrpc_primitives::types::config::RpcProtocolConfigError,
+    > {
+        let config_diff_view = self
+            .view_client_addr
+            .send(GetProtocolConfigDiff { from: request_data.from, to: request_data.to })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::config::RpcProtocolConfigDiffResponse {
+            config_diff_view,
+        })
+    }
+
     async fn query(
         &self,
         request_data: near_jsonrpc_primitives::types::query::RpcQueryRequest,
@@ -995,6 +1570,178 @@ impl JsonRpcHandler {
         }
     }
 
+    async fn delayed_receipts(
+        &self,
+        request_data: near_jsonrpc_primitives::types::receipts::RpcDelayedReceiptsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::receipts::RpcDelayedReceiptsResponse,
+        near_jsonrpc_primitives::types::receipts::RpcDelayedReceiptsError,
+    > {
+        let delayed_receipts_view = self
+            .view_client_addr
+            .send(GetDelayedReceipts {
+                block_reference: request_data.block_reference,
+                shard_id: request_data.shard_id,
+                from_index: request_data.from_index,
+                limit: request_data.limit,
+            })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::receipts::RpcDelayedReceiptsResponse {
+            delayed_receipts_view,
+        })
+    }
+
+    /// Returns a page of the transactions and receipts executed on the account, newest first.
+    async fn account_history(
+        &self,
+        request_data: near_jsonrpc_primitives::types::account_history::RpcAccountHistoryRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse,
+        near_jsonrpc_primitives::types::account_history::RpcAccountHistoryError,
+    > {
+        let cursor = match request_data.cursor {
+            Some(cursor) => Some(from_base(&cursor).map_err(|_| {
+                near_jsonrpc_primitives::types::account_history::RpcAccountHistoryError::InvalidCursor {
+                    cursor,
+                }
+            })?),
+            None => None,
+        };
+        let account_history_view = self
+            .view_client_addr
+            .send(GetAccountHistory {
+                account_id: request_data.account_id,
+                cursor,
+                limit: request_data.limit,
+            })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::account_history::RpcAccountHistoryResponse {
+            account_history_view,
+        })
+    }
+
+    /// Returns the accounts with the contract code of the given hash deployed as of the block.
+    async fn accounts_by_code_hash(
+        &self,
+        request_data: near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashResponse,
+        near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashError,
+    > {
+        let accounts_by_code_hash_view = self
+            .view_client_addr
+            .send(GetAccountsByCodeHash {
+                block_reference: request_data.block_reference,
+                code_hash: request_data.code_hash,
+            })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::contracts::RpcAccountsByCodeHashResponse {
+            accounts_by_code_hash_view,
+        })
+    }
+
+    /// Returns the accounts which burnt the most gas in an epoch.  Exposed only when debug RPC is
+    /// enabled.
+    async fn gas_attribution(
+        &self,
+        request_data: near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionResponse,
+        near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionError::NotAvailable {
+                    error_message: "debug RPC is disabled".to_string(),
+                },
+            );
+        }
+        let gas_attribution_view = self
+            .view_client_addr
+            .send(GetGasAttribution { epoch_id: request_data.epoch_id })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::gas_attribution::RpcGasAttributionResponse {
+            gas_attribution_view,
+        })
+    }
+
+    /// Returns the statistics of the memory allocator of the node and optionally dumps its heap
+    /// profile.  Doesn't go through any actor, like `debug_threads`, but is exposed only when
+    /// debug RPC is enabled since the dump writes to the disk of the node.
+    async fn allocator_stats(
+        &self,
+        request_data: near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsResponse,
+        near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsError::NotAvailable {
+                    error_message: "debug RPC is disabled".to_string(),
+                },
+            );
+        }
+        let heap_profile = if request_data.dump_heap_profile {
+            let path = near_performance_metrics::allocator_stats::dump_heap_profile()
+                .map_err(convert_allocator_stats_error)?;
+            Some(path.display().to_string())
+        } else {
+            None
+        };
+        let allocator_stats = near_performance_metrics::allocator_stats::snapshot()
+            .map_err(convert_allocator_stats_error)?;
+        Ok(near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsResponse {
+            allocator_stats: convert_allocator_stats(allocator_stats),
+            heap_profile,
+        })
+    }
+
+    /// Returns the profiles of the contract calls made since the previous request and forgets
+    /// them.  Requires the node to be built with the `vm_profiler` feature and, like
+    /// `allocator_stats`, doesn't go through any actor but is exposed only when debug RPC is
+    /// enabled.
+    async fn contract_profiles(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesResponse,
+        near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesError::NotAvailable {
+                    error_message: "debug RPC is disabled".to_string(),
+                },
+            );
+        }
+        Ok(near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesResponse {
+            profiles: take_contract_profiles()?,
+        })
+    }
+
+    /// Replays the receipt and returns the host function calls and storage accesses it made.
+    /// Replaying is expensive, so the method is only exposed when debug RPC is enabled.
+    async fn receipt_trace(
+        &self,
+        request_data: near_jsonrpc_primitives::types::receipts::RpcReceiptTraceRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::receipts::RpcReceiptTraceResponse,
+        near_jsonrpc_primitives::types::receipts::RpcReceiptTraceError,
+    > {
+        if !self.enable_debug_rpc {
+            return Err(
+                near_jsonrpc_primitives::types::receipts::RpcReceiptTraceError::TraceNotAvailable {
+                    error_message: "debug RPC is disabled".to_string(),
+                },
+            );
+        }
+        let receipt_trace_view = self
+            .view_client_addr
+            .send(TraceReceipt { receipt_id: request_data.receipt_reference.receipt_id })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::receipts::RpcReceiptTraceResponse { receipt_trace_view })
+    }
+
     async fn changes_in_block(
         &self,
         request: near_jsonrpc_primitives::types::changes::RpcStateChangesInBlockRequest,
@@ -1131,6 +1878,132 @@ impl JsonRpcHandler {
             request;
         Ok(self.view_client_addr.send(GetValidatorOrdered { block_id }).await??.into())
     }
+
+    /// Returns the upcoming heights of the current epoch at which the given validator produces
+    /// neither blocks nor chunks.
+    async fn maintenance_windows(
+        &self,
+        request_data: near_jsonrpc_primitives::types::validator::RpcMaintenanceWindowsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcMaintenanceWindowsResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let maintenance_windows = self
+            .view_client_addr
+            .send(GetMaintenanceWindows { account_id: request_data.account_id })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcMaintenanceWindowsResponse {
+            maintenance_windows,
+        })
+    }
+
+    /// Returns the block producer of each height and the chunk producer of each height and shard
+    /// of the epoch.
+    async fn producer_schedule(
+        &self,
+        request_data: near_jsonrpc_primitives::types::validator::RpcValidatorRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcProducerScheduleResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let schedule = self
+            .view_client_addr
+            .send(GetProducerSchedule { epoch_reference: request_data.epoch_reference })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcProducerScheduleResponse { schedule })
+    }
+
+    /// Returns the events reported since the given one when the validator run by the node missed
+    /// blocks or chunks or was projected to be kicked out.
+    async fn validator_events(
+        &self,
+        request_data: near_jsonrpc_primitives::types::validator::RpcValidatorEventsRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcValidatorEventsResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let validator_events =
+            self.client_addr.send(GetValidatorEvents { from_id: request_data.from_id }).await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcValidatorEventsResponse {
+            validator_events,
+        })
+    }
+
+    async fn missed_production(
+        &self,
+        request_data: near_jsonrpc_primitives::types::validator::RpcMissedProductionRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcMissedProductionResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let misses =
+            self.client_addr.send(GetMissedProduction { from_id: request_data.from_id }).await?;
+        Ok(near_jsonrpc_primitives::types::validator::RpcMissedProductionResponse { misses })
+    }
+
+    async fn fee_estimate(
+        &self,
+        request_data: near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateResponse,
+        near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateError,
+    > {
+        let fee_estimate_view =
+            self.client_addr.send(GetFeeEstimate { shard_id: request_data.shard_id }).await??;
+        Ok(near_jsonrpc_primitives::types::gas_price::RpcFeeEstimateResponse { fee_estimate_view })
+    }
+
+    async fn epoch_summary(
+        &self,
+        request_data: near_jsonrpc_primitives::types::validator::RpcEpochSummaryRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcEpochSummaryResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let summary = self
+            .client_addr
+            .send(GetEpochSummary { epoch_height: request_data.epoch_height })
+            .await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcEpochSummaryResponse { summary })
+    }
+
+    /// Projects the validators, seat price and kickouts of the epoch after next as if the current
+    /// epoch ended at the latest known block.
+    async fn validators_projection(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcValidatorsProjectionResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let projection = self.view_client_addr.send(GetValidatorProjection).await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcValidatorsProjectionResponse {
+            projection,
+        })
+    }
+
+    /// Returns the stake of the block producers of the current epoch voting for each protocol
+    /// version and the protocol upgrade the votes lead to.
+    async fn protocol_version_votes(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcProtocolVersionVotesResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let votes = self.view_client_addr.send(GetProtocolVersionVotes).await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcProtocolVersionVotesResponse { votes })
+    }
+
+    /// Returns the rewards of the current epoch projected from the block and chunk production so
+    /// far, with the resulting yield of each validator and inflation.
+    async fn reward_projection(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::validator::RpcRewardProjectionResponse,
+        near_jsonrpc_primitives::types::validator::RpcValidatorError,
+    > {
+        let projection = self.view_client_addr.send(GetRewardProjection).await??;
+        Ok(near_jsonrpc_primitives::types::validator::RpcRewardProjectionResponse { projection })
+    }
 }
 
 #[cfg(feature = "sandbox")]
@@ -1189,6 +2062,40 @@ impl JsonRpcHandler {
             .await?;
         Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxFastForwardResponse {})
     }
+
+    async fn sandbox_set_timestamp(
+        &self,
+        set_timestamp_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxSetTimestampRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetTimestampResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetTimestampError,
+    > {
+        self.client_addr
+            .send(NetworkClientMessages::Sandbox(
+                near_network_primitives::types::NetworkSandboxMessage::SandboxSetTimestamp(
+                    set_timestamp_request.timestamp,
+                ),
+            ))
+            .await?;
+        Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxSetTimestampResponse {})
+    }
+
+    async fn sandbox_set_gas_price(
+        &self,
+        set_gas_price_request: near_jsonrpc_primitives::types::sandbox::RpcSandboxSetGasPriceRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetGasPriceResponse,
+        near_jsonrpc_primitives::types::sandbox::RpcSandboxSetGasPriceError,
+    > {
+        self.client_addr
+            .send(NetworkClientMessages::Sandbox(
+                near_network_primitives::types::NetworkSandboxMessage::SandboxSetGasPrice(
+                    set_gas_price_request.gas_price,
+                ),
+            ))
+            .await?;
+        Ok(near_jsonrpc_primitives::types::sandbox::RpcSandboxSetGasPriceResponse {})
+    }
 }
 
 #[cfg(feature = "test_features")]
@@ -1296,6 +2203,20 @@ impl JsonRpcHandler {
         }
     }
 
+    async fn adv_set_store_faults(&self, params: Option<Value>) -> Result<Value, RpcError> {
+        let params =
+            parse_params::<near_jsonrpc_adversarial_primitives::SetStoreFaultsRequest>(params)?;
+        self.client_addr
+            .send(near_network::types::NetworkClientMessages::Adversarial(
+                near_network_primitives::types::NetworkAdversarialMessage::AdvSetStoreFaults(
+                    params.read_delay_ms,
+                    params.read_error_percent,
+                ),
+            ))
+            .await?;
+        Ok(Value::String("".to_string()))
+    }
+
     async fn adv_check_store(&self, _params: Option<Value>) -> Result<Value, RpcError> {
         match self
             .client_addr
@@ -1313,13 +2234,141 @@ impl JsonRpcHandler {
     }
 }
 
+fn convert_allocator_stats(
+    stats: near_performance_metrics::allocator_stats::AllocatorStats,
+) -> near_jsonrpc_primitives::types::allocator::AllocatorStats {
+    near_jsonrpc_primitives::types::allocator::AllocatorStats {
+        allocator: stats.allocator,
+        allocated: stats.allocated,
+        active: stats.active,
+        metadata: stats.metadata,
+        resident: stats.resident,
+        mapped: stats.mapped,
+        retained: stats.retained,
+        fragmentation: stats.fragmentation,
+        arenas: stats
+            .arenas
+            .into_iter()
+            .map(|arena| near_jsonrpc_primitives::types::allocator::ArenaStats {
+                index: arena.index,
+                threads: arena.threads,
+                active: arena.active,
+                dirty: arena.dirty,
+                muzzy: arena.muzzy,
+                resident: arena.resident,
+                mapped: arena.mapped,
+            })
+            .collect(),
+    }
+}
+
+fn convert_allocator_stats_error(
+    error: near_performance_metrics::allocator_stats::AllocatorStatsError,
+) -> near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsError {
+    use near_jsonrpc_primitives::types::allocator::RpcAllocatorStatsError;
+    use near_performance_metrics::allocator_stats::AllocatorStatsError;
+    match error {
+        AllocatorStatsError::NotAvailable | AllocatorStatsError::ProfilingDisabled(_) => {
+            RpcAllocatorStatsError::NotAvailable { error_message: error.to_string() }
+        }
+        AllocatorStatsError::Allocator(_) => {
+            RpcAllocatorStatsError::InternalError { error_message: error.to_string() }
+        }
+    }
+}
+
+#[cfg(feature = "vm_profiler")]
+fn take_contract_profiles() -> Result<
+    Vec<near_jsonrpc_primitives::types::contract_profiles::ContractCallProfileView>,
+    near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesError,
+> {
+    use near_jsonrpc_primitives::types::contract_profiles::{
+        ContractCallProfileView, OpcodeProfileView,
+    };
+    let profiles = near_vm_runner::profiler::take_profiles()
+        .into_iter()
+        .map(|profile| ContractCallProfileView {
+            code_hash: profile.code_hash,
+            method_name: profile.method_name,
+            wall_time_us: profile.wall_time.as_micros() as u64,
+            burnt_gas: profile.burnt_gas,
+            host_function_calls: profile
+                .host_function_calls
+                .into_iter()
+                .map(|(name, count)| (name.to_string(), count))
+                .collect(),
+            gas_profile: near_primitives::views::ExecutionMetadataView::from(
+                near_primitives::transaction::ExecutionMetadata::V2(profile.gas_profile),
+            )
+            .gas_profile
+            .unwrap_or_default(),
+            opcodes: profile
+                .opcodes
+                .into_iter()
+                .map(|(name, opcode)| {
+                    (name, OpcodeProfileView { count: opcode.count, gas: opcode.gas })
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(profiles)
+}
+
+#[cfg(not(feature = "vm_profiler"))]
+fn take_contract_profiles() -> Result<
+    Vec<near_jsonrpc_primitives::types::contract_profiles::ContractCallProfileView>,
+    near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesError,
+> {
+    Err(near_jsonrpc_primitives::types::contract_profiles::RpcContractProfilesError::NotAvailable {
+        error_message: "the node was built without the vm_profiler feature".to_string(),
+    })
+}
+
+fn observe_request(request_method: &str, timer: Instant, error: Option<&RpcError>) {
+    metrics::HTTP_RPC_REQUEST_COUNT.with_label_values(&[request_method]).inc();
+    metrics::RPC_PROCESSING_TIME
+        .with_label_values(&[request_method])
+        .observe(timer.elapsed().as_secs_f64());
+
+    if let Some(err) = error {
+        metrics::RPC_ERROR_COUNT.with_label_values(&[request_method, &err.code.to_string()]).inc();
+        metrics::RPC_ERROR_KIND_COUNT
+            .with_label_values(&[request_method, error_kind_label(err)])
+            .inc();
+    }
+}
+
 fn rpc_handler(
     message: web::Json<Message>,
     handler: web::Data<JsonRpcHandler>,
+    http_request: HttpRequest,
 ) -> impl Future<Output = Result<HttpResponse, HttpError>> {
     let response = async move {
+        match &message.0 {
+            Message::Request(request)
+                if BORSH_METHODS.contains(&request.method.as_str())
+                    && wants_borsh(&http_request) =>
+            {
+                let id = request.id.clone();
+                return match handler.process_borsh_request(request.clone()).await {
+                    Ok(body) => Ok(HttpResponse::Ok().content_type(BORSH_CONTENT_TYPE).body(body)),
+                    Err(err) => Ok(HttpResponse::Ok().json(&Message::response(id, Err(err)))),
+                };
+            }
+            _ => {}
+        }
+        let request_method = match &message.0 {
+            Message::Request(request) => Some(request.method.clone()),
+            _ => None,
+        };
         let message = handler.process(message.0).await?;
-        Ok(HttpResponse::Ok().json(&message))
+        let body = serde_json::to_vec(&message)?;
+        if let (Some(request_method), Message::Response(response)) = (request_method, &message) {
+            metrics::RPC_RESPONSE_SIZE
+                .with_label_values(&[method_label(&request_method, &response.result)])
+                .observe(body.len() as f64);
+        }
+        Ok(HttpResponse::Ok().content_type("application/json").body(body))
     };
     response.boxed()
 }
@@ -1450,7 +2499,10 @@ pub fn start_http(
         polling_config,
         limits_config,
         enable_debug_rpc,
+        jobs_config,
     } = config;
+    let genesis_config = Arc::new(genesis_config);
+    let jobs = jobs::JobRegistry::new(jobs_config);
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr);
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
     info!(target:"network", "Starting http server at {}", addr);
@@ -1464,6 +2516,7 @@ pub fn start_http(
                 polling_config,
                 genesis_config: genesis_config.clone(),
                 enable_debug_rpc,
+                jobs: jobs.clone(),
                 #[cfg(feature = "test_features")]
                 peer_manager_addr: peer_manager_addr.clone(),
                 #[cfg(feature = "test_features")]
@@ -1484,6 +2537,11 @@ pub fn start_http(
             )
             .service(web::resource("/network_info").route(web::get().to(network_info_handler)))
             .service(web::resource("/metrics").route(web::get().to(prometheus_handler)))
+            .service(
+                web::resource("/contract_logs")
+                    .route(web::get().to(contract_logs::contract_logs_handler)),
+            )
+            .service(web::resource("/tx_commit").route(web::get().to(tx_commit::tx_commit_handler)))
             .service(web::resource("/debug/api/last_blocks").route(web::get().to(debug_handler)))
             .service(debug_html)
             .service(last_blocks_html)
@@ -1,4 +1,4 @@
-use near_metrics::{HistogramVec, IntCounter, IntCounterVec};
+use near_metrics::{HistogramVec, IntCounter, IntCounterVec, IntGauge};
 use once_cell::sync::Lazy;
 
 pub static RPC_PROCESSING_TIME: Lazy<HistogramVec> = Lazy::new(|| {
@@ -47,3 +47,44 @@ pub static RPC_ERROR_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static RPC_ERROR_KIND_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_metrics::try_create_int_counter_vec(
+        "near_rpc_error_kind_count",
+        "Total count of errors by method and structured error kind",
+        &["method", "kind"],
+    )
+    .unwrap()
+});
+pub static RPC_RESPONSE_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    near_metrics::try_create_histogram_vec(
+        "near_rpc_response_size_bytes",
+        "Size in bytes of serialized rpc responses",
+        &["method"],
+        Some(prometheus::exponential_buckets(64.0, 4.0, 12).unwrap()),
+    )
+    .unwrap()
+});
+pub static RPC_IN_FLIGHT_REQUESTS: Lazy<IntGauge> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge(
+        "near_rpc_in_flight_requests",
+        "Number of rpc requests currently being processed",
+    )
+    .unwrap()
+});
+
+/// Keeps [`RPC_IN_FLIGHT_REQUESTS`] incremented for as long as it is alive, so that requests
+/// dropped half-way (e.g. on client disconnect) are still accounted for.
+pub(crate) struct InFlightRequestGuard;
+
+impl InFlightRequestGuard {
+    pub(crate) fn new() -> Self {
+        RPC_IN_FLIGHT_REQUESTS.inc();
+        Self
+    }
+}
+
+impl Drop for InFlightRequestGuard {
+    fn drop(&mut self) {
+        RPC_IN_FLIGHT_REQUESTS.dec();
+    }
+}
@@ -0,0 +1,90 @@
+//! `/tx_commit` WebSocket, which sends the transaction given in the first text message, with the
+//! same parameters as `broadcast_tx_commit`, and streams its progress as JSON text messages until
+//! it's executed with the requested finality.
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web::{web, Error as HttpError, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use near_jsonrpc_primitives::errors::RpcError;
+use near_jsonrpc_primitives::types::transactions::{
+    RpcBroadcastTransactionRequest, RpcTransactionProgress,
+};
+
+use crate::JsonRpcHandler;
+
+pub(crate) async fn tx_commit_handler(
+    handler: web::Data<JsonRpcHandler>,
+    request: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, HttpError> {
+    ws::start(TxCommitSession { handler, received: false }, &request, stream)
+}
+
+struct TxCommitSession {
+    handler: web::Data<JsonRpcHandler>,
+    /// Whether the transaction was received; a session sends a single transaction.
+    received: bool,
+}
+
+impl Actor for TxCommitSession {
+    type Context = ws::WebsocketContext<Self>;
+}
+
+impl TxCommitSession {
+    fn send_tx(&mut self, params: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        let request = serde_json::from_str(params)
+            .map_err(|err| RpcError::parse_error(err.to_string()))
+            .and_then(|params| Ok(RpcBroadcastTransactionRequest::parse(Some(params))?));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        ctx.add_stream(UnboundedReceiverStream::new(receiver));
+        let request = match request {
+            Ok(request) => request,
+            Err(error) => {
+                let _ = sender.send(RpcTransactionProgress::Failed { error });
+                return;
+            }
+        };
+        let handler = self.handler.clone();
+        actix::spawn(async move {
+            let progress = match handler.send_tx_commit(request, Some(&sender)).await {
+                Ok(response) => RpcTransactionProgress::Completed {
+                    final_execution_outcome: response.final_execution_outcome,
+                },
+                Err(err) => RpcTransactionProgress::Failed { error: err.into() },
+            };
+            let _ = sender.send(progress);
+        });
+    }
+}
+
+impl StreamHandler<RpcTransactionProgress> for TxCommitSession {
+    fn handle(&mut self, progress: RpcTransactionProgress, ctx: &mut Self::Context) {
+        ctx.text(serde_json::to_string(&progress).unwrap());
+    }
+
+    /// The sender is dropped once the transaction completed or failed.
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseCode::Normal.into()));
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for TxCommitSession {
+    fn handle(&mut self, message: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match message {
+            Ok(ws::Message::Text(params)) if !self.received => {
+                self.received = true;
+                self.send_tx(&params, ctx);
+            }
+            Ok(ws::Message::Ping(payload)) => ctx.pong(&payload),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => {}
+            Err(_) => ctx.stop(),
+        }
+    }
+}
@@ -0,0 +1,600 @@
+//! Verification of the data a light client receives from an untrusted node: the light client
+//! blocks which move its head from epoch to epoch, the proofs of execution outcomes returned by
+//! `EXPERIMENTAL_light_client_proof`, and the proofs of state items under the state root of a
+//! chunk.
+//!
+//! The checks are those of the [light client spec](https://nomicon.io/ChainSpec/LightClient),
+//! built on the hashing and merkle code of the node itself, so that bridges and other light
+//! clients follow the protocol changes by updating this crate.
+use std::collections::HashMap;
+
+use borsh::BorshSerialize;
+use near_primitives::block_header::{Approval, ApprovalInner, BlockHeaderInnerLite};
+use near_primitives::challenge::PartialState;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::merkle::{
+    combine_hash, compute_root_from_path_and_item, verify_hash, verify_path, MerklePath,
+};
+use near_primitives::serialize::from_base64;
+use near_primitives::transaction::{PartialExecutionOutcome, PartialExecutionStatus};
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::{AccountId, Balance, StateRoot};
+use near_primitives::views::validator_stake_view::ValidatorStakeView;
+use near_primitives::views::{
+    BlockHeaderInnerLiteView, ExecutionOutcomeView, ExecutionOutcomeWithIdView,
+    ExecutionStatusView, LightClientBlockLiteView, LightClientBlockView,
+};
+use near_store::{PartialStorage, Trie};
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum LightClientError {
+    #[error("Block height {height} is not above the height of the head {head_height}")]
+    NotNewerThanHead { height: u64, head_height: u64 },
+    #[error(
+        "Block is in epoch {epoch_id} which is neither the epoch of the head nor the next one"
+    )]
+    UnknownEpoch { epoch_id: CryptoHash },
+    #[error("Block producers of epoch {epoch_id} are unknown")]
+    UnknownBlockProducers { epoch_id: CryptoHash },
+    #[error("Block is the first one in the next epoch but doesn't carry the block producers of the epoch after it")]
+    MissingNextBlockProducers,
+    #[error("Block has {num_approvals} approvals for {num_block_producers} block producers")]
+    ApprovalsMismatch { num_approvals: usize, num_block_producers: usize },
+    #[error("Approval of block producer {account_id} has an invalid signature")]
+    InvalidApprovalSignature { account_id: AccountId },
+    #[error("Approvals carry {approved_stake} of the total stake {total_stake}, which is not more than 2/3")]
+    NotEnoughApprovals { approved_stake: Balance, total_stake: Balance },
+    #[error("Hash of the next block producers doesn't match next_bp_hash of the block")]
+    NextBlockProducersMismatch,
+    #[error("Execution outcome has an invalid value: {0}")]
+    InvalidOutcome(String),
+    #[error("Execution outcome is not included in the outcome root of its block")]
+    InvalidOutcomeProof,
+    #[error("Execution outcome is in block {expected}, but the header given is of block {actual}")]
+    BlockHashMismatch { expected: CryptoHash, actual: CryptoHash },
+    #[error("Block {block_hash} is not included in the block merkle root of the head")]
+    InvalidBlockProof { block_hash: CryptoHash },
+    #[error("State root is not included in the state root of the block")]
+    InvalidStateRootProof,
+    #[error("State proof is incomplete or doesn't lead to the state root: {0}")]
+    InvalidStateProof(String),
+}
+
+/// Hash of the block the header of which is given.
+pub fn block_hash(
+    prev_block_hash: &CryptoHash,
+    inner_lite: &BlockHeaderInnerLiteView,
+    inner_rest_hash: &CryptoHash,
+) -> CryptoHash {
+    let inner_lite_bytes =
+        BlockHeaderInnerLite::from(inner_lite.clone()).try_to_vec().expect("Failed to serialize");
+    let inner_hash = combine_hash(&hash(&inner_lite_bytes), inner_rest_hash);
+    combine_hash(&inner_hash, prev_block_hash)
+}
+
+/// Hash of the block of the light client block.
+pub fn light_client_block_hash(block_view: &LightClientBlockView) -> CryptoHash {
+    block_hash(&block_view.prev_block_hash, &block_view.inner_lite, &block_view.inner_rest_hash)
+}
+
+/// Hash of the block of the lite header.
+pub fn lite_block_hash(block_view: &LightClientBlockLiteView) -> CryptoHash {
+    block_hash(&block_view.prev_block_hash, &block_view.inner_lite, &block_view.inner_rest_hash)
+}
+
+/// Checks that the light client block may follow the head: it's in the epoch of the head or in the
+/// next one, `block_producers` of its epoch approved the block after next with more than 2/3 of
+/// the stake, and the block producers of the next epoch it carries, if any, match its header.
+pub fn validate_light_client_block(
+    head: &LightClientBlockLiteView,
+    block_producers: &[ValidatorStakeView],
+    block_view: &LightClientBlockView,
+) -> Result<(), LightClientError> {
+    let inner_lite = &block_view.inner_lite;
+    if inner_lite.height <= head.inner_lite.height {
+        return Err(LightClientError::NotNewerThanHead {
+            height: inner_lite.height,
+            head_height: head.inner_lite.height,
+        });
+    }
+    if inner_lite.epoch_id != head.inner_lite.epoch_id
+        && inner_lite.epoch_id != head.inner_lite.next_epoch_id
+    {
+        return Err(LightClientError::UnknownEpoch { epoch_id: inner_lite.epoch_id });
+    }
+    if inner_lite.epoch_id == head.inner_lite.next_epoch_id && block_view.next_bps.is_none() {
+        return Err(LightClientError::MissingNextBlockProducers);
+    }
+    if block_view.approvals_after_next.len() > block_producers.len() {
+        return Err(LightClientError::ApprovalsMismatch {
+            num_approvals: block_view.approvals_after_next.len(),
+            num_block_producers: block_producers.len(),
+        });
+    }
+
+    // The approvals are those of the block after next, endorsing the next block.
+    let current_block_hash = light_client_block_hash(block_view);
+    let next_block_hash = combine_hash(&block_view.next_block_inner_hash, &current_block_hash);
+    let approval_message = Approval::get_data_for_sig(
+        &ApprovalInner::Endorsement(next_block_hash),
+        inner_lite.height + 2,
+    );
+    let mut total_stake: Balance = 0;
+    let mut approved_stake: Balance = 0;
+    for (i, block_producer) in block_producers.iter().enumerate() {
+        let block_producer = block_producer.clone().into_validator_stake();
+        total_stake += block_producer.stake();
+        let signature = match block_view.approvals_after_next.get(i) {
+            Some(Some(signature)) => signature,
+            _ => continue,
+        };
+        if !signature.verify(&approval_message, block_producer.public_key()) {
+            return Err(LightClientError::InvalidApprovalSignature {
+                account_id: block_producer.take_account_id(),
+            });
+        }
+        approved_stake += block_producer.stake();
+    }
+    if approved_stake * 3 <= total_stake * 2 {
+        return Err(LightClientError::NotEnoughApprovals { approved_stake, total_stake });
+    }
+
+    if let Some(next_bps) = &block_view.next_bps {
+        let next_bps: Vec<_> =
+            next_bps.iter().map(|bp| bp.clone().into_validator_stake()).collect();
+        // The hash is over the versioned stakes since `BlockHeaderV3` and over the first version
+        // before that.
+        let bp_hash = hash(&next_bps.try_to_vec().expect("Failed to serialize"));
+        let legacy_bp_hash = hash(
+            &next_bps
+                .into_iter()
+                .map(|bp| bp.into_v1())
+                .collect::<Vec<_>>()
+                .try_to_vec()
+                .expect("Failed to serialize"),
+        );
+        if inner_lite.next_bp_hash != bp_hash && inner_lite.next_bp_hash != legacy_bp_hash {
+            return Err(LightClientError::NextBlockProducersMismatch);
+        }
+    }
+    Ok(())
+}
+
+/// Head of a light client along with the block producers of the epochs it can validate the blocks
+/// of.  The head is moved with the blocks returned by `next_light_client_block`.
+pub struct LightClient {
+    head: LightClientBlockLiteView,
+    epoch_block_producers: HashMap<CryptoHash, Vec<ValidatorStakeView>>,
+}
+
+impl LightClient {
+    /// Starts from a trusted light client block and the block producers of its epoch.
+    pub fn new(head: LightClientBlockView, block_producers: Vec<ValidatorStakeView>) -> Self {
+        let mut epoch_block_producers = HashMap::new();
+        epoch_block_producers.insert(head.inner_lite.epoch_id, block_producers);
+        if let Some(next_bps) = head.next_bps {
+            epoch_block_producers.insert(head.inner_lite.next_epoch_id, next_bps);
+        }
+        let head = LightClientBlockLiteView {
+            prev_block_hash: head.prev_block_hash,
+            inner_rest_hash: head.inner_rest_hash,
+            inner_lite: head.inner_lite,
+        };
+        Self { head, epoch_block_producers }
+    }
+
+    pub fn head(&self) -> &LightClientBlockLiteView {
+        &self.head
+    }
+
+    /// Validates the block and makes it the head.
+    pub fn update_head(
+        &mut self,
+        block_view: LightClientBlockView,
+    ) -> Result<(), LightClientError> {
+        let epoch_id = block_view.inner_lite.epoch_id;
+        let block_producers = self
+            .epoch_block_producers
+            .get(&epoch_id)
+            .ok_or(LightClientError::UnknownBlockProducers { epoch_id })?;
+        validate_light_client_block(&self.head, block_producers, &block_view)?;
+        if let Some(next_bps) = block_view.next_bps {
+            self.epoch_block_producers.insert(block_view.inner_lite.next_epoch_id, next_bps);
+        }
+        // Only the epochs of the new head and the next one are needed from now on.
+        let (epoch_id, next_epoch_id) =
+            (block_view.inner_lite.epoch_id, block_view.inner_lite.next_epoch_id);
+        self.epoch_block_producers.retain(|id, _| *id == epoch_id || *id == next_epoch_id);
+        self.head = LightClientBlockLiteView {
+            prev_block_hash: block_view.prev_block_hash,
+            inner_rest_hash: block_view.inner_rest_hash,
+            inner_lite: block_view.inner_lite,
+        };
+        Ok(())
+    }
+
+    /// Verifies the execution outcome proof against the block merkle root of the head, which
+    /// covers the blocks before the head.
+    pub fn verify_execution_outcome(
+        &self,
+        outcome_proof: &ExecutionOutcomeWithIdView,
+        outcome_root_proof: &MerklePath,
+        block_header_lite: &LightClientBlockLiteView,
+        block_proof: &MerklePath,
+    ) -> Result<(), LightClientError> {
+        verify_execution_outcome(
+            &self.head.inner_lite.block_merkle_root,
+            outcome_proof,
+            outcome_root_proof,
+            block_header_lite,
+            block_proof,
+        )
+    }
+}
+
+/// Hashes of the execution outcome which its merkle proof is over.
+pub fn execution_outcome_hashes(
+    outcome: &ExecutionOutcomeView,
+) -> Result<Vec<CryptoHash>, LightClientError> {
+    let status = match &outcome.status {
+        ExecutionStatusView::Unknown => PartialExecutionStatus::Unknown,
+        ExecutionStatusView::SuccessValue(value) => PartialExecutionStatus::SuccessValue(
+            from_base64(value).map_err(|err| LightClientError::InvalidOutcome(err.to_string()))?,
+        ),
+        ExecutionStatusView::Failure(_) => PartialExecutionStatus::Failure,
+        ExecutionStatusView::SuccessReceiptId(id) => PartialExecutionStatus::SuccessReceiptId(*id),
+    };
+    let partial_outcome = PartialExecutionOutcome {
+        receipt_ids: outcome.receipt_ids.clone(),
+        gas_burnt: outcome.gas_burnt,
+        tokens_burnt: outcome.tokens_burnt,
+        executor_id: outcome.executor_id.clone(),
+        status,
+    };
+    let mut result = vec![hash(&partial_outcome.try_to_vec().expect("Failed to serialize"))];
+    result.extend(outcome.logs.iter().map(|log| hash(log.as_bytes())));
+    Ok(result)
+}
+
+/// Verifies the response of `EXPERIMENTAL_light_client_proof`: the outcome is included in the
+/// outcome root of its block, and the block is included in `block_merkle_root`, that of the light
+/// client head the proof was requested for.
+pub fn verify_execution_outcome(
+    block_merkle_root: &CryptoHash,
+    outcome_proof: &ExecutionOutcomeWithIdView,
+    outcome_root_proof: &MerklePath,
+    block_header_lite: &LightClientBlockLiteView,
+    block_proof: &MerklePath,
+) -> Result<(), LightClientError> {
+    let mut outcome_hashes = vec![outcome_proof.id];
+    outcome_hashes.extend(execution_outcome_hashes(&outcome_proof.outcome)?);
+    let chunk_outcome_root = compute_root_from_path_and_item(&outcome_proof.proof, &outcome_hashes);
+    if !verify_path(
+        block_header_lite.inner_lite.outcome_root,
+        outcome_root_proof,
+        &chunk_outcome_root,
+    ) {
+        return Err(LightClientError::InvalidOutcomeProof);
+    }
+    let block_hash = lite_block_hash(block_header_lite);
+    if block_hash != outcome_proof.block_hash {
+        return Err(LightClientError::BlockHashMismatch {
+            expected: outcome_proof.block_hash,
+            actual: block_hash,
+        });
+    }
+    if !verify_hash(*block_merkle_root, block_proof, block_hash) {
+        return Err(LightClientError::InvalidBlockProof { block_hash });
+    }
+    Ok(())
+}
+
+/// Verifies that the state root of a chunk is included in the state root of the block, which is
+/// the merkle root of the state roots of all its chunks.
+pub fn verify_chunk_state_root(
+    block_header_lite: &LightClientBlockLiteView,
+    chunk_state_root: &StateRoot,
+    proof: &MerklePath,
+) -> Result<(), LightClientError> {
+    if verify_path(block_header_lite.inner_lite.prev_state_root, proof, chunk_state_root) {
+        Ok(())
+    } else {
+        Err(LightClientError::InvalidStateRootProof)
+    }
+}
+
+/// Looks the key up in the trie nodes of a state proof, returning the value under the key, or
+/// `None` if the proof shows there is none.  Fails if the nodes don't lead from the state root to
+/// the key.
+pub fn verify_state_proof(
+    state_root: &StateRoot,
+    proof: Vec<Vec<u8>>,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, LightClientError> {
+    let trie = Trie::from_recorded_storage(PartialStorage { nodes: PartialState(proof) });
+    trie.get(state_root, key).map_err(|err| LightClientError::InvalidStateProof(err.to_string()))
+}
+
+/// Looks up the contract data of the account in the trie nodes of a state proof.
+pub fn verify_contract_data_proof(
+    state_root: &StateRoot,
+    proof: Vec<Vec<u8>>,
+    account_id: &AccountId,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, LightClientError> {
+    let trie_key = TrieKey::ContractData { account_id: account_id.clone(), key: key.to_vec() };
+    verify_state_proof(state_root, proof, &trie_key.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::KeyType;
+    use near_primitives::merkle::{merklize, Direction, MerklePathItem};
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::types::validator_stake::ValidatorStake;
+    use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+    use near_store::test_utils::{create_tries, test_populate_trie};
+
+    use super::*;
+
+    fn signers() -> Vec<InMemoryValidatorSigner> {
+        ["test0", "test1", "test2"]
+            .iter()
+            .map(|account_id| {
+                InMemoryValidatorSigner::from_seed(
+                    account_id.parse().unwrap(),
+                    KeyType::ED25519,
+                    account_id,
+                )
+            })
+            .collect()
+    }
+
+    fn block_producers(signers: &[InMemoryValidatorSigner]) -> Vec<ValidatorStakeView> {
+        signers
+            .iter()
+            .map(|signer| {
+                ValidatorStake::new_v1(signer.validator_id().clone(), signer.public_key(), 100)
+                    .into()
+            })
+            .collect()
+    }
+
+    fn inner_lite(
+        height: u64,
+        epoch_id: CryptoHash,
+        next_epoch_id: CryptoHash,
+    ) -> BlockHeaderInnerLiteView {
+        BlockHeaderInnerLiteView {
+            height,
+            epoch_id,
+            next_epoch_id,
+            prev_state_root: CryptoHash::default(),
+            outcome_root: CryptoHash::default(),
+            timestamp: 0,
+            timestamp_nanosec: 0,
+            next_bp_hash: CryptoHash::default(),
+            block_merkle_root: CryptoHash::default(),
+        }
+    }
+
+    /// Head in epoch `epoch0` carrying all the signers as the block producers of `epoch1`.
+    fn head() -> LightClientBlockView {
+        LightClientBlockView {
+            prev_block_hash: CryptoHash::default(),
+            next_block_inner_hash: CryptoHash::default(),
+            inner_lite: inner_lite(5, hash(b"epoch0"), hash(b"epoch1")),
+            inner_rest_hash: CryptoHash::default(),
+            next_bps: Some(block_producers(&signers())),
+            approvals_after_next: vec![],
+        }
+    }
+
+    /// Block in `epoch1` approved by the first `num_approvals` signers.
+    fn light_client_block(
+        signers: &[InMemoryValidatorSigner],
+        num_approvals: usize,
+        next_bps: Vec<ValidatorStakeView>,
+    ) -> LightClientBlockView {
+        let mut inner_lite = inner_lite(10, hash(b"epoch1"), hash(b"epoch2"));
+        let stakes: Vec<_> = next_bps.iter().map(|bp| bp.clone().into_validator_stake()).collect();
+        inner_lite.next_bp_hash = hash(&stakes.try_to_vec().unwrap());
+        let mut block_view = LightClientBlockView {
+            prev_block_hash: hash(b"prev"),
+            next_block_inner_hash: hash(b"next"),
+            inner_lite,
+            inner_rest_hash: hash(b"rest"),
+            next_bps: Some(next_bps),
+            approvals_after_next: vec![],
+        };
+        let next_block_hash =
+            combine_hash(&block_view.next_block_inner_hash, &light_client_block_hash(&block_view));
+        let inner = ApprovalInner::Endorsement(next_block_hash);
+        block_view.approvals_after_next = signers
+            .iter()
+            .enumerate()
+            .map(|(i, signer)| {
+                if i < num_approvals {
+                    Some(signer.sign_approval(&inner, 12).unwrap())
+                } else {
+                    None
+                }
+            })
+            .collect();
+        block_view
+    }
+
+    #[test]
+    fn test_update_head() {
+        let signers = signers();
+        let mut light_client = LightClient::new(head(), vec![]);
+
+        let block_view = light_client_block(&signers, 3, block_producers(&signers[1..]));
+        light_client.update_head(block_view.clone()).unwrap();
+        assert_eq!(light_client.head().inner_lite.height, 10);
+        assert_eq!(
+            light_client.epoch_block_producers.get(&hash(b"epoch2")),
+            Some(&block_producers(&signers[1..]))
+        );
+        assert!(light_client.epoch_block_producers.get(&hash(b"epoch0")).is_none());
+
+        assert_eq!(
+            light_client.update_head(block_view),
+            Err(LightClientError::NotNewerThanHead { height: 10, head_height: 10 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_light_client_blocks() {
+        let signers = signers();
+        let head = head();
+        let bps = head.next_bps.clone().unwrap();
+        let head = LightClientBlockLiteView {
+            prev_block_hash: head.prev_block_hash,
+            inner_rest_hash: head.inner_rest_hash,
+            inner_lite: head.inner_lite,
+        };
+        let validate = |block_view: &LightClientBlockView| {
+            validate_light_client_block(&head, &bps, block_view)
+        };
+
+        validate(&light_client_block(&signers, 3, bps.clone())).unwrap();
+
+        // Exactly 2/3 of the stake is not enough.
+        assert_eq!(
+            validate(&light_client_block(&signers, 2, bps.clone())),
+            Err(LightClientError::NotEnoughApprovals { approved_stake: 200, total_stake: 300 })
+        );
+
+        let mut block_view = light_client_block(&signers, 3, bps.clone());
+        block_view.approvals_after_next.swap(0, 1);
+        assert_eq!(
+            validate(&block_view),
+            Err(LightClientError::InvalidApprovalSignature {
+                account_id: signers[0].validator_id().clone()
+            })
+        );
+
+        let mut block_view = light_client_block(&signers, 3, bps.clone());
+        block_view.next_bps = Some(block_producers(&signers[..1]));
+        assert_eq!(validate(&block_view), Err(LightClientError::NextBlockProducersMismatch));
+
+        let mut block_view = light_client_block(&signers, 3, bps.clone());
+        block_view.next_bps = None;
+        assert_eq!(validate(&block_view), Err(LightClientError::MissingNextBlockProducers));
+
+        let mut block_view = light_client_block(&signers, 3, bps.clone());
+        block_view.inner_lite.epoch_id = hash(b"epoch2");
+        assert_eq!(
+            validate(&block_view),
+            Err(LightClientError::UnknownEpoch { epoch_id: hash(b"epoch2") })
+        );
+    }
+
+    #[test]
+    fn test_verify_execution_outcome() {
+        let outcome = |id: &[u8]| ExecutionOutcomeWithIdView {
+            proof: vec![],
+            block_hash: CryptoHash::default(),
+            id: hash(id),
+            outcome: ExecutionOutcomeView {
+                logs: vec!["log".to_string()],
+                receipt_ids: vec![],
+                gas_burnt: 100,
+                tokens_burnt: 1000,
+                executor_id: "test0".parse().unwrap(),
+                status: ExecutionStatusView::SuccessValue(String::new()),
+                metadata: Default::default(),
+            },
+        };
+        let outcomes = vec![outcome(b"a"), outcome(b"b"), outcome(b"c")];
+        let outcome_hashes: Vec<_> = outcomes
+            .iter()
+            .map(|outcome| {
+                let mut hashes = vec![outcome.id];
+                hashes.extend(execution_outcome_hashes(&outcome.outcome).unwrap());
+                hashes
+            })
+            .collect();
+        let (chunk_outcome_root, outcome_proofs) = merklize(&outcome_hashes);
+        let (outcome_root, outcome_root_proofs) =
+            merklize(&[hash(b"other chunk"), chunk_outcome_root]);
+
+        let mut inner_lite = inner_lite(10, hash(b"epoch1"), hash(b"epoch2"));
+        inner_lite.outcome_root = outcome_root;
+        let block_header_lite = LightClientBlockLiteView {
+            prev_block_hash: hash(b"prev"),
+            inner_rest_hash: hash(b"rest"),
+            inner_lite,
+        };
+        // The leaves of the block merkle tree are the block hashes themselves.
+        let block_hash = lite_block_hash(&block_header_lite);
+        let block_merkle_root = combine_hash(&hash(b"other block"), &block_hash);
+        let block_proof =
+            vec![MerklePathItem { hash: hash(b"other block"), direction: Direction::Left }];
+
+        let mut outcome_proof = outcomes[1].clone();
+        outcome_proof.proof = outcome_proofs[1].clone();
+        outcome_proof.block_hash = block_hash;
+        let verify = |outcome_proof: &ExecutionOutcomeWithIdView| {
+            verify_execution_outcome(
+                &block_merkle_root,
+                outcome_proof,
+                &outcome_root_proofs[1],
+                &block_header_lite,
+                &block_proof,
+            )
+        };
+        verify(&outcome_proof).unwrap();
+
+        let mut tampered = outcome_proof.clone();
+        tampered.outcome.gas_burnt += 1;
+        assert_eq!(verify(&tampered), Err(LightClientError::InvalidOutcomeProof));
+        let mut tampered = outcome_proof.clone();
+        tampered.block_hash = hash(b"other block");
+        assert_eq!(
+            verify(&tampered),
+            Err(LightClientError::BlockHashMismatch {
+                expected: hash(b"other block"),
+                actual: block_hash
+            })
+        );
+        assert_eq!(
+            verify_execution_outcome(
+                &hash(b"root"),
+                &outcome_proof,
+                &outcome_root_proofs[1],
+                &block_header_lite,
+                &block_proof,
+            ),
+            Err(LightClientError::InvalidBlockProof { block_hash })
+        );
+    }
+
+    #[test]
+    fn test_verify_state_proof() {
+        let tries = create_tries();
+        let account_id: AccountId = "test0".parse().unwrap();
+        let changes = [b"a", b"b", b"c"]
+            .iter()
+            .map(|key| {
+                let trie_key =
+                    TrieKey::ContractData { account_id: account_id.clone(), key: key.to_vec() };
+                (trie_key.to_vec(), Some(b"value".to_vec()))
+            })
+            .collect();
+        let root =
+            test_populate_trie(&tries, &CryptoHash::default(), ShardUId::single_shard(), changes);
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard()).recording_reads();
+        let trie_key = TrieKey::ContractData { account_id: account_id.clone(), key: b"b".to_vec() };
+        trie.get(&root, &trie_key.to_vec()).unwrap();
+        let proof = trie.recorded_storage().unwrap().nodes.0;
+
+        assert_eq!(
+            verify_contract_data_proof(&root, proof.clone(), &account_id, b"b"),
+            Ok(Some(b"value".to_vec()))
+        );
+        assert!(verify_contract_data_proof(&root, proof[1..].to_vec(), &account_id, b"b").is_err());
+        assert!(verify_contract_data_proof(&hash(b"root"), proof, &account_id, b"b").is_err());
+    }
+}
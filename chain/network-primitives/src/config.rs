@@ -1,6 +1,7 @@
-use crate::network_protocol::PeerInfo;
+use crate::network_protocol::{PeerClientVersion, PeerInfo};
 use crate::types::ROUTED_MESSAGE_TTL;
 use near_crypto::{KeyType, PublicKey, SecretKey};
+use near_primitives::network::PeerId;
 use near_primitives::types::AccountId;
 use std::collections::{HashMap, HashSet};
 use std::net::{AddrParseError, IpAddr, SocketAddr};
@@ -15,6 +16,10 @@ pub struct NetworkConfig {
     pub account_id: Option<AccountId>,
     pub addr: Option<SocketAddr>,
     pub boot_nodes: Vec<PeerInfo>,
+    /// Domain names whose TXT records list more boot nodes as `peer_id@host:port`.
+    pub dns_seeds: Vec<String>,
+    /// How often the DNS seeds are resolved again, to follow their rotation.
+    pub dns_seeds_resolve_period: Duration,
     pub handshake_timeout: Duration,
     pub reconnect_delay: Duration,
     pub bootstrap_peers_period: Duration,
@@ -66,6 +71,33 @@ pub struct NetworkConfig {
     pub outbound_disabled: bool,
     /// Not clear old data, set `true` for archive nodes.
     pub archive: bool,
+    /// Proxy nodes of a private validator.  If not empty, the node connects only to these peers,
+    /// accepts connections only from them and doesn't advertise its own address, so all of its
+    /// traffic, including approvals and chunks, is routed through the proxies.
+    pub proxy_nodes: Vec<PeerInfo>,
+    /// Private validators this node is a proxy for.  Connections with them are accepted even
+    /// though they don't advertise an address or the node is at `max_num_peers`, and are never
+    /// dropped to make room for other peers.
+    pub private_peers: HashSet<PeerId>,
+    /// Peers which can take the slots reserved for validators and aren't subject to the limits
+    /// on inbound connections from the same IP or subnet.
+    pub whitelisted_peers: HashSet<PeerId>,
+    /// Number of the `max_num_peers` slots which only validators which announced their accounts
+    /// and whitelisted peers can take.
+    pub reserved_peer_slots: u32,
+    /// Maximum number of inbound connections from the same IP, unlimited if not set.
+    pub max_inbound_peers_per_ip: Option<u32>,
+    /// Maximum number of inbound connections from the same /24 IPv4 or /64 IPv6 subnet,
+    /// unlimited if not set.
+    pub max_inbound_peers_per_subnet: Option<u32>,
+    /// Whether the least useful peer is dropped when there are too many connections, rather than
+    /// a random one.  Validators and whitelisted peers are never dropped.
+    pub evict_lowest_scoring_peer: bool,
+    /// Whether the port the node listens on is mapped in the router with UPnP or NAT-PMP, so
+    /// that the node is reachable from outside without manual port forwarding.
+    pub port_mapping: bool,
+    /// Version of the software this node runs, told to the peers after the handshake.
+    pub client_version: PeerClientVersion,
 }
 
 impl NetworkConfig {
@@ -79,6 +111,8 @@ impl NetworkConfig {
             account_id: Some(seed.parse().unwrap()),
             addr: Some(format!("0.0.0.0:{}", port).parse().unwrap()),
             boot_nodes: vec![],
+            dns_seeds: vec![],
+            dns_seeds_resolve_period: Duration::from_secs(60 * 60),
             handshake_timeout: Duration::from_secs(60),
             reconnect_delay: Duration::from_secs(60),
             bootstrap_peers_period: Duration::from_millis(100),
@@ -101,6 +135,15 @@ impl NetworkConfig {
             blacklist: HashMap::new(),
             outbound_disabled: false,
             archive: false,
+            proxy_nodes: vec![],
+            private_peers: HashSet::new(),
+            whitelisted_peers: HashSet::new(),
+            reserved_peer_slots: 0,
+            max_inbound_peers_per_ip: None,
+            max_inbound_peers_per_subnet: None,
+            evict_lowest_scoring_peer: true,
+            port_mapping: false,
+            client_version: PeerClientVersion::default(),
         }
     }
 
@@ -120,6 +163,13 @@ impl NetworkConfig {
             );
         }
 
+        if !(self.reserved_peer_slots <= self.max_num_peers - self.ideal_connections_hi) {
+            anyhow::bail!(
+                "reserved_peer_slots({}) must be at most the difference between max_num_peers({}) and ideal_connections_hi({}), so that the other peers can reach the ideal number of connections.",
+                self.reserved_peer_slots, self.max_num_peers, self.ideal_connections_hi
+            );
+        }
+
         if self.outbound_disabled {
             anyhow::bail!("Outbound connections are disabled.");
         }
@@ -132,6 +182,10 @@ impl NetworkConfig {
             );
         }
 
+        if let Some(proxy) = self.proxy_nodes.iter().find(|proxy| proxy.addr.is_none()) {
+            anyhow::bail!("Proxy node {} has no address.", proxy.id);
+        }
+
         if UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE * 2 > self.peer_recent_time_window {
             anyhow::bail!(
                 "Very short peer_recent_time_window({}). it should be at least twice update_interval_last_time_received_message({}).",
@@ -142,6 +196,9 @@ impl NetworkConfig {
     }
 }
 
+/// Prefix of the entries of `boot_nodes` in the config which are DNS seeds rather than nodes.
+pub const DNS_SEED_PREFIX: &str = "dns:";
+
 #[derive(Debug, Clone)]
 pub enum BlockedPorts {
     All,
@@ -213,7 +270,7 @@ pub const UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE: Duration = Duration::from_
 
 #[cfg(test)]
 mod test {
-    use crate::types::{NetworkConfig, UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE};
+    use crate::types::{NetworkConfig, PeerInfo, UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE};
 
     #[test]
     fn test_network_config() {
@@ -239,5 +296,15 @@ mod test {
         nc.peer_recent_time_window = UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE;
         let res = nc.verify();
         assert!(res.is_err(), "{:?}", res);
+
+        let mut nc = NetworkConfig::from_seed("123", 213);
+        nc.proxy_nodes = vec![PeerInfo::random()];
+        let res = nc.verify();
+        assert!(res.is_err(), "{:?}", res);
+
+        let mut nc = NetworkConfig::from_seed("123", 213);
+        nc.reserved_peer_slots = nc.max_num_peers - nc.ideal_connections_hi + 1;
+        let res = nc.verify();
+        assert!(res.is_err(), "{:?}", res);
     }
 }
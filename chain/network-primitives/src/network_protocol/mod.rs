@@ -85,11 +85,11 @@ impl FromStr for PeerInfo {
                 account_id = None;
             } else {
                 addr = None;
-                account_id = Some(chunks[1].parse().unwrap());
+                account_id = Some(chunks[1].parse()?);
             }
         } else if chunks.len() == 3 {
             addr = Some(chunks[1].parse::<SocketAddr>()?);
-            account_id = Some(chunks[2].parse().unwrap());
+            account_id = Some(chunks[2].parse()?);
         } else {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -147,6 +147,24 @@ impl From<PeerChainInfo> for PeerChainInfoV2 {
     }
 }
 
+/// State a peer can serve to nodes doing state sync: the shards of which it has the state for the
+/// given sync hash.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Eq, PartialEq)]
+pub struct ServableStateInfo {
+    /// Hash of the first block of the epoch to which the state is synced.
+    pub sync_hash: CryptoHash,
+    pub shard_ids: Vec<ShardId>,
+}
+
+/// Version of the software a peer runs, which it sends after the handshake.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PeerClientVersion {
+    pub version: String,
+    pub build: String,
+}
+
 /// Test code that someone become part of our protocol?
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug)]
@@ -205,6 +223,9 @@ pub enum RoutedMessageBody {
     VersionedPartialEncodedChunk(PartialEncodedChunk),
     VersionedStateResponse(StateResponseInfo),
     PartialEncodedChunkForward(PartialEncodedChunkForwardMsg),
+    /// Approvals sent to the same block producer at once.  Nodes older than it can't decode it,
+    /// so it's only sent with `approval_batching`.
+    BlockApprovals(Vec<Approval>),
 }
 
 impl From<PartialEncodedChunkWithArcReceipts> for RoutedMessageBody {
@@ -229,6 +250,14 @@ impl Debug for RoutedMessageBody {
                 "Approval({}, {}, {:?})",
                 approval.target_height, approval.account_id, approval.inner
             ),
+            RoutedMessageBody::BlockApprovals(approvals) => write!(
+                f,
+                "Approvals({:?})",
+                approvals
+                    .iter()
+                    .map(|approval| (approval.target_height, &approval.account_id))
+                    .collect::<Vec<_>>()
+            ),
             RoutedMessageBody::ForwardTx(tx) => write!(f, "tx {}", tx.get_hash()),
             RoutedMessageBody::TxStatusRequest(account_id, hash) => {
                 write!(f, "TxStatusRequest({}, {})", account_id, hash)
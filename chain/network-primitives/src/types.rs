@@ -31,11 +31,12 @@ use tokio::net::TcpStream;
 /// Exported types, which are part of network protocol.
 pub use crate::network_protocol::{
     PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
-    PeerChainInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, Ping, Pong, RoutedMessage,
-    RoutedMessageBody, StateResponseInfo, StateResponseInfoV1, StateResponseInfoV2,
+    PeerChainInfo, PeerChainInfoV2, PeerClientVersion, PeerIdOrHash, PeerInfo, Ping, Pong,
+    RoutedMessage, RoutedMessageBody, ServableStateInfo, StateResponseInfo, StateResponseInfoV1,
+    StateResponseInfoV2,
 };
 
-pub use crate::config::{blacklist_from_iter, BlockedPorts, NetworkConfig};
+pub use crate::config::{blacklist_from_iter, BlockedPorts, NetworkConfig, DNS_SEED_PREFIX};
 
 pub use crate::network_protocol::edge::{Edge, EdgeState, PartialEdgeInfo, SimpleEdge};
 
@@ -268,6 +269,8 @@ pub enum NetworkAdversarialMessage {
     AdvGetSavedBlocks,
     AdvCheckStorageConsistency,
     AdvSetSyncInfo(u64),
+    /// Delay of every store read in milliseconds and percentage of reads failing.
+    AdvSetStoreFaults(u64, u8),
 }
 
 #[cfg(feature = "sandbox")]
@@ -276,6 +279,10 @@ pub enum NetworkSandboxMessage {
     SandboxPatchState(Vec<near_primitives::state_record::StateRecord>),
     SandboxPatchStateStatus,
     SandboxFastForward(near_primitives::types::BlockHeightDelta),
+    /// Moves the node clock so that the next block gets the given timestamp (in nanoseconds).
+    SandboxSetTimestamp(u64),
+    /// Forces the gas price of new blocks, `None` restores the regular gas price computation.
+    SandboxSetGasPrice(Option<near_primitives::types::Balance>),
 }
 
 #[cfg(feature = "sandbox")]
@@ -316,6 +323,8 @@ pub enum NetworkViewClientMessages {
     /// They are paired with last epoch id known to this announcement, in order to accept only
     /// newer announcements.
     AnnounceAccount(Vec<(AnnounceAccount, Option<EpochId>)>),
+    /// Get the state this node can serve to nodes doing state sync.
+    GetServableState,
 }
 
 #[derive(Debug, actix::MessageResponse)]
@@ -341,6 +350,8 @@ pub enum NetworkViewClientResponses {
     StateResponse(Box<StateResponseInfo>),
     /// Valid announce accounts.
     AnnounceAccount(Vec<AnnounceAccount>),
+    /// State this node can serve to nodes doing state sync.
+    ServableState(Vec<ServableStateInfo>),
     /// A response to a request for a light client block during Epoch Sync
     EpochSyncResponse(Box<EpochSyncResponse>),
     /// A response to a request for headers and proofs during Epoch Sync
@@ -369,6 +380,10 @@ pub struct PeerStatsResult {
     pub is_abusive: bool,
     /// Counts of incoming/outgoing messages from given peer.
     pub message_counts: (usize, usize),
+    /// State the peer advertised it can serve to nodes doing state sync.
+    pub servable_state: Vec<ServableStateInfo>,
+    /// Version of the software the peer runs, once it told it.
+    pub client_version: Option<PeerClientVersion>,
 }
 
 #[cfg(test)]
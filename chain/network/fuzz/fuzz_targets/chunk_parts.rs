@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use near_network_fuzz::check_roundtrip;
+use near_network_primitives::types::{
+    PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
+};
+use near_primitives::sharding::PartialEncodedChunk;
+
+// The first byte selects the type of the message, so that one corpus covers all of them.
+fuzz_target!(|bytes: &[u8]| {
+    if let Some((kind, bytes)) = bytes.split_first() {
+        match kind % 4 {
+            0 => check_roundtrip::<PartialEncodedChunk>(bytes),
+            1 => check_roundtrip::<PartialEncodedChunkRequestMsg>(bytes),
+            2 => check_roundtrip::<PartialEncodedChunkResponseMsg>(bytes),
+            _ => check_roundtrip::<PartialEncodedChunkForwardMsg>(bytes),
+        }
+    }
+});
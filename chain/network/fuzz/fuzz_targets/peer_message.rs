@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use near_network::types::PeerMessage;
+use near_network_fuzz::check_roundtrip;
+
+fuzz_target!(|bytes: &[u8]| {
+    check_roundtrip::<PeerMessage>(bytes);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use near_network_fuzz::check_roundtrip;
+use near_network_primitives::types::RoutedMessage;
+
+fuzz_target!(|bytes: &[u8]| {
+    check_roundtrip::<RoutedMessage>(bytes);
+});
@@ -0,0 +1,186 @@
+//! Writes the seed corpus of the fuzz targets: valid encodings of the messages, which the fuzzer
+//! mutates into the parts of the decoders it wouldn't reach from random bytes.
+//!
+//! Usage: `cargo run --bin generate_corpus [DIR]`, where `DIR` defaults to `corpus`, the directory
+//! `cargo fuzz` reads the corpora from.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use borsh::BorshSerialize;
+use near_crypto::{InMemorySigner, KeyType, SecretKey, Signature};
+use near_network::types::{Handshake, PeerCapabilities, PeerMessage};
+use near_network_primitives::types::{
+    PartialEdgeInfo, PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg,
+    PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerIdOrHash, PeerInfo, RoutedMessage,
+    RoutedMessageBody,
+};
+use near_primitives::block::{Approval, ApprovalInner};
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::network::PeerId;
+use near_primitives::sharding::{
+    PartialEncodedChunk, PartialEncodedChunkPart, PartialEncodedChunkV2, ShardChunkHeader,
+    ShardChunkHeaderV3,
+};
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::validator_signer::InMemoryValidatorSigner;
+use near_primitives::version::PROTOCOL_VERSION;
+
+fn write_seed(dir: &Path, target: &str, name: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+    let dir = dir.join(target);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), bytes)?;
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| "corpus".to_string()));
+
+    let secret_key = SecretKey::from_seed(KeyType::ED25519, "fuzz");
+    let peer_id = PeerId::new(secret_key.public_key());
+    let block_hash = hash(b"block");
+    let signer = InMemorySigner::from_seed("test0".parse()?, KeyType::ED25519, "test0");
+    let validator_signer =
+        InMemoryValidatorSigner::from_seed("test0".parse()?, KeyType::ED25519, "test0");
+
+    let approval = Approval::new(block_hash, 1, 2, &validator_signer)?;
+    let routed = RoutedMessage {
+        target: PeerIdOrHash::PeerId(peer_id.clone()),
+        author: peer_id.clone(),
+        signature: secret_key.sign(block_hash.as_ref()),
+        ttl: 100,
+        body: RoutedMessageBody::BlockApproval(approval),
+    };
+    let routed_hash = RoutedMessage { target: PeerIdOrHash::Hash(block_hash), ..routed.clone() };
+
+    let part = PartialEncodedChunkPart {
+        part_ord: 0,
+        part: vec![1, 2, 3].into_boxed_slice(),
+        merkle_proof: vec![],
+    };
+    let header = ShardChunkHeader::V3(ShardChunkHeaderV3::new(
+        block_hash,
+        CryptoHash::default(),
+        CryptoHash::default(),
+        CryptoHash::default(),
+        3,
+        2,
+        0,
+        0,
+        1_000_000,
+        0,
+        CryptoHash::default(),
+        CryptoHash::default(),
+        vec![],
+        &validator_signer,
+    )?);
+    let chunk_hash = header.chunk_hash();
+    let partial_chunk = PartialEncodedChunk::V2(PartialEncodedChunkV2 {
+        header,
+        parts: vec![part.clone()],
+        receipts: vec![],
+    });
+    let request = PartialEncodedChunkRequestMsg {
+        chunk_hash: chunk_hash.clone(),
+        part_ords: vec![0, 1],
+        tracking_shards: [0].into_iter().collect(),
+    };
+    let response = PartialEncodedChunkResponseMsg {
+        chunk_hash: chunk_hash.clone(),
+        parts: vec![part.clone()],
+        receipts: vec![],
+    };
+    let forward = PartialEncodedChunkForwardMsg {
+        chunk_hash,
+        inner_header_hash: CryptoHash::default(),
+        merkle_root: CryptoHash::default(),
+        signature: Signature::empty(KeyType::ED25519),
+        prev_block_hash: block_hash,
+        height_created: 2,
+        shard_id: 0,
+        parts: vec![part],
+    };
+
+    let handshake = |capabilities| {
+        Handshake::new(
+            PROTOCOL_VERSION,
+            peer_id.clone(),
+            peer_id.clone(),
+            Some(24567),
+            PeerChainInfoV2 {
+                genesis_id: Default::default(),
+                height: 0,
+                tracked_shards: vec![0],
+                archival: false,
+            },
+            PartialEdgeInfo::default(),
+            capabilities,
+        )
+    };
+    let peer_messages = vec![
+        ("handshake", PeerMessage::Handshake(handshake(None))),
+        (
+            "handshake_capabilities",
+            PeerMessage::Handshake(handshake(Some(PeerCapabilities::SERVES_STATE_SYNC))),
+        ),
+        ("peers_request", PeerMessage::PeersRequest),
+        (
+            "peers_response",
+            PeerMessage::PeersResponse(vec![PeerInfo {
+                id: peer_id.clone(),
+                addr: Some("127.0.0.1:24567".parse()?),
+                account_id: Some("test0".parse()?),
+            }]),
+        ),
+        ("block_headers_request", PeerMessage::BlockHeadersRequest(vec![block_hash])),
+        ("block_request", PeerMessage::BlockRequest(block_hash)),
+        (
+            "transaction",
+            PeerMessage::Transaction(SignedTransaction::send_money(
+                1,
+                "test0".parse()?,
+                "test1".parse()?,
+                &signer,
+                100,
+                block_hash,
+            )),
+        ),
+        ("routed", PeerMessage::Routed(Box::new(routed.clone()))),
+        (
+            "routed_chunk",
+            PeerMessage::Routed(Box::new(RoutedMessage {
+                body: RoutedMessageBody::VersionedPartialEncodedChunk(partial_chunk.clone()),
+                ..routed.clone()
+            })),
+        ),
+        ("disconnect", PeerMessage::Disconnect),
+    ];
+    for (name, msg) in peer_messages {
+        write_seed(&dir, "peer_message", name, msg.try_to_vec()?)?;
+    }
+
+    write_seed(&dir, "routed_message", "approval", routed.try_to_vec()?)?;
+    write_seed(&dir, "routed_message", "approval_to_hash", routed_hash.try_to_vec()?)?;
+    write_seed(
+        &dir,
+        "routed_message",
+        "chunk_request",
+        RoutedMessage {
+            body: RoutedMessageBody::PartialEncodedChunkRequest(request.clone()),
+            ..routed.clone()
+        }
+        .try_to_vec()?,
+    )?;
+
+    // The first byte selects the type of the message, see `chunk_parts.rs`.
+    let chunk_parts = vec![
+        ("chunk", 0u8, partial_chunk.try_to_vec()?),
+        ("request", 1, request.try_to_vec()?),
+        ("response", 2, response.try_to_vec()?),
+        ("forward", 3, forward.try_to_vec()?),
+    ];
+    for (name, kind, bytes) in chunk_parts {
+        write_seed(&dir, "chunk_parts", name, [vec![kind], bytes].concat())?;
+    }
+    println!("Wrote the seed corpus to {}", dir.display());
+    Ok(())
+}
@@ -0,0 +1,16 @@
+//! Helpers shared by the fuzz targets of the network messages.
+use std::fmt::Debug;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Checks that a message decoded from `bytes`, if any, decodes to the same message once encoded
+/// again.  Decoding must never panic, whatever the bytes.
+pub fn check_roundtrip<T>(bytes: &[u8])
+where
+    T: BorshSerialize + BorshDeserialize + PartialEq + Debug,
+{
+    if let Ok(msg) = T::try_from_slice(bytes) {
+        let encoded = msg.try_to_vec().unwrap();
+        assert_eq!(T::try_from_slice(&encoded).unwrap(), msg);
+    }
+}
@@ -3,9 +3,11 @@
 /// WARNING WARNING WARNING
 /// WARNING WARNING WARNING
 /// We need to maintain backwards compatibility, all changes to this file needs to be reviews.
+use crate::stats::metrics;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_network_primitives::types::{
-    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerInfo, RoutedMessage, RoutedMessageBody,
+    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerClientVersion, PeerInfo, RoutedMessage,
+    RoutedMessageBody, ServableStateInfo,
 };
 use near_primitives::block::{Block, BlockHeader, GenesisId};
 use near_primitives::challenge::Challenge;
@@ -20,8 +22,38 @@ use std::{fmt, io};
 
 const ERROR_UNEXPECTED_LENGTH_OF_INPUT: &str = "Unexpected length of input";
 
+/// Features supported by a peer, advertised in its handshake so that requests are only routed
+/// to the peers which can serve them.  The bits unknown to this node are kept but otherwise
+/// ignored, so that new capabilities can be added without bumping the protocol version.
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
-#[derive(BorshSerialize, PartialEq, Eq, Clone, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct PeerCapabilities(u64);
+
+impl PeerCapabilities {
+    /// Serves state parts to the nodes doing state sync.
+    pub const SERVES_STATE_SYNC: PeerCapabilities = PeerCapabilities(1 << 0);
+    /// Keeps the whole history of the chain.
+    pub const ARCHIVAL: PeerCapabilities = PeerCapabilities(1 << 1);
+    /// Runs a validator, which takes direct connections from the other validators.
+    pub const TIER1: PeerCapabilities = PeerCapabilities(1 << 2);
+    /// Accepts compressed messages.  Not advertised yet, as no message is compressed.
+    pub const COMPRESSION: PeerCapabilities = PeerCapabilities(1 << 3);
+    /// Decodes `PeerMessage::ServableState`, which older nodes reject as invalid data.
+    pub const SERVABLE_STATE: PeerCapabilities = PeerCapabilities(1 << 4);
+    /// Decodes `PeerMessage::ClientVersion`, which older nodes reject as invalid data.
+    pub const CLIENT_VERSION: PeerCapabilities = PeerCapabilities(1 << 5);
+
+    pub fn contains(self, other: PeerCapabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: PeerCapabilities) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(PartialEq, Eq, Clone, Debug)]
 /// Structure representing handshake between peers.
 /// This replaces deprecated handshake `HandshakeV2`.
 pub struct Handshake {
@@ -39,6 +71,10 @@ pub struct Handshake {
     pub(crate) sender_chain_info: PeerChainInfoV2,
     /// Represents new `edge`. Contains only `none` and `Signature` from the sender.
     pub(crate) partial_edge_info: PartialEdgeInfo,
+    /// Sender's capabilities, unless it talks a protocol version which predates them.
+    /// They're encoded after the other fields only if present, so that older peers can still
+    /// decode the handshake.
+    pub(crate) capabilities: Option<PeerCapabilities>,
 }
 
 /// Struct describing the layout for Handshake.
@@ -62,13 +98,14 @@ struct HandshakeAutoDes {
 }
 
 impl Handshake {
-    pub(crate) fn new(
+    pub fn new(
         version: ProtocolVersion,
         peer_id: PeerId,
         target_peer_id: PeerId,
         listen_port: Option<u16>,
         chain_info: PeerChainInfoV2,
         partial_edge_info: PartialEdgeInfo,
+        capabilities: Option<PeerCapabilities>,
     ) -> Self {
         Handshake {
             protocol_version: version,
@@ -78,10 +115,27 @@ impl Handshake {
             sender_listen_port: listen_port,
             sender_chain_info: chain_info,
             partial_edge_info,
+            capabilities,
         }
     }
 }
 
+impl BorshSerialize for Handshake {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.protocol_version.serialize(writer)?;
+        self.oldest_supported_version.serialize(writer)?;
+        self.sender_peer_id.serialize(writer)?;
+        self.target_peer_id.serialize(writer)?;
+        self.sender_listen_port.serialize(writer)?;
+        self.sender_chain_info.serialize(writer)?;
+        self.partial_edge_info.serialize(writer)?;
+        if let Some(capabilities) = &self.capabilities {
+            capabilities.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
 // Use custom deserializer for HandshakeV2. Try to read version of the other peer from the header.
 // If the version is supported then fallback to standard deserializer.
 impl BorshDeserialize for Handshake {
@@ -98,7 +152,15 @@ impl BorshDeserialize for Handshake {
 
         if PEER_MIN_ALLOWED_PROTOCOL_VERSION <= version && version <= PROTOCOL_VERSION {
             // If we support this version, then try to deserialize with custom deserializer
-            <HandshakeAutoDes as BorshDeserialize>::deserialize(buf).map(Into::into)
+            let mut handshake: Handshake =
+                <HandshakeAutoDes as BorshDeserialize>::deserialize(buf)?.into();
+            if !buf.is_empty() {
+                handshake.capabilities = Some(PeerCapabilities::deserialize(buf)?);
+            }
+            // The handshake is the last field of its message, so whatever is left are the fields
+            // added by newer versions, which are skipped.
+            *buf = &[];
+            Ok(handshake)
         } else {
             Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -121,6 +183,7 @@ impl From<HandshakeAutoDes> for Handshake {
             sender_listen_port: handshake.sender_listen_port,
             sender_chain_info: handshake.sender_chain_info,
             partial_edge_info: handshake.partial_edge_info,
+            capabilities: None,
         }
     }
 }
@@ -215,6 +278,13 @@ pub enum PeerMessage {
 
     #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
     RoutingTableSyncV2(RoutingSyncV2),
+    /// Keeps the index of the following messages the same without the feature.
+    #[cfg(not(feature = "protocol_feature_routing_exchange_algorithm"))]
+    _RoutingTableSyncV2,
+    /// Shards and sync hashes for which the peer can serve state to nodes doing state sync.
+    ServableState(Vec<ServableStateInfo>),
+    /// Version of the software the peer runs, sent once after the handshake.
+    ClientVersion(PeerClientVersion),
 }
 #[cfg(target_arch = "x86_64")] // Non-x86_64 doesn't match this requirement yet but it's not bad as it's not production-ready
 const _: () = assert!(std::mem::size_of::<PeerMessage>() <= 1144, "PeerMessage > 1144 bytes");
@@ -264,6 +334,16 @@ impl fmt::Display for PeerMessage {
 }
 
 impl PeerMessage {
+    /// Decodes a message received from a peer.  A panic while decoding, e.g. in a hand-written
+    /// `BorshDeserialize` implementation, is counted and reported as invalid data instead of
+    /// taking down the node.
+    pub fn decode(bytes: &[u8]) -> io::Result<PeerMessage> {
+        std::panic::catch_unwind(|| PeerMessage::try_from_slice(bytes)).unwrap_or_else(|_| {
+            metrics::PEER_MESSAGE_DECODE_PANICS_TOTAL.inc();
+            Err(io::Error::new(io::ErrorKind::InvalidData, "decoding the message panicked"))
+        })
+    }
+
     pub(crate) fn msg_variant(&self) -> &str {
         match self {
             PeerMessage::Routed(routed_message) => {
@@ -284,6 +364,7 @@ impl PeerMessage {
             PeerMessage::Routed(r) => matches!(
                 r.body,
                 RoutedMessageBody::BlockApproval(_)
+                    | RoutedMessageBody::BlockApprovals(_)
                     | RoutedMessageBody::ForwardTx(_)
                     | RoutedMessageBody::PartialEncodedChunk(_)
                     | RoutedMessageBody::PartialEncodedChunkForward(_)
@@ -107,8 +107,8 @@ impl Decoder for Codec {
 #[cfg(test)]
 mod test {
     use crate::peer::codec::{Codec, NETWORK_MESSAGE_MAX_SIZE_BYTES};
-    use crate::types::{Handshake, PeerMessage, RoutingTableUpdate};
-    use borsh::{BorshDeserialize, BorshSerialize};
+    use crate::types::{Handshake, PeerCapabilities, PeerMessage, RoutingTableUpdate};
+    use borsh::BorshSerialize;
     use bytes::{BufMut, BytesMut};
     use near_crypto::{KeyType, SecretKey};
     use near_network_primitives::types::{
@@ -127,7 +127,13 @@ mod test {
         let mut buffer = BytesMut::new();
         codec.encode(msg.try_to_vec().unwrap(), &mut buffer).unwrap();
         let decoded = codec.decode(&mut buffer).unwrap().unwrap().unwrap();
-        assert_eq!(PeerMessage::try_from_slice(&decoded).unwrap(), msg);
+        assert_eq!(PeerMessage::decode(&decoded).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_decode_invalid_peer_message() {
+        assert!(PeerMessage::decode(&[]).is_err());
+        assert!(PeerMessage::decode(&[255, 0, 0, 0]).is_err());
     }
 
     #[test]
@@ -174,11 +180,65 @@ mod test {
                 archival: false,
             },
             partial_edge_info: PartialEdgeInfo::default(),
+            capabilities: None,
         };
         let msg = PeerMessage::Handshake(fake_handshake);
         test_codec(msg);
     }
 
+    #[test]
+    fn test_peer_message_handshake_capabilities() {
+        let peer_info = PeerInfo::random();
+        let mut capabilities = PeerCapabilities::default();
+        capabilities.insert(PeerCapabilities::ARCHIVAL);
+        capabilities.insert(PeerCapabilities::TIER1);
+        let handshake = Handshake::new(
+            PROTOCOL_VERSION,
+            peer_info.id.clone(),
+            peer_info.id,
+            Some(24567),
+            PeerChainInfoV2 {
+                genesis_id: Default::default(),
+                height: 0,
+                tracked_shards: vec![0],
+                archival: true,
+            },
+            PartialEdgeInfo::default(),
+            Some(capabilities),
+        );
+        let msg = PeerMessage::Handshake(handshake.clone());
+        test_codec(msg.clone());
+        assert!(capabilities.contains(PeerCapabilities::ARCHIVAL));
+        assert!(!capabilities.contains(PeerCapabilities::SERVES_STATE_SYNC));
+
+        // Unknown capabilities and the fields added by newer versions are skipped.
+        let mut bytes = msg.try_to_vec().unwrap();
+        let capabilities_pos = bytes.len() - 8;
+        bytes[capabilities_pos + 7] = 0x80;
+        bytes.extend_from_slice(&[1, 2, 3]);
+        match PeerMessage::decode(&bytes).unwrap() {
+            PeerMessage::Handshake(decoded) => {
+                let decoded_capabilities = decoded.capabilities.unwrap();
+                assert!(decoded_capabilities.contains(capabilities));
+                assert_ne!(decoded_capabilities, capabilities);
+                assert_eq!(
+                    Handshake { capabilities: handshake.capabilities, ..decoded },
+                    handshake
+                );
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+
+        // The handshakes of the older peers have no capabilities, while cut off ones are invalid.
+        bytes.truncate(capabilities_pos);
+        match PeerMessage::decode(&bytes).unwrap() {
+            PeerMessage::Handshake(decoded) => assert_eq!(decoded.capabilities, None),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+        bytes.push(0);
+        assert!(PeerMessage::decode(&bytes).is_err());
+    }
+
     #[test]
     fn test_peer_message_info_gossip() {
         let peer_info1 = PeerInfo::random();
@@ -7,32 +7,34 @@ use crate::private_actix::{
 use crate::stats::metrics::{self, NetworkMetrics};
 use crate::types::{
     Handshake, HandshakeFailureReason, NetworkClientMessages, NetworkClientResponses,
-    NetworkRequests, NetworkResponses, PeerManagerMessageRequest, PeerMessage, PeerRequest,
-    PeerResponse, PeersResponse,
+    NetworkRequests, NetworkResponses, PeerCapabilities, PeerManagerMessageRequest, PeerMessage,
+    PeerRequest, PeerResponse, PeersResponse,
 };
 use crate::PeerManagerActor;
 use actix::{
     Actor, ActorContext, ActorFuture, Addr, Arbiter, AsyncContext, Context, ContextFutureSpawner,
     Handler, Recipient, Running, StreamHandler, WrapFuture,
 };
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshSerialize;
 use lru::LruCache;
 use near_crypto::Signature;
 use near_network_primitives::types::{
-    Ban, NetworkViewClientMessages, NetworkViewClientResponses, PeerChainInfoV2, PeerIdOrHash,
-    PeerInfo, PeerManagerRequest, PeerStatsResult, PeerType, QueryPeerStats, ReasonForBan,
-    RoutedMessage, RoutedMessageBody, RoutedMessageFrom, StateResponseInfo,
-    UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE,
+    Ban, NetworkViewClientMessages, NetworkViewClientResponses, PeerChainInfoV2, PeerClientVersion,
+    PeerIdOrHash, PeerInfo, PeerManagerRequest, PeerStatsResult, PeerType, QueryPeerStats,
+    ReasonForBan, RoutedMessage, RoutedMessageBody, RoutedMessageFrom, ServableStateInfo,
+    StateResponseInfo, UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE,
 };
 use near_network_primitives::types::{Edge, PartialEdgeInfo};
 use near_performance_metrics::framed_write::{FramedWrite, WriteHandler};
 use near_performance_metrics_macros::perf;
 use near_primitives::block::GenesisId;
 use near_primitives::borsh::maybestd::io::Error;
+use near_primitives::checked_feature;
 use near_primitives::logging;
 use near_primitives::network::PeerId;
 use near_primitives::sharding::PartialEncodedChunk;
 use near_primitives::time::Clock;
+use near_primitives::types::ShardId;
 use near_primitives::utils::DisplayOption;
 use near_primitives::version::{
     ProtocolVersion, PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION,
@@ -61,6 +63,12 @@ const MAX_TRANSACTIONS_PER_BLOCK_MESSAGE: usize = 1000;
 const ROUTED_MESSAGE_CACHE_SIZE: usize = 1000;
 /// Duplicated messages will be dropped if routed through the same peer multiple times.
 const DROP_DUPLICATED_MESSAGES_PERIOD: Duration = Duration::from_millis(50);
+/// How often the state this node can serve is checked and advertised to the peer if changed.
+const ADVERTISE_SERVABLE_STATE_PERIOD: Duration = Duration::from_secs(60);
+/// Maximum number of sync hashes of servable state kept per peer.
+const MAX_SERVABLE_STATE_INFOS: usize = 4;
+/// Maximum length of the version and build strings kept per peer.
+const MAX_CLIENT_VERSION_LEN: usize = 64;
 
 pub(crate) struct PeerActor {
     /// This node's id and address (either listening or socket address).
@@ -106,6 +114,19 @@ pub(crate) struct PeerActor {
     routed_message_cache: LruCache<(PeerId, PeerIdOrHash, Signature), Instant>,
     /// A helper data structure for limiting reading
     throttle_controller: ThrottleController,
+    /// Capabilities the peer advertised in its handshake.
+    peer_capabilities: PeerCapabilities,
+    /// State the peer advertised it can serve to nodes doing state sync.
+    servable_state: Vec<ServableStateInfo>,
+    /// State this node last advertised to the peer.
+    advertised_servable_state: Option<Vec<ServableStateInfo>>,
+    /// Version of the software this node runs.
+    my_client_version: PeerClientVersion,
+    /// Version of the software the peer runs, once it told it.
+    client_version: Option<PeerClientVersion>,
+    /// Faults injected by tests, shared with the peer manager.
+    #[cfg(feature = "test_features")]
+    network_faults: Arc<crate::test_utils::NetworkFaults>,
 }
 
 impl Debug for PeerActor {
@@ -131,6 +152,8 @@ impl PeerActor {
         txns_since_last_block: Arc<AtomicUsize>,
         peer_counter: Arc<AtomicUsize>,
         throttle_controller: ThrottleController,
+        my_client_version: PeerClientVersion,
+        #[cfg(feature = "test_features")] network_faults: Arc<crate::test_utils::NetworkFaults>,
     ) -> Self {
         PeerActor {
             my_node_info,
@@ -154,10 +177,28 @@ impl PeerActor {
             peer_counter,
             routed_message_cache: LruCache::new(ROUTED_MESSAGE_CACHE_SIZE),
             throttle_controller,
+            peer_capabilities: PeerCapabilities::default(),
+            servable_state: vec![],
+            advertised_servable_state: None,
+            my_client_version,
+            client_version: None,
+            #[cfg(feature = "test_features")]
+            network_faults,
         }
     }
 
     fn send_message(&mut self, msg: &PeerMessage) {
+        // The handshake isn't affected, so that the connection is established.
+        #[cfg(feature = "test_features")]
+        if self.peer_status == PeerStatus::Ready {
+            if let Some(peer_id) = self.other_peer_id() {
+                if self.network_faults.should_drop(peer_id) {
+                    trace!(target: "network", ?peer_id, msg = %msg.msg_variant(), "Dropping message by injected fault");
+                    return;
+                }
+            }
+        }
+
         // Skip sending block and headers if we received it or header from this peer.
         // Record block requests in tracker.
         match msg {
@@ -206,6 +247,23 @@ impl PeerActor {
         );
     }
 
+    /// Capabilities advertised in the handshake, given the shards the node tracks and whether
+    /// it's archival.
+    fn my_capabilities(&self, tracked_shards: &[ShardId], archival: bool) -> PeerCapabilities {
+        let mut capabilities = PeerCapabilities::SERVABLE_STATE;
+        capabilities.insert(PeerCapabilities::CLIENT_VERSION);
+        if !tracked_shards.is_empty() {
+            capabilities.insert(PeerCapabilities::SERVES_STATE_SYNC);
+        }
+        if archival {
+            capabilities.insert(PeerCapabilities::ARCHIVAL);
+        }
+        if self.my_node_info.account_id.is_some() {
+            capabilities.insert(PeerCapabilities::TIER1);
+        }
+        capabilities
+    }
+
     fn send_handshake(&self, ctx: &mut Context<PeerActor>) {
         if self.other_peer_id().is_none() {
             error!(target: "network", "Sending handshake to an unknown peer");
@@ -222,6 +280,13 @@ impl PeerActor {
                     tracked_shards,
                     archival,
                 }) => {
+                    // Peers which talk older protocol versions fail to decode the capabilities.
+                    let capabilities = checked_feature!(
+                        "protocol_feature_peer_capabilities",
+                        PeerCapabilities,
+                        act.protocol_version
+                    )
+                    .then(|| act.my_capabilities(&tracked_shards, archival));
                     let handshake = match act.protocol_version {
                         39..=PROTOCOL_VERSION => PeerMessage::Handshake(Handshake::new(
                             act.protocol_version,
@@ -230,6 +295,7 @@ impl PeerActor {
                             act.my_node_info.addr_port(),
                             PeerChainInfoV2 { genesis_id, height, tracked_shards, archival },
                             act.partial_edge_info.as_ref().unwrap().clone(),
+                            capabilities,
                         )),
                         _ => {
                             error!(target: "network", "Trying to talk with peer with no supported version: {}", act.protocol_version);
@@ -249,6 +315,38 @@ impl PeerActor {
             .spawn(ctx);
     }
 
+    /// Periodically sends the state this node can serve to the peer, if it changed since it was
+    /// last sent.  Only for the peers advertising `PeerCapabilities::SERVABLE_STATE`.
+    fn advertise_servable_state_trigger(&self, ctx: &mut Context<PeerActor>) {
+        self.view_client_addr
+            .send(NetworkViewClientMessages::GetServableState)
+            .into_actor(self)
+            .then(move |res, act, _ctx| {
+                match res {
+                    Ok(NetworkViewClientResponses::ServableState(servable_state)) => {
+                        if act.advertised_servable_state.as_ref() != Some(&servable_state) {
+                            act.send_message(&PeerMessage::ServableState(servable_state.clone()));
+                            act.advertised_servable_state = Some(servable_state);
+                        }
+                    }
+                    Err(err) => {
+                        error!(target: "network", "Failed sending GetServableState to client: {}", err);
+                    }
+                    _ => {}
+                }
+                actix::fut::ready(())
+            })
+            .spawn(ctx);
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            ADVERTISE_SERVABLE_STATE_PERIOD,
+            move |act, ctx| {
+                act.advertise_servable_state_trigger(ctx);
+            },
+        );
+    }
+
     fn ban_peer(&mut self, ctx: &mut Context<PeerActor>, ban_reason: ReasonForBan) {
         warn!(target: "network", "Banning peer {} for {:?}", self.peer_info, ban_reason);
         self.peer_status = PeerStatus::Banned(ban_reason);
@@ -416,6 +514,9 @@ impl PeerActor {
                     RoutedMessageBody::BlockApproval(approval) => {
                         NetworkClientMessages::BlockApproval(approval, peer_id)
                     }
+                    RoutedMessageBody::BlockApprovals(approvals) => {
+                        NetworkClientMessages::BlockApprovals(approvals, peer_id)
+                    }
                     RoutedMessageBody::ForwardTx(transaction) => {
                         NetworkClientMessages::Transaction {
                             transaction,
@@ -485,7 +586,14 @@ impl PeerActor {
             | PeerMessage::BlockRequest(_)
             | PeerMessage::BlockHeadersRequest(_)
             | PeerMessage::EpochSyncRequest(_)
-            | PeerMessage::EpochSyncFinalizationRequest(_) => {
+            | PeerMessage::EpochSyncFinalizationRequest(_)
+            | PeerMessage::ServableState(_)
+            | PeerMessage::ClientVersion(_) => {
+                error!(target: "network", "Peer receive_client_message received unexpected type: {:?}", msg);
+                return;
+            }
+            #[cfg(not(feature = "protocol_feature_routing_exchange_algorithm"))]
+            PeerMessage::_RoutingTableSyncV2 => {
                 error!(target: "network", "Peer receive_client_message received unexpected type: {:?}", msg);
                 return;
             }
@@ -661,7 +769,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
         if self.should_we_drop_msg_without_decoding(&msg) {
             return;
         }
-        let peer_msg = match PeerMessage::try_from_slice(&msg) {
+        let peer_msg = match PeerMessage::decode(&msg) {
             Ok(peer_msg) => peer_msg,
             Err(err) => {
                 // This may send `HandshakeFailure` to the other peer.
@@ -809,15 +917,18 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                     account_id: None,
                 };
                 self.chain_info = handshake.sender_chain_info.clone();
+                self.peer_capabilities = handshake.capabilities.unwrap_or_default();
                 self.peer_manager_addr
                     .send(ActixMessageWrapper::new_without_size(PeerManagerMessageRequest::RegisterPeer(RegisterPeer {
                         actor: ctx.address(),
                         peer_info: peer_info.clone(),
                         peer_type: self.peer_type,
+                        peer_addr: self.peer_addr,
                         chain_info: handshake.sender_chain_info.clone(),
                         this_edge_info: self.partial_edge_info.clone(),
                         other_edge_info: handshake.partial_edge_info.clone(),
                         peer_protocol_version: self.protocol_version,
+                        peer_capabilities: handshake.capabilities,
                         throttle_controller: self.throttle_controller.clone(),
                     }), Some(self.throttle_controller.clone())))
                     .into_actor(self)
@@ -826,11 +937,17 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                             Ok(RegisterPeerResponse::Accept(edge_info)) => {
                                 act.peer_info = Some(peer_info).into();
                                 act.peer_status = PeerStatus::Ready;
+                                if act.peer_capabilities.contains(PeerCapabilities::SERVABLE_STATE) {
+                                    act.advertise_servable_state_trigger(ctx);
+                                }
                                 // Respond to handshake if it's inbound and connection was consolidated.
                                 if act.peer_type == PeerType::Inbound {
                                     act.partial_edge_info = edge_info;
                                     act.send_handshake(ctx);
                                 }
+                                if act.peer_capabilities.contains(PeerCapabilities::CLIENT_VERSION) {
+                                    act.send_message(&PeerMessage::ClientVersion(act.my_client_version.clone()));
+                                }
                                 actix::fut::ready(())
                             },
                             Ok(RegisterPeerResponse::InvalidNonce(edge)) => {
@@ -972,6 +1089,19 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                     Some(self.throttle_controller.clone()),
                 ));
             }
+            (PeerStatus::Ready, PeerMessage::ServableState(mut servable_state)) => {
+                servable_state.truncate(MAX_SERVABLE_STATE_INFOS);
+                self.servable_state = servable_state;
+            }
+            (PeerStatus::Ready, PeerMessage::ClientVersion(mut client_version)) => {
+                truncate_on_char_boundary(&mut client_version.version, MAX_CLIENT_VERSION_LEN);
+                truncate_on_char_boundary(&mut client_version.build, MAX_CLIENT_VERSION_LEN);
+                self.client_version = Some(client_version);
+            }
+            #[cfg(not(feature = "protocol_feature_routing_exchange_algorithm"))]
+            (PeerStatus::Ready, PeerMessage::_RoutingTableSyncV2) => {
+                debug!(target: "network", "Received RoutingTableSyncV2 from {}, which isn't supported", self.peer_info);
+            }
             (PeerStatus::Ready, PeerMessage::Routed(routed_message)) => {
                 trace!(target: "network", "Received routed message from {} to {:?}.", self.peer_info, routed_message.target);
 
@@ -1052,6 +1182,8 @@ impl Handler<QueryPeerStats> for PeerActor {
             sent_bytes_per_sec: sent.bytes_per_min / 60,
             is_abusive,
             message_counts: (sent.count_per_min, received.count_per_min),
+            servable_state: self.servable_state.clone(),
+            client_version: self.client_version.clone(),
         }
     }
 }
@@ -1084,3 +1216,11 @@ enum PeerStatus {
     /// Banned, should shutdown this peer.
     Banned(ReasonForBan),
 }
+
+/// Truncates the string to at most `max_len` bytes, without splitting a character.
+fn truncate_on_char_boundary(s: &mut String, max_len: usize) {
+    if s.len() > max_len {
+        let len = (0..=max_len).rev().find(|&len| s.is_char_boundary(len)).unwrap_or(0);
+        s.truncate(len);
+    }
+}
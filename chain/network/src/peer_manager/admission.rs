@@ -0,0 +1,91 @@
+use near_primitives::types::BlockHeight;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Prefix lengths of the subnets whose inbound connections are capped by
+/// `max_inbound_peers_per_subnet`.
+const IPV4_SUBNET_PREFIX_LEN: u32 = 24;
+const IPV6_SUBNET_PREFIX_LEN: u32 = 64;
+
+/// The subnet the address belongs to, as its first address.
+pub(crate) fn subnet(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX << (32 - IPV4_SUBNET_PREFIX_LEN);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX << (128 - IPV6_SUBNET_PREFIX_LEN);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    }
+}
+
+/// Which of the limits on the inbound connections from the same IP or subnet another inbound
+/// connection from `ip` would exceed, given the IPs of the inbound peers already connected.
+pub(crate) fn inbound_limit_exceeded(
+    inbound_ips: impl Iterator<Item = IpAddr>,
+    ip: IpAddr,
+    max_per_ip: Option<u32>,
+    max_per_subnet: Option<u32>,
+) -> Option<&'static str> {
+    let (mut same_ip, mut same_subnet) = (0, 0);
+    for inbound_ip in inbound_ips {
+        if inbound_ip == ip {
+            same_ip += 1;
+        }
+        if subnet(inbound_ip) == subnet(ip) {
+            same_subnet += 1;
+        }
+    }
+    if max_per_ip.map_or(false, |max| same_ip >= max) {
+        Some("max_inbound_peers_per_ip")
+    } else if max_per_subnet.map_or(false, |max| same_subnet >= max) {
+        Some("max_inbound_peers_per_subnet")
+    } else {
+        None
+    }
+}
+
+/// How useful a connected peer is, to drop the least useful one when there are too many
+/// connections.  Peers which keep up with the chain score above those which don't, then the
+/// ones which send more data score higher.
+pub(crate) fn peer_score(
+    height: BlockHeight,
+    highest_height: BlockHeight,
+    highest_peer_horizon: u64,
+    received_bytes_per_sec: u64,
+) -> (bool, u64) {
+    (height.saturating_add(highest_peer_horizon) >= highest_height, received_bytes_per_sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inbound_limit_exceeded, peer_score, subnet};
+    use std::net::IpAddr;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_admission() {
+        assert_eq!(subnet(ip("10.1.2.3")), ip("10.1.2.0"));
+        assert_eq!(subnet(ip("2001:db8:1:2:3:4:5:6")), ip("2001:db8:1:2::"));
+
+        let inbound = || vec![ip("10.1.2.3"), ip("10.1.2.3"), ip("10.1.2.4")].into_iter();
+        assert_eq!(inbound_limit_exceeded(inbound(), ip("10.1.2.3"), None, None), None);
+        assert_eq!(
+            inbound_limit_exceeded(inbound(), ip("10.1.2.3"), Some(2), None),
+            Some("max_inbound_peers_per_ip")
+        );
+        assert_eq!(inbound_limit_exceeded(inbound(), ip("10.1.2.4"), Some(2), Some(4)), None);
+        assert_eq!(
+            inbound_limit_exceeded(inbound(), ip("10.1.2.5"), Some(2), Some(3)),
+            Some("max_inbound_peers_per_subnet")
+        );
+        assert_eq!(inbound_limit_exceeded(inbound(), ip("10.1.3.3"), Some(2), Some(3)), None);
+
+        assert!(peer_score(95, 100, 5, 0) > peer_score(94, 100, 5, 1000));
+        assert!(peer_score(100, 100, 5, 10) > peer_score(100, 100, 5, 1));
+    }
+}
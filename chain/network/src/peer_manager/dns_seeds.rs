@@ -0,0 +1,259 @@
+//! Resolves DNS seeds: domain names whose TXT records list boot nodes as `peer_id@host:port`, so
+//! that the operators of the seeds can rotate them without changing the config of every node.
+//!
+//! The TXT records are queried from the first name server of `/etc/resolv.conf`, over UDP and
+//! over TCP if the reply doesn't fit.
+use near_network_primitives::types::PeerInfo;
+use near_primitives::network::PeerId;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tracing::{debug, warn};
+
+/// How long to wait for the reply of the name server.
+const TIMEOUT: Duration = Duration::from_secs(5);
+const DNS_PORT: u16 = 53;
+const TYPE_TXT: u16 = 16;
+const TYPE_OPT: u16 = 41;
+const CLASS_IN: u16 = 1;
+/// Size of the UDP replies the name server is told it can send, so that larger sets of records
+/// don't need TCP.
+const UDP_PAYLOAD_SIZE: u16 = 4096;
+
+/// Resolves the boot nodes listed by the seeds.  The seeds which fail to resolve are skipped.
+pub(crate) async fn resolve_seeds(seeds: Vec<String>) -> Vec<PeerInfo> {
+    let mut peers = vec![];
+    for seed in seeds {
+        let records = match resolve_txt(&seed).await {
+            Ok(records) => records,
+            Err(err) => {
+                warn!(target: "network", %seed, %err, "Failed to resolve DNS seed");
+                continue;
+            }
+        };
+        for record in records {
+            match parse_peer(&record).await {
+                Ok(peer_info) => peers.push(peer_info),
+                Err(err) => {
+                    debug!(target: "network", %seed, %record, %err, "Skipping invalid DNS seed record")
+                }
+            }
+        }
+    }
+    peers
+}
+
+/// Parses a `peer_id@host:port` record, resolving the host.
+async fn parse_peer(record: &str) -> io::Result<PeerInfo> {
+    let (peer_id, host_port) =
+        record.trim().split_once('@').ok_or_else(|| other("expected peer_id@host:port"))?;
+    let peer_id = PeerId::new(peer_id.parse().map_err(|err| other(format!("{}", err)))?);
+    let addr = tokio::net::lookup_host(host_port)
+        .await?
+        .next()
+        .ok_or_else(|| other(format!("can't resolve {}", host_port)))?;
+    Ok(PeerInfo { id: peer_id, addr: Some(addr), account_id: None })
+}
+
+async fn resolve_txt(name: &str) -> io::Result<Vec<String>> {
+    let nameserver = nameserver()?;
+    let id = rand::random();
+    let query = build_query(id, name)?;
+
+    let socket = UdpSocket::bind(match nameserver {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    })
+    .await?;
+    socket.connect(nameserver).await?;
+    socket.send(&query).await?;
+    let mut buf = vec![0u8; UDP_PAYLOAD_SIZE as usize];
+    let len = with_timeout(socket.recv(&mut buf)).await?;
+    let reply = parse_reply(id, &buf[..len])?;
+    if !reply.truncated {
+        return Ok(reply.records);
+    }
+
+    let mut stream = with_timeout(TcpStream::connect(nameserver)).await?;
+    stream.write_all(&(query.len() as u16).to_be_bytes()).await?;
+    stream.write_all(&query).await?;
+    let len = with_timeout(stream.read_u16()).await?;
+    let mut buf = vec![0u8; len as usize];
+    with_timeout(stream.read_exact(&mut buf)).await?;
+    Ok(parse_reply(id, &buf)?.records)
+}
+
+fn nameserver() -> io::Result<SocketAddr> {
+    let resolv_conf = std::fs::read_to_string("/etc/resolv.conf")?;
+    parse_nameserver(&resolv_conf).ok_or_else(|| other("no name server in /etc/resolv.conf"))
+}
+
+fn parse_nameserver(resolv_conf: &str) -> Option<SocketAddr> {
+    resolv_conf.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("nameserver") {
+            return None;
+        }
+        // IPv6 addresses may have a zone, which isn't supported.
+        let ip: IpAddr = words.next()?.parse().ok()?;
+        Some(SocketAddr::new(ip, DNS_PORT))
+    })
+}
+
+/// A recursive query for the TXT records of the name, advertising the UDP payload size.
+fn build_query(id: u16, name: &str) -> io::Result<Vec<u8>> {
+    let mut query = vec![];
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired; one question and one additional record.
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 1]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(other(format!("invalid name {}", name)));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&TYPE_TXT.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    // OPT record: root name, type, payload size in place of the class, zero TTL and no data.
+    query.push(0);
+    query.extend_from_slice(&TYPE_OPT.to_be_bytes());
+    query.extend_from_slice(&UDP_PAYLOAD_SIZE.to_be_bytes());
+    query.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    Ok(query)
+}
+
+#[derive(Debug, PartialEq)]
+struct Reply {
+    truncated: bool,
+    /// TXT records, each with its strings joined.
+    records: Vec<String>,
+}
+
+fn parse_reply(id: u16, reply: &[u8]) -> io::Result<Reply> {
+    let malformed = || other("malformed DNS reply");
+    let u16_at = |pos: usize| -> io::Result<u16> {
+        Ok(u16::from_be_bytes([
+            *reply.get(pos).ok_or_else(malformed)?,
+            *reply.get(pos + 1).ok_or_else(malformed)?,
+        ]))
+    };
+    if u16_at(0)? != id {
+        return Err(other("DNS reply to another query"));
+    }
+    let flags = u16_at(2)?;
+    match flags & 0x000f {
+        0 => {}
+        3 => return Err(other("no such name")),
+        rcode => return Err(other(format!("DNS error code {}", rcode))),
+    }
+    // The records of a truncated reply may be cut off, they're queried again over TCP.
+    if flags & 0x0200 != 0 {
+        return Ok(Reply { truncated: true, records: vec![] });
+    }
+    let (questions, answers) = (u16_at(4)?, u16_at(6)?);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(reply, pos).ok_or_else(malformed)? + 4;
+    }
+    let mut records = vec![];
+    for _ in 0..answers {
+        pos = skip_name(reply, pos).ok_or_else(malformed)?;
+        let record_type = u16_at(pos)?;
+        let len = u16_at(pos + 8)? as usize;
+        let data = reply.get(pos + 10..pos + 10 + len).ok_or_else(malformed)?;
+        pos += 10 + len;
+        if record_type != TYPE_TXT {
+            continue;
+        }
+        let mut record = vec![];
+        let mut data_pos = 0;
+        while data_pos < data.len() {
+            let string_len = data[data_pos] as usize;
+            record.extend_from_slice(
+                data.get(data_pos + 1..data_pos + 1 + string_len).ok_or_else(malformed)?,
+            );
+            data_pos += 1 + string_len;
+        }
+        records.push(String::from_utf8_lossy(&record).into_owned());
+    }
+    Ok(Reply { truncated: false, records })
+}
+
+/// Position after the possibly compressed name at `pos`.
+fn skip_name(reply: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *reply.get(pos)?;
+        if len == 0 {
+            return Some(pos + 1);
+        }
+        if len & 0xc0 == 0xc0 {
+            return Some(pos + 2);
+        }
+        pos += 1 + len as usize;
+    }
+}
+
+async fn with_timeout<T>(
+    future: impl std::future::Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    tokio::time::timeout(TIMEOUT, future)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "the name server didn't reply"))?
+}
+
+fn other(error: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_query, parse_nameserver, parse_reply, Reply};
+
+    #[test]
+    fn test_dns_seeds_parsing() {
+        assert_eq!(
+            parse_nameserver("# comment\nsearch lan\nnameserver 192.168.1.1\nnameserver 8.8.8.8\n"),
+            Some("192.168.1.1:53".parse().unwrap())
+        );
+        assert_eq!(parse_nameserver("search lan\n"), None);
+
+        let query = build_query(0x1234, "seeds.example.").unwrap();
+        assert_eq!(&query[12..27], b"\x05seeds\x07example\x00");
+        assert!(build_query(1, "seeds..example").is_err());
+
+        // The query, then two TXT answers with the name compressed to point at the question,
+        // the first one split into two strings, and an answer of another type.
+        let mut reply = query.clone();
+        reply[2] = 0x81;
+        reply[3] = 0x80;
+        reply[7] = 3;
+        reply[11] = 0;
+        reply.truncate(31);
+        for (record_type, data) in [
+            (16u16, &b"\x03abc\x04@h:1"[..]),
+            (16, &b"\x06def@h2"[..]),
+            (1, &b"\x7f\x00\x00\x01"[..]),
+        ] {
+            reply.extend_from_slice(&[0xc0, 12]);
+            reply.extend_from_slice(&record_type.to_be_bytes());
+            reply.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+            reply.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            reply.extend_from_slice(data);
+        }
+        assert_eq!(
+            parse_reply(0x1234, &reply).unwrap(),
+            Reply { truncated: false, records: vec!["abc@h:1".to_string(), "def@h2".to_string()] }
+        );
+        assert!(parse_reply(0x4321, &reply).is_err());
+        reply[3] = 0x83;
+        assert!(parse_reply(0x1234, &reply).is_err());
+        reply[3] = 0x80;
+        reply.pop();
+        assert!(parse_reply(0x1234, &reply).is_err());
+    }
+}
@@ -1,2 +1,5 @@
+pub(crate) mod admission;
+pub(crate) mod dns_seeds;
 pub(crate) mod peer_manager_actor;
 pub(crate) mod peer_store;
+pub(crate) mod port_mapping;
@@ -1,6 +1,9 @@
 use crate::peer::codec::Codec;
 use crate::peer::peer_actor::PeerActor;
+use crate::peer_manager::admission;
+use crate::peer_manager::dns_seeds;
 use crate::peer_manager::peer_store::{PeerStore, TrustLevel};
+use crate::peer_manager::port_mapping;
 use crate::private_actix::{
     PeerRequestResult, PeersRequest, RegisterPeer, RegisterPeerResponse, SendMessage, StopMsg,
     Unregister, ValidateEdgeList,
@@ -18,8 +21,8 @@ use crate::types::{
     PeersResponse, RoutingTableUpdate,
 };
 use actix::{
-    Actor, ActorFuture, Addr, Arbiter, AsyncContext, Context, ContextFutureSpawner, Handler,
-    Recipient, Running, StreamHandler, WrapFuture,
+    Actor, ActorContext, ActorFuture, Addr, Arbiter, AsyncContext, Context, ContextFutureSpawner,
+    Handler, Recipient, Running, StreamHandler, WrapFuture,
 };
 #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
 use futures::FutureExt;
@@ -31,6 +34,7 @@ use near_network_primitives::types::{
     RoutedMessageFrom, StateResponseInfo,
 };
 use near_network_primitives::types::{EdgeState, PartialEdgeInfo};
+use near_performance_metrics::actor_metrics::{spawn_mailbox_probes, MailboxProbe};
 use near_performance_metrics::framed_write::FramedWrite;
 use near_performance_metrics_macros::perf;
 use near_primitives::checked_feature;
@@ -39,6 +43,7 @@ use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::time::Clock;
 use near_primitives::types::{AccountId, ProtocolVersion};
 use near_primitives::utils::from_timestamp;
+use near_primitives::views::PortMappingView;
 use near_rate_limiter::{
     ActixMessageResponse, ActixMessageWrapper, ThrottleController, ThrottleFramedRead,
     ThrottleToken,
@@ -48,7 +53,7 @@ use rand::seq::IteratorRandom;
 use rand::thread_rng;
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -109,10 +114,10 @@ struct ConnectedPeer {
     last_time_peer_requested: Instant,
     /// Last time we received a message from this peer.
     last_time_received_message: Instant,
-    /// Time where the connection was established.
-    connection_established_time: Instant,
     /// Who started connection. Inbound (other) or Outbound (us).
     peer_type: PeerType,
+    /// Address of the other end of the TCP connection.
+    peer_addr: SocketAddr,
     /// A helper data structure for limiting reading, reporting stats.
     throttle_controller: ThrottleController,
 }
@@ -125,6 +130,8 @@ struct AdvHelper {
     adv_disable_edge_signature_verification: bool,
     #[cfg(feature = "test_features")]
     adv_disable_edge_pruning: bool,
+    #[cfg(feature = "test_features")]
+    network_faults: Arc<crate::test_utils::NetworkFaults>,
 }
 
 impl AdvHelper {
@@ -188,6 +195,8 @@ pub struct PeerManagerActor {
     peer_counter: Arc<AtomicUsize>,
     /// Used for testing, for disabling features.
     adv_helper: AdvHelper,
+    /// State of the mapping of the listen port in the router, if enabled.
+    port_mapping: Option<PortMappingView>,
 }
 
 impl Actor for PeerManagerActor {
@@ -219,6 +228,16 @@ impl Actor for PeerManagerActor {
                     }
                 };
             });
+
+            if self.config.port_mapping {
+                self.port_mapping = Some(PortMappingView::Pending);
+                self.map_port_trigger(ctx, server_addr);
+            }
+        }
+
+        // Periodically resolves the DNS seeds.
+        if !self.config.dns_seeds.is_empty() && !self.is_private() {
+            self.resolve_dns_seeds_trigger(ctx);
         }
 
         // Periodically push network information to client.
@@ -245,6 +264,9 @@ impl Actor for PeerManagerActor {
 
         // Periodically prints bandwidth stats for each peer.
         self.report_bandwidth_stats_trigger(ctx, REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL);
+
+        // Periodically measures how long messages wait in the mailbox.
+        spawn_mailbox_probes(ctx.address());
     }
 
     /// Try to gracefully disconnect from connected peers.
@@ -269,7 +291,9 @@ impl PeerManagerActor {
         view_client_addr: Recipient<NetworkViewClientMessages>,
         routing_table_addr: Addr<RoutingTableActor>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let peer_store = PeerStore::new(store.clone(), &config.boot_nodes)?;
+        let boot_nodes =
+            if config.proxy_nodes.is_empty() { &config.boot_nodes } else { &config.proxy_nodes };
+        let peer_store = PeerStore::new(store.clone(), boot_nodes)?;
         debug!(target: "network", len = peer_store.len(), boot_nodes = config.boot_nodes.len(), "Found known peers");
         debug!(target: "network", blacklist = ?config.blacklist, "Blacklist");
 
@@ -295,6 +319,7 @@ impl PeerManagerActor {
             txns_since_last_block,
             peer_counter: Arc::new(AtomicUsize::new(0)),
             adv_helper: AdvHelper::default(),
+            port_mapping: None,
         })
     }
 
@@ -418,6 +443,66 @@ impl PeerManagerActor {
         });
     }
 
+    /// Resolves the boot nodes listed by the DNS seeds and adds them to the peer store like the
+    /// boot nodes from the config, then does it again after `dns_seeds_resolve_period`.
+    fn resolve_dns_seeds_trigger(&mut self, ctx: &mut Context<Self>) {
+        dns_seeds::resolve_seeds(self.config.dns_seeds.clone())
+            .into_actor(self)
+            .then(|peers, act, ctx| {
+                debug!(target: "network", num_peers = peers.len(), "Resolved DNS seeds");
+                for peer_info in
+                    peers.into_iter().filter(|peer_info| peer_info.id != act.my_peer_id)
+                {
+                    if let Err(err) = act.peer_store.add_trusted_peer(peer_info, TrustLevel::Signed)
+                    {
+                        error!(target: "network", ?err, "Fail to update peer store");
+                    }
+                }
+                near_performance_metrics::actix::run_later(
+                    ctx,
+                    act.config.dns_seeds_resolve_period,
+                    |act, ctx| act.resolve_dns_seeds_trigger(ctx),
+                );
+                actix::fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
+    /// Maps the listen port in the router and renews the mapping before its lease expires, or
+    /// tries again later if it fails.
+    fn map_port_trigger(&mut self, ctx: &mut Context<Self>, listen_addr: SocketAddr) {
+        port_mapping::map_port(listen_addr)
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                let next_attempt = match res {
+                    Ok(mapping) => {
+                        if mapping.external_addr.port() != listen_addr.port() {
+                            warn!(target: "network", external_addr = %mapping.external_addr, "The router mapped the port to a different one, peers can't connect to it");
+                        } else {
+                            debug!(target: "network", external_addr = %mapping.external_addr, protocol = ?mapping.protocol, "Mapped the port");
+                        }
+                        metrics::PORT_MAPPING_ACTIVE.set(1);
+                        act.port_mapping = Some(PortMappingView::Mapped {
+                            protocol: mapping.protocol,
+                            external_addr: mapping.external_addr.to_string(),
+                        });
+                        port_mapping::LEASE_DURATION / 2
+                    }
+                    Err(err) => {
+                        warn!(target: "network", %err, "Failed to map the port in the router");
+                        metrics::PORT_MAPPING_ACTIVE.set(0);
+                        act.port_mapping = Some(PortMappingView::Failed { error: err.to_string() });
+                        port_mapping::RETRY_INTERVAL
+                    }
+                };
+                near_performance_metrics::actix::run_later(ctx, next_attempt, move |act, ctx| {
+                    act.map_port_trigger(ctx, listen_addr);
+                });
+                actix::fut::ready(())
+            })
+            .spawn(ctx);
+    }
+
     /// Receives list of edges that were verified, in a trigger every 20ms, and adds them to
     /// the routing table.
     fn broadcast_validated_edges_trigger(&mut self, ctx: &mut Context<Self>, interval: Duration) {
@@ -563,6 +648,7 @@ impl PeerManagerActor {
         full_peer_info: FullPeerInfo,
         partial_edge_info: PartialEdgeInfo,
         peer_type: PeerType,
+        peer_addr: SocketAddr,
         addr: Addr<PeerActor>,
         peer_protocol_version: ProtocolVersion,
         throttle_controller: ThrottleController,
@@ -599,8 +685,8 @@ impl PeerManagerActor {
                 received_bytes_per_sec: 0,
                 last_time_peer_requested: Clock::instant(),
                 last_time_received_message: Clock::instant(),
-                connection_established_time: Clock::instant(),
                 peer_type,
+                peer_addr,
                 throttle_controller: throttle_controller.clone(),
             },
         );
@@ -794,6 +880,7 @@ impl PeerManagerActor {
     ) {
         let my_peer_id = self.my_peer_id.clone();
         let account_id = self.config.account_id.clone();
+        let is_private = self.is_private();
         let server_addr = self.config.addr;
         let handshake_timeout = self.config.handshake_timeout;
         let client_addr = self.client_addr.clone();
@@ -820,6 +907,9 @@ impl PeerManagerActor {
 
         let network_metrics = self.network_metrics.clone();
         let txns_since_last_block = Arc::clone(&self.txns_since_last_block);
+        let client_version = self.config.client_version.clone();
+        #[cfg(feature = "test_features")]
+        let network_faults = self.adv_helper.network_faults.clone();
 
         // Start every peer actor on separate thread.
         let arbiter = Arbiter::new();
@@ -845,7 +935,12 @@ impl PeerManagerActor {
             );
 
             PeerActor::new(
-                PeerInfo { id: my_peer_id, addr: Some(server_addr), account_id },
+                // A private validator doesn't tell its address, so that it never gets gossiped.
+                PeerInfo {
+                    id: my_peer_id,
+                    addr: if is_private { None } else { Some(server_addr) },
+                    account_id,
+                },
                 remote_addr,
                 peer_info,
                 peer_type,
@@ -859,6 +954,9 @@ impl PeerManagerActor {
                 txns_since_last_block,
                 peer_counter,
                 rate_limiter,
+                client_version,
+                #[cfg(feature = "test_features")]
+                network_faults,
             )
         });
     }
@@ -866,7 +964,7 @@ impl PeerManagerActor {
     /// Check if it is needed to create a new outbound connection.
     /// If the number of active connections is less than `ideal_connections_lo` or
     /// (the number of outgoing connections is less than `minimum_outbound_peers`
-    ///     and the total connections is less than `max_num_peers`)
+    ///     and the total connections is less than `max_num_peers` minus the reserved slots)
     fn is_outbound_bootstrap_needed(&self) -> bool {
         let total_connections = self.connected_peers.len() + self.outgoing_peers.len();
         let potential_outgoing_connections = (self.connected_peers.values())
@@ -875,13 +973,55 @@ impl PeerManagerActor {
             + self.outgoing_peers.len();
 
         (total_connections < self.config.ideal_connections_lo as usize
-            || (total_connections < self.config.max_num_peers as usize
+            || (total_connections < self.unreserved_peer_slots()
                 && potential_outgoing_connections < self.config.minimum_outbound_peers as usize))
             && !self.config.outbound_disabled
     }
 
-    fn is_inbound_allowed(&self) -> bool {
-        self.connected_peers.len() + self.outgoing_peers.len() < self.config.max_num_peers as usize
+    /// Whether this node is a private validator, which talks only to its proxy nodes.
+    fn is_private(&self) -> bool {
+        !self.config.proxy_nodes.is_empty()
+    }
+
+    fn is_proxy_node(&self, peer_id: &PeerId) -> bool {
+        self.config.proxy_nodes.iter().any(|proxy| &proxy.id == peer_id)
+    }
+
+    /// Whether there is a free slot for an inbound connection.  The reserved slots are taken
+    /// only by validators and whitelisted peers.
+    fn is_inbound_allowed(&self, is_reserved_peer: bool) -> bool {
+        let limit = if is_reserved_peer {
+            self.config.max_num_peers as usize
+        } else {
+            self.unreserved_peer_slots()
+        };
+        self.connected_peers.len() + self.outgoing_peers.len() < limit
+    }
+
+    fn unreserved_peer_slots(&self) -> usize {
+        self.config.max_num_peers.saturating_sub(self.config.reserved_peer_slots) as usize
+    }
+
+    /// Whether the peer is whitelisted or a validator which announced its account, so that it can
+    /// take the reserved slots and isn't dropped to make room for other peers.
+    fn is_reserved_peer(&self, peer_id: &PeerId) -> bool {
+        self.config.whitelisted_peers.contains(peer_id)
+            || (self.routing_table_view.get_announce_accounts())
+                .any(|announce_account| &announce_account.peer_id == peer_id)
+    }
+
+    /// Which limit on the inbound connections from the same IP or subnet another inbound
+    /// connection from `ip` would exceed, if any.
+    fn inbound_limit_exceeded(&self, ip: IpAddr) -> Option<&'static str> {
+        let inbound_ips = (self.connected_peers.values())
+            .filter(|connected_peer| connected_peer.peer_type == PeerType::Inbound)
+            .map(|connected_peer| connected_peer.peer_addr.ip());
+        admission::inbound_limit_exceeded(
+            inbound_ips,
+            ip,
+            self.config.max_inbound_peers_per_ip,
+            self.config.max_inbound_peers_per_subnet,
+        )
     }
 
     /// Returns single random peer with close to the highest height
@@ -1040,6 +1180,8 @@ impl PeerManagerActor {
                                 // }
                             } else if let Some(connected_peer) = act.connected_peers.get_mut(&peer_id1) {
                                 connected_peer.full_peer_info.chain_info = res.chain_info;
+                                connected_peer.full_peer_info.servable_state = res.servable_state;
+                                connected_peer.full_peer_info.client_version = res.client_version;
                                 connected_peer.sent_bytes_per_sec = res.sent_bytes_per_sec;
                                 connected_peer.received_bytes_per_sec = res.received_bytes_per_sec;
                             }
@@ -1066,6 +1208,8 @@ impl PeerManagerActor {
     ///         Among all the peers we have received a message within the last peer_recent_time_window,
     ///             find the one we connected earlier and add it to the safe set.
     ///         else break
+    ///     Validators and whitelisted peers are always in the safe set.
+    ///     With `evict_lowest_scoring_peer` the peer with the lowest score is selected instead.
     fn try_stop_active_connection(&self) {
         debug!(target: "network",
             connected_peers_len = self.connected_peers.len(),
@@ -1074,7 +1218,14 @@ impl PeerManagerActor {
         );
 
         // Build safe set
-        let mut safe_set = HashSet::new();
+        let mut safe_set: HashSet<&PeerId> = self.config.private_peers.iter().collect();
+        safe_set.extend(self.config.whitelisted_peers.iter());
+        let announced_peers: HashSet<&PeerId> = (self.routing_table_view.get_announce_accounts())
+            .map(|announce_account| &announce_account.peer_id)
+            .collect();
+        safe_set.extend(
+            self.connected_peers.keys().filter(|peer_id| announced_peers.contains(peer_id)),
+        );
 
         if (self.connected_peers.values())
             .filter(|connected_peer| connected_peer.peer_type == PeerType::Outbound)
@@ -1091,12 +1242,12 @@ impl PeerManagerActor {
 
         if self.config.archive
             && (self.connected_peers.values())
-                .filter(|connected_peer| connected_peer.full_peer_info.chain_info.archival)
+                .filter(|connected_peer| connected_peer.full_peer_info.is_archival())
                 .count()
                 <= self.config.archival_peer_connections_lower_bound as usize
         {
             for (peer, active) in self.connected_peers.iter() {
-                if active.full_peer_info.chain_info.archival {
+                if active.full_peer_info.is_archival() {
                     safe_set.insert(peer);
                 }
             }
@@ -1107,7 +1258,7 @@ impl PeerManagerActor {
             .filter_map(|(peer_id, active)| {
                 if active.last_time_received_message.elapsed() < self.config.peer_recent_time_window
                 {
-                    Some((peer_id.clone(), active.connection_established_time))
+                    Some((peer_id.clone(), active.full_peer_info.connection_established_time))
                 } else {
                     None
                 }
@@ -1136,7 +1287,24 @@ impl PeerManagerActor {
             }
         });
 
-        if let Some(peer_id) = candidates.choose(&mut rand::thread_rng()) {
+        let selected = if self.config.evict_lowest_scoring_peer {
+            let max_height = (self.connected_peers.values())
+                .map(|connected_peer| connected_peer.full_peer_info.chain_info.height)
+                .max()
+                .unwrap_or_default();
+            candidates.min_by_key(|peer_id| {
+                let connected_peer = &self.connected_peers[*peer_id];
+                admission::peer_score(
+                    connected_peer.full_peer_info.chain_info.height,
+                    max_height,
+                    self.config.highest_peer_horizon,
+                    connected_peer.received_bytes_per_sec,
+                )
+            })
+        } else {
+            candidates.choose(&mut rand::thread_rng())
+        };
+        if let Some(peer_id) = selected {
             if let Some(connected_peer) = self.connected_peers.get(peer_id) {
                 debug!(target: "network", ?peer_id, "Stop active connection");
                 connected_peer.addr.do_send(PeerManagerRequest::UnregisterPeer);
@@ -1187,6 +1355,8 @@ impl PeerManagerActor {
                     || self.config.addr == peer_state.peer_info.addr
                     // Or to peers we are currently trying to connect to
                     || self.outgoing_peers.contains(&peer_state.peer_info.id)
+                    // A private validator connects only to its proxies
+                    || (self.is_private() && !self.is_proxy_node(&peer_state.peer_info.id))
             }) {
                 // Start monitor_peers_attempts from start after we discover the first healthy peer
                 if !self.started_connect_attempts {
@@ -1198,7 +1368,7 @@ impl PeerManagerActor {
                 ctx.notify(PeerManagerMessageRequest::OutboundTcpConnect(OutboundTcpConnect {
                     peer_info,
                 }));
-            } else {
+            } else if !self.is_private() {
                 self.query_connected_peers_for_more_peers();
             }
         }
@@ -1471,6 +1641,7 @@ impl PeerManagerActor {
                 })
                 .collect(),
             peer_counter: self.peer_counter.load(Ordering::SeqCst),
+            port_mapping: self.port_mapping.clone(),
         }
     }
 
@@ -1509,6 +1680,10 @@ impl PeerManagerActor {
                 );
                 NetworkResponses::NoResponse
             }
+            NetworkRequests::Approvals { target, approvals } => {
+                self.send_message_to_account(&target, RoutedMessageBody::BlockApprovals(approvals));
+                NetworkResponses::NoResponse
+            }
             NetworkRequests::BlockRequest { hash, peer_id } => {
                 if Self::send_message(
                     &self.connected_peers,
@@ -1617,7 +1792,7 @@ impl PeerManagerActor {
                     } else {
                         let mut matching_peers = vec![];
                         for (peer_id, connected_peer) in self.connected_peers.iter() {
-                            if (connected_peer.full_peer_info.chain_info.archival
+                            if (connected_peer.full_peer_info.is_archival()
                                 || !target.only_archival)
                                 && connected_peer.full_peer_info.chain_info.height
                                     >= target.min_height
@@ -1889,6 +2064,13 @@ impl PeerManagerActor {
         }
     }
 
+    #[cfg(feature = "test_features")]
+    #[perf]
+    fn handle_msg_set_network_faults(&mut self, msg: crate::test_utils::SetNetworkFaults) {
+        debug!(target: "network", peer_id = ?msg.peer_id, drop_percent = msg.drop_percent, "test_features set_network_faults");
+        self.adv_helper.network_faults.set_drop_percent(msg.peer_id, msg.drop_percent);
+    }
+
     #[cfg(all(feature = "test_features", feature = "protocol_feature_routing_exchange_algorithm"))]
     #[perf]
     fn handle_msg_set_routing_table(
@@ -1914,7 +2096,8 @@ impl PeerManagerActor {
     fn handle_msg_inbound_tcp_connect(&self, msg: InboundTcpConnect, ctx: &mut Context<Self>) {
         let _d = delay_detector::DelayDetector::new(|| "inbound tcp connect".into());
 
-        if self.is_inbound_allowed() {
+        // Who connects is known only after the handshake, so the reserved slots are checked then.
+        if self.is_inbound_allowed(true) {
             self.try_connect_peer(ctx.address(), msg.stream, PeerType::Inbound, None, None);
         } else {
             // TODO(1896): Gracefully drop inbound connection for other peer.
@@ -1987,9 +2170,17 @@ impl PeerManagerActor {
     ) -> RegisterPeerResponse {
         let _d = delay_detector::DelayDetector::new(|| "consolidate".into());
 
-        // Check if this is a blacklisted peer.
-        if (msg.peer_info.addr.as_ref())
-            .map_or(true, |addr| Self::is_blacklisted(&self.config.blacklist, addr))
+        let is_private_peer = self.config.private_peers.contains(&msg.peer_info.id);
+
+        if self.is_private() && !self.is_proxy_node(&msg.peer_info.id) {
+            debug!(target: "network", peer_info = ?msg.peer_info, "Dropping connection from a peer which is not a proxy node");
+            return RegisterPeerResponse::Reject;
+        }
+
+        // Check if this is a blacklisted peer.  Private validators don't advertise their address.
+        if !is_private_peer
+            && (msg.peer_info.addr.as_ref())
+                .map_or(true, |addr| Self::is_blacklisted(&self.config.blacklist, addr))
         {
             debug!(target: "network", peer_info = ?msg.peer_info, "Dropping connection from blacklisted peer or unknown address");
             return RegisterPeerResponse::Reject;
@@ -2016,14 +2207,26 @@ impl PeerManagerActor {
             }
         }
 
-        if msg.peer_type == PeerType::Inbound && !self.is_inbound_allowed() {
-            // TODO(1896): Gracefully drop inbound connection for other peer.
-            debug!(target: "network",
-                connected_peers = self.connected_peers.len(), outgoing_peers = self.outgoing_peers.len(),
-                max_num_peers = self.config.max_num_peers,
-                "Inbound connection dropped (network at max capacity)."
-            );
-            return RegisterPeerResponse::Reject;
+        if msg.peer_type == PeerType::Inbound && !is_private_peer {
+            let is_reserved_peer = self.is_reserved_peer(&msg.peer_info.id);
+            if !self.is_inbound_allowed(is_reserved_peer) {
+                // TODO(1896): Gracefully drop inbound connection for other peer.
+                debug!(target: "network",
+                    connected_peers = self.connected_peers.len(), outgoing_peers = self.outgoing_peers.len(),
+                    max_num_peers = self.config.max_num_peers, reserved_peer_slots = self.config.reserved_peer_slots,
+                    "Inbound connection dropped (network at max capacity)."
+                );
+                return RegisterPeerResponse::Reject;
+            }
+            let exceeded_limit = if is_reserved_peer {
+                None
+            } else {
+                self.inbound_limit_exceeded(msg.peer_addr.ip())
+            };
+            if let Some(limit) = exceeded_limit {
+                debug!(target: "network", peer_info = ?msg.peer_info, peer_addr = ?msg.peer_addr, limit, "Inbound connection dropped (too many connections from the same address).");
+                return RegisterPeerResponse::Reject;
+            }
         }
 
         if msg.other_edge_info.nonce == 0 {
@@ -2060,9 +2263,15 @@ impl PeerManagerActor {
                 peer_info: msg.peer_info,
                 chain_info: msg.chain_info,
                 partial_edge_info: msg.other_edge_info,
+                servable_state: vec![],
+                protocol_version: msg.peer_protocol_version,
+                client_version: None,
+                capabilities: msg.peer_capabilities,
+                connection_established_time: Clock::instant(),
             },
             edge_info,
             msg.peer_type,
+            msg.peer_addr,
             msg.actor,
             msg.peer_protocol_version,
             msg.throttle_controller,
@@ -2087,9 +2296,20 @@ impl PeerManagerActor {
     #[perf]
     fn handle_msg_peers_request(&self, _msg: PeersRequest) -> PeerRequestResult {
         let _d = delay_detector::DelayDetector::new(|| "peers request".into());
-        PeerRequestResult {
-            peers: self.peer_store.healthy_peers(self.config.max_send_peers as usize),
+        let mut peers = self.peer_store.healthy_peers(self.config.max_send_peers as usize);
+        if !self.config.private_peers.is_empty() {
+            // A proxy advertises itself in place of its private validators, which are reached
+            // through it.
+            peers.retain(|peer_info| !self.config.private_peers.contains(&peer_info.id));
+            if let Some(addr) = self.config.addr {
+                peers.push(PeerInfo {
+                    id: self.my_peer_id.clone(),
+                    addr: Some(addr),
+                    account_id: None,
+                });
+            }
         }
+        PeerRequestResult { peers }
     }
 
     fn handle_msg_peers_response(&mut self, msg: PeersResponse) {
@@ -2157,6 +2377,11 @@ impl PeerManagerActor {
                 self.handle_msg_ban(msg);
                 PeerManagerMessageResponse::Ban(())
             }
+            PeerManagerMessageRequest::Stop => {
+                // Peers are disconnected from in `stopping`.
+                ctx.stop();
+                PeerManagerMessageResponse::Stop(())
+            }
             #[cfg(feature = "test_features")]
             #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
             PeerManagerMessageRequest::StartRoutingTableSync(msg) => {
@@ -2169,6 +2394,11 @@ impl PeerManagerActor {
                 PeerManagerMessageResponse::SetAdvOptions(())
             }
             #[cfg(feature = "test_features")]
+            PeerManagerMessageRequest::SetNetworkFaults(msg) => {
+                self.handle_msg_set_network_faults(msg);
+                PeerManagerMessageResponse::SetNetworkFaults(())
+            }
+            #[cfg(feature = "test_features")]
             #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
             PeerManagerMessageRequest::SetRoutingTable(msg) => {
                 self.handle_msg_set_routing_table(msg, ctx);
@@ -2310,3 +2540,10 @@ impl Handler<PeerManagerMessageRequest> for PeerManagerActor {
         self.handle_peer_manager_message(msg, ctx, None)
     }
 }
+
+impl Handler<MailboxProbe> for PeerManagerActor {
+    type Result = ();
+    fn handle(&mut self, msg: MailboxProbe, _ctx: &mut Self::Context) {
+        msg.observe();
+    }
+}
@@ -0,0 +1,300 @@
+//! Maps the port the node listens on in the router, so that nodes behind consumer routers are
+//! reachable without manual port forwarding.  UPnP IGD is tried first, then NAT-PMP with the
+//! default gateway.
+//!
+//! The external port is the same as the port the node listens on, since the peers learn the
+//! address to connect to from the IP they see and the port the node sends in the handshake.
+use near_primitives::views::PortMappingProtocol;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tracing::debug;
+
+/// How long the router keeps the mapping; it's renewed halfway through.
+pub(crate) const LEASE_DURATION: Duration = Duration::from_secs(60 * 60);
+/// How long to wait before trying again after a failure.
+pub(crate) const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How long to wait for each reply of the router.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const IGD_SERVICE_TYPES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+const NAT_PMP_PORT: u16 = 5351;
+const MAPPING_DESCRIPTION: &str = "near";
+
+/// A port mapped in the router.
+#[derive(Debug, Clone)]
+pub(crate) struct PortMapping {
+    pub protocol: PortMappingProtocol,
+    pub external_addr: SocketAddr,
+}
+
+/// Maps the port of the listen address in the router, or renews the mapping.
+pub(crate) async fn map_port(listen_addr: SocketAddr) -> io::Result<PortMapping> {
+    let port = listen_addr.port();
+    let upnp_err = match map_port_upnp(port).await {
+        Ok(mapping) => return Ok(mapping),
+        Err(err) => err,
+    };
+    debug!(target: "network", ?upnp_err, "Failed to map the port with UPnP, trying NAT-PMP");
+    map_port_nat_pmp(port).await.map_err(|nat_pmp_err| {
+        other(format!("UPnP failed: {}; NAT-PMP failed: {}", upnp_err, nat_pmp_err))
+    })
+}
+
+async fn map_port_upnp(port: u16) -> io::Result<PortMapping> {
+    let location = discover_gateway().await?;
+    let (host, path) = split_url(&location)?;
+    let description =
+        String::from_utf8_lossy(&http_request(&host, "GET", &path, &[], "").await?).into_owned();
+    let (service_type, control_url) = IGD_SERVICE_TYPES
+        .iter()
+        .find_map(|service_type| {
+            let rest = &description[description.find(service_type)?..];
+            Some((*service_type, xml_value(rest, "controlURL")?))
+        })
+        .ok_or_else(|| other("the gateway has no WAN connection service"))?;
+    let (control_host, control_path) = if control_url.starts_with("http://") {
+        split_url(control_url)?
+    } else if control_url.starts_with('/') {
+        (host.clone(), control_url.to_string())
+    } else {
+        (host.clone(), format!("/{}", control_url))
+    };
+
+    // The router maps the port to the address the node reaches it from.
+    let gateway_addr = tokio::net::lookup_host(&control_host)
+        .await?
+        .next()
+        .ok_or_else(|| other(format!("can't resolve {}", control_host)))?;
+    let internal_ip = local_ip_towards(gateway_addr).await?;
+    let args = format!(
+        "<NewRemoteHost></NewRemoteHost><NewExternalPort>{port}</NewExternalPort>\
+         <NewProtocol>TCP</NewProtocol><NewInternalPort>{port}</NewInternalPort>\
+         <NewInternalClient>{internal_ip}</NewInternalClient><NewEnabled>1</NewEnabled>\
+         <NewPortMappingDescription>{description}</NewPortMappingDescription>\
+         <NewLeaseDuration>{lease}</NewLeaseDuration>",
+        port = port,
+        internal_ip = internal_ip,
+        description = MAPPING_DESCRIPTION,
+        lease = LEASE_DURATION.as_secs(),
+    );
+    soap_request(&control_host, &control_path, service_type, "AddPortMapping", &args).await?;
+    let response =
+        soap_request(&control_host, &control_path, service_type, "GetExternalIPAddress", "")
+            .await?;
+    let external_ip: IpAddr = xml_value(&response, "NewExternalIPAddress")
+        .and_then(|ip| ip.parse().ok())
+        .ok_or_else(|| other("the gateway didn't tell its external address"))?;
+    Ok(PortMapping {
+        protocol: PortMappingProtocol::Upnp,
+        external_addr: SocketAddr::new(external_ip, port),
+    })
+}
+
+/// Finds the internet gateway device with SSDP and returns the URL of its description.
+async fn discover_gateway() -> io::Result<String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\n\r\n",
+        SSDP_ADDR
+    );
+    socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+    let mut buf = [0u8; 2048];
+    let (len, _) = with_timeout(socket.recv_from(&mut buf)).await?;
+    String::from_utf8_lossy(&buf[..len])
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("location").then(|| value.trim().to_string())
+        })
+        .ok_or_else(|| other("the gateway sent no location"))
+}
+
+async fn soap_request(
+    host: &str,
+    path: &str,
+    service_type: &str,
+    action: &str,
+    args: &str,
+) -> io::Result<String> {
+    let body = format!(
+        "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{action} xmlns:u=\"{service_type}\">{args}</u:{action}></s:Body></s:Envelope>",
+        action = action,
+        service_type = service_type,
+        args = args,
+    );
+    let soap_action = format!("\"{}#{}\"", service_type, action);
+    let headers =
+        [("Content-Type", "text/xml; charset=\"utf-8\""), ("SOAPAction", soap_action.as_str())];
+    let response = http_request(host, "POST", path, &headers, &body).await?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Sends an HTTP/1.0 request, so that the reply isn't chunked, and returns the body of a
+/// successful reply.
+async fn http_request(
+    host: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> io::Result<Vec<u8>> {
+    let mut request = format!(
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        host,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+
+    let mut stream = with_timeout(TcpStream::connect(host)).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = vec![];
+    with_timeout(stream.read_to_end(&mut response)).await?;
+    let header_end = (response.windows(4).position(|window| window == b"\r\n\r\n"))
+        .ok_or_else(|| other("malformed HTTP reply"))?;
+    let status_line = String::from_utf8_lossy(&response[..header_end])
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(other(format!("{} {} failed: {}", method, path, status_line)));
+    }
+    Ok(response.split_off(header_end + 4))
+}
+
+async fn map_port_nat_pmp(port: u16) -> io::Result<PortMapping> {
+    let gateway = default_gateway()?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect((gateway, NAT_PMP_PORT)).await?;
+
+    // Version 0, external address request.
+    let response = nat_pmp_request(&socket, &[0, 0], 12).await?;
+    let external_ip = Ipv4Addr::new(response[8], response[9], response[10], response[11]);
+
+    // Version 0, TCP mapping request.
+    let mut request = vec![0, 2, 0, 0];
+    request.extend_from_slice(&port.to_be_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    request.extend_from_slice(&(LEASE_DURATION.as_secs() as u32).to_be_bytes());
+    let response = nat_pmp_request(&socket, &request, 16).await?;
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    Ok(PortMapping {
+        protocol: PortMappingProtocol::NatPmp,
+        external_addr: SocketAddr::new(external_ip.into(), external_port),
+    })
+}
+
+/// Sends a NAT-PMP request and returns the reply, checking its opcode and result code.
+async fn nat_pmp_request(
+    socket: &UdpSocket,
+    request: &[u8],
+    response_len: usize,
+) -> io::Result<Vec<u8>> {
+    socket.send(request).await?;
+    let mut buf = [0u8; 16];
+    let len = with_timeout(socket.recv(&mut buf)).await?;
+    if len < response_len || buf[1] != request[1] + 128 {
+        return Err(other("malformed NAT-PMP reply"));
+    }
+    match u16::from_be_bytes([buf[2], buf[3]]) {
+        0 => Ok(buf[..len].to_vec()),
+        code => Err(other(format!("NAT-PMP result code {}", code))),
+    }
+}
+
+/// The gateway of the default route.  Only known on Linux.
+fn default_gateway() -> io::Result<Ipv4Addr> {
+    let routes = std::fs::read_to_string("/proc/net/route")?;
+    parse_default_gateway(&routes).ok_or_else(|| other("no default route"))
+}
+
+/// Parses `/proc/net/route`, where the addresses are hex in the native byte order.
+fn parse_default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_ne_bytes())).filter(|gateway| !gateway.is_unspecified())
+    })
+}
+
+/// The local address used to reach the given one.
+async fn local_ip_towards(addr: SocketAddr) -> io::Result<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.connect(addr).await?;
+    Ok(socket.local_addr()?.ip())
+}
+
+/// Splits `http://host:port/path` into the host with the port and the path.
+fn split_url(url: &str) -> io::Result<(String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| other(format!("bad URL {}", url)))?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    Ok((host, path.to_string()))
+}
+
+/// Text of the first element with the given tag.
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(xml[start..end].trim())
+}
+
+async fn with_timeout<T>(
+    future: impl std::future::Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    tokio::time::timeout(TIMEOUT, future)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "the gateway didn't reply"))?
+}
+
+fn other(error: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_default_gateway, split_url, xml_value};
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_port_mapping_parsing() {
+        assert_eq!(
+            split_url("http://192.168.1.1:5000/rootDesc.xml").unwrap(),
+            ("192.168.1.1:5000".to_string(), "/rootDesc.xml".to_string())
+        );
+        assert_eq!(split_url("http://router").unwrap(), ("router:80".to_string(), "/".to_string()));
+        assert!(split_url("https://router/").is_err());
+
+        let xml = "<service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL> /ctl/IPConn </controlURL></service>";
+        assert_eq!(xml_value(xml, "controlURL"), Some("/ctl/IPConn"));
+        assert_eq!(xml_value(xml, "eventSubURL"), None);
+
+        let routes = format!(
+            "Iface\tDestination\tGateway\tFlags\n\
+             eth0\t0001A8C0\t00000000\t0001\n\
+             eth0\t00000000\t{:08X}\t0003\n",
+            u32::from_ne_bytes([192, 168, 1, 1])
+        );
+        assert_eq!(parse_default_gateway(&routes), Some(Ipv4Addr::new(192, 168, 1, 1)));
+        assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+}
@@ -1,6 +1,6 @@
 /// This file is contains all types used for communication between `Actors` within this crate.
 /// They are not meant to be used outside.
-use crate::network_protocol::PeerMessage;
+use crate::network_protocol::{PeerCapabilities, PeerMessage};
 use crate::peer::peer_actor::PeerActor;
 use actix::{Addr, Message};
 use conqueue::QueueSender;
@@ -13,6 +13,7 @@ use near_rate_limiter::ThrottleController;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 
 /// Actor message which asks `PeerManagerActor` to register peer.
@@ -24,6 +25,8 @@ pub struct RegisterPeer {
     pub(crate) actor: Addr<PeerActor>,
     pub(crate) peer_info: PeerInfo,
     pub(crate) peer_type: PeerType,
+    /// Address of the other end of the TCP connection. Unlike `peer_info.addr` it is always known.
+    pub(crate) peer_addr: SocketAddr,
     pub(crate) chain_info: PeerChainInfoV2,
     /// Edge information from this node.
     /// If this is None it implies we are outbound connection, so we need to create our
@@ -33,6 +36,8 @@ pub struct RegisterPeer {
     pub(crate) other_edge_info: PartialEdgeInfo,
     /// Protocol version of new peer. May be higher than ours.
     pub(crate) peer_protocol_version: ProtocolVersion,
+    /// Capabilities from the handshake of the new peer.
+    pub(crate) peer_capabilities: Option<PeerCapabilities>,
     /// A helper data structure for limiting reading, reporting bandwidth stats.
     pub(crate) throttle_controller: ThrottleController,
 }
@@ -47,6 +52,7 @@ impl deepsize::DeepSizeOf for RegisterPeer {
             + self.this_edge_info.deep_size_of_children(context)
             + self.other_edge_info.deep_size_of_children(context)
             + self.peer_protocol_version.deep_size_of_children(context)
+            + self.peer_capabilities.deep_size_of_children(context)
     }
 }
 
@@ -116,7 +122,7 @@ impl Debug for ValidateEdgeList {
 
 /// List of `Edges`, which we received from `source_peer_id` gor purpose of validation.
 /// Those are list of edges received through `NetworkRequests::Sync` or `NetworkRequests::IbfMessage`.
-#[derive(actix::Message)]
+#[derive(actix::Message, Clone)]
 #[rtype(result = "bool")]
 pub struct ValidateEdgeList {
     /// The list of edges is provided by `source_peer_id`, that peer will be banned
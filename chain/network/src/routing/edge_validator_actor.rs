@@ -1,4 +1,5 @@
 use crate::private_actix::{StopMsg, ValidateEdgeList};
+use crate::stats::metrics;
 use actix::{Actor, Handler, SyncContext, System};
 use conqueue::{QueueReceiver, QueueSender};
 use near_network_primitives::types::Edge;
@@ -32,6 +33,7 @@ impl Handler<ValidateEdgeList> for EdgeValidatorActor {
 
     #[perf]
     fn handle(&mut self, msg: ValidateEdgeList, _ctx: &mut Self::Context) -> Self::Result {
+        let _timer = metrics::EDGE_VERIFICATION_HISTOGRAM.start_timer();
         for edge in msg.edges {
             let key = edge.key();
             if msg.edges_info_shared.lock().unwrap().get(key).cloned().unwrap_or(0u64)
@@ -40,6 +42,7 @@ impl Handler<ValidateEdgeList> for EdgeValidatorActor {
                 continue;
             }
 
+            metrics::EDGES_VERIFIED.inc();
             #[cfg(feature = "test_features")]
             if !msg.adv_disable_edge_signature_verification && !edge.verify() {
                 return false;
@@ -15,11 +15,22 @@ use near_primitives::utils::index_to_bytes;
 use near_rate_limiter::{ActixMessageResponse, ActixMessageWrapper, ThrottleToken};
 use near_store::db::DBCol::{ColComponentEdges, ColLastComponentNonce, ColPeerComponent};
 use near_store::{Store, StoreUpdate};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, trace, warn};
 
+/// Number of threads verifying the signatures of edges, each verifying one list at a time.
+const EDGE_VALIDATOR_THREADS: usize = 4;
+/// Number of edges which can wait to be verified.  The edges received beyond that are dropped
+/// unverified, so that routing storms can't pile up unbounded work; they're learnt again from
+/// later routing table syncs.
+const MAX_EDGES_TO_VALIDATE: usize = 100_000;
+/// Number of edges adjacent to this node which can wait to be verified, on top of
+/// `MAX_EDGES_TO_VALIDATE`.  They skip the queue, so they get a budget of their own, which a
+/// storm of other edges can't use up.
+const MAX_ADJACENT_EDGES_TO_VALIDATE: usize = 10_000;
+
 /// `Prune` enum is to specify how often should we prune edges.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Prune {
@@ -75,7 +86,14 @@ pub struct RoutingTableActor {
     edge_validator_pool: Addr<EdgeValidatorActor>,
     /// Number of edge validations in progress; We will not update routing table as long as
     /// this number is non zero.
-    edge_validator_requests_in_progress: u64,
+    edge_validator_requests_in_progress: usize,
+    /// Lists of edges waiting for a thread of `edge_validator_pool`.  The edges adjacent to this
+    /// node are verified first, as they change the routes to the connected peers.
+    edges_to_validate: VecDeque<ValidateEdgeList>,
+    /// Number of edges in `edges_to_validate` which aren't adjacent to this node.
+    num_edges_to_validate: usize,
+    /// Number of edges in `edges_to_validate` which are adjacent to this node.
+    num_adjacent_edges_to_validate: usize,
     /// List of Peers to ban
     peers_to_ban: Vec<PeerId>,
 }
@@ -86,7 +104,8 @@ impl RoutingTableActor {
             .get_ser::<u64>(ColLastComponentNonce, &[])
             .unwrap_or(None)
             .map_or(0, |nonce| nonce + 1);
-        let edge_validator_pool = SyncArbiter::start(4, || EdgeValidatorActor {});
+        let edge_validator_pool =
+            SyncArbiter::start(EDGE_VALIDATOR_THREADS, || EdgeValidatorActor {});
         Self {
             edges_info: Default::default(),
             #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
@@ -99,6 +118,9 @@ impl RoutingTableActor {
             needs_routing_table_recalculation: Default::default(),
             edge_validator_pool,
             edge_validator_requests_in_progress: Default::default(),
+            edges_to_validate: Default::default(),
+            num_edges_to_validate: 0,
+            num_adjacent_edges_to_validate: 0,
             peers_to_ban: Default::default(),
         }
     }
@@ -114,7 +136,10 @@ impl RoutingTableActor {
         self.peer_ibf_set.remove_edge(&edge.to_simple_edge());
 
         let key = edge.key();
-        if self.edges_info.remove(key).is_some() {
+        if let Some(removed) = self.edges_info.remove(key) {
+            if removed.edge_type() == EdgeState::Active {
+                metrics::EDGES_REMOVED.inc();
+            }
             self.raw_graph.remove_edge(&edge.key().0, &edge.key().1);
             self.needs_routing_table_recalculation = true;
         }
@@ -131,9 +156,13 @@ impl RoutingTableActor {
             self.needs_routing_table_recalculation = true;
             match edge.edge_type() {
                 EdgeState::Active => {
+                    metrics::EDGES_ADDED.inc();
                     self.raw_graph.add_edge(&key.0, &key.1);
                 }
                 EdgeState::Removed => {
+                    if self.edges_info.get(key).map(Edge::edge_type) == Some(EdgeState::Active) {
+                        metrics::EDGES_REMOVED.inc();
+                    }
                     self.raw_graph.remove_edge(&key.0, &key.1);
                 }
             }
@@ -424,24 +453,63 @@ impl RoutingTableActor {
         msg: ValidateEdgeList,
         ctx: &mut Context<RoutingTableActor>,
     ) -> bool {
-        self.edge_validator_requests_in_progress += 1;
         let mut msg = msg;
         msg.edges.retain(|x| self.is_edge_newer(x.key(), x.nonce()));
-        let peer_id = msg.source_peer_id.clone();
-        self.edge_validator_pool
-            .send(msg)
-            .into_actor(self)
-            .map(move |res, act, _| {
-                act.edge_validator_requests_in_progress -= 1;
-
-                if let Ok(false) = res {
-                    act.peers_to_ban.push(peer_id);
-                }
-            })
-            .spawn(ctx);
-
+        let (adjacent_edges, other_edges): (Vec<_>, Vec<_>) = std::mem::take(&mut msg.edges)
+            .into_iter()
+            .partition(|edge| edge.other(self.my_peer_id()).is_some());
+        if self.num_adjacent_edges_to_validate + adjacent_edges.len()
+            > MAX_ADJACENT_EDGES_TO_VALIDATE
+        {
+            debug!(target: "network", peer_id = ?msg.source_peer_id, num_edges = adjacent_edges.len(), "Too many adjacent edges to verify, dropping");
+            metrics::EDGES_DROPPED_UNVERIFIED.inc_by(adjacent_edges.len() as u64);
+        } else if !adjacent_edges.is_empty() {
+            self.num_adjacent_edges_to_validate += adjacent_edges.len();
+            self.edges_to_validate
+                .push_front(ValidateEdgeList { edges: adjacent_edges, ..msg.clone() });
+        }
+        if self.num_edges_to_validate + other_edges.len() > MAX_EDGES_TO_VALIDATE {
+            debug!(target: "network", peer_id = ?msg.source_peer_id, num_edges = other_edges.len(), "Too many edges to verify, dropping");
+            metrics::EDGES_DROPPED_UNVERIFIED.inc_by(other_edges.len() as u64);
+        } else if !other_edges.is_empty() {
+            self.num_edges_to_validate += other_edges.len();
+            self.edges_to_validate.push_back(ValidateEdgeList { edges: other_edges, ..msg });
+        }
+        self.validate_queued_edges(ctx);
         true
     }
+
+    /// Sends the queued lists of edges to the threads of `edge_validator_pool` which are idle.
+    fn validate_queued_edges(&mut self, ctx: &mut Context<RoutingTableActor>) {
+        while self.edge_validator_requests_in_progress < EDGE_VALIDATOR_THREADS {
+            let msg = match self.edges_to_validate.pop_front() {
+                Some(msg) => msg,
+                None => break,
+            };
+            // The lists hold either only adjacent edges or none.
+            if msg.edges.iter().any(|edge| edge.other(self.my_peer_id()).is_some()) {
+                self.num_adjacent_edges_to_validate -= msg.edges.len();
+            } else {
+                self.num_edges_to_validate -= msg.edges.len();
+            }
+            self.edge_validator_requests_in_progress += 1;
+            let peer_id = msg.source_peer_id.clone();
+            self.edge_validator_pool
+                .send(msg)
+                .into_actor(self)
+                .map(move |res, act, ctx| {
+                    act.edge_validator_requests_in_progress -= 1;
+
+                    if let Ok(false) = res {
+                        act.peers_to_ban.push(peer_id);
+                    }
+                    act.validate_queued_edges(ctx);
+                })
+                .spawn(ctx);
+        }
+        metrics::EDGES_TO_VERIFY
+            .set((self.num_edges_to_validate + self.num_adjacent_edges_to_validate) as i64);
+    }
 }
 
 /// Messages for `RoutingTableActor`
@@ -551,7 +619,10 @@ impl Handler<RoutingTableMessages> for RoutingTableActor {
                 mut prune,
                 prune_edges_not_reachable_for,
             } => {
-                if prune == Prune::OncePerHour && self.edge_validator_requests_in_progress != 0 {
+                if prune == Prune::OncePerHour
+                    && (self.edge_validator_requests_in_progress != 0
+                        || !self.edges_to_validate.is_empty())
+                {
                     prune = Prune::Disable;
                 }
 
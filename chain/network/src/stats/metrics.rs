@@ -11,6 +11,13 @@ use strum::VariantNames;
 pub static PEER_CONNECTIONS_TOTAL: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_peer_connections_total", "Number of connected peers").unwrap()
 });
+pub static PORT_MAPPING_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_port_mapping_active",
+        "Whether the port the node listens on is mapped in the router",
+    )
+    .unwrap()
+});
 pub static PEER_DATA_RECEIVED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
     try_create_int_counter("near_peer_data_received_bytes", "Total data received from peers")
         .unwrap()
@@ -56,6 +63,42 @@ pub static ROUTING_TABLE_RECALCULATION_HISTOGRAM: Lazy<Histogram> = Lazy::new(||
     )
     .unwrap()
 });
+pub static EDGES_ADDED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter("near_edges_added_total", "Number of active edges added to the graph")
+        .unwrap()
+});
+pub static EDGES_REMOVED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_edges_removed_total",
+        "Number of active edges removed from the graph, by the peers or by pruning",
+    )
+    .unwrap()
+});
+pub static EDGE_VERIFICATION_HISTOGRAM: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_edge_verification_seconds",
+        "Time spent verifying the signatures of a list of edges received from a peer",
+    )
+    .unwrap()
+});
+pub static EDGES_VERIFIED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter("near_edges_verified_total", "Number of edge signatures verified")
+        .unwrap()
+});
+pub static EDGES_TO_VERIFY: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_edges_to_verify",
+        "Number of edges received from peers waiting for their signatures to be verified",
+    )
+    .unwrap()
+});
+pub static EDGES_DROPPED_UNVERIFIED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_edges_dropped_unverified_total",
+        "Number of edges received from peers dropped because too many were waiting to be verified",
+    )
+    .unwrap()
+});
 pub static EDGE_UPDATES: Lazy<IntCounter> =
     Lazy::new(|| try_create_int_counter("near_edge_updates", "Unique edge updates").unwrap());
 pub static EDGE_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
@@ -89,6 +132,13 @@ pub static DROPPED_MESSAGES_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static PEER_MESSAGE_DECODE_PANICS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_peer_message_decode_panics_total",
+        "Number of messages received from peers whose decoding panicked",
+    )
+    .unwrap()
+});
 pub static PARTIAL_ENCODED_CHUNK_REQUEST_DELAY: Lazy<Histogram> = Lazy::new(|| {
     try_create_histogram(
         "partial_encoded_chunk_request_delay",
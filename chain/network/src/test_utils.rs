@@ -420,6 +420,44 @@ pub struct SetAdvOptions {
     pub set_max_peers: Option<u64>,
 }
 
+/// Drops the given percentage of the messages sent to the peer from now on.
+#[cfg(feature = "test_features")]
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Message, Clone, Debug)]
+#[rtype(result = "()")]
+pub struct SetNetworkFaults {
+    pub peer_id: PeerId,
+    pub drop_percent: u8,
+}
+
+/// Faults injected into the connections of a node by tests, shared by its `PeerManagerActor` and
+/// `PeerActor`s.
+#[cfg(feature = "test_features")]
+#[derive(Default)]
+pub struct NetworkFaults {
+    /// Percentage of the messages to each peer which are dropped instead of being sent.
+    drop_percent: RwLock<HashMap<PeerId, u8>>,
+}
+
+#[cfg(feature = "test_features")]
+impl NetworkFaults {
+    pub fn set_drop_percent(&self, peer_id: PeerId, drop_percent: u8) {
+        let mut drop_percents = self.drop_percent.write().unwrap();
+        if drop_percent == 0 {
+            drop_percents.remove(&peer_id);
+        } else {
+            drop_percents.insert(peer_id, drop_percent.min(100));
+        }
+    }
+
+    pub(crate) fn should_drop(&self, peer_id: &PeerId) -> bool {
+        match self.drop_percent.read().unwrap().get(peer_id) {
+            Some(&drop_percent) => rand::Rng::gen_range(&mut thread_rng(), 0, 100) < drop_percent,
+            None => false,
+        }
+    }
+}
+
 #[cfg(feature = "test_features")]
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(Message, Clone, Debug)]
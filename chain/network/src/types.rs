@@ -1,6 +1,6 @@
 /// Type that belong to the network protocol.
 pub use crate::network_protocol::{
-    Handshake, HandshakeFailureReason, PeerMessage, RoutingTableUpdate,
+    Handshake, HandshakeFailureReason, PeerCapabilities, PeerMessage, RoutingTableUpdate,
 };
 #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
 pub use crate::network_protocol::{PartialSync, RoutingState, RoutingSyncV2, RoutingVersion2};
@@ -13,8 +13,9 @@ use futures::future::BoxFuture;
 use near_network_primitives::types::{
     AccountIdOrPeerTrackingShard, AccountOrPeerIdOrHash, Ban, Edge, InboundTcpConnect,
     KnownProducer, OutboundTcpConnect, PartialEdgeInfo, PartialEncodedChunkForwardMsg,
-    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo, Ping,
-    Pong, ReasonForBan, RoutedMessageBody, RoutedMessageFrom, StateResponseInfo,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerChainInfoV2,
+    PeerClientVersion, PeerInfo, Ping, Pong, ReasonForBan, RoutedMessageBody, RoutedMessageFrom,
+    ServableStateInfo, StateResponseInfo,
 };
 use near_primitives::block::{Approval, ApprovalMessage, Block, BlockHeader};
 use near_primitives::challenge::Challenge;
@@ -25,8 +26,8 @@ use near_primitives::sharding::{PartialEncodedChunk, PartialEncodedChunkWithArcR
 use near_primitives::syncing::{EpochSyncFinalizationResponse, EpochSyncResponse};
 use near_primitives::time::Instant;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{AccountId, BlockReference, EpochId, ShardId};
-use near_primitives::views::{NetworkInfoView, PeerInfoView, QueryRequest};
+use near_primitives::types::{AccountId, BlockReference, EpochId, ProtocolVersion, ShardId};
+use near_primitives::views::{NetworkInfoView, PeerInfoView, PortMappingView, QueryRequest};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use strum::AsStaticStr;
@@ -99,12 +100,16 @@ pub enum PeerManagerMessageRequest {
     InboundTcpConnect(InboundTcpConnect),
     Unregister(Unregister),
     Ban(Ban),
+    /// Disconnects from all peers and stops the peer manager, when the node is shutting down.
+    Stop,
     #[cfg(feature = "test_features")]
     #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
     StartRoutingTableSync(crate::private_actix::StartRoutingTableSync),
     #[cfg(feature = "test_features")]
     SetAdvOptions(crate::test_utils::SetAdvOptions),
     #[cfg(feature = "test_features")]
+    SetNetworkFaults(crate::test_utils::SetNetworkFaults),
+    #[cfg(feature = "test_features")]
     #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
     SetRoutingTable(crate::test_utils::SetRoutingTable),
 }
@@ -142,12 +147,15 @@ pub enum PeerManagerMessageResponse {
     InboundTcpConnect(()),
     Unregister(()),
     Ban(()),
+    Stop(()),
     #[cfg(feature = "test_features")]
     #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
     StartRoutingTableSync(()),
     #[cfg(feature = "test_features")]
     SetAdvOptions(()),
     #[cfg(feature = "test_features")]
+    SetNetworkFaults(()),
+    #[cfg(feature = "test_features")]
     #[cfg(feature = "protocol_feature_routing_exchange_algorithm")]
     SetRoutingTable(()),
 }
@@ -223,6 +231,11 @@ pub enum NetworkRequests {
     Approval {
         approval_message: ApprovalMessage,
     },
+    /// Sends several approvals to the same block producer in one message.
+    Approvals {
+        target: AccountId,
+        approvals: Vec<Approval>,
+    },
     /// Request block with given hash from given peer.
     BlockRequest {
         hash: CryptoHash,
@@ -337,6 +350,32 @@ pub struct FullPeerInfo {
     pub peer_info: PeerInfo,
     pub chain_info: PeerChainInfoV2,
     pub partial_edge_info: PartialEdgeInfo,
+    /// State the peer can serve to nodes doing state sync.
+    pub servable_state: Vec<ServableStateInfo>,
+    /// Protocol version the peer runs, from its handshake.
+    pub protocol_version: ProtocolVersion,
+    /// Version of the software the peer runs, once it told it.
+    pub client_version: Option<PeerClientVersion>,
+    /// Capabilities the peer advertised in its handshake, if its protocol version has them.
+    pub capabilities: Option<PeerCapabilities>,
+    /// Time when the connection was established.
+    pub connection_established_time: Instant,
+}
+
+impl FullPeerInfo {
+    /// Whether the peer keeps the whole history of the chain.
+    pub fn is_archival(&self) -> bool {
+        self.capabilities.map_or(self.chain_info.archival, |capabilities| {
+            capabilities.contains(PeerCapabilities::ARCHIVAL)
+        })
+    }
+
+    /// Whether the peer serves state parts.  The peers which don't advertise capabilities are
+    /// assumed to.
+    pub fn serves_state_sync(&self) -> bool {
+        self.capabilities
+            .map_or(true, |capabilities| capabilities.contains(PeerCapabilities::SERVES_STATE_SYNC))
+    }
 }
 
 impl From<&FullPeerInfo> for PeerInfoView {
@@ -365,6 +404,8 @@ pub struct NetworkInfo {
     /// Accounts of known block and chunk producers from routing table.
     pub known_producers: Vec<KnownProducer>,
     pub peer_counter: usize,
+    /// State of the mapping of the port the node listens on in the router, if enabled.
+    pub port_mapping: Option<PortMappingView>,
 }
 
 impl From<NetworkInfo> for NetworkInfoView {
@@ -416,6 +457,8 @@ pub enum NetworkClientMessages {
     BlockHeaders(Vec<BlockHeader>, PeerId),
     /// Block approval.
     BlockApproval(Approval, PeerId),
+    /// Block approvals sent together.
+    BlockApprovals(Vec<Approval>, PeerId),
     /// State response.
     StateResponse(StateResponseInfo),
     /// Epoch Sync response for light client block request
@@ -144,7 +144,7 @@ async fn network_status(
         peers: network_info
             .connected_peers
             .into_iter()
-            .map(|peer| models::Peer { peer_id: peer.id.to_string() })
+            .map(|peer| models::Peer { peer_id: peer.peer_info.id.to_string() })
             .collect(),
     }))
 }
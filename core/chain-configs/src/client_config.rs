@@ -1,10 +1,13 @@
 //! Chain Client Configuration
 use std::cmp::min;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use near_primitives::types::{AccountId, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId};
+use near_primitives::types::{
+    AccountId, BlockHeightDelta, EpochHeight, Gas, NumBlocks, NumSeats, ShardId,
+};
 use near_primitives::version::Version;
 
 pub const TEST_STATE_SYNC_TIMEOUT: u64 = 5;
@@ -17,6 +20,139 @@ pub enum LogSummaryStyle {
     Colored,
 }
 
+/// Automatic rotation of the validator key.
+///
+/// From the epoch with the given height on, a staking transaction with the next validator key and
+/// the current stake is submitted, again every few blocks until it is included.  Once the next key
+/// becomes the key of the validator (two epochs later), it is used for signing instead of the
+/// current one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyRotationConfig {
+    /// File with the validator key to switch to.
+    pub next_validator_key_file: PathBuf,
+    /// File with a full access key of the validator account, used to sign the staking
+    /// transaction.
+    pub account_key_file: PathBuf,
+    /// Height of the epoch from which on the staking transaction is submitted.
+    pub epoch_height: EpochHeight,
+}
+
+fn default_block_warning_threshold() -> u8 {
+    95
+}
+
+fn default_chunk_warning_threshold() -> u8 {
+    80
+}
+
+fn default_validator_events_check_period() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_validator_events_min_blocks() -> NumBlocks {
+    100
+}
+
+/// Events reported when the validator run by the node is at risk of being kicked out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValidatorEventsConfig {
+    /// Percentage of produced blocks out of the expected ones below which an event is reported.
+    #[serde(default = "default_block_warning_threshold")]
+    pub block_warning_threshold: u8,
+    /// Percentage of produced chunks out of the expected ones below which an event is reported.
+    #[serde(default = "default_chunk_warning_threshold")]
+    pub chunk_warning_threshold: u8,
+    /// How often the stats of the validator are checked.  They are also checked every time the
+    /// events are requested via RPC.
+    #[serde(default = "default_validator_events_check_period")]
+    pub check_period: Duration,
+    /// Number of heights the epoch has to span so far before its stats are checked, as the
+    /// first few blocks of an epoch say little about the rest of it.
+    #[serde(default = "default_validator_events_min_blocks")]
+    pub min_blocks: NumBlocks,
+    /// If set, every event is also posted as JSON to this URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+impl Default for ValidatorEventsConfig {
+    fn default() -> Self {
+        Self {
+            block_warning_threshold: default_block_warning_threshold(),
+            chunk_warning_threshold: default_chunk_warning_threshold(),
+            check_period: default_validator_events_check_period(),
+            min_blocks: default_validator_events_min_blocks(),
+            webhook_url: None,
+        }
+    }
+}
+
+/// Log lines of contract executions streamed to subscribers as they are included in new blocks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContractLogsConfig {
+    /// Prefixes of the log lines to stream, e.g. `EVENT_JSON:`.
+    pub prefixes: Vec<String>,
+    /// Accounts whose executions' logs are streamed; all accounts if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub account_ids: Vec<AccountId>,
+    /// If set, the matching logs are also appended as JSON lines to this file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<PathBuf>,
+}
+
+fn default_metrics_push_prefix() -> String {
+    "near".to_string()
+}
+
+/// Metrics pushed to a StatsD agent every time the summary is logged, in addition to being served
+/// for Prometheus to scrape.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MetricsPushConfig {
+    /// Address of the agent as `host:port`, e.g. `127.0.0.1:8125`.
+    pub address: String,
+    /// Prefix of the names of the metrics, separated from them with a dot.  No prefix if empty.
+    #[serde(default = "default_metrics_push_prefix")]
+    pub prefix: String,
+}
+
+fn default_state_parts_precompute() -> bool {
+    true
+}
+
+fn default_state_parts_disk_budget_bytes() -> u64 {
+    100_000_000_000
+}
+
+fn default_state_parts_memory_budget_bytes() -> u64 {
+    1_000_000_000
+}
+
+/// Caching of the state parts served to nodes doing state sync.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatePartsCacheConfig {
+    /// Whether the parts of the tracked shards are generated for the sync hash of the current
+    /// epoch as soon as the epoch starts instead of when they are first requested.
+    #[serde(default = "default_state_parts_precompute")]
+    pub precompute: bool,
+    /// Maximum total size of the parts generated ahead of requests.  Parts generated for previous
+    /// sync hashes are deleted when a new epoch starts.
+    #[serde(default = "default_state_parts_disk_budget_bytes")]
+    pub disk_budget_bytes: u64,
+    /// Maximum total size of the recently served parts kept in memory.
+    #[serde(default = "default_state_parts_memory_budget_bytes")]
+    pub memory_budget_bytes: u64,
+}
+
+impl Default for StatePartsCacheConfig {
+    fn default() -> Self {
+        Self {
+            precompute: default_state_parts_precompute(),
+            disk_budget_bytes: default_state_parts_disk_budget_bytes(),
+            memory_budget_bytes: default_state_parts_memory_budget_bytes(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ClientConfig {
     /// Version of the binary.
@@ -97,10 +233,51 @@ pub struct ClientConfig {
     pub view_client_throttle_period: Duration,
     /// Upper bound of the byte size of contract state that is still viewable. None is no limit
     pub trie_viewer_state_size_limit: Option<u64>,
+    /// Maximum time a view_state query may run for before it is cancelled, so that it doesn't
+    /// occupy a ViewClientActor thread for long. None is no limit
+    pub view_query_timeout: Option<Duration>,
     /// Max burnt gas per view method.  If present, overrides value stored in
     /// genesis file.  The value only affects the RPCs without influencing the
     /// protocol thus changing it per-node doesn’t affect the blockchain.
     pub max_gas_burnt_view: Option<Gas>,
+    /// If set, gas burnt in applied chunks is attributed to accounts and this many heaviest
+    /// accounts of every epoch are tracked.
+    pub gas_attribution_top_k: Option<usize>,
+    /// If set and below the gas limit of the chunk, transactions of the produced chunks are
+    /// selected as if the gas limit were this.
+    pub produced_chunk_gas_limit: Option<Gas>,
+    /// If set, produced chunks include at most this many transactions.
+    pub produced_chunk_max_transactions: Option<usize>,
+    /// Transactions which the node doesn't include itself are forwarded to the chunk producers
+    /// of their shard at this many upcoming heights, and at twice as far.
+    pub tx_routing_height_horizon: BlockHeightDelta,
+    /// If set, the approvals sent to the same block producer at once are coalesced into one
+    /// message, which peers older than this option can't decode.
+    pub approval_batching: bool,
+    /// Epoch height from which the cumulative uptime of the validator is counted.  If not set,
+    /// it's counted from the first epoch the node saw end.
+    pub uptime_tracking_start_epoch_height: Option<EpochHeight>,
+    /// If set, the validator key is only used to produce blocks, chunks and approvals locally,
+    /// which are compared with the published ones instead of being broadcast.
+    pub shadow_validation: bool,
+    /// If set, the validator key is rotated automatically.
+    pub key_rotation: Option<KeyRotationConfig>,
+    /// If set, events are reported when the validator misses blocks or chunks or is projected to
+    /// be kicked out.
+    pub validator_events: Option<ValidatorEventsConfig>,
+    /// If set, matching log lines of contract executions are streamed to subscribers.
+    pub contract_logs: Option<ContractLogsConfig>,
+    /// If set, state parts served to other nodes are cached in memory and optionally generated
+    /// ahead of requests.
+    pub state_parts_cache: Option<StatePartsCacheConfig>,
+    /// If set, only block headers and epoch info are synced, validated and stored, and the head
+    /// of the chain follows the header head.  Chunks are never requested and no state is kept.
+    pub header_only: bool,
+    /// If set, the transactions and receipts executed on every account are indexed, so that the
+    /// history of an account can be paged through over RPC.
+    pub account_history_index: bool,
+    /// If set, the metrics are also pushed to a StatsD agent.
+    pub metrics_push: Option<MetricsPushConfig>,
 }
 
 impl ClientConfig {
@@ -158,7 +335,22 @@ impl ClientConfig {
             epoch_sync_enabled,
             view_client_throttle_period: Duration::from_secs(1),
             trie_viewer_state_size_limit: None,
+            view_query_timeout: None,
             max_gas_burnt_view: None,
+            gas_attribution_top_k: None,
+            produced_chunk_gas_limit: None,
+            produced_chunk_max_transactions: None,
+            tx_routing_height_horizon: 4,
+            approval_batching: false,
+            uptime_tracking_start_epoch_height: None,
+            shadow_validation: false,
+            key_rotation: None,
+            validator_events: None,
+            contract_logs: None,
+            state_parts_cache: None,
+            header_only: false,
+            account_history_index: false,
+            metrics_push: None,
         }
     }
 }
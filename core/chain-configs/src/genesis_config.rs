@@ -181,6 +181,10 @@ pub struct GenesisConfig {
     #[serde(default = "default_minimum_stake_ratio")]
     #[default(Rational::new(160, 1_000_000))]
     pub minimum_stake_ratio: Rational,
+    /// Directory with overrides of the runtime parameters per protocol version, relative to the
+    /// home directory.  Only for custom networks, see `runtime_config_overrides`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_config_overrides_dir: Option<PathBuf>,
 }
 
 impl From<&GenesisConfig> for EpochConfig {
@@ -558,9 +562,15 @@ impl Genesis {
         hasher.finalize()
     }
 
+    /// Streams records from `records_file`, which is decompressed if its extension is `xz`.
     fn stream_records_with_callback(&self, callback: impl FnMut(StateRecord)) -> io::Result<()> {
-        let reader = BufReader::new(File::open(&self.records_file)?);
-        stream_records_from_file(reader, callback).map_err(io::Error::from)
+        let file = File::open(&self.records_file)?;
+        let result = if self.records_file.extension().map_or(false, |ext| ext == "xz") {
+            stream_records_from_file(BufReader::new(xz2::read::XzDecoder::new(file)), callback)
+        } else {
+            stream_records_from_file(BufReader::new(file), callback)
+        };
+        result.map_err(io::Error::from)
     }
 
     /// If records vector is empty processes records stream from records_file.
@@ -1,9 +1,14 @@
 mod client_config;
 mod genesis_config;
 pub mod genesis_validate;
+mod runtime_config_overrides;
 
-pub use client_config::{ClientConfig, LogSummaryStyle, TEST_STATE_SYNC_TIMEOUT};
+pub use client_config::{
+    ClientConfig, ContractLogsConfig, KeyRotationConfig, LogSummaryStyle, MetricsPushConfig,
+    StatePartsCacheConfig, ValidatorEventsConfig, TEST_STATE_SYNC_TIMEOUT,
+};
 pub use genesis_config::{
     get_initial_supply, Genesis, GenesisConfig, GenesisRecords, GenesisValidationMode,
     ProtocolConfig, ProtocolConfigView,
 };
+pub use runtime_config_overrides::{apply_runtime_config_overrides, load_runtime_config_overrides};
@@ -0,0 +1,142 @@
+//! Overrides of the runtime parameters for custom networks, so that private chains can tune the
+//! gas costs and limits without changes to the code.
+//!
+//! The overrides are JSON files named `<protocol_version>.json` in the directory given by
+//! `runtime_config_overrides_dir` of the genesis config.  Each file contains the subset of the
+//! fields of `RuntimeConfig` to change, nested the same way, e.g.
+//! `{"wasm_config": {"limit_config": {"max_gas_burnt": 500000000000000}}}`.  An override applies
+//! from its protocol version on, on top of the built-in config of every such version and of the
+//! overrides of the lower versions.
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use near_primitives::runtime::config_store::RuntimeConfigStore;
+use near_primitives::types::ProtocolVersion;
+use serde_json::Value;
+
+/// Reads the overrides from the directory, ordered by protocol version.
+pub fn load_runtime_config_overrides(dir: &Path) -> anyhow::Result<Vec<(ProtocolVersion, Value)>> {
+    let mut overrides = vec![];
+    for entry in std::fs::read_dir(dir).with_context(|| {
+        format!("Failed to read runtime config overrides from {}", dir.display())
+    })? {
+        let path = entry?.path();
+        let protocol_version = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|version| version.parse::<ProtocolVersion>().ok())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Runtime config override {} isn't named <protocol_version>.json",
+                    path.display()
+                )
+            })?;
+        let contents = std::fs::read(&path).with_context(|| {
+            format!("Failed to read runtime config override {}", path.display())
+        })?;
+        let value = serde_json::from_slice(&contents).with_context(|| {
+            format!("Failed to parse runtime config override {}", path.display())
+        })?;
+        overrides.push((protocol_version, value));
+    }
+    overrides.sort_by_key(|(protocol_version, _)| *protocol_version);
+    Ok(overrides)
+}
+
+/// Applies the overrides, ordered by protocol version, to the configs of the store.
+pub fn apply_runtime_config_overrides(
+    store: &RuntimeConfigStore,
+    overrides: &[(ProtocolVersion, Value)],
+) -> anyhow::Result<RuntimeConfigStore> {
+    let mut protocol_versions: Vec<_> = store.configs().map(|(version, _)| version).collect();
+    protocol_versions.extend(overrides.iter().map(|(version, _)| *version));
+    protocol_versions.sort();
+    protocol_versions.dedup();
+
+    let mut configs = BTreeMap::new();
+    for protocol_version in protocol_versions {
+        let mut config = serde_json::to_value(store.get_config(protocol_version).as_ref())?;
+        for (override_version, patch) in overrides {
+            if *override_version > protocol_version {
+                break;
+            }
+            merge(&mut config, patch, "").with_context(|| {
+                format!("Invalid runtime config override for protocol version {}", override_version)
+            })?;
+        }
+        let config = serde_json::from_value(config).with_context(|| {
+            format!(
+                "Invalid runtime config for protocol version {} after overrides",
+                protocol_version
+            )
+        })?;
+        configs.insert(protocol_version, Arc::new(config));
+    }
+    Ok(RuntimeConfigStore::from_configs(configs))
+}
+
+/// Replaces the fields of `base` with those of `patch`, merging the nested objects.  Fails on
+/// fields `base` doesn't have, so that typos don't go unnoticed.
+fn merge(base: &mut Value, patch: &Value, path: &str) -> anyhow::Result<()> {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                let field = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                match base.get_mut(key) {
+                    Some(base_value) => merge(base_value, value, &field)?,
+                    None => bail!("RuntimeConfig has no field {}", field),
+                }
+            }
+        }
+        (base @ Value::Object(_), _) => bail!("{} must be an object, not {}", path, base),
+        (base, patch) => *base = patch.clone(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::runtime::config_store::RuntimeConfigStore;
+    use serde_json::json;
+
+    use super::apply_runtime_config_overrides;
+
+    #[test]
+    fn test_runtime_config_overrides() {
+        let store = RuntimeConfigStore::new(None);
+        let max_gas_burnt = |store: &RuntimeConfigStore, protocol_version| {
+            store.get_config(protocol_version).wasm_config.limit_config.max_gas_burnt
+        };
+        let overrides = vec![
+            (45, json!({"wasm_config": {"limit_config": {"max_gas_burnt": 1}}})),
+            (51, json!({"account_creation_config": {"min_allowed_top_level_account_length": 2}})),
+        ];
+        let overridden = apply_runtime_config_overrides(&store, &overrides).unwrap();
+        assert_eq!(overridden.get_config(44), store.get_config(44));
+        assert_eq!(max_gas_burnt(&overridden, 45), 1);
+        // The override of 45 applies on top of the built-in config of 52 as well.
+        assert_eq!(max_gas_burnt(&overridden, 51), 1);
+        assert_eq!(max_gas_burnt(&overridden, 52), 1);
+        assert_eq!(
+            overridden.get_config(52).account_creation_config.min_allowed_top_level_account_length,
+            2
+        );
+        assert_eq!(
+            overridden.get_config(52).transaction_costs,
+            store.get_config(52).transaction_costs
+        );
+
+        let typo = vec![(45, json!({"wasm_config": {"limit_config": {"max_gas_brunt": 1}}}))];
+        let err = apply_runtime_config_overrides(&store, &typo).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("wasm_config.limit_config.max_gas_brunt"),
+            "{:#}",
+            err
+        );
+        let not_object = vec![(45, json!({"wasm_config": 1}))];
+        assert!(apply_runtime_config_overrides(&store, &not_object).is_err());
+    }
+}
@@ -59,13 +59,15 @@
 //! ```
 
 pub use prometheus::{
-    Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Result,
-    TextEncoder,
+    Encoder, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    Result, TextEncoder,
 };
-use prometheus::{GaugeVec, HistogramOpts, HistogramTimer, Opts};
+use prometheus::{HistogramOpts, HistogramTimer, Opts};
 
 use tracing::error;
 
+pub mod push;
+
 /// Collect all the metrics for reporting.
 pub fn gather() -> Vec<prometheus::proto::MetricFamily> {
     prometheus::gather()
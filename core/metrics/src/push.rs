@@ -0,0 +1,150 @@
+//! Pushing the metrics of the global registry to a StatsD agent, for the nodes which the
+//! Prometheus server can't scrape, e.g. those behind a NAT.
+//!
+//! Every push sends all the metrics over UDP in the StatsD line format: gauges as gauges, counters
+//! as counters incremented by the growth since the previous push, and histograms and summaries as
+//! the `count` and `sum` gauges.  The labels of a metric are sent as DogStatsD tags, which most
+//! agents (Datadog, Telegraf, statsd_exporter) understand.
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use prometheus::proto::{MetricFamily, MetricType};
+
+/// Datagrams are kept within the usual MTU so that they're not fragmented.
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+pub struct StatsdExporter {
+    socket: UdpSocket,
+    prefix: String,
+    /// Values of the counters at the previous push, by their names and tags.
+    counters: HashMap<String, f64>,
+}
+
+impl StatsdExporter {
+    /// Resolves the address of the agent, given as `host:port`, once for all the pushes.
+    pub fn new(address: &str, prefix: &str) -> io::Result<Self> {
+        let address = address.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{} doesn't resolve", address))
+        })?;
+        let local_address: SocketAddr =
+            if address.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local_address)?;
+        socket.connect(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, prefix: prefix.to_string(), counters: HashMap::new() })
+    }
+
+    /// Sends the current values of all the metrics.
+    pub fn push(&mut self) -> io::Result<()> {
+        let lines = self.lines(&crate::gather());
+        let mut datagram = String::new();
+        for line in lines {
+            if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM_SIZE {
+                self.socket.send(datagram.as_bytes())?;
+                datagram.clear();
+            }
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+            datagram.push_str(&line);
+        }
+        if !datagram.is_empty() {
+            self.socket.send(datagram.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn lines(&mut self, families: &[MetricFamily]) -> Vec<String> {
+        let mut lines = vec![];
+        for family in families {
+            let name = if self.prefix.is_empty() {
+                family.get_name().to_string()
+            } else {
+                format!("{}.{}", self.prefix, family.get_name())
+            };
+            for metric in family.get_metric() {
+                let tags = metric
+                    .get_label()
+                    .iter()
+                    .map(|label| format!("{}:{}", label.get_name(), label.get_value()))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let tags = if tags.is_empty() { tags } else { format!("|#{}", tags) };
+                match family.get_field_type() {
+                    MetricType::COUNTER => {
+                        let value = metric.get_counter().get_value();
+                        let key = format!("{}{}", name, tags);
+                        // Counters which went down were reset, so all of their value is new.
+                        let previous = self.counters.insert(key, value).unwrap_or(0.0);
+                        let delta = if value >= previous { value - previous } else { value };
+                        lines.push(format!("{}:{}|c{}", name, delta, tags));
+                    }
+                    MetricType::GAUGE => {
+                        lines.push(gauge(&name, metric.get_gauge().get_value(), &tags));
+                    }
+                    MetricType::UNTYPED => {
+                        lines.push(gauge(&name, metric.get_untyped().get_value(), &tags));
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let count = histogram.get_sample_count() as f64;
+                        lines.push(gauge(&format!("{}.count", name), count, &tags));
+                        let sum = histogram.get_sample_sum();
+                        lines.push(gauge(&format!("{}.sum", name), sum, &tags));
+                    }
+                    MetricType::SUMMARY => {
+                        let summary = metric.get_summary();
+                        let count = summary.get_sample_count() as f64;
+                        lines.push(gauge(&format!("{}.count", name), count, &tags));
+                        let sum = summary.get_sample_sum();
+                        lines.push(gauge(&format!("{}.sum", name), sum, &tags));
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+
+fn gauge(name: &str, value: f64, tags: &str) -> String {
+    // StatsD reads a leading sign as a change of the gauge rather than its value, so negative
+    // values are sent as a reset to zero followed by the change.
+    if value < 0.0 {
+        format!("{}:0|g{}\n{}:{}|g{}", name, tags, name, value, tags)
+    } else {
+        format!("{}:{}|g{}", name, value, tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{IntCounterVec, IntGauge, Opts, Registry};
+
+    use super::StatsdExporter;
+
+    #[test]
+    fn test_statsd_lines() {
+        let registry = Registry::new();
+        let counter =
+            IntCounterVec::new(Opts::new("requests_total", "Requests"), &["method"]).unwrap();
+        let gauge = IntGauge::new("peers", "Peers").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(gauge.clone())).unwrap();
+        let mut exporter = StatsdExporter::new("127.0.0.1:8125", "near").unwrap();
+
+        counter.with_label_values(&["query"]).inc_by(5);
+        gauge.set(-3);
+        assert_eq!(
+            exporter.lines(&registry.gather()),
+            ["near.peers:0|g\nnear.peers:-3|g", "near.requests_total:5|c|#method:query"]
+        );
+
+        counter.with_label_values(&["query"]).inc_by(2);
+        gauge.set(7);
+        assert_eq!(
+            exporter.lines(&registry.gather()),
+            ["near.peers:7|g", "near.requests_total:2|c|#method:query"]
+        );
+    }
+}
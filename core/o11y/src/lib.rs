@@ -3,8 +3,15 @@
 pub use {tracing, tracing_appender, tracing_subscriber};
 
 use std::borrow::Cow;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use tracing_subscriber::EnvFilter;
+use once_cell::sync::Lazy;
+use opentelemetry::sdk::trace::Tracer;
+use opentelemetry_otlp::WithExportConfig;
+use tracing_subscriber::filter::{Directive, ParseError};
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 /// The default value for the `RUST_LOG` environment variable if one isn't specified otherwise.
 pub const DEFAULT_RUST_LOG: &'static str = "tokio_reactor=info,\
@@ -31,9 +38,30 @@ pub struct DefaultSubcriberGuard {
     // other way around, the events/spans generated while the subscriber drop guard runs would be
     // lost.
     subscriber_guard: tracing::subscriber::DefaultGuard,
+    opentelemetry_guard: Option<OpenTelemetryGuard>,
     writer_guard: tracing_appender::non_blocking::WorkerGuard,
 }
 
+/// Keeps the runtime exporting the spans to OpenTelemetry alive, and flushes the spans not
+/// exported yet when dropped.
+struct OpenTelemetryGuard {
+    _runtime: tokio::runtime::Runtime,
+}
+
+impl Drop for OpenTelemetryGuard {
+    fn drop(&mut self) {
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum OpenTelemetryError {
+    #[error("failed to start the OpenTelemetry runtime: {0}")]
+    Runtime(#[from] std::io::Error),
+    #[error("failed to set up the OTLP exporter: {0}")]
+    Trace(#[from] opentelemetry::trace::TraceError),
+}
+
 /// Run the code with a default subscriber set to the option appropriate for the NEAR code.
 ///
 /// This will override any subscribers set until now, and will be in effect until the value
@@ -47,24 +75,194 @@ pub struct DefaultSubcriberGuard {
 /// near_o11y::tracing::info!(message = "Still a lot of work remains to make it proper o11y");
 /// ```
 pub fn default_subscriber(log_filter: EnvFilter) -> DefaultSubcriberGuard {
+    subscriber(log_filter, None)
+}
+
+/// Same as [`default_subscriber`], but also exports the spans passing `otlp_filter` to an
+/// OpenTelemetry collector over OTLP/gRPC, e.g. at `http://localhost:4317`.  The spans exported
+/// don't depend on the log filter, so that changing the latter doesn't affect the traces.
+pub fn default_subscriber_with_opentelemetry(
+    log_filter: EnvFilter,
+    otlp_filter: EnvFilter,
+    service_name: &str,
+    otlp_endpoint: &str,
+) -> Result<DefaultSubcriberGuard, OpenTelemetryError> {
+    // The exporter needs a Tokio runtime, which the caller may not have started yet.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("opentelemetry")
+        .enable_all()
+        .build()?;
+    let tracer = {
+        let _runtime_guard = runtime.enter();
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(otlp_endpoint))
+            .with_trace_config(opentelemetry::sdk::trace::config().with_resource(
+                opentelemetry::sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+                    "service.name",
+                    service_name.to_string(),
+                )]),
+            ))
+            .install_batch(opentelemetry::runtime::Tokio)?
+    };
+    Ok(subscriber(
+        log_filter,
+        Some((tracer, otlp_filter, OpenTelemetryGuard { _runtime: runtime })),
+    ))
+}
+
+fn subscriber(
+    log_filter: EnvFilter,
+    opentelemetry: Option<(Tracer, EnvFilter, OpenTelemetryGuard)>,
+) -> DefaultSubcriberGuard {
+    let (opentelemetry_layer, opentelemetry_guard) = match opentelemetry {
+        Some((tracer, otlp_filter, guard)) => (
+            Some(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(otlp_filter)),
+            Some(guard),
+        ),
+        None => (None, None),
+    };
     // Do not lock the `stderr` here to allow for things like `dbg!()` work during development.
     let stderr = std::io::stderr();
     let lined_stderr = std::io::LineWriter::new(stderr);
     let (writer, writer_guard) = tracing_appender::non_blocking(lined_stderr);
-    let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        .with_span_events(
-            tracing_subscriber::fmt::format::FmtSpan::ENTER
-                | tracing_subscriber::fmt::format::FmtSpan::CLOSE,
+    let directives = log_filter.to_string();
+    // Each layer has a filter of its own rather than the log filter applying to all of them.
+    let (log_filter, handle) = reload::Layer::new(log_filter);
+    let subscriber = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_span_events(
+                    tracing_subscriber::fmt::format::FmtSpan::ENTER
+                        | tracing_subscriber::fmt::format::FmtSpan::CLOSE,
+                )
+                .with_writer(writer)
+                .with_filter(log_filter),
         )
-        .with_env_filter(log_filter)
-        .with_writer(writer)
-        .finish();
+        .with(opentelemetry_layer);
+    *LOG_FILTER.lock().unwrap() =
+        Some(ReloadableLogFilter { handle, directives, temporary_directives: vec![] });
     DefaultSubcriberGuard {
         subscriber_guard: tracing::subscriber::set_default(subscriber),
+        opentelemetry_guard,
         writer_guard,
     }
 }
 
+/// Log filter of the subscriber set up by [`default_subscriber`], which can be changed while the
+/// node runs.
+struct ReloadableLogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives in the `RUST_LOG` format.
+    directives: String,
+    /// Directives applied on top of `directives` with the time they expire at.
+    temporary_directives: Vec<(String, Instant)>,
+}
+
+impl ReloadableLogFilter {
+    fn reload(&mut self) -> Result<(), LogFilterError> {
+        let now = Instant::now();
+        self.temporary_directives.retain(|(_, expires_at)| *expires_at > now);
+        let mut env_filter = EnvFilter::try_new(&self.directives)?;
+        for (directive, _) in &self.temporary_directives {
+            env_filter = env_filter.add_directive(directive.parse()?);
+        }
+        Ok(self.handle.reload(env_filter)?)
+    }
+
+    fn status(&self) -> LogFilterStatus {
+        let now = Instant::now();
+        LogFilterStatus {
+            directives: self.directives.clone(),
+            temporary_directives: self
+                .temporary_directives
+                .iter()
+                .map(|(directive, expires_at)| {
+                    (directive.clone(), expires_at.saturating_duration_since(now))
+                })
+                .collect(),
+        }
+    }
+}
+
+static LOG_FILTER: Lazy<Mutex<Option<ReloadableLogFilter>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(thiserror::Error, Debug)]
+pub enum LogFilterError {
+    #[error("no subscriber with a reloadable log filter is set up")]
+    NoSubscriber,
+    #[error("invalid log filter directive: {0}")]
+    InvalidDirective(#[from] ParseError),
+    #[error("failed to reload the log filter: {0}")]
+    Reload(#[from] reload::Error),
+}
+
+/// Log filter in effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilterStatus {
+    /// Directives in the `RUST_LOG` format.
+    pub directives: String,
+    /// Directives applied on top of `directives`, with the time left until they expire.
+    pub temporary_directives: Vec<(String, Duration)>,
+}
+
+fn with_log_filter<T>(
+    f: impl FnOnce(&mut ReloadableLogFilter) -> Result<T, LogFilterError>,
+) -> Result<T, LogFilterError> {
+    match LOG_FILTER.lock().unwrap().as_mut() {
+        Some(log_filter) => f(log_filter),
+        None => Err(LogFilterError::NoSubscriber),
+    }
+}
+
+/// Returns the log filter of the subscriber set up by [`default_subscriber`].
+pub fn log_filter() -> Result<LogFilterStatus, LogFilterError> {
+    with_log_filter(|log_filter| Ok(log_filter.status()))
+}
+
+/// Replaces the directives of the log filter of the subscriber set up by [`default_subscriber`]
+/// with the given ones, in the `RUST_LOG` format.  Temporary directives stay in effect until they
+/// expire.
+pub fn set_log_filter(directives: &str) -> Result<LogFilterStatus, LogFilterError> {
+    // Validate before touching the filter in effect.
+    EnvFilter::try_new(directives)?;
+    with_log_filter(|log_filter| {
+        log_filter.directives = directives.to_string();
+        log_filter.reload()?;
+        Ok(log_filter.status())
+    })
+}
+
+/// Applies the given directives, in the `RUST_LOG` format, on top of the log filter of the
+/// subscriber set up by [`default_subscriber`] for the given duration, e.g. `network=debug` to
+/// debug the network for a while without restarting the node.
+pub fn add_temporary_log_filter(
+    directives: &str,
+    duration: Duration,
+) -> Result<LogFilterStatus, LogFilterError> {
+    let directives = directives
+        .split(',')
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| directive.parse::<Directive>().map(|_| directive.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let expires_at = Instant::now() + duration;
+    let status = with_log_filter(|log_filter| {
+        log_filter
+            .temporary_directives
+            .extend(directives.into_iter().map(|directive| (directive, expires_at)));
+        log_filter.reload()?;
+        Ok(log_filter.status())
+    })?;
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        if let Err(err) = with_log_filter(|log_filter| log_filter.reload()) {
+            tracing::warn!(target: "o11y", "Failed to expire temporary log filter: {}", err);
+        }
+    });
+    Ok(status)
+}
+
 pub struct EnvFilterBuilder<'a> {
     rust_log: Cow<'a, str>,
     verbose: Option<Cow<'a, str>>,
@@ -114,3 +312,29 @@ impl<'a> EnvFilterBuilder<'a> {
         env_filter
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_reload_log_filter() {
+        let _subscriber = default_subscriber(EnvFilter::new("info"));
+        assert!(matches!(set_log_filter("near=nope"), Err(LogFilterError::InvalidDirective(_))));
+        assert_eq!(log_filter().unwrap().directives, "info");
+
+        let status = set_log_filter("warn,near=info").unwrap();
+        assert_eq!(status.directives, "warn,near=info");
+
+        let status = add_temporary_log_filter("network=debug", Duration::from_millis(100)).unwrap();
+        assert_eq!(status.temporary_directives.len(), 1);
+        assert_eq!(status.temporary_directives[0].0, "network=debug");
+
+        std::thread::sleep(Duration::from_millis(500));
+        let status = log_filter().unwrap();
+        assert_eq!(status.directives, "warn,near=info");
+        assert!(status.temporary_directives.is_empty());
+    }
+}
@@ -390,12 +390,36 @@ pub struct ExtCostsConfig {
     /// Cost for pairing check per byte
     #[cfg(feature = "protocol_feature_alt_bn128")]
     pub alt_bn128_pairing_check_byte: Gas,
+
+    // ###############
+    // # Precompiles #
+    // ###############
+    /// Base cost of calling the `sha512` precompile
+    #[cfg(feature = "protocol_feature_precompiles")]
+    #[serde(default = "default_sha512_base")]
+    pub sha512_base: Gas,
+    /// Cost of the `sha512` precompile per byte of input
+    #[cfg(feature = "protocol_feature_precompiles")]
+    #[serde(default = "default_sha512_byte")]
+    pub sha512_byte: Gas,
 }
 
 // We multiply the actual computed costs by the fixed factor to ensure we
 // have certain reserve for further gas price variation.
 const SAFETY_MULTIPLIER: u64 = 3;
 
+// The precompile costs aren't in the runtime parameter files of the released protocol versions,
+// which must not change.
+#[cfg(feature = "protocol_feature_precompiles")]
+fn default_sha512_base() -> Gas {
+    SAFETY_MULTIPLIER * 3016516777
+}
+
+#[cfg(feature = "protocol_feature_precompiles")]
+fn default_sha512_byte() -> Gas {
+    SAFETY_MULTIPLIER * 14176907
+}
+
 impl ExtCostsConfig {
     pub fn test() -> ExtCostsConfig {
         ExtCostsConfig {
@@ -466,6 +490,10 @@ impl ExtCostsConfig {
             alt_bn128_g1_sum_base: SAFETY_MULTIPLIER * 1058438125,
             #[cfg(feature = "protocol_feature_alt_bn128")]
             alt_bn128_g1_sum_byte: SAFETY_MULTIPLIER * 25406181,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_base: default_sha512_base(),
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_byte: default_sha512_byte(),
         }
     }
 
@@ -537,6 +565,10 @@ impl ExtCostsConfig {
             alt_bn128_g1_sum_base: 0,
             #[cfg(feature = "protocol_feature_alt_bn128")]
             alt_bn128_g1_sum_byte: 0,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_base: 0,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_byte: 0,
         }
     }
 }
@@ -611,6 +643,10 @@ pub enum ExtCosts {
     alt_bn128_g1_sum_base,
     #[cfg(feature = "protocol_feature_alt_bn128")]
     alt_bn128_g1_sum_byte,
+    #[cfg(feature = "protocol_feature_precompiles")]
+    sha512_base,
+    #[cfg(feature = "protocol_feature_precompiles")]
+    sha512_byte,
 
     // NOTE: this should be the last element of the enum.
     __count,
@@ -738,6 +774,10 @@ impl ExtCosts {
             alt_bn128_g1_sum_base => config.alt_bn128_g1_sum_base,
             #[cfg(feature = "protocol_feature_alt_bn128")]
             alt_bn128_g1_sum_byte => config.alt_bn128_g1_sum_byte,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_base => config.sha512_base,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            sha512_byte => config.sha512_byte,
 
             __count => unreachable!(),
         }
@@ -815,6 +855,10 @@ impl ExtCosts {
             "alt_bn128_g1_sum_base",
             #[cfg(feature = "protocol_feature_alt_bn128")]
             "alt_bn128_g1_sum_byte",
+            #[cfg(feature = "protocol_feature_precompiles")]
+            "sha512_base",
+            #[cfg(feature = "protocol_feature_precompiles")]
+            "sha512_byte",
         ][index]
     }
 }
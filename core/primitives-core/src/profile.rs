@@ -9,7 +9,12 @@ use crate::config::{ActionCosts, ExtCosts};
 pub struct DataArray(Box<[u64; Self::LEN]>);
 
 impl DataArray {
+    #[cfg(not(feature = "protocol_feature_precompiles"))]
     pub const LEN: usize = Cost::ALL.len();
+    // The precompile costs come after the slots of the alt_bn128 costs, which are reserved even
+    // when those are disabled.
+    #[cfg(feature = "protocol_feature_precompiles")]
+    pub const LEN: usize = 72;
 }
 
 impl Index<usize> for DataArray {
@@ -259,6 +264,10 @@ impl Cost {
         Cost::ExtCost { ext_cost_kind: ExtCosts::alt_bn128_g1_sum_base },
         #[cfg(feature = "protocol_feature_alt_bn128")]
         Cost::ExtCost { ext_cost_kind: ExtCosts::alt_bn128_g1_sum_byte },
+        #[cfg(feature = "protocol_feature_precompiles")]
+        Cost::ExtCost { ext_cost_kind: ExtCosts::sha512_base },
+        #[cfg(feature = "protocol_feature_precompiles")]
+        Cost::ExtCost { ext_cost_kind: ExtCosts::sha512_byte },
     ];
 
     pub fn index(self) -> usize {
@@ -341,6 +350,10 @@ impl Cost {
             Cost::ExtCost { ext_cost_kind: ExtCosts::alt_bn128_g1_sum_base } => 68,
             #[cfg(feature = "protocol_feature_alt_bn128")]
             Cost::ExtCost { ext_cost_kind: ExtCosts::alt_bn128_g1_sum_byte } => 69,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            Cost::ExtCost { ext_cost_kind: ExtCosts::sha512_base } => 70,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            Cost::ExtCost { ext_cost_kind: ExtCosts::sha512_byte } => 71,
             Cost::ExtCost { ext_cost_kind: ExtCosts::__count } => unreachable!(),
         }
     }
@@ -400,4 +413,13 @@ mod test {
         assert_eq!(profile_data.get_action_cost(ActionCosts::function_call), 333);
         assert_eq!(profile_data.get_ext_cost(ExtCosts::storage_read_base), 33);
     }
+
+    #[test]
+    fn test_cost_indices() {
+        let mut indices: Vec<usize> = Cost::ALL.iter().map(|cost| cost.index()).collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), Cost::ALL.len());
+        assert!(indices.iter().all(|index| *index < DataArray::LEN));
+    }
 }
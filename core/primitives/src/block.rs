@@ -22,7 +22,7 @@ use crate::sharding::{
 };
 use crate::types::{Balance, BlockHeight, EpochId, Gas, NumBlocks, NumShards, StateRoot};
 use crate::utils::to_timestamp;
-use crate::validator_signer::{EmptyValidatorSigner, ValidatorSigner};
+use crate::validator_signer::{EmptyValidatorSigner, SignerError, ValidatorSigner};
 use crate::version::{ProtocolVersion, SHARD_CHUNK_HEADER_UPGRADE_VERSION};
 use std::ops::Index;
 
@@ -214,7 +214,7 @@ impl Block {
         signer: &dyn ValidatorSigner,
         next_bp_hash: CryptoHash,
         block_merkle_root: CryptoHash,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         // Collect aggregate of validators and gas usage/limits from chunks.
         let mut validator_proposals = vec![];
         let mut gas_used = 0;
@@ -246,7 +246,7 @@ impl Block {
         let now = to_timestamp(Clock::utc());
         let time = if now <= prev.raw_timestamp() { prev.raw_timestamp() + 1 } else { now };
 
-        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value().as_ref());
+        let (vrf_value, vrf_proof) = signer.compute_vrf_with_proof(prev.random_value().as_ref())?;
         let random_value = hash(vrf_value.0.as_ref());
 
         let last_ds_final_block =
@@ -296,16 +296,16 @@ impl Block {
             next_bp_hash,
             block_merkle_root,
             prev.height(),
-        );
+        )?;
 
-        Self::block_from_protocol_version(
+        Ok(Self::block_from_protocol_version(
             next_epoch_protocol_version,
             header,
             chunks,
             challenges,
             vrf_value,
             vrf_proof,
-        )
+        ))
     }
 
     pub fn verify_gas_price(
@@ -449,6 +449,14 @@ impl Block {
         }
     }
 
+    /// See [`BlockHeader::set_signature`].
+    pub fn set_signature(&mut self, signature: Signature) {
+        match self {
+            Block::BlockV1(block) => block.header.set_signature(signature),
+            Block::BlockV2(block) => block.header.set_signature(signature),
+        }
+    }
+
     pub fn chunks(&self) -> ChunksCollection {
         match self {
             Block::BlockV1(block) => ChunksCollection::V1(
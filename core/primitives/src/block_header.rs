@@ -12,7 +12,7 @@ use crate::network::PeerId;
 use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter, ValidatorStakeV1};
 use crate::types::{AccountId, Balance, BlockHeight, EpochId, MerkleHash, NumBlocks};
 use crate::utils::{from_timestamp, to_timestamp};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use crate::version::{get_protocol_version, ProtocolVersion, PROTOCOL_VERSION};
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -211,10 +211,10 @@ impl Approval {
         parent_height: BlockHeight,
         target_height: BlockHeight,
         signer: &dyn ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ApprovalInner::new(&parent_hash, parent_height, target_height);
-        let signature = signer.sign_approval(&inner, target_height);
-        Approval { inner, target_height, signature, account_id: signer.validator_id().clone() }
+        let signature = signer.sign_approval(&inner, target_height)?;
+        Ok(Approval { inner, target_height, signature, account_id: signer.validator_id().clone() })
     }
 
     pub fn get_data_for_sig(inner: &ApprovalInner, target_height: BlockHeight) -> Vec<u8> {
@@ -370,7 +370,7 @@ impl BlockHeader {
         next_bp_hash: CryptoHash,
         block_merkle_root: CryptoHash,
         prev_height: BlockHeight,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner_lite = BlockHeaderInnerLite {
             height,
             epoch_id,
@@ -408,14 +408,14 @@ impl BlockHeader {
                 prev_hash,
                 &inner_lite.try_to_vec().expect("Failed to serialize"),
                 &inner_rest.try_to_vec().expect("Failed to serialize"),
-            );
-            Self::BlockHeaderV1(Box::new(BlockHeaderV1 {
+            )?;
+            Ok(Self::BlockHeaderV1(Box::new(BlockHeaderV1 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else if this_epoch_protocol_version <= last_header_v2_version {
             let inner_rest = BlockHeaderInnerRestV2 {
                 chunk_receipts_root,
@@ -437,14 +437,14 @@ impl BlockHeader {
                 prev_hash,
                 &inner_lite.try_to_vec().expect("Failed to serialize"),
                 &inner_rest.try_to_vec().expect("Failed to serialize"),
-            );
-            Self::BlockHeaderV2(Box::new(BlockHeaderV2 {
+            )?;
+            Ok(Self::BlockHeaderV2(Box::new(BlockHeaderV2 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         } else {
             let inner_rest = BlockHeaderInnerRestV3 {
                 chunk_receipts_root,
@@ -469,14 +469,14 @@ impl BlockHeader {
                 prev_hash,
                 &inner_lite.try_to_vec().expect("Failed to serialize"),
                 &inner_rest.try_to_vec().expect("Failed to serialize"),
-            );
-            Self::BlockHeaderV3(Box::new(BlockHeaderV3 {
+            )?;
+            Ok(Self::BlockHeaderV3(Box::new(BlockHeaderV3 {
                 prev_hash,
                 inner_lite,
                 inner_rest,
                 signature,
                 hash,
-            }))
+            })))
         }
     }
 
@@ -628,6 +628,16 @@ impl BlockHeader {
         }
     }
 
+    /// Sets the signature of a header which was built before it was signed, e.g. by a remote
+    /// signer.  The hash doesn't cover the signature, so it stays the same.
+    pub fn set_signature(&mut self, signature: Signature) {
+        match self {
+            BlockHeader::BlockHeaderV1(header) => header.signature = signature,
+            BlockHeader::BlockHeaderV2(header) => header.signature = signature,
+            BlockHeader::BlockHeaderV3(header) => header.signature = signature,
+        }
+    }
+
     #[inline]
     pub fn height(&self) -> BlockHeight {
         match self {
@@ -0,0 +1,111 @@
+//! Recording of the host function calls and storage accesses made while executing a single
+//! receipt.
+//!
+//! Tracing is used to debug past receipts by replaying the chunk they were executed in, so it is
+//! scoped to the current thread: [`trace_receipt`] enables it for the duration of the replay and
+//! the runtime marks the boundaries of every receipt with [`ReceiptTraceGuard`].  The runtime and
+//! the VM only call into this module when built with their `receipt_trace` feature.
+use std::cell::RefCell;
+
+use crate::hash::CryptoHash;
+use crate::views::ReceiptTraceEvent;
+
+struct TraceState {
+    receipt_id: CryptoHash,
+    /// Whether the traced receipt is being executed right now.
+    recording: bool,
+    /// `None` until the traced receipt starts executing.
+    events: Option<Vec<ReceiptTraceEvent>>,
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<TraceState>> = RefCell::new(None);
+}
+
+/// Runs `f` recording what the receipt with the given id does if `f` executes it.  Returns the
+/// recorded events or `None` if the receipt has not been executed.
+pub fn trace_receipt<T>(
+    receipt_id: CryptoHash,
+    f: impl FnOnce() -> T,
+) -> (T, Option<Vec<ReceiptTraceEvent>>) {
+    TRACE.with(|trace| {
+        *trace.borrow_mut() = Some(TraceState { receipt_id, recording: false, events: None })
+    });
+    let result = f();
+    let events = TRACE.with(|trace| trace.borrow_mut().take().and_then(|state| state.events));
+    (result, events)
+}
+
+/// Marks the execution of a receipt.  Events are recorded until the guard is dropped if the
+/// receipt is the one being traced.
+pub struct ReceiptTraceGuard {
+    recording: bool,
+}
+
+impl ReceiptTraceGuard {
+    pub fn enter(receipt_id: &CryptoHash) -> Self {
+        let recording = TRACE.with(|trace| match trace.borrow_mut().as_mut() {
+            Some(state) if &state.receipt_id == receipt_id => {
+                state.recording = true;
+                state.events.get_or_insert_with(Vec::new);
+                true
+            }
+            _ => false,
+        });
+        Self { recording }
+    }
+}
+
+impl Drop for ReceiptTraceGuard {
+    fn drop(&mut self) {
+        if self.recording {
+            TRACE.with(|trace| {
+                if let Some(state) = trace.borrow_mut().as_mut() {
+                    state.recording = false;
+                }
+            });
+        }
+    }
+}
+
+/// Records an event of the receipt being traced.  `event` is only evaluated while recording.
+pub fn record(event: impl FnOnce() -> ReceiptTraceEvent) {
+    TRACE.with(|trace| {
+        if let Some(TraceState { recording: true, events: Some(events), .. }) =
+            trace.borrow_mut().as_mut()
+        {
+            events.push(event());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host_call(name: &str) -> ReceiptTraceEvent {
+        ReceiptTraceEvent::HostFunctionCall { name: name.to_string() }
+    }
+
+    #[test]
+    fn test_only_traced_receipt_is_recorded() {
+        let traced = CryptoHash::hash_bytes(b"traced");
+        let other = CryptoHash::hash_bytes(b"other");
+        let ((), events) = trace_receipt(traced, || {
+            for receipt_id in [&other, &traced, &other] {
+                let _guard = ReceiptTraceGuard::enter(receipt_id);
+                record(|| host_call(if receipt_id == &traced { "traced" } else { "other" }));
+            }
+        });
+        assert_eq!(events, Some(vec![host_call("traced")]));
+        // Tracing is off outside of `trace_receipt`.
+        let _guard = ReceiptTraceGuard::enter(&traced);
+        record(|| panic!("must not be evaluated"));
+    }
+
+    #[test]
+    fn test_receipt_not_executed() {
+        let ((), events) = trace_receipt(CryptoHash::hash_bytes(b"traced"), || {});
+        assert_eq!(events, None);
+    }
+}
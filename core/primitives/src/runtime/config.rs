@@ -1,5 +1,8 @@
 //! Settings of the parameters of the runtime.
+use std::collections::BTreeSet;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::config::VMConfig;
 use crate::runtime::fees::RuntimeFeesConfig;
@@ -41,6 +44,51 @@ impl RuntimeConfig {
             account_creation_config: AccountCreationConfig::default(),
         }
     }
+
+    /// Returns all parameters whose values differ between `self` and `other`, ordered by their
+    /// path in the serialized config.
+    pub fn diff(&self, other: &RuntimeConfig) -> Vec<RuntimeConfigChange> {
+        let old_value = serde_json::to_value(self).expect("RuntimeConfig is serializable");
+        let new_value = serde_json::to_value(other).expect("RuntimeConfig is serializable");
+        let mut changes = vec![];
+        diff_values("", &old_value, &new_value, &mut changes);
+        changes
+    }
+}
+
+/// A single runtime config parameter which differs between two configs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct RuntimeConfigChange {
+    /// Dot-separated path to the parameter, e.g. `wasm_config.limit_config.max_gas_burnt`.
+    pub parameter: String,
+    /// Value in the old config, `null` if the parameter did not exist.
+    pub old_value: Value,
+    /// Value in the new config, `null` if the parameter does not exist anymore.
+    pub new_value: Value,
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<RuntimeConfigChange>) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            let keys: BTreeSet<&String> = old_fields.keys().chain(new_fields.keys()).collect();
+            for key in keys {
+                let child_path =
+                    if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                diff_values(
+                    &child_path,
+                    old_fields.get(key).unwrap_or(&Value::Null),
+                    new_fields.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if old != new => changes.push(RuntimeConfigChange {
+            parameter: path.to_string(),
+            old_value: old.clone(),
+            new_value: new.clone(),
+        }),
+        _ => {}
+    }
 }
 
 /// The structure describes configuration for creation of new accounts.
@@ -61,3 +109,31 @@ impl Default for AccountCreationConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_same_config() {
+        assert_eq!(RuntimeConfig::test().diff(&RuntimeConfig::test()), vec![]);
+    }
+
+    #[test]
+    fn test_diff_changed_parameters() {
+        let old_config = RuntimeConfig::test();
+        let mut new_config = old_config.clone();
+        new_config.storage_amount_per_byte += 1;
+        new_config.wasm_config.limit_config.max_gas_burnt += 1;
+        let changes = old_config.diff(&new_config);
+        let parameters = changes.iter().map(|change| change.parameter.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            parameters,
+            vec!["storage_amount_per_byte", "wasm_config.limit_config.max_gas_burnt"]
+        );
+        assert_eq!(
+            changes[1].new_value,
+            serde_json::json!(old_config.wasm_config.limit_config.max_gas_burnt + 1)
+        );
+    }
+}
@@ -58,6 +58,18 @@ impl RuntimeConfigStore {
         Self { store }
     }
 
+    /// Constructs a store from the configs of the protocol versions where the config changes.
+    /// There must be a config for version 0.
+    pub fn from_configs(configs: BTreeMap<ProtocolVersion, Arc<RuntimeConfig>>) -> Self {
+        assert!(configs.contains_key(&0), "No RuntimeConfig for protocol version 0");
+        Self { store: configs }
+    }
+
+    /// The protocol versions where the config changes, along with the configs.
+    pub fn configs(&self) -> impl Iterator<Item = (ProtocolVersion, &Arc<RuntimeConfig>)> {
+        self.store.iter().map(|(protocol_version, config)| (*protocol_version, config))
+    }
+
     /// Constructs test store.
     pub fn with_one_config(runtime_config: RuntimeConfig) -> Self {
         Self { store: BTreeMap::from_iter([(0, Arc::new(runtime_config))].iter().cloned()) }
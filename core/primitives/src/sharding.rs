@@ -10,7 +10,7 @@ use crate::receipt::Receipt;
 use crate::transaction::SignedTransaction;
 use crate::types::validator_stake::{ValidatorStake, ValidatorStakeIter, ValidatorStakeV1};
 use crate::types::{Balance, BlockHeight, Gas, MerkleHash, ShardId, StateRoot};
-use crate::validator_signer::ValidatorSigner;
+use crate::validator_signer::{SignerError, ValidatorSigner};
 use crate::version::{
     ProtocolFeature, ProtocolVersion, ProtocolVersionRange, SHARD_CHUNK_HEADER_UPGRADE_VERSION,
 };
@@ -126,7 +126,7 @@ impl ShardChunkHeaderV2 {
         tx_root: CryptoHash,
         validator_proposals: Vec<ValidatorStakeV1>,
         signer: &dyn ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -143,8 +143,8 @@ impl ShardChunkHeaderV2 {
             validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_chunk_hash(&hash)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -191,7 +191,7 @@ impl ShardChunkHeaderV3 {
         tx_root: CryptoHash,
         validator_proposals: Vec<ValidatorStake>,
         signer: &dyn ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ShardChunkHeaderInner::V2(ShardChunkHeaderInnerV2 {
             prev_block_hash,
             prev_state_root,
@@ -208,8 +208,8 @@ impl ShardChunkHeaderV3 {
             validator_proposals,
         });
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_chunk_hash(&hash)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -454,7 +454,7 @@ impl ShardChunkHeaderV1 {
         tx_root: CryptoHash,
         validator_proposals: Vec<ValidatorStakeV1>,
         signer: &dyn ValidatorSigner,
-    ) -> Self {
+    ) -> Result<Self, SignerError> {
         let inner = ShardChunkHeaderInnerV1 {
             prev_block_hash,
             prev_state_root,
@@ -471,8 +471,8 @@ impl ShardChunkHeaderV1 {
             validator_proposals,
         };
         let hash = Self::compute_hash(&inner);
-        let signature = signer.sign_chunk_hash(&hash);
-        Self { inner, height_included: 0, signature, hash }
+        let signature = signer.sign_chunk_hash(&hash)?;
+        Ok(Self { inner, height_included: 0, signature, hash })
     }
 }
 
@@ -814,6 +814,11 @@ impl EncodedShardChunkBody {
         rs.reconstruct(self.parts.as_mut_slice())
     }
 
+    /// Computes the parity parts from the data parts, which must all be present.
+    pub fn encode(&mut self, rs: &ReedSolomonWrapper) -> Result<(), reed_solomon_erasure::Error> {
+        rs.encode(self.parts.as_mut_slice())
+    }
+
     pub fn get_merkle_hash_and_paths(&self) -> (MerkleHash, Vec<MerklePath>) {
         merklize(&self.parts.iter().map(|x| x.as_ref().unwrap().clone()).collect::<Vec<_>>())
     }
@@ -1005,7 +1010,7 @@ impl EncodedShardChunk {
             Self::encode_transaction_receipts(rs, transactions, outgoing_receipts)?;
 
         let mut content = EncodedShardChunkBody { parts: transaction_receipts_parts };
-        content.reconstruct(rs).unwrap();
+        content.encode(rs).unwrap();
         let (encoded_merkle_root, merkle_paths) = content.get_merkle_hash_and_paths();
 
         let block_header_v3_version = Some(ProtocolFeature::BlockHeaderV3.protocol_version());
@@ -1028,7 +1033,8 @@ impl EncodedShardChunk {
                 tx_root,
                 validator_proposals,
                 signer,
-            );
+            )
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
             let chunk = EncodedShardChunkV1 { header, content };
             Ok((Self::V1(chunk), merkle_paths))
         } else if block_header_v3_version.is_none()
@@ -1051,7 +1057,8 @@ impl EncodedShardChunk {
                 tx_root,
                 validator_proposals,
                 signer,
-            );
+            )
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V2(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         } else {
@@ -1070,7 +1077,8 @@ impl EncodedShardChunk {
                 tx_root,
                 validator_proposals,
                 signer,
-            );
+            )
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
             let chunk = EncodedShardChunkV2 { header: ShardChunkHeader::V3(header), content };
             Ok((Self::V2(chunk), merkle_paths))
         }
@@ -1198,6 +1206,29 @@ impl ReedSolomonWrapper {
         res
     }
 
+    /// Computes the missing parity shards from the data shards.  Unlike `reconstruct`, doesn't
+    /// invert the matrix of the present shards nor cache it, so encoding doesn't use up the
+    /// instance.
+    pub fn encode(
+        &self,
+        shards: &mut [Option<Box<[u8]>>],
+    ) -> Result<(), reed_solomon_erasure::Error> {
+        let data_shards = self.rs.data_shard_count();
+        if shards.len() < data_shards {
+            return Err(reed_solomon_erasure::Error::TooFewShards);
+        }
+        if shards[..data_shards].iter().any(|shard| shard.is_none()) {
+            return Err(reed_solomon_erasure::Error::TooFewDataShards);
+        }
+        let shard_len = shards[0].as_ref().unwrap().len();
+        for shard in shards[data_shards..].iter_mut().filter(|shard| shard.is_none()) {
+            *shard = Some(vec![0; shard_len].into_boxed_slice());
+        }
+        let mut slices: Vec<&mut [u8]> =
+            shards.iter_mut().map(|shard| &mut shard.as_mut().unwrap()[..]).collect();
+        self.rs.encode(slices.as_mut_slice())
+    }
+
     pub fn data_shard_count(&self) -> usize {
         self.rs.data_shard_count()
     }
@@ -1206,3 +1237,29 @@ impl ReedSolomonWrapper {
         self.rs.total_shard_count()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ReedSolomonWrapper;
+
+    #[test]
+    fn test_encode_matches_reconstruct() {
+        let mut rs = ReedSolomonWrapper::new(4, 6);
+        let data: Vec<Option<Box<[u8]>>> = (0..4u8)
+            .map(|i| Some((0..32).map(|j| i * 32 + j).collect::<Vec<u8>>().into_boxed_slice()))
+            .chain((0..6).map(|_| None))
+            .collect();
+        let mut encoded = data.clone();
+        rs.encode(&mut encoded).unwrap();
+        let mut reconstructed = data.clone();
+        rs.reconstruct(&mut reconstructed).unwrap();
+        assert_eq!(encoded, reconstructed);
+
+        let mut missing_data = data;
+        missing_data[1] = None;
+        assert_eq!(
+            rs.encode(&mut missing_data),
+            Err(reed_solomon_erasure::Error::TooFewDataShards)
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
 use borsh::{BorshDeserialize, BorshSerialize};
 
 use crate::block_header::BlockHeader;
@@ -5,9 +9,11 @@ use crate::epoch_manager::block_info::BlockInfo;
 use crate::epoch_manager::epoch_info::EpochInfo;
 use crate::hash::CryptoHash;
 use crate::merkle::{MerklePath, PartialMerkleTree};
+use crate::shard_layout::ShardUId;
 use crate::sharding::{
     ReceiptProof, ShardChunk, ShardChunkHeader, ShardChunkHeaderV1, ShardChunkV1,
 };
+use crate::time::{Clock, Duration, Instant};
 use crate::types::{BlockHeight, ShardId, StateRoot, StateRootNode};
 use crate::views::LightClientBlockView;
 
@@ -236,3 +242,67 @@ pub fn get_num_state_parts(memory_usage: u64) -> u64 {
     // TODO #1708
     memory_usage / STATE_PART_MEMORY_LIMIT.as_u64() + 3
 }
+
+/// Progress of building the states of the shards split from a parent shard.  Updated while the
+/// states are built in the background and read to report the progress.
+#[derive(Debug)]
+pub struct StateSplitProgress {
+    start_time: Instant,
+    num_parts: AtomicU64,
+    parts_done: AtomicU64,
+    /// Bytes of keys and values written to every child shard so far.
+    child_bytes: Mutex<HashMap<ShardUId, u64>>,
+}
+
+impl StateSplitProgress {
+    pub fn new() -> Self {
+        Self {
+            start_time: Clock::instant(),
+            num_parts: AtomicU64::new(0),
+            parts_done: AtomicU64::new(0),
+            child_bytes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_num_parts(&self, num_parts: u64) {
+        self.num_parts.store(num_parts, Ordering::Relaxed);
+    }
+
+    /// Records a part of the parent state split, with the bytes it added to every child shard.
+    pub fn part_done(&self, child_bytes: HashMap<ShardUId, u64>) {
+        let mut total = self.child_bytes.lock().unwrap();
+        for (shard_uid, bytes) in child_bytes {
+            *total.entry(shard_uid).or_default() += bytes;
+        }
+        self.parts_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of parts done and the total number of parts, which is 0 until it is known.
+    pub fn parts(&self) -> (u64, u64) {
+        (self.parts_done.load(Ordering::Relaxed), self.num_parts.load(Ordering::Relaxed))
+    }
+
+    /// Remaining time, assuming the remaining parts take as long as the ones done so far.
+    pub fn eta(&self) -> Option<Duration> {
+        let (parts_done, num_parts) = self.parts();
+        if parts_done == 0 || num_parts < parts_done {
+            return None;
+        }
+        let elapsed = self.start_time.elapsed();
+        Some(elapsed.mul_f64((num_parts - parts_done) as f64 / parts_done as f64))
+    }
+
+    /// Bytes written to every child shard so far, ordered by shard.
+    pub fn child_bytes(&self) -> Vec<(ShardUId, u64)> {
+        let mut child_bytes: Vec<_> =
+            self.child_bytes.lock().unwrap().iter().map(|(k, v)| (*k, *v)).collect();
+        child_bytes.sort_by_key(|(shard_uid, _)| (shard_uid.version, shard_uid.shard_id));
+        child_bytes
+    }
+}
+
+impl Default for StateSplitProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,7 +2,8 @@
 //! node count and their status across the network.
 use serde::{Deserialize, Serialize};
 
-use crate::types::BlockHeight;
+use crate::serialize::option_u128_dec_format;
+use crate::types::{Balance, BlockHeight, ShardId};
 
 use crate::types::AccountId;
 
@@ -30,6 +31,20 @@ pub struct TelemetryChainInfo {
     pub latest_block_hash: String,
     pub latest_block_height: BlockHeight,
     pub num_peers: usize,
+    /// Gas price of the last block processed.
+    #[serde(with = "option_u128_dec_format")]
+    pub gas_price: Option<Balance>,
+    pub shards: Vec<TelemetryShardInfo>,
+}
+
+/// Congestion of a shard since the previous report.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TelemetryShardInfo {
+    pub shard_id: ShardId,
+    /// Gas used by the chunks of the shard in the blocks processed, relative to their gas limit.
+    pub block_fullness: Option<f64>,
+    /// Number of receipts in the delayed receipts queue of the shard as of the head.
+    pub delayed_receipts: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
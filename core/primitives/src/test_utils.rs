@@ -280,11 +280,13 @@ impl BlockHeader {
     }
 
     pub fn resign(&mut self, signer: &dyn ValidatorSigner) {
-        let (hash, signature) = signer.sign_block_header_parts(
-            *self.prev_hash(),
-            &self.inner_lite_bytes(),
-            &self.inner_rest_bytes(),
-        );
+        let (hash, signature) = signer
+            .sign_block_header_parts(
+                *self.prev_hash(),
+                &self.inner_lite_bytes(),
+                &self.inner_rest_bytes(),
+            )
+            .unwrap();
         match self {
             BlockHeader::BlockHeaderV1(header) => {
                 header.hash = hash;
@@ -439,6 +441,7 @@ impl Block {
             next_bp_hash,
             block_merkle_root,
         )
+        .unwrap()
     }
 }
 
@@ -30,9 +30,16 @@ pub use chrono::Utc;
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::default::Default;
+#[cfg(feature = "sandbox")]
+use std::sync::atomic::{AtomicI64, Ordering};
 pub use std::time::{Duration, Instant};
 pub use time::Time;
 
+/// Offset in nanoseconds added to the real time returned by `Clock::utc()`.  Sandbox uses it to
+/// move the time of the node forward.
+#[cfg(feature = "sandbox")]
+static SANDBOX_UTC_OFFSET_NANOS: AtomicI64 = AtomicI64::new(0);
+
 #[derive(Default)]
 struct MockClockPerState {
     /// List of timestamps, we will return one timestamp for each call of `Clock::utc()`.
@@ -43,6 +50,9 @@ struct MockClockPerState {
     utc_call_count: u64,
     /// Number of times `Clock::instant()` method was called since we started mocking.
     instant_call_count: u64,
+    /// Virtual time returned by `Clock::instant()` and `Clock::utc()` once the lists of
+    /// timestamps are empty.
+    virtual_time: Option<(Instant, DateTime<Utc>)>,
 }
 
 /// Stores the mocking state.
@@ -90,6 +100,33 @@ impl MockClockGuard {
         });
     }
 
+    /// Sets the virtual time, which is returned by `Clock::instant()` and `Clock::utc()`
+    /// whenever there are no timestamps added by `Self::add_instant` or `Self::add_utc` left.
+    /// The virtual time stands still until it's set again or advanced.
+    pub fn set_virtual_time(&self, instant: Instant, utc: DateTime<chrono::Utc>) {
+        MockClockPerThread::with(|clock| match &mut clock.mock {
+            Some(clock) => {
+                clock.virtual_time = Some((instant, utc));
+            }
+            None => {
+                panic!("Use MockClockGuard in your test");
+            }
+        });
+    }
+
+    /// Moves the virtual time set by `Self::set_virtual_time` forward by `duration`.
+    pub fn advance_virtual_time(&self, duration: Duration) {
+        MockClockPerThread::with(|clock| match &mut clock.mock {
+            Some(MockClockPerState { virtual_time: Some((instant, utc)), .. }) => {
+                *instant += duration;
+                *utc = *utc + chrono::Duration::from_std(duration).unwrap();
+            }
+            _ => {
+                panic!("Set the virtual time with MockClockGuard::set_virtual_time first");
+            }
+        });
+    }
+
     /// Returns number of calls  to `Self::utc` since `Self::mock()` was called.
     pub fn utc_call_count(&self) -> u64 {
         MockClockPerThread::with(|clock| match &mut clock.mock {
@@ -148,7 +185,10 @@ impl Clock {
         MockClockPerThread::with(|clock| match &mut clock.mock {
             Some(clock) => {
                 clock.instant_call_count += 1;
-                let x = clock.instant_list.pop_front();
+                let x = clock
+                    .instant_list
+                    .pop_front()
+                    .or_else(|| clock.virtual_time.map(|(instant, _)| instant));
                 match x {
                     Some(t) => t,
                     None => {
@@ -166,7 +206,8 @@ impl Clock {
         MockClockPerThread::with(|clock| match &mut clock.mock {
             Some(clock) => {
                 clock.utc_call_count += 1;
-                let x = clock.utc_list.pop_front();
+                let x =
+                    clock.utc_list.pop_front().or_else(|| clock.virtual_time.map(|(_, utc)| utc));
                 match x {
                     Some(t) => t,
                     None => {
@@ -174,9 +215,31 @@ impl Clock {
                     }
                 }
             }
-            None => chrono::Utc::now(),
+            None => Self::real_utc(),
         })
     }
+
+    #[cfg(not(feature = "sandbox"))]
+    fn real_utc() -> DateTime<chrono::Utc> {
+        chrono::Utc::now()
+    }
+
+    #[cfg(feature = "sandbox")]
+    fn real_utc() -> DateTime<chrono::Utc> {
+        chrono::Utc::now()
+            + chrono::Duration::nanoseconds(SANDBOX_UTC_OFFSET_NANOS.load(Ordering::Relaxed))
+    }
+
+    /// Shifts the time returned by `Self::utc()` so that the current time becomes `utc`.
+    /// The time keeps flowing at the normal pace afterwards.
+    #[cfg(feature = "sandbox")]
+    pub fn sandbox_set_utc(utc: DateTime<chrono::Utc>) {
+        let offset = utc.signed_duration_since(chrono::Utc::now());
+        SANDBOX_UTC_OFFSET_NANOS.store(
+            offset.num_nanoseconds().expect("sandbox time offset overflows i64 nanoseconds"),
+            Ordering::Relaxed,
+        );
+    }
 }
 
 #[cfg(test)]
@@ -274,6 +337,25 @@ mod tests {
         assert_eq!(mock_clock_guard.instant_call_count(), 0);
     }
 
+    #[test]
+    fn test_clock_virtual_time() {
+        let mock_clock_guard = MockClockGuard::default();
+
+        let instant_now = Instant::now();
+        let utc_now = Utc::now();
+        mock_clock_guard.set_virtual_time(instant_now, utc_now);
+        mock_clock_guard.add_instant(instant_now.add(Duration::from_secs(5)));
+        // Queued timestamps take precedence over the virtual time.
+        assert_eq!(Clock::instant(), instant_now.add(Duration::from_secs(5)));
+        assert_eq!(Clock::instant(), instant_now);
+        assert_eq!(Clock::instant(), instant_now);
+        assert_eq!(Clock::utc(), utc_now);
+
+        mock_clock_guard.advance_virtual_time(Duration::from_millis(1500));
+        assert_eq!(Clock::instant(), instant_now.add(Duration::from_millis(1500)));
+        assert_eq!(Clock::utc(), utc_now + chrono::Duration::milliseconds(1500));
+    }
+
     #[test]
     fn test_threading() {
         thread::spawn(|| {
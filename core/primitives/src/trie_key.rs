@@ -391,6 +391,16 @@ pub mod trie_key_parsers {
         })
     }
 
+    /// Returns the common prefix of the raw keys of all accounts.
+    pub fn get_raw_prefix_for_accounts() -> &'static [u8] {
+        col::ACCOUNT
+    }
+
+    /// Returns the common prefix of the raw keys of the access keys of all accounts.
+    pub fn get_raw_prefix_for_all_access_keys() -> &'static [u8] {
+        col::ACCESS_KEY
+    }
+
     pub fn get_raw_prefix_for_access_keys(account_id: &AccountId) -> Vec<u8> {
         let mut res = Vec::with_capacity(col::ACCESS_KEY.len() * 2 + account_id.len());
         res.extend(col::ACCESS_KEY);
@@ -217,6 +217,7 @@ pub enum StateChangesRequest {
     AccountChanges { account_ids: Vec<AccountId> },
     SingleAccessKeyChanges { keys: Vec<AccountWithPublicKey> },
     AllAccessKeyChanges { account_ids: Vec<AccountId> },
+    AccessKeyChangesByPublicKey { public_keys: Vec<PublicKey> },
     ContractCodeChanges { account_ids: Vec<AccountId> },
     DataChanges { account_ids: Vec<AccountId>, key_prefix: StoreKey },
 }
@@ -920,6 +921,14 @@ impl Serialize for EpochReference {
     }
 }
 
+/// Selects a protocol version either directly or through the epoch in which it was active.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolVersionReference {
+    EpochId(EpochId),
+    ProtocolVersion(ProtocolVersion),
+}
+
 /// Reasons for removing a validator from the validator set.
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -950,6 +959,16 @@ pub enum TransactionOrReceiptId {
     Receipt { receipt_id: CryptoHash, receiver_id: AccountId },
 }
 
+/// Whether an entry of the account history is the execution of a transaction or of a receipt.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountHistoryKind {
+    Transaction,
+    Receipt,
+}
+
 /// Cache for compiled modules
 pub trait CompiledContractCache: Send + Sync {
     fn put(&self, key: &[u8], value: &[u8]) -> Result<(), std::io::Error>;
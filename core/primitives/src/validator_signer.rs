@@ -13,6 +13,19 @@ use crate::sharding::ChunkHash;
 use crate::telemetry::TelemetryInfo;
 use crate::types::{AccountId, BlockHeight, EpochId};
 
+/// Failure of a signer which doesn't hold the key itself, e.g. a remote signer which timed out
+/// or refused to sign.  Nothing is produced or sent in place of the signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerError(pub String);
+
+impl std::fmt::Display for SignerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to sign: {}", self.0)
+    }
+}
+
+impl std::error::Error for SignerError {}
+
 /// Validator signer that is used to sign blocks and approvals.
 pub trait ValidatorSigner: Sync + Send {
     /// Account id of the given validator.
@@ -21,6 +34,12 @@ pub trait ValidatorSigner: Sync + Send {
     /// Public key that identifies this validator.
     fn public_key(&self) -> PublicKey;
 
+    /// Whether signatures are obtained over the network instead of computed locally, in which
+    /// case the client signs approvals and produced blocks off its actor thread.
+    fn is_remote(&self) -> bool {
+        false
+    }
+
     /// Serializes telemetry info to JSON and signs it, returning JSON with "signature" field.
     fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value;
 
@@ -30,13 +49,17 @@ pub trait ValidatorSigner: Sync + Send {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature);
+    ) -> Result<(CryptoHash, Signature), SignerError>;
 
     /// Signs given inner of the chunk header.
-    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Signature;
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, SignerError>;
 
     /// Signs approval of given parent hash and reference hash.
-    fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature;
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, SignerError>;
 
     /// Signs challenge body.
     fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature);
@@ -52,7 +75,7 @@ pub trait ValidatorSigner: Sync + Send {
     fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof);
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError>;
 
     /// Used by test infrastructure, only implement if make sense for testing otherwise raise `unimplemented`.
     fn write_to_file(&self, path: &Path) -> std::io::Result<()>;
@@ -84,17 +107,21 @@ impl ValidatorSigner for EmptyValidatorSigner {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature) {
+    ) -> Result<(CryptoHash, Signature), SignerError> {
         let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
-        (hash, Signature::default())
+        Ok((hash, Signature::default()))
     }
 
-    fn sign_chunk_hash(&self, _chunk_hash: &ChunkHash) -> Signature {
-        Signature::default()
+    fn sign_chunk_hash(&self, _chunk_hash: &ChunkHash) -> Result<Signature, SignerError> {
+        Ok(Signature::default())
     }
 
-    fn sign_approval(&self, _inner: &ApprovalInner, _target_height: BlockHeight) -> Signature {
-        Signature::default()
+    fn sign_approval(
+        &self,
+        _inner: &ApprovalInner,
+        _target_height: BlockHeight,
+    ) -> Result<Signature, SignerError> {
+        Ok(Signature::default())
     }
 
     fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
@@ -114,7 +141,7 @@ impl ValidatorSigner for EmptyValidatorSigner {
     fn compute_vrf_with_proof(
         &self,
         _data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError> {
         unimplemented!()
     }
 
@@ -172,17 +199,21 @@ impl ValidatorSigner for InMemoryValidatorSigner {
         prev_hash: CryptoHash,
         inner_lite: &[u8],
         inner_rest: &[u8],
-    ) -> (CryptoHash, Signature) {
+    ) -> Result<(CryptoHash, Signature), SignerError> {
         let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
-        (hash, self.signer.sign(hash.as_ref()))
+        Ok((hash, self.signer.sign(hash.as_ref())))
     }
 
-    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Signature {
-        self.signer.sign(chunk_hash.as_ref())
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, SignerError> {
+        Ok(self.signer.sign(chunk_hash.as_ref()))
     }
 
-    fn sign_approval(&self, inner: &ApprovalInner, target_height: BlockHeight) -> Signature {
-        self.signer.sign(&Approval::get_data_for_sig(inner, target_height))
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, SignerError> {
+        Ok(self.signer.sign(&Approval::get_data_for_sig(inner, target_height)))
     }
 
     fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
@@ -204,8 +235,8 @@ impl ValidatorSigner for InMemoryValidatorSigner {
     fn compute_vrf_with_proof(
         &self,
         data: &[u8],
-    ) -> (near_crypto::vrf::Value, near_crypto::vrf::Proof) {
-        self.signer.compute_vrf_with_proof(data)
+    ) -> Result<(near_crypto::vrf::Value, near_crypto::vrf::Proof), SignerError> {
+        Ok(self.signer.compute_vrf_with_proof(data))
     }
 
     fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
@@ -17,7 +17,11 @@ pub struct Version {
 pub type DbVersion = u32;
 
 /// Current version of the database.
-pub const DB_VERSION: DbVersion = 31;
+#[cfg(not(feature = "protocol_feature_inline_small_values"))]
+pub const DB_VERSION: DbVersion = 34;
+/// Current version of the database.
+#[cfg(feature = "protocol_feature_inline_small_values")]
+pub const DB_VERSION: DbVersion = 35;
 
 use crate::upgrade_schedule::{get_protocol_version_internal, ProtocolUpgradeVotingSchedule};
 /// Protocol version type.
@@ -152,6 +156,22 @@ pub enum ProtocolFeature {
     FixStakingThreshold,
     #[cfg(feature = "protocol_feature_function_call_weight")]
     FunctionCallWeight,
+    /// Natively implemented functions callable on system accounts, see
+    /// `near_vm_runner::precompiles`.
+    #[cfg(feature = "protocol_feature_precompiles")]
+    Precompiles,
+    /// Inline small values into the trie nodes referring to them, which saves a lookup when
+    /// reading them.
+    #[cfg(feature = "protocol_feature_inline_small_values")]
+    InlineSmallValues,
+    /// Challenges of invalid chunk encodings and state transitions received from the network
+    /// are processed and included in blocks, which slashes the producers of the invalid chunks.
+    #[cfg(feature = "protocol_feature_challenges")]
+    Challenges,
+    /// Peers advertise their capabilities in the handshake, see
+    /// `near_network::types::PeerCapabilities`.
+    #[cfg(feature = "protocol_feature_peer_capabilities")]
+    PeerCapabilities,
 }
 
 /// Both, outgoing and incoming tcp connections to peers, will be rejected if `peer's`
@@ -168,7 +188,7 @@ const STABLE_PROTOCOL_VERSION: ProtocolVersion = 53;
 pub const PROTOCOL_VERSION: ProtocolVersion = STABLE_PROTOCOL_VERSION;
 /// Current latest nightly version of the protocol.
 #[cfg(feature = "nightly_protocol")]
-pub const PROTOCOL_VERSION: ProtocolVersion = 127;
+pub const PROTOCOL_VERSION: ProtocolVersion = 131;
 
 /// The points in time after which the voting for the protocol version should start.
 #[allow(dead_code)]
@@ -230,6 +250,14 @@ impl ProtocolFeature {
             ProtocolFeature::FixStakingThreshold => 126,
             #[cfg(feature = "protocol_feature_function_call_weight")]
             ProtocolFeature::FunctionCallWeight => 127,
+            #[cfg(feature = "protocol_feature_precompiles")]
+            ProtocolFeature::Precompiles => 128,
+            #[cfg(feature = "protocol_feature_inline_small_values")]
+            ProtocolFeature::InlineSmallValues => 129,
+            #[cfg(feature = "protocol_feature_challenges")]
+            ProtocolFeature::Challenges => 130,
+            #[cfg(feature = "protocol_feature_peer_capabilities")]
+            ProtocolFeature::PeerCapabilities => 131,
         }
     }
 }
@@ -19,13 +19,15 @@ use crate::block_header::{
     BlockHeaderV1, BlockHeaderV2, BlockHeaderV3,
 };
 use crate::challenge::{Challenge, ChallengesResult};
+use crate::config::ExtCosts;
 use crate::contract::ContractCode;
 use crate::errors::TxExecutionError;
 use crate::hash::{hash, CryptoHash};
 use crate::logging;
 use crate::merkle::MerklePath;
-use crate::profile::Cost;
+use crate::profile::{Cost, ProfileData};
 use crate::receipt::{ActionReceipt, DataReceipt, DataReceiver, Receipt, ReceiptEnum};
+use crate::runtime::config::RuntimeConfigChange;
 use crate::serialize::{
     base64_format, from_base64, option_base64_format, option_u128_dec_format, to_base64,
     u128_dec_format, u64_dec_format,
@@ -40,10 +42,10 @@ use crate::transaction::{
     ExecutionStatus, FunctionCallAction, SignedTransaction, StakeAction, TransferAction,
 };
 use crate::types::{
-    AccountId, AccountWithPublicKey, Balance, BlockHeight, CompiledContractCache, EpochHeight,
-    EpochId, FunctionArgs, Gas, Nonce, NumBlocks, ShardId, StateChangeCause, StateChangeKind,
-    StateChangeValue, StateChangeWithCause, StateChangesRequest, StateRoot, StorageUsage, StoreKey,
-    StoreValue, ValidatorKickoutReason,
+    AccountHistoryKind, AccountId, AccountWithPublicKey, Balance, BlockHeight, BlockHeightDelta,
+    CompiledContractCache, EpochHeight, EpochId, FunctionArgs, Gas, Nonce, NumBlocks, ShardId,
+    StateChangeCause, StateChangeKind, StateChangeValue, StateChangeWithCause, StateChangesRequest,
+    StateRoot, StorageUsage, StoreKey, StoreValue, ValidatorKickoutReason,
 };
 use crate::version::{ProtocolVersion, Version};
 use validator_stake_view::ValidatorStakeView;
@@ -72,6 +74,34 @@ pub struct ContractCodeView {
     pub hash: CryptoHash,
 }
 
+/// Where the ABI of a contract was found.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractAbiSource {
+    /// Published by the contract in its own state.
+    State,
+    /// Loaded from the ABI directory configured on the node.
+    Directory,
+}
+
+/// A view of the ABI of a contract.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct ContractAbiView {
+    #[serde(rename = "abi_base64", with = "base64_format")]
+    pub abi: Vec<u8>,
+    /// Hash of the ABI.
+    pub abi_hash: CryptoHash,
+    /// Hash of the deployed code the ABI describes.
+    pub code_hash: CryptoHash,
+    pub source: ContractAbiSource,
+    /// Whether the deployed code declares the hash of its ABI, which then matches `abi_hash`.
+    pub verified: bool,
+}
+
 /// State for the view call.
 #[derive(Debug)]
 pub struct ViewApplyState {
@@ -259,6 +289,7 @@ pub enum QueryResponseKind {
     CallResult(CallResult),
     AccessKey(AccessKeyView),
     AccessKeyList(AccessKeyList),
+    ViewAbi(ContractAbiView),
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -289,6 +320,9 @@ pub enum QueryRequest {
         #[serde(rename = "args_base64", with = "base64_format")]
         args: FunctionArgs,
     },
+    ViewAbi {
+        account_id: AccountId,
+    },
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -331,6 +365,9 @@ pub struct DebugChunkStatus {
     pub gas_used: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub processing_time_ms: Option<u64>,
+    // Total size of the trie nodes and values touched while applying the chunk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_witness_size: Option<u64>,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -397,6 +434,30 @@ pub struct StatusResponse {
     /// Information about last blocks and sync info.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detailed_debug_status: Option<DetailedDebugStatus>,
+    /// State of the mapping of the node's port in the router, if enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_mapping: Option<PortMappingView>,
+}
+
+/// State of the mapping of the port the node listens on in the router.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PortMappingView {
+    /// The port isn't mapped yet.
+    Pending,
+    /// The port is mapped, with the given protocol, to the given external address.
+    Mapped { protocol: PortMappingProtocol, external_addr: String },
+    /// The last attempt to map the port, or to renew the mapping, failed.
+    Failed { error: String },
+}
+
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortMappingProtocol {
+    Upnp,
+    NatPmp,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -410,7 +471,7 @@ impl From<Challenge> for ChallengeView {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct BlockHeaderView {
     pub height: BlockHeight,
     pub prev_height: Option<BlockHeight>,
@@ -681,7 +742,7 @@ impl From<BlockHeaderInnerLiteView> for BlockHeaderInnerLite {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct ChunkHeaderView {
     pub chunk_hash: CryptoHash,
     pub prev_block_hash: CryptoHash,
@@ -764,7 +825,7 @@ impl From<ChunkHeaderView> for ShardChunkHeader {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct BlockView {
     pub author: AccountId,
     pub header: BlockHeaderView,
@@ -781,7 +842,7 @@ impl BlockView {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct ChunkView {
     pub author: AccountId,
     pub header: ChunkHeaderView,
@@ -1050,6 +1111,85 @@ pub struct CostGasUsed {
 pub struct ExecutionMetadataView {
     pub version: u32,
     pub gas_profile: Option<Vec<CostGasUsed>>,
+    /// The gas profile summed up by what the gas was spent on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_breakdown: Option<GasBreakdownView>,
+}
+
+/// Gas burnt while executing a function call grouped by what it was spent on.  The rest of the gas
+/// burnt by the receipt is the fees of the receipt and its actions.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone, Eq, Debug, Default,
+)]
+pub struct GasBreakdownView {
+    /// Loading and running the contract code.
+    #[serde(with = "u64_dec_format")]
+    pub wasm_execution: Gas,
+    /// Reading storage, including iterating it and touching trie nodes.
+    #[serde(with = "u64_dec_format")]
+    pub storage_reads: Gas,
+    /// Writing and removing storage entries.
+    #[serde(with = "u64_dec_format")]
+    pub storage_writes: Gas,
+    /// Creating promises and the receipts and actions they send.
+    #[serde(with = "u64_dec_format")]
+    pub promises: Gas,
+    /// Other host functions, e.g. memory and registers, hashing and logs.
+    #[serde(with = "u64_dec_format")]
+    pub host_functions: Gas,
+}
+
+impl From<&ProfileData> for GasBreakdownView {
+    fn from(profile_data: &ProfileData) -> Self {
+        let mut breakdown = GasBreakdownView::default();
+        for &cost in Cost::ALL {
+            let category = match cost {
+                Cost::WasmInstruction
+                | Cost::ExtCost {
+                    ext_cost_kind:
+                        ExtCosts::contract_compile_base | ExtCosts::contract_compile_bytes,
+                } => &mut breakdown.wasm_execution,
+                Cost::ActionCost { .. }
+                | Cost::ExtCost {
+                    ext_cost_kind:
+                        ExtCosts::promise_and_base
+                        | ExtCosts::promise_and_per_promise
+                        | ExtCosts::promise_return,
+                } => &mut breakdown.promises,
+                Cost::ExtCost {
+                    ext_cost_kind:
+                        ExtCosts::storage_read_base
+                        | ExtCosts::storage_read_key_byte
+                        | ExtCosts::storage_read_value_byte
+                        | ExtCosts::storage_has_key_base
+                        | ExtCosts::storage_has_key_byte
+                        | ExtCosts::storage_iter_create_prefix_base
+                        | ExtCosts::storage_iter_create_prefix_byte
+                        | ExtCosts::storage_iter_create_range_base
+                        | ExtCosts::storage_iter_create_from_byte
+                        | ExtCosts::storage_iter_create_to_byte
+                        | ExtCosts::storage_iter_next_base
+                        | ExtCosts::storage_iter_next_key_byte
+                        | ExtCosts::storage_iter_next_value_byte
+                        | ExtCosts::touching_trie_node,
+                } => &mut breakdown.storage_reads,
+                Cost::ExtCost {
+                    ext_cost_kind:
+                        ExtCosts::storage_write_base
+                        | ExtCosts::storage_write_key_byte
+                        | ExtCosts::storage_write_value_byte
+                        | ExtCosts::storage_write_evicted_byte
+                        | ExtCosts::storage_remove_base
+                        | ExtCosts::storage_remove_key_byte
+                        | ExtCosts::storage_remove_ret_value_byte,
+                } => &mut breakdown.storage_writes,
+                Cost::ExtCost { .. } => &mut breakdown.host_functions,
+            };
+            *category += profile_data[cost];
+        }
+        breakdown
+    }
 }
 
 impl Default for ExecutionMetadataView {
@@ -1060,6 +1200,10 @@ impl Default for ExecutionMetadataView {
 
 impl From<ExecutionMetadata> for ExecutionMetadataView {
     fn from(metadata: ExecutionMetadata) -> Self {
+        let gas_breakdown = match &metadata {
+            ExecutionMetadata::V1 => None,
+            ExecutionMetadata::V2(profile_data) => Some(profile_data.into()),
+        };
         let gas_profile = match metadata {
             ExecutionMetadata::V1 => None,
             ExecutionMetadata::V2(profile_data) => {
@@ -1099,7 +1243,7 @@ impl From<ExecutionMetadata> for ExecutionMetadataView {
                 Some(costs)
             }
         };
-        ExecutionMetadataView { version: 1, gas_profile }
+        ExecutionMetadataView { version: 1, gas_profile, gas_breakdown }
     }
 }
 
@@ -1429,6 +1573,75 @@ impl TryFrom<ReceiptView> for Receipt {
     }
 }
 
+/// Receipt waiting in the delayed receipts queue of a shard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DelayedReceiptView {
+    /// Position of the receipt in the queue.
+    pub index: u64,
+    #[serde(flatten)]
+    pub receipt: ReceiptView,
+}
+
+/// A page of the delayed receipts queue of a shard.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DelayedReceiptsView {
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Index of the first receipt in the queue.
+    pub first_index: u64,
+    /// Index the next delayed receipt will be stored at, i.e. the end of the queue.
+    pub next_available_index: u64,
+    pub receipts: Vec<DelayedReceiptView>,
+}
+
+/// Accounts with the contract code of the given hash deployed as of a block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountsByCodeHashView {
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    pub code_hash: CryptoHash,
+    pub account_ids: Vec<AccountId>,
+}
+
+/// Execution of a transaction signed by an account or of a receipt received by it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountHistoryEntryView {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Hash of the transaction or id of the receipt.
+    pub id: CryptoHash,
+    pub kind: AccountHistoryKind,
+}
+
+/// Page of the transactions and receipts executed on an account, newest first.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountHistoryView {
+    pub account_id: AccountId,
+    pub entries: Vec<AccountHistoryEntryView>,
+    /// Cursor to pass to get the next page, if there are more entries.
+    pub next_cursor: Option<String>,
+}
+
+/// Estimated gas burnt by an account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountGasView {
+    pub account_id: AccountId,
+    #[serde(with = "u64_dec_format")]
+    pub gas_burnt: Gas,
+    /// Maximal overestimation of `gas_burnt`.
+    #[serde(with = "u64_dec_format")]
+    pub error: Gas,
+}
+
+/// Accounts which burnt the most gas in an epoch.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GasAttributionView {
+    pub epoch_id: EpochId,
+    /// Heaviest accounts first.
+    pub accounts: Vec<AccountGasView>,
+}
+
 /// Information about this epoch validators and next epoch validators
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
@@ -1451,11 +1664,296 @@ pub struct EpochValidatorInfo {
     pub epoch_height: EpochHeight,
 }
 
+/// Validators which would be selected if the current epoch ended now, projected from the
+/// proposals and block and chunk production stats gathered so far in the epoch.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct EpochValidatorProjectionView {
+    /// Height of the epoch the projection is for, i.e. the epoch after the next one.
+    pub epoch_height: EpochHeight,
+    /// Minimum stake to get a seat in the projected epoch.
+    #[serde(with = "u128_dec_format")]
+    pub seat_price: Balance,
+    /// Projected validators.  Shard assignments are indicative only, since they depend on the
+    /// randomness of the last block of the epoch.
+    pub validators: Vec<NextEpochValidatorInfo>,
+    /// Projected fishermen
+    pub fishermen: Vec<ValidatorStakeView>,
+    /// Validators which would be kicked out, including those not meeting the seat price.
+    pub kickout: Vec<ValidatorKickoutView>,
+}
+
+/// Protocol versions voted for by the block producers of the current epoch in the blocks they
+/// produced so far, and the protocol upgrade they lead to.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ProtocolVersionVotesView {
+    pub epoch_height: EpochHeight,
+    /// Protocol version of the current epoch.
+    pub protocol_version: ProtocolVersion,
+    /// Protocol version of the next epoch, already decided by the votes of the previous epoch.
+    pub next_epoch_protocol_version: ProtocolVersion,
+    /// Total stake of the block producers of the current epoch.
+    #[serde(with = "u128_dec_format")]
+    pub total_stake: Balance,
+    /// A protocol version is adopted when voted for by more than this stake.
+    #[serde(with = "u128_dec_format")]
+    pub threshold_stake: Balance,
+    /// Stake voting for each protocol version, ordered from the newest version.
+    pub versions: Vec<ProtocolVersionStakeView>,
+    /// Block producers ordered by stake.
+    pub validators: Vec<ProtocolVersionVoteView>,
+    /// Upgrade decided for the next epoch or, if there is none, the upgrade the epoch after next
+    /// would get if the current epoch ended now.
+    pub upgrade: Option<ProtocolUpgradeView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ProtocolVersionStakeView {
+    pub protocol_version: ProtocolVersion,
+    #[serde(with = "u128_dec_format")]
+    pub stake: Balance,
+    pub num_validators: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ProtocolVersionVoteView {
+    pub account_id: AccountId,
+    #[serde(with = "u128_dec_format")]
+    pub stake: Balance,
+    /// Latest protocol version of the binary of the validator, as advertised in the last block it
+    /// produced in the epoch, or `None` if it hasn't produced any yet.
+    pub protocol_version: Option<ProtocolVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ProtocolUpgradeView {
+    pub protocol_version: ProtocolVersion,
+    /// Height of the first epoch with the new protocol version.
+    pub epoch_height: EpochHeight,
+    /// Estimated height of the first block with the new protocol version.
+    pub block_height: BlockHeight,
+}
+
+/// Upcoming heights of the current epoch at which a validator is scheduled to produce neither
+/// blocks nor chunks, so that its node can be restarted without missing any.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MaintenanceWindowsView {
+    pub account_id: AccountId,
+    /// Height of the head the windows are computed from.
+    pub head_height: BlockHeight,
+    /// Estimated height of the first block of the next epoch, whose schedule isn't known yet.
+    pub next_epoch_start_height: BlockHeight,
+    /// Ranges of heights, with exclusive ends, ordered by height.  A window ending at
+    /// `next_epoch_start_height` may be followed by production in the next epoch.
+    pub windows: Vec<std::ops::Range<BlockHeight>>,
+}
+
+/// Rewards the validators of the current epoch would get if it went on as it did so far,
+/// computed the same way as at the end of the epoch.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EpochRewardProjectionView {
+    pub epoch_height: EpochHeight,
+    pub protocol_version: ProtocolVersion,
+    /// Total supply as of the latest block, which the rewards are computed from.
+    #[serde(with = "u128_dec_format")]
+    pub total_supply: Balance,
+    /// Duration of the epoch in nanoseconds, extrapolated from the blocks produced so far.
+    pub epoch_duration_ns: u64,
+    /// Amount minted at the end of the epoch, including the reward of the protocol treasury.
+    #[serde(with = "u128_dec_format")]
+    pub minted_amount: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub protocol_treasury_reward: Balance,
+    /// Transaction fees burnt over the epoch, extrapolated from those burnt so far.
+    #[serde(with = "u128_dec_format")]
+    pub burnt_amount: Balance,
+    /// Yearly growth of the total supply at this pace of minting and burning.
+    pub inflation_rate: f64,
+    /// Validators ordered by stake.  Those which would be kicked out get no reward and aren't
+    /// listed.
+    pub validators: Vec<ValidatorRewardProjectionView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ValidatorRewardProjectionView {
+    pub account_id: AccountId,
+    #[serde(with = "u128_dec_format")]
+    pub stake: Balance,
+    pub num_produced_blocks: NumBlocks,
+    pub num_expected_blocks: NumBlocks,
+    pub num_produced_chunks: NumBlocks,
+    pub num_expected_chunks: NumBlocks,
+    #[serde(with = "u128_dec_format")]
+    pub reward: Balance,
+    /// Yearly return on the stake at this reward per epoch, without compounding.
+    pub apy: f64,
+}
+
+/// Summary of an epoch, reported by the client when the next epoch starts.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EpochSummaryView {
+    pub epoch_id: EpochId,
+    pub epoch_height: EpochHeight,
+    pub protocol_version: ProtocolVersion,
+    pub start_height: BlockHeight,
+    pub last_block_height: BlockHeight,
+    /// Set if the node runs a validator, whether or not it had a seat in the epoch.
+    pub validator: Option<EpochSummaryValidatorView>,
+    /// Stake of the block producers of the epoch voting for each protocol version, ordered from
+    /// the newest version.
+    pub protocol_version_votes: Vec<ProtocolVersionStakeView>,
+    /// Upgrade decided by the votes, if any.
+    pub protocol_upgrade: Option<ProtocolUpgradeView>,
+    /// Accounts whose stake for the epoch after the one which started, decided at the end of this
+    /// epoch, differs from their stake in this epoch.
+    pub stake_changes: Vec<StakeChangeView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct StakeChangeView {
+    pub account_id: AccountId,
+    /// Stake in the epoch, zero if the account had no seat in it.
+    #[serde(with = "u128_dec_format")]
+    pub stake: Balance,
+    /// Stake locked for the epoch after the one which started.
+    #[serde(with = "u128_dec_format")]
+    pub new_stake: Balance,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EpochSummaryValidatorView {
+    pub account_id: AccountId,
+    /// Stake in the epoch, zero if the validator had no seat in it.
+    #[serde(with = "u128_dec_format")]
+    pub stake: Balance,
+    pub num_produced_blocks: NumBlocks,
+    pub num_expected_blocks: NumBlocks,
+    pub num_produced_chunks: NumBlocks,
+    pub num_expected_chunks: NumBlocks,
+    /// Average of the shares of the expected blocks and chunks produced, one if none were
+    /// expected.
+    pub uptime: f64,
+    /// Reward for the epoch, added to the stake at its end.
+    #[serde(with = "u128_dec_format")]
+    pub reward: Balance,
+    /// Seat in the epoch after the one which started, decided at the end of this epoch.  The
+    /// stake change is the difference between its stake and `stake`.
+    pub next_seat: EpochSummarySeatView,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EpochSummarySeatView {
+    Seated {
+        #[serde(with = "u128_dec_format")]
+        stake: Balance,
+        shards: Vec<ShardId>,
+    },
+    KickedOut {
+        reason: ValidatorKickoutReason,
+    },
+    NotSeated,
+}
+
+/// Block and chunk producers assigned to each height of an epoch.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ProducerScheduleView {
+    pub epoch_id: EpochId,
+    /// Height of the first block of the epoch, estimated if the epoch hasn't started yet.
+    pub start_height: BlockHeight,
+    /// Producers of each height from `start_height` up to the estimated start of the next epoch.
+    pub heights: Vec<HeightProducersView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct HeightProducersView {
+    pub height: BlockHeight,
+    pub block_producer: AccountId,
+    /// Chunk producer of each shard, indexed by shard id.
+    pub chunk_producers: Vec<AccountId>,
+}
+
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct ValidatorKickoutView {
     pub account_id: AccountId,
     pub reason: ValidatorKickoutReason,
+    /// Minimum percentage of the expected blocks or chunks the validator had to produce, when
+    /// kicked out for producing too few of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub produced_threshold_percent: Option<u8>,
+}
+
+/// Event concerning the validator run by the node, reported when it is at risk of being kicked
+/// out at the end of the epoch.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ValidatorEventView {
+    /// Sequence number of the event, increasing by one with every event.
+    pub id: u64,
+    pub account_id: AccountId,
+    pub epoch_height: EpochHeight,
+    /// Height of the head at which the event was detected.
+    pub block_height: BlockHeight,
+    pub kind: ValidatorEventKind,
+}
+
+/// Events concerning the validator run by the node.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ValidatorEventsView {
+    pub events: Vec<ValidatorEventView>,
+    /// Set if the epoch of the head doesn't span enough blocks yet for the stats of the validator
+    /// to be checked, so no events are reported for it so far.
+    pub insufficient_data: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub enum ValidatorEventKind {
+    /// Share of produced blocks in the epoch so far fell below the warning threshold.
+    MissingBlocks { produced: NumBlocks, expected: NumBlocks },
+    /// Share of produced chunks in the epoch so far fell below the warning threshold.
+    MissingChunks { produced: NumBlocks, expected: NumBlocks },
+    /// The validator would be kicked out if the epoch ended now.
+    Kickout { reason: ValidatorKickoutReason },
+}
+
+/// Block or chunk the validator run by the node was scheduled to produce but didn't.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MissedProductionView {
+    /// Sequence number of the miss, increasing by one with every miss.
+    pub id: u64,
+    pub height: BlockHeight,
+    /// Shard of the missed chunk; `None` for a missed block.
+    pub shard_id: Option<ShardId>,
+    pub reason: MissedProductionReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedProductionReason {
+    /// The node was syncing.
+    NotSynced,
+    /// The parts of the chunks of the previous block didn't arrive in time, so there was
+    /// nothing to build on.
+    MissingChunkParts,
+    /// The block or chunk wasn't ready, or didn't reach the block producer, before the chain
+    /// moved on.
+    Timeout,
+    /// The local key doesn't match the key the validator staked with.
+    SignerError,
+    /// Producing the block or chunk failed.
+    Error,
+}
+
+/// Log line of a contract execution matching the configured prefixes, streamed when the block
+/// including the execution is applied.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ContractLogView {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Id of the receipt or transaction whose execution emitted the log.
+    pub id: CryptoHash,
+    pub executor_id: AccountId,
+    pub log: String,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -1519,6 +2017,86 @@ pub struct GasPriceView {
     pub gas_price: Balance,
 }
 
+/// Gas price of the latest block along with the fullness of the recent blocks and of the recent
+/// chunks of a shard, and the multipliers of the gas price which cover its growth if the blocks
+/// stay that full.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeEstimateView {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    #[serde(with = "u128_dec_format")]
+    pub gas_price: Balance,
+    pub shard_id: ShardId,
+    /// Number of the latest blocks the estimate is based on.
+    pub num_blocks: u64,
+    /// Number of blocks the multipliers cover the growth of the gas price for.
+    pub horizon: BlockHeightDelta,
+    pub percentiles: Vec<FeePercentileView>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeePercentileView {
+    pub percentile: u8,
+    /// Gas used by the blocks divided by their gas limit.
+    pub block_fullness: f64,
+    /// Gas used by the chunks of the shard divided by their gas limit, without the missing chunks.
+    pub chunk_fullness: f64,
+    /// Multiplier of `gas_price` which covers the gas price `horizon` blocks later if the blocks
+    /// are `block_fullness` full.  Never below 1.
+    pub gas_price_multiplier: f64,
+}
+
+/// Runtime config parameters that changed between two protocol versions.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RuntimeConfigDiffView {
+    pub from_protocol_version: ProtocolVersion,
+    pub to_protocol_version: ProtocolVersion,
+    pub changes: Vec<RuntimeConfigChange>,
+}
+
+/// Host function call or storage access made while executing a receipt.  Storage keys are the
+/// keys used by the contract, without the account prefix.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ReceiptTraceEvent {
+    HostFunctionCall {
+        name: String,
+    },
+    StorageRead {
+        #[serde(rename = "key_base64", with = "base64_format")]
+        key: Vec<u8>,
+        /// `None` if there is no value under the key.
+        value_len: Option<u64>,
+    },
+    StorageHasKey {
+        #[serde(rename = "key_base64", with = "base64_format")]
+        key: Vec<u8>,
+        found: bool,
+    },
+    StorageWrite {
+        #[serde(rename = "key_base64", with = "base64_format")]
+        key: Vec<u8>,
+        value_len: u64,
+    },
+    StorageRemove {
+        #[serde(rename = "key_base64", with = "base64_format")]
+        key: Vec<u8>,
+    },
+}
+
+/// Result of replaying a past receipt with tracing enabled.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReceiptTraceView {
+    pub receipt_id: CryptoHash,
+    /// Block in which the receipt was executed.
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Outcome of the replay, including the logs.  It matches the recorded outcome unless the
+    /// node's runtime behaves differently from the one which executed the receipt originally.
+    pub outcome: ExecutionOutcomeView,
+    pub events: Vec<ReceiptTraceEvent>,
+}
+
 /// It is a [serializable view] of [`StateChangesRequest`].
 ///
 /// [serializable view]: ./index.html
@@ -1535,6 +2113,9 @@ pub enum StateChangesRequestView {
     AllAccessKeyChanges {
         account_ids: Vec<AccountId>,
     },
+    AccessKeyChangesByPublicKey {
+        public_keys: Vec<PublicKey>,
+    },
     ContractCodeChanges {
         account_ids: Vec<AccountId>,
     },
@@ -1557,6 +2138,9 @@ impl From<StateChangesRequestView> for StateChangesRequest {
             StateChangesRequestView::AllAccessKeyChanges { account_ids } => {
                 Self::AllAccessKeyChanges { account_ids }
             }
+            StateChangesRequestView::AccessKeyChangesByPublicKey { public_keys } => {
+                Self::AccessKeyChangesByPublicKey { public_keys }
+            }
             StateChangesRequestView::ContractCodeChanges { account_ids } => {
                 Self::ContractCodeChanges { account_ids }
             }
@@ -4,10 +4,16 @@ extern crate bencher;
 use bencher::Bencher;
 use rand::random;
 
+use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::trie_key::TrieKey;
+use near_primitives::types::StateChangeCause;
 use near_store::test_utils::create_tries;
 use near_store::Trie;
 
+/// Number of keys written by a contract in the bulk write benchmarks.
+const BULK_WRITE_KEYS: u32 = 1000;
+
 fn rand_bytes() -> Vec<u8> {
     (0..10).map(|_| random::<u8>()).collect()
 }
@@ -49,5 +55,62 @@ fn trie_update(bench: &mut Bencher) {
     });
 }
 
-benchmark_group!(benches, trie_lookup, trie_update);
+fn bulk_write_keys() -> Vec<TrieKey> {
+    (0..BULK_WRITE_KEYS)
+        .map(|i| TrieKey::ContractData {
+            account_id: "alice.near".parse().unwrap(),
+            key: i.to_le_bytes().to_vec(),
+        })
+        .collect()
+}
+
+/// A contract writing many keys, one receipt at a time, with the changes finalized once.
+fn trie_update_bulk_write(bench: &mut Bencher) {
+    let tries = create_tries();
+    let keys = bulk_write_keys();
+    let cause = StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() };
+
+    bench.iter(|| {
+        let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), Trie::empty_root());
+        for key in &keys {
+            trie_update.set(key.clone(), rand_bytes());
+            trie_update.commit(cause.clone());
+        }
+        let trie_changes = trie_update.finalize().unwrap().0;
+        let (store_update, root) =
+            tries.apply_all(&trie_changes, ShardUId::single_shard()).unwrap();
+        store_update.commit().unwrap();
+        root
+    });
+}
+
+/// The same writes finalized and applied after every key, rebuilding the shared paths each time.
+fn trie_update_bulk_write_per_key(bench: &mut Bencher) {
+    let tries = create_tries();
+    let keys = bulk_write_keys();
+    let cause = StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() };
+
+    bench.iter(|| {
+        let mut root = Trie::empty_root();
+        for key in &keys {
+            let mut trie_update = tries.new_trie_update(ShardUId::single_shard(), root);
+            trie_update.set(key.clone(), rand_bytes());
+            trie_update.commit(cause.clone());
+            let trie_changes = trie_update.finalize().unwrap().0;
+            let (store_update, new_root) =
+                tries.apply_all(&trie_changes, ShardUId::single_shard()).unwrap();
+            store_update.commit().unwrap();
+            root = new_root;
+        }
+        root
+    });
+}
+
+benchmark_group!(
+    benches,
+    trie_lookup,
+    trie_update,
+    trie_update_bulk_write,
+    trie_update_bulk_write_per_key
+);
 benchmark_main!(benches);
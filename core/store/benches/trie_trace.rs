@@ -0,0 +1,155 @@
+//! Trie benchmarks replaying a recorded access trace.
+//!
+//! Record a trace on a node with mainnet data with
+//! `neard view_state apply_range --sequential --record_trie_trace <file> ...` and point the
+//! benchmarks at it with `NEAR_TRIE_TRACE=<file> cargo bench -p near-store --bench trie_trace`.
+//! Without `NEAR_TRIE_TRACE` a small synthetic trace is used, which only checks that the
+//! benchmarks run; its numbers say nothing about the performance on real data.
+#[macro_use]
+extern crate criterion;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use criterion::{black_box, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::TrieCacheMode;
+use near_store::access_trace::{read_trace, TrieAccessOp, TrieAccessRecord};
+use near_store::test_utils::create_tries;
+use near_store::{ShardTries, Trie};
+
+/// Number of recorded keys to seek to in the iteration benchmark.
+const NUM_SEEKS: usize = 1000;
+/// Number of items read after each seek in the iteration benchmark.
+const ITEMS_PER_SEEK: usize = 10;
+
+const CACHE_MODES: [(&str, TrieCacheMode); 2] =
+    [("shard", TrieCacheMode::CachingShard), ("chunk", TrieCacheMode::CachingChunk)];
+
+fn load_trace() -> Vec<TrieAccessRecord> {
+    match std::env::var_os("NEAR_TRIE_TRACE") {
+        Some(path) => {
+            let trace = read_trace(Path::new(&path)).expect("failed to read NEAR_TRIE_TRACE");
+            eprintln!("Replaying {} trie accesses from {:?}", trace.len(), path);
+            trace
+        }
+        None => {
+            eprintln!("NEAR_TRIE_TRACE is not set, using a synthetic trace");
+            synthetic_trace()
+        }
+    }
+}
+
+/// Mimics the layout of the state keys: a column byte followed by an account id and, for
+/// contract data, a key suffix.
+fn synthetic_trace() -> Vec<TrieAccessRecord> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let accounts: Vec<Vec<u8>> =
+        (0..500).map(|i| format!("account{}.near", i * 7919 % 100_000).into_bytes()).collect();
+    (0..20_000)
+        .map(|_| {
+            let mut key = vec![rng.gen_range(0, 10)];
+            key.extend_from_slice(&accounts[rng.gen_range(0, accounts.len())]);
+            if key[0] == 9 {
+                key.push(b',');
+                key.extend((0..rng.gen_range(1, 40)).map(|_| rng.gen::<u8>()));
+            }
+            let op = match rng.gen_range(0, 10) {
+                0..=6 => TrieAccessOp::Get,
+                7..=8 => TrieAccessOp::Insert,
+                _ => TrieAccessOp::Delete,
+            };
+            let value_len = if op == TrieAccessOp::Delete { 0 } else { rng.gen_range(1, 300) };
+            TrieAccessRecord { op, key, value_len }
+        })
+        .collect()
+}
+
+/// Populates a trie with all the values read by the trace, with their recorded lengths.
+fn populate(trace: &[TrieAccessRecord]) -> (ShardTries, CryptoHash) {
+    let mut state = HashMap::new();
+    for record in trace {
+        if record.op == TrieAccessOp::Get && record.value_len > 0 {
+            state.entry(record.key.clone()).or_insert_with(|| vec![1u8; record.value_len as usize]);
+        }
+    }
+    let tries = create_tries();
+    let shard_uid = ShardUId::single_shard();
+    let trie = tries.get_trie_for_shard(shard_uid);
+    let changes = state.into_iter().map(|(key, value)| (key, Some(value)));
+    let trie_changes = trie.update(&Trie::empty_root(), changes).unwrap();
+    let (store_update, root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+    store_update.commit().unwrap();
+    (tries, root)
+}
+
+fn trie_trace(c: &mut Criterion) {
+    let trace = load_trace();
+    let (tries, root) = populate(&trace);
+    let shard_uid = ShardUId::single_shard();
+    let gets: Vec<&[u8]> = trace
+        .iter()
+        .filter(|record| record.op == TrieAccessOp::Get)
+        .map(|record| record.key.as_slice())
+        .collect();
+    let changes: Vec<(Vec<u8>, Option<Vec<u8>>)> = trace
+        .iter()
+        .filter_map(|record| match record.op {
+            TrieAccessOp::Get => None,
+            TrieAccessOp::Insert => {
+                Some((record.key.clone(), Some(vec![2u8; record.value_len as usize])))
+            }
+            TrieAccessOp::Delete => Some((record.key.clone(), None)),
+        })
+        .collect();
+
+    for (mode_name, mode) in CACHE_MODES {
+        // A new trie for every iteration, like for every applied chunk, so that the chunk cache
+        // starts empty.  The shard cache is shared and stays warm.
+        let new_trie = || {
+            let trie = tries.get_trie_for_shard(shard_uid);
+            trie.set_cache_mode(mode);
+            trie
+        };
+        c.bench_function(&format!("trie_trace_get_{}", mode_name), |bench| {
+            bench.iter_batched(
+                new_trie,
+                |trie| {
+                    for key in &gets {
+                        black_box(trie.get(&root, key).unwrap());
+                    }
+                },
+                BatchSize::PerIteration,
+            )
+        });
+        c.bench_function(&format!("trie_trace_update_{}", mode_name), |bench| {
+            bench.iter_batched(
+                || (new_trie(), changes.clone()),
+                |(trie, changes)| black_box(trie.update(&root, changes.into_iter()).unwrap()),
+                BatchSize::PerIteration,
+            )
+        });
+        c.bench_function(&format!("trie_trace_iterate_{}", mode_name), |bench| {
+            bench.iter_batched(
+                new_trie,
+                |trie| {
+                    let mut iter = trie.iter(&root).unwrap();
+                    for key in gets.iter().take(NUM_SEEKS) {
+                        iter.seek(key).unwrap();
+                        for item in iter.by_ref().take(ITEMS_PER_SEEK) {
+                            black_box(item.unwrap());
+                        }
+                    }
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+}
+
+criterion_group!(benches, trie_trace);
+criterion_main!(benches);
@@ -0,0 +1,161 @@
+//! Optional index of the transactions and receipts executed on every account, i.e. the
+//! transactions signed by it and the receipts received by it, so that the history of an account
+//! can be paged through without an external indexer.
+//!
+//! The rows are written for every applied chunk, including the chunks of blocks on forks, so the
+//! readers must check that the block of an entry is on the canonical chain.  The keys start with
+//! the account id followed by the inverted block height, so that iterating over the rows of an
+//! account returns the newest entries first.
+use std::io;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountHistoryKind, AccountId, BlockHeight, ShardId};
+
+use crate::{DBCol, Store, StoreUpdate};
+
+/// Execution of a transaction or a receipt on an account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountHistoryEntry {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    pub shard_id: ShardId,
+    /// Hash of the transaction or id of the receipt.
+    pub id: CryptoHash,
+    pub kind: AccountHistoryKind,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct AccountHistoryValue {
+    shard_id: ShardId,
+    kind: AccountHistoryKind,
+}
+
+/// Entries of an account read from the index, along with the cursor of the entry following them
+/// if there are more.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccountHistoryPage {
+    pub entries: Vec<AccountHistoryEntry>,
+    pub next_cursor: Option<Vec<u8>>,
+}
+
+fn account_prefix(account_id: &AccountId) -> Vec<u8> {
+    let mut prefix = account_id.as_ref().as_bytes().to_vec();
+    // Account ids can't contain zero bytes, so the rows of an account aren't mixed with those of
+    // the accounts whose ids it is a prefix of.
+    prefix.push(0);
+    prefix
+}
+
+pub fn account_history_key(
+    account_id: &AccountId,
+    block_height: BlockHeight,
+    block_hash: &CryptoHash,
+    id: &CryptoHash,
+) -> Vec<u8> {
+    let mut key = account_prefix(account_id);
+    key.extend_from_slice(&(u64::MAX - block_height).to_be_bytes());
+    key.extend_from_slice(block_hash.as_ref());
+    key.extend_from_slice(id.as_ref());
+    key
+}
+
+pub fn set_account_history_entry(
+    store_update: &mut StoreUpdate,
+    account_id: &AccountId,
+    entry: &AccountHistoryEntry,
+) {
+    let key = account_history_key(account_id, entry.block_height, &entry.block_hash, &entry.id);
+    store_update
+        .set_ser(
+            DBCol::ColAccountHistory,
+            &key,
+            &AccountHistoryValue { shard_id: entry.shard_id, kind: entry.kind },
+        )
+        .expect("Borsh cannot fail");
+}
+
+/// Returns up to `limit` entries of the account, newest first, starting from the one with the
+/// given cursor, which must be a cursor returned by a previous call for the same account.
+pub fn get_account_history(
+    store: &Store,
+    account_id: &AccountId,
+    cursor: Option<&[u8]>,
+    limit: usize,
+) -> io::Result<AccountHistoryPage> {
+    let prefix = account_prefix(account_id);
+    let lower_bound = [prefix.as_slice(), cursor.unwrap_or_default()].concat();
+    let mut entries = vec![];
+    let mut next_cursor = None;
+    for (key, value) in store.iter_prefix_from(DBCol::ColAccountHistory, &prefix, &lower_bound) {
+        let suffix = &key[prefix.len()..];
+        if entries.len() == limit {
+            next_cursor = Some(suffix.to_vec());
+            break;
+        }
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid account history key of {}", account_id),
+            )
+        };
+        if suffix.len() != 8 + 32 + 32 {
+            return Err(invalid());
+        }
+        let mut inverted_height = [0; 8];
+        inverted_height.copy_from_slice(&suffix[..8]);
+        let value = AccountHistoryValue::try_from_slice(&value)?;
+        entries.push(AccountHistoryEntry {
+            block_height: u64::MAX - u64::from_be_bytes(inverted_height),
+            block_hash: CryptoHash::try_from(&suffix[8..40]).map_err(|_| invalid())?,
+            shard_id: value.shard_id,
+            id: CryptoHash::try_from(&suffix[40..]).map_err(|_| invalid())?,
+            kind: value.kind,
+        });
+    }
+    Ok(AccountHistoryPage { entries, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::hash;
+    use near_primitives::types::AccountHistoryKind;
+
+    use super::{get_account_history, set_account_history_entry, AccountHistoryEntry};
+    use crate::test_utils::create_test_store;
+
+    #[test]
+    fn test_account_history() {
+        let store = create_test_store();
+        let entry = |block_height, id: &[u8]| AccountHistoryEntry {
+            block_height,
+            block_hash: hash(&block_height.to_le_bytes()),
+            shard_id: 0,
+            id: hash(id),
+            kind: AccountHistoryKind::Receipt,
+        };
+        let mut store_update = store.store_update();
+        for (account_id, entry) in [
+            ("alice.near", entry(5, b"a")),
+            ("alice.near", entry(7, b"b")),
+            ("alice.near", entry(6, b"c")),
+            ("alice.near.x", entry(8, b"d")),
+            ("bob.near", entry(9, b"e")),
+        ] {
+            set_account_history_entry(&mut store_update, &account_id.parse().unwrap(), &entry);
+        }
+        store_update.commit().unwrap();
+
+        let account_id = "alice.near".parse().unwrap();
+        let first = get_account_history(&store, &account_id, None, 2).unwrap();
+        assert_eq!(first.entries, vec![entry(7, b"b"), entry(6, b"c")]);
+        let second =
+            get_account_history(&store, &account_id, first.next_cursor.as_deref(), 2).unwrap();
+        assert_eq!(second.entries, vec![entry(5, b"a")]);
+        assert_eq!(second.next_cursor, None);
+        assert!(get_account_history(&store, &"carol.near".parse().unwrap(), None, 2)
+            .unwrap()
+            .entries
+            .is_empty());
+    }
+}
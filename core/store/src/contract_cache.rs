@@ -0,0 +1,343 @@
+//! Configurable on-disk cache for compiled contracts.
+//!
+//! Compiled contracts are kept either in `DBCol::ColCachedContractCode` of the main database or in
+//! a separate directory with one file per contract.  Optionally the total size of the cache is
+//! bounded, in which case the least recently used contracts are evicted first.
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use near_primitives::serialize::{from_base, to_base};
+use near_primitives::types::CompiledContractCache;
+
+use crate::{metrics, DBCol, Store, StoreCompiledContractCache};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ContractCacheBackend {
+    /// Keep compiled contracts in the main database.
+    Store,
+    /// Keep compiled contracts as separate files in the given directory.  Relative paths are
+    /// resolved against the home directory of the node.
+    Directory { path: PathBuf },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct ContractCacheConfig {
+    pub backend: ContractCacheBackend,
+    /// Maximum total size of the compiled contracts in bytes.  `None` means unbounded.
+    pub max_size: Option<u64>,
+    /// Number of most recently used contracts loaded into memory on startup.
+    pub warmup_contracts: usize,
+}
+
+impl Default for ContractCacheConfig {
+    fn default() -> Self {
+        Self { backend: ContractCacheBackend::Store, max_size: None, warmup_contracts: 0 }
+    }
+}
+
+/// Storage of the compiled contracts which supports removal and enumeration of the entries.
+trait ContractCacheStorage: Send + Sync {
+    fn read(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+    fn write(&self, key: &[u8], value: &[u8]) -> io::Result<()>;
+    fn remove(&self, key: &[u8]) -> io::Result<()>;
+    /// Returns keys and sizes of all the entries, least recently used first.
+    fn entries(&self) -> io::Result<Vec<(Vec<u8>, u64)>>;
+}
+
+impl ContractCacheStorage for StoreCompiledContractCache {
+    fn read(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.get(key)
+    }
+
+    fn write(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.put(key, value)
+    }
+
+    fn remove(&self, key: &[u8]) -> io::Result<()> {
+        let mut store_update = self.store.store_update();
+        store_update.delete(DBCol::ColCachedContractCode, key);
+        store_update.commit()
+    }
+
+    fn entries(&self) -> io::Result<Vec<(Vec<u8>, u64)>> {
+        // The database does not track access times, so the order is arbitrary.
+        Ok(self
+            .store
+            .iter(DBCol::ColCachedContractCode)
+            .map(|(key, value)| (key.to_vec(), value.len() as u64))
+            .collect())
+    }
+}
+
+/// Keeps every compiled contract in a separate file named after the base58 of its key.
+pub struct DirectoryCompiledContractCache {
+    path: PathBuf,
+}
+
+impl DirectoryCompiledContractCache {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        fs::create_dir_all(path)?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+
+    fn file_path(&self, key: &[u8]) -> PathBuf {
+        self.path.join(to_base(key))
+    }
+}
+
+impl ContractCacheStorage for DirectoryCompiledContractCache {
+    fn read(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.file_path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn write(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        // Write to a temporary file first so that a crash never leaves a truncated entry behind.
+        let path = self.file_path(key);
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, value)?;
+        fs::rename(&tmp_path, &path)
+    }
+
+    fn remove(&self, key: &[u8]) -> io::Result<()> {
+        match fs::remove_file(self.file_path(key)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn entries(&self) -> io::Result<Vec<(Vec<u8>, u64)>> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let key = match entry.file_name().to_str().and_then(|name| from_base(name).ok()) {
+                Some(key) if metadata.is_file() => key,
+                // Leftover temporary files and anything else not written by us.
+                _ => continue,
+            };
+            entries.push((metadata.modified()?, key, metadata.len()));
+        }
+        // Access times are not reliable across filesystems, use modification times instead.
+        entries.sort();
+        Ok(entries.into_iter().map(|(_, key, size)| (key, size)).collect())
+    }
+}
+
+impl CompiledContractCache for DirectoryCompiledContractCache {
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.write(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.read(key)
+    }
+}
+
+/// Index of the cached entries used to enforce the size limit.
+struct LruIndex {
+    /// Sizes of the entries in least recently used order.
+    entries: LruCache<Vec<u8>, u64>,
+    total_size: u64,
+}
+
+/// Compiled contract cache on top of one of the configured backends.  Reports hit rate, enforces
+/// the size limit and serves warmed up contracts from memory.
+pub struct ConfiguredCompiledContractCache {
+    storage: Box<dyn ContractCacheStorage>,
+    max_size: Option<u64>,
+    /// Only maintained when the cache size is bounded or warm up is requested, since building it
+    /// requires scanning the whole cache.
+    index: Option<Mutex<LruIndex>>,
+    warm: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl ConfiguredCompiledContractCache {
+    fn new(
+        storage: Box<dyn ContractCacheStorage>,
+        max_size: Option<u64>,
+        warmup_contracts: usize,
+    ) -> io::Result<Self> {
+        let index = if max_size.is_some() || warmup_contracts > 0 {
+            let mut index = LruIndex { entries: LruCache::unbounded(), total_size: 0 };
+            for (key, size) in storage.entries()? {
+                index.total_size += size;
+                index.entries.put(key, size);
+            }
+            metrics::COMPILED_CONTRACT_CACHE_SIZE.set(index.total_size as i64);
+            Some(Mutex::new(index))
+        } else {
+            None
+        };
+        let cache = Self { storage, max_size, index, warm: Default::default() };
+        cache.evict_if_needed()?;
+        cache.warm_up(warmup_contracts)?;
+        Ok(cache)
+    }
+
+    /// Loads up to `num_contracts` most recently used contracts into memory.
+    fn warm_up(&self, num_contracts: usize) -> io::Result<()> {
+        let keys: Vec<Vec<u8>> = match &self.index {
+            Some(index) => {
+                let index = index.lock().unwrap();
+                index.entries.iter().take(num_contracts).map(|(key, _)| key.clone()).collect()
+            }
+            None => return Ok(()),
+        };
+        let mut warm = self.warm.lock().unwrap();
+        for key in keys {
+            if let Some(value) = self.storage.read(&key)? {
+                warm.insert(key, value);
+            }
+        }
+        if !warm.is_empty() {
+            info!(target: "store", "Loaded {} compiled contracts into memory", warm.len());
+        }
+        Ok(())
+    }
+
+    fn evict_if_needed(&self) -> io::Result<()> {
+        let (index, max_size) = match (&self.index, self.max_size) {
+            (Some(index), Some(max_size)) => (index, max_size),
+            _ => return Ok(()),
+        };
+        let mut index = index.lock().unwrap();
+        while index.total_size > max_size {
+            let (key, size) = match index.entries.pop_lru() {
+                Some(entry) => entry,
+                None => break,
+            };
+            self.storage.remove(&key)?;
+            self.warm.lock().unwrap().remove(&key);
+            index.total_size -= size;
+            metrics::COMPILED_CONTRACT_CACHE_EVICTIONS.inc();
+        }
+        metrics::COMPILED_CONTRACT_CACHE_SIZE.set(index.total_size as i64);
+        Ok(())
+    }
+}
+
+impl CompiledContractCache for ConfiguredCompiledContractCache {
+    fn put(&self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.storage.write(key, value)?;
+        if let Some(index) = &self.index {
+            let mut index = index.lock().unwrap();
+            let size = value.len() as u64;
+            if let Some(old_size) = index.entries.put(key.to_vec(), size) {
+                index.total_size -= old_size;
+            }
+            index.total_size += size;
+        }
+        self.evict_if_needed()
+    }
+
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if let Some(index) = &self.index {
+            // Mark as recently used.
+            index.lock().unwrap().entries.get(&key.to_vec());
+        }
+        let value = match self.warm.lock().unwrap().get(key) {
+            Some(value) => Some(value.clone()),
+            None => self.storage.read(key)?,
+        };
+        if value.is_some() {
+            metrics::COMPILED_CONTRACT_CACHE_HITS.inc();
+        } else {
+            metrics::COMPILED_CONTRACT_CACHE_MISSES.inc();
+        }
+        Ok(value)
+    }
+}
+
+/// Creates compiled contract cache according to the config.
+pub fn create_compiled_contract_cache(
+    home_dir: &Path,
+    store: &Store,
+    config: &ContractCacheConfig,
+) -> io::Result<Arc<ConfiguredCompiledContractCache>> {
+    let storage: Box<dyn ContractCacheStorage> = match &config.backend {
+        ContractCacheBackend::Store => {
+            Box::new(StoreCompiledContractCache { store: store.clone() })
+        }
+        ContractCacheBackend::Directory { path } => {
+            Box::new(DirectoryCompiledContractCache::new(&home_dir.join(path))?)
+        }
+    };
+    if config.max_size == Some(0) {
+        warn!(target: "store", "Compiled contract cache size is set to 0, contracts will be recompiled on every call");
+    }
+    Ok(Arc::new(ConfiguredCompiledContractCache::new(
+        storage,
+        config.max_size,
+        config.warmup_contracts,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_test_store;
+
+    fn create_cache(
+        dir: &Path,
+        max_size: Option<u64>,
+        warmup_contracts: usize,
+    ) -> Arc<ConfiguredCompiledContractCache> {
+        let config = ContractCacheConfig {
+            backend: ContractCacheBackend::Directory { path: dir.to_path_buf() },
+            max_size,
+            warmup_contracts,
+        };
+        create_compiled_contract_cache(dir, &create_test_store(), &config).unwrap()
+    }
+
+    #[test]
+    fn test_directory_cache_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = create_cache(dir.path(), Some(20), 0);
+        cache.put(b"a", &[0; 10]).unwrap();
+        cache.put(b"b", &[1; 10]).unwrap();
+        assert!(cache.get(b"a").unwrap().is_some());
+        cache.put(b"c", &[2; 10]).unwrap();
+        assert!(cache.get(b"a").unwrap().is_some());
+        assert!(cache.get(b"b").unwrap().is_none());
+        assert_eq!(cache.get(b"c").unwrap(), Some(vec![2; 10]));
+    }
+
+    #[test]
+    fn test_directory_cache_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let cache = create_cache(dir.path(), None, 0);
+            cache.put(b"a", &[0; 10]).unwrap();
+            cache.put(b"b", &[1; 10]).unwrap();
+        }
+        let cache = create_cache(dir.path(), Some(10), 1);
+        assert_eq!(cache.warm.lock().unwrap().len(), 1);
+        assert_eq!(cache.get(b"b").unwrap(), Some(vec![1; 10]));
+        assert!(cache.get(b"a").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_store_cache() {
+        let store = create_test_store();
+        let config = ContractCacheConfig { max_size: Some(10), ..Default::default() };
+        let cache = create_compiled_contract_cache(Path::new("."), &store, &config).unwrap();
+        cache.put(b"a", &[0; 10]).unwrap();
+        cache.put(b"b", &[1; 10]).unwrap();
+        assert!(cache.get(b"a").unwrap().is_none());
+        assert_eq!(store.get(DBCol::ColCachedContractCode, b"b").unwrap(), Some(vec![1; 10]));
+    }
+}
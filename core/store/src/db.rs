@@ -1,5 +1,9 @@
+use crate::db::compaction_gc::{canonical_block, written_blocks, GcWatermark};
 use crate::db::refcount::merge_refcounted_records;
+use crate::StoreResources;
 use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::block::Tip;
+use near_primitives::types::BlockHeight;
 use near_primitives::version::DbVersion;
 use once_cell::sync::Lazy;
 use rocksdb::checkpoint::Checkpoint;
@@ -12,10 +16,12 @@ use std::collections::HashMap;
 use std::io;
 use std::path::Path;
 use std::sync::atomic::Ordering;
-use std::sync::{Condvar, Mutex, RwLock};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use strum::{EnumCount, EnumIter};
 use tracing::{debug, error, info, warn};
 
+pub(crate) mod async_commit;
+pub(crate) mod compaction_gc;
 pub(crate) mod refcount;
 pub(crate) mod v6_to_v7;
 
@@ -265,6 +271,21 @@ pub enum DBCol {
     /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
     /// - *Column type*: StateChangesForSplitStates
     ColStateChangesForSplitStates = 49,
+    /// Accounts which had contract code deployed, indexed by the hash of the code.  Rows aren't
+    /// removed when the code of the account changes, so the current code hash must be checked.
+    /// - *Rows*: code hash (CryptoHash) || account id
+    /// - *Column type*: empty
+    ColContractAccounts = 50,
+    /// Transactions and receipts executed on every account, newest first.  Only written when the
+    /// account history index is enabled.
+    /// - *Rows*: account id || 0 || (u64::MAX - block height) || block hash || tx hash or receipt id
+    /// - *Column type*: AccountHistoryValue
+    ColAccountHistory = 51,
+    /// Refcount records of `ColState` written by a commit and not merged into it yet, see
+    /// `AsyncStateCommitDB`.
+    /// - *Rows*: sequence number of the commit (u64, big endian)
+    /// - *Column type*: Vec<(key, refcount record)>
+    ColStateWriteIntents = 52,
 }
 
 impl std::fmt::Display for DBCol {
@@ -322,6 +343,9 @@ impl std::fmt::Display for DBCol {
             Self::ColStateChangesForSplitStates => {
                 "state changes indexed by block hash and shard id"
             }
+            Self::ColContractAccounts => "accounts indexed by contract code hash",
+            Self::ColAccountHistory => "transactions and receipts indexed by account",
+            Self::ColStateWriteIntents => "state writes not merged yet",
         };
         write!(formatter, "{}", desc)
     }
@@ -355,6 +379,8 @@ pub static SHOULD_COL_GC: [bool; DBCol::COUNT] = {
     col_gc[DBCol::ColEpochValidatorInfo as usize] = false; // https://github.com/nearprotocol/nearcore/pull/2952
     col_gc[DBCol::ColEpochStart as usize] = false; // https://github.com/nearprotocol/nearcore/pull/2952
     col_gc[DBCol::ColCachedContractCode as usize] = false;
+    col_gc[DBCol::ColContractAccounts as usize] = false;
+    col_gc[DBCol::ColStateWriteIntents as usize] = false; // removed once merged
     col_gc
 };
 
@@ -366,6 +392,8 @@ pub static SKIP_COL_GC: [bool; DBCol::COUNT] = {
     col_gc[DBCol::ColStateHeaders as usize] = true;
     // True until #2515
     col_gc[DBCol::ColStateParts as usize] = true;
+    // Only written when the account history index is enabled
+    col_gc[DBCol::ColAccountHistory as usize] = true;
     col_gc
 };
 
@@ -391,6 +419,7 @@ pub const LARGEST_TARGET_HEIGHT_KEY: &[u8; 21] = b"LARGEST_TARGET_HEIGHT";
 pub const VERSION_KEY: &[u8; 7] = b"VERSION";
 pub const GENESIS_JSON_HASH_KEY: &[u8; 17] = b"GENESIS_JSON_HASH";
 pub const GENESIS_STATE_ROOTS_KEY: &[u8; 19] = b"GENESIS_STATE_ROOTS";
+pub const VALIDATOR_STATS_KEY: &[u8; 15] = b"VALIDATOR_STATS";
 
 pub struct DBTransaction {
     pub ops: Vec<DBOp>,
@@ -443,6 +472,9 @@ pub struct RocksDB {
     check_free_space_interval: u16,
     free_space_threshold: bytesize::ByteSize,
 
+    /// Set if the rows of the columns collected by compaction are removed below the GC tail.
+    gc_watermark: Option<Arc<GcWatermark>>,
+
     // RAII-style of keeping track of the number of instances of RocksDB in a global variable.
     _instance_counter: InstanceCounter,
 }
@@ -471,6 +503,7 @@ pub struct RocksDBOptions {
     free_space_threshold: bytesize::ByteSize,
     warn_treshold: bytesize::ByteSize,
     enable_statistics: bool,
+    resources: StoreResources,
 }
 
 /// Sets [`RocksDBOptions::check_free_space_interval`] to 256,
@@ -486,6 +519,7 @@ impl Default for RocksDBOptions {
             free_space_threshold: bytesize::ByteSize::mb(16),
             warn_treshold: bytesize::ByteSize::mb(256),
             enable_statistics: false,
+            resources: StoreResources::default(),
         }
     }
 }
@@ -528,11 +562,19 @@ impl RocksDBOptions {
         self
     }
 
+    /// Sizes of the block caches and the number of open files.
+    pub fn resources(mut self, resources: StoreResources) -> Self {
+        self.resources = resources;
+        self
+    }
+
     /// Opens a read only database.
     pub fn read_only<P: AsRef<std::path::Path>>(self, path: P) -> Result<RocksDB, DBError> {
         use strum::IntoEnumIterator;
-        let options = self.rocksdb_options.unwrap_or_else(rocksdb_options);
-        let cf_with_opts = DBCol::iter().map(|col| (col_name(col), rocksdb_column_options(col)));
+        let resources = self.resources;
+        let options = self.rocksdb_options.unwrap_or_else(|| rocksdb_options(&resources));
+        let cf_with_opts =
+            DBCol::iter().map(|col| (col_name(col), rocksdb_column_options(col, &resources)));
         let db = DB::open_cf_with_opts_for_read_only(&options, path, cf_with_opts, false)?;
         let cfs = DBCol::iter()
             .map(|col| db.cf_handle(&col_name(col)).unwrap() as *const ColumnFamily)
@@ -545,22 +587,35 @@ impl RocksDBOptions {
             check_free_space_interval: self.check_free_space_interval,
             check_free_space_counter: std::sync::atomic::AtomicU16::new(0),
             free_space_threshold: self.free_space_threshold,
+            gc_watermark: None,
             _instance_counter: InstanceCounter::new(),
         })
     }
 
-    /// Opens the database in read/write mode.
+    /// Opens the database in read/write mode.  Unless the column families are given, the rows of
+    /// the columns collected by compaction are removed below the GC tail, see
+    /// [`compaction_gc`].  The GC of the chain then leaves them to compaction.
     pub fn read_write<P: AsRef<std::path::Path>>(self, path: P) -> Result<RocksDB, DBError> {
         use strum::IntoEnumIterator;
-        let mut options = self.rocksdb_options.unwrap_or_else(rocksdb_options);
+        let resources = self.resources;
+        let mut options = self.rocksdb_options.unwrap_or_else(|| rocksdb_options(&resources));
         if self.enable_statistics {
             options = enable_statistics(options);
         }
         let cf_names =
             self.cf_names.unwrap_or_else(|| DBCol::iter().map(|col| col_name(col)).collect());
+        let gc_watermark = self.cf_descriptors.is_none().then(GcWatermark::new);
         let cf_descriptors = self.cf_descriptors.unwrap_or_else(|| {
             DBCol::iter()
-                .map(|col| ColumnFamilyDescriptor::new(col_name(col), rocksdb_column_options(col)))
+                .map(|col| {
+                    let mut opts = rocksdb_column_options(col, &resources);
+                    if let Some(gc_watermark) = gc_watermark.as_ref() {
+                        if compaction_gc::is_collected_by_compaction(col) {
+                            gc_watermark.set_compaction_filter(col, &mut opts);
+                        }
+                    }
+                    ColumnFamilyDescriptor::new(col_name(col), opts)
+                })
                 .collect()
         });
         let db = DB::open_cf_descriptors(&options, path, cf_descriptors)?;
@@ -575,15 +630,20 @@ impl RocksDBOptions {
         }
         let cfs =
             cf_names.iter().map(|n| db.cf_handle(n).unwrap() as *const ColumnFamily).collect();
-        Ok(RocksDB {
+        let db = RocksDB {
             db,
             db_opt: options,
             cfs,
             check_free_space_interval: self.check_free_space_interval,
             check_free_space_counter: std::sync::atomic::AtomicU16::new(0),
             free_space_threshold: self.free_space_threshold,
+            gc_watermark,
             _instance_counter: InstanceCounter::new(),
-        })
+        };
+        if let Some(gc_watermark) = db.gc_watermark.as_ref() {
+            db.load_gc_watermark(gc_watermark)?;
+        }
+        Ok(db)
     }
 
     pub fn enable_statistics(mut self) -> Self {
@@ -601,6 +661,7 @@ pub trait Database: Sync + Send {
         DBTransaction { ops: Vec::new() }
     }
     fn get(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError>;
+    fn get_without_rc_logic(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError>;
     fn iter<'a>(&'a self, column: DBCol) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
     fn iter_without_rc_logic<'a>(
         &'a self,
@@ -611,10 +672,26 @@ pub trait Database: Sync + Send {
         col: DBCol,
         key_prefix: &'a [u8],
     ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a>;
+    /// Like `iter_prefix`, but starts from the first key which is at least `lower_bound`.
+    fn iter_prefix_from<'a>(
+        &'a self,
+        col: DBCol,
+        key_prefix: &'a [u8],
+        lower_bound: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        Box::new(
+            self.iter_prefix(col, key_prefix).skip_while(move |(key, _value)| &**key < lower_bound),
+        )
+    }
     fn write(&self, batch: DBTransaction) -> Result<(), DBError>;
     fn as_rocksdb(&self) -> Option<&RocksDB> {
         None
     }
+    /// Whether the rows of the columns collected by compaction are removed once the GC tail
+    /// passes them, without being deleted explicitly.
+    fn gc_by_compaction(&self) -> bool {
+        false
+    }
     fn get_store_statistics(&self) -> Option<StoreStatistics> {
         None
     }
@@ -627,6 +704,11 @@ impl Database for RocksDB {
         Ok(RocksDB::get_with_rc_logic(col, result))
     }
 
+    fn get_without_rc_logic(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        let read_options = rocksdb_read_options();
+        Ok(self.db.get_cf_opt(unsafe { &*self.cfs[col as usize] }, key, &read_options)?)
+    }
+
     fn iter_without_rc_logic<'a>(
         &'a self,
         col: DBCol,
@@ -673,6 +755,29 @@ impl Database for RocksDB {
         }
     }
 
+    fn iter_prefix_from<'a>(
+        &'a self,
+        col: DBCol,
+        key_prefix: &'a [u8],
+        lower_bound: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        let lower_bound = std::cmp::max(key_prefix, lower_bound);
+        let mut read_options = rocksdb_read_options();
+        read_options.set_prefix_same_as_start(true);
+        unsafe {
+            let cf_handle = &*self.cfs[col as usize];
+            let iterator = self
+                .db
+                .iterator_cf_opt(
+                    cf_handle,
+                    read_options,
+                    IteratorMode::From(lower_bound, Direction::Forward),
+                )
+                .take_while(move |(key, _value)| key.starts_with(key_prefix));
+            RocksDB::iter_with_rc_logic(col, iterator)
+        }
+    }
+
     fn write(&self, transaction: DBTransaction) -> Result<(), DBError> {
         if let Err(check) = self.pre_write_check() {
             if check.is_io() {
@@ -682,6 +787,7 @@ impl Database for RocksDB {
             }
         }
 
+        let written_blocks = self.gc_watermark.as_ref().map(|_| written_blocks(&transaction));
         let mut batch = WriteBatch::default();
         for op in transaction.ops {
             match op {
@@ -708,13 +814,24 @@ impl Database for RocksDB {
                 }
             }
         }
-        Ok(self.db.write(batch)?)
+        let _timer = crate::metrics::ROCKSDB_WRITE_TIME.start_timer();
+        self.db.write(batch)?;
+        if let (Some(gc_watermark), Some((tail, blocks))) =
+            (self.gc_watermark.as_ref(), written_blocks)
+        {
+            gc_watermark.update(tail, blocks);
+        }
+        Ok(())
     }
 
     fn as_rocksdb(&self) -> Option<&RocksDB> {
         Some(self)
     }
 
+    fn gc_by_compaction(&self) -> bool {
+        self.gc_watermark.as_ref().map_or(false, |gc_watermark| gc_watermark.tail().is_some())
+    }
+
     fn get_store_statistics(&self) -> Option<StoreStatistics> {
         if let Some(stats_str) = self.db_opt.get_statistics() {
             match parse_statistics(&stats_str) {
@@ -736,6 +853,10 @@ impl Database for TestDB {
         Ok(RocksDB::get_with_rc_logic(col, result))
     }
 
+    fn get_without_rc_logic(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        Ok(self.db.read().unwrap()[col as usize].get(key).cloned())
+    }
+
     fn iter<'a>(&'a self, col: DBCol) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
         let iterator = self.iter_without_rc_logic(col);
         RocksDB::iter_with_rc_logic(col, iterator)
@@ -811,14 +932,14 @@ fn set_compression_options(opts: &mut Options) {
 }
 
 /// DB level options
-fn rocksdb_options() -> Options {
+fn rocksdb_options(resources: &StoreResources) -> Options {
     let mut opts = Options::default();
 
     set_compression_options(&mut opts);
     opts.create_missing_column_families(true);
     opts.create_if_missing(true);
     opts.set_use_fsync(false);
-    opts.set_max_open_files(512);
+    opts.set_max_open_files(resources.max_open_files);
     opts.set_keep_log_file_num(1);
     opts.set_bytes_per_sync(bytesize::MIB);
     opts.set_write_buffer_size(256 * bytesize::MIB as usize);
@@ -860,7 +981,8 @@ fn rocksdb_read_options() -> ReadOptions {
 fn rocksdb_block_based_options(cache_size: usize) -> BlockBasedOptions {
     let mut block_opts = BlockBasedOptions::default();
     block_opts.set_block_size(16 * bytesize::KIB as usize);
-    // We create block_cache for each of 47 columns, so the total cache size is 32 * 47 = 1504mb
+    // We create block_cache for each column, so with the default sizes the total cache size is
+    // 512mb for State and 32mb for each of the other columns.
     block_opts.set_block_cache(&Cache::new_lru_cache(cache_size).unwrap());
     block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
     block_opts.set_cache_index_and_filter_blocks(true);
@@ -868,19 +990,18 @@ fn rocksdb_block_based_options(cache_size: usize) -> BlockBasedOptions {
     block_opts
 }
 
-// TODO(#5213) Use ByteSize package to represent sizes.
-fn choose_cache_size(col: DBCol) -> usize {
+fn choose_cache_size(col: DBCol, resources: &StoreResources) -> usize {
     match col {
-        DBCol::ColState => 512 * 1024 * 1024,
-        _ => 32 * 1024 * 1024,
+        DBCol::ColState => resources.col_state_cache_size,
+        _ => resources.default_cache_size,
     }
 }
 
-fn rocksdb_column_options(col: DBCol) -> Options {
+fn rocksdb_column_options(col: DBCol, resources: &StoreResources) -> Options {
     let mut opts = Options::default();
     set_compression_options(&mut opts);
     opts.set_level_compaction_dynamic_level_bytes(true);
-    let cache_size = choose_cache_size(col);
+    let cache_size = choose_cache_size(col, resources);
     opts.set_block_based_table_factory(&rocksdb_block_based_options(cache_size));
 
     // Note that this function changes a lot of rustdb parameters including:
@@ -922,6 +1043,36 @@ impl RocksDB {
         info!(target: "db", "All RocksDB instances performed a graceful shutdown");
     }
 
+    /// Loads the GC tail and the heights of the canonical blocks from
+    /// [`compaction_gc::HEIGHTS_BELOW_TAIL`] below it up to the head.
+    fn load_gc_watermark(&self, gc_watermark: &GcWatermark) -> Result<(), DBError> {
+        let tail = match self
+            .get(DBCol::ColBlockMisc, TAIL_KEY)?
+            .map(|tail| BlockHeight::try_from_slice(&tail))
+        {
+            Some(Ok(tail)) => tail,
+            Some(Err(err)) => {
+                warn!(target: "db", "Invalid GC tail, not collecting by compaction: {}", err);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        let head =
+            match self.get(DBCol::ColBlockMisc, HEAD_KEY)?.map(|head| Tip::try_from_slice(&head)) {
+                Some(Ok(head)) => head.height,
+                _ => tail,
+            };
+        let mut blocks = vec![];
+        for height in tail.saturating_sub(compaction_gc::HEIGHTS_BELOW_TAIL)..=head {
+            let key = height.to_le_bytes();
+            if let Some(value) = self.get(DBCol::ColBlockHeight, &key)? {
+                blocks.extend(canonical_block(&key, &value));
+            }
+        }
+        gc_watermark.update(Some(tail), blocks);
+        Ok(())
+    }
+
     /// Returns version of the database state on disk.
     pub fn get_version<P: AsRef<std::path::Path>>(path: P) -> Result<DbVersion, DBError> {
         let db = RocksDB::new_read_only(path)?;
@@ -965,6 +1116,26 @@ impl RocksDB {
         }
     }
 
+    /// Whether a process holds the lock RocksDB takes on the database at `path` while it's open.
+    /// Locks held by the calling process aren't detected.
+    pub fn is_locked(path: &Path) -> io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+        let lock_file =
+            match std::fs::OpenOptions::new().read(true).write(true).open(path.join("LOCK")) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => return Err(err),
+            };
+        // RocksDB locks the whole file with `fcntl`, which is what `F_GETLK` checks against.
+        let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+        lock.l_type = libc::F_WRLCK as libc::c_short;
+        lock.l_whence = libc::SEEK_SET as libc::c_short;
+        if unsafe { libc::fcntl(lock_file.as_raw_fd(), libc::F_GETLK, &mut lock) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(lock.l_type != libc::F_UNLCK as libc::c_short)
+    }
+
     /// Creates a Checkpoint object that can be used to actually create a checkpoint on disk.
     pub fn checkpoint(&self) -> Result<Checkpoint, DBError> {
         Checkpoint::new(&self.db).map_err(|err| DBError(err))
@@ -1097,7 +1268,7 @@ mod tests {
 
     impl RocksDB {
         #[cfg(not(feature = "single_thread_rocksdb"))]
-        fn compact(&self, col: DBCol) {
+        pub(crate) fn compact(&self, col: DBCol) {
             self.db.compact_range_cf(
                 unsafe { &*self.cfs[col as usize] },
                 Option::<&[u8]>::None,
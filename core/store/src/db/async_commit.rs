@@ -0,0 +1,365 @@
+//! Writes of the state, i.e. the refcount records of `ColState`, moved off the committing thread.
+//!
+//! A transaction writing to `ColState` is split: the records are serialized into a single
+//! `ColStateWriteIntents` row, which is written together with the rest of the transaction, and a
+//! background thread then merges them into `ColState` and deletes the row in one batch.  The
+//! intent row makes the split crash consistent: if the node stops before the background thread
+//! is done, the records are merged when the database is opened again.  Until then the records
+//! are kept in memory and merged into what is read from `ColState`.
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use tracing::error;
+
+use crate::db::refcount::merge_refcounted_records;
+use crate::db::{DBError, DBOp, DBTransaction, Database, RocksDB, StoreStatistics};
+use crate::DBCol;
+
+/// Refcount records of a commit, by key.
+type Records = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Default)]
+struct Pending {
+    /// Records of the commits not written yet, by key, with the sequence number of the commit.
+    by_key: HashMap<Vec<u8>, Vec<(u64, Vec<u8>)>>,
+    /// Records of the commits not written yet.
+    commits: BTreeMap<u64, Arc<Records>>,
+    /// Commit being written by the background thread.
+    writing: Option<u64>,
+}
+
+impl Pending {
+    fn add(&mut self, seq: u64, records: Arc<Records>) {
+        for (key, value) in records.iter() {
+            self.by_key.entry(key.clone()).or_default().push((seq, value.clone()));
+        }
+        self.commits.insert(seq, records);
+        crate::metrics::PENDING_STATE_COMMITS.set(self.commits.len() as i64);
+    }
+
+    fn remove(&mut self, seq: u64) {
+        if let Some(records) = self.commits.remove(&seq) {
+            for (key, _) in records.iter() {
+                if let Some(values) = self.by_key.get_mut(key) {
+                    values.retain(|(value_seq, _)| *value_seq != seq);
+                    if values.is_empty() {
+                        self.by_key.remove(key);
+                    }
+                }
+            }
+        }
+        crate::metrics::PENDING_STATE_COMMITS.set(self.commits.len() as i64);
+    }
+}
+
+pub struct AsyncStateCommitDB {
+    inner: Arc<dyn Database>,
+    pending: Arc<(Mutex<Pending>, Condvar)>,
+    next_seq: Mutex<u64>,
+    commits: Mutex<Option<mpsc::Sender<u64>>>,
+    committer: Option<JoinHandle<()>>,
+}
+
+impl AsyncStateCommitDB {
+    /// Merges the records of the commits left over by a previous run, then starts the thread
+    /// writing the new ones.
+    pub fn new(inner: Arc<dyn Database>) -> Result<Self, DBError> {
+        replay_state_write_intents(&*inner)?;
+        let pending = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+        let (commits, commits_rx) = mpsc::channel::<u64>();
+        let committer = {
+            let inner = inner.clone();
+            let pending = pending.clone();
+            std::thread::Builder::new()
+                .name("state_committer".to_string())
+                .spawn(move || {
+                    for seq in commits_rx {
+                        write_commit(&*inner, &pending, seq);
+                    }
+                })
+                .expect("Failed to start the state committer thread")
+        };
+        Ok(Self {
+            inner,
+            pending,
+            next_seq: Mutex::new(0),
+            commits: Mutex::new(Some(commits)),
+            committer: Some(committer),
+        })
+    }
+
+    /// Waits until the background thread has written every commit.
+    fn flush(&self) {
+        let (lock, written) = &*self.pending;
+        let mut pending = lock.lock().unwrap();
+        while !pending.commits.is_empty() {
+            pending = written.wait(pending).unwrap();
+        }
+    }
+
+    /// `ColState` row with the records not written yet merged into it.
+    fn get_state(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        let (lock, written) = &*self.pending;
+        let mut pending = lock.lock().unwrap();
+        // While the commit being written has records of the key, the row may or may not have
+        // them merged already.  Any other commit doesn't change the row.
+        while let Some(values) = pending.by_key.get(key) {
+            if values.iter().all(|(seq, _)| Some(*seq) != pending.writing) {
+                break;
+            }
+            pending = written.wait(pending).unwrap();
+        }
+        let value = self.inner.get_without_rc_logic(DBCol::ColState, key)?;
+        match pending.by_key.get(key) {
+            Some(values) => {
+                let mut result = vec![];
+                if let Some(value) = value {
+                    merge_refcounted_records(&mut result, &value);
+                }
+                for (_, value) in values {
+                    merge_refcounted_records(&mut result, value);
+                }
+                Ok(Some(result))
+            }
+            None => Ok(value),
+        }
+    }
+}
+
+/// Whether the database has commits whose records aren't merged into `ColState` yet.
+pub(crate) fn has_state_write_intents(db: &dyn Database) -> bool {
+    db.iter(DBCol::ColStateWriteIntents).next().is_some()
+}
+
+/// Merges the records of the commits left over by a previous run into `ColState`.  This has to
+/// happen whenever the database is opened for writing, whether the commits are async or not.
+pub(crate) fn replay_state_write_intents(db: &dyn Database) -> Result<(), DBError> {
+    let intents: Vec<_> = db.iter(DBCol::ColStateWriteIntents).collect();
+    for (key, value) in intents {
+        let records = Records::try_from_slice(&value).expect("Failed to decode a state commit");
+        db.write(commit_transaction(&key, &records))?;
+    }
+    Ok(())
+}
+
+/// Merges the records of the commit into `ColState` and deletes its intent row.
+fn commit_transaction(intent_key: &[u8], records: &Records) -> DBTransaction {
+    let mut transaction = DBTransaction { ops: Vec::with_capacity(records.len() + 1) };
+    for (key, value) in records {
+        transaction.update_refcount(DBCol::ColState, key, value);
+    }
+    transaction.delete(DBCol::ColStateWriteIntents, intent_key);
+    transaction
+}
+
+fn write_commit(inner: &dyn Database, pending: &(Mutex<Pending>, Condvar), seq: u64) {
+    let (lock, written) = pending;
+    let records = {
+        let mut pending = lock.lock().unwrap();
+        pending.writing = Some(seq);
+        pending.commits[&seq].clone()
+    };
+    // The intent row is still there if the write fails, so the records are merged on the next
+    // start.
+    if let Err(err) = inner.write(commit_transaction(&seq.to_be_bytes(), &records)) {
+        error!(target: "store", "Failed to write a state commit: {:?}", err);
+        panic!("Failed to write a state commit: {:?}", err);
+    }
+    let mut pending = lock.lock().unwrap();
+    pending.remove(seq);
+    pending.writing = None;
+    written.notify_all();
+}
+
+impl Drop for AsyncStateCommitDB {
+    fn drop(&mut self) {
+        // The background thread writes the commits left and stops, so that the database isn't
+        // closed before.
+        self.commits.lock().unwrap().take();
+        if let Some(committer) = self.committer.take() {
+            let _ = committer.join();
+        }
+    }
+}
+
+impl Database for AsyncStateCommitDB {
+    fn get(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        if col == DBCol::ColState {
+            Ok(RocksDB::get_with_rc_logic(col, self.get_state(key)?))
+        } else {
+            self.inner.get(col, key)
+        }
+    }
+
+    fn get_without_rc_logic(&self, col: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, DBError> {
+        if col == DBCol::ColState {
+            self.get_state(key)
+        } else {
+            self.inner.get_without_rc_logic(col, key)
+        }
+    }
+
+    fn iter<'a>(&'a self, col: DBCol) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        if col == DBCol::ColState {
+            self.flush();
+        }
+        self.inner.iter(col)
+    }
+
+    fn iter_without_rc_logic<'a>(
+        &'a self,
+        col: DBCol,
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        if col == DBCol::ColState {
+            self.flush();
+        }
+        self.inner.iter_without_rc_logic(col)
+    }
+
+    fn iter_prefix<'a>(
+        &'a self,
+        col: DBCol,
+        key_prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        if col == DBCol::ColState {
+            self.flush();
+        }
+        self.inner.iter_prefix(col, key_prefix)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> Result<(), DBError> {
+        let mut records = Records::new();
+        let mut ops = Vec::with_capacity(transaction.ops.len());
+        for op in transaction.ops {
+            match op {
+                DBOp::UpdateRefcount { col: DBCol::ColState, key, value } => {
+                    records.push((key, value))
+                }
+                DBOp::Insert { col: DBCol::ColState, .. }
+                | DBOp::Delete { col: DBCol::ColState, .. }
+                | DBOp::DeleteAll { col: DBCol::ColState } => {
+                    // Only the refcount records are deferred; anything else waits for them.
+                    self.flush();
+                    ops.push(op);
+                }
+                op => ops.push(op),
+            }
+        }
+        if records.is_empty() {
+            return self.inner.write(DBTransaction { ops });
+        }
+
+        // The sequence numbers order the commits, so they are taken under the same lock as the
+        // write and the send to the background thread.
+        let mut next_seq = self.next_seq.lock().unwrap();
+        let seq = *next_seq;
+        let records = Arc::new(records);
+        ops.push(DBOp::Insert {
+            col: DBCol::ColStateWriteIntents,
+            key: seq.to_be_bytes().to_vec(),
+            value: records.try_to_vec().expect("Borsh cannot fail"),
+        });
+        // The records are readable before the rest of the transaction, which may refer to them,
+        // is written.
+        self.pending.0.lock().unwrap().add(seq, records);
+        if let Err(err) = self.inner.write(DBTransaction { ops }) {
+            self.pending.0.lock().unwrap().remove(seq);
+            return Err(err);
+        }
+        *next_seq += 1;
+        if let Some(commits) = &*self.commits.lock().unwrap() {
+            let _ = commits.send(seq);
+        }
+        Ok(())
+    }
+
+    fn as_rocksdb(&self) -> Option<&RocksDB> {
+        self.inner.as_rocksdb()
+    }
+
+    fn gc_by_compaction(&self) -> bool {
+        self.inner.gc_by_compaction()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.inner.get_store_statistics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::db::refcount::encode_value_with_rc;
+    use crate::db::{DBTransaction, Database, TestDB};
+    use crate::DBCol;
+
+    use super::{has_state_write_intents, replay_state_write_intents, AsyncStateCommitDB};
+
+    #[test]
+    fn test_async_state_commits() {
+        let inner: Arc<dyn Database> = Arc::new(TestDB::new());
+        let db = AsyncStateCommitDB::new(inner.clone()).unwrap();
+        for _ in 0..2 {
+            let mut transaction = DBTransaction { ops: vec![] };
+            transaction.update_refcount(DBCol::ColState, b"node", encode_value_with_rc(b"v", 1));
+            transaction.put(DBCol::ColBlockMisc, b"head", b"1");
+            db.write(transaction).unwrap();
+        }
+        assert_eq!(db.get(DBCol::ColState, b"node").unwrap(), Some(b"v".to_vec()));
+        assert_eq!(
+            db.get_without_rc_logic(DBCol::ColState, b"node").unwrap(),
+            Some(encode_value_with_rc(b"v", 2))
+        );
+        assert_eq!(db.iter(DBCol::ColState).count(), 1);
+        assert_eq!(inner.iter(DBCol::ColStateWriteIntents).count(), 0);
+        assert_eq!(
+            inner.get_without_rc_logic(DBCol::ColState, b"node").unwrap(),
+            Some(encode_value_with_rc(b"v", 2))
+        );
+        drop(db);
+
+        // The intent of a commit whose records weren't merged is replayed once on start.
+        let mut transaction = DBTransaction { ops: vec![] };
+        transaction.put(
+            DBCol::ColStateWriteIntents,
+            0u64.to_be_bytes(),
+            borsh::BorshSerialize::try_to_vec(&vec![(
+                b"node".to_vec(),
+                encode_value_with_rc(b"v", -2),
+            )])
+            .unwrap(),
+        );
+        inner.write(transaction).unwrap();
+        let db = AsyncStateCommitDB::new(inner.clone()).unwrap();
+        assert_eq!(db.get(DBCol::ColState, b"node").unwrap(), None);
+        assert_eq!(inner.iter(DBCol::ColStateWriteIntents).count(), 0);
+    }
+
+    #[test]
+    fn test_replay_without_async_state_commits() {
+        let inner: Arc<dyn Database> = Arc::new(TestDB::new());
+        let mut transaction = DBTransaction { ops: vec![] };
+        transaction.update_refcount(DBCol::ColState, b"node", encode_value_with_rc(b"v", 1));
+        transaction.put(
+            DBCol::ColStateWriteIntents,
+            0u64.to_be_bytes(),
+            borsh::BorshSerialize::try_to_vec(&vec![(
+                b"node".to_vec(),
+                encode_value_with_rc(b"v", 1),
+            )])
+            .unwrap(),
+        );
+        inner.write(transaction).unwrap();
+        assert!(has_state_write_intents(&*inner));
+
+        replay_state_write_intents(&*inner).unwrap();
+        assert!(!has_state_write_intents(&*inner));
+        assert_eq!(
+            inner.get_without_rc_logic(DBCol::ColState, b"node").unwrap(),
+            Some(encode_value_with_rc(b"v", 2))
+        );
+    }
+}
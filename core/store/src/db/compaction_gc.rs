@@ -0,0 +1,274 @@
+//! Garbage collection of `ColTrieChanges` and `ColChunkExtra` by compaction filters.
+//!
+//! The rows of these columns are keyed by block hash and shard and are dead once the GC tail
+//! passes the height of their block: the trie changes of the blocks at and below the tail have
+//! been applied or reverted, and the chunk extras of the blocks below it are never read again.
+//! Rather than deleting them one by one, GC leaves the rows of canonical blocks to RocksDB, which
+//! drops them whenever it compacts the files holding them.  The tail is the one last written to
+//! `ColBlockMisc`.
+//!
+//! The filters learn the heights of the blocks from the database, which keeps the heights of the
+//! canonical blocks from [`HEIGHTS_BELOW_TAIL`] below the tail on in memory: it loads them from
+//! `ColBlockHeight` when it's opened and adds the ones written to it later.  Every compaction
+//! takes a snapshot of the tail and the heights when it starts, so the filters neither lock nor
+//! read the database.  The rows of blocks the filters don't know are kept, which is why GC still
+//! deletes the rows of blocks on forks, and all rows until a tail has been written.
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::{Arc, RwLock};
+
+use borsh::BorshDeserialize;
+use rocksdb::compaction_filter::{CompactionFilter, Decision};
+use rocksdb::compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory};
+use rocksdb::Options;
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+
+use crate::db::{DBCol, DBOp, DBTransaction, TAIL_KEY};
+use crate::metrics;
+
+/// How far below the GC tail the heights of the blocks are kept.  The rows of blocks which are
+/// still around by then are left behind, so it's well beyond the time RocksDB takes to compact
+/// all the files of a column.
+pub(crate) const HEIGHTS_BELOW_TAIL: BlockHeight = 100_000;
+
+/// The heights below the tail are dropped every this many heights.
+const PRUNE_INTERVAL: BlockHeight = 1_000;
+
+const FILTER_NAME: &[u8] = b"gc tail filter\0";
+
+/// Whether the rows of the column below the GC tail are removed by compaction.
+pub(crate) fn is_collected_by_compaction(col: DBCol) -> bool {
+    matches!(col, DBCol::ColTrieChanges | DBCol::ColChunkExtra)
+}
+
+/// Returns the GC tail and the canonical blocks written by the transaction.
+pub(crate) fn written_blocks(
+    transaction: &DBTransaction,
+) -> (Option<BlockHeight>, Vec<(CryptoHash, BlockHeight)>) {
+    let mut tail = None;
+    let mut blocks = vec![];
+    for op in transaction.ops.iter() {
+        match op {
+            DBOp::Insert { col: DBCol::ColBlockMisc, key, value } if key == TAIL_KEY => {
+                tail = BlockHeight::try_from_slice(value).ok().or(tail);
+            }
+            DBOp::Insert { col: DBCol::ColBlockHeight, key, value } => {
+                if let Some(block) = canonical_block(key, value) {
+                    blocks.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+    (tail, blocks)
+}
+
+/// Decodes a row of `ColBlockHeight`.
+pub(crate) fn canonical_block(key: &[u8], value: &[u8]) -> Option<(CryptoHash, BlockHeight)> {
+    let height = BlockHeight::from_le_bytes(key.try_into().ok()?);
+    Some((CryptoHash::try_from_slice(value).ok()?, height))
+}
+
+#[derive(Clone, Default)]
+struct GcSnapshot {
+    tail: Option<BlockHeight>,
+    heights: HashMap<CryptoHash, BlockHeight>,
+}
+
+impl GcSnapshot {
+    fn is_collected(&self, col: DBCol, key: &[u8]) -> bool {
+        let tail = match self.tail {
+            Some(tail) => tail,
+            None => return false,
+        };
+        let height = key
+            .get(..32)
+            .and_then(|hash| CryptoHash::try_from(hash).ok())
+            .and_then(|block_hash| self.heights.get(&block_hash));
+        match (col, height) {
+            (DBCol::ColTrieChanges, Some(height)) => *height <= tail,
+            (DBCol::ColChunkExtra, Some(height)) => *height < tail,
+            _ => false,
+        }
+    }
+}
+
+/// GC tail and block heights shared between the database and the compaction filters of its
+/// collected columns.
+pub(crate) struct GcWatermark {
+    snapshot: RwLock<Arc<GcSnapshot>>,
+}
+
+impl GcWatermark {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self { snapshot: RwLock::new(Arc::new(GcSnapshot::default())) })
+    }
+
+    pub(crate) fn tail(&self) -> Option<BlockHeight> {
+        self.snapshot().tail
+    }
+
+    fn snapshot(&self) -> Arc<GcSnapshot> {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    /// Applies the GC tail and the canonical blocks written to the database.
+    pub(crate) fn update(
+        &self,
+        tail: Option<BlockHeight>,
+        blocks: impl IntoIterator<Item = (CryptoHash, BlockHeight)>,
+    ) {
+        let mut blocks = blocks.into_iter().peekable();
+        if tail.is_none() && blocks.peek().is_none() {
+            return;
+        }
+        let mut snapshot = self.snapshot.write().unwrap();
+        // Copies the heights only if a running compaction holds the current snapshot.
+        let snapshot = Arc::make_mut(&mut snapshot);
+        snapshot.heights.extend(blocks);
+        if let Some(tail) = tail {
+            let prev_tail = snapshot.tail.replace(tail);
+            if prev_tail
+                .map_or(true, |prev_tail| prev_tail / PRUNE_INTERVAL != tail / PRUNE_INTERVAL)
+            {
+                let min_height = tail.saturating_sub(HEIGHTS_BELOW_TAIL);
+                snapshot.heights.retain(|_, height| *height >= min_height);
+            }
+        }
+    }
+
+    /// Installs the compaction filter of a collected column.
+    pub(crate) fn set_compaction_filter(self: &Arc<Self>, col: DBCol, opts: &mut Options) {
+        debug_assert!(is_collected_by_compaction(col));
+        opts.set_compaction_filter_factory(GcFilterFactory { col, watermark: self.clone() });
+    }
+}
+
+struct GcFilterFactory {
+    col: DBCol,
+    watermark: Arc<GcWatermark>,
+}
+
+impl CompactionFilterFactory for GcFilterFactory {
+    type Filter = GcFilter;
+
+    fn create(&mut self, _context: CompactionFilterContext) -> GcFilter {
+        GcFilter { col: self.col, snapshot: self.watermark.snapshot() }
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(FILTER_NAME).unwrap()
+    }
+}
+
+struct GcFilter {
+    col: DBCol,
+    snapshot: Arc<GcSnapshot>,
+}
+
+impl CompactionFilter for GcFilter {
+    fn filter(&mut self, _level: u32, key: &[u8], _value: &[u8]) -> Decision {
+        if self.snapshot.is_collected(self.col, key) {
+            metrics::COMPACTION_GC_REMOVED_ROWS.inc();
+            Decision::Remove
+        } else {
+            Decision::Keep
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(FILTER_NAME).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+    use near_primitives::block::{genesis_chunks, Block, Tip};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::time::Clock;
+    use near_primitives::version::PROTOCOL_VERSION;
+
+    use crate::create_store;
+    use crate::db::{DBCol, HEAD_KEY, TAIL_KEY};
+
+    fn block_at(height: u64) -> Block {
+        let chunks =
+            genesis_chunks(vec![CryptoHash::default()], 1, 1_000, height, PROTOCOL_VERSION);
+        Block::genesis(
+            PROTOCOL_VERSION,
+            chunks.into_iter().map(|chunk| chunk.take_header()).collect(),
+            Clock::utc(),
+            height,
+            1_000,
+            1_000,
+            CryptoHash::default(),
+        )
+    }
+
+    #[test]
+    #[cfg(not(feature = "single_thread_rocksdb"))]
+    fn test_compaction_gc() {
+        let tmp_dir = tempfile::Builder::new().prefix("_test_compaction_gc").tempdir().unwrap();
+        let store = create_store(tmp_dir.path());
+        let rocksdb = store.storage.as_rocksdb().unwrap();
+        let blocks: Vec<_> = [5, 10, 15].into_iter().map(block_at).collect();
+        // A block on a fork, which isn't in `ColBlockHeight`.
+        let fork_block = block_at(4);
+        let row_key = |block: &Block| [block.hash().as_ref(), &[0u8; 8][..]].concat();
+        let mut store_update = store.store_update();
+        for block in blocks.iter().chain(Some(&fork_block)) {
+            store_update.set(DBCol::ColTrieChanges, &row_key(block), &[1]);
+            store_update.set(DBCol::ColChunkExtra, &row_key(block), &[2]);
+        }
+        for block in &blocks {
+            store_update
+                .set_ser(
+                    DBCol::ColBlockHeight,
+                    &block.header().height().to_le_bytes(),
+                    block.hash(),
+                )
+                .unwrap();
+        }
+        store_update
+            .set_ser(DBCol::ColBlockMisc, HEAD_KEY, &Tip::from_header(blocks[2].header()))
+            .unwrap();
+        store_update.commit().unwrap();
+
+        // Without a tail nothing is collected.
+        assert!(!store.gc_by_compaction());
+        rocksdb.compact(DBCol::ColTrieChanges);
+        assert!(store.get(DBCol::ColTrieChanges, &row_key(&blocks[0])).unwrap().is_some());
+
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::ColBlockMisc, TAIL_KEY, &10u64.try_to_vec().unwrap());
+        store_update.commit().unwrap();
+        assert!(store.gc_by_compaction());
+        rocksdb.compact(DBCol::ColTrieChanges);
+        rocksdb.compact(DBCol::ColChunkExtra);
+        let exists = |col, block| store.get(col, &row_key(block)).unwrap().is_some();
+        assert_eq!(
+            blocks.iter().map(|block| exists(DBCol::ColTrieChanges, block)).collect::<Vec<_>>(),
+            vec![false, false, true]
+        );
+        assert_eq!(
+            blocks.iter().map(|block| exists(DBCol::ColChunkExtra, block)).collect::<Vec<_>>(),
+            vec![false, true, true]
+        );
+        assert!(exists(DBCol::ColTrieChanges, &fork_block));
+
+        // The tail is read back when the database is opened again.
+        drop(store);
+        let store = create_store(tmp_dir.path());
+        let rocksdb = store.storage.as_rocksdb().unwrap();
+        assert_eq!(rocksdb.gc_watermark.as_ref().unwrap().tail(), Some(10));
+        // So are the heights of the canonical blocks up to the head.
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::ColBlockMisc, TAIL_KEY, &20u64.try_to_vec().unwrap());
+        store_update.commit().unwrap();
+        rocksdb.compact(DBCol::ColTrieChanges);
+        assert!(store.get(DBCol::ColTrieChanges, &row_key(&blocks[2])).unwrap().is_none());
+    }
+}
@@ -5,7 +5,7 @@ use rocksdb::{ColumnFamilyDescriptor, MergeOperands, Options};
 use strum::IntoEnumIterator;
 
 use crate::db::{col_name, rocksdb_column_options, DBError, RocksDB, RocksDBOptions};
-use crate::DBCol;
+use crate::{DBCol, StoreResources};
 
 fn refcount_merge_v6(
     _new_key: &[u8],
@@ -47,7 +47,7 @@ fn merge_refcounted_records_v6(result: &mut Vec<u8>, val: &[u8]) {
 }
 
 fn rocksdb_column_options_v6(col: DBCol) -> Options {
-    let mut opts = rocksdb_column_options(DBCol::ColDbVersion);
+    let mut opts = rocksdb_column_options(DBCol::ColDbVersion, &StoreResources::default());
 
     if col == DBCol::ColState {
         opts.set_merge_operator("refcount merge", refcount_merge_v6, refcount_merge_v6);
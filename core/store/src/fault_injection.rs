@@ -0,0 +1,56 @@
+//! Faults injected into the reads of a store by tests, to exercise how the node copes with a slow
+//! or flaky disk.
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Shared by all the clones of a `Store`.
+#[derive(Default)]
+pub struct StoreFaults {
+    read_delay_us: AtomicU64,
+    read_error_percent: AtomicU8,
+}
+
+impl StoreFaults {
+    /// Makes every read wait for `read_delay` and fail with a transient error with the
+    /// probability of `read_error_percent` percent.  Zeros turn the faults off.
+    pub fn set(&self, read_delay: Duration, read_error_percent: u8) {
+        self.read_delay_us.store(read_delay.as_micros() as u64, Ordering::Relaxed);
+        self.read_error_percent.store(read_error_percent.min(100), Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_read(&self) -> io::Result<()> {
+        let read_delay_us = self.read_delay_us.load(Ordering::Relaxed);
+        if read_delay_us > 0 {
+            std::thread::sleep(Duration::from_micros(read_delay_us));
+        }
+        let read_error_percent = self.read_error_percent.load(Ordering::Relaxed);
+        if read_error_percent > 0 && rand::thread_rng().gen_range(0, 100) < read_error_percent {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "injected store read fault"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::test_utils::create_test_store;
+    use crate::DBCol;
+
+    #[test]
+    fn test_store_read_faults() {
+        let store = create_test_store();
+        let mut store_update = store.store_update();
+        store_update.set(DBCol::ColBlockMisc, b"key", b"value");
+        store_update.commit().unwrap();
+
+        store.clone().faults().set(Duration::ZERO, 100);
+        assert!(store.get(DBCol::ColBlockMisc, b"key").is_err());
+        store.faults().set(Duration::ZERO, 0);
+        assert_eq!(store.get(DBCol::ColBlockMisc, b"key").unwrap(), Some(b"value".to_vec()));
+    }
+}
@@ -13,6 +13,7 @@ pub use db::DBCol::{self, *};
 pub use db::{
     CHUNK_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY,
     LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY, SHOULD_COL_GC, SKIP_COL_GC, TAIL_KEY,
+    VALIDATOR_STATS_KEY,
 };
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
@@ -25,35 +26,72 @@ pub use near_primitives::shard_layout::ShardUId;
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::{AccountId, CompiledContractCache, StateRoot};
 
+pub use crate::account_history::{
+    account_history_key, get_account_history, set_account_history_entry, AccountHistoryEntry,
+    AccountHistoryPage,
+};
+pub use crate::contract_cache::{
+    create_compiled_contract_cache, ConfiguredCompiledContractCache, ContractCacheBackend,
+    ContractCacheConfig, DirectoryCompiledContractCache,
+};
+use crate::db::async_commit::{
+    has_state_write_intents, replay_state_write_intents, AsyncStateCommitDB,
+};
 pub use crate::db::refcount::decode_value_with_rc;
 use crate::db::refcount::encode_value_with_rc;
 use crate::db::{
     DBOp, DBTransaction, Database, RocksDB, RocksDBOptions, StoreStatistics, GENESIS_JSON_HASH_KEY,
     GENESIS_STATE_ROOTS_KEY,
 };
+#[cfg(feature = "test_features")]
+pub use crate::fault_injection::StoreFaults;
+pub use crate::resources::{ResourceProfile, StoreResources, StoreResourcesConfig};
+pub use crate::trie::accounts::{AccountFilter, AccountIterator};
 pub use crate::trie::iterator::TrieIterator;
+pub use crate::trie::mem_trie::{MemTrie, MemTriesConfig};
+pub use crate::trie::rc_audit::{RefcountAudit, RefcountMismatch};
 pub use crate::trie::update::{TrieUpdate, TrieUpdateIterator, TrieUpdateValuePtr};
 pub use crate::trie::{
-    split_state, ApplyStatePartResult, KeyForStateChanges, PartialStorage, ShardTries, Trie,
-    TrieChanges, WrappedTrieChanges,
+    access_trace, split_state, ApplyStatePartResult, KeyForStateChanges, PartialStorage,
+    ShardTries, Trie, TrieChanges, WrappedTrieChanges,
 };
 
+mod account_history;
+mod contract_cache;
 pub mod db;
+#[cfg(feature = "test_features")]
+mod fault_injection;
+mod metrics;
 pub mod migrations;
+mod resources;
 pub mod test_utils;
 mod trie;
 
 #[derive(Clone)]
 pub struct Store {
     storage: Arc<dyn Database>,
+    #[cfg(feature = "test_features")]
+    faults: Arc<StoreFaults>,
 }
 
 impl Store {
     pub fn new(storage: Arc<dyn Database>) -> Store {
-        Store { storage }
+        Store {
+            storage,
+            #[cfg(feature = "test_features")]
+            faults: Default::default(),
+        }
+    }
+
+    /// Faults injected into the reads of this store and its clones by tests.
+    #[cfg(feature = "test_features")]
+    pub fn faults(&self) -> &StoreFaults {
+        &self.faults
     }
 
     pub fn get(&self, column: DBCol, key: &[u8]) -> Result<Option<Vec<u8>>, io::Error> {
+        #[cfg(feature = "test_features")]
+        self.faults.on_read()?;
         self.storage.get(column, key).map_err(|e| e.into())
     }
 
@@ -62,6 +100,8 @@ impl Store {
         column: DBCol,
         key: &[u8],
     ) -> Result<Option<T>, io::Error> {
+        #[cfg(feature = "test_features")]
+        self.faults.on_read()?;
         match self.storage.get(column, key) {
             Ok(Some(bytes)) => match T::try_from_slice(bytes.as_ref()) {
                 Ok(result) => Ok(Some(result)),
@@ -72,6 +112,15 @@ impl Store {
         }
     }
 
+    /// Returns the value as stored, i.e. with the reference count of reference counted columns.
+    pub fn get_without_rc_logic(
+        &self,
+        column: DBCol,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, io::Error> {
+        self.storage.get_without_rc_logic(column, key).map_err(|e| e.into())
+    }
+
     pub fn exists(&self, column: DBCol, key: &[u8]) -> Result<bool, io::Error> {
         self.storage.get(column, key).map(|value| value.is_some()).map_err(|e| e.into())
     }
@@ -102,6 +151,15 @@ impl Store {
         self.storage.iter_prefix(column, key_prefix)
     }
 
+    pub fn iter_prefix_from<'a>(
+        &'a self,
+        column: DBCol,
+        key_prefix: &'a [u8],
+        lower_bound: &'a [u8],
+    ) -> Box<dyn Iterator<Item = (Box<[u8]>, Box<[u8]>)> + 'a> {
+        self.storage.iter_prefix_from(column, key_prefix, lower_bound)
+    }
+
     pub fn iter_prefix_ser<'a, T: BorshDeserialize>(
         &'a self,
         column: DBCol,
@@ -156,6 +214,12 @@ impl Store {
     pub fn get_store_statistics(&self) -> Option<StoreStatistics> {
         self.storage.get_store_statistics()
     }
+
+    /// Whether the trie changes and chunk extras of the blocks the GC tail passed are removed by
+    /// compaction, in which case GC doesn't need to delete them.
+    pub fn gc_by_compaction(&self) -> bool {
+        self.storage.gc_by_compaction()
+    }
 }
 
 /// Keeps track of current changes to the database and can commit all of them to the database.
@@ -302,6 +366,7 @@ pub fn read_with_cache<'a, T: BorshDeserialize + 'a>(
 
 pub fn create_store(path: &Path) -> Store {
     let db = Arc::new(RocksDB::new(path).expect("Failed to open the database"));
+    replay_state_write_intents(&*db).expect("Failed to merge the pending state writes");
     Store::new(db)
 }
 
@@ -312,10 +377,14 @@ pub struct StoreConfig {
     /// Re-export storage layer statistics as prometheus metrics.
     /// Minor performance impact is expected.
     pub enable_statistics: bool,
+    /// Sizes of the block caches and the number of open files.
+    pub resources: StoreResources,
+    /// Write the state of the commits on a background thread, see `AsyncStateCommitDB`.
+    pub async_state_commits: bool,
 }
 
 pub fn create_store_with_config(path: &Path, store_config: StoreConfig) -> Store {
-    let mut opts = RocksDBOptions::default();
+    let mut opts = RocksDBOptions::default().resources(store_config.resources);
     if store_config.enable_statistics {
         opts = opts.enable_statistics();
     }
@@ -324,6 +393,22 @@ pub fn create_store_with_config(path: &Path, store_config: StoreConfig) -> Store
         (if store_config.read_only { opts.read_only(path) } else { opts.read_write(path) })
             .expect("Failed to open the database"),
     );
+    if store_config.read_only {
+        // `ColState` lacks the records of the commits left over by a crash until a read-write
+        // open merges them.
+        if has_state_write_intents(&*db) {
+            panic!(
+                "The database has state writes which weren't merged yet. \
+                 Start the node once to merge them before opening the database read-only"
+            );
+        }
+        return Store::new(db);
+    }
+    if store_config.async_state_commits {
+        let db = AsyncStateCommitDB::new(db).expect("Failed to merge the pending state writes");
+        return Store::new(Arc::new(db));
+    }
+    replay_state_write_intents(&*db).expect("Failed to merge the pending state writes");
     Store::new(db)
 }
 
@@ -529,6 +614,38 @@ pub fn set_genesis_state_roots(store_update: &mut StoreUpdate, genesis_roots: &V
         .expect("Borsh cannot fail");
 }
 
+/// Records that the contract code with the given hash was deployed to the account.
+pub fn set_contract_account(
+    store_update: &mut StoreUpdate,
+    code_hash: &CryptoHash,
+    account_id: &AccountId,
+) {
+    let key = [code_hash.as_ref(), account_id.as_ref().as_bytes()].concat();
+    store_update.set(DBCol::ColContractAccounts, &key, &[]);
+}
+
+/// Returns the accounts which had the contract code with the given hash deployed at some point,
+/// in the order of their ids.
+pub fn get_contract_accounts(
+    store: &Store,
+    code_hash: &CryptoHash,
+) -> Result<Vec<AccountId>, std::io::Error> {
+    store
+        .iter_prefix(DBCol::ColContractAccounts, code_hash.as_ref())
+        .map(|(key, _)| {
+            std::str::from_utf8(&key[code_hash.as_ref().len()..])
+                .ok()
+                .and_then(|account_id| account_id.parse().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid account id in contract accounts key {}", to_base(&key)),
+                    )
+                })
+        })
+        .collect()
+}
+
 pub struct StoreCompiledContractCache {
     pub store: Store,
 }
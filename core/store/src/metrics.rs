@@ -0,0 +1,71 @@
+use near_metrics::{
+    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
+    IntGauge,
+};
+use once_cell::sync::Lazy;
+
+pub static ROCKSDB_WRITE_TIME: Lazy<Histogram> = Lazy::new(|| {
+    try_create_histogram(
+        "near_rocksdb_write_time",
+        "Time taken to write a transaction to RocksDB, which blocks the thread committing it",
+    )
+    .unwrap()
+});
+
+pub static PENDING_STATE_COMMITS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_pending_state_commits",
+        "Number of commits whose state writes haven't been merged into the state column yet",
+    )
+    .unwrap()
+});
+
+pub static COMPILED_CONTRACT_CACHE_HITS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_compiled_contract_cache_hits_total",
+        "Number of compiled contract cache lookups which found the contract",
+    )
+    .unwrap()
+});
+pub static COMPILED_CONTRACT_CACHE_MISSES: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_compiled_contract_cache_misses_total",
+        "Number of compiled contract cache lookups which required compiling the contract",
+    )
+    .unwrap()
+});
+pub static COMPILED_CONTRACT_CACHE_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_compiled_contract_cache_evictions_total",
+        "Number of compiled contracts evicted from the cache to stay within the size limit",
+    )
+    .unwrap()
+});
+pub static COMPILED_CONTRACT_CACHE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_compiled_contract_cache_size_bytes",
+        "Total size of the compiled contracts in the cache. Only tracked when the size is bounded",
+    )
+    .unwrap()
+});
+pub static MEM_TRIES_MEMORY_USAGE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_mem_tries_memory_usage_bytes",
+        "Approximate memory used by the state of the shards kept in memory",
+    )
+    .unwrap()
+});
+pub static MEM_TRIES_LOADED_SHARDS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_mem_tries_loaded_shards",
+        "Number of shards whose state is kept in memory",
+    )
+    .unwrap()
+});
+pub static COMPACTION_GC_REMOVED_ROWS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_compaction_gc_removed_rows_total",
+        "Number of trie changes and chunk extras below the GC tail removed by compaction",
+    )
+    .unwrap()
+});
@@ -0,0 +1,224 @@
+//! Sizing of the RocksDB block caches, the number of files RocksDB keeps open and the capacity of
+//! the trie caches from the memory and the open files limit of the machine, so that the defaults
+//! sized for a machine with 16 GB of memory don't waste one with 512 GB.  The caches are never
+//! smaller than those defaults.
+//!
+//! Every size can be set in the config instead, which takes precedence over the detected one.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Descriptors left for the sockets of the peers and RPC clients and the other files of the
+/// node when the open files limit is large.
+const RESERVED_FILES: u64 = 1024;
+/// RocksDB doesn't need more open files than this even for the largest databases.
+const MAX_OPEN_FILES: u64 = 65536;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct StoreResourcesConfig {
+    /// Size of the block cache of the `State` column in bytes.
+    pub col_state_cache_size: Option<u64>,
+    /// Size of the block cache of each of the other columns in bytes.
+    pub default_cache_size: Option<u64>,
+    /// Maximum number of files RocksDB keeps open.
+    pub max_open_files: Option<u32>,
+    /// Maximum number of trie nodes and values cached for every shard.
+    pub trie_cache_capacity: Option<usize>,
+}
+
+/// Class of the machine by its memory, which determines the cache sizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceProfile {
+    /// Less than 24 GB of memory.  Gets the same caches as `Medium`, which are the floor.
+    Small,
+    /// Less than 96 GB of memory, or memory which couldn't be detected.
+    Medium,
+    /// Less than 256 GB of memory.
+    Large,
+    XLarge,
+}
+
+impl ResourceProfile {
+    pub fn from_memory(memory_bytes: Option<u64>) -> Self {
+        match memory_bytes {
+            None => Self::Medium,
+            Some(bytes) if bytes < 24 * bytesize::GIB => Self::Small,
+            Some(bytes) if bytes < 96 * bytesize::GIB => Self::Medium,
+            Some(bytes) if bytes < 256 * bytesize::GIB => Self::Large,
+            Some(_) => Self::XLarge,
+        }
+    }
+}
+
+impl fmt::Display for ResourceProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+            Self::XLarge => "xlarge",
+        })
+    }
+}
+
+/// Sizes the store is opened with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoreResources {
+    pub col_state_cache_size: usize,
+    pub default_cache_size: usize,
+    pub max_open_files: i32,
+    pub trie_cache_capacity: usize,
+}
+
+/// The sizes of the medium profile, which were the defaults before they were chosen by the
+/// resources of the machine.
+impl Default for StoreResources {
+    fn default() -> Self {
+        Self::for_profile(ResourceProfile::Medium, 512)
+    }
+}
+
+impl StoreResources {
+    fn for_profile(profile: ResourceProfile, max_open_files: i32) -> Self {
+        let mib = bytesize::MIB as usize;
+        let (col_state_cache_size, default_cache_size, trie_cache_capacity) = match profile {
+            ResourceProfile::Small | ResourceProfile::Medium => (512 * mib, 32 * mib, 50_000),
+            ResourceProfile::Large => (2048 * mib, 64 * mib, 200_000),
+            ResourceProfile::XLarge => (8192 * mib, 128 * mib, 500_000),
+        };
+        Self { col_state_cache_size, default_cache_size, max_open_files, trie_cache_capacity }
+    }
+
+    /// Sizes for a machine with the given memory and open files limit, either of which is `None`
+    /// if it couldn't be detected, overridden by those set in the config.
+    pub fn choose(
+        memory_bytes: Option<u64>,
+        open_files_limit: Option<u64>,
+        config: &StoreResourcesConfig,
+    ) -> (ResourceProfile, Self) {
+        let profile = ResourceProfile::from_memory(memory_bytes);
+        let max_open_files = match open_files_limit {
+            Some(limit) => {
+                limit.saturating_sub(RESERVED_FILES).max(limit / 2).min(MAX_OPEN_FILES) as i32
+            }
+            None => Self::default().max_open_files,
+        };
+        let mut resources = Self::for_profile(profile, max_open_files);
+        if let Some(size) = config.col_state_cache_size {
+            resources.col_state_cache_size = size as usize;
+        }
+        if let Some(size) = config.default_cache_size {
+            resources.default_cache_size = size as usize;
+        }
+        if let Some(max_open_files) = config.max_open_files {
+            resources.max_open_files = max_open_files as i32;
+        }
+        if let Some(capacity) = config.trie_cache_capacity {
+            resources.trie_cache_capacity = capacity;
+        }
+        (profile, resources)
+    }
+
+    /// Detects the memory and the open files limit of the machine, raising the soft limit to the
+    /// hard one, and logs the chosen sizes.
+    pub fn detect(config: &StoreResourcesConfig) -> Self {
+        let memory_bytes = available_memory();
+        let open_files_limit = raise_open_files_limit();
+        let (profile, resources) = Self::choose(memory_bytes, open_files_limit, config);
+        let memory = memory_bytes
+            .map_or_else(|| "unknown".to_string(), |bytes| bytesize::ByteSize(bytes).to_string());
+        let open_files_limit =
+            open_files_limit.map_or_else(|| "unknown".to_string(), |limit| limit.to_string());
+        info!(
+            target: "store",
+            "Store resource profile {} for {} of memory and a limit of {} open files: block cache of {} for State and {} for other columns, {} open files, trie cache of {} entries per shard",
+            profile,
+            memory,
+            open_files_limit,
+            bytesize::ByteSize(resources.col_state_cache_size as u64),
+            bytesize::ByteSize(resources.default_cache_size as u64),
+            resources.max_open_files,
+            resources.trie_cache_capacity,
+        );
+        resources
+    }
+}
+
+/// Physical memory of the machine, or the memory limit of the cgroup of the process if it is
+/// lower, e.g. in a container.
+fn available_memory() -> Option<u64> {
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let physical = if pages > 0 && page_size > 0 {
+        Some(pages as u64 * page_size as u64)
+    } else {
+        warn!(target: "store", "Failed to detect the memory of the machine");
+        None
+    };
+    // cgroup v2 and v1 respectively.  The limits are `max` or a huge number if there are none.
+    let cgroup_limit = ["/sys/fs/cgroup/memory.max", "/sys/fs/cgroup/memory/memory.limit_in_bytes"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok()?.trim().parse::<u64>().ok());
+    match (physical, cgroup_limit) {
+        (Some(physical), Some(limit)) => Some(physical.min(limit)),
+        (physical, limit) => physical.or(limit),
+    }
+}
+
+/// Raises the soft limit of open files to the hard one and returns it.
+fn raise_open_files_limit() -> Option<u64> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        warn!(target: "store", "Failed to read the open files limit");
+        return None;
+    }
+    if limit.rlim_cur < limit.rlim_max {
+        let raised = libc::rlimit { rlim_cur: limit.rlim_max, rlim_max: limit.rlim_max };
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } == 0 {
+            limit = raised;
+        }
+    }
+    Some(limit.rlim_cur as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResourceProfile, StoreResources, StoreResourcesConfig};
+
+    const GIB: u64 = bytesize::GIB;
+
+    #[test]
+    fn test_choose_store_resources() {
+        let config = StoreResourcesConfig::default();
+        let (profile, resources) = StoreResources::choose(None, None, &config);
+        assert_eq!(profile, ResourceProfile::Medium);
+        assert_eq!(resources, StoreResources::default());
+
+        let (profile, resources) = StoreResources::choose(Some(16 * GIB), Some(1024), &config);
+        assert_eq!(profile, ResourceProfile::Small);
+        assert_eq!(resources.col_state_cache_size, 512 << 20);
+        assert_eq!(resources.trie_cache_capacity, 50_000);
+        assert_eq!(resources.max_open_files, 512);
+
+        let (profile, resources) = StoreResources::choose(Some(512 * GIB), Some(1 << 20), &config);
+        assert_eq!(profile, ResourceProfile::XLarge);
+        assert_eq!(resources.trie_cache_capacity, 500_000);
+        assert_eq!(resources.max_open_files, 65536);
+
+        let (_, resources) = StoreResources::choose(Some(128 * GIB), Some(65536), &config);
+        assert_eq!(resources.max_open_files, 65536 - 1024);
+
+        let config = StoreResourcesConfig {
+            col_state_cache_size: Some(GIB),
+            max_open_files: Some(1000),
+            ..Default::default()
+        };
+        let (profile, resources) = StoreResources::choose(Some(16 * GIB), Some(1024), &config);
+        assert_eq!(profile, ResourceProfile::Small);
+        assert_eq!(resources.col_state_cache_size, GIB as usize);
+        assert_eq!(resources.default_cache_size, 32 << 20);
+        assert_eq!(resources.max_open_files, 1000);
+    }
+}
@@ -0,0 +1,80 @@
+//! Recording of trie accesses, used to drive the trie benchmarks with realistic workloads.
+//!
+//! While recording is on, every `Trie::get_ref` and every change passed to `Trie::update` is
+//! appended to the trace file as a borsh-serialized [`TrieAccessRecord`].  Only the lengths of
+//! the values are recorded, which is enough to rebuild a trie with the same shape.
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+use crate::trie::POISONED_LOCK_ERR;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrieAccessOp {
+    Get,
+    Insert,
+    Delete,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TrieAccessRecord {
+    pub op: TrieAccessOp,
+    pub key: Vec<u8>,
+    /// Length of the value read or written; zero for deletions and reads of missing keys.
+    pub value_len: u32,
+}
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static RECORDER: Lazy<Mutex<Option<BufWriter<File>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Starts recording the accesses of all tries in the process to a new file at `path`.
+pub fn start_recording(path: &Path) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    *RECORDER.lock().expect(POISONED_LOCK_ERR) = Some(writer);
+    RECORDING.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Stops recording and flushes the trace file.
+pub fn stop_recording() -> io::Result<()> {
+    RECORDING.store(false, Ordering::Release);
+    match RECORDER.lock().expect(POISONED_LOCK_ERR).take() {
+        Some(mut writer) => writer.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Reads all the records of a trace written by [`start_recording`].
+pub fn read_trace(path: &Path) -> io::Result<Vec<TrieAccessRecord>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    let mut buf = bytes.as_slice();
+    let mut records = Vec::new();
+    while !buf.is_empty() {
+        records.push(TrieAccessRecord::deserialize(&mut buf)?);
+    }
+    Ok(records)
+}
+
+#[inline]
+pub(crate) fn is_recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+pub(crate) fn record(op: TrieAccessOp, key: &[u8], value_len: u32) {
+    let mut recorder = RECORDER.lock().expect(POISONED_LOCK_ERR);
+    if let Some(writer) = recorder.as_mut() {
+        let record = TrieAccessRecord { op, key: key.to_vec(), value_len };
+        if let Err(err) = record.serialize(writer) {
+            warn!(target: "store", "Failed to write the trie access trace, stopping: {}", err);
+            RECORDING.store(false, Ordering::Release);
+            *recorder = None;
+        }
+    }
+}
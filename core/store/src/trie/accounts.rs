@@ -0,0 +1,144 @@
+use borsh::BorshDeserialize;
+
+use near_primitives::account::Account;
+use near_primitives::hash::CryptoHash;
+use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::types::{AccountId, Balance, StateRoot, StorageUsage};
+
+use crate::trie::iterator::TrieIterator;
+use crate::{StorageError, Trie};
+
+/// Conditions which accounts returned by [`AccountIterator`] must satisfy.  Unset fields match
+/// all accounts and the ranges are inclusive.
+#[derive(Clone, Debug, Default)]
+pub struct AccountFilter {
+    /// Whether the account must (or must not) have a contract deployed.
+    pub has_contract: Option<bool>,
+    /// Bounds of the liquid balance of the account.
+    pub min_balance: Option<Balance>,
+    pub max_balance: Option<Balance>,
+    pub min_storage_usage: Option<StorageUsage>,
+    pub max_storage_usage: Option<StorageUsage>,
+}
+
+impl AccountFilter {
+    pub fn matches(&self, account: &Account) -> bool {
+        let has_contract = account.code_hash() != CryptoHash::default();
+        self.has_contract.map_or(true, |expected| expected == has_contract)
+            && self.min_balance.map_or(true, |min| account.amount() >= min)
+            && self.max_balance.map_or(true, |max| account.amount() <= max)
+            && self.min_storage_usage.map_or(true, |min| account.storage_usage() >= min)
+            && self.max_storage_usage.map_or(true, |max| account.storage_usage() <= max)
+    }
+}
+
+/// Iterates over accounts stored in a trie in the order of their ids, skipping the ones which
+/// don't match the filter.  Accounts are read lazily, so the whole shard is never kept in memory.
+pub struct AccountIterator<'a> {
+    trie_iter: TrieIterator<'a>,
+    filter: AccountFilter,
+}
+
+impl<'a> AccountIterator<'a> {
+    pub fn new(
+        trie: &'a Trie,
+        state_root: &StateRoot,
+        filter: AccountFilter,
+    ) -> Result<Self, StorageError> {
+        let mut trie_iter = trie.iter(state_root)?;
+        trie_iter.seek(trie_key_parsers::get_raw_prefix_for_accounts())?;
+        Ok(Self { trie_iter, filter })
+    }
+
+    fn parse(key: &[u8], value: &[u8]) -> Result<(AccountId, Account), StorageError> {
+        let account_id = trie_key_parsers::parse_account_id_from_account_key(key)
+            .map_err(|err| StorageError::StorageInconsistentState(err.to_string()))?;
+        let account = Account::try_from_slice(value).map_err(|err| {
+            StorageError::StorageInconsistentState(format!(
+                "failed to deserialize account {}: {}",
+                account_id, err
+            ))
+        })?;
+        Ok((account_id, account))
+    }
+}
+
+impl<'a> Iterator for AccountIterator<'a> {
+    type Item = Result<(AccountId, Account), StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, value) = match self.trie_iter.next()? {
+                Ok(item) => item,
+                Err(err) => return Some(Err(err)),
+            };
+            if !key.starts_with(trie_key_parsers::get_raw_prefix_for_accounts()) {
+                return None;
+            }
+            match Self::parse(&key, &value) {
+                Ok((_, account)) if !self.filter.matches(&account) => continue,
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+
+    use near_primitives::account::Account;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::trie_key::TrieKey;
+    use near_primitives::types::AccountId;
+
+    use super::{AccountFilter, AccountIterator};
+    use crate::test_utils::{create_tries, test_populate_trie};
+
+    fn account_ids(filter: AccountFilter) -> Vec<String> {
+        let tries = create_tries();
+        let mut changes = vec![];
+        for (account_id, amount, code_hash, storage_usage) in [
+            ("alice.near", 100, CryptoHash::default(), 10),
+            ("bob.near", 1000, CryptoHash::hash_bytes(b"contract"), 500),
+            ("carol.near", 10, CryptoHash::default(), 1000),
+        ] {
+            let account_id: AccountId = account_id.parse().unwrap();
+            let account = Account::new(amount, 0, code_hash, storage_usage);
+            changes.push((
+                TrieKey::Account { account_id: account_id.clone() }.to_vec(),
+                Some(account.try_to_vec().unwrap()),
+            ));
+            // Records of other columns must not be returned.
+            changes.push((
+                TrieKey::ContractData { account_id, key: b"key".to_vec() }.to_vec(),
+                Some(b"value".to_vec()),
+            ));
+        }
+        let root =
+            test_populate_trie(&tries, &CryptoHash::default(), ShardUId::single_shard(), changes);
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard());
+        AccountIterator::new(&trie, &root, filter)
+            .unwrap()
+            .map(|item| item.unwrap().0.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_account_iterator() {
+        assert_eq!(account_ids(Default::default()), ["alice.near", "bob.near", "carol.near"]);
+        assert_eq!(
+            account_ids(AccountFilter { has_contract: Some(true), ..Default::default() }),
+            ["bob.near"]
+        );
+        assert_eq!(
+            account_ids(AccountFilter {
+                min_balance: Some(50),
+                max_storage_usage: Some(100),
+                ..Default::default()
+            }),
+            ["alice.near"]
+        );
+    }
+}
@@ -5,7 +5,7 @@ use near_primitives::hash::{hash, CryptoHash};
 use crate::trie::nibble_slice::NibbleSlice;
 use crate::trie::{
     NodeHandle, RawTrieNode, RawTrieNodeWithSize, StorageHandle, StorageValueHandle, TrieNode,
-    TrieNodeWithSize, ValueHandle,
+    TrieNodeWithSize, ValueHandle, MAX_INLINE_VALUE_LENGTH,
 };
 use crate::{StorageError, Trie, TrieChanges};
 
@@ -544,6 +544,7 @@ impl Trie {
         old_root: &CryptoHash,
         memory: NodesStorage,
         node: StorageHandle,
+        inline_small_values: bool,
     ) -> Result<TrieChanges, StorageError> {
         let mut stack: Vec<(StorageHandle, FlattenNodesCrumb)> = Vec::new();
         stack.push((node, FlattenNodesCrumb::Entering));
@@ -593,10 +594,17 @@ impl Trie {
                                 _ => unreachable!(),
                             }
                         }
+                        let inline_value = value
+                            .as_ref()
+                            .filter(|_| inline_small_values)
+                            .and_then(|value| Trie::inline_value(&memory, value));
                         let new_value =
                             value.clone().map(|value| Trie::flatten_value(&mut memory, value));
 
-                        RawTrieNode::Branch(*new_children, new_value)
+                        match inline_value {
+                            Some(value) => RawTrieNode::BranchInline(*new_children, value),
+                            None => RawTrieNode::Branch(*new_children, new_value),
+                        }
                     }
                     FlattenNodesCrumb::Exiting => unreachable!(),
                 },
@@ -615,8 +623,16 @@ impl Trie {
                 TrieNode::Leaf(key, value) => {
                     let key = key.clone();
                     let value = value.clone();
+                    let inline_value = if inline_small_values {
+                        Trie::inline_value(&memory, &value)
+                    } else {
+                        None
+                    };
                     let (value_length, value_hash) = Trie::flatten_value(&mut memory, value);
-                    RawTrieNode::Leaf(key, value_length, value_hash)
+                    match inline_value {
+                        Some(value) => RawTrieNode::LeafInline(key, value),
+                        None => RawTrieNode::Leaf(key, value_length, value_hash),
+                    }
                 }
             };
             let raw_node_with_size = RawTrieNodeWithSize { node: raw_node, memory_usage };
@@ -634,6 +650,18 @@ impl Trie {
         Ok(TrieChanges { old_root: *old_root, new_root: last_hash, insertions, deletions })
     }
 
+    /// Returns the value to inline into the node referring to it: a new value, as the values
+    /// which are already stored are only known by hash, up to `MAX_INLINE_VALUE_LENGTH` bytes.
+    fn inline_value(memory: &NodesStorage, value: &ValueHandle) -> Option<Vec<u8>> {
+        match value {
+            ValueHandle::InMemory(value_handle) => {
+                let value = memory.value_ref(*value_handle);
+                (value.len() <= MAX_INLINE_VALUE_LENGTH).then(|| value.clone())
+            }
+            ValueHandle::HashAndSize(..) => None,
+        }
+    }
+
     fn flatten_value(memory: &mut NodesStorage, value: ValueHandle) -> (u32, CryptoHash) {
         match value {
             ValueHandle::InMemory(value_handle) => {
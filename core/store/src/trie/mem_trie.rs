@@ -0,0 +1,253 @@
+//! Keeping the whole state of some shards in memory.
+//!
+//! A [`MemTrie`] holds every node and value of a shard in the State column along with its
+//! reference count, so that applying the chunks of the shard doesn't read RocksDB.  The tries
+//! are loaded when the node starts and follow the reference count updates committed through
+//! [`crate::ShardTries`].  Whatever is missing from a memtrie is read from the disk, so a
+//! memtrie which fell behind the disk only costs performance, never correctness.
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::db::refcount::decode_value_with_rc;
+use crate::trie::{TrieCachingStorage, POISONED_LOCK_ERR};
+use crate::{metrics, DBCol, Store};
+
+/// Approximate memory used by an entry on top of the node or value itself: the key, the
+/// reference count, the slice pointer and the hash map bookkeeping.
+const ENTRY_OVERHEAD: u64 = 32 + 8 + 16 + 8;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct MemTriesConfig {
+    /// Whether to load the state of the tracked shards into memory on startup.
+    pub enabled: bool,
+    /// Maximum memory used by the tries of all shards.  Shards which don't fit are read from
+    /// the disk, as are shards which outgrow the budget later.
+    pub memory_budget_bytes: u64,
+}
+
+impl Default for MemTriesConfig {
+    fn default() -> Self {
+        Self { enabled: false, memory_budget_bytes: 16 * bytesize::GIB }
+    }
+}
+
+#[derive(Default)]
+struct MemTrieInner {
+    nodes: HashMap<CryptoHash, (Arc<[u8]>, i64)>,
+    memory_usage: u64,
+}
+
+/// Nodes and values of the state of a shard, by hash.
+#[derive(Clone, Default)]
+pub struct MemTrie(Arc<RwLock<MemTrieInner>>);
+
+impl MemTrie {
+    pub fn get(&self, hash: &CryptoHash) -> Option<Arc<[u8]>> {
+        self.0.read().expect(POISONED_LOCK_ERR).nodes.get(hash).map(|(value, _rc)| value.clone())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.read().expect(POISONED_LOCK_ERR).nodes.len()
+    }
+
+    pub fn memory_usage(&self) -> u64 {
+        self.0.read().expect(POISONED_LOCK_ERR).memory_usage
+    }
+
+    fn clear(&self) {
+        *self.0.write().expect(POISONED_LOCK_ERR) = MemTrieInner::default();
+    }
+
+    /// Applies a reference count update as written to the State column.  Nodes and values are
+    /// removed once no reference to them remains; decrements of entries which aren't in memory
+    /// are ignored.
+    fn update_refcount(&self, hash: CryptoHash, value_rc: &[u8]) {
+        let (value, rc_delta) = decode_value_with_rc(value_rc);
+        let mut inner = self.0.write().expect(POISONED_LOCK_ERR);
+        let inner = &mut *inner;
+        match inner.nodes.get_mut(&hash) {
+            Some((value, rc)) => {
+                *rc += rc_delta;
+                if *rc <= 0 {
+                    inner.memory_usage -= value.len() as u64 + ENTRY_OVERHEAD;
+                    inner.nodes.remove(&hash);
+                }
+            }
+            None => {
+                if let Some(value) = value.filter(|_| rc_delta > 0) {
+                    inner.memory_usage += value.len() as u64 + ENTRY_OVERHEAD;
+                    inner.nodes.insert(hash, (value.into(), rc_delta));
+                }
+            }
+        }
+    }
+}
+
+/// The memtries of all shards, within a common memory budget.
+#[derive(Default)]
+pub(crate) struct MemTries {
+    tries: HashMap<ShardUId, MemTrie>,
+    memory_budget_bytes: u64,
+}
+
+impl MemTries {
+    /// Loads the state of the given shards from the State column, in a single pass over it.  The
+    /// shards are loaded in the order of their keys as long as they fit into the budget.
+    pub(crate) fn load(
+        store: &Store,
+        shard_uids: &[ShardUId],
+        memory_budget_bytes: u64,
+    ) -> io::Result<Self> {
+        let mut tries: HashMap<ShardUId, MemTrie> =
+            shard_uids.iter().map(|&shard_uid| (shard_uid, MemTrie::default())).collect();
+        let mut memory_usage = 0;
+        for (key, value) in store.iter_without_rc_logic(DBCol::ColState) {
+            let (shard_uid, hash) = TrieCachingStorage::get_shard_uid_and_hash_from_key(&key)?;
+            let mem_trie = match tries.get(&shard_uid) {
+                Some(mem_trie) => mem_trie,
+                None => continue,
+            };
+            let previous_usage = mem_trie.memory_usage();
+            mem_trie.update_refcount(hash, &value);
+            memory_usage += mem_trie.memory_usage() - previous_usage;
+            if memory_usage > memory_budget_bytes {
+                warn!(
+                    target: "store",
+                    "State of shard {} doesn't fit into the memtrie budget of {} bytes, it is read from the disk",
+                    shard_uid.shard_id, memory_budget_bytes
+                );
+                memory_usage -= mem_trie.memory_usage();
+                tries.remove(&shard_uid);
+            }
+        }
+        for (shard_uid, mem_trie) in tries.iter() {
+            info!(
+                target: "store",
+                "Loaded the state of shard {} into memory: {} entries, {} bytes",
+                shard_uid.shard_id, mem_trie.len(), mem_trie.memory_usage()
+            );
+        }
+        let mem_tries = Self { tries, memory_budget_bytes };
+        mem_tries.update_metrics();
+        Ok(mem_tries)
+    }
+
+    pub(crate) fn get(&self, shard_uid: &ShardUId) -> Option<MemTrie> {
+        self.tries.get(shard_uid).cloned()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tries.is_empty()
+    }
+
+    pub(crate) fn update_refcount(&self, shard_uid: ShardUId, hash: CryptoHash, value_rc: &[u8]) {
+        if let Some(mem_trie) = self.tries.get(&shard_uid) {
+            mem_trie.update_refcount(hash, value_rc);
+        }
+    }
+
+    /// Drops the memtries of the shards beyond the budget, largest first, to fall back to the
+    /// disk for them.
+    pub(crate) fn enforce_budget(&mut self) {
+        while self.memory_usage() > self.memory_budget_bytes {
+            let (&shard_uid, _) =
+                match self.tries.iter().max_by_key(|(_, mem_trie)| mem_trie.memory_usage()) {
+                    Some(largest) => largest,
+                    None => break,
+                };
+            warn!(
+                target: "store",
+                "State of shard {} outgrew the memtrie budget of {} bytes, it is read from the disk from now on",
+                shard_uid.shard_id, self.memory_budget_bytes
+            );
+            self.tries.remove(&shard_uid);
+        }
+        self.update_metrics();
+    }
+
+    /// Drops all the nodes and values, which are gone from the State column.  The memtries keep
+    /// following the updates, so they are complete again once the state is synced.
+    pub(crate) fn clear(&self) {
+        for mem_trie in self.tries.values() {
+            mem_trie.clear();
+        }
+        self.update_metrics();
+    }
+
+    fn memory_usage(&self) -> u64 {
+        self.tries.values().map(MemTrie::memory_usage).sum()
+    }
+
+    fn update_metrics(&self) {
+        metrics::MEM_TRIES_MEMORY_USAGE.set(self.memory_usage() as i64);
+        metrics::MEM_TRIES_LOADED_SHARDS.set(self.tries.len() as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_tries_complex;
+    use crate::trie::TrieCache;
+    use crate::{ShardTries, Trie};
+
+    /// Trie which reads the store and the memtrie, if any, but has no shard cache.
+    fn uncached_trie(tries: &ShardTries, shard_uid: ShardUId) -> Trie {
+        let mut storage = TrieCachingStorage::new(tries.get_store(), TrieCache::new(), shard_uid);
+        storage.mem_trie = tries.get_mem_trie(shard_uid);
+        Trie::new(Box::new(storage), shard_uid)
+    }
+    use near_primitives::hash::hash;
+
+    #[test]
+    fn test_mem_tries() {
+        let tries = create_tries_complex(1, 2);
+        let store = tries.get_store();
+        let shard_uids = [0, 1].map(|shard_id| ShardUId { version: 1, shard_id });
+        let mut roots = vec![];
+        for shard_uid in shard_uids {
+            let trie = tries.get_trie_for_shard(shard_uid);
+            let changes = (0..50u8).map(|i| (vec![i], Some(vec![i; 100])));
+            let trie_changes = trie.update(&Trie::empty_root(), changes).unwrap();
+            let (store_update, root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+            store_update.commit().unwrap();
+            roots.push(root);
+        }
+
+        // Only the first shard fits into the budget.
+        let loaded = tries.load_mem_tries(&shard_uids, 20_000).unwrap();
+        assert_eq!(loaded, vec![shard_uids[0]]);
+        let mem_trie = tries.get_mem_trie(shard_uids[0]).unwrap();
+        assert!(mem_trie.len() > 50);
+
+        // Reads of the loaded shard don't need the disk.
+        let mut store_update = store.store_update();
+        for (key, value) in store.iter_without_rc_logic(DBCol::ColState) {
+            let (bytes, rc) = decode_value_with_rc(&value);
+            store_update.update_refcount(DBCol::ColState, &key, bytes.unwrap(), -rc);
+        }
+        store_update.commit().unwrap();
+        let trie = uncached_trie(&tries, shard_uids[0]);
+        assert_eq!(trie.get(&roots[0], &[7]).unwrap(), Some(vec![7; 100]));
+        let trie = uncached_trie(&tries, shard_uids[1]);
+        assert!(trie.get(&roots[1], &[7]).is_err());
+
+        // Updates of the state are followed.
+        let trie = uncached_trie(&tries, shard_uids[0]);
+        let changes = (0..10u8).map(|i| (vec![i], None));
+        let trie_changes = trie.update(&roots[0], changes).unwrap();
+        let (store_update, root) = tries.apply_all(&trie_changes, shard_uids[0]).unwrap();
+        store_update.commit().unwrap();
+        assert_eq!(trie.get(&root, &[7]).unwrap(), None);
+        assert_eq!(trie.get(&root, &[17]).unwrap(), Some(vec![17; 100]));
+        assert!(mem_trie.get(&hash(&[7; 100])).is_none());
+        assert!(mem_trie.get(&hash(&[17; 100])).is_some());
+    }
+}
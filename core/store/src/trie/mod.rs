@@ -11,8 +11,9 @@ use near_primitives::challenge::PartialState;
 use near_primitives::contract::ContractCode;
 use near_primitives::hash::{hash, CryptoHash};
 pub use near_primitives::shard_layout::ShardUId;
-use near_primitives::types::{StateRoot, StateRootNode};
+use near_primitives::types::{StateRoot, StateRootNode, TrieCacheMode};
 
+use crate::trie::access_trace::TrieAccessOp;
 use crate::trie::insert_delete::NodesStorage;
 use crate::trie::iterator::TrieIterator;
 use crate::trie::nibble_slice::NibbleSlice;
@@ -21,9 +22,13 @@ pub(crate) use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
 use crate::trie::trie_storage::{TrieMemoryPartialStorage, TrieRecordingStorage, TrieStorage};
 use crate::StorageError;
 
+pub mod access_trace;
+pub mod accounts;
 mod insert_delete;
 pub mod iterator;
+pub mod mem_trie;
 mod nibble_slice;
+pub mod rc_audit;
 mod shard_tries;
 pub mod split_state;
 mod state_parts;
@@ -118,20 +123,29 @@ impl TrieNode {
             RawTrieNode::Leaf(key, value_length, value_hash) => {
                 TrieNode::Leaf(key, ValueHandle::HashAndSize(value_length, value_hash))
             }
-            RawTrieNode::Branch(children, value) => {
-                let mut new_children: Box<[Option<NodeHandle>; 16]> = Default::default();
-                for i in 0..children.len() {
-                    new_children[i] = children[i].map(NodeHandle::Hash);
-                }
-                TrieNode::Branch(
-                    new_children,
-                    value.map(|(value_length, value_hash)| {
-                        ValueHandle::HashAndSize(value_length, value_hash)
-                    }),
-                )
-            }
+            RawTrieNode::Branch(children, value) => TrieNode::Branch(
+                Self::new_children(children),
+                value.map(|(value_length, value_hash)| {
+                    ValueHandle::HashAndSize(value_length, value_hash)
+                }),
+            ),
             RawTrieNode::Extension(key, child) => TrieNode::Extension(key, NodeHandle::Hash(child)),
+            RawTrieNode::LeafInline(key, value) => {
+                TrieNode::Leaf(key, ValueHandle::HashAndSize(value.len() as u32, hash(&value)))
+            }
+            RawTrieNode::BranchInline(children, value) => TrieNode::Branch(
+                Self::new_children(children),
+                Some(ValueHandle::HashAndSize(value.len() as u32, hash(&value))),
+            ),
+        }
+    }
+
+    fn new_children(children: [Option<CryptoHash>; 16]) -> Box<[Option<NodeHandle>; 16]> {
+        let mut new_children: Box<[Option<NodeHandle>; 16]> = Default::default();
+        for i in 0..children.len() {
+            new_children[i] = children[i].map(NodeHandle::Hash);
         }
+        new_children
     }
 
     fn print(
@@ -255,6 +269,10 @@ enum RawTrieNode {
     Leaf(Vec<u8>, u32, CryptoHash),
     Branch([Option<CryptoHash>; 16], Option<(u32, CryptoHash)>),
     Extension(Vec<u8>, CryptoHash),
+    /// Leaf with its value inlined, see `MAX_INLINE_VALUE_LENGTH`.
+    LeafInline(Vec<u8>, Vec<u8>),
+    /// Branch with its value inlined, see `MAX_INLINE_VALUE_LENGTH`.
+    BranchInline([Option<CryptoHash>; 16], Vec<u8>),
 }
 
 /// Trie node + memory cost of its subtree
@@ -269,6 +287,14 @@ const LEAF_NODE: u8 = 0;
 const BRANCH_NODE_NO_VALUE: u8 = 1;
 const BRANCH_NODE_WITH_VALUE: u8 = 2;
 const EXTENSION_NODE: u8 = 3;
+const LEAF_NODE_INLINE_VALUE: u8 = 4;
+const BRANCH_NODE_INLINE_VALUE: u8 = 5;
+
+/// Values up to this length are inlined into the new nodes referring to them if the trie is
+/// updated with `inline_small_values`, which saves a lookup when reading them.  The values are
+/// still stored separately as well, so that the nodes are traversed, split into state parts and
+/// reference counted the same way whether their values are inlined or not.
+const MAX_INLINE_VALUE_LENGTH: usize = 128;
 
 fn decode_children(cursor: &mut Cursor<&[u8]>) -> Result<[Option<CryptoHash>; 16], std::io::Error> {
     let mut children: [Option<CryptoHash>; 16] = Default::default();
@@ -285,6 +311,34 @@ fn decode_children(cursor: &mut Cursor<&[u8]>) -> Result<[Option<CryptoHash>; 16
     Ok(children)
 }
 
+fn decode_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Vec<u8>, std::io::Error> {
+    let length = cursor.read_u32::<LittleEndian>()?;
+    let mut bytes = vec![0; length as usize];
+    cursor.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn encode_children(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    children: &[Option<CryptoHash>; 16],
+) -> Result<(), std::io::Error> {
+    let mut bitmap: u16 = 0;
+    let mut pos: u16 = 1;
+    for child in children.iter() {
+        if child.is_some() {
+            bitmap |= pos
+        }
+        pos <<= 1;
+    }
+    cursor.write_u16::<LittleEndian>(bitmap)?;
+    for child in children.iter() {
+        if let Some(hash) = child {
+            cursor.write_all(hash.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
 impl RawTrieNode {
     fn encode_into(&self, out: &mut Vec<u8>) -> Result<(), std::io::Error> {
         let mut cursor = Cursor::new(out);
@@ -307,20 +361,7 @@ impl RawTrieNode {
                 } else {
                     cursor.write_u8(BRANCH_NODE_NO_VALUE)?;
                 }
-                let mut bitmap: u16 = 0;
-                let mut pos: u16 = 1;
-                for child in children.iter() {
-                    if child.is_some() {
-                        bitmap |= pos
-                    }
-                    pos <<= 1;
-                }
-                cursor.write_u16::<LittleEndian>(bitmap)?;
-                for child in children.iter() {
-                    if let Some(hash) = child {
-                        cursor.write_all(hash.as_ref())?;
-                    }
-                }
+                encode_children(&mut cursor, children)?;
             }
             // size <= 1 + 4 + key_length + 32
             RawTrieNode::Extension(key, child) => {
@@ -329,6 +370,21 @@ impl RawTrieNode {
                 cursor.write_all(key)?;
                 cursor.write_all(child.as_ref())?;
             }
+            // size <= 1 + 4 + key_length + 4 + value_length
+            RawTrieNode::LeafInline(key, value) => {
+                cursor.write_u8(LEAF_NODE_INLINE_VALUE)?;
+                cursor.write_u32::<LittleEndian>(key.len() as u32)?;
+                cursor.write_all(key)?;
+                cursor.write_u32::<LittleEndian>(value.len() as u32)?;
+                cursor.write_all(value)?;
+            }
+            // size <= 1 + 4 + value_length + 2 + 32 * num_children
+            RawTrieNode::BranchInline(children, value) => {
+                cursor.write_u8(BRANCH_NODE_INLINE_VALUE)?;
+                cursor.write_u32::<LittleEndian>(value.len() as u32)?;
+                cursor.write_all(value)?;
+                encode_children(&mut cursor, children)?;
+            }
         }
         Ok(())
     }
@@ -373,6 +429,16 @@ impl RawTrieNode {
                 cursor.read_exact(&mut child)?;
                 Ok(RawTrieNode::Extension(key, CryptoHash(child)))
             }
+            LEAF_NODE_INLINE_VALUE => {
+                let key = decode_bytes(&mut cursor)?;
+                let value = decode_bytes(&mut cursor)?;
+                Ok(RawTrieNode::LeafInline(key, value))
+            }
+            BRANCH_NODE_INLINE_VALUE => {
+                let value = decode_bytes(&mut cursor)?;
+                let children = decode_children(&mut cursor)?;
+                Ok(RawTrieNode::BranchInline(children, value))
+            }
             _ => Err(std::io::Error::new(std::io::ErrorKind::Other, "Wrong type")),
         }
     }
@@ -407,6 +473,23 @@ pub struct Trie {
     pub(crate) storage: Box<dyn TrieStorage>,
 }
 
+/// Value found by a lookup.
+pub(crate) enum LookupValue {
+    /// Length and hash of a value stored separately.
+    Ref(u32, CryptoHash),
+    /// Value inlined into its node.
+    Inline(Vec<u8>),
+}
+
+impl LookupValue {
+    pub(crate) fn len(&self) -> u32 {
+        match self {
+            LookupValue::Ref(length, _) => *length,
+            LookupValue::Inline(value) => value.len() as u32,
+        }
+    }
+}
+
 /// Stores reference count change for some key-value pair in DB.
 #[derive(BorshSerialize, BorshDeserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub struct TrieRefcountChange {
@@ -623,11 +706,54 @@ impl Trie {
         }
     }
 
+    /// Visits the hashes of the nodes and values of the trie with the given root, depth-first.
+    /// The children and value of a node are visited only if `visit` returns true for the node,
+    /// which lets the caller skip subtrees it has already seen.
+    pub fn visit_nodes(
+        &self,
+        root: &StateRoot,
+        mut visit: impl FnMut(&CryptoHash) -> Result<bool, StorageError>,
+    ) -> Result<(), StorageError> {
+        let mut stack = vec![];
+        if *root != Trie::empty_root() {
+            stack.push(*root);
+        }
+        while let Some(hash) = stack.pop() {
+            if !visit(&hash)? {
+                continue;
+            }
+            let bytes = self.storage.retrieve_raw_bytes(&hash)?;
+            let node = RawTrieNodeWithSize::decode(&bytes).map_err(|_| {
+                StorageError::StorageInconsistentState(format!("Failed to decode node {}", hash))
+            })?;
+            match node.node {
+                RawTrieNode::Leaf(_, _, value_hash) => {
+                    visit(&value_hash)?;
+                }
+                RawTrieNode::Extension(_, child) => stack.push(child),
+                RawTrieNode::Branch(children, value) => {
+                    if let Some((_, value_hash)) = value {
+                        visit(&value_hash)?;
+                    }
+                    stack.extend(children.iter().flatten());
+                }
+                RawTrieNode::LeafInline(_, value) => {
+                    visit(&hash(&value))?;
+                }
+                RawTrieNode::BranchInline(children, value) => {
+                    visit(&hash(&value))?;
+                    stack.extend(children.iter().flatten());
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn lookup(
         &self,
         root: &CryptoHash,
         mut key: NibbleSlice<'_>,
-    ) -> Result<Option<(u32, CryptoHash)>, StorageError> {
+    ) -> Result<Option<LookupValue>, StorageError> {
         let mut hash = *root;
 
         loop {
@@ -639,59 +765,76 @@ impl Trie {
                 StorageError::StorageInconsistentState("RawTrieNode decode failed".to_string())
             })?;
 
-            match node.node {
+            let (children, value) = match node.node {
                 RawTrieNode::Leaf(existing_key, value_length, value_hash) => {
-                    if NibbleSlice::from_encoded(&existing_key).0 == key {
-                        return Ok(Some((value_length, value_hash)));
-                    } else {
-                        return Ok(None);
-                    }
+                    let found = NibbleSlice::from_encoded(&existing_key).0 == key;
+                    return Ok(found.then(|| LookupValue::Ref(value_length, value_hash)));
+                }
+                RawTrieNode::LeafInline(existing_key, value) => {
+                    let found = NibbleSlice::from_encoded(&existing_key).0 == key;
+                    return Ok(found.then(|| LookupValue::Inline(value)));
                 }
                 RawTrieNode::Extension(existing_key, child) => {
                     let existing_key = NibbleSlice::from_encoded(&existing_key).0;
                     if key.starts_with(&existing_key) {
                         hash = child;
                         key = key.mid(existing_key.len());
+                        continue;
                     } else {
                         return Ok(None);
                     }
                 }
-                RawTrieNode::Branch(mut children, value) => {
-                    if key.is_empty() {
-                        match value {
-                            Some((value_length, value_hash)) => {
-                                return Ok(Some((value_length, value_hash)));
-                            }
-                            None => return Ok(None),
-                        }
-                    } else {
-                        match children[key.at(0) as usize].take() {
-                            Some(x) => {
-                                hash = x;
-                                key = key.mid(1);
-                            }
-                            None => return Ok(None),
-                        }
-                    }
+                RawTrieNode::Branch(children, value) => {
+                    (children, value.map(|(length, hash)| LookupValue::Ref(length, hash)))
+                }
+                RawTrieNode::BranchInline(children, value) => {
+                    (children, Some(LookupValue::Inline(value)))
                 }
             };
+            if key.is_empty() {
+                return Ok(value);
+            }
+            match children[key.at(0) as usize] {
+                Some(x) => {
+                    hash = x;
+                    key = key.mid(1);
+                }
+                None => return Ok(None),
+            }
         }
     }
 
+    /// Looks up the value of the key, which comes with the node if it was inlined.
+    pub(crate) fn get_value_ref(
+        &self,
+        root: &CryptoHash,
+        key: &[u8],
+    ) -> Result<Option<LookupValue>, StorageError> {
+        let result = self.lookup(root, NibbleSlice::new(key))?;
+        if access_trace::is_recording() {
+            let value_len = result.as_ref().map_or(0, LookupValue::len);
+            access_trace::record(TrieAccessOp::Get, key, value_len);
+        }
+        Ok(result)
+    }
+
     pub fn get_ref(
         &self,
         root: &CryptoHash,
         key: &[u8],
     ) -> Result<Option<(u32, CryptoHash)>, StorageError> {
-        let key = NibbleSlice::new(key);
-        self.lookup(root, key)
+        Ok(self.get_value_ref(root, key)?.map(|value| match value {
+            LookupValue::Ref(length, hash) => (length, hash),
+            LookupValue::Inline(value) => (value.len() as u32, hash(&value)),
+        }))
     }
 
     pub fn get(&self, root: &CryptoHash, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
-        match self.get_ref(root, key)? {
-            Some((_length, hash)) => {
+        match self.get_value_ref(root, key)? {
+            Some(LookupValue::Ref(_length, hash)) => {
                 self.storage.retrieve_raw_bytes(&hash).map(|bytes| Some(bytes.to_vec()))
             }
+            Some(LookupValue::Inline(value)) => Ok(Some(value)),
             None => Ok(None),
         }
     }
@@ -723,12 +866,33 @@ impl Trie {
     }
 
     pub fn update<I>(&self, root: &CryptoHash, changes: I) -> Result<TrieChanges, StorageError>
+    where
+        I: Iterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
+    {
+        self.update_with_options(root, changes, false)
+    }
+
+    /// Like `update`, but if `inline_small_values` is set, the new values up to
+    /// `MAX_INLINE_VALUE_LENGTH` bytes are inlined into the nodes referring to them.
+    pub(crate) fn update_with_options<I>(
+        &self,
+        root: &CryptoHash,
+        changes: I,
+        inline_small_values: bool,
+    ) -> Result<TrieChanges, StorageError>
     where
         I: Iterator<Item = (Vec<u8>, Option<Vec<u8>>)>,
     {
         let mut memory = NodesStorage::new();
         let mut root_node = self.move_node_to_mutable(&mut memory, root)?;
+        let recording = access_trace::is_recording();
         for (key, value) in changes {
+            if recording {
+                match &value {
+                    Some(arr) => access_trace::record(TrieAccessOp::Insert, &key, arr.len() as u32),
+                    None => access_trace::record(TrieAccessOp::Delete, &key, 0),
+                }
+            }
             let key = NibbleSlice::new(&key);
             match value {
                 Some(arr) => {
@@ -744,20 +908,33 @@ impl Trie {
         {
             self.memory_usage_verify(&memory, NodeHandle::InMemory(root_node));
         }
-        Trie::flatten_nodes(root, memory, root_node)
+        Trie::flatten_nodes(root, memory, root_node, inline_small_values)
     }
 
     pub fn iter<'a>(&'a self, root: &CryptoHash) -> Result<TrieIterator<'a>, StorageError> {
         TrieIterator::new(self, root)
     }
 
+    /// Sets the cache mode of the underlying storage, if it caches nodes.
+    pub fn set_cache_mode(&self, mode: TrieCacheMode) {
+        if let Some(storage) = self.storage.as_caching_storage() {
+            storage.set_mode(mode);
+        }
+    }
+
     pub fn get_touched_nodes_count(&self) -> u64 {
         self.storage.get_touched_nodes_count()
     }
+
+    pub fn get_state_witness_size(&self) -> u64 {
+        self.storage.get_state_witness_size()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use rand::Rng;
 
     use crate::db::DBCol::ColState;
@@ -811,6 +988,56 @@ mod tests {
         let buf = node.encode().expect("Failed to serialize");
         let new_node = RawTrieNode::decode(&buf).expect("Failed to deserialize");
         assert_eq!(node, new_node);
+
+        let node = RawTrieNode::LeafInline(vec![1, 2, 3], value.clone());
+        let buf = node.encode().expect("Failed to serialize");
+        let new_node = RawTrieNode::decode(&buf).expect("Failed to deserialize");
+        assert_eq!(node, new_node);
+
+        let node = RawTrieNode::BranchInline(children, value);
+        let buf = node.encode().expect("Failed to serialize");
+        let new_node = RawTrieNode::decode(&buf).expect("Failed to deserialize");
+        assert_eq!(node, new_node);
+    }
+
+    #[test]
+    fn test_inline_small_values() {
+        let tries = create_tries();
+        let shard_uid = ShardUId::single_shard();
+        let trie = tries.get_trie_for_shard(shard_uid);
+        let small = b"balance".to_vec();
+        let large = vec![7; MAX_INLINE_VALUE_LENGTH + 1];
+        let apply = |root: &CryptoHash, changes: TrieChanges, inline_small_values: bool| {
+            let trie_changes =
+                trie.update_with_options(root, changes.into_iter(), inline_small_values).unwrap();
+            let (store_update, root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+            store_update.commit().unwrap();
+            root
+        };
+        // Nodes without inlined values are still read once nodes with inlined values are added.
+        let root = apply(
+            &Trie::empty_root(),
+            vec![(b"alice".to_vec(), Some(small.clone())), (b"bob".to_vec(), Some(large.clone()))],
+            false,
+        );
+        let root = apply(
+            &root,
+            vec![(b"al".to_vec(), Some(small.clone())), (b"carol".to_vec(), Some(small.clone()))],
+            true,
+        );
+        for (key, value) in [("alice", &small), ("al", &small), ("bob", &large), ("carol", &small)]
+        {
+            assert_eq!(trie.get(&root, key.as_bytes()), Ok(Some(value.clone())));
+            let value_ref = (value.len() as u32, hash(value));
+            assert_eq!(trie.get_ref(&root, key.as_bytes()), Ok(Some(value_ref)));
+        }
+        let values: Vec<_> = trie.iter(&root).unwrap().map(|item| item.unwrap().1).collect();
+        assert_eq!(values, vec![small.clone(), small.clone(), large, small]);
+
+        // The root extension, the branch and the leaf, without the value.
+        let trie = tries.get_trie_for_shard(shard_uid);
+        trie.get(&root, b"carol").unwrap();
+        assert_eq!(trie.get_touched_nodes_count(), 3);
     }
 
     #[test]
@@ -1066,6 +1293,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_visit_nodes() {
+        let tries = create_tries();
+        let shard_uid = ShardUId::single_shard();
+        let changes = vec![
+            (b"doge".to_vec(), Some(b"coin".to_vec())),
+            (b"docu".to_vec(), Some(b"value".to_vec())),
+            (b"do".to_vec(), Some(b"verb".to_vec())),
+            (b"horse".to_vec(), Some(b"stallion".to_vec())),
+        ];
+        let root = test_populate_trie(&tries, &Trie::empty_root(), shard_uid, changes);
+        let trie = tries.get_trie_for_shard(shard_uid);
+
+        let mut visited = vec![];
+        trie.visit_nodes(&root, |hash| {
+            visited.push(*hash);
+            Ok(true)
+        })
+        .unwrap();
+        let stored: HashSet<_> = tries
+            .get_store()
+            .iter(ColState)
+            .map(|(key, _)| CryptoHash::try_from(&key[8..]).unwrap())
+            .collect();
+        assert_eq!(visited.iter().copied().collect::<HashSet<_>>(), stored);
+
+        let mut visited_root_only = vec![];
+        trie.visit_nodes(&root, |hash| {
+            visited_root_only.push(*hash);
+            Ok(false)
+        })
+        .unwrap();
+        assert_eq!(visited_root_only, vec![root]);
+    }
+
     #[test]
     fn test_trie_restart() {
         let store = create_test_store();
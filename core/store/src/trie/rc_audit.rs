@@ -0,0 +1,221 @@
+//! Recomputing the reference counts of the nodes and values in the State column.
+//!
+//! A node or value is counted once for every position at which it occurs in the state of a
+//! shard.  On top of that, the changes of the blocks which aren't garbage collected yet count:
+//! the deletions of a block on the canonical chain are applied only once the block is garbage
+//! collected, and the insertions of a block on a fork are reverted only then.
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::StateRoot;
+
+use crate::db::refcount::decode_value_with_rc;
+use crate::trie::{RawTrieNode, RawTrieNodeWithSize, TrieCachingStorage, TrieRefcountChange};
+use crate::{DBCol, Store, Trie, TrieChanges};
+
+/// Number of reference count updates written in one batch when repairing.
+const REPAIR_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefcountMismatch {
+    pub shard_uid: ShardUId,
+    pub hash: CryptoHash,
+    /// Reference count in the State column; zero if the node or value is missing.
+    pub stored: i64,
+    pub expected: i64,
+    /// Whether the stored reference count was replaced by the expected one.  Nodes and values
+    /// missing from the column can be restored only if some unapplied trie changes contain them.
+    pub repaired: bool,
+}
+
+#[derive(Default)]
+pub struct RefcountAudit {
+    expected: HashMap<(ShardUId, CryptoHash), i64>,
+    /// Contents of the nodes and values found in the trie changes, to restore missing ones.
+    contents: HashMap<(ShardUId, CryptoHash), Vec<u8>>,
+    shard_uids: HashSet<ShardUId>,
+}
+
+impl RefcountAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the nodes and values of the state of the shard with the given root.  The subtrees
+    /// of the nodes missing from the State column can't be counted.
+    pub fn add_state(
+        &mut self,
+        store: &Store,
+        shard_uid: ShardUId,
+        root: &StateRoot,
+    ) -> io::Result<()> {
+        self.shard_uids.insert(shard_uid);
+        let mut stack = vec![];
+        if *root != Trie::empty_root() {
+            stack.push(*root);
+        }
+        while let Some(hash) = stack.pop() {
+            *self.expected.entry((shard_uid, hash)).or_insert(0) += 1;
+            let key = TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, &hash);
+            let bytes = match store.get(DBCol::ColState, &key)? {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+            let mut count_value = |value_hash: CryptoHash| {
+                *self.expected.entry((shard_uid, value_hash)).or_insert(0) += 1;
+            };
+            match RawTrieNodeWithSize::decode(&bytes)?.node {
+                RawTrieNode::Leaf(_, _, value_hash) => count_value(value_hash),
+                RawTrieNode::Extension(_, child) => stack.push(child),
+                RawTrieNode::Branch(children, value) => {
+                    if let Some((_, value_hash)) = value {
+                        count_value(value_hash);
+                    }
+                    stack.extend(children.iter().flatten());
+                }
+                RawTrieNode::LeafInline(_, value) => count_value(hash(&value)),
+                RawTrieNode::BranchInline(children, value) => {
+                    count_value(hash(&value));
+                    stack.extend(children.iter().flatten());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the deletions of trie changes of a block on the canonical chain which isn't
+    /// garbage collected yet.
+    pub fn add_unapplied_deletions(&mut self, shard_uid: ShardUId, trie_changes: &TrieChanges) {
+        self.add_changes(shard_uid, &trie_changes.deletions);
+    }
+
+    /// Counts the insertions of trie changes of a block on a fork which isn't garbage collected
+    /// yet.
+    pub fn add_unreverted_insertions(&mut self, shard_uid: ShardUId, trie_changes: &TrieChanges) {
+        self.add_changes(shard_uid, &trie_changes.insertions);
+    }
+
+    fn add_changes(&mut self, shard_uid: ShardUId, changes: &[TrieRefcountChange]) {
+        for change in changes {
+            let key = (shard_uid, change.trie_node_or_value_hash);
+            *self.expected.entry(key).or_insert(0) += change.rc as i64;
+            self.contents.entry(key).or_insert_with(|| change.trie_node_or_value.clone());
+        }
+    }
+
+    /// Compares the counted references with the State column, for the shards whose state was
+    /// added, and if `repair` is set, replaces the stored reference counts which differ.
+    pub fn compare(mut self, store: &Store, repair: bool) -> io::Result<Vec<RefcountMismatch>> {
+        let mut mismatches = vec![];
+        let mut store_update = store.store_update();
+        let mut num_updates = 0;
+        let mut update_refcount = |key: &[u8], value: &[u8], rc_delta: i64| -> io::Result<()> {
+            store_update.update_refcount(DBCol::ColState, key, value, rc_delta);
+            num_updates += 1;
+            if num_updates % REPAIR_BATCH_SIZE == 0 {
+                std::mem::replace(&mut store_update, store.store_update()).commit()?;
+            }
+            Ok(())
+        };
+
+        for (key, value) in store.iter_without_rc_logic(DBCol::ColState) {
+            let (shard_uid, hash) = TrieCachingStorage::get_shard_uid_and_hash_from_key(&key)?;
+            if !self.shard_uids.contains(&shard_uid) {
+                continue;
+            }
+            let (bytes, stored) = decode_value_with_rc(&value);
+            let expected = self.expected.remove(&(shard_uid, hash)).unwrap_or(0);
+            if stored == expected {
+                continue;
+            }
+            let contents =
+                bytes.or_else(|| self.contents.get(&(shard_uid, hash)).map(Vec::as_slice));
+            let repaired = repair && (expected <= 0 || contents.is_some());
+            if repaired {
+                update_refcount(&key, contents.unwrap_or(&[]), expected - stored)?;
+            }
+            mismatches.push(RefcountMismatch { shard_uid, hash, stored, expected, repaired });
+        }
+        // The rest are missing from the column.
+        for ((shard_uid, hash), expected) in self.expected {
+            if expected == 0 {
+                continue;
+            }
+            let contents = self.contents.get(&(shard_uid, hash));
+            if let (true, Some(contents)) = (repair, contents) {
+                let key = TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, &hash);
+                update_refcount(&key, contents, expected)?;
+            }
+            let repaired = repair && contents.is_some();
+            mismatches.push(RefcountMismatch { shard_uid, hash, stored: 0, expected, repaired });
+        }
+        drop(update_refcount);
+        store_update.commit()?;
+        Ok(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_tries;
+
+    #[test]
+    fn test_refcount_audit() {
+        let tries = create_tries();
+        let shard_uid = ShardUId::single_shard();
+        let store = tries.get_store();
+        let trie = tries.get_trie_for_shard(shard_uid);
+        let changes: Vec<_> =
+            (0..100u8).map(|i| (vec![i / 10, i], Some(vec![i % 3; 10]))).collect();
+        let trie_changes = trie.update(&Trie::empty_root(), changes.into_iter()).unwrap();
+        let (store_update, root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+        store_update.commit().unwrap();
+
+        // A block changing the state, not garbage collected yet.
+        let changes =
+            (0..20u8).map(|i| (vec![i / 10, i], if i % 2 == 0 { None } else { Some(vec![7]) }));
+        let trie_changes = trie.update(&root, changes).unwrap();
+        let mut store_update = store.store_update();
+        tries.apply_insertions(&trie_changes, shard_uid, &mut store_update).unwrap();
+        store_update.commit().unwrap();
+        let audit = || {
+            let mut audit = RefcountAudit::new();
+            audit.add_state(&store, shard_uid, &trie_changes.new_root).unwrap();
+            audit.add_unapplied_deletions(shard_uid, &trie_changes);
+            audit
+        };
+        assert_eq!(audit().compare(&store, false).unwrap(), vec![]);
+
+        // A reference too many and a node which went missing.
+        let value = vec![1; 10];
+        let value_hash = hash(&value);
+        let key = TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, &value_hash);
+        let mut store_update = store.store_update();
+        store_update.update_refcount(DBCol::ColState, &key, &value, 1);
+        let root_key = TrieCachingStorage::get_key_from_shard_uid_and_hash(shard_uid, &root);
+        let (root_node, rc) = decode_value_with_rc(
+            &store.get_without_rc_logic(DBCol::ColState, &root_key).unwrap().unwrap(),
+        );
+        store_update.update_refcount(DBCol::ColState, &root_key, root_node.unwrap(), -rc);
+        store_update.commit().unwrap();
+        let mut mismatches = audit().compare(&store, true).unwrap();
+        mismatches.sort_by_key(|mismatch| mismatch.stored);
+        assert_eq!(
+            mismatches,
+            vec![
+                RefcountMismatch { shard_uid, hash: root, stored: 0, expected: 1, repaired: true },
+                RefcountMismatch {
+                    shard_uid,
+                    hash: value_hash,
+                    stored: 33 + 1,
+                    expected: 33,
+                    repaired: true
+                },
+            ]
+        );
+        assert_eq!(audit().compare(&store, false).unwrap(), vec![]);
+    }
+}
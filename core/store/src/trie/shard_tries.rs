@@ -1,9 +1,10 @@
 use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 
 use borsh::BorshSerialize;
 use near_primitives::borsh::maybestd::collections::HashMap;
-use near_primitives::hash::CryptoHash;
+use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::shard_layout;
 use near_primitives::shard_layout::{ShardUId, ShardVersion};
 use near_primitives::trie_key::TrieKey;
@@ -12,7 +13,8 @@ use near_primitives::types::{
 };
 
 use crate::db::{DBCol, DBOp, DBTransaction};
-use crate::trie::trie_storage::{TrieCache, TrieCachingStorage};
+use crate::trie::mem_trie::{MemTrie, MemTries};
+use crate::trie::trie_storage::{TrieCache, TrieCachingStorage, TRIE_MAX_SHARD_CACHE_SIZE};
 use crate::trie::{TrieRefcountChange, POISONED_LOCK_ERR};
 use crate::{StorageError, Store, StoreUpdate, Trie, TrieChanges, TrieUpdate};
 
@@ -22,6 +24,10 @@ struct ShardTriesInner {
     caches: RwLock<HashMap<ShardUId, TrieCache>>,
     /// Cache for readers.
     view_caches: RwLock<HashMap<ShardUId, TrieCache>>,
+    /// Capacity of each of the caches above.
+    cache_capacity: AtomicUsize,
+    /// State of the shards kept in memory, shared by the client actor and readers.
+    mem_tries: RwLock<MemTries>,
 }
 
 #[derive(Clone)]
@@ -32,6 +38,24 @@ impl ShardTries {
         shards.iter().map(|&shard_id| (shard_id, TrieCache::new())).collect()
     }
 
+    fn new_cache(&self) -> TrieCache {
+        TrieCache::with_capacity(self.0.cache_capacity.load(Ordering::Relaxed))
+    }
+
+    /// Changes the capacity of the cache of every shard, for the client actor and the readers
+    /// alike.  Has no effect if the node is built with `no_cache`.
+    pub fn set_cache_capacity(&self, capacity: usize) {
+        if cfg!(feature = "no_cache") {
+            return;
+        }
+        self.0.cache_capacity.store(capacity, Ordering::Relaxed);
+        for caches in [&self.0.caches, &self.0.view_caches] {
+            for cache in caches.read().expect(POISONED_LOCK_ERR).values() {
+                cache.set_capacity(capacity);
+            }
+        }
+    }
+
     pub fn new(store: Store, shard_version: ShardVersion, num_shards: NumShards) -> Self {
         assert_ne!(num_shards, 0);
         let shards: Vec<_> = (0..num_shards)
@@ -41,9 +65,30 @@ impl ShardTries {
             store,
             caches: RwLock::new(Self::get_new_cache(&shards)),
             view_caches: RwLock::new(Self::get_new_cache(&shards)),
+            cache_capacity: AtomicUsize::new(TRIE_MAX_SHARD_CACHE_SIZE),
+            mem_tries: RwLock::new(MemTries::default()),
         }))
     }
 
+    /// Loads the state of the given shards into memory, within the budget, and returns the
+    /// shards which fit.  Tries of the other shards, and those of the given shards created
+    /// before, read the state from the disk.
+    pub fn load_mem_tries(
+        &self,
+        shard_uids: &[ShardUId],
+        memory_budget_bytes: u64,
+    ) -> std::io::Result<Vec<ShardUId>> {
+        let mem_tries = MemTries::load(&self.0.store, shard_uids, memory_budget_bytes)?;
+        let loaded =
+            shard_uids.iter().copied().filter(|uid| mem_tries.get(uid).is_some()).collect();
+        *self.0.mem_tries.write().expect(POISONED_LOCK_ERR) = mem_tries;
+        Ok(loaded)
+    }
+
+    pub fn get_mem_trie(&self, shard_uid: ShardUId) -> Option<MemTrie> {
+        self.0.mem_tries.read().expect(POISONED_LOCK_ERR).get(&shard_uid)
+    }
+
     pub fn is_same(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.0, &other.0)
     }
@@ -60,9 +105,10 @@ impl ShardTries {
         let caches_to_use = if is_view { &self.0.view_caches } else { &self.0.caches };
         let cache = {
             let mut caches = caches_to_use.write().expect(POISONED_LOCK_ERR);
-            caches.entry(shard_uid).or_insert_with(TrieCache::new).clone()
+            caches.entry(shard_uid).or_insert_with(|| self.new_cache()).clone()
         };
-        let store = Box::new(TrieCachingStorage::new(self.0.store.clone(), cache, shard_uid));
+        let mut store = Box::new(TrieCachingStorage::new(self.0.store.clone(), cache, shard_uid));
+        store.mem_trie = self.get_mem_trie(shard_uid);
         Trie::new(store, shard_uid)
     }
 
@@ -80,12 +126,14 @@ impl ShardTries {
 
     pub fn update_cache(&self, transaction: &DBTransaction) -> std::io::Result<()> {
         let mut caches = self.0.caches.write().expect(POISONED_LOCK_ERR);
+        let mut mem_tries = self.0.mem_tries.write().expect(POISONED_LOCK_ERR);
         let mut shards = HashMap::new();
         for op in &transaction.ops {
             match op {
                 DBOp::UpdateRefcount { col, ref key, ref value } if *col == DBCol::ColState => {
                     let (shard_uid, hash) =
                         TrieCachingStorage::get_shard_uid_and_hash_from_key(key)?;
+                    mem_tries.update_refcount(shard_uid, hash, value);
                     shards.entry(shard_uid).or_insert(vec![]).push((hash, Some(value)));
                 }
                 DBOp::Insert { col, .. } if *col == DBCol::ColState => unreachable!(),
@@ -95,12 +143,16 @@ impl ShardTries {
                     for (_, cache) in caches.iter() {
                         cache.clear();
                     }
+                    mem_tries.clear();
                 }
                 _ => {}
             }
         }
+        if !mem_tries.is_empty() {
+            mem_tries.enforce_budget();
+        }
         for (shard_uid, ops) in shards {
-            let cache = caches.entry(shard_uid).or_insert_with(TrieCache::new).clone();
+            let cache = caches.entry(shard_uid).or_insert_with(|| self.new_cache()).clone();
             cache.update_cache(ops);
         }
         Ok(())
@@ -304,6 +356,14 @@ impl WrappedTrieChanges {
                 "Resharding changes must never be finalized."
             );
 
+            if let TrieKey::ContractCode { account_id } = &change_with_trie_key.trie_key {
+                for RawStateChange { data, .. } in &change_with_trie_key.changes {
+                    if let Some(code) = data {
+                        crate::set_contract_account(store_update, &hash(code), account_id);
+                    }
+                }
+            }
+
             // Filtering trie keys for user facing RPC reporting.
             // NOTE: If the trie key is not one of the account specific, it may cause key conflict
             // when the node tracks multiple shards. See #2563.
@@ -410,3 +470,50 @@ impl KeyForStateChanges {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::trie_key::TrieKey;
+    use near_primitives::types::{RawStateChange, RawStateChangesWithTrieKey, StateChangeCause};
+
+    use crate::test_utils::create_tries;
+    use crate::{get_contract_accounts, TrieChanges, WrappedTrieChanges};
+
+    #[test]
+    fn test_contract_accounts_index() {
+        let tries = create_tries();
+        let code = vec![1, 2, 3];
+        let code_change = |account_id: &str, data: Option<Vec<u8>>| RawStateChangesWithTrieKey {
+            trie_key: TrieKey::ContractCode { account_id: account_id.parse().unwrap() },
+            changes: vec![RawStateChange {
+                cause: StateChangeCause::ReceiptProcessing { receipt_hash: CryptoHash::default() },
+                data,
+            }],
+        };
+        let mut changes = WrappedTrieChanges::new(
+            tries.clone(),
+            ShardUId::single_shard(),
+            TrieChanges::empty(CryptoHash::default()),
+            vec![
+                code_change("bob.near", Some(code.clone())),
+                code_change("alice.near", Some(code.clone())),
+                code_change("carol.near", Some(vec![4])),
+                // Deleted along with the account.
+                code_change("dave.near", None),
+            ],
+            CryptoHash::default(),
+        );
+        let mut store_update = tries.get_store().store_update();
+        changes.state_changes_into(&mut store_update);
+        store_update.commit().unwrap();
+        assert_eq!(
+            get_contract_accounts(&tries.get_store(), &hash(&code)).unwrap(),
+            vec!["alice.near".parse().unwrap(), "bob.near".parse().unwrap()]
+        );
+        assert!(get_contract_accounts(&tries.get_store(), &CryptoHash::default())
+            .unwrap()
+            .is_empty());
+    }
+}
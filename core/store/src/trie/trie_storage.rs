@@ -5,6 +5,7 @@ use std::sync::{Arc, Mutex};
 use near_primitives::hash::CryptoHash;
 
 use crate::db::refcount::decode_value_with_rc;
+use crate::trie::mem_trie::MemTrie;
 use crate::trie::POISONED_LOCK_ERR;
 use crate::{ColState, StorageError, Store};
 use lru::LruCache;
@@ -26,6 +27,11 @@ impl TrieCache {
         Self(Arc::new(Mutex::new(LruCache::new(cap))))
     }
 
+    /// Changes the capacity, evicting the least recently used entries if it shrinks.
+    pub fn set_capacity(&self, cap: usize) {
+        self.0.lock().expect(POISONED_LOCK_ERR).resize(cap)
+    }
+
     pub fn get(&self, key: &CryptoHash) -> Option<Arc<[u8]>> {
         self.0.lock().expect(POISONED_LOCK_ERR).get(key).cloned()
     }
@@ -77,6 +83,10 @@ pub trait TrieStorage {
     }
 
     fn get_touched_nodes_count(&self) -> u64;
+
+    /// Total size of the distinct trie nodes and values retrieved from the storage, i.e. the size
+    /// of the state witness needed to repeat the same reads without the storage.
+    fn get_state_witness_size(&self) -> u64;
 }
 
 /// Records every value read by retrieve_raw_bytes.
@@ -113,6 +123,10 @@ impl TrieStorage for TrieRecordingStorage {
     fn get_touched_nodes_count(&self) -> u64 {
         unimplemented!();
     }
+
+    fn get_state_witness_size(&self) -> u64 {
+        self.recorded.borrow().values().map(|value| value.len() as u64).sum()
+    }
 }
 
 /// Storage for validating recorded partial storage.
@@ -141,6 +155,15 @@ impl TrieStorage for TrieMemoryPartialStorage {
     fn get_touched_nodes_count(&self) -> u64 {
         unimplemented!();
     }
+
+    fn get_state_witness_size(&self) -> u64 {
+        self.visited_nodes
+            .borrow()
+            .iter()
+            .filter_map(|hash| self.recorded_storage.get(hash))
+            .map(|value| value.len() as u64)
+            .sum()
+    }
 }
 
 /// Maximum number of cache entries.
@@ -148,11 +171,12 @@ impl TrieStorage for TrieMemoryPartialStorage {
 /// TRIE_LIMIT_CACHED_VALUE_SIZE * 2 (number of caches - for regular and view client) = 1.6 GB.
 /// In our tests on a single shard, it barely occupied 40 MB, which is dominated by state cache size
 /// with 512 MB limit. The total RAM usage for a single shard was 1 GB.
+/// The node chooses the capacity by the memory of the machine instead, see `StoreResources`.
 #[cfg(not(feature = "no_cache"))]
-const TRIE_MAX_SHARD_CACHE_SIZE: usize = 50000;
+pub(crate) const TRIE_MAX_SHARD_CACHE_SIZE: usize = 50000;
 
 #[cfg(feature = "no_cache")]
-const TRIE_MAX_SHARD_CACHE_SIZE: usize = 1;
+pub(crate) const TRIE_MAX_SHARD_CACHE_SIZE: usize = 1;
 
 /// Values above this size (in bytes) are never cached.
 /// Note that Trie inner nodes are always smaller than this.
@@ -174,6 +198,15 @@ pub struct TrieCachingStorage {
 
     /// Counts retrieved trie nodes. Used to compute gas cost for touching trie nodes.
     pub(crate) counter: Cell<u64>,
+
+    /// Hashes of all distinct items retrieved from this storage and their total size. Used to
+    /// estimate the size of the state witness for applying a chunk.
+    pub(crate) witness_nodes: RefCell<HashSet<CryptoHash>>,
+    pub(crate) witness_size: Cell<u64>,
+
+    /// The whole state of the shard, if it's kept in memory.  Consulted before the shard cache
+    /// and the store, which are used only for what it misses.
+    pub(crate) mem_trie: Option<MemTrie>,
 }
 
 impl TrieCachingStorage {
@@ -185,6 +218,9 @@ impl TrieCachingStorage {
             cache_mode: Cell::new(TrieCacheMode::CachingShard),
             chunk_cache: RefCell::new(Default::default()),
             counter: Cell::new(0u64),
+            witness_nodes: RefCell::new(Default::default()),
+            witness_size: Cell::new(0u64),
+            mem_trie: None,
         }
     }
 
@@ -213,45 +249,64 @@ impl TrieCachingStorage {
         self.counter.set(self.counter.get() + 1);
     }
 
+    fn record_witness_node(&self, hash: &CryptoHash, value: &[u8]) {
+        if self.witness_nodes.borrow_mut().insert(*hash) {
+            self.witness_size.set(self.witness_size.get() + value.len() as u64);
+        }
+    }
+
     /// Set cache mode.
     pub fn set_mode(&self, state: TrieCacheMode) {
         self.cache_mode.set(state);
     }
+
+    fn retrieve_from_shard_cache_or_store(
+        &self,
+        hash: &CryptoHash,
+    ) -> Result<Arc<[u8]>, StorageError> {
+        // Try to get value from shard cache containing most recently touched nodes.
+        let mut guard = self.shard_cache.0.lock().expect(POISONED_LOCK_ERR);
+        if let Some(val) = guard.get(hash) {
+            return Ok(val.clone());
+        }
+
+        // If value is not present in cache, get it from the storage.
+        let key = Self::get_key_from_shard_uid_and_hash(self.shard_uid, hash);
+        let val = self
+            .store
+            .get(ColState, key.as_ref())
+            .map_err(|_| StorageError::StorageInternalError)?
+            .ok_or_else(|| {
+                StorageError::StorageInconsistentState("Trie node missing".to_string())
+            })?;
+        let val: Arc<[u8]> = val.into();
+
+        // Insert value to shard cache, if its size is small enough.
+        // It is fine to have a size limit for shard cache and **not** have a limit for chunk cache, because key
+        // is always a value hash, so for each key there could be only one value, and it is impossible to have
+        // **different** values for the given key in shard and chunk caches.
+        if val.len() < TRIE_LIMIT_CACHED_VALUE_SIZE {
+            guard.put(*hash, val.clone());
+        }
+        Ok(val)
+    }
 }
 
 impl TrieStorage for TrieCachingStorage {
     fn retrieve_raw_bytes(&self, hash: &CryptoHash) -> Result<Arc<[u8]>, StorageError> {
         // Try to get value from chunk cache containing free of charge nodes.
+        // Nodes from the chunk cache have been retrieved before, so they are already accounted
+        // in the state witness.
         if let Some(val) = self.chunk_cache.borrow_mut().get(hash) {
             return Ok(val.clone());
         }
 
-        // Try to get value from shard cache containing most recently touched nodes.
-        let mut guard = self.shard_cache.0.lock().expect(POISONED_LOCK_ERR);
-        let val = match guard.get(hash) {
-            Some(val) => val.clone(),
-            None => {
-                // If value is not present in cache, get it from the storage.
-                let key = Self::get_key_from_shard_uid_and_hash(self.shard_uid, hash);
-                let val = self
-                    .store
-                    .get(ColState, key.as_ref())
-                    .map_err(|_| StorageError::StorageInternalError)?
-                    .ok_or_else(|| {
-                        StorageError::StorageInconsistentState("Trie node missing".to_string())
-                    })?;
-                let val: Arc<[u8]> = val.into();
-
-                // Insert value to shard cache, if its size is small enough.
-                // It is fine to have a size limit for shard cache and **not** have a limit for chunk cache, because key
-                // is always a value hash, so for each key there could be only one value, and it is impossible to have
-                // **different** values for the given key in shard and chunk caches.
-                if val.len() < TRIE_LIMIT_CACHED_VALUE_SIZE {
-                    guard.put(*hash, val.clone());
-                }
-
-                val
-            }
+        // Try to get value from the memtrie, then from the shard cache or the storage.  Nodes
+        // from the memtrie are charged like any others, so keeping the state in memory doesn't
+        // change the cost of applying a chunk.
+        let val = match self.mem_trie.as_ref().and_then(|mem_trie| mem_trie.get(hash)) {
+            Some(val) => val,
+            None => self.retrieve_from_shard_cache_or_store(hash)?,
         };
 
         // Because node is not present in chunk cache, increment the nodes counter and optionally insert it into the
@@ -265,6 +320,7 @@ impl TrieStorage for TrieCachingStorage {
         // (`storage_read_value_byte`) ~= (500 * 10**12 / 5611005) / 2**20 ~= 85 MB.
         // All values are given as of 16/03/2022. We may consider more precise limit for the chunk cache as well.
         self.inc_counter();
+        self.record_witness_node(hash, &val);
         if let TrieCacheMode::CachingChunk = self.cache_mode.borrow().get() {
             self.chunk_cache.borrow_mut().insert(*hash, val.clone());
         };
@@ -279,4 +335,8 @@ impl TrieStorage for TrieCachingStorage {
     fn get_touched_nodes_count(&self) -> u64 {
         self.counter.get()
     }
+
+    fn get_state_witness_size(&self) -> u64 {
+        self.witness_size.get()
+    }
 }
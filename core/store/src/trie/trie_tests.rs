@@ -57,6 +57,10 @@ impl TrieStorage for IncompletePartialStorage {
     fn get_touched_nodes_count(&self) -> u64 {
         unimplemented!();
     }
+
+    fn get_state_witness_size(&self) -> u64 {
+        unimplemented!();
+    }
 }
 
 fn setup_storage<F, Out>(trie: Rc<Trie>, test: &mut F) -> (PartialStorage, Out)
@@ -255,6 +259,23 @@ mod caching_storage_tests {
         }
     }
 
+    /// Check that every distinct item is accounted in the state witness exactly once.
+    #[test]
+    fn test_state_witness_size() {
+        let values = vec![vec![1u8], vec![2u8, 3u8]];
+        let shard_uid = ShardUId::single_shard();
+        let store = create_store_with_values(&values, shard_uid);
+        let trie_caching_storage = TrieCachingStorage::new(store, TrieCache::new(), shard_uid);
+        assert_eq!(trie_caching_storage.get_state_witness_size(), 0);
+
+        for _ in 0..2 {
+            for value in values.iter() {
+                trie_caching_storage.retrieve_raw_bytes(&hash(value)).unwrap();
+            }
+            assert_eq!(trie_caching_storage.get_state_witness_size(), 3);
+        }
+    }
+
     /// Check that if item is not present in a store, retrieval returns an error.
     #[test]
     fn test_retrieve_error() {
@@ -6,7 +6,7 @@ use near_primitives::types::{
     RawStateChange, RawStateChanges, RawStateChangesWithTrieKey, StateChangeCause, TrieCacheMode,
 };
 
-use crate::trie::TrieChanges;
+use crate::trie::{LookupValue, TrieChanges};
 use crate::StorageError;
 
 use super::{Trie, TrieIterator};
@@ -28,11 +28,14 @@ pub struct TrieUpdate {
     root: CryptoHash,
     committed: RawStateChanges,
     prospective: TrieUpdates,
+    inline_small_values: bool,
 }
 
 pub enum TrieUpdateValuePtr<'a> {
     HashAndSize(&'a Trie, u32, CryptoHash),
     MemoryRef(&'a Vec<u8>),
+    /// Value inlined into its trie node.
+    Inline(Vec<u8>),
 }
 
 impl<'a> TrieUpdateValuePtr<'a> {
@@ -40,12 +43,14 @@ impl<'a> TrieUpdateValuePtr<'a> {
         match self {
             TrieUpdateValuePtr::MemoryRef(value) => value.len() as u32,
             TrieUpdateValuePtr::HashAndSize(_, length, _) => *length,
+            TrieUpdateValuePtr::Inline(value) => value.len() as u32,
         }
     }
 
     pub fn deref_value(&self) -> Result<Vec<u8>, StorageError> {
         match self {
             TrieUpdateValuePtr::MemoryRef(value) => Ok((*value).clone()),
+            TrieUpdateValuePtr::Inline(value) => Ok(value.clone()),
             TrieUpdateValuePtr::HashAndSize(trie, _, hash) => {
                 trie.storage.retrieve_raw_bytes(hash).map(|bytes| bytes.to_vec())
             }
@@ -55,7 +60,13 @@ impl<'a> TrieUpdateValuePtr<'a> {
 
 impl TrieUpdate {
     pub fn new(trie: Rc<Trie>, root: CryptoHash) -> Self {
-        TrieUpdate { trie, root, committed: Default::default(), prospective: Default::default() }
+        TrieUpdate {
+            trie,
+            root,
+            committed: Default::default(),
+            prospective: Default::default(),
+            inline_small_values: false,
+        }
     }
 
     pub fn trie(&self) -> &Trie {
@@ -84,8 +95,13 @@ impl TrieUpdate {
                 return Ok(data.as_ref().map(TrieUpdateValuePtr::MemoryRef));
             }
         }
-        self.trie.get_ref(&self.root, &key).map(|option| {
-            option.map(|(length, hash)| TrieUpdateValuePtr::HashAndSize(&self.trie, length, hash))
+        self.trie.get_value_ref(&self.root, &key).map(|option| {
+            option.map(|value| match value {
+                LookupValue::Ref(length, hash) => {
+                    TrieUpdateValuePtr::HashAndSize(&self.trie, length, hash)
+                }
+                LookupValue::Inline(value) => TrieUpdateValuePtr::Inline(value),
+            })
         })
     }
 
@@ -118,9 +134,9 @@ impl TrieUpdate {
 
     pub fn finalize(self) -> Result<(TrieChanges, Vec<RawStateChangesWithTrieKey>), StorageError> {
         assert!(self.prospective.is_empty(), "Finalize cannot be called with uncommitted changes.");
-        let TrieUpdate { trie, root, committed, .. } = self;
+        let TrieUpdate { trie, root, committed, inline_small_values, .. } = self;
         let mut state_changes = Vec::with_capacity(committed.len());
-        let trie_changes = trie.update(
+        let trie_changes = trie.update_with_options(
             &root,
             committed.into_iter().map(|(k, changes_with_trie_key)| {
                 let data = changes_with_trie_key
@@ -132,6 +148,7 @@ impl TrieUpdate {
                 state_changes.push(changes_with_trie_key);
                 (k, data)
             }),
+            inline_small_values,
         )?;
         Ok((trie_changes, state_changes))
     }
@@ -172,10 +189,14 @@ impl TrieUpdate {
         self.root
     }
 
+    /// Makes `finalize` inline the new small values into the trie nodes referring to them.  The
+    /// encoding of the nodes determines the state root, so this is part of the protocol.
+    pub fn set_inline_small_values(&mut self, inline_small_values: bool) {
+        self.inline_small_values = inline_small_values;
+    }
+
     pub fn set_trie_cache_mode(&self, state: TrieCacheMode) {
-        if let Some(storage) = self.trie.storage.as_caching_storage() {
-            storage.set_mode(state);
-        }
+        self.trie.set_cache_mode(state);
     }
 }
 
@@ -432,6 +453,52 @@ mod tests {
         assert_eq!(new_root, CryptoHash::default());
     }
 
+    /// Keys written in many commits end up in the same trie changes as keys written in one: the
+    /// paths shared by the keys are rebuilt once per finalize rather than once per key.
+    #[test]
+    fn trie_finalize_coalesces_commits() {
+        let tries = create_tries();
+        let shard_uid = ShardUId::single_shard();
+        let cause = StateChangeCause::TransactionProcessing { tx_hash: CryptoHash::default() };
+        let keys: Vec<_> = (0..100u32).map(|i| test_key(i.to_le_bytes().to_vec())).collect();
+
+        let mut per_commit = tries.new_trie_update(shard_uid, CryptoHash::default());
+        for key in keys.iter().rev() {
+            per_commit.set(key.clone(), b"puppy".to_vec());
+            per_commit.commit(cause.clone());
+            per_commit.set(key.clone(), b"dog".to_vec());
+            per_commit.commit(cause.clone());
+        }
+        let mut at_once = tries.new_trie_update(shard_uid, CryptoHash::default());
+        for key in &keys {
+            at_once.set(key.clone(), b"dog".to_vec());
+        }
+        at_once.commit(cause.clone());
+        let trie_changes = at_once.finalize().unwrap().0;
+        assert_eq!(per_commit.finalize().unwrap().0, trie_changes);
+
+        // Finalizing after every key creates the intermediate nodes over and over again.
+        let mut root = CryptoHash::default();
+        let mut per_key_insertions = 0;
+        for key in &keys {
+            let mut trie_update = tries.new_trie_update(shard_uid, root);
+            trie_update.set(key.clone(), b"dog".to_vec());
+            trie_update.commit(cause.clone());
+            let trie_changes = trie_update.finalize().unwrap().0;
+            per_key_insertions += trie_changes.insertions.len();
+            let (store_update, new_root) = tries.apply_all(&trie_changes, shard_uid).unwrap();
+            store_update.commit().unwrap();
+            root = new_root;
+        }
+        assert_eq!(root, trie_changes.new_root);
+        assert!(
+            2 * trie_changes.insertions.len() < per_key_insertions,
+            "{} vs {}",
+            trie_changes.insertions.len(),
+            per_key_insertions
+        );
+    }
+
     #[test]
     fn trie_iter() {
         let tries = create_tries();
@@ -66,6 +66,11 @@ impl MockPeerManagerActor {
                 archival: false,
             },
             partial_edge_info: PartialEdgeInfo::default(),
+            servable_state: vec![],
+            protocol_version: near_primitives::version::PROTOCOL_VERSION,
+            client_version: None,
+            capabilities: None,
+            connection_established_time: near_primitives::time::Clock::instant(),
         };
         let network_info = NetworkInfo {
             connected_peers: vec![peer.clone()],
@@ -76,6 +81,7 @@ impl MockPeerManagerActor {
             received_bytes_per_sec: 0,
             known_producers: vec![],
             peer_counter: 0,
+            port_mapping: None,
         };
         Self {
             client_addr,
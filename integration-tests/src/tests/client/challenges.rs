@@ -69,7 +69,8 @@ fn test_verify_block_double_sign_challenge() {
         &signer,
         *b1.header().next_bp_hash(),
         block_merkle_tree.root(),
-    );
+    )
+    .unwrap();
     let epoch_id = b1.header().epoch_id().clone();
     let valid_challenge = Challenge::produce(
         ChallengeBody::BlockDoubleSign(BlockDoubleSign {
@@ -369,7 +370,8 @@ fn test_verify_chunk_invalid_state_challenge() {
         &validator_signer,
         *last_block.header().next_bp_hash(),
         block_merkle_tree.root(),
-    );
+    )
+    .unwrap();
 
     let challenge_body = {
         use near_chain::chain::{ChainUpdate, OrphanBlockPool};
@@ -518,9 +520,8 @@ fn test_receive_two_chunks_from_one_producer() {}
 fn test_receive_two_blocks_from_one_producer() {}
 
 /// Receive challenges in the blocks.
-/// TODO(2445): Enable challenges when they are working correctly.
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "protocol_feature_challenges"), ignore)]
 fn test_block_challenge() {
     init_test_logger();
     let mut env = TestEnv::builder(ChainGenesis::test()).build();
@@ -39,6 +39,7 @@ use near_primitives::errors::TxExecutionError;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::verify_hash;
 use near_primitives::receipt::DelayedReceiptIndices;
+use near_primitives::receipt_trace;
 use near_primitives::runtime::config::RuntimeConfig;
 use near_primitives::runtime::config_store::RuntimeConfigStore;
 use near_primitives::shard_layout::ShardUId;
@@ -59,11 +60,12 @@ use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner
 use near_primitives::version::ProtocolFeature;
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{
-    BlockHeaderView, FinalExecutionStatus, QueryRequest, QueryResponseKind,
+    BlockHeaderView, FinalExecutionStatus, GasBreakdownView, QueryRequest, QueryResponseKind,
+    ReceiptTraceEvent,
 };
 use near_store::db::DBCol::ColStateParts;
-use near_store::get;
 use near_store::test_utils::create_test_store;
+use near_store::{get, get_contract_accounts};
 use nearcore::config::{GenesisExt, TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
 use nearcore::{TrackedConfig, NEAR_BASE};
 use rand::prelude::StdRng;
@@ -377,7 +379,8 @@ fn receive_network_block() {
                 &signer,
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
-            );
+            )
+            .unwrap();
             client.do_send(NetworkClientMessages::Block(block, PeerInfo::random().id, false));
             future::ready(())
         }));
@@ -453,7 +456,8 @@ fn produce_block_with_approvals() {
                 &signer1,
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
-            );
+            )
+            .unwrap();
             client.do_send(NetworkClientMessages::Block(
                 block.clone(),
                 PeerInfo::random().id,
@@ -474,7 +478,8 @@ fn produce_block_with_approvals() {
                     block.header().height(),
                     10, // the height at which "test1" is producing
                     &signer,
-                );
+                )
+                .unwrap();
                 client
                     .do_send(NetworkClientMessages::BlockApproval(approval, PeerInfo::random().id));
             }
@@ -642,7 +647,8 @@ fn invalid_blocks_common(is_requested: bool) {
                 &signer,
                 last_block.header.next_bp_hash,
                 block_merkle_tree.root(),
-            );
+            )
+            .unwrap();
             // Send block with invalid chunk mask
             let mut block = valid_block.clone();
             block.mut_header().get_mut().inner_rest.chunk_mask = vec![];
@@ -958,6 +964,11 @@ fn client_sync_headers() {
                     archival: false,
                 },
                 partial_edge_info: near_network_primitives::types::PartialEdgeInfo::default(),
+                servable_state: vec![],
+                protocol_version: PROTOCOL_VERSION,
+                client_version: None,
+                capabilities: None,
+                connection_established_time: near_primitives::time::Clock::instant(),
             }],
             num_connected_peers: 1,
             peer_max_count: 1,
@@ -970,11 +981,17 @@ fn client_sync_headers() {
                     archival: false,
                 },
                 partial_edge_info: near_network_primitives::types::PartialEdgeInfo::default(),
+                servable_state: vec![],
+                protocol_version: PROTOCOL_VERSION,
+                client_version: None,
+                capabilities: None,
+                connection_established_time: near_primitives::time::Clock::instant(),
             }],
             sent_bytes_per_sec: 0,
             received_bytes_per_sec: 0,
             known_producers: vec![],
             peer_counter: 0,
+            port_mapping: None,
         }));
         wait_or_panic(2000);
     });
@@ -1112,7 +1129,8 @@ fn test_invalid_approvals() {
                     KeyType::ED25519,
                     account_id.as_ref(),
                 )
-                .sign_approval(&ApprovalInner::Endorsement(*genesis.hash()), 1),
+                .sign_approval(&ApprovalInner::Endorsement(*genesis.hash()), 1)
+                .unwrap(),
             )
         })
         .collect();
@@ -1661,6 +1679,32 @@ fn test_tx_forwarding_no_double_forwarding() {
     assert!(env.network_adapters[0].requests.read().unwrap().is_empty());
 }
 
+#[test]
+fn test_produced_chunk_max_transactions() {
+    let mut env = TestEnv::builder(ChainGenesis::test()).build();
+    env.clients[0].config.produced_chunk_max_transactions = Some(2);
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+    for nonce in 1..=5 {
+        let tx = SignedTransaction::send_money(
+            nonce,
+            "test0".parse().unwrap(),
+            "test1".parse().unwrap(),
+            &signer,
+            1,
+            genesis_hash,
+        );
+        env.clients[0].shards_mgr.insert_transaction(0, tx);
+    }
+    let (encoded_chunk, _, _) = create_chunk_on_height(&mut env.clients[0], 1);
+    let data_parts = env.clients[0].runtime_adapter.num_data_parts();
+    let chunk = encoded_chunk.decode_chunk(data_parts).unwrap();
+    let nonces: Vec<_> = chunk.transactions().iter().map(|tx| tx.transaction.nonce).collect();
+    assert_eq!(nonces, vec![1, 2]);
+    // The transactions left out stay in the pool for the next chunks.
+    assert_eq!(env.clients[0].shards_mgr.pool_len(0), 5);
+}
+
 #[test]
 fn test_tx_forward_around_epoch_boundary() {
     let epoch_length = 4;
@@ -2794,7 +2838,16 @@ fn test_execution_metadata() {
             .map(|it| it.gas_used)
             .sum::<u64>();
 
-    assert_eq!(expected_receipt_cost, actual_receipt_cost)
+    assert_eq!(expected_receipt_cost, actual_receipt_cost);
+
+    let expected_breakdown = GasBreakdownView {
+        wasm_execution: config.wasm_config.ext_costs.contract_compile_base
+            + 18423750
+            + config.wasm_config.regular_op_cost as u64 * 2,
+        host_functions: config.wasm_config.ext_costs.base,
+        ..Default::default()
+    };
+    assert_eq!(metadata.gas_breakdown, Some(expected_breakdown));
 }
 
 #[test]
@@ -3061,6 +3114,36 @@ fn test_fork_execution_outcome() {
     assert!(receipt_execution_outcomes.is_empty());
 }
 
+/// Replaying a receipt reproduces its outcome without touching the chain.
+#[test]
+fn test_replay_receipt() {
+    let (mut env, tx_hashes) = prepare_env_with_congestion(PROTOCOL_VERSION, None, 1);
+    for height in 3..6 {
+        env.produce_block(0, height);
+    }
+    let chain = &mut env.clients[0].chain;
+    let tx_outcome = chain.get_execution_outcome(&tx_hashes[0]).unwrap();
+    let receipt_id = tx_outcome.outcome_with_id.outcome.receipt_ids[0];
+    let outcome = chain.get_execution_outcome(&receipt_id).unwrap();
+    let head = chain.head().unwrap();
+
+    let (result, events) =
+        receipt_trace::trace_receipt(receipt_id, || chain.replay_receipt(&receipt_id));
+    let (block_hash, _, replayed_outcome) = result.unwrap();
+    assert_eq!(block_hash, outcome.block_hash);
+    assert_eq!(replayed_outcome, outcome.outcome_with_id);
+    assert_eq!(chain.head().unwrap(), head);
+    assert_eq!(chain.get_execution_outcome(&receipt_id).unwrap(), outcome);
+    if cfg!(feature = "receipt_trace") {
+        assert!(events
+            .unwrap()
+            .iter()
+            .any(|event| matches!(event, ReceiptTraceEvent::HostFunctionCall { .. })));
+    } else {
+        assert_eq!(events, None);
+    }
+}
+
 fn prepare_env_with_transaction() -> (TestEnv, CryptoHash) {
     let epoch_length = 5;
     let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
@@ -3133,11 +3216,13 @@ fn test_header_version_downgrade() {
         match header {
             BlockHeader::BlockHeaderV1(ref mut header) => {
                 header.inner_rest.latest_protocol_version = PROTOCOL_VERSION;
-                let (hash, signature) = validator_signer.sign_block_header_parts(
-                    header.prev_hash,
-                    &header.inner_lite.try_to_vec().expect("Failed to serialize"),
-                    &header.inner_rest.try_to_vec().expect("Failed to serialize"),
-                );
+                let (hash, signature) = validator_signer
+                    .sign_block_header_parts(
+                        header.prev_hash,
+                        &header.inner_lite.try_to_vec().expect("Failed to serialize"),
+                        &header.inner_rest.try_to_vec().expect("Failed to serialize"),
+                    )
+                    .unwrap();
                 header.hash = hash;
                 header.signature = signature;
             }
@@ -3292,6 +3377,25 @@ fn test_congestion_receipt_execution() {
     }
 }
 
+#[test]
+fn test_contract_accounts_index() {
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let chain_genesis = ChainGenesis::from(&genesis);
+    let mut env = TestEnv::builder(chain_genesis)
+        .runtime_adapters(create_nightshade_runtimes(&genesis, 1))
+        .build();
+    let code = near_test_contracts::rs_contract();
+    let height = deploy_test_contract(&mut env, "test0".parse().unwrap(), code, epoch_length, 1);
+    deploy_test_contract(&mut env, "test1".parse().unwrap(), code, epoch_length, height);
+    let store = env.clients[0].chain.store().store().clone();
+    assert_eq!(
+        get_contract_accounts(&store, &hash(code)).unwrap(),
+        vec!["test0".parse().unwrap(), "test1".parse().unwrap()]
+    );
+}
+
 #[test]
 fn test_validator_stake_host_function() {
     init_test_logger();
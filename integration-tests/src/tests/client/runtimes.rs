@@ -48,7 +48,7 @@ fn test_pending_approvals() {
     let signer =
         InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
     let parent_hash = hash(&[1]);
-    let approval = Approval::new(parent_hash, 0, 1, &signer);
+    let approval = Approval::new(parent_hash, 0, 1, &signer).unwrap();
     let peer_id = PeerId::random();
     env.clients[0].collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id.clone()));
     let approvals = env.clients[0].pending_approvals.pop(&ApprovalInner::Endorsement(parent_hash));
@@ -70,7 +70,7 @@ fn test_invalid_approvals() {
         InMemoryValidatorSigner::from_seed("random".parse().unwrap(), KeyType::ED25519, "random");
     let parent_hash = hash(&[1]);
     // Approval not from a validator. Should be dropped
-    let approval = Approval::new(parent_hash, 1, 3, &signer);
+    let approval = Approval::new(parent_hash, 1, 3, &signer).unwrap();
     let peer_id = PeerId::random();
     env.clients[0].collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id.clone()));
     assert_eq!(env.clients[0].pending_approvals.len(), 0);
@@ -78,11 +78,28 @@ fn test_invalid_approvals() {
     let signer =
         InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "random");
     let genesis_hash = *env.clients[0].chain.genesis().hash();
-    let approval = Approval::new(genesis_hash, 0, 1, &signer);
+    let approval = Approval::new(genesis_hash, 0, 1, &signer).unwrap();
     env.clients[0].collect_block_approval(&approval, ApprovalType::PeerApproval(peer_id));
     assert_eq!(env.clients[0].pending_approvals.len(), 0);
 }
 
+#[test]
+fn test_batched_approvals_cap() {
+    let mut env =
+        TestEnv::builder(ChainGenesis::test()).runtime_adapters(create_runtimes(1)).build();
+    let signer =
+        InMemoryValidatorSigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
+    let parent_hash = hash(&[1]);
+    let approval = Approval::new(parent_hash, 0, 1, &signer).unwrap();
+    let peer_id = PeerId::random();
+    // There is a single block producer, so a batch of two approvals is rejected as a whole.
+    assert!(!env.clients[0]
+        .collect_block_approvals(vec![approval.clone(), approval.clone()], peer_id.clone()));
+    assert_eq!(env.clients[0].pending_approvals.len(), 0);
+    assert!(env.clients[0].collect_block_approvals(vec![approval], peer_id));
+    assert_eq!(env.clients[0].pending_approvals.len(), 1);
+}
+
 #[test]
 fn test_cap_max_gas_price() {
     use near_chain::Provenance;
@@ -8,7 +8,7 @@ use near_client::{ClientActor, ViewClientActor};
 use near_logger_utils::init_integration_logger;
 use near_network::test_utils::{convert_boot_nodes, open_port};
 use near_primitives::types::{BlockHeight, BlockHeightDelta, NumSeats, NumShards};
-use nearcore::{config::GenesisExt, load_test_config, start_with_config};
+use nearcore::{config::GenesisExt, load_test_config, start_localnet_with_configs};
 use tempfile::TempDir;
 
 pub fn start_nodes(
@@ -56,12 +56,16 @@ pub fn start_nodes(
         near_configs.push(near_config);
     }
 
-    let mut res = vec![];
-    for (i, near_config) in near_configs.into_iter().enumerate() {
-        let nearcore::NearNode { client, view_client, arbiters, .. } =
-            start_with_config(dirs[i].path(), near_config).expect("start_with_config");
-        res.push((client, view_client, arbiters))
-    }
+    let nodes = start_localnet_with_configs(
+        dirs.iter().map(|dir| dir.path().to_path_buf()).zip(near_configs).collect(),
+    )
+    .expect("start_localnet_with_configs");
+    let res = nodes
+        .into_iter()
+        .map(|nearcore::NearNode { client, view_client, arbiters, .. }| {
+            (client, view_client, arbiters)
+        })
+        .collect();
     (genesis, rpc_addrs, res)
 }
 
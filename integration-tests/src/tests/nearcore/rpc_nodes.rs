@@ -11,18 +11,17 @@ use near_actix_test_utils::spawn_interruptible;
 use near_client::{GetBlock, GetExecutionOutcome, GetValidatorInfo};
 use near_crypto::{InMemorySigner, KeyType};
 use near_jsonrpc::client::new_client;
+use near_light_client::execution_outcome_hashes;
 use near_logger_utils::init_integration_logger;
 use near_network::test_utils::WaitOrTimeoutActor;
-use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{compute_root_from_path_and_item, verify_path};
 use near_primitives::runtime::config_store::RuntimeConfigStore;
-use near_primitives::serialize::{from_base64, to_base64};
-use near_primitives::transaction::{PartialExecutionStatus, SignedTransaction};
+use near_primitives::serialize::to_base64;
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
     BlockId, BlockReference, EpochId, EpochReference, Finality, TransactionOrReceiptId,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::{ExecutionOutcomeView, ExecutionStatusView};
 
 use crate::tests::nearcore::node_cluster::NodeCluster;
 
@@ -68,32 +67,6 @@ fn test_get_validator_info_rpc() {
     });
 }
 
-fn outcome_view_to_hashes(outcome: &ExecutionOutcomeView) -> Vec<CryptoHash> {
-    let status = match &outcome.status {
-        ExecutionStatusView::Unknown => PartialExecutionStatus::Unknown,
-        ExecutionStatusView::SuccessValue(s) => {
-            PartialExecutionStatus::SuccessValue(from_base64(s).unwrap())
-        }
-        ExecutionStatusView::Failure(_) => PartialExecutionStatus::Failure,
-        ExecutionStatusView::SuccessReceiptId(id) => PartialExecutionStatus::SuccessReceiptId(*id),
-    };
-    let mut result = vec![hash(
-        &(
-            outcome.receipt_ids.clone(),
-            outcome.gas_burnt,
-            outcome.tokens_burnt,
-            outcome.executor_id.clone(),
-            status,
-        )
-            .try_to_vec()
-            .expect("Failed to serialize"),
-    )];
-    for log in outcome.logs.iter() {
-        result.push(hash(log.as_bytes()));
-    }
-    result
-}
-
 fn test_get_execution_outcome(is_tx_successful: bool) {
     init_integration_logger();
 
@@ -171,11 +144,12 @@ fn test_get_execution_outcome(is_tx_successful: bool) {
                                                     execution_outcome_response.outcome_proof.id,
                                                 ];
                                                 outcome_with_id_to_hash.extend(
-                                                    outcome_view_to_hashes(
+                                                    execution_outcome_hashes(
                                                         &execution_outcome_response
                                                             .outcome_proof
                                                             .outcome,
-                                                    ),
+                                                    )
+                                                    .unwrap(),
                                                 );
                                                 let chunk_outcome_root =
                                                     compute_root_from_path_and_item(
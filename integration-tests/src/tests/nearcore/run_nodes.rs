@@ -1,12 +1,16 @@
 use actix::{Actor, System};
 use futures::{future, FutureExt};
 
-use near_actix_test_utils::spawn_interruptible;
+use near_actix_test_utils::{run_actix, spawn_interruptible};
 use near_client::GetBlock;
 use near_network::test_utils::WaitOrTimeoutActor;
+use near_primitives::shard_layout::ShardLayout;
 use near_primitives::types::{BlockHeightDelta, NumSeats, NumShards};
+use nearcore::config::init_testnet_configs;
+use nearcore::start_localnet;
 use rand::{thread_rng, Rng};
 
+use crate::test_helpers::heavy_test;
 use crate::tests::nearcore::node_cluster::NodeCluster;
 
 fn run_heavy_nodes(
@@ -86,3 +90,35 @@ fn run_nodes_1_4_4() {
 fn run_nodes_4_4_2() {
     run_heavy_nodes(4, 4, 2, 8, 32);
 }
+
+/// Runs the nodes of a localnet initialized with local ports in one process, as
+/// `neard localnet --in-process` does.
+#[test]
+#[cfg_attr(not(feature = "expensive_tests"), ignore)]
+fn run_localnet_in_process() {
+    heavy_test(|| {
+        let dir = tempfile::Builder::new().prefix("localnet_in_process").tempdir().unwrap();
+        init_testnet_configs(dir.path(), ShardLayout::v0(2, 0), 3, 1, "node", true, false, None);
+        let home_dirs = (0..4).map(|i| dir.path().join(format!("node{}", i))).collect::<Vec<_>>();
+        run_actix(async move {
+            let nodes = start_localnet(&home_dirs).unwrap();
+            let view_client = nodes.last().unwrap().view_client.clone();
+            WaitOrTimeoutActor::new(
+                Box::new(move |_ctx| {
+                    spawn_interruptible(view_client.send(GetBlock::latest()).then(move |res| {
+                        if let Ok(Ok(block)) = res {
+                            if block.header.height > 20 {
+                                System::current().stop();
+                            }
+                        }
+                        future::ready(())
+                    }));
+                }),
+                100,
+                60000,
+            )
+            .start();
+        });
+    });
+    std::thread::sleep(std::time::Duration::from_millis(250));
+}
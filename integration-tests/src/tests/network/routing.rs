@@ -229,6 +229,53 @@ fn blacklist_all() {
     start_test(runner);
 }
 
+/// Node 0 is a private validator behind the proxy node 1, which accepts it even at max capacity.
+/// The other nodes reach the validator and learn its account through the proxy.
+#[test]
+fn private_validator_behind_proxy() {
+    let mut runner = Runner::new(4, 4).max_num_peers(2).add_proxy_node(0, 1);
+
+    runner.push(Action::AddEdge(2, 1));
+    runner.push(Action::AddEdge(3, 1));
+    runner.push(Action::CheckRoutingTable(1, vec![(2, vec![2]), (3, vec![3])]));
+    runner.push(Action::AddEdge(0, 1));
+    runner.push(Action::CheckRoutingTable(1, vec![(0, vec![0]), (2, vec![2]), (3, vec![3])]));
+    runner.push(Action::CheckRoutingTable(2, vec![(1, vec![1]), (3, vec![1]), (0, vec![1])]));
+    runner.push(Action::CheckAccountId(2, vec![0]));
+
+    start_test(runner);
+}
+
+/// A private validator connects to and accepts connections from its proxy nodes only.
+#[test]
+fn private_validator_rejects_other_peers() {
+    let mut runner = Runner::new(3, 3).add_proxy_node(0, 1);
+
+    runner.push(Action::AddEdge(2, 0));
+    runner.push(Action::AddEdge(0, 2));
+    runner.push(Action::Wait(100));
+    runner.push(Action::CheckRoutingTable(0, vec![]));
+    runner.push(Action::CheckRoutingTable(2, vec![]));
+    runner.push(Action::AddEdge(0, 1));
+    runner.push(Action::CheckRoutingTable(0, vec![(1, vec![1])]));
+
+    start_test(runner);
+}
+
+/// A proxy shares its own address instead of the one of its private validator.
+#[cfg(feature = "test_features")]
+#[test]
+fn proxy_advertises_itself() {
+    let mut runner = Runner::new(3, 3).add_proxy_node(0, 1);
+
+    runner.push(Action::AddEdge(0, 1));
+    runner.push(Action::AddEdge(2, 1));
+    runner.push(Action::CheckRoutingTable(2, vec![(1, vec![1]), (0, vec![1])]));
+    runner.push_action(check_peers_response(1, vec![1, 2], vec![0]));
+
+    start_test(runner);
+}
+
 /// Spawn 4 nodes with max peers required equal 2. Connect first three peers in a triangle.
 /// Try to connect peer3 to peer0 and see it fail since first three peer are at max capacity.
 #[test]
@@ -463,6 +463,8 @@ struct TestConfig {
     minimum_outbound_peers: Option<u32>,
     safe_set_size: Option<u32>,
     archive: bool,
+    proxy_nodes: Vec<usize>,
+    private_peers: Vec<usize>,
 }
 
 impl TestConfig {
@@ -478,6 +480,8 @@ impl TestConfig {
             minimum_outbound_peers: None,
             safe_set_size: None,
             archive: false,
+            proxy_nodes: vec![],
+            private_peers: vec![],
         }
     }
 }
@@ -532,6 +536,13 @@ impl Runner {
         self
     }
 
+    /// Make node `v` a proxy node of node `u`, which becomes a private validator.
+    pub fn add_proxy_node(mut self, u: usize, v: usize) -> Self {
+        self.test_config[u].proxy_nodes.push(v);
+        self.test_config[v].private_peers.push(u);
+        self
+    }
+
     /// Specify boot nodes. By default there are no boot nodes.
     pub fn use_boot_nodes(mut self, boot_nodes: Vec<usize>) -> Self {
         self.apply_all(move |test_config| {
@@ -647,6 +658,18 @@ impl Runner {
         network_config.outbound_disabled = test_config.outbound_disabled;
         network_config.boot_nodes = boot_nodes;
         network_config.archive = test_config.archive;
+        network_config.proxy_nodes = convert_boot_nodes(
+            test_config
+                .proxy_nodes
+                .iter()
+                .map(|ix| (accounts_id[*ix].as_ref(), ports[*ix]))
+                .collect(),
+        );
+        network_config.private_peers = test_config
+            .private_peers
+            .iter()
+            .map(|ix| peer_id_from_seed(accounts_id[*ix].as_ref()))
+            .collect();
 
         network_config.ideal_connections_lo =
             test_config.ideal_connections.map_or(network_config.ideal_connections_lo, |(lo, _)| lo);
@@ -890,6 +913,44 @@ pub fn check_direct_connection(node_id: usize, target_id: usize) -> ActionFn {
     )
 }
 
+/// Check that the peers node `node_id` shares when asked for more peers include all of `expected`
+/// and none of `unexpected`.
+#[cfg(feature = "test_features")]
+pub fn check_peers_response(
+    node_id: usize,
+    expected: Vec<usize>,
+    unexpected: Vec<usize>,
+) -> ActionFn {
+    Box::new(
+        move |info: SharedRunningInfo,
+              flag: Arc<AtomicBool>,
+              _ctx: &mut Context<WaitOrTimeoutActor>,
+              _runner| {
+            let info = info.read().unwrap();
+            let expected: Vec<_> =
+                expected.iter().map(|ix| info.peers_info[*ix].id.clone()).collect();
+            let unexpected: Vec<_> =
+                unexpected.iter().map(|ix| info.peers_info[*ix].id.clone()).collect();
+            actix::spawn(
+                info.pm_addr[node_id]
+                    .send(PeerManagerMessageRequest::PeersRequest(
+                        near_network::private_actix::PeersRequest {},
+                    ))
+                    .map(move |res| {
+                        if let Ok(PeerManagerMessageResponse::PeerRequestResult(result)) = res {
+                            let ids: HashSet<_> = result.peers.into_iter().map(|p| p.id).collect();
+                            if expected.iter().all(|id| ids.contains(id))
+                                && !unexpected.iter().any(|id| ids.contains(id))
+                            {
+                                flag.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    }),
+            );
+        },
+    )
+}
+
 /// Restart a node that was already stopped.
 pub fn restart(node_id: usize) -> ActionFn {
     let can_write_log = Arc::new(AtomicBool::new(true));
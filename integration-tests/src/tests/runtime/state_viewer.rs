@@ -3,7 +3,7 @@ use near_primitives::{
     account::Account,
     hash::hash as sha256,
     hash::CryptoHash,
-    views::{StateItem, ViewApplyState},
+    views::{ContractAbiSource, StateItem, ViewApplyState},
 };
 use near_primitives::{
     test_utils::MockEpochInfoProvider,
@@ -14,6 +14,7 @@ use near_primitives::{
 use near_store::set_account;
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
+use std::time::Duration;
 use testlib::runtime_utils::{alice_account, encode_int};
 
 #[test]
@@ -147,6 +148,11 @@ fn test_view_state() {
         result.values,
         [StateItem { key: "dGVzdDEyMw==".to_string(), value: "MTIz".to_string(), proof: vec![] }]
     );
+
+    let mut trie_viewer = TrieViewer::default();
+    trie_viewer.set_view_state_timeout(Some(Duration::ZERO));
+    let result = trie_viewer.view_state(&state_update, &alice_account(), b"");
+    assert!(matches!(result, Err(errors::ViewStateError::Timeout { .. })));
 }
 
 #[test]
@@ -179,6 +185,50 @@ fn test_view_state_with_large_contract() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn test_view_abi() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let mut state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    let abi = br#"{"schema_version":"0.1.0"}"#.to_vec();
+    let mut section = vec![ABI_HASH_SECTION.len() as u8];
+    section.extend_from_slice(ABI_HASH_SECTION.as_bytes());
+    section.extend_from_slice(sha256(&abi).as_ref());
+    let mut code = b"\0asm\x01\0\0\0".to_vec();
+    code.push(0);
+    code.push(section.len() as u8);
+    code.extend_from_slice(&section);
+    let code_hash = sha256(&code);
+    set_account(&mut state_update, alice_account(), &Account::new(0, 0, code_hash, 1000));
+    state_update.set(TrieKey::ContractCode { account_id: alice_account() }, code);
+
+    let abi_dir = tempfile::tempdir().unwrap();
+    let mut trie_viewer = TrieViewer::default();
+    trie_viewer.set_abi_dir(Some(abi_dir.path().to_path_buf()));
+    let result = trie_viewer.view_abi(&state_update, &alice_account());
+    assert!(matches!(result, Err(errors::ViewAbiError::NoContractAbi { .. })));
+
+    std::fs::write(abi_dir.path().join(format!("{}.json", code_hash)), &abi).unwrap();
+    let result = trie_viewer.view_abi(&state_update, &alice_account()).unwrap();
+    assert_eq!(result.abi, abi);
+    assert_eq!(result.source, ContractAbiSource::Directory);
+    assert!(result.verified);
+
+    // The ABI published by the contract doesn't match the hash declared by the code, so the one
+    // from the directory is returned.
+    let abi_key =
+        TrieKey::ContractData { account_id: alice_account(), key: CONTRACT_ABI_KEY.to_vec() };
+    state_update.set(abi_key.clone(), b"{}".to_vec());
+    let result = trie_viewer.view_abi(&state_update, &alice_account()).unwrap();
+    assert_eq!(result.source, ContractAbiSource::Directory);
+    let result = TrieViewer::default().view_abi(&state_update, &alice_account());
+    assert!(matches!(result, Err(errors::ViewAbiError::AbiHashMismatch { .. })));
+
+    state_update.set(abi_key, abi.clone());
+    let result = trie_viewer.view_abi(&state_update, &alice_account()).unwrap();
+    assert_eq!(result.abi_hash, sha256(&abi));
+    assert_eq!(result.source, ContractAbiSource::State);
+}
+
 #[test]
 fn test_log_when_panic() {
     let (viewer, root) = get_test_trie_viewer();
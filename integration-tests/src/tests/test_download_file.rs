@@ -1,19 +1,41 @@
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request, Response, Server};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use nearcore::config::FileDownloadError;
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::Barrier;
 
-#[tokio::test]
-async fn test_file_download() {
-    let port = portpicker::pick_unused_port().expect("No ports free");
+/// SHA-256 of the served data.
+const DATA_SHA256: &str = "b2256110f2c4226de0008dd4382a388e033f211b617bd3237135ab1d59a722b6";
 
-    async fn handle_request(_req: Request<Body>) -> Result<Response<Body>, Infallible> {
-        let data: [u8; 1024] = [42; 1024];
-        Ok(Response::new(Body::from(data.to_vec())))
-    }
+/// ETag of the served data.
+const DATA_ETAG: &str = "\"v1\"";
+
+/// Serves 1024 bytes of data, honouring `Range: bytes=N-` headers.
+async fn handle_request(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let data: [u8; 1024] = [42; 1024];
+    let offset: Option<usize> = req.headers().get(hyper::header::RANGE).and_then(|range| {
+        range.to_str().ok()?.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok()
+    });
+    let response = Response::builder().header(hyper::header::ETAG, DATA_ETAG);
+    Ok(match offset {
+        Some(offset) if offset >= data.len() => response
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(hyper::header::CONTENT_RANGE, format!("bytes */{}", data.len()))
+            .body(Body::empty())
+            .unwrap(),
+        Some(offset) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .body(Body::from(data[offset..].to_vec()))
+            .unwrap(),
+        None => response.body(Body::from(data.to_vec())).unwrap(),
+    })
+}
 
+/// Starts the server and returns its URL.
+async fn start_server() -> String {
+    let port = portpicker::pick_unused_port().expect("No ports free");
     let barrier = Arc::new(Barrier::new(2));
     let server_barrier = barrier.clone();
 
@@ -29,18 +51,94 @@ async fn test_file_download() {
         }
     });
 
+    barrier.wait().await;
+    format!("http://localhost:{}", port)
+}
+
+#[tokio::test]
+async fn test_file_download() {
+    let url = start_server().await;
     let tmp_downloaded_file = tempfile::NamedTempFile::new().unwrap();
     let tmp_downloaded_file_path = tmp_downloaded_file.path();
 
-    barrier.wait().await;
-
-    nearcore::config::download_file(
-        &format!("http://localhost:{}", port),
-        tmp_downloaded_file_path,
-    )
-    .await
-    .unwrap();
+    nearcore::config::download_file(&url, tmp_downloaded_file_path, None).await.unwrap();
 
     let downloaded_file_content = std::fs::read(tmp_downloaded_file_path).unwrap();
     assert_eq!(downloaded_file_content, [42; 1024].to_vec());
 }
+
+#[tokio::test]
+async fn test_file_download_resume() {
+    let url = start_server().await;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("genesis.json");
+    let part_path = tmp_dir.path().join("genesis.json.part");
+    std::fs::write(&part_path, [42; 512]).unwrap();
+
+    nearcore::config::download_file(&url, &path, Some(DATA_SHA256)).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), [42; 1024].to_vec());
+    assert!(!part_path.exists());
+}
+
+#[tokio::test]
+async fn test_file_download_resume_same_source() {
+    let url = start_server().await;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("genesis.json");
+    std::fs::write(tmp_dir.path().join("genesis.json.part"), [7; 512]).unwrap();
+    let source = serde_json::json!({"url": format!("{}/", url), "etag": DATA_ETAG});
+    std::fs::write(tmp_dir.path().join("genesis.json.part.source"), source.to_string()).unwrap();
+
+    // Without a checksum, the data is resumed from as it comes from the same version of the same
+    // resource.
+    nearcore::config::download_file(&url, &path, None).await.unwrap();
+
+    let mut expected = vec![7; 512];
+    expected.extend_from_slice(&[42; 512]);
+    assert_eq!(std::fs::read(&path).unwrap(), expected);
+    assert!(!tmp_dir.path().join("genesis.json.part.source").exists());
+}
+
+#[tokio::test]
+async fn test_file_download_discard_unknown_source() {
+    let url = start_server().await;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("genesis.json");
+    // Without a checksum or the source it came from, the data can't be trusted.
+    std::fs::write(tmp_dir.path().join("genesis.json.part"), [7; 2048]).unwrap();
+
+    nearcore::config::download_file(&url, &path, None).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), [42; 1024].to_vec());
+}
+
+#[tokio::test]
+async fn test_file_download_unsatisfiable_range() {
+    let url = start_server().await;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("genesis.json");
+    // More data than the resource has isn't taken as a complete download.
+    std::fs::write(tmp_dir.path().join("genesis.json.part"), [42; 2048]).unwrap();
+
+    nearcore::config::download_file(&url, &path, Some(DATA_SHA256)).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), [42; 1024].to_vec());
+}
+
+#[tokio::test]
+async fn test_file_download_checksum_mismatch() {
+    let url = start_server().await;
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("genesis.json");
+    // Resuming from corrupted data gives a wrong checksum.
+    std::fs::write(tmp_dir.path().join("genesis.json.part"), [0; 512]).unwrap();
+
+    let result = nearcore::config::download_file(&url, &path, Some(DATA_SHA256)).await;
+
+    assert!(matches!(result, Err(FileDownloadError::ChecksumMismatch(_, _))), "{:?}", result);
+    assert!(!path.exists());
+    // The download starts over the next time.
+    nearcore::config::download_file(&url, &path, Some(DATA_SHA256)).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), [42; 1024].to_vec());
+}
@@ -0,0 +1,193 @@
+//! Parameters of the custom chains created by `neard init`, given as flags or in a YAML file
+//! passed with `--chain-params`:
+//!
+//! ```yaml
+//! epoch_length: 600
+//! min_block_production_delay_ms: 1000
+//! max_block_production_delay_ms: 2500
+//! num_block_producer_seats: 20
+//! num_chunk_producer_seats_per_shard: 10
+//! gas_limit: 1000000000000000
+//! ```
+//!
+//! The parameters which aren't given keep the values `neard init` uses otherwise.  They're
+//! checked against each other and against the rest of the config before anything is written, so
+//! that a chain can't be created in a state where it can't make progress.
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use near_chain_configs::GenesisConfig;
+use near_primitives::runtime::config_store::RuntimeConfigStore;
+use near_primitives::types::{BlockHeightDelta, Gas, NumSeats};
+
+use crate::config::Config;
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChainParams {
+    pub epoch_length: Option<BlockHeightDelta>,
+    pub min_block_production_delay_ms: Option<u64>,
+    pub max_block_production_delay_ms: Option<u64>,
+    pub num_block_producer_seats: Option<NumSeats>,
+    /// Number of the block producers assigned to produce the chunks of each shard.
+    pub num_chunk_producer_seats_per_shard: Option<NumSeats>,
+    /// Gas limit of a chunk.
+    pub gas_limit: Option<Gas>,
+}
+
+impl ChainParams {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Could not open chain params {}", path.display()))?;
+        serde_yaml::from_reader(file)
+            .with_context(|| format!("Failed to parse chain params {}", path.display()))
+    }
+
+    /// Whether any parameter is given.
+    pub fn is_empty(&self) -> bool {
+        self.epoch_length.is_none()
+            && self.min_block_production_delay_ms.is_none()
+            && self.max_block_production_delay_ms.is_none()
+            && self.num_block_producer_seats.is_none()
+            && self.num_chunk_producer_seats_per_shard.is_none()
+            && self.gas_limit.is_none()
+    }
+
+    /// Sets the given parameters in the config and the genesis config of a new chain, then checks
+    /// that they're consistent.
+    pub fn apply(
+        &self,
+        config: &mut Config,
+        genesis_config: &mut GenesisConfig,
+    ) -> anyhow::Result<()> {
+        let consensus = &mut config.consensus;
+        if let Some(epoch_length) = self.epoch_length {
+            genesis_config.epoch_length = epoch_length;
+        }
+        if let Some(delay) = self.min_block_production_delay_ms {
+            consensus.min_block_production_delay = Duration::from_millis(delay);
+        }
+        if let Some(delay) = self.max_block_production_delay_ms {
+            consensus.max_block_production_delay = Duration::from_millis(delay);
+        }
+        let num_shards = genesis_config.shard_layout.num_shards();
+        if let Some(num_seats) = self.num_block_producer_seats {
+            genesis_config.num_block_producer_seats = num_seats;
+            if self.num_chunk_producer_seats_per_shard.is_none() {
+                genesis_config.num_block_producer_seats_per_shard =
+                    near_primitives::utils::get_num_seats_per_shard(num_shards, num_seats);
+            }
+        }
+        if let Some(num_seats) = self.num_chunk_producer_seats_per_shard {
+            genesis_config.num_block_producer_seats_per_shard =
+                vec![num_seats; num_shards as usize];
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            genesis_config.gas_limit = gas_limit;
+        }
+        check_consistency(config, genesis_config)
+    }
+}
+
+fn check_consistency(config: &Config, genesis_config: &GenesisConfig) -> anyhow::Result<()> {
+    let consensus = &config.consensus;
+    let sync_horizon = consensus.block_header_fetch_horizon + consensus.state_fetch_horizon;
+    if genesis_config.epoch_length <= sync_horizon {
+        bail!(
+            "epoch length ({}) must be larger than block_header_fetch_horizon plus state_fetch_horizon ({}), otherwise the nodes which fall behind can't state sync",
+            genesis_config.epoch_length,
+            sync_horizon
+        );
+    }
+    if consensus.min_block_production_delay.is_zero() {
+        bail!("min block production delay must be positive");
+    }
+    if consensus.min_block_production_delay > consensus.max_block_production_delay {
+        bail!(
+            "min block production delay ({:?}) must be at most the max block production delay ({:?})",
+            consensus.min_block_production_delay,
+            consensus.max_block_production_delay
+        );
+    }
+    if consensus.max_block_production_delay >= consensus.max_block_wait_delay {
+        bail!(
+            "max block production delay ({:?}) must be below max_block_wait_delay ({:?}), otherwise the block producers skip heights while waiting for approvals",
+            consensus.max_block_production_delay,
+            consensus.max_block_wait_delay
+        );
+    }
+    let num_seats = genesis_config.num_block_producer_seats;
+    if num_seats == 0 {
+        bail!("number of block producer seats must be positive");
+    }
+    if let Some(seats) = genesis_config
+        .num_block_producer_seats_per_shard
+        .iter()
+        .find(|seats| **seats == 0 || **seats > num_seats)
+    {
+        bail!(
+            "number of chunk producer seats per shard ({}) must be between 1 and the number of block producer seats ({})",
+            seats,
+            num_seats
+        );
+    }
+    let runtime_config_store = RuntimeConfigStore::new(None);
+    let max_prepaid_gas = runtime_config_store
+        .get_config(genesis_config.protocol_version)
+        .wasm_config
+        .limit_config
+        .max_total_prepaid_gas;
+    if genesis_config.gas_limit < max_prepaid_gas {
+        bail!(
+            "gas limit ({}) must be at least the gas a transaction can attach ({}), otherwise such transactions never fit in a chunk",
+            genesis_config.gas_limit,
+            max_prepaid_gas
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::GenesisConfig;
+    use near_primitives::version::PROTOCOL_VERSION;
+
+    use super::ChainParams;
+    use crate::config::{Config, INITIAL_GAS_LIMIT};
+
+    #[test]
+    fn test_chain_params() {
+        let genesis_config = || GenesisConfig {
+            protocol_version: PROTOCOL_VERSION,
+            epoch_length: 100,
+            num_block_producer_seats: 50,
+            num_block_producer_seats_per_shard: vec![50],
+            gas_limit: INITIAL_GAS_LIMIT,
+            ..GenesisConfig::default()
+        };
+        let apply = |params: &str| -> anyhow::Result<(Config, GenesisConfig)> {
+            let params: ChainParams = serde_yaml::from_str(params)?;
+            let (mut config, mut genesis_config) = (Config::default(), genesis_config());
+            params.apply(&mut config, &mut genesis_config)?;
+            Ok((config, genesis_config))
+        };
+
+        let (config, genesis_config) =
+            apply("epoch_length: 600\nmin_block_production_delay_ms: 1000\nnum_block_producer_seats: 10\n")
+                .unwrap();
+        assert_eq!(genesis_config.epoch_length, 600);
+        assert_eq!(config.consensus.min_block_production_delay.as_millis(), 1000);
+        assert_eq!(genesis_config.num_block_producer_seats_per_shard, vec![10]);
+
+        assert!(apply("epoch_length: 20\n").is_err());
+        assert!(apply("min_block_production_delay_ms: 3000\n").is_err());
+        assert!(apply("max_block_production_delay_ms: 7000\n").is_err());
+        assert!(apply("num_block_producer_seats: 10\nnum_chunk_producer_seats_per_shard: 20\n")
+            .is_err());
+        assert!(apply("gas_limit: 1000\n").is_err());
+        assert!(apply("epoch_lenght: 600\n").is_err());
+    }
+}
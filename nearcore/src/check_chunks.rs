@@ -0,0 +1,221 @@
+//! Verification of the chunks stored in the database.
+//!
+//! Checks that the chunks included in the blocks of a range of heights decode, match the hashes
+//! and merkle roots of their headers, and that the outcomes of applying them are stored.
+use std::fmt;
+use std::path::Path;
+
+use borsh::BorshDeserialize;
+use near_chain::types::ApplyTransactionResult;
+use near_chain::validate::validate_chunk_proofs;
+use near_chain::{ChainStore, ChainStoreAccess, RuntimeAdapter};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::{ChunkHash, ShardChunk, ShardChunkHeader};
+use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::utils::get_block_shard_id;
+use near_store::{create_store, create_store_with_config, DBCol, Store, StoreConfig};
+use tracing::{info, warn};
+
+use crate::{get_store_path, store_path_exists, NearConfig, NightshadeRuntime};
+
+/// What is wrong with a stored chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkProblem {
+    /// The chunk of a tracked shard isn't stored.
+    Missing,
+    /// The stored chunk can't be decoded.
+    Undecodable(String),
+    /// The hash or the merkle roots of the transactions and receipts of the stored chunk don't
+    /// match its header.
+    InvalidProofs,
+    /// The outcomes of applying the chunk aren't stored.
+    MissingOutcomes,
+    /// The stored outcomes don't match the outcome root of the chunk extra.
+    OutcomeRootMismatch,
+}
+
+impl ChunkProblem {
+    /// Whether the stored chunk itself is corrupt, as opposed to data derived from it.
+    pub fn is_corrupt_chunk(&self) -> bool {
+        matches!(self, ChunkProblem::Undecodable(_) | ChunkProblem::InvalidProofs)
+    }
+}
+
+impl fmt::Display for ChunkProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkProblem::Missing => write!(f, "chunk is missing"),
+            ChunkProblem::Undecodable(err) => write!(f, "chunk can’t be decoded: {}", err),
+            ChunkProblem::InvalidProofs => write!(f, "chunk doesn’t match its header"),
+            ChunkProblem::MissingOutcomes => write!(f, "outcomes are missing"),
+            ChunkProblem::OutcomeRootMismatch => write!(f, "outcomes don’t match the outcome root"),
+        }
+    }
+}
+
+/// A problem found with the chunk of a shard included in the block at a height.
+#[derive(Debug, Clone)]
+pub struct BadChunk {
+    pub height: BlockHeight,
+    pub shard_id: ShardId,
+    pub chunk_hash: ChunkHash,
+    pub problem: ChunkProblem,
+}
+
+/// Checks the chunks included in the canonical blocks from `from_height` up to `to_height` in the
+/// database in `home_dir`.  Only the shards tracked according to the config are checked.
+///
+/// With `delete`, the corrupt chunks are deleted so that they aren't served anymore.  The
+/// outcomes can only be restored by applying the chunks again, so they're reported only.
+pub fn check_chunks(
+    home_dir: &Path,
+    near_config: &NearConfig,
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+    delete: bool,
+) -> anyhow::Result<Vec<BadChunk>> {
+    let store_path = get_store_path(home_dir);
+    anyhow::ensure!(
+        store_path_exists(&store_path),
+        "{}: storage doesn’t exist",
+        store_path.display()
+    );
+    let store = if delete {
+        create_store(&store_path)
+    } else {
+        create_store_with_config(&store_path, StoreConfig { read_only: true, ..Default::default() })
+    };
+    let runtime = NightshadeRuntime::with_config(home_dir, store.clone(), near_config, None, None);
+    let genesis_height = near_config.genesis.config.genesis_height;
+    let mut chain_store = ChainStore::new(store.clone(), genesis_height);
+    let me = near_config.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+
+    let head = chain_store.head()?;
+    let chunk_tail = chain_store.chunk_tail()?;
+    let from_height = from_height.max(genesis_height + 1);
+    let to_height = to_height.min(head.height);
+    anyhow::ensure!(
+        from_height >= chunk_tail,
+        "the database doesn’t contain the chunks below height {}",
+        chunk_tail
+    );
+
+    info!("Checking chunks from height {} up to {}", from_height, to_height);
+    let mut bad_chunks = vec![];
+    for height in from_height..=to_height {
+        let block_hash = match chain_store.get_block_hash_by_height(height) {
+            Ok(block_hash) => block_hash,
+            // No block at this height on the canonical chain.
+            Err(_) => continue,
+        };
+        let block = chain_store.get_block(&block_hash)?.clone();
+        for chunk_header in block.chunks().iter() {
+            if chunk_header.height_included() != height {
+                continue;
+            }
+            let shard_id = chunk_header.shard_id();
+            if !runtime.cares_about_shard(me.as_ref(), block.header().prev_hash(), shard_id, true) {
+                continue;
+            }
+            let problem = match check_chunk(&store, &runtime, chunk_header)? {
+                Some(problem) => Some(problem),
+                None => check_outcomes(
+                    &store,
+                    &mut chain_store,
+                    &runtime,
+                    &block_hash,
+                    block.header().epoch_id(),
+                    shard_id,
+                )?,
+            };
+            if let Some(problem) = problem {
+                warn!(
+                    "Chunk {} of shard {} at height {}: {}",
+                    chunk_header.chunk_hash().0,
+                    shard_id,
+                    height,
+                    problem
+                );
+                bad_chunks.push(BadChunk {
+                    height,
+                    shard_id,
+                    chunk_hash: chunk_header.chunk_hash(),
+                    problem,
+                });
+            }
+        }
+        if (height - from_height + 1) % 10000 == 0 {
+            info!("Checked chunks up to height {} of {}", height, to_height);
+        }
+    }
+
+    if delete {
+        let mut store_update = store.store_update();
+        let mut num_deleted = 0;
+        for bad_chunk in bad_chunks.iter().filter(|bad_chunk| bad_chunk.problem.is_corrupt_chunk())
+        {
+            store_update.delete(DBCol::ColChunks, bad_chunk.chunk_hash.as_ref());
+            num_deleted += 1;
+        }
+        store_update.commit()?;
+        info!("Deleted {} corrupt chunks", num_deleted);
+    }
+    Ok(bad_chunks)
+}
+
+fn check_chunk(
+    store: &Store,
+    runtime: &NightshadeRuntime,
+    chunk_header: &ShardChunkHeader,
+) -> anyhow::Result<Option<ChunkProblem>> {
+    let bytes = match store.get(DBCol::ColChunks, chunk_header.chunk_hash().as_ref())? {
+        Some(bytes) => bytes,
+        None => return Ok(Some(ChunkProblem::Missing)),
+    };
+    let chunk = match ShardChunk::try_from_slice(&bytes) {
+        Ok(chunk) => chunk,
+        Err(err) => return Ok(Some(ChunkProblem::Undecodable(err.to_string()))),
+    };
+    if chunk.chunk_hash() != chunk_header.chunk_hash() || !validate_chunk_proofs(&chunk, runtime)? {
+        return Ok(Some(ChunkProblem::InvalidProofs));
+    }
+    Ok(None)
+}
+
+/// Checks the outcomes of applying the new chunk of the shard in the block against the outcome
+/// root computed when applying it.
+fn check_outcomes(
+    store: &Store,
+    chain_store: &mut ChainStore,
+    runtime: &NightshadeRuntime,
+    block_hash: &CryptoHash,
+    epoch_id: &EpochId,
+    shard_id: ShardId,
+) -> anyhow::Result<Option<ChunkProblem>> {
+    let shard_uid = runtime.shard_id_to_uid(shard_id, epoch_id)?;
+    let outcome_root = match chain_store.get_chunk_extra(block_hash, &shard_uid) {
+        Ok(chunk_extra) => *chunk_extra.outcome_root(),
+        // The chunk wasn't applied, e.g. because the node state synced after this block.
+        Err(_) => return Ok(None),
+    };
+    let block_shard_id = get_block_shard_id(block_hash, shard_id);
+    let outcome_ids: Vec<CryptoHash> = match store.get_ser(DBCol::ColOutcomeIds, &block_shard_id)? {
+        Some(outcome_ids) => outcome_ids,
+        None => return Ok(Some(ChunkProblem::MissingOutcomes)),
+    };
+    let mut outcomes = Vec::with_capacity(outcome_ids.len());
+    for outcome_id in outcome_ids {
+        let outcome = chain_store
+            .get_outcomes_by_id(&outcome_id)?
+            .into_iter()
+            .find(|outcome| &outcome.block_hash == block_hash);
+        match outcome {
+            Some(outcome) => outcomes.push(outcome.outcome_with_id),
+            None => return Ok(Some(ChunkProblem::MissingOutcomes)),
+        }
+    }
+    if ApplyTransactionResult::compute_outcomes_proof(&outcomes).0 != outcome_root {
+        return Ok(Some(ChunkProblem::OutcomeRootMismatch));
+    }
+    Ok(None)
+}
@@ -15,20 +15,24 @@ use serde::{Deserialize, Serialize};
 #[cfg(test)]
 use tempfile::tempdir;
 use tokio::io::AsyncWriteExt;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
+use near_chain::TX_ROUTING_HEIGHT_HORIZON;
+use near_chain_configs::genesis_validate::validate_genesis;
 use near_chain_configs::{
-    get_initial_supply, ClientConfig, Genesis, GenesisConfig, GenesisValidationMode,
-    LogSummaryStyle,
+    get_initial_supply, ClientConfig, ContractLogsConfig, Genesis, GenesisConfig,
+    GenesisValidationMode, KeyRotationConfig, LogSummaryStyle, MetricsPushConfig,
+    StatePartsCacheConfig, ValidatorEventsConfig,
 };
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
 #[cfg(feature = "json_rpc")]
 use near_jsonrpc::RpcConfig;
 use near_network::test_utils::open_port;
-use near_network_primitives::types::blacklist_from_iter;
+use near_network_primitives::types::{blacklist_from_iter, DNS_SEED_PREFIX};
 use near_network_primitives::types::{NetworkConfig, ROUTED_MESSAGE_TTL};
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
 #[cfg(test)]
 use near_primitives::shard_layout::account_id_to_shard_id;
 use near_primitives::shard_layout::ShardLayout;
@@ -42,7 +46,16 @@ use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner
 use near_primitives::version::PROTOCOL_VERSION;
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::RosettaRpcConfig;
+use near_store::{ContractCacheConfig, MemTriesConfig, StoreResourcesConfig};
 use near_telemetry::TelemetryConfig;
+use near_vm_runner::VMKind;
+
+use crate::chain_params::ChainParams;
+use crate::config_validate::validate_config;
+use crate::genesis_spec::{write_compressed_records, GenesisSpec, GENESIS_RECORDS_FILENAME};
+use crate::remote_signer::{RemoteSignerConfig, RemoteValidatorSigner};
+use crate::startup_check::StartupCheck;
+use crate::with_suffix;
 
 /// Initial balance used in tests.
 pub const TESTING_INIT_BALANCE: Balance = 1_000_000_000 * NEAR_BASE;
@@ -141,6 +154,8 @@ pub const CONFIG_FILENAME: &str = "config.json";
 pub const GENESIS_CONFIG_FILENAME: &str = "genesis.json";
 pub const NODE_KEY_FILE: &str = "node_key.json";
 pub const VALIDATOR_KEY_FILE: &str = "validator_key.json";
+/// Genesis records downloaded by `init_configs`, which are stored decompressed.
+pub const DOWNLOADED_GENESIS_RECORDS_FILENAME: &str = "genesis_records.json";
 
 pub const MAINNET_TELEMETRY_URL: &str = "https://explorer.mainnet.near.org/api/nodes";
 pub const NETWORK_TELEMETRY_URL: &str = "https://explorer.{}.near.org/api/nodes";
@@ -197,6 +212,14 @@ fn default_ttl_account_id_router() -> Duration {
 fn default_peer_stats_period() -> Duration {
     Duration::from_secs(5)
 }
+/// How often the DNS seeds are resolved again.
+fn default_dns_seeds_resolve_period() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+/// Drop the least useful peer when there are too many connections.
+fn default_evict_lowest_scoring_peer() -> bool {
+    true
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Network {
@@ -205,8 +228,12 @@ pub struct Network {
     /// Address to advertise to peers for them to connect.
     /// If empty, will use the same port as the addr, and will introspect on the listener.
     pub external_address: String,
-    /// Comma separated list of nodes to connect to.
+    /// Comma separated list of nodes to connect to.  Entries like `dns:seeds.example.org` are
+    /// DNS seeds, whose TXT records list nodes as `peer_id@host:port`.
     pub boot_nodes: String,
+    /// How often the DNS seeds in `boot_nodes` are resolved again.
+    #[serde(default = "default_dns_seeds_resolve_period")]
+    pub dns_seeds_resolve_period: Duration,
     /// Maximum number of active peers. Hard limit.
     #[serde(default = "default_max_num_peers")]
     pub max_num_peers: u32,
@@ -248,6 +275,37 @@ pub struct Network {
     /// Period to check on peer status
     #[serde(default = "default_peer_stats_period")]
     pub peer_stats_period: Duration,
+    /// Comma separated list of proxy nodes of a private validator.  If set, the node connects
+    /// only to these nodes and doesn't advertise its address.
+    #[serde(default)]
+    pub proxy_nodes: String,
+    /// Node public keys of the private validators this node is a proxy for.
+    #[serde(default)]
+    pub private_peers: Vec<PublicKey>,
+    /// Node public keys of the peers which, like the validators, can take the reserved slots and
+    /// aren't subject to the limits on inbound connections from the same IP or subnet.
+    #[serde(default)]
+    pub whitelisted_peers: Vec<PublicKey>,
+    /// Number of the `max_num_peers` slots which only validators and whitelisted peers can take.
+    /// At most `max_num_peers - ideal_connections_hi`.
+    #[serde(default)]
+    pub reserved_peer_slots: u32,
+    /// Maximum number of inbound connections from the same IP, unlimited if not set.
+    #[serde(default)]
+    pub max_inbound_peers_per_ip: Option<u32>,
+    /// Maximum number of inbound connections from the same /24 IPv4 or /64 IPv6 subnet,
+    /// unlimited if not set.
+    #[serde(default)]
+    pub max_inbound_peers_per_subnet: Option<u32>,
+    /// Whether the least useful peer, the one furthest behind or sending the least data, is
+    /// dropped when there are too many connections, rather than a random one.
+    #[serde(default = "default_evict_lowest_scoring_peer")]
+    pub evict_lowest_scoring_peer: bool,
+    /// Map the port of `addr` in the router with UPnP or NAT-PMP, for nodes behind a router
+    /// which can't forward the port manually.  The state of the mapping is shown by the
+    /// `status` RPC.
+    #[serde(default)]
+    pub port_mapping: bool,
 }
 
 impl Default for Network {
@@ -256,6 +314,7 @@ impl Default for Network {
             addr: "0.0.0.0:24567".to_string(),
             external_address: "".to_string(),
             boot_nodes: "".to_string(),
+            dns_seeds_resolve_period: default_dns_seeds_resolve_period(),
             max_num_peers: default_max_num_peers(),
             minimum_outbound_peers: default_minimum_outbound_connections(),
             ideal_connections_lo: default_ideal_connections_lo(),
@@ -270,6 +329,14 @@ impl Default for Network {
             blacklist: vec![],
             ttl_account_id_router: default_ttl_account_id_router(),
             peer_stats_period: default_peer_stats_period(),
+            proxy_nodes: "".to_string(),
+            private_peers: vec![],
+            whitelisted_peers: vec![],
+            reserved_peer_slots: 0,
+            max_inbound_peers_per_ip: None,
+            max_inbound_peers_per_subnet: None,
+            evict_lowest_scoring_peer: default_evict_lowest_scoring_peer(),
+            port_mapping: false,
         }
     }
 }
@@ -311,6 +378,10 @@ fn default_gc_blocks_limit() -> NumBlocks {
     2
 }
 
+fn default_tx_routing_height_horizon() -> BlockHeightDelta {
+    TX_ROUTING_HEIGHT_HORIZON
+}
+
 fn default_view_client_threads() -> usize {
     4
 }
@@ -327,6 +398,14 @@ fn default_trie_viewer_state_size_limit() -> Option<u64> {
     Some(50_000)
 }
 
+fn default_view_query_timeout() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
+
+fn default_graceful_shutdown_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
 fn default_use_checkpoints_for_db_migration() -> bool {
     true
 }
@@ -445,6 +524,8 @@ pub struct Config {
     pub view_client_throttle_period: Duration,
     #[serde(default = "default_trie_viewer_state_size_limit")]
     pub trie_viewer_state_size_limit: Option<u64>,
+    #[serde(default = "default_view_query_timeout")]
+    pub view_query_timeout: Option<Duration>,
     /// If set, overrides value in genesis configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_gas_burnt_view: Option<Gas>,
@@ -459,6 +540,116 @@ pub struct Config {
     pub db_migration_snapshot_path: Option<PathBuf>,
     #[serde(default = "default_enable_rocksdb_statistics")]
     pub enable_rocksdb_statistics: bool,
+    /// Write the state changed by the applied chunks on a background thread, so that the client
+    /// doesn't wait for RocksDB.  The writes are logged with the rest of each commit first, so
+    /// they are finished on the next start if the node stops before.
+    #[serde(default)]
+    pub async_state_commits: bool,
+    /// Where and how much of compiled contracts to keep.
+    pub contract_cache: ContractCacheConfig,
+    /// If set, gas burnt in applied chunks is attributed to accounts and this many heaviest
+    /// accounts of every epoch are exposed via metrics and debug RPC.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_attribution_top_k: Option<usize>,
+    /// Local soft cap for block producers on marginal hardware: if set and below the gas limit of
+    /// the chunk, transactions of the produced chunks are selected as if the gas limit were
+    /// this.  Doesn't change what the node accepts from other producers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_chunk_gas_limit: Option<Gas>,
+    /// Local soft cap on the number of transactions in the produced chunks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_chunk_max_transactions: Option<usize>,
+    /// Number of upcoming heights whose chunk producers a transaction is forwarded to.  Raising
+    /// it makes forwarding more reliable when some producers are flaky, at the cost of more
+    /// messages.
+    #[serde(default = "default_tx_routing_height_horizon")]
+    pub tx_routing_height_horizon: BlockHeightDelta,
+    /// If set, the approvals the node sends to the same block producer at once, e.g. skips of
+    /// several heights, are coalesced into one message.  Nodes which don't know the message can't
+    /// decode it, so it should only be enabled once the validators and the nodes routing between
+    /// them are upgraded.
+    #[serde(default)]
+    pub approval_batching: bool,
+    /// Epoch height from which the cumulative uptime of the validator, exported as
+    /// `near_validator_cumulative_uptime_ratio`, is counted.  Changing it restarts the count.
+    #[serde(default)]
+    pub uptime_tracking_start_epoch_height: Option<EpochHeight>,
+    /// If set, contracts are executed with this VM instead of the one chosen by the protocol
+    /// version.  Only allowed on non-validator nodes, for platforms which cannot run the default
+    /// VM reliably.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_kind: Option<VMKind>,
+    /// If set, every contract call is additionally executed with this VM and divergences from the
+    /// regular execution are logged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub differential_vm_kind: Option<VMKind>,
+    /// If set, the node doesn't validate even if its validator key is configured.  Instead it
+    /// produces blocks, chunks and approvals locally without broadcasting them, and reports how
+    /// they differ from the published ones.  Lets prospective validators qualify their hardware
+    /// before staking.
+    #[serde(default)]
+    pub shadow_validation: bool,
+    /// If set, the validator key is rotated automatically: a staking transaction with the next
+    /// key is submitted in the given epoch and the node switches to the next key once it takes
+    /// effect.  Relative paths are resolved against the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_rotation: Option<KeyRotationConfig>,
+    /// If set, validator signatures are produced by an external signing service instead of the
+    /// key in `validator_key_file`, which is not used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
+    /// If set, events are reported via the `EXPERIMENTAL_validator_events` RPC, metrics and
+    /// optionally a webhook when the validator misses blocks or chunks or is projected to be
+    /// kicked out at the end of the epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator_events: Option<ValidatorEventsConfig>,
+    /// If set, log lines of contract executions with the given prefixes are streamed over the
+    /// `/contract_logs` WebSocket of the RPC server and optionally written to a file as the
+    /// blocks including them are applied.  Relative paths are resolved against the home
+    /// directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_logs: Option<ContractLogsConfig>,
+    /// If set, state parts served to nodes doing state sync are kept in memory within
+    /// `memory_budget_bytes` and, unless `precompute` is disabled, the parts of the tracked shards
+    /// are generated as soon as an epoch starts within `disk_budget_bytes` rather than on the
+    /// first request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_parts_cache: Option<StatePartsCacheConfig>,
+    /// How long `neard run` waits on SIGINT or SIGTERM for the block being applied to be
+    /// persisted, the store to be flushed and peers to be disconnected before stopping anyway.
+    #[serde(default = "default_graceful_shutdown_timeout")]
+    pub graceful_shutdown_timeout: Duration,
+    /// Whether and within how much memory the state of the tracked shards is kept in memory,
+    /// so that applying chunks doesn't read the database.
+    pub mem_tries: MemTriesConfig,
+    /// If set, the node only syncs, validates and stores block headers and epoch info, which is
+    /// enough to serve header sync and light client RPCs.  Requires a node without a validator
+    /// key which neither tracks shards nor is archival.
+    #[serde(default)]
+    pub header_only: bool,
+    /// Whether the consistency of the chain heads and the data of the head block is checked on
+    /// startup.  With `check` the node refuses to start if they're inconsistent, with `repair`
+    /// it moves the heads back to consistent blocks where possible.
+    pub startup_check: StartupCheck,
+    /// If set, the transactions signed by and the receipts received by every account are indexed
+    /// as the chunks are applied, and the `EXPERIMENTAL_account_history` RPC pages through them.
+    /// Meant for archival nodes, since on the other nodes the entries are garbage collected along
+    /// with the blocks.
+    #[serde(default)]
+    pub account_history_index: bool,
+    /// Directory, relative to the home directory, with the ABIs of contracts which don't publish
+    /// them in their state, named `<code hash>.json`.  They're served by `view_abi` queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi_dir: Option<PathBuf>,
+    /// If set, the metrics served to Prometheus are also pushed over UDP to the StatsD agent at
+    /// `address` every time the summary is logged, for nodes which can't be scraped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_push: Option<MetricsPushConfig>,
+    /// Sizes of the RocksDB block caches, the number of files RocksDB keeps open and the
+    /// capacity of the trie caches.  Those which aren't set are chosen by the memory and the
+    /// open files limit of the machine on startup.
+    #[serde(default)]
+    pub store_resources: StoreResourcesConfig,
 }
 
 impl Default for Config {
@@ -484,10 +675,35 @@ impl Default for Config {
             view_client_threads: default_view_client_threads(),
             view_client_throttle_period: default_view_client_throttle_period(),
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
+            view_query_timeout: default_view_query_timeout(),
             max_gas_burnt_view: None,
             db_migration_snapshot_path: None,
             use_db_migration_snapshot: true,
             enable_rocksdb_statistics: false,
+            async_state_commits: false,
+            contract_cache: ContractCacheConfig::default(),
+            gas_attribution_top_k: None,
+            produced_chunk_gas_limit: None,
+            produced_chunk_max_transactions: None,
+            tx_routing_height_horizon: default_tx_routing_height_horizon(),
+            approval_batching: false,
+            uptime_tracking_start_epoch_height: None,
+            vm_kind: None,
+            differential_vm_kind: None,
+            shadow_validation: false,
+            key_rotation: None,
+            remote_signer: None,
+            validator_events: None,
+            contract_logs: None,
+            state_parts_cache: None,
+            graceful_shutdown_timeout: default_graceful_shutdown_timeout(),
+            mem_tries: MemTriesConfig::default(),
+            header_only: false,
+            startup_check: StartupCheck::default(),
+            account_history_index: false,
+            abi_dir: None,
+            metrics_push: None,
+            store_resources: StoreResourcesConfig::default(),
         }
     }
 }
@@ -689,27 +905,46 @@ impl NearConfig {
                 epoch_sync_enabled: config.epoch_sync_enabled,
                 view_client_throttle_period: config.view_client_throttle_period,
                 trie_viewer_state_size_limit: config.trie_viewer_state_size_limit,
+                view_query_timeout: config.view_query_timeout,
                 max_gas_burnt_view: config.max_gas_burnt_view,
+                gas_attribution_top_k: config.gas_attribution_top_k,
+                produced_chunk_gas_limit: config.produced_chunk_gas_limit,
+                produced_chunk_max_transactions: config.produced_chunk_max_transactions,
+                tx_routing_height_horizon: config.tx_routing_height_horizon,
+                approval_batching: config.approval_batching,
+                uptime_tracking_start_epoch_height: config.uptime_tracking_start_epoch_height,
+                shadow_validation: config.shadow_validation,
+                key_rotation: config.key_rotation,
+                validator_events: config.validator_events,
+                contract_logs: config.contract_logs,
+                state_parts_cache: config.state_parts_cache,
+                header_only: config.header_only,
+                account_history_index: config.account_history_index,
+                metrics_push: config.metrics_push,
             },
             network_config: NetworkConfig {
                 public_key: network_key_pair.public_key,
                 secret_key: network_key_pair.secret_key,
-                account_id: validator_signer.as_ref().map(|vs| vs.validator_id().clone()),
-                addr: if config.network.addr.is_empty() {
+                // A shadow validator must not be routed messages meant for the validator.
+                account_id: if config.shadow_validation {
                     None
                 } else {
-                    Some(config.network.addr.parse().unwrap())
+                    validator_signer.as_ref().map(|vs| vs.validator_id().clone())
                 },
-                boot_nodes: if config.network.boot_nodes.is_empty() {
-                    vec![]
+                addr: if config.network.addr.is_empty() {
+                    None
                 } else {
-                    config
-                        .network
-                        .boot_nodes
-                        .split(',')
-                        .map(|chunk| chunk.try_into().expect("Failed to parse PeerInfo"))
-                        .collect()
+                    Some(config.network.addr.parse().unwrap())
                 },
+                boot_nodes: (config.network.boot_nodes.split(','))
+                    .filter(|chunk| !chunk.is_empty() && !chunk.starts_with(DNS_SEED_PREFIX))
+                    .map(|chunk| chunk.try_into().expect("Failed to parse PeerInfo"))
+                    .collect(),
+                dns_seeds: (config.network.boot_nodes.split(','))
+                    .filter_map(|chunk| chunk.strip_prefix(DNS_SEED_PREFIX))
+                    .map(str::to_string)
+                    .collect(),
+                dns_seeds_resolve_period: config.network.dns_seeds_resolve_period,
                 handshake_timeout: config.network.handshake_timeout,
                 reconnect_delay: config.network.reconnect_delay,
                 bootstrap_peers_period: Duration::from_secs(60),
@@ -733,7 +968,28 @@ impl NearConfig {
                 push_info_period: Duration::from_millis(100),
                 blacklist: blacklist_from_iter(config.network.blacklist),
                 outbound_disabled: false,
+                proxy_nodes: if config.network.proxy_nodes.is_empty() {
+                    vec![]
+                } else {
+                    config
+                        .network
+                        .proxy_nodes
+                        .split(',')
+                        .map(|chunk| chunk.try_into().expect("Failed to parse PeerInfo"))
+                        .collect()
+                },
+                private_peers: config.network.private_peers.into_iter().map(PeerId::new).collect(),
+                whitelisted_peers: (config.network.whitelisted_peers.into_iter())
+                    .map(PeerId::new)
+                    .collect(),
+                reserved_peer_slots: config.network.reserved_peer_slots,
+                max_inbound_peers_per_ip: config.network.max_inbound_peers_per_ip,
+                max_inbound_peers_per_subnet: config.network.max_inbound_peers_per_subnet,
+                evict_lowest_scoring_peer: config.network.evict_lowest_scoring_peer,
+                port_mapping: config.network.port_mapping,
                 archive: config.archive,
+                // Set from the client config when the node starts.
+                client_version: Default::default(),
             },
             telemetry_config: config.telemetry,
             #[cfg(feature = "json_rpc")]
@@ -762,7 +1018,9 @@ impl NearConfig {
 
         self.config.write_to_file(&dir.join(CONFIG_FILENAME)).expect("Error writing config");
 
-        if let Some(validator_signer) = &self.validator_signer {
+        // The key of a remote signer is not available to write.
+        if let (Some(validator_signer), None) = (&self.validator_signer, &self.config.remote_signer)
+        {
             validator_signer
                 .write_to_file(&dir.join(&self.config.validator_key_file))
                 .expect("Error writing validator key file");
@@ -949,10 +1207,15 @@ pub fn init_configs(
     genesis: Option<&str>,
     should_download_genesis: bool,
     download_genesis_url: Option<&str>,
+    download_genesis_sha256: Option<&str>,
+    download_records_url: Option<&str>,
+    download_records_sha256: Option<&str>,
     should_download_config: bool,
     download_config_url: Option<&str>,
+    download_config_sha256: Option<&str>,
     boot_nodes: Option<&str>,
     max_gas_burnt_view: Option<Gas>,
+    chain_params: &ChainParams,
 ) -> anyhow::Result<()> {
     fs::create_dir_all(dir).with_context(|| anyhow!("Failed to create directory {:?}", dir))?;
 
@@ -977,12 +1240,12 @@ pub fn init_configs(
         .unwrap_or_else(random_chain_id);
 
     if let Some(url) = download_config_url {
-        download_config(&url.to_string(), &dir.join(CONFIG_FILENAME))
+        download_config(&url.to_string(), &dir.join(CONFIG_FILENAME), download_config_sha256)
             .context(format!("Failed to download the config file from {}", url))?;
         config = Config::from_file(&dir.join(CONFIG_FILENAME))?;
     } else if should_download_config {
         let url = get_config_url(&chain_id);
-        download_config(&url, &dir.join(CONFIG_FILENAME))
+        download_config(&url, &dir.join(CONFIG_FILENAME), download_config_sha256)
             .context(format!("Failed to download the config file from {}", url))?;
         config = Config::from_file(&dir.join(CONFIG_FILENAME))?;
     }
@@ -995,6 +1258,10 @@ pub fn init_configs(
         config.max_gas_burnt_view = max_gas_burnt_view;
     }
 
+    if ["mainnet", "testnet", "betanet"].contains(&chain_id.as_str()) && !chain_params.is_empty() {
+        bail!("Chain parameters can only be set for new chains, not for {}", chain_id);
+    }
+
     match chain_id.as_ref() {
         "mainnet" => {
             if test_seed.is_some() {
@@ -1018,6 +1285,9 @@ pub fn init_configs(
                 bail!("Test seed is not supported for official testnet");
             }
             config.telemetry.endpoints.push(NETWORK_TELEMETRY_URL.replace("{}", &chain_id));
+            if download_records_url.is_some() {
+                config.genesis_records_file = Some(DOWNLOADED_GENESIS_RECORDS_FILENAME.to_string());
+            }
             config.write_to_file(&dir.join(CONFIG_FILENAME)).with_context(|| {
                 format!("Error writing config to {}", dir.join(CONFIG_FILENAME).display())
             })?;
@@ -1031,11 +1301,11 @@ pub fn init_configs(
                 genesis_path.to_str().with_context(|| "Genesis path must be initialized")?;
 
             if let Some(url) = download_genesis_url {
-                download_genesis(&url.to_string(), &genesis_path)
+                download_genesis(&url.to_string(), &genesis_path, download_genesis_sha256)
                     .context(format!("Failed to download the genesis file from {}", url))?;
             } else if should_download_genesis {
                 let url = get_genesis_url(&chain_id);
-                download_genesis(&url, &genesis_path)
+                download_genesis(&url, &genesis_path, download_genesis_sha256)
                     .context(format!("Failed to download the genesis file from {}", url))?;
             } else {
                 genesis_path_str = match genesis {
@@ -1050,10 +1320,30 @@ pub fn init_configs(
                 };
             }
 
-            let mut genesis = Genesis::from_file(&genesis_path_str, GenesisValidationMode::Full);
-            genesis.config.chain_id = chain_id.clone();
-
-            genesis.to_file(&dir.join(config.genesis_file));
+            if let Some(url) = download_records_url {
+                // Records are kept in a separate file next to the genesis config, which then
+                // mustn't contain any, so that neither has to be rewritten.
+                let records_path = dir.join(DOWNLOADED_GENESIS_RECORDS_FILENAME);
+                download_records(&url.to_string(), &records_path, download_records_sha256)
+                    .context(format!("Failed to download the genesis records from {}", url))?;
+                let mut genesis_config =
+                    GenesisConfig::from_file(&genesis_path_str).with_context(|| {
+                        anyhow!("Failed to read genesis config {}", genesis_path_str)
+                    })?;
+                genesis_config.chain_id = chain_id.clone();
+                genesis_config.to_file(&dir.join(&config.genesis_file));
+                // Fails if the records don't match the genesis config.
+                Genesis::from_files(
+                    &dir.join(&config.genesis_file),
+                    &records_path,
+                    GenesisValidationMode::Full,
+                );
+            } else {
+                let mut genesis =
+                    Genesis::from_file(&genesis_path_str, GenesisValidationMode::Full);
+                genesis.config.chain_id = chain_id.clone();
+                genesis.to_file(&dir.join(&config.genesis_file));
+            }
             info!(target: "near", "Generated for {} network node key and genesis file in {}", chain_id, dir.display());
         }
         _ => {
@@ -1065,9 +1355,6 @@ pub fn init_configs(
                 config.consensus.max_block_production_delay =
                     Duration::from_millis(FAST_MAX_BLOCK_PRODUCTION_DELAY);
             }
-            config.write_to_file(&dir.join(CONFIG_FILENAME)).with_context(|| {
-                format!("Error writing config to {}", dir.join(CONFIG_FILENAME).display())
-            })?;
 
             let account_id = account_id.unwrap_or_else(|| "test.near".parse().unwrap());
             let signer =
@@ -1100,7 +1387,7 @@ pub fn init_configs(
                 ShardLayout::v0_single_shard()
             };
 
-            let genesis_config = GenesisConfig {
+            let mut genesis_config = GenesisConfig {
                 protocol_version: PROTOCOL_VERSION,
                 genesis_time: Clock::utc(),
                 chain_id,
@@ -1137,6 +1424,12 @@ pub fn init_configs(
                 min_gas_price: MIN_GAS_PRICE,
                 ..Default::default()
             };
+            chain_params
+                .apply(&mut config, &mut genesis_config)
+                .context("Invalid chain parameters")?;
+            config.write_to_file(&dir.join(CONFIG_FILENAME)).with_context(|| {
+                format!("Error writing config to {}", dir.join(CONFIG_FILENAME).display())
+            })?;
             let genesis = Genesis::new(genesis_config, records.into());
             genesis.to_file(&dir.join(config.genesis_file));
             info!(target: "near", "Generated node key, validator key, genesis file in {}", dir.display());
@@ -1216,20 +1509,30 @@ pub fn create_testnet_configs(
 
 pub fn init_testnet_configs(
     dir: &Path,
-    num_shards: NumShards,
+    shard_layout: ShardLayout,
     num_validator_seats: NumSeats,
     num_non_validator_seats: NumSeats,
     prefix: &str,
+    local_ports: bool,
     archive: bool,
+    genesis_spec: Option<&GenesisSpec>,
 ) {
-    let (configs, validator_signers, network_signers, genesis) = create_testnet_configs(
-        num_shards,
+    let (mut configs, validator_signers, network_signers, mut genesis) = create_testnet_configs(
+        shard_layout.num_shards(),
         num_validator_seats,
         num_non_validator_seats,
         prefix,
-        false,
+        local_ports,
         archive,
     );
+    genesis.config.shard_layout = shard_layout;
+    if let Some(genesis_spec) = genesis_spec {
+        genesis_spec
+            .populate(&mut genesis)
+            .unwrap_or_else(|err| panic!("Failed to populate genesis: {:#}", err));
+        validate_genesis(&genesis);
+    }
+    let compress_records = genesis_spec.map_or(false, |spec| spec.compress_records);
     for i in 0..(num_validator_seats + num_non_validator_seats) as usize {
         let node_dir = dir.join(format!("{}{}", prefix, i));
         fs::create_dir_all(node_dir.clone()).expect("Failed to create directory");
@@ -1241,7 +1544,14 @@ pub fn init_testnet_configs(
             .write_to_file(&node_dir.join(&configs[i].node_key_file))
             .expect("Error writing key file");
 
-        genesis.to_file(&node_dir.join(&configs[i].genesis_file));
+        if compress_records {
+            genesis.config.to_file(&node_dir.join(&configs[i].genesis_file));
+            write_compressed_records(&genesis.records.0, &node_dir.join(GENESIS_RECORDS_FILENAME))
+                .expect("Error writing genesis records");
+            configs[i].genesis_records_file = Some(GENESIS_RECORDS_FILENAME.to_string());
+        } else {
+            genesis.to_file(&node_dir.join(&configs[i].genesis_file));
+        }
         configs[i].write_to_file(&node_dir.join(CONFIG_FILENAME)).expect("Error writing config");
         info!(target: "near", "Generated node key, validator key, genesis file in {}", node_dir.display());
     }
@@ -1279,6 +1589,12 @@ pub enum FileDownloadError {
     UriError(#[from] hyper::http::uri::InvalidUri),
     #[error("Failed to remove temporary file: {0}. Download previously failed")]
     RemoveTemporaryFileError(std::io::Error, #[source] Box<FileDownloadError>),
+    #[error("Unexpected HTTP response status {0}")]
+    HttpStatusError(hyper::StatusCode),
+    #[error("Failed to read partially downloaded file at {0:?}")]
+    ReadError(PathBuf, #[source] std::io::Error),
+    #[error("SHA-256 checksum mismatch: expected {0}, got {1}")]
+    ChecksumMismatch(String, String),
 }
 
 /// Object which allows transparent XZ decoding when saving data to a file.
@@ -1504,27 +1820,120 @@ fn test_auto_xz_decode_corrupted() {
     }
 }
 
-/// Downloads resource at given `uri` and saves it to `file`.  On failure,
-/// `file` may be left in inconsistent state (i.e. may contain partial data).
-///
-/// If the downloaded file is an XZ stream (i.e. starts with the XZ 6-byte magic
-/// number), transparently decompresses the file as it’s being downloaded.
+/// How many times a download is attempted, each attempt resuming where the previous one stopped,
+/// before giving up.
+const DOWNLOAD_ATTEMPTS: usize = 5;
+
+/// Resource the partially downloaded data was downloaded from, stored next to it so that a
+/// download is only resumed from the same version of the same resource.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct DownloadSource {
+    url: String,
+    etag: Option<String>,
+}
+
+fn response_etag(resp: &hyper::Response<hyper::Body>) -> Option<String> {
+    resp.headers().get(hyper::header::ETAG)?.to_str().ok().map(str::to_string)
+}
+
+/// Size of the whole resource from the `Content-Range: bytes */<size>` header of a response to an
+/// unsatisfiable range request.
+fn unsatisfied_range_size(resp: &hyper::Response<hyper::Body>) -> Option<u64> {
+    let range = resp.headers().get(hyper::header::CONTENT_RANGE)?.to_str().ok()?;
+    range.strip_prefix("bytes */")?.parse().ok()
+}
+
+/// Downloads resource at given `uri` and appends it to the partially downloaded file at
+/// `part_path`, asking the server for the missing range only.  The partial data is only resumed
+/// from if it is checked against a checksum afterwards (`verified`) or it comes from the same URL
+/// with the same ETag, and the download starts over otherwise or if the server doesn't support
+/// ranges.  On failure, the file may be left with partial data to resume from.
 async fn download_file_impl(
     uri: hyper::Uri,
-    path: &std::path::Path,
-    file: tokio::fs::File,
+    part_path: &std::path::Path,
+    verified: bool,
 ) -> anyhow::Result<(), FileDownloadError> {
-    let mut out = AutoXzDecoder::new(path, file);
+    let source_path = with_suffix(part_path, ".source");
+    let write_error = |e| FileDownloadError::WriteError(part_path.to_path_buf(), e);
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(part_path)
+        .await
+        .map_err(FileDownloadError::OpenError)?;
+    let mut offset = file
+        .metadata()
+        .await
+        .map_err(|e| FileDownloadError::ReadError(part_path.to_path_buf(), e))?
+        .len();
+    let url = uri.to_string();
+    let mut etag = None;
+    if offset > 0 {
+        let source = std::fs::read(&source_path)
+            .ok()
+            .and_then(|source| serde_json::from_slice::<DownloadSource>(&source).ok())
+            .filter(|source| source.url == url);
+        etag = source.and_then(|source| source.etag);
+        if !verified && etag.is_none() {
+            info!(target: "near", "Discarding partially downloaded data from an unknown source");
+            file.set_len(0).await.map_err(write_error)?;
+            offset = 0;
+        }
+    }
+
     let https_connector = hyper_tls::HttpsConnector::new();
     let client = hyper::Client::builder().build::<_, hyper::Body>(https_connector);
-    let mut resp = client.get(uri).await.map_err(FileDownloadError::HttpError)?;
+    let mut resp = loop {
+        let mut request = hyper::Request::get(uri.clone());
+        if offset > 0 {
+            info!(target: "near", "Resuming download from byte {} ...", offset);
+            request = request.header(hyper::header::RANGE, format!("bytes={}-", offset));
+            // The server sends the whole resource if it has changed since.
+            if let Some(etag) = &etag {
+                request = request.header(hyper::header::IF_RANGE, etag.as_str());
+            }
+        }
+        let resp = client
+            .request(request.body(hyper::Body::empty()).expect("Range header is valid"))
+            .await
+            .map_err(FileDownloadError::HttpError)?;
+        let same_version = verified || (etag.is_some() && response_etag(&resp) == etag);
+        match resp.status() {
+            hyper::StatusCode::PARTIAL_CONTENT if offset > 0 && same_version => break resp,
+            hyper::StatusCode::OK => {
+                // The server sends the whole resource.
+                file.set_len(0).await.map_err(write_error)?;
+                offset = 0;
+                let source = DownloadSource { url: url.clone(), etag: response_etag(&resp) };
+                std::fs::write(&source_path, serde_json::to_vec(&source).unwrap())
+                    .map_err(|e| FileDownloadError::WriteError(source_path.clone(), e))?;
+                break resp;
+            }
+            // The previous attempt got everything but didn't get to finish.
+            hyper::StatusCode::RANGE_NOT_SATISFIABLE
+                if offset > 0 && same_version && unsatisfied_range_size(&resp) == Some(offset) =>
+            {
+                return Ok(());
+            }
+            hyper::StatusCode::PARTIAL_CONTENT | hyper::StatusCode::RANGE_NOT_SATISFIABLE
+                if offset > 0 =>
+            {
+                info!(target: "near", "Partially downloaded data doesn't match the resource, starting over");
+                file.set_len(0).await.map_err(write_error)?;
+                offset = 0;
+            }
+            status => return Err(FileDownloadError::HttpStatusError(status)),
+        }
+    };
+
     let bar = if let Some(file_size) = resp.size_hint().upper() {
-        let bar = ProgressBar::new(file_size);
+        let bar = ProgressBar::new(offset + file_size);
         bar.set_style(
             ProgressStyle::default_bar().template(
                 "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} [{bytes_per_sec}] ({eta})"
             ).progress_chars("#>-")
         );
+        bar.set_position(offset);
         bar
     } else {
         let bar = ProgressBar::new_spinner();
@@ -1536,26 +1945,106 @@ async fn download_file_impl(
     };
     while let Some(next_chunk_result) = resp.data().await {
         let next_chunk = next_chunk_result.map_err(FileDownloadError::HttpError)?;
-        out.write_all(next_chunk.as_ref()).await?;
+        file.write_all(next_chunk.as_ref())
+            .await
+            .map_err(|e| FileDownloadError::WriteError(part_path.to_path_buf(), e))?;
         bar.inc(next_chunk.len() as u64);
     }
-    out.finish().await?;
+    file.flush().await.map_err(|e| FileDownloadError::WriteError(part_path.to_path_buf(), e))?;
     bar.finish();
     Ok(())
 }
 
+/// Returns the hex-encoded SHA-256 digest of the file at `path`.
+async fn sha256_of_file(path: &Path) -> Result<String, FileDownloadError> {
+    use sha2::Digest;
+    use tokio::io::AsyncReadExt;
+
+    let read_error = |e| FileDownloadError::ReadError(path.to_path_buf(), e);
+    let mut file = tokio::fs::File::open(path).await.map_err(read_error)?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let len = file.read(&mut buffer).await.map_err(read_error)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buffer[..len]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Decompresses the downloaded file at `part_path`, if it's an XZ stream, into `file`.
+async fn decode_file(
+    part_path: &Path,
+    path: &Path,
+    file: tokio::fs::File,
+) -> Result<(), FileDownloadError> {
+    use tokio::io::AsyncReadExt;
+
+    let read_error = |e| FileDownloadError::ReadError(part_path.to_path_buf(), e);
+    let mut part_file = tokio::fs::File::open(part_path).await.map_err(read_error)?;
+    let mut out = AutoXzDecoder::new(path, file);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let len = part_file.read(&mut buffer).await.map_err(read_error)?;
+        if len == 0 {
+            break;
+        }
+        out.write_all(&buffer[..len]).await?;
+    }
+    out.finish().await
+}
+
 /// Downloads a resource at given `url` and saves it to `path`.  On success, if
 /// file at `path` exists it will be overwritten.  On failure, file at `path` is
 /// left unchanged (if it exists).
-pub async fn download_file(url: &str, path: &Path) -> Result<(), FileDownloadError> {
-    let uri = url.parse()?;
+///
+/// The raw data is downloaded to `path` with a `.part` suffix first, from which interrupted
+/// downloads, including ones by an earlier invocation, are resumed.  If `sha256` is given, the
+/// hex-encoded SHA-256 digest of the downloaded data, e.g. as published next to it, must match it.
+/// Without it, a download is only resumed if the URL and ETag of the resource are the same as when
+/// it started, as recorded in a `.part.source` file.
+/// If the downloaded file is an XZ stream (i.e. starts with the XZ 6-byte magic
+/// number), it's transparently decompressed.
+pub async fn download_file(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+) -> Result<(), FileDownloadError> {
+    let uri: hyper::Uri = url.parse()?;
+    let part_path = with_suffix(path, ".part");
+    let remove_part = || {
+        let _ = std::fs::remove_file(&part_path);
+        let _ = std::fs::remove_file(with_suffix(&part_path, ".source"));
+    };
+
+    let mut attempt = 1;
+    while let Err(err) = download_file_impl(uri.clone(), &part_path, sha256.is_some()).await {
+        if attempt == DOWNLOAD_ATTEMPTS || !matches!(err, FileDownloadError::HttpError(_)) {
+            return Err(err);
+        }
+        warn!(target: "near", "Download attempt {} of {} failed: {}", attempt, DOWNLOAD_ATTEMPTS, err);
+        attempt += 1;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    if let Some(expected) = sha256 {
+        let actual = sha256_of_file(&part_path).await?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            // There's no point in resuming from corrupted data.
+            remove_part();
+            return Err(FileDownloadError::ChecksumMismatch(expected.to_string(), actual));
+        }
+        info!(target: "near", "Verified SHA-256 checksum {}", actual);
+    }
 
     let (tmp_file, tmp_path) = {
         let tmp_dir = path.parent().unwrap_or(Path::new("."));
         tempfile::NamedTempFile::new_in(tmp_dir).map_err(FileDownloadError::OpenError)?.into_parts()
     };
 
-    let result = match download_file_impl(uri, &tmp_path, tokio::fs::File::from_std(tmp_file)).await
+    let result = match decode_file(&part_path, &tmp_path, tokio::fs::File::from_std(tmp_file)).await
     {
         Err(err) => Err((tmp_path, err)),
         Ok(()) => tmp_path.persist(path).map_err(|e| {
@@ -1565,32 +2054,61 @@ pub async fn download_file(url: &str, path: &Path) -> Result<(), FileDownloadErr
         }),
     };
 
-    result.map_err(|(tmp_path, err)| match tmp_path.close() {
+    let result = result.map_err(|(tmp_path, err)| match tmp_path.close() {
         Ok(()) => err,
         Err(close_err) => FileDownloadError::RemoveTemporaryFileError(close_err, Box::new(err)),
-    })
+    });
+    if result.is_ok() {
+        remove_part();
+    }
+    result
 }
 
-fn run_download_file(url: &str, path: &Path) -> Result<(), FileDownloadError> {
+fn run_download_file(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+) -> Result<(), FileDownloadError> {
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
         .unwrap()
-        .block_on(async { download_file(url, path).await })
+        .block_on(async { download_file(url, path, sha256).await })
 }
 
-pub fn download_genesis(url: &str, path: &Path) -> Result<(), FileDownloadError> {
+pub fn download_genesis(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+) -> Result<(), FileDownloadError> {
     info!(target: "near", "Downloading genesis file from: {} ...", url);
-    let result = run_download_file(url, path);
+    let result = run_download_file(url, path, sha256);
     if result.is_ok() {
         info!(target: "near", "Saved the genesis file to: {} ...", path.display());
     }
     result
 }
 
-pub fn download_config(url: &str, path: &Path) -> Result<(), FileDownloadError> {
+pub fn download_records(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+) -> Result<(), FileDownloadError> {
+    info!(target: "near", "Downloading genesis records file from: {} ...", url);
+    let result = run_download_file(url, path, sha256);
+    if result.is_ok() {
+        info!(target: "near", "Saved the genesis records file to: {} ...", path.display());
+    }
+    result
+}
+
+pub fn download_config(
+    url: &str,
+    path: &Path,
+    sha256: Option<&str>,
+) -> Result<(), FileDownloadError> {
     info!(target: "near", "Downloading config file from: {} ...", url);
-    let result = run_download_file(url, path);
+    let result = run_download_file(url, path, sha256);
     if result.is_ok() {
         info!(target: "near", "Saved the config file to: {} ...", path.display());
     }
@@ -1632,7 +2150,11 @@ impl From<NodeKeyFile> for KeyFile {
 pub fn load_config(dir: &Path, genesis_validation: GenesisValidationMode) -> NearConfig {
     let config = Config::from_file(&dir.join(CONFIG_FILENAME)).unwrap();
     let genesis_file = dir.join(&config.genesis_file);
-    let validator_signer = if dir.join(&config.validator_key_file).exists() {
+    let validator_signer = if let Some(remote_signer) = &config.remote_signer {
+        let signer = RemoteValidatorSigner::new(remote_signer.clone(), dir)
+            .unwrap_or_else(|err| panic!("Failed to start remote signer: {:#}", err));
+        Some(Arc::new(signer) as Arc<dyn ValidatorSigner>)
+    } else if dir.join(&config.validator_key_file).exists() {
         let signer =
             Arc::new(InMemoryValidatorSigner::from_file(&dir.join(&config.validator_key_file)))
                 as Arc<dyn ValidatorSigner>;
@@ -1640,22 +2162,27 @@ pub fn load_config(dir: &Path, genesis_validation: GenesisValidationMode) -> Nea
     } else {
         None
     };
+    let genesis = match &config.genesis_records_file {
+        Some(genesis_records_file) => {
+            Genesis::from_files(&genesis_file, &dir.join(genesis_records_file), genesis_validation)
+        }
+        None => Genesis::from_file(&genesis_file, genesis_validation),
+    };
+    if let Err(err) = validate_config(dir, &config, &genesis.config) {
+        panic!("{}", err);
+    }
     let network_signer = NodeKeyFile::from_file(&dir.join(&config.node_key_file));
 
-    let genesis_records_file = config.genesis_records_file.clone();
-    NearConfig::new(
-        config,
-        match genesis_records_file {
-            Some(genesis_records_file) => Genesis::from_files(
-                &genesis_file,
-                &dir.join(genesis_records_file),
-                genesis_validation,
-            ),
-            None => Genesis::from_file(&genesis_file, genesis_validation),
-        },
-        network_signer.into(),
-        validator_signer,
-    )
+    let mut near_config = NearConfig::new(config, genesis, network_signer.into(), validator_signer);
+    if let Some(key_rotation) = &mut near_config.client_config.key_rotation {
+        key_rotation.next_validator_key_file = dir.join(&key_rotation.next_validator_key_file);
+        key_rotation.account_key_file = dir.join(&key_rotation.account_key_file);
+    }
+    if let Some(contract_logs) = &mut near_config.client_config.contract_logs {
+        contract_logs.output_file =
+            contract_logs.output_file.as_ref().map(|output_file| dir.join(output_file));
+    }
+    near_config
 }
 
 pub fn load_test_config(seed: &str, port: u16, genesis: Genesis) -> NearConfig {
@@ -1697,10 +2224,15 @@ fn test_init_config_localnet() {
         None,
         false,
         None,
+        None,
+        None,
+        None,
         false,
         None,
         None,
         None,
+        None,
+        &Default::default(),
     )
     .unwrap();
     let genesis =
@@ -1729,3 +2261,39 @@ fn test_init_config_localnet() {
         2
     );
 }
+
+#[test]
+fn test_init_testnet_configs_boundary_accounts() {
+    // Check that the localnet nodes get the given shard layout with a validator seat per shard.
+    let temp_dir = tempdir().unwrap();
+    init_testnet_configs(
+        &temp_dir.path(),
+        ShardLayout::v1(vec![], vec![AccountId::from_str("node1").unwrap()], None, 1),
+        2,
+        1,
+        "node",
+        false,
+        false,
+        None,
+    );
+    let genesis = Genesis::from_file(
+        temp_dir.path().join("node2").join("genesis.json"),
+        GenesisValidationMode::UnsafeFast,
+    );
+    assert_eq!(genesis.config.shard_layout.num_shards(), 2);
+    assert_eq!(genesis.config.num_block_producer_seats_per_shard, vec![1, 1]);
+    assert_eq!(
+        account_id_to_shard_id(
+            &AccountId::from_str("node0").unwrap(),
+            &genesis.config.shard_layout
+        ),
+        0
+    );
+    assert_eq!(
+        account_id_to_shard_id(
+            &AccountId::from_str("node2").unwrap(),
+            &genesis.config.shard_layout
+        ),
+        1
+    );
+}
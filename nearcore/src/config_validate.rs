@@ -0,0 +1,339 @@
+//! Validation of `config.json` against itself and the genesis, run on startup and by
+//! `neard validate-config`.
+//!
+//! Unlike the checks done while the node starts, which stop at the first problem and often with
+//! a panic deep in some component, the validation collects all the problems it finds, each with
+//! the field to fix.  Settings which are valid but likely mistakes are only logged.
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use near_chain::chain::NUM_EPOCHS_TO_KEEP_STORE_DATA;
+use near_chain_configs::GenesisConfig;
+use near_network_primitives::types::{PatternAddr, PeerInfo, DNS_SEED_PREFIX};
+use tracing::warn;
+
+use crate::config::Config;
+
+/// The problems found in the config.
+#[derive(Debug)]
+pub struct ValidationErrors(pub Vec<String>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config.json:")?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Checks the config of the node with the given home directory.
+pub fn validate_config(
+    home_dir: &Path,
+    config: &Config,
+    genesis_config: &GenesisConfig,
+) -> Result<(), ValidationErrors> {
+    let mut validator = Validator { errors: vec![], warnings: vec![] };
+    validator.validate_files(home_dir, config);
+    validator.validate_network(config);
+    validator.validate_tracking(config, genesis_config);
+    validator.validate_consensus(config, genesis_config);
+    validator.validate_metrics_push(config);
+    #[cfg(feature = "json_rpc")]
+    validator.validate_rpc(config);
+    for warning in &validator.warnings {
+        warn!(target: "neard", "config.json: {}", warning);
+    }
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors(validator.errors))
+    }
+}
+
+struct Validator {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Validator {
+    fn error(&mut self, error: String) {
+        self.errors.push(error);
+    }
+
+    fn warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    fn validate_files(&mut self, home_dir: &Path, config: &Config) {
+        let node_key_file = home_dir.join(&config.node_key_file);
+        if !node_key_file.exists() {
+            self.error(format!(
+                "node_key_file: {} doesn't exist, run `neard init` to generate a node key",
+                node_key_file.display()
+            ));
+        }
+        if let Some(genesis_records_file) = &config.genesis_records_file {
+            let genesis_records_file = home_dir.join(genesis_records_file);
+            if !genesis_records_file.exists() {
+                self.error(format!(
+                    "genesis_records_file: {} doesn't exist",
+                    genesis_records_file.display()
+                ));
+            }
+        }
+        if let Some(abi_dir) = &config.abi_dir {
+            let abi_dir = home_dir.join(abi_dir);
+            if !abi_dir.is_dir() {
+                self.warning(format!(
+                    "abi_dir: {} isn't a directory, so no ABIs are loaded from it",
+                    abi_dir.display()
+                ));
+            }
+        }
+    }
+
+    fn validate_network(&mut self, config: &Config) {
+        let network = &config.network;
+        self.validate_addr("network.addr", &network.addr);
+        // Only advertised to other nodes, which is why a domain name will do.
+        if !network.external_address.is_empty() && !is_host_port(&network.external_address) {
+            self.warning(format!(
+                "network.external_address: {} isn't a valid host:port",
+                network.external_address
+            ));
+        }
+        for boot_node in network.boot_nodes.split(',').filter(|entry| !entry.is_empty()) {
+            match boot_node.strip_prefix(DNS_SEED_PREFIX) {
+                Some("") => self.error(format!(
+                    "network.boot_nodes: {} has no domain name, expected {}<domain>",
+                    boot_node, DNS_SEED_PREFIX
+                )),
+                Some(_) => {}
+                None => self.validate_peer_info("network.boot_nodes", boot_node),
+            }
+        }
+        for proxy_node in network.proxy_nodes.split(',').filter(|entry| !entry.is_empty()) {
+            self.validate_peer_info("network.proxy_nodes", proxy_node);
+            if matches!(proxy_node.parse::<PeerInfo>(), Ok(PeerInfo { addr: None, .. })) {
+                self.error(format!("network.proxy_nodes: {} has no address", proxy_node));
+            }
+        }
+        for entry in &network.blacklist {
+            if entry.parse::<PatternAddr>().is_err() {
+                self.error(format!(
+                    "network.blacklist: {} is neither an IP nor an IP:port, it would be ignored",
+                    entry
+                ));
+            }
+        }
+
+        if network.ideal_connections_lo > network.ideal_connections_hi {
+            self.error(format!(
+                "network.ideal_connections_lo ({}) must be at most network.ideal_connections_hi ({})",
+                network.ideal_connections_lo, network.ideal_connections_hi
+            ));
+        }
+        if network.ideal_connections_hi >= network.max_num_peers {
+            self.error(format!(
+                "network.max_num_peers ({}) must be larger than network.ideal_connections_hi ({}), otherwise new connections get declined",
+                network.max_num_peers, network.ideal_connections_hi
+            ));
+        } else if network.reserved_peer_slots > network.max_num_peers - network.ideal_connections_hi
+        {
+            self.error(format!(
+                "network.reserved_peer_slots ({}) must be at most network.max_num_peers ({}) minus network.ideal_connections_hi ({})",
+                network.reserved_peer_slots, network.max_num_peers, network.ideal_connections_hi
+            ));
+        }
+        if network.safe_set_size <= network.minimum_outbound_peers {
+            self.error(format!(
+                "network.safe_set_size ({}) must be larger than network.minimum_outbound_peers ({})",
+                network.safe_set_size, network.minimum_outbound_peers
+            ));
+        }
+    }
+
+    fn validate_addr(&mut self, field: &str, addr: &str) {
+        if !addr.is_empty() && addr.parse::<SocketAddr>().is_err() {
+            self.error(format!("{}: {} isn't a valid IP:port", field, addr));
+        }
+    }
+
+    fn validate_peer_info(&mut self, field: &str, peer_info: &str) {
+        if let Err(err) = peer_info.parse::<PeerInfo>() {
+            self.error(format!(
+                "{}: {} isn't a valid peer_id@host:port entry: {}",
+                field, peer_info, err
+            ));
+        }
+    }
+
+    fn validate_tracking(&mut self, config: &Config, genesis_config: &GenesisConfig) {
+        let num_shards = genesis_config.shard_layout.num_shards();
+        for shard_id in &config.tracked_shards {
+            if *shard_id >= num_shards {
+                self.warning(format!(
+                    "tracked_shards: the genesis has {} shards, so there's no shard {}; any entry makes the node track all shards",
+                    num_shards, shard_id
+                ));
+            }
+        }
+        if config.archive && config.tracked_shards.is_empty() && config.tracked_accounts.is_empty()
+        {
+            self.warning(
+                "archive is set but no shards are tracked, so only the blocks are archived; set tracked_shards to [0] to archive the state of all shards"
+                    .to_string(),
+            );
+        }
+        if config.account_history_index && !config.archive {
+            self.warning(
+                "account_history_index is set but archive isn't, so the history of the accounts only covers the blocks which aren't garbage collected yet"
+                    .to_string(),
+            );
+        }
+        if !config.archive && config.gc_blocks_limit == 0 {
+            self.error(
+                "gc_blocks_limit must be positive unless archive is set, otherwise the old data is never collected"
+                    .to_string(),
+            );
+        }
+    }
+
+    fn validate_consensus(&mut self, config: &Config, genesis_config: &GenesisConfig) {
+        let consensus = &config.consensus;
+        if consensus.min_block_production_delay > consensus.max_block_production_delay {
+            self.error(format!(
+                "consensus.min_block_production_delay ({:?}) must be at most consensus.max_block_production_delay ({:?})",
+                consensus.min_block_production_delay, consensus.max_block_production_delay
+            ));
+        }
+        // State sync can only be served for the epochs whose data the peers didn't collect yet.
+        let epoch_length = genesis_config.epoch_length;
+        if consensus.block_header_fetch_horizon + consensus.state_fetch_horizon >= epoch_length {
+            self.warning(format!(
+                "consensus.block_header_fetch_horizon ({}) plus consensus.state_fetch_horizon ({}) is at least the epoch length ({}), so state sync may target an epoch before the previous one",
+                consensus.block_header_fetch_horizon, consensus.state_fetch_horizon, epoch_length
+            ));
+        }
+        if !config.archive
+            && consensus.block_fetch_horizon >= NUM_EPOCHS_TO_KEEP_STORE_DATA * epoch_length
+        {
+            self.warning(format!(
+                "consensus.block_fetch_horizon ({}) spans more than the {} epochs of {} blocks kept before garbage collection, so block sync may request collected blocks",
+                consensus.block_fetch_horizon, NUM_EPOCHS_TO_KEEP_STORE_DATA, epoch_length
+            ));
+        }
+    }
+
+    fn validate_metrics_push(&mut self, config: &Config) {
+        let metrics_push = match &config.metrics_push {
+            Some(metrics_push) => metrics_push,
+            None => return,
+        };
+        if !is_host_port(&metrics_push.address) {
+            self.error(format!(
+                "metrics_push.address: {} isn't a valid host:port",
+                metrics_push.address
+            ));
+        }
+    }
+
+    #[cfg(feature = "json_rpc")]
+    fn validate_rpc(&mut self, config: &Config) {
+        let rpc = match &config.rpc {
+            Some(rpc) => rpc,
+            None => return,
+        };
+        self.validate_addr("rpc.addr", &rpc.addr);
+        if !rpc.addr.is_empty() && rpc.addr == config.network.addr {
+            self.error(format!("rpc.addr and network.addr are both {}", rpc.addr));
+        }
+        if let Some(prometheus_addr) = &rpc.prometheus_addr {
+            self.validate_addr("rpc.prometheus_addr", prometheus_addr);
+            if *prometheus_addr == rpc.addr || *prometheus_addr == config.network.addr {
+                self.error(format!(
+                    "rpc.prometheus_addr ({}) must differ from rpc.addr and network.addr",
+                    prometheus_addr
+                ));
+            }
+        }
+        if rpc.limits_config.json_payload_max_size == 0 {
+            self.error(
+                "rpc.limits_config.json_payload_max_size must be positive, otherwise every request is rejected"
+                    .to_string(),
+            );
+        }
+        let jobs = &rpc.jobs_config;
+        if jobs.max_concurrent_jobs == 0 {
+            self.error("rpc.jobs_config.max_concurrent_jobs must be positive".to_string());
+        }
+        if jobs.max_jobs < jobs.max_concurrent_jobs {
+            self.error(format!(
+                "rpc.jobs_config.max_jobs ({}) must be at least rpc.jobs_config.max_concurrent_jobs ({})",
+                jobs.max_jobs, jobs.max_concurrent_jobs
+            ));
+        }
+    }
+}
+
+fn is_host_port(addr: &str) -> bool {
+    addr.rsplit_once(':')
+        .map_or(false, |(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::{GenesisConfig, MetricsPushConfig};
+
+    use super::validate_config;
+    use crate::config::Config;
+
+    #[test]
+    fn test_validate_config() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        std::fs::write(home_dir.path().join(&config.node_key_file), "{}").unwrap();
+        let genesis_config = GenesisConfig { epoch_length: 60, ..GenesisConfig::default() };
+        config.network.external_address = "node.example.org:24567".to_string();
+        validate_config(home_dir.path(), &config, &genesis_config).unwrap();
+        config.network.external_address = "node.example.org".to_string();
+        validate_config(home_dir.path(), &config, &genesis_config).unwrap();
+
+        config.genesis_records_file = Some("records.json".to_string());
+        config.network.boot_nodes = "ed25519:invalid@127.0.0.1:24567,dns:".to_string();
+        config.network.blacklist = vec!["127.0.0.1:port".to_string()];
+        config.network.ideal_connections_hi = config.network.max_num_peers;
+        config.gc_blocks_limit = 0;
+        config.consensus.min_block_production_delay =
+            config.consensus.max_block_production_delay * 2;
+        config.metrics_push =
+            Some(MetricsPushConfig { address: "localhost".to_string(), prefix: String::new() });
+        config.set_rpc_addr(config.network.addr.clone());
+        let errors = validate_config(home_dir.path(), &config, &genesis_config).unwrap_err().0;
+        let fields: Vec<_> = errors
+            .iter()
+            .map(|error| error.split(|c| c == ':' || c == ' ').next().unwrap())
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "genesis_records_file",
+                "network.boot_nodes",
+                "network.boot_nodes",
+                "network.blacklist",
+                "network.max_num_peers",
+                "gc_blocks_limit",
+                "consensus.min_block_production_delay",
+                "metrics_push.address",
+                "rpc.addr",
+            ],
+            "{:?}",
+            errors
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Programmable population of localnet genesis, used by `neard localnet --genesis-spec`.
+//!
+//! A spec is a YAML file describing groups of accounts to create on top of the validators of a
+//! localnet, optionally with a contract deployed to each of them, and arbitrary extra state
+//! records:
+//!
+//! ```yaml
+//! accounts:
+//!   - prefix: user
+//!     count: 10000
+//!     balance: "1000000000000000000000000000"
+//!   - prefix: token
+//!     count: 10
+//!     balance: "1000000000000000000000000000"
+//!     contract: fungible_token.wasm
+//! records: []
+//! compress_records: true
+//! ```
+//!
+//! Every created account gets a full access key derived from the seed equal to its account id,
+//! i.e. `InMemorySigner::from_seed(account_id, KeyType::ED25519, account_id)`, so load-testing
+//! tools can sign transactions without distributing key files.
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use near_chain_configs::Genesis;
+use near_crypto::{InMemorySigner, KeyType};
+use near_primitives::account::{AccessKey, Account};
+use near_primitives::hash::hash;
+use near_primitives::serialize::u128_dec_format_compatible;
+use near_primitives::state_record::StateRecord;
+use near_primitives::types::{AccountId, Balance};
+
+/// Name of the file with genesis records written next to `genesis.json` when
+/// [`GenesisSpec::compress_records`] is set.
+pub const GENESIS_RECORDS_FILENAME: &str = "genesis_records.json.xz";
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisSpec {
+    #[serde(default)]
+    pub accounts: Vec<AccountsSpec>,
+    /// Records added to genesis as they are, e.g. contract data of the created accounts.
+    #[serde(default)]
+    pub records: Vec<StateRecord>,
+    /// Whether records are written xz-compressed into a separate [`GENESIS_RECORDS_FILENAME`]
+    /// file instead of `genesis.json`.
+    #[serde(default)]
+    pub compress_records: bool,
+}
+
+/// Group of accounts named `{prefix}{index}` for indices in `0..count`.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AccountsSpec {
+    pub prefix: String,
+    pub count: u64,
+    #[serde(with = "u128_dec_format_compatible")]
+    pub balance: Balance,
+    /// Path of the wasm code deployed to every account of the group.  Relative paths are resolved
+    /// against the directory of the spec file.
+    #[serde(default)]
+    pub contract: Option<PathBuf>,
+}
+
+impl GenesisSpec {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open genesis spec {}", path.display()))?;
+        let mut spec: GenesisSpec = serde_yaml::from_reader(file)
+            .with_context(|| format!("Failed to parse genesis spec {}", path.display()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for accounts in &mut spec.accounts {
+            if let Some(contract) = &mut accounts.contract {
+                *contract = dir.join(&contract);
+            }
+        }
+        Ok(spec)
+    }
+
+    /// Adds the accounts and records of the spec to the genesis and updates its total supply.
+    pub fn populate(&self, genesis: &mut Genesis) -> anyhow::Result<()> {
+        let records = &mut genesis.records.0;
+        let mut total_supply = genesis.config.total_supply;
+        for accounts in &self.accounts {
+            let code = match &accounts.contract {
+                Some(path) => Some(
+                    std::fs::read(path)
+                        .with_context(|| format!("Could not read contract {}", path.display()))?,
+                ),
+                None => None,
+            };
+            let code_hash = code.as_deref().map(hash).unwrap_or_default();
+            for index in 0..accounts.count {
+                let account_id: AccountId = format!("{}{}", accounts.prefix, index)
+                    .parse()
+                    .with_context(|| format!("Invalid account id {}{}", accounts.prefix, index))?;
+                let signer = InMemorySigner::from_seed(
+                    account_id.clone(),
+                    KeyType::ED25519,
+                    account_id.as_ref(),
+                );
+                records.push(StateRecord::Account {
+                    account_id: account_id.clone(),
+                    account: Account::new(accounts.balance, 0, code_hash, 0),
+                });
+                records.push(StateRecord::AccessKey {
+                    account_id: account_id.clone(),
+                    public_key: signer.public_key,
+                    access_key: AccessKey::full_access(),
+                });
+                if let Some(code) = &code {
+                    records.push(StateRecord::Contract { account_id, code: code.clone() });
+                }
+                total_supply = match total_supply.checked_add(accounts.balance) {
+                    Some(total_supply) => total_supply,
+                    None => bail!("Total supply overflows"),
+                };
+            }
+        }
+        for record in &self.records {
+            if let StateRecord::Account { account, .. } = record {
+                total_supply = match total_supply.checked_add(account.amount() + account.locked()) {
+                    Some(total_supply) => total_supply,
+                    None => bail!("Total supply overflows"),
+                };
+            }
+            records.push(record.clone());
+        }
+        genesis.config.total_supply = total_supply;
+        Ok(())
+    }
+}
+
+/// Writes genesis records as an xz-compressed JSON array.
+pub fn write_compressed_records(records: &[StateRecord], path: &Path) -> anyhow::Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    let mut encoder = xz2::write::XzEncoder::new(BufWriter::new(file), 6);
+    serde_json::to_writer(&mut encoder, records)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain_configs::genesis_validate::validate_genesis;
+    use near_chain_configs::Genesis;
+
+    use super::GenesisSpec;
+    use crate::config::GenesisExt;
+
+    #[test]
+    fn test_populate_genesis() {
+        let spec: GenesisSpec = serde_yaml::from_str(
+            r#"
+accounts:
+  - prefix: user
+    count: 100
+    balance: "1000"
+"#,
+        )
+        .unwrap();
+        let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        let total_supply = genesis.config.total_supply;
+        let num_records = genesis.records.0.len();
+        spec.populate(&mut genesis).unwrap();
+        assert_eq!(genesis.config.total_supply, total_supply + 100 * 1000);
+        // An account and an access key per account.
+        assert_eq!(genesis.records.0.len(), num_records + 200);
+        validate_genesis(&genesis);
+    }
+}
@@ -0,0 +1,60 @@
+//! Building the index of accounts by contract code hash from the state.
+//!
+//! The index is updated whenever a chunk deploying contracts is applied, so a node only needs to
+//! build it for the contracts deployed before the node started applying chunks, e.g. because it
+//! state synced or was upgraded from a version without the index.
+use std::path::Path;
+
+use near_chain::{ChainStore, ChainStoreAccess, RuntimeAdapter};
+use near_primitives::types::ShardId;
+use near_store::{create_store, set_contract_account, AccountFilter, AccountIterator};
+use tracing::info;
+
+use crate::{get_store_path, store_path_exists, NearConfig, NightshadeRuntime};
+
+/// Number of accounts indexed in one batch.
+const BATCH_SIZE: u64 = 10_000;
+
+/// Indexes the accounts with contracts in the state of the tracked shards as of the head of the
+/// chain in the database in `home_dir`.  Returns the number of indexed accounts.
+pub fn index_contracts(home_dir: &Path, near_config: &NearConfig) -> anyhow::Result<u64> {
+    let store_path = get_store_path(home_dir);
+    anyhow::ensure!(
+        store_path_exists(&store_path),
+        "{}: storage doesn’t exist",
+        store_path.display()
+    );
+    let store = create_store(&store_path);
+    let runtime = NightshadeRuntime::with_config(home_dir, store.clone(), near_config, None, None);
+    let genesis_height = near_config.genesis.config.genesis_height;
+    let mut chain_store = ChainStore::new(store.clone(), genesis_height);
+    let head = chain_store.head()?;
+    let header = chain_store.get_block_header(&head.last_block_hash)?.clone();
+
+    info!("Indexing contracts as of the block {} at height {}", head.last_block_hash, head.height);
+    let filter = AccountFilter { has_contract: Some(true), ..Default::default() };
+    let mut num_indexed = 0;
+    let mut store_update = store.store_update();
+    for shard_uid in runtime.get_shard_layout(&head.epoch_id)?.get_shard_uids() {
+        let shard_id = shard_uid.shard_id as ShardId;
+        if !runtime.cares_about_shard(None, header.prev_hash(), shard_id, false) {
+            continue;
+        }
+        let state_root =
+            *chain_store.get_chunk_extra(&head.last_block_hash, &shard_uid)?.state_root();
+        let trie = runtime.get_tries().get_trie_for_shard(shard_uid);
+        for item in AccountIterator::new(&trie, &state_root, filter.clone())? {
+            let (account_id, account) = item?;
+            set_contract_account(&mut store_update, &account.code_hash(), &account_id);
+            num_indexed += 1;
+            if num_indexed % BATCH_SIZE == 0 {
+                std::mem::replace(&mut store_update, store.store_update()).commit()?;
+                info!("Indexed {} accounts", num_indexed);
+            }
+        }
+        info!("Indexed the contracts in shard {}", shard_id);
+    }
+    store_update.commit()?;
+    info!("Done; indexed {} accounts", num_indexed);
+    Ok(num_indexed)
+}
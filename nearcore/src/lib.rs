@@ -1,21 +1,29 @@
+pub use crate::check_chunks::{check_chunks, BadChunk, ChunkProblem};
 pub use crate::config::{init_configs, load_config, load_test_config, NearConfig, NEAR_BASE};
+pub use crate::index_contracts::index_contracts;
+pub use crate::localnet::{start_localnet, start_localnet_with_configs};
 use crate::migrations::{
     migrate_12_to_13, migrate_18_to_19, migrate_19_to_20, migrate_22_to_23, migrate_23_to_24,
     migrate_24_to_25, migrate_30_to_31,
 };
+pub use crate::rc_audit::rc_audit;
 pub use crate::runtime::NightshadeRuntime;
 pub use crate::shard_tracker::TrackedConfig;
+pub use crate::startup_check::{Inconsistency, StartupCheck};
+pub use crate::trim::trim_database;
 use actix::{Actor, Addr, Arbiter};
 use actix_rt::ArbiterHandle;
 use actix_web;
 use anyhow::Context;
-use near_chain::ChainGenesis;
+use near_chain::{ChainGenesis, RuntimeAdapter};
 #[cfg(feature = "test_features")]
 use near_client::AdversarialControls;
 use near_client::{start_client, start_view_client, ClientActor, ViewClientActor};
 use near_network::routing::start_routing_table_actor;
 use near_network::test_utils::NetworkRecipient;
 use near_network::PeerManagerActor;
+use near_network_primitives::types::PeerClientVersion;
+use near_performance_metrics::runtime_stats::{runtime_heartbeat, start_watchdog};
 use near_primitives::network::PeerId;
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::start_rosetta_rpc;
@@ -29,20 +37,30 @@ use near_store::migrations::{
     migrate_21_to_22, migrate_25_to_26, migrate_26_to_27, migrate_28_to_29, migrate_29_to_30,
     migrate_6_to_7, migrate_7_to_8, migrate_8_to_9, migrate_9_to_10, set_store_version,
 };
-use near_store::{create_store, create_store_with_config, Store, StoreConfig};
+use near_store::{create_store, create_store_with_config, Store, StoreConfig, StoreResources};
 use near_telemetry::TelemetryActor;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::oneshot;
-use tracing::{error, info, trace};
+use tracing::{error, info, trace, warn};
 
 pub mod append_only_map;
+pub mod chain_params;
+mod check_chunks;
 pub mod config;
+pub mod config_validate;
+pub mod genesis_spec;
+mod index_contracts;
+mod localnet;
 mod metrics;
 pub mod migrations;
+mod rc_audit;
+pub mod remote_signer;
 mod runtime;
 mod shard_tracker;
+mod startup_check;
+mod trim;
 
 const STORE_PATH: &str = "data";
 
@@ -76,17 +94,16 @@ pub fn get_default_home() -> PathBuf {
 
 /// Returns the path of the DB checkpoint.
 /// Default location is the same as the database location: `path`.
-fn db_checkpoint_path(path: &Path, near_config: &NearConfig) -> PathBuf {
-    let root_path =
-        if let Some(db_migration_snapshot_path) = &near_config.config.db_migration_snapshot_path {
-            assert!(
-                db_migration_snapshot_path.is_absolute(),
-                "'db_migration_snapshot_path' must be an absolute path to an existing directory."
-            );
-            db_migration_snapshot_path.clone()
-        } else {
-            path.to_path_buf()
-        };
+fn db_checkpoint_path(path: &Path, config: &config::Config) -> PathBuf {
+    let root_path = if let Some(db_migration_snapshot_path) = &config.db_migration_snapshot_path {
+        assert!(
+            db_migration_snapshot_path.is_absolute(),
+            "'db_migration_snapshot_path' must be an absolute path to an existing directory."
+        );
+        db_migration_snapshot_path.clone()
+    } else {
+        path.to_path_buf()
+    };
     root_path.join(DB_CHECKPOINT_NAME)
 }
 
@@ -95,12 +112,12 @@ const DB_CHECKPOINT_NAME: &str = "db_migration_snapshot";
 /// Creates a consistent DB checkpoint and returns its path.
 /// By default it creates checkpoints in the DB directory, but can be overridden by the config.
 fn create_db_checkpoint(path: &Path, near_config: &NearConfig) -> Result<PathBuf, anyhow::Error> {
-    let checkpoint_path = db_checkpoint_path(path, near_config);
+    let checkpoint_path = db_checkpoint_path(path, &near_config.config);
     if checkpoint_path.exists() {
         return Err(anyhow::anyhow!(
             "Detected an existing database migration snapshot: '{}'.\n\
              Probably a database migration got interrupted and your database is corrupted.\n\
-             Please replace the contents of '{}' with data from that checkpoint, delete the checkpoint and try again.",
+             Please run `neard database rollback-migration` to replace the contents of '{}' with data from that checkpoint and try again.",
             checkpoint_path.display(),
             path.display()));
     }
@@ -114,6 +131,93 @@ fn create_db_checkpoint(path: &Path, near_config: &NearConfig) -> Result<PathBuf
     Ok(checkpoint_path)
 }
 
+/// Returns `path` with `suffix` appended to its file name.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Restores the database at `home_dir` from the snapshot created before a database migration,
+/// which is left behind if the migration failed or got interrupted, and deletes the snapshot.
+/// The node mustn't be running.  Returns the DB version of the restored database.
+///
+/// The snapshot is first copied to a staging directory next to the database, which then replaces
+/// the database, so that an interrupted rollback leaves the snapshot intact and can be retried.
+pub fn rollback_store_migration(
+    home_dir: &Path,
+    config: &config::Config,
+) -> anyhow::Result<near_primitives::version::DbVersion> {
+    let path = get_store_path(home_dir);
+    let staging_path = with_suffix(&path, ".rollback");
+    let replaced_path = with_suffix(&path, ".replaced");
+    // A rollback interrupted between the renames below leaves no database, but the replaced one,
+    // which holds the snapshot if it's kept inside the database directory.
+    if !path.exists() && replaced_path.exists() {
+        fs::rename(&replaced_path, &path).with_context(|| {
+            format!("Failed to move '{}' back to '{}'", replaced_path.display(), path.display())
+        })?;
+    }
+    let checkpoint_path = db_checkpoint_path(&path, config);
+    anyhow::ensure!(
+        checkpoint_path.is_dir(),
+        "{}: database migration snapshot doesn’t exist",
+        checkpoint_path.display()
+    );
+    anyhow::ensure!(
+        !RocksDB::is_locked(&path)
+            .with_context(|| format!("Failed to check the lock of '{}'", path.display()))?,
+        "{}: the database is in use, stop the node before rolling back the migration",
+        path.display()
+    );
+
+    info!(target: "near", "Restoring the database from the migration snapshot '{}' into '{}'", checkpoint_path.display(), staging_path.display());
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path).with_context(|| {
+            format!("Failed to remove the staging directory '{}'", staging_path.display())
+        })?;
+    }
+    fs::create_dir_all(&staging_path)?;
+    for entry in fs::read_dir(&checkpoint_path)? {
+        let entry_path = entry?.path();
+        let dst_path = staging_path.join(entry_path.file_name().unwrap());
+        // Snapshot files are hard links unless the snapshot is on a different file system.
+        if fs::hard_link(&entry_path, &dst_path).is_err() {
+            fs::copy(&entry_path, &dst_path).with_context(|| {
+                format!("Failed to copy '{}' to '{}'", entry_path.display(), dst_path.display())
+            })?;
+        }
+    }
+
+    info!(target: "near", "Replacing the database in '{}'", path.display());
+    if replaced_path.exists() {
+        fs::remove_dir_all(&replaced_path)
+            .with_context(|| format!("Failed to remove '{}'", replaced_path.display()))?;
+    }
+    if path.exists() {
+        fs::rename(&path, &replaced_path).with_context(|| {
+            format!("Failed to move '{}' to '{}'", path.display(), replaced_path.display())
+        })?;
+    }
+    fs::rename(&staging_path, &path).with_context(|| {
+        format!("Failed to move '{}' to '{}'", staging_path.display(), path.display())
+    })?;
+
+    // The snapshot is kept inside the database directory by default, in which case it goes with
+    // the replaced database.
+    if checkpoint_path.exists() {
+        fs::remove_dir_all(&checkpoint_path).with_context(|| {
+            format!("Failed to delete the migration snapshot '{}'", checkpoint_path.display())
+        })?;
+    }
+    if replaced_path.exists() {
+        fs::remove_dir_all(&replaced_path).with_context(|| {
+            format!("Failed to delete the replaced database '{}'", replaced_path.display())
+        })?;
+    }
+    Ok(get_store_version(&path))
+}
+
 /// Function checks current version of the database and applies migrations to the database.
 pub fn apply_store_migrations(path: &Path, near_config: &NearConfig) {
     let db_version = get_store_version(path);
@@ -327,6 +431,37 @@ pub fn apply_store_migrations(path: &Path, near_config: &NearConfig) {
         info!(target: "near", "Migrate DB from version 30 to 31");
         migrate_30_to_31(path, &near_config);
     }
+    if db_version <= 31 {
+        // version 31 => 32: add ColContractAccounts
+        // Does not need to do anything since open db with option `create_missing_column_families`
+        info!(target: "near", "Migrate DB from version 31 to 32");
+        let store = create_store(path);
+        set_store_version(&store, 32);
+    }
+    if db_version <= 32 {
+        // version 32 => 33: add ColAccountHistory
+        // Does not need to do anything since open db with option `create_missing_column_families`
+        info!(target: "near", "Migrate DB from version 32 to 33");
+        let store = create_store(path);
+        set_store_version(&store, 33);
+    }
+    if db_version <= 33 {
+        // version 33 => 34: add ColStateWriteIntents
+        // Does not need to do anything since open db with option `create_missing_column_families`
+        info!(target: "near", "Migrate DB from version 33 to 34");
+        let store = create_store(path);
+        set_store_version(&store, 34);
+    }
+    #[cfg(feature = "protocol_feature_inline_small_values")]
+    if db_version <= 34 {
+        // version 34 => 35: trie nodes may be stored as LeafInline and BranchInline
+        // Existing nodes are still readable and rewriting them would change the state roots, so
+        // only the version is bumped. This keeps older binaries, which can't decode the new
+        // nodes, from opening the database.
+        info!(target: "near", "Migrate DB from version 34 to 35");
+        let store = create_store(path);
+        set_store_version(&store, 35);
+    }
 
     #[cfg(feature = "nightly_protocol")]
     {
@@ -362,7 +497,11 @@ pub fn apply_store_migrations(path: &Path, near_config: &NearConfig) {
     }
 }
 
-pub fn init_and_migrate_store(home_dir: &Path, near_config: &NearConfig) -> Store {
+pub fn init_and_migrate_store(
+    home_dir: &Path,
+    near_config: &NearConfig,
+    resources: StoreResources,
+) -> Store {
     let path = get_store_path(home_dir);
     let store_exists = store_path_exists(&path);
     if store_exists {
@@ -373,6 +512,8 @@ pub fn init_and_migrate_store(home_dir: &Path, near_config: &NearConfig) -> Stor
         StoreConfig {
             read_only: false,
             enable_statistics: near_config.config.enable_rocksdb_statistics,
+            resources,
+            async_state_commits: near_config.config.async_state_commits,
         },
     );
     if !store_exists {
@@ -384,22 +525,88 @@ pub fn init_and_migrate_store(home_dir: &Path, near_config: &NearConfig) -> Stor
 pub struct NearNode {
     pub client: Addr<ClientActor>,
     pub view_client: Addr<ViewClientActor>,
+    pub network: Addr<PeerManagerActor>,
     pub arbiters: Vec<ArbiterHandle>,
     pub rpc_servers: Vec<(&'static str, actix_web::dev::Server)>,
 }
 
+/// Applies the per-node choice of the VM executing contracts.
+fn configure_vm_kind(config: &NearConfig) -> anyhow::Result<()> {
+    if let Some(vm_kind) = config.config.vm_kind {
+        if config.validator_signer.is_some() {
+            anyhow::bail!("vm_kind cannot be overridden on a validator node");
+        }
+        if !vm_kind.is_available() {
+            anyhow::bail!("{:?} VM is not supported by this binary", vm_kind);
+        }
+        if near_vm_runner::set_vm_kind_override(vm_kind).is_err() {
+            warn!(target: "near", "VM kind has already been overridden, ignoring {:?}", vm_kind);
+        }
+    }
+    if let Some(vm_kind) = config.config.differential_vm_kind {
+        if !vm_kind.is_available() {
+            anyhow::bail!("{:?} VM is not supported by this binary", vm_kind);
+        }
+        if near_vm_runner::set_differential_vm_kind(vm_kind).is_err() {
+            warn!(target: "near", "Differential VM has already been set, ignoring {:?}", vm_kind);
+        }
+    }
+    Ok(())
+}
+
+/// Starts only the view client, over a read-only store of an archival node, without the client
+/// and network.  Used to read historical data at disk speed, possibly while the node which owns
+/// the store is running.
+pub fn start_read_only_view_client(
+    home_dir: &Path,
+    config: NearConfig,
+) -> Result<Addr<ViewClientActor>, anyhow::Error> {
+    anyhow::ensure!(
+        config.client_config.archive,
+        "reading historical blocks requires an archival node"
+    );
+    configure_vm_kind(&config)?;
+    let store = create_store_with_config(
+        &get_store_path(home_dir),
+        StoreConfig { read_only: true, ..Default::default() },
+    );
+    let runtime = Arc::new(NightshadeRuntime::with_config(
+        home_dir,
+        store,
+        &config,
+        config.client_config.trie_viewer_state_size_limit,
+        config.client_config.max_gas_burnt_view,
+    ));
+    Ok(start_view_client(
+        None,
+        ChainGenesis::from(&config.genesis),
+        runtime,
+        Arc::new(NetworkRecipient::default()),
+        config.client_config,
+        #[cfg(feature = "test_features")]
+        Arc::new(std::sync::RwLock::new(AdversarialControls::default())),
+    ))
+}
+
 pub fn start_with_config(home_dir: &Path, config: NearConfig) -> Result<NearNode, anyhow::Error> {
     start_with_config_and_synchronization(home_dir, config, None)
 }
 
 pub fn start_with_config_and_synchronization(
     home_dir: &Path,
-    config: NearConfig,
+    mut config: NearConfig,
     // 'shutdown_signal' will notify the corresponding `oneshot::Receiver` when an instance of
     // `ClientActor` gets dropped.
     shutdown_signal: Option<oneshot::Sender<()>>,
 ) -> Result<NearNode, anyhow::Error> {
-    let store = init_and_migrate_store(home_dir, &config);
+    configure_vm_kind(&config)?;
+    metrics::export_build_info(&config.client_config.version, &config.genesis.config.chain_id);
+    config.network_config.client_version = PeerClientVersion {
+        version: config.client_config.version.version.clone(),
+        build: config.client_config.version.build.clone(),
+    };
+    let store_resources = StoreResources::detect(&config.config.store_resources);
+    let store = init_and_migrate_store(home_dir, &config, store_resources);
 
     let runtime = Arc::new(NightshadeRuntime::with_config(
         home_dir,
@@ -408,6 +615,25 @@ pub fn start_with_config_and_synchronization(
         config.client_config.trie_viewer_state_size_limit,
         config.client_config.max_gas_burnt_view,
     ));
+    runtime.get_tries().set_cache_capacity(store_resources.trie_cache_capacity);
+    if config.config.startup_check != StartupCheck::Disabled {
+        let me = config.validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+        let inconsistencies = startup_check::check_store(
+            &store,
+            &*runtime,
+            config.genesis.config.genesis_height,
+            me.as_ref(),
+            config.client_config.header_only,
+            config.config.startup_check == StartupCheck::Repair,
+        )?;
+        for inconsistency in inconsistencies.iter() {
+            error!(target: "near", "Store is inconsistent: {}; {}", inconsistency, inconsistency.suggestion());
+        }
+        anyhow::ensure!(inconsistencies.is_empty(), "the store is inconsistent, refusing to start");
+    }
+    if config.config.mem_tries.enabled {
+        runtime.load_mem_tries(&config).context("Failed to load the state into memory")?;
+    }
 
     let telemetry = TelemetryActor::new(config.telemetry_config.clone()).start();
     let chain_genesis = ChainGenesis::from(&config.genesis);
@@ -417,8 +643,29 @@ pub fn start_with_config_and_synchronization(
     #[cfg(feature = "test_features")]
     let adv = Arc::new(std::sync::RwLock::new(AdversarialControls::default()));
 
+    if config.client_config.shadow_validation && config.validator_signer.is_none() {
+        anyhow::bail!("shadow_validation requires a validator key");
+    }
+    if config.client_config.key_rotation.is_some() && config.validator_signer.is_none() {
+        anyhow::bail!("key_rotation requires a validator key");
+    }
+    if config.client_config.header_only {
+        if config.validator_signer.is_some() {
+            anyhow::bail!("header_only nodes cannot have a validator key");
+        }
+        if config.client_config.archive
+            || !config.client_config.tracked_shards.is_empty()
+            || !config.client_config.tracked_accounts.is_empty()
+        {
+            anyhow::bail!("header_only nodes cannot be archival or track shards or accounts");
+        }
+    }
     let view_client = start_view_client(
-        config.validator_signer.as_ref().map(|signer| signer.validator_id().clone()),
+        if config.client_config.shadow_validation {
+            None
+        } else {
+            config.validator_signer.as_ref().map(|signer| signer.validator_id().clone())
+        },
         chain_genesis.clone(),
         runtime.clone(),
         network_adapter.clone(),
@@ -488,7 +735,12 @@ pub fn start_with_config_and_synchronization(
         ));
     }
 
-    network_adapter.set_recipient(network_actor.recipient());
+    network_adapter.set_recipient(network_actor.clone().recipient());
+
+    start_watchdog();
+    actix::spawn(runtime_heartbeat("main"));
+    client_arbiter_handle.spawn(runtime_heartbeat("client"));
+    arbiter.spawn(runtime_heartbeat("network"));
 
     rpc_servers.shrink_to_fit();
 
@@ -501,6 +753,7 @@ pub fn start_with_config_and_synchronization(
     Ok(NearNode {
         client: client_actor,
         view_client,
+        network: network_actor,
         rpc_servers,
         arbiters: vec![client_arbiter_handle, arbiter.handle()],
     })
@@ -543,10 +796,8 @@ pub fn recompress_storage(home_dir: &Path, dst_dir: &Path) -> anyhow::Result<()>
     );
 
     info!("Recompressing data from {} into {}", src_dir.display(), dst_dir.display());
-    let src_store = create_store_with_config(
-        &src_dir,
-        StoreConfig { read_only: true, enable_statistics: false },
-    );
+    let src_store =
+        create_store_with_config(&src_dir, StoreConfig { read_only: true, ..Default::default() });
     let dst_store = create_store(&dst_dir);
 
     const BATCH_SIZE_BYTES: u64 = 150_000_000;
@@ -595,3 +846,37 @@ pub fn recompress_storage(home_dir: &Path, dst_dir: &Path) -> anyhow::Result<()>
     info!("Done; recompressed database at {}", dst_dir.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_store_migration() {
+        let home_dir = tempfile::tempdir().unwrap();
+        let path = get_store_path(home_dir.path());
+        let store = create_store(&path);
+        set_store_version(&store, 30);
+        drop(store);
+        let config = config::Config::default();
+        let checkpoint_path = db_checkpoint_path(&path, &config);
+        RocksDB::new(&path)
+            .unwrap()
+            .checkpoint()
+            .unwrap()
+            .create_checkpoint(&checkpoint_path)
+            .unwrap();
+
+        // A migration which got interrupted half way.
+        set_store_version(&create_store(&path), 31);
+        // And a rollback which got interrupted after moving the database away.
+        fs::create_dir_all(with_suffix(&path, ".rollback").join("junk")).unwrap();
+        fs::rename(&path, with_suffix(&path, ".replaced")).unwrap();
+
+        assert_eq!(rollback_store_migration(home_dir.path(), &config).unwrap(), 30);
+        assert!(!checkpoint_path.exists());
+        assert!(!with_suffix(&path, ".rollback").exists());
+        assert!(!with_suffix(&path, ".replaced").exists());
+        assert!(rollback_store_migration(home_dir.path(), &config).is_err());
+    }
+}
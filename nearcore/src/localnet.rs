@@ -0,0 +1,32 @@
+//! Running all the nodes of a localnet in one process, e.g. to exercise several validators without
+//! starting a process per node.  The nodes talk to each other over the loopback interface, so
+//! their configs must use distinct local ports, as `init_testnet_configs` does with `local_ports`.
+use std::path::PathBuf;
+
+use anyhow::Context;
+use near_chain_configs::GenesisValidationMode;
+
+use crate::{load_config, start_with_config, NearConfig, NearNode};
+
+/// Starts the nodes with the given home directories and configs in the current actix system.
+pub fn start_localnet_with_configs(
+    nodes: Vec<(PathBuf, NearConfig)>,
+) -> anyhow::Result<Vec<NearNode>> {
+    nodes
+        .into_iter()
+        .map(|(home_dir, near_config)| {
+            start_with_config(&home_dir, near_config)
+                .with_context(|| format!("failed to start the node in {}", home_dir.display()))
+        })
+        .collect()
+}
+
+/// Starts the nodes with the configs in the given home directories in the current actix system.
+pub fn start_localnet(home_dirs: &[PathBuf]) -> anyhow::Result<Vec<NearNode>> {
+    start_localnet_with_configs(
+        home_dirs
+            .iter()
+            .map(|home_dir| (home_dir.clone(), load_config(home_dir, GenesisValidationMode::Full)))
+            .collect(),
+    )
+}
@@ -1,4 +1,8 @@
-use near_metrics::{try_create_histogram_vec, HistogramVec};
+use near_metrics::{
+    try_create_histogram_vec, try_create_int_counter_vec, try_create_int_gauge_vec, HistogramVec,
+    IntCounterVec, IntGaugeVec,
+};
+use near_primitives::version::{Version, PROTOCOL_VERSION};
 use once_cell::sync::Lazy;
 
 pub static APPLY_CHUNK_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
@@ -24,3 +28,83 @@ pub static SECONDS_PER_PETAGAS: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub static CHUNK_STATE_WITNESS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_chunk_state_witness_size_bytes",
+        "Total size of the distinct trie nodes and values touched while applying a chunk, i.e. the size of its state witness",
+        &["shard_id"],
+        Some(prometheus::exponential_buckets(1024.0, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+
+pub static DELAYED_RECEIPTS_COUNT: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_delayed_receipts_count",
+        "Number of receipts in the delayed receipts queue of the shard after the last applied chunk",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub static DELAYED_RECEIPTS_GAS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_delayed_receipts_gas",
+        "Total prepaid gas of the receipts in the delayed receipts queue of the shard after the last applied chunk",
+        &["shard_id"],
+    )
+    .unwrap()
+});
+
+pub static REMOTE_SIGNER_REQUEST_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_remote_signer_request_time_seconds",
+        "Time to obtain a signature from the remote signer, by kind of the signed data",
+        &["kind"],
+        Some(prometheus::exponential_buckets(0.001, 2.0, 12).unwrap()),
+    )
+    .unwrap()
+});
+
+pub static REMOTE_SIGNER_FAILURES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_remote_signer_failures_total",
+        "Number of signatures which were refused or couldn't be obtained from the remote signer",
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub static BUILD_INFO: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_build_info",
+        "Always 1; the labels describe the running binary and the chain it is connected to",
+        &["version", "build", "rustc_version", "protocol_version", "features", "chain_id"],
+    )
+    .unwrap()
+});
+
+/// Exports the version of the binary, the cargo features it was built with which change the
+/// behavior of the node, and the chain id.
+pub(crate) fn export_build_info(version: &Version, chain_id: &str) {
+    let features: Vec<&str> = [
+        ("sandbox", cfg!(feature = "sandbox")),
+        ("nightly_protocol", cfg!(feature = "nightly_protocol")),
+        ("nightly_protocol_features", cfg!(feature = "nightly_protocol_features")),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| *feature)
+    .collect();
+    BUILD_INFO
+        .with_label_values(&[
+            &version.version,
+            &version.build,
+            &version.rustc_version,
+            &PROTOCOL_VERSION.to_string(),
+            &features.join(","),
+            chain_id,
+        ])
+        .set(1);
+}
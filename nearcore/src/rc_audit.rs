@@ -0,0 +1,94 @@
+//! Auditing the reference counts of the State column against the state of the tracked shards.
+//!
+//! The expected reference counts are those of the state as of the head of the chain plus the
+//! trie changes of the blocks which aren't garbage collected yet.  The state left behind by
+//! garbage collection after state sync, whose trie changes are dropped without being applied,
+//! shows up as references in excess.
+use std::path::Path;
+
+use borsh::BorshDeserialize;
+use near_chain::{ChainStore, ChainStoreAccess, RuntimeAdapter};
+use near_primitives::shard_layout::get_block_shard_uid_rev;
+use near_primitives::types::ShardId;
+use near_store::{
+    create_store_with_config, DBCol, RefcountAudit, RefcountMismatch, StoreConfig, TrieChanges,
+};
+use tracing::{info, warn};
+
+use crate::{get_store_path, store_path_exists, NearConfig, NightshadeRuntime};
+
+/// Recomputes the reference counts of the nodes and values in the State column of the database
+/// in `home_dir` for the shards tracked as of the head of the chain and returns those which
+/// differ from the stored ones.  If `repair` is set, the stored reference counts are replaced
+/// by the expected ones.
+pub fn rc_audit(
+    home_dir: &Path,
+    near_config: &NearConfig,
+    repair: bool,
+) -> anyhow::Result<Vec<RefcountMismatch>> {
+    let store_path = get_store_path(home_dir);
+    anyhow::ensure!(
+        store_path_exists(&store_path),
+        "{}: storage doesn’t exist",
+        store_path.display()
+    );
+    let store = create_store_with_config(
+        &store_path,
+        StoreConfig { read_only: !repair, ..Default::default() },
+    );
+    let runtime = NightshadeRuntime::with_config(home_dir, store.clone(), near_config, None, None);
+    let genesis_height = near_config.genesis.config.genesis_height;
+    let mut chain_store = ChainStore::new(store.clone(), genesis_height);
+    let head = chain_store.head()?;
+    let header = chain_store.get_block_header(&head.last_block_hash)?.clone();
+
+    info!("Counting the references of the state as of the block {}", head.last_block_hash);
+    let mut audit = RefcountAudit::new();
+    let mut shard_uids = vec![];
+    for shard_uid in runtime.get_shard_layout(&head.epoch_id)?.get_shard_uids() {
+        let shard_id = shard_uid.shard_id as ShardId;
+        if !runtime.cares_about_shard(None, header.prev_hash(), shard_id, false) {
+            continue;
+        }
+        let state_root =
+            *chain_store.get_chunk_extra(&head.last_block_hash, &shard_uid)?.state_root();
+        audit.add_state(&store, shard_uid, &state_root)?;
+        shard_uids.push(shard_uid);
+        info!("Counted the references of the state of shard {}", shard_id);
+    }
+
+    // With compaction based GC the trie changes of garbage collected canonical blocks stay around
+    // until they are compacted away.
+    let tail = chain_store.tail()?;
+    let mut num_trie_changes = 0;
+    for (key, value) in store.iter(DBCol::ColTrieChanges) {
+        let (block_hash, shard_uid) = get_block_shard_uid_rev(&key)
+            .map_err(|err| anyhow::anyhow!("invalid trie changes key: {}", err))?;
+        if !shard_uids.contains(&shard_uid) {
+            continue;
+        }
+        let trie_changes = TrieChanges::try_from_slice(&value)?;
+        let height = match chain_store.get_block_header(&block_hash) {
+            Ok(header) => header.height(),
+            Err(err) => {
+                warn!("Skipping the trie changes of the block {}: {}", block_hash, err);
+                continue;
+            }
+        };
+        let canonical_hash = chain_store.get_block_hash_by_height(height).ok();
+        if canonical_hash == Some(block_hash) {
+            if height <= tail {
+                continue;
+            }
+            audit.add_unapplied_deletions(shard_uid, &trie_changes);
+        } else {
+            audit.add_unreverted_insertions(shard_uid, &trie_changes);
+        }
+        num_trie_changes += 1;
+    }
+    info!("Counted the references of {} trie changes", num_trie_changes);
+
+    let mismatches = audit.compare(&store, repair)?;
+    info!("Done; found {} mismatching reference counts", mismatches.len());
+    Ok(mismatches)
+}
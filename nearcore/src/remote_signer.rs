@@ -0,0 +1,404 @@
+//! Validator signer which delegates signing to an external service, e.g. one backed by an HSM,
+//! so that the validator key never has to be stored on the node host.  Enabled by setting
+//! `remote_signer` in `config.json`.
+//!
+//! For every signature the node sends a `POST` request with a JSON body to the configured URL:
+//!
+//! ```json
+//! {
+//!   "account_id": "validator.near",
+//!   "public_key": "ed25519:...",
+//!   "kind": "approval",
+//!   "height": 1000,
+//!   "data": "<base64 of the bytes to sign>"
+//! }
+//! ```
+//!
+//! and expects `{"signature": "ed25519:..."}` in response.  `kind` is one of `block_header`,
+//! `chunk`, `approval`, `challenge`, `account_announce`, `telemetry` and `vrf`; `height` is only
+//! set for block headers and approvals, so that the service can apply its own slashing
+//! protection.  The response to a `vrf` request is `{"vrf_value": "<base64>", "vrf_proof":
+//! "<base64>"}` instead.
+//!
+//! Before sending a request the node refuses to sign a block header or an approval at a height
+//! not above the last one it has signed, which it persists in `remote_signer_state.json` in the
+//! home directory first, so that a restart can't make it sign twice at the same height.  It
+//! verifies every signature it gets back.  If a signature is refused or not obtained within
+//! `timeout`, the block, chunk or approval isn't produced.  Approvals and blocks are signed off
+//! the client actor thread, so it keeps processing blocks while waiting for the service.
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, ensure, Context};
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use near_crypto::key_conversion::convert_public_key;
+use near_crypto::vrf::{Proof, Value};
+use near_crypto::{PublicKey, Signature};
+use near_primitives::block::{Approval, ApprovalInner, BlockHeader, BlockHeaderInnerLite};
+use near_primitives::challenge::ChallengeBody;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::serialize::{from_base64, to_base64};
+use near_primitives::sharding::ChunkHash;
+use near_primitives::telemetry::TelemetryInfo;
+use near_primitives::types::{AccountId, BlockHeight, EpochId};
+use near_primitives::validator_signer::{SignerError, ValidatorSigner};
+
+use crate::metrics;
+
+/// File in the home directory the heights signed by the remote signer are persisted to.
+pub const REMOTE_SIGNER_STATE_FILENAME: &str = "remote_signer_state.json";
+
+fn default_remote_signer_timeout() -> Duration {
+    Duration::from_millis(300)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RemoteSignerConfig {
+    /// URL of the signing service.
+    pub url: String,
+    /// Account id of the validator.
+    pub account_id: AccountId,
+    /// Validator key held by the signing service, signatures are checked against it.
+    pub public_key: PublicKey,
+    /// Latency budget of a single signature.
+    #[serde(default = "default_remote_signer_timeout")]
+    pub timeout: Duration,
+}
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    account_id: &'a AccountId,
+    public_key: &'a PublicKey,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    height: Option<BlockHeight>,
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    #[serde(default)]
+    signature: Option<Signature>,
+    #[serde(default)]
+    vrf_value: Option<String>,
+    #[serde(default)]
+    vrf_proof: Option<String>,
+}
+
+/// Request to the signing service together with the channel to send the response body to.
+type Job = (hyper::Request<hyper::Body>, mpsc::SyncSender<anyhow::Result<Vec<u8>>>);
+
+/// Local slashing protection: refuses to sign a block header or an approval at a height which is
+/// not above the highest one signed so far.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+struct SigningPolicy {
+    last_block_height: Option<BlockHeight>,
+    last_approval_height: Option<BlockHeight>,
+}
+
+impl SigningPolicy {
+    fn check_block(&mut self, height: BlockHeight) -> anyhow::Result<()> {
+        check_height(&mut self.last_block_height, height)
+    }
+
+    fn check_approval(&mut self, target_height: BlockHeight) -> anyhow::Result<()> {
+        check_height(&mut self.last_approval_height, target_height)
+    }
+}
+
+fn check_height(last: &mut Option<BlockHeight>, height: BlockHeight) -> anyhow::Result<()> {
+    if let Some(last) = *last {
+        ensure!(height > last, "height {} is not above the last signed height {}", height, last);
+    }
+    *last = Some(height);
+    Ok(())
+}
+
+/// [`SigningPolicy`] persisted to a file, which is written before any signature is requested.
+struct PersistedPolicy {
+    path: PathBuf,
+    policy: SigningPolicy,
+}
+
+impl PersistedPolicy {
+    fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let policy = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("malformed {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => SigningPolicy::default(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+        Ok(Self { path, policy })
+    }
+
+    /// Applies the check to a copy of the policy and persists the copy if it passes, so that the
+    /// height is only considered signed once the file is durably written.
+    fn update(
+        &mut self,
+        check: impl FnOnce(&mut SigningPolicy) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let mut policy = self.policy.clone();
+        check(&mut policy)?;
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut file = std::fs::File::create(&tmp_path)?;
+            serde_json::to_writer(&mut file, &policy)?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        self.policy = policy;
+        Ok(())
+    }
+}
+
+/// Signer that sends everything to sign to a remote signing service, see the module docs.
+pub struct RemoteValidatorSigner {
+    config: RemoteSignerConfig,
+    jobs: Mutex<mpsc::Sender<Job>>,
+    policy: Mutex<PersistedPolicy>,
+}
+
+impl RemoteValidatorSigner {
+    pub fn new(config: RemoteSignerConfig, home_dir: &Path) -> anyhow::Result<Self> {
+        ensure!(
+            matches!(config.public_key, PublicKey::ED25519(_)),
+            "remote signer supports only ed25519 validator keys"
+        );
+        let policy = PersistedPolicy::load(home_dir.join(REMOTE_SIGNER_STATE_FILENAME))?;
+        let jobs = start_worker(config.timeout)?;
+        Ok(Self { config, jobs: Mutex::new(jobs), policy: Mutex::new(policy) })
+    }
+
+    fn request(
+        &self,
+        kind: &'static str,
+        height: Option<BlockHeight>,
+        data: &[u8],
+    ) -> anyhow::Result<SignResponse> {
+        let _timer = metrics::REMOTE_SIGNER_REQUEST_TIME.with_label_values(&[kind]).start_timer();
+        let body = serde_json::to_vec(&SignRequest {
+            account_id: &self.config.account_id,
+            public_key: &self.config.public_key,
+            kind,
+            height,
+            data: to_base64(data),
+        })?;
+        let request = hyper::Request::post(&self.config.url)
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(hyper::Body::from(body))?;
+        let (response_tx, response_rx) = mpsc::sync_channel(1);
+        self.jobs
+            .lock()
+            .unwrap()
+            .send((request, response_tx))
+            .map_err(|_| anyhow!("remote signer worker stopped"))?;
+        let body = match response_rx.recv_timeout(self.config.timeout) {
+            Ok(body) => body?,
+            Err(_) => bail!("no response within {:?}", self.config.timeout),
+        };
+        serde_json::from_slice(&body).context("malformed response")
+    }
+
+    fn sign(
+        &self,
+        kind: &'static str,
+        height: Option<BlockHeight>,
+        data: &[u8],
+    ) -> Result<Signature, SignerError> {
+        let result = self.request(kind, height, data).and_then(|response| {
+            let signature = response.signature.context("response without signature")?;
+            ensure!(signature.verify(data, &self.config.public_key), "invalid signature");
+            Ok(signature)
+        });
+        result.map_err(|err| self.report_failure(kind, err))
+    }
+
+    /// Signs the header or approval at `height` if `check` lets the policy sign it.
+    fn sign_at_height(
+        &self,
+        kind: &'static str,
+        height: BlockHeight,
+        data: &[u8],
+        check: impl FnOnce(&mut SigningPolicy) -> anyhow::Result<()>,
+    ) -> Result<Signature, SignerError> {
+        self.policy.lock().unwrap().update(check).map_err(|err| self.report_failure(kind, err))?;
+        self.sign(kind, Some(height), data)
+    }
+
+    fn report_failure(&self, kind: &str, err: anyhow::Error) -> SignerError {
+        error!(target: "near", "Remote signer failed to sign {}: {:#}", kind, err);
+        metrics::REMOTE_SIGNER_FAILURES_TOTAL.with_label_values(&[kind]).inc();
+        SignerError(format!("{} via the remote signer: {:#}", kind, err))
+    }
+
+    fn compute_vrf(&self, data: &[u8]) -> anyhow::Result<(Value, Proof)> {
+        let response = self.request("vrf", None, data)?;
+        let decode = |field: Option<String>| {
+            from_base64(&field.context("response without vrf output")?)
+                .map_err(|err| anyhow!("malformed vrf output: {}", err))
+        };
+        let value = Value::try_from(decode(response.vrf_value)?.as_slice())
+            .map_err(|_| anyhow!("malformed vrf value"))?;
+        let proof = Proof::try_from(decode(response.vrf_proof)?.as_slice())
+            .map_err(|_| anyhow!("malformed vrf proof"))?;
+        let public_key = convert_public_key(self.config.public_key.unwrap_as_ed25519())
+            .context("invalid validator key")?;
+        ensure!(public_key.is_vrf_valid(&data, &value, &proof), "invalid vrf proof");
+        Ok((value, proof))
+    }
+}
+
+/// Starts the thread which sends the requests to the signing service.  The signer is called
+/// synchronously, so the requests are executed on a runtime of their own.
+fn start_worker(timeout: Duration) -> anyhow::Result<mpsc::Sender<Job>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to start remote signer runtime")?;
+    let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+    std::thread::Builder::new().name("remote_signer".to_string()).spawn(move || {
+        let client =
+            hyper::Client::builder().build::<_, hyper::Body>(hyper_tls::HttpsConnector::new());
+        for (request, response_tx) in jobs_rx {
+            let response = runtime.block_on(async {
+                let response = tokio::time::timeout(timeout, client.request(request))
+                    .await
+                    .map_err(|_| anyhow!("no response within {:?}", timeout))??;
+                let status = response.status();
+                let body = hyper::body::to_bytes(response.into_body()).await?;
+                ensure!(status.is_success(), "signing service responded with {}", status);
+                Ok::<_, anyhow::Error>(body.to_vec())
+            });
+            // The caller may have given up waiting already.
+            let _ = response_tx.send(response);
+        }
+    })?;
+    Ok(jobs_tx)
+}
+
+impl ValidatorSigner for RemoteValidatorSigner {
+    fn validator_id(&self) -> &AccountId {
+        &self.config.account_id
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.config.public_key.clone()
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    /// Telemetry, challenges and account announcements are sent with an empty signature if the
+    /// service fails, so they are rejected by their recipients.
+    fn sign_telemetry(&self, info: &TelemetryInfo) -> serde_json::Value {
+        let mut value = serde_json::to_value(info).expect("Telemetry must serialize to JSON");
+        let content = serde_json::to_string(&value).expect("Telemetry must serialize to JSON");
+        let signature = self.sign("telemetry", None, content.as_bytes()).unwrap_or_default();
+        value["signature"] = format!("{}", signature).into();
+        value
+    }
+
+    fn sign_block_header_parts(
+        &self,
+        prev_hash: CryptoHash,
+        inner_lite: &[u8],
+        inner_rest: &[u8],
+    ) -> Result<(CryptoHash, Signature), SignerError> {
+        let hash = BlockHeader::compute_hash(prev_hash, inner_lite, inner_rest);
+        let height = BlockHeaderInnerLite::try_from_slice(inner_lite)
+            .map_err(|err| self.report_failure("block_header", err.into()))?
+            .height;
+        let signature = self.sign_at_height("block_header", height, hash.as_ref(), |policy| {
+            policy.check_block(height)
+        })?;
+        Ok((hash, signature))
+    }
+
+    fn sign_chunk_hash(&self, chunk_hash: &ChunkHash) -> Result<Signature, SignerError> {
+        self.sign("chunk", None, chunk_hash.as_ref())
+    }
+
+    fn sign_approval(
+        &self,
+        inner: &ApprovalInner,
+        target_height: BlockHeight,
+    ) -> Result<Signature, SignerError> {
+        let data = Approval::get_data_for_sig(inner, target_height);
+        self.sign_at_height("approval", target_height, &data, |policy| {
+            policy.check_approval(target_height)
+        })
+    }
+
+    fn sign_challenge(&self, challenge_body: &ChallengeBody) -> (CryptoHash, Signature) {
+        let hash = hash(&challenge_body.try_to_vec().expect("Failed to serialize"));
+        (hash, self.sign("challenge", None, hash.as_ref()).unwrap_or_default())
+    }
+
+    fn sign_account_announce(
+        &self,
+        account_id: &AccountId,
+        peer_id: &PeerId,
+        epoch_id: &EpochId,
+    ) -> Signature {
+        let hash = AnnounceAccount::build_header_hash(account_id, peer_id, epoch_id);
+        self.sign("account_announce", None, hash.as_ref()).unwrap_or_default()
+    }
+
+    fn compute_vrf_with_proof(&self, data: &[u8]) -> Result<(Value, Proof), SignerError> {
+        self.compute_vrf(data).map_err(|err| self.report_failure("vrf", err))
+    }
+
+    fn write_to_file(&self, _path: &Path) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "the validator key is held by the remote signer",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PersistedPolicy, SigningPolicy};
+
+    #[test]
+    fn test_signing_policy() {
+        let mut policy = SigningPolicy::default();
+        policy.check_block(10).unwrap();
+        assert!(policy.check_block(10).is_err());
+        assert!(policy.check_block(9).is_err());
+        policy.check_block(12).unwrap();
+
+        // Approvals are tracked separately from blocks.
+        policy.check_approval(11).unwrap();
+        assert!(policy.check_approval(11).is_err());
+        policy.check_approval(13).unwrap();
+    }
+
+    #[test]
+    fn test_persisted_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(super::REMOTE_SIGNER_STATE_FILENAME);
+        let mut policy = PersistedPolicy::load(path.clone()).unwrap();
+        policy.update(|policy| policy.check_block(10)).unwrap();
+        policy.update(|policy| policy.check_approval(11)).unwrap();
+        // A refused height is neither persisted nor remembered.
+        assert!(policy.update(|policy| policy.check_block(9)).is_err());
+        assert_eq!(policy.policy.last_block_height, Some(10));
+
+        // After a restart the heights signed before are still refused.
+        let mut policy = PersistedPolicy::load(path).unwrap();
+        assert!(policy.update(|policy| policy.check_block(10)).is_err());
+        assert!(policy.update(|policy| policy.check_approval(11)).is_err());
+        policy.update(|policy| policy.check_block(12)).unwrap();
+    }
+}
@@ -62,6 +62,41 @@ impl QueryError {
         }
     }
 
+    pub fn from_view_abi_error(
+        error: node_runtime::state_viewer::errors::ViewAbiError,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    ) -> Self {
+        match error {
+            node_runtime::state_viewer::errors::ViewAbiError::InvalidAccountId {
+                requested_account_id,
+            } => Self::InvalidAccount { requested_account_id, block_height, block_hash },
+            node_runtime::state_viewer::errors::ViewAbiError::AccountDoesNotExist {
+                requested_account_id,
+            } => Self::UnknownAccount { requested_account_id, block_height, block_hash },
+            node_runtime::state_viewer::errors::ViewAbiError::NoContractCode {
+                contract_account_id,
+            } => Self::NoContractCode { contract_account_id, block_height, block_hash },
+            node_runtime::state_viewer::errors::ViewAbiError::NoContractAbi {
+                contract_account_id,
+            } => Self::NoContractAbi { contract_account_id, block_height, block_hash },
+            node_runtime::state_viewer::errors::ViewAbiError::AbiHashMismatch {
+                contract_account_id,
+                abi_hash,
+                declared_hash,
+            } => Self::AbiHashMismatch {
+                contract_account_id,
+                abi_hash,
+                declared_hash,
+                block_height,
+                block_hash,
+            },
+            node_runtime::state_viewer::errors::ViewAbiError::InternalError { error_message } => {
+                Self::InternalError { error_message, block_height, block_hash }
+            }
+        }
+    }
+
     pub fn from_view_state_error(
         error: node_runtime::state_viewer::errors::ViewStateError,
         block_height: near_primitives::types::BlockHeight,
@@ -80,6 +115,9 @@ impl QueryError {
             node_runtime::state_viewer::errors::ViewStateError::AccountStateTooLarge {
                 requested_account_id,
             } => Self::TooLargeContractState { requested_account_id, block_height, block_hash },
+            node_runtime::state_viewer::errors::ViewStateError::Timeout {
+                requested_account_id,
+            } => Self::QueryTimeout { requested_account_id, block_height, block_hash },
         }
     }
 
@@ -2,11 +2,12 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
 
 use borsh::ser::BorshSerialize;
 use borsh::BorshDeserialize;
+use once_cell::sync::Lazy;
 use tracing::{debug, error, info, warn};
 
 use near_chain::chain::NUM_EPOCHS_TO_KEEP_STORE_DATA;
@@ -14,12 +15,15 @@ use near_chain::types::{
     ApplySplitStateResult, ApplyTransactionResult, BlockHeaderInfo, ValidatorInfoIdentifier,
 };
 use near_chain::{BlockHeader, Doomslug, DoomslugThresholdMode, Error, ErrorKind, RuntimeAdapter};
-use near_chain_configs::{Genesis, GenesisConfig, ProtocolConfig};
+use near_chain_configs::{
+    apply_runtime_config_overrides, load_runtime_config_overrides, Genesis, GenesisConfig,
+    ProtocolConfig,
+};
 use near_crypto::{PublicKey, Signature};
 use near_epoch_manager::EpochManager;
 use near_pool::types::PoolIterator;
 use near_primitives::account::{AccessKey, Account};
-use near_primitives::block::{Approval, ApprovalInner};
+use near_primitives::block::{Approval, ApprovalInner, Tip};
 use near_primitives::challenge::ChallengesResult;
 use near_primitives::contract::ContractCode;
 use near_primitives::epoch_manager::block_info::BlockInfo;
@@ -27,10 +31,13 @@ use near_primitives::epoch_manager::epoch_info::EpochInfo;
 use near_primitives::epoch_manager::{EpochConfig, ShardConfig};
 use near_primitives::errors::{EpochError, InvalidTxError, RuntimeError};
 use near_primitives::hash::{hash, CryptoHash};
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
+use near_primitives::runtime::config::RuntimeConfig;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::state_record::{state_record_to_account_id, StateRecord};
 use near_primitives::transaction::SignedTransaction;
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::validator_stake::{ValidatorStake, ValidatorStakeIter};
 use near_primitives::types::{
     AccountId, ApprovalStake, Balance, BlockHeight, CompiledContractCache, EpochHeight, EpochId,
@@ -39,16 +46,19 @@ use near_primitives::types::{
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, EpochValidatorInfo, QueryRequest, QueryResponse,
+    AccessKeyInfoView, CallResult, ContractAbiView, EpochRewardProjectionView, EpochValidatorInfo,
+    EpochValidatorProjectionView, ProtocolVersionVotesView, QueryRequest, QueryResponse,
     QueryResponseKind, ViewApplyState, ViewStateResult,
 };
 use near_store::{
-    get_genesis_hash, get_genesis_state_roots, set_genesis_hash, set_genesis_state_roots,
-    ApplyStatePartResult, ColState, PartialStorage, ShardTries, Store, StoreCompiledContractCache,
-    StoreUpdate, Trie, WrappedTrieChanges,
+    create_compiled_contract_cache, get, get_delayed_receipt_indices, get_genesis_hash,
+    get_genesis_state_roots, set_genesis_hash, set_genesis_state_roots, ApplyStatePartResult,
+    ColState, DBCol, PartialStorage, ShardTries, StorageError, Store, StoreCompiledContractCache,
+    StoreUpdate, Trie, WrappedTrieChanges, HEAD_KEY,
 };
 use near_vm_runner::precompile_contract;
 use node_runtime::adapter::ViewRuntimeAdapter;
+use node_runtime::config::total_receipt_prepaid_gas;
 use node_runtime::state_viewer::TrieViewer;
 use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
@@ -65,7 +75,7 @@ use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
 use near_primitives::shard_layout::{
     account_id_to_shard_id, account_id_to_shard_uid, ShardLayout, ShardUId,
 };
-use near_primitives::syncing::{get_num_state_parts, STATE_PART_MEMORY_LIMIT};
+use near_primitives::syncing::{get_num_state_parts, StateSplitProgress, STATE_PART_MEMORY_LIMIT};
 use near_store::split_state::get_delayed_receipts;
 use node_runtime::near_primitives::shard_layout::ShardLayoutError;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
@@ -141,6 +151,79 @@ pub struct NightshadeRuntime {
     shard_tracker: ShardTracker,
     genesis_state_roots: Vec<StateRoot>,
     migration_data: Arc<MigrationData>,
+    compiled_contract_cache: Arc<dyn CompiledContractCache>,
+    delayed_receipts_backlogs: Arc<Mutex<HashMap<ShardUId, DelayedReceiptsBacklog>>>,
+}
+
+/// Prepaid gas of the delayed receipts queue of a shard, reported in the metrics.
+#[derive(Default)]
+struct DelayedReceiptsBacklog {
+    /// Gas of the queue, unless it isn't known yet, e.g. after a restart.
+    gas: Option<Gas>,
+    /// Set while the queue is scanned: gas the chunks applied since the scanned state added to
+    /// the queue, minus the gas they removed from it.
+    scan_delta: Option<i128>,
+}
+
+fn add_gas_delta(gas: Gas, delta: i128) -> Gas {
+    (gas as i128 + delta).clamp(0, Gas::MAX as i128) as Gas
+}
+
+fn set_delayed_receipts_gas_metric(shard_uid: ShardUId, gas: Gas) {
+    metrics::DELAYED_RECEIPTS_GAS
+        .with_label_values(&[&shard_uid.shard_id.to_string()])
+        .set(gas.min(i64::MAX as u64) as i64);
+}
+
+/// Sums up the prepaid gas of all the receipts in the delayed receipts queue of the shard.
+fn compute_delayed_receipts_gas(
+    tries: &ShardTries,
+    shard_uid: ShardUId,
+    state_root: &StateRoot,
+) -> Result<Gas, Error> {
+    let trie_update = tries.new_trie_update_view(shard_uid, *state_root);
+    let mut total_gas: Gas = 0;
+    let mut start_index = None;
+    while let Some((next_index, receipts)) =
+        get_delayed_receipts(&trie_update, start_index, STATE_PART_MEMORY_LIMIT)?
+    {
+        for receipt in receipts.iter() {
+            let gas = total_receipt_prepaid_gas(receipt).unwrap_or(Gas::MAX);
+            total_gas = total_gas.saturating_add(gas);
+        }
+        start_index = Some(next_index);
+    }
+    Ok(total_gas)
+}
+
+/// Pool on which contracts are precompiled.  It is limited to half of all threads so that the node
+/// still functions normally while compiling, and is shared by all compilations so that they queue
+/// up instead of each starting threads of its own.
+static PRECOMPILE_CONTRACTS_POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(std::cmp::max(rayon::current_num_threads() / 2, 1))
+        .thread_name(|i| format!("precompile_contracts_{}", i))
+        .build()
+        .expect("failed to create the contract precompilation thread pool")
+});
+
+/// Compiles the contracts in parallel on the current rayon pool.  Compilation errors are ignored,
+/// as the contracts are compiled again when called.
+fn precompile_all(
+    contract_codes: &[ContractCode],
+    runtime_config: &RuntimeConfig,
+    protocol_version: ProtocolVersion,
+    compiled_contract_cache: Arc<dyn CompiledContractCache>,
+) {
+    contract_codes.par_iter().for_each(|code| {
+        precompile_contract(
+            code,
+            &runtime_config.wasm_config,
+            protocol_version,
+            Some(&*compiled_contract_cache),
+        )
+        .ok();
+    })
 }
 
 impl NightshadeRuntime {
@@ -151,7 +234,10 @@ impl NightshadeRuntime {
         trie_viewer_state_size_limit: Option<u64>,
         max_gas_burnt_view: Option<Gas>,
     ) -> Self {
-        Self::new(
+        let compiled_contract_cache =
+            create_compiled_contract_cache(home_dir, &store, &config.config.contract_cache)
+                .expect("Failed to open compiled contract cache");
+        let mut runtime = Self::new(
             home_dir,
             store,
             &config.genesis,
@@ -159,7 +245,46 @@ impl NightshadeRuntime {
             trie_viewer_state_size_limit,
             max_gas_burnt_view,
             None,
-        )
+        );
+        runtime.compiled_contract_cache = compiled_contract_cache;
+        runtime.trie_viewer.set_view_state_timeout(config.client_config.view_query_timeout);
+        runtime
+            .trie_viewer
+            .set_abi_dir(config.config.abi_dir.as_ref().map(|dir| home_dir.join(dir)));
+        runtime
+    }
+
+    /// Loads the state of the shards tracked as of the head of the chain into memory, within
+    /// the budget of `mem_tries` in the config.  Shards which the node starts tracking later are
+    /// read from the disk until it's restarted.
+    pub fn load_mem_tries(&self, config: &NearConfig) -> anyhow::Result<()> {
+        let head = match self.store.get_ser::<Tip>(DBCol::ColBlockMisc, HEAD_KEY)? {
+            Some(head) => head,
+            None => {
+                info!(target: "runtime", "No chain yet, not loading the state into memory");
+                return Ok(());
+            }
+        };
+        let me = config.validator_signer.as_ref().map(|signer| signer.validator_id());
+        let shard_uids: Vec<_> = self
+            .get_shard_layout(&head.epoch_id)?
+            .get_shard_uids()
+            .into_iter()
+            .filter(|shard_uid| {
+                let shard_id = shard_uid.shard_id as ShardId;
+                self.cares_about_shard(me, &head.prev_block_hash, shard_id, true)
+                    || self.will_care_about_shard(me, &head.prev_block_hash, shard_id, true)
+            })
+            .collect();
+        let loaded =
+            self.tries.load_mem_tries(&shard_uids, config.config.mem_tries.memory_budget_bytes)?;
+        info!(
+            target: "runtime",
+            "Keeping the state of shards {:?} of {} tracked ones in memory",
+            loaded.iter().map(|shard_uid| shard_uid.shard_id).collect::<Vec<_>>(),
+            shard_uids.len()
+        );
+        Ok(())
     }
 
     pub fn new(
@@ -173,11 +298,13 @@ impl NightshadeRuntime {
     ) -> Self {
         let runtime_config_store = match runtime_config_store {
             Some(store) => store,
-            None => NightshadeRuntime::create_runtime_config_store(&genesis.config.chain_id),
+            None => NightshadeRuntime::create_runtime_config_store(home_dir, &genesis.config),
         };
 
         let runtime = Runtime::new();
         let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
+        // Start the threads of the pool with the runtime rather than on the first compilation.
+        Lazy::force(&PRECOMPILE_CONTRACTS_POOL);
         let genesis_config = genesis.config.clone();
         assert_eq!(
             genesis_config.shard_layout.num_shards(),
@@ -198,6 +325,7 @@ impl NightshadeRuntime {
                 .expect("Failed to start Epoch Manager"),
         ));
         let shard_tracker = ShardTracker::new(tracked_config, epoch_manager.clone());
+        let compiled_contract_cache = Arc::new(StoreCompiledContractCache { store: store.clone() });
         NightshadeRuntime {
             genesis_config,
             runtime_config_store,
@@ -209,6 +337,8 @@ impl NightshadeRuntime {
             shard_tracker,
             genesis_state_roots: state_roots,
             migration_data: Arc::new(load_migration_data(&genesis.config.chain_id)),
+            compiled_contract_cache,
+            delayed_receipts_backlogs: Default::default(),
         }
     }
 
@@ -237,21 +367,43 @@ impl NightshadeRuntime {
         epoch_manager.get_epoch_id(hash).map_err(Error::from)
     }
 
-    /// Create store of runtime configs for the given chain id.
+    /// Create store of runtime configs for the given chain.
     ///
     /// For mainnet and other chains except testnet we don't need to override runtime config for
     /// first protocol versions.
     /// For testnet, runtime config for genesis block was (incorrectly) different, that's why we
     /// need to override it specifically to preserve compatibility.
-    fn create_runtime_config_store(chain_id: &str) -> RuntimeConfigStore {
-        match chain_id {
+    /// Custom chains can override the runtime parameters with the files in
+    /// `runtime_config_overrides_dir` of the genesis config.
+    fn create_runtime_config_store(
+        home_dir: &Path,
+        genesis_config: &GenesisConfig,
+    ) -> RuntimeConfigStore {
+        let store = match genesis_config.chain_id.as_str() {
             "testnet" => {
                 let genesis_runtime_config =
                     serde_json::from_slice(INITIAL_TESTNET_CONFIG).unwrap();
                 RuntimeConfigStore::new(Some(&genesis_runtime_config))
             }
             _ => RuntimeConfigStore::new(None),
-        }
+        };
+        let overrides_dir = match &genesis_config.runtime_config_overrides_dir {
+            Some(overrides_dir) => home_dir.join(overrides_dir),
+            None => return store,
+        };
+        assert!(
+            !matches!(genesis_config.chain_id.as_str(), "mainnet" | "testnet"),
+            "runtime_config_overrides_dir is only supported on custom chains"
+        );
+        let overridden_store = load_runtime_config_overrides(&overrides_dir)
+            .and_then(|overrides| apply_runtime_config_overrides(&store, &overrides))
+            .unwrap_or_else(|err| panic!("{:#}", err));
+        info!(
+            target: "runtime",
+            "Overriding the runtime parameters with the files in {}",
+            overrides_dir.display()
+        );
+        overridden_store
     }
 
     fn genesis_state_from_dump(store: Store, home_dir: &Path) -> Vec<StateRoot> {
@@ -267,7 +419,11 @@ impl NightshadeRuntime {
         state_roots
     }
 
-    fn genesis_state_from_records(store: Store, genesis: &Genesis) -> Vec<StateRoot> {
+    fn genesis_state_from_records(
+        store: Store,
+        home_dir: &Path,
+        genesis: &Genesis,
+    ) -> Vec<StateRoot> {
         if !genesis.records.as_ref().is_empty() {
             info!(target: "runtime", "Genesis state has {} records, computing state roots", genesis.records.0.len());
         } else {
@@ -293,7 +449,7 @@ impl NightshadeRuntime {
         let tries = ShardTries::new(store, genesis.config.shard_layout.version(), num_shards);
         let runtime = Runtime::new();
         let runtime_config_store =
-            NightshadeRuntime::create_runtime_config_store(&genesis.config.chain_id);
+            NightshadeRuntime::create_runtime_config_store(home_dir, &genesis.config);
         let runtime_config = runtime_config_store.get_config(genesis.config.protocol_version);
 
         for shard_id in 0..num_shards {
@@ -366,7 +522,7 @@ impl NightshadeRuntime {
             let state_roots = Self::genesis_state_from_dump(store, home_dir);
             state_roots
         } else {
-            Self::genesis_state_from_records(store, genesis)
+            Self::genesis_state_from_records(store, home_dir, genesis)
         }
     }
 
@@ -405,7 +561,7 @@ impl NightshadeRuntime {
         Ok(ShardUId::from_shard_id_and_layout(shard_id, shard_layout))
     }
 
-    /// Processes state update.
+    /// Processes state update.  `record_metrics` is false when the state update is replayed.
     fn process_state_update(
         &self,
         trie: Trie,
@@ -425,6 +581,7 @@ impl NightshadeRuntime {
         is_new_chunk: bool,
         is_first_block_with_chunk_of_version: bool,
         states_to_patch: Option<Vec<StateRecord>>,
+        record_metrics: bool,
     ) -> Result<ApplyTransactionResult, Error> {
         let _span = tracing::debug_span!(target: "runtime", "process_state_update").entered();
         let epoch_id = self.get_epoch_id_from_prev_block(prev_block_hash)?;
@@ -528,7 +685,7 @@ impl NightshadeRuntime {
             random_seed,
             current_protocol_version,
             config: self.runtime_config_store.get_config(current_protocol_version).clone(),
-            cache: Some(Arc::new(StoreCompiledContractCache { store: self.store.clone() })),
+            cache: Some(Arc::clone(&self.compiled_contract_cache)),
             is_new_chunk,
             migration_data: Arc::clone(&self.migration_data),
             migration_flags: MigrationFlags {
@@ -567,13 +724,18 @@ impl NightshadeRuntime {
 
         let total_gas_burnt =
             apply_result.outcomes.iter().map(|tx_result| tx_result.outcome.gas_burnt).sum();
-        metrics::APPLY_CHUNK_DELAY
-            .with_label_values(&[&format_total_gas_burnt(total_gas_burnt)])
-            .observe(elapsed.as_secs_f64());
-        if total_gas_burnt > 0 {
-            metrics::SECONDS_PER_PETAGAS
-                .with_label_values(&[])
-                .observe(elapsed.as_secs_f64() * 1e15 / total_gas_burnt as f64);
+        if record_metrics {
+            metrics::APPLY_CHUNK_DELAY
+                .with_label_values(&[&format_total_gas_burnt(total_gas_burnt)])
+                .observe(elapsed.as_secs_f64());
+            metrics::CHUNK_STATE_WITNESS_SIZE
+                .with_label_values(&[&shard_id.to_string()])
+                .observe(apply_result.stats.state_witness_size as f64);
+            if total_gas_burnt > 0 {
+                metrics::SECONDS_PER_PETAGAS
+                    .with_label_values(&[])
+                    .observe(elapsed.as_secs_f64() * 1e15 / total_gas_burnt as f64);
+            }
         }
         let total_balance_burnt = apply_result
             .stats
@@ -585,7 +747,6 @@ impl NightshadeRuntime {
             })?;
 
         let shard_uid = self.get_shard_uid_from_prev_hash(shard_id, prev_block_hash)?;
-
         let result = ApplyTransactionResult {
             trie_changes: WrappedTrieChanges::new(
                 self.get_tries(),
@@ -602,39 +763,14 @@ impl NightshadeRuntime {
             total_balance_burnt,
             proof: apply_result.proof,
             processed_delayed_receipts: apply_result.processed_delayed_receipts,
+            state_witness_size: apply_result.stats.state_witness_size,
+            delayed_receipts_count: apply_result.stats.delayed_receipts_count,
+            delayed_receipts_gas_added: apply_result.stats.delayed_receipts_gas_added,
+            delayed_receipts_gas_removed: apply_result.stats.delayed_receipts_gas_removed,
         };
 
         Ok(result)
     }
-
-    fn precompile_contracts(
-        &self,
-        epoch_id: &EpochId,
-        contract_codes: Vec<ContractCode>,
-    ) -> Result<(), Error> {
-        let protocol_version = self.get_epoch_protocol_version(epoch_id)?;
-        let runtime_config = self.runtime_config_store.get_config(protocol_version);
-        let compiled_contract_cache: Option<Arc<dyn CompiledContractCache>> =
-            Some(Arc::new(StoreCompiledContractCache { store: self.store.clone() }));
-        // Execute precompile_contract in parallel but prevent it from using more than half of all
-        // threads so that node will still function normally.
-        rayon::ThreadPoolBuilder::new()
-            .num_threads(std::cmp::max(rayon::current_num_threads() / 2, 1))
-            .build()
-            .unwrap()
-            .install(|| {
-                contract_codes.par_iter().for_each(|code| {
-                    precompile_contract(
-                        code,
-                        &runtime_config.wasm_config,
-                        protocol_version,
-                        compiled_contract_cache.as_deref(),
-                    )
-                    .ok();
-                })
-            });
-        Ok(())
-    }
 }
 
 fn format_total_gas_burnt(gas: Gas) -> String {
@@ -1385,6 +1521,7 @@ impl RuntimeAdapter for NightshadeRuntime {
             is_new_chunk,
             is_first_block_with_chunk_of_version,
             states_to_patch,
+            true,
         ) {
             Ok(result) => Ok(result),
             Err(e) => match e.kind() {
@@ -1434,6 +1571,47 @@ impl RuntimeAdapter for NightshadeRuntime {
             is_new_chunk,
             is_first_block_with_chunk_of_version,
             None,
+            true,
+        )
+    }
+
+    fn replay_transactions(
+        &self,
+        shard_id: ShardId,
+        state_root: &StateRoot,
+        height: BlockHeight,
+        block_timestamp: u64,
+        prev_block_hash: &CryptoHash,
+        block_hash: &CryptoHash,
+        receipts: &[Receipt],
+        transactions: &[SignedTransaction],
+        last_validator_proposals: ValidatorStakeIter,
+        gas_price: Balance,
+        gas_limit: Gas,
+        challenges: &ChallengesResult,
+        random_seed: CryptoHash,
+        is_first_block_with_chunk_of_version: bool,
+    ) -> Result<ApplyTransactionResult, Error> {
+        let trie = self.get_trie_for_shard(shard_id, prev_block_hash)?;
+        self.process_state_update(
+            trie,
+            *state_root,
+            shard_id,
+            height,
+            block_hash,
+            block_timestamp,
+            prev_block_hash,
+            receipts,
+            transactions,
+            last_validator_proposals,
+            gas_price,
+            gas_limit,
+            challenges,
+            random_seed,
+            true,
+            is_first_block_with_chunk_of_version,
+            None,
+            false,
         )
     }
 
@@ -1533,6 +1711,21 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
+            QueryRequest::ViewAbi { account_id } => {
+                let contract_abi =
+                    self.view_abi(&shard_uid, *state_root, account_id).map_err(|err| {
+                        near_chain::near_chain_primitives::error::QueryError::from_view_abi_error(
+                            err,
+                            block_height,
+                            *block_hash,
+                        )
+                    })?;
+                Ok(QueryResponse {
+                    kind: QueryResponseKind::ViewAbi(contract_abi),
+                    block_height,
+                    block_hash: *block_hash,
+                })
+            }
             QueryRequest::ViewAccessKeyList { account_id } => {
                 let access_key_list =
                     self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
@@ -1575,6 +1768,87 @@ impl RuntimeAdapter for NightshadeRuntime {
         }
     }
 
+    fn get_delayed_receipts(
+        &self,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+        from_index: Option<u64>,
+        limit: u64,
+    ) -> Result<(DelayedReceiptIndices, Vec<(u64, Receipt)>), Error> {
+        let trie_update = self.tries.new_trie_update_view(shard_uid, *state_root);
+        let indices = get_delayed_receipt_indices(&trie_update)?;
+        let start_index = from_index.unwrap_or(indices.first_index).max(indices.first_index);
+        let end_index = start_index.saturating_add(limit).min(indices.next_available_index);
+        let mut receipts = vec![];
+        for index in start_index..end_index {
+            let receipt: Receipt = get(&trie_update, &TrieKey::DelayedReceipt { index })?
+                .ok_or_else(|| {
+                    StorageError::StorageInconsistentState(format!(
+                        "Delayed receipt #{} should be in the state",
+                        index
+                    ))
+                })?;
+            receipts.push((index, receipt));
+        }
+        Ok((indices, receipts))
+    }
+
+    /// The gas backlog is updated by the gas the chunk added to and removed from the queue.  The
+    /// queue is only scanned when the backlog of a non-empty queue isn't known, e.g. after a
+    /// restart, and the scan runs on a separate thread so that it doesn't hold up applying
+    /// chunks.  Chunks of several forks may make the backlog drift until the queue is drained.
+    fn update_delayed_receipts_metrics(
+        &self,
+        shard_uid: ShardUId,
+        apply_result: &ApplyTransactionResult,
+    ) {
+        metrics::DELAYED_RECEIPTS_COUNT
+            .with_label_values(&[&shard_uid.shard_id.to_string()])
+            .set(apply_result.delayed_receipts_count as i64);
+        let delta = apply_result.delayed_receipts_gas_added as i128
+            - apply_result.delayed_receipts_gas_removed as i128;
+        let mut backlogs = self.delayed_receipts_backlogs.lock().expect(POISONED_LOCK_ERR);
+        let backlog = backlogs.entry(shard_uid).or_default();
+        if apply_result.delayed_receipts_count == 0 {
+            *backlog = DelayedReceiptsBacklog { gas: Some(0), scan_delta: None };
+        } else if let Some(gas) = backlog.gas {
+            backlog.gas = Some(add_gas_delta(gas, delta));
+        } else if let Some(scan_delta) = backlog.scan_delta.as_mut() {
+            *scan_delta += delta;
+            return;
+        } else {
+            backlog.scan_delta = Some(0);
+            let shared_backlogs = self.delayed_receipts_backlogs.clone();
+            let tries = self.tries.clone();
+            let state_root = apply_result.new_root;
+            let spawned = std::thread::Builder::new()
+                .name("delayed_receipts_gas".to_string())
+                .spawn(move || {
+                    let gas = compute_delayed_receipts_gas(&tries, shard_uid, &state_root);
+                    let mut backlogs = shared_backlogs.lock().expect(POISONED_LOCK_ERR);
+                    let backlog = backlogs.entry(shard_uid).or_default();
+                    // The delta is gone if the queue was drained in the meantime.
+                    match (gas, backlog.scan_delta.take()) {
+                        (Ok(gas), Some(delta)) => {
+                            let gas = add_gas_delta(gas, delta);
+                            backlog.gas = Some(gas);
+                            set_delayed_receipts_gas_metric(shard_uid, gas);
+                        }
+                        (Ok(_), None) => {}
+                        (Err(err), _) => {
+                            warn!(target: "runtime", "Failed to scan delayed receipts: {}", err)
+                        }
+                    }
+                });
+            if let Err(err) = spawned {
+                warn!(target: "runtime", "Failed to scan delayed receipts: {}", err);
+                backlog.scan_delta = None;
+            }
+            return;
+        }
+        set_delayed_receipts_gas_metric(shard_uid, backlog.gas.unwrap_or_default());
+    }
+
     fn get_validator_info(
         &self,
         epoch_id: ValidatorInfoIdentifier,
@@ -1583,6 +1857,36 @@ impl RuntimeAdapter for NightshadeRuntime {
         epoch_manager.get_validator_info(epoch_id).map_err(|e| e.into())
     }
 
+    fn get_validator_projection(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochValidatorProjectionView, Error> {
+        let mut epoch_manager = self.epoch_manager.as_ref().write().expect(POISONED_LOCK_ERR);
+        epoch_manager.get_validator_projection(last_block_hash).map_err(|e| e.into())
+    }
+
+    fn get_protocol_version_votes(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolVersionVotesView, Error> {
+        let mut epoch_manager = self.epoch_manager.as_ref().write().expect(POISONED_LOCK_ERR);
+        epoch_manager.get_protocol_version_votes(last_block_hash).map_err(|e| e.into())
+    }
+
+    fn get_reward_projection(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<EpochRewardProjectionView, Error> {
+        let mut epoch_manager = self.epoch_manager.as_ref().write().expect(POISONED_LOCK_ERR);
+        epoch_manager.get_reward_projection(last_block_hash).map_err(|e| e.into())
+    }
+
+    fn get_epoch_info_after_next(&self, last_block_hash: &CryptoHash) -> Result<EpochInfo, Error> {
+        let mut epoch_manager = self.epoch_manager.as_ref().write().expect(POISONED_LOCK_ERR);
+        // The id of the epoch after next is the hash of the last block of the epoch.
+        Ok(epoch_manager.get_epoch_info(&EpochId(*last_block_hash))?.clone())
+    }
+
     /// Returns StorageError when storage is inconsistent.
     /// This is possible with the used isolation level + running ViewClient in a separate thread
     /// `block_hash` is a block whose `prev_state_root` is `state_root`
@@ -1669,6 +1973,7 @@ impl RuntimeAdapter for NightshadeRuntime {
         shard_uid: ShardUId,
         state_root: &StateRoot,
         next_epoch_shard_layout: &ShardLayout,
+        progress: &StateSplitProgress,
     ) -> Result<HashMap<ShardUId, StateRoot>, Error> {
         let trie = self.tries.get_view_trie_for_shard(shard_uid);
         let shard_id = shard_uid.shard_id();
@@ -1695,8 +2000,18 @@ impl RuntimeAdapter for NightshadeRuntime {
         let state_root_node = trie.retrieve_root_node(state_root)?;
         let num_parts = get_num_state_parts(state_root_node.memory_usage);
         debug!(target: "runtime", "splitting state for shard {} to {} parts to build new states", shard_id, num_parts);
+        progress.set_num_parts(num_parts);
         for part_id in 0..num_parts {
             let trie_items = trie.get_trie_items_for_part(part_id, num_parts, state_root)?;
+            let mut child_bytes = HashMap::new();
+            for (key, value) in trie_items.iter() {
+                // Delayed receipts are added after all the parts, they are not counted here.
+                if let Ok(Some(account_id)) = parse_account_id_from_raw_key(key) {
+                    let new_shard_uid = checked_account_id_to_shard_id(&account_id);
+                    *child_bytes.entry(new_shard_uid).or_default() +=
+                        (key.len() + value.len()) as u64;
+                }
+            }
             let (store_update, new_state_roots) = self.tries.add_values_to_split_states(
                 &state_roots,
                 trie_items.into_iter().map(|(key, value)| (key, Some(value))).collect(),
@@ -1704,6 +2019,7 @@ impl RuntimeAdapter for NightshadeRuntime {
             )?;
             state_roots = new_state_roots;
             store_update.commit()?;
+            progress.part_done(child_bytes);
         }
         state_roots = apply_delayed_receipts(
             &self.tries,
@@ -1736,6 +2052,44 @@ impl RuntimeAdapter for NightshadeRuntime {
         Ok(store_update.commit()?)
     }
 
+    fn precompile_contracts(
+        &self,
+        epoch_id: &EpochId,
+        contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error> {
+        let protocol_version = self.get_epoch_protocol_version(epoch_id)?;
+        let runtime_config = self.runtime_config_store.get_config(protocol_version).clone();
+        let compiled_contract_cache = Arc::clone(&self.compiled_contract_cache);
+        PRECOMPILE_CONTRACTS_POOL.install(|| {
+            precompile_all(
+                &contract_codes,
+                &runtime_config,
+                protocol_version,
+                compiled_contract_cache,
+            )
+        });
+        Ok(())
+    }
+
+    fn precompile_contracts_in_background(
+        &self,
+        epoch_id: &EpochId,
+        contract_codes: Vec<ContractCode>,
+    ) -> Result<(), Error> {
+        let protocol_version = self.get_epoch_protocol_version(epoch_id)?;
+        let runtime_config = self.runtime_config_store.get_config(protocol_version).clone();
+        let compiled_contract_cache = Arc::clone(&self.compiled_contract_cache);
+        PRECOMPILE_CONTRACTS_POOL.spawn(move || {
+            precompile_all(
+                &contract_codes,
+                &runtime_config,
+                protocol_version,
+                compiled_contract_cache,
+            )
+        });
+        Ok(())
+    }
+
     /// `block_hash` is a block whose `prev_state_root` is `state_root`
     fn get_state_root_node(
         &self,
@@ -1826,6 +2180,10 @@ impl RuntimeAdapter for NightshadeRuntime {
         Ok(ProtocolConfig { genesis_config, runtime_config })
     }
 
+    fn get_runtime_config(&self, protocol_version: ProtocolVersion) -> Arc<RuntimeConfig> {
+        self.runtime_config_store.get_config(protocol_version).clone()
+    }
+
     fn get_prev_epoch_id_from_prev_block(
         &self,
         prev_block_hash: &CryptoHash,
@@ -1882,6 +2240,16 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         self.trie_viewer.view_contract_code(&state_update, account_id)
     }
 
+    fn view_abi(
+        &self,
+        shard_uid: &ShardUId,
+        state_root: MerkleHash,
+        account_id: &AccountId,
+    ) -> Result<ContractAbiView, node_runtime::state_viewer::errors::ViewAbiError> {
+        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
+        self.trie_viewer.view_abi(&state_update, account_id)
+    }
+
     fn call_function(
         &self,
         shard_uid: &ShardUId,
@@ -1908,7 +2276,7 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             epoch_height,
             block_timestamp,
             current_protocol_version,
-            cache: Some(Arc::new(StoreCompiledContractCache { store: self.tries.get_store() })),
+            cache: Some(Arc::clone(&self.compiled_contract_cache)),
         };
         self.trie_viewer.call_function(
             state_update,
@@ -2870,7 +3238,8 @@ mod test {
             response.prev_epoch_kickout,
             vec![ValidatorKickoutView {
                 account_id: "test1".parse().unwrap(),
-                reason: ValidatorKickoutReason::Unstaked
+                reason: ValidatorKickoutReason::Unstaked,
+                produced_threshold_percent: None,
             }]
         );
         assert_eq!(response.epoch_start_height, 3);
@@ -0,0 +1,325 @@
+//! Fast consistency check of the chain heads on startup.
+//!
+//! A node which crashed while committing can come back with heads pointing at blocks whose data
+//! never made it to the disk, and produce invalid blocks right away.  Unlike the store validator,
+//! the check only looks at the heads and the data of the head block, so it takes no time even on
+//! large databases.
+use std::fmt;
+
+use near_chain::types::Tip;
+use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate, ErrorKind, RuntimeAdapter};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use near_store::Store;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Whether the chain heads are checked on startup and what happens if they're inconsistent.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupCheck {
+    /// The heads aren't checked.
+    Disabled,
+    /// The node refuses to start if the heads are inconsistent.
+    Check,
+    /// The inconsistencies are repaired where possible; the node refuses to start only if some
+    /// can't be.
+    Repair,
+}
+
+impl Default for StartupCheck {
+    fn default() -> Self {
+        StartupCheck::Disabled
+    }
+}
+
+/// An inconsistency between the chain heads and the data stored for them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// Data of the head block needed to build on it is missing.
+    MissingHeadData { head: CryptoHash, missing: String },
+    /// The header head is missing or below the head.
+    HeaderHeadBehind { header_head: Option<BlockHeight>, head: BlockHeight },
+    /// The final head isn't the head or one of its ancestors.
+    InvalidFinalHead { final_head: Option<CryptoHash>, head: CryptoHash },
+    /// The tail is above the final head, so blocks the chain may still need were collected.
+    TailAboveFinalHead { tail: BlockHeight, final_head: BlockHeight },
+}
+
+impl Inconsistency {
+    /// Whether `StartupCheck::Repair` can repair the inconsistency.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Inconsistency::TailAboveFinalHead { .. })
+    }
+
+    /// What the operator can do about the inconsistency.
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Inconsistency::MissingHeadData { .. } => {
+                "run `neard run --repair-store` to move the head back to the last block whose data is stored"
+            }
+            Inconsistency::HeaderHeadBehind { .. } | Inconsistency::InvalidFinalHead { .. } => {
+                "run `neard run --repair-store` to reset it from the head"
+            }
+            Inconsistency::TailAboveFinalHead { .. } => {
+                "restore the database from a backup or a snapshot"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::MissingHeadData { head, missing } => {
+                write!(f, "{} of the head block {} is missing", missing, head)
+            }
+            Inconsistency::HeaderHeadBehind { header_head: Some(height), head } => {
+                write!(f, "header head at {} is below the head at {}", height, head)
+            }
+            Inconsistency::HeaderHeadBehind { header_head: None, .. } => {
+                write!(f, "header head is missing")
+            }
+            Inconsistency::InvalidFinalHead { final_head: Some(final_head), head } => {
+                write!(f, "final head {} isn't an ancestor of the head {}", final_head, head)
+            }
+            Inconsistency::InvalidFinalHead { final_head: None, .. } => {
+                write!(f, "final head is missing")
+            }
+            Inconsistency::TailAboveFinalHead { tail, final_head } => {
+                write!(f, "tail at {} is above the final head at {}", tail, final_head)
+            }
+        }
+    }
+}
+
+/// Checks the heads of the chain in the store.  Without `repair` returns the inconsistencies
+/// found; with it repairs them where possible and returns those which couldn't be repaired.
+///
+/// The head block must be stored along with the chunk extras of the shards tracked by `me`,
+/// unless the node is `header_only`.  The header head must be at or above the head, the final
+/// head must be the head or one of its ancestors and the tail must be at or below the final
+/// head.
+pub(crate) fn check_store(
+    store: &Store,
+    runtime: &dyn RuntimeAdapter,
+    genesis_height: BlockHeight,
+    me: Option<&AccountId>,
+    header_only: bool,
+    repair: bool,
+) -> anyhow::Result<Vec<Inconsistency>> {
+    let mut chain_store = ChainStore::new(store.clone(), genesis_height);
+    let mut head = match chain_store.head() {
+        Ok(head) => head,
+        // A new database, the chain starts from genesis.
+        Err(err) if matches!(err.kind(), ErrorKind::DBNotFoundErr(_)) => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    let mut chain_store_update = chain_store.store_update();
+    let update = &mut chain_store_update;
+    let mut inconsistencies = vec![];
+
+    if !header_only {
+        if let Some(missing) = missing_block_data(update, runtime, me, &head.last_block_hash)? {
+            let new_head =
+                if repair { last_block_with_data(update, runtime, me, &head)? } else { None };
+            match new_head {
+                Some(new_head) => {
+                    warn!(
+                        target: "near",
+                        "{} of the head block {} is missing, moving the head back to {} at {}",
+                        missing, head.last_block_hash, new_head.last_block_hash, new_head.height
+                    );
+                    update.save_body_head(&new_head)?;
+                    head = new_head;
+                }
+                None => inconsistencies
+                    .push(Inconsistency::MissingHeadData { head: head.last_block_hash, missing }),
+            }
+        }
+    }
+
+    let header_head = match update.header_head() {
+        Ok(tip) if update.get_block_header(&tip.last_block_hash).is_ok() => Some(tip.height),
+        _ => None,
+    };
+    if header_head.map_or(true, |height| height < head.height) {
+        if repair {
+            warn!(target: "near", "Resetting the header head to the head at {}", head.height);
+            update.save_header_head_if_not_challenged(&head)?;
+        } else {
+            inconsistencies
+                .push(Inconsistency::HeaderHeadBehind { header_head, head: head.height });
+        }
+    }
+
+    let mut final_head = update.final_head().ok();
+    let final_head_valid = match final_head.as_ref() {
+        Some(final_head) => is_ancestor(update, final_head, &head),
+        None => false,
+    };
+    if !final_head_valid {
+        if repair {
+            let new_final_head = last_final_tip(update, &head, genesis_height)?;
+            warn!(
+                target: "near",
+                "Resetting the final head to {} at {}",
+                new_final_head.last_block_hash, new_final_head.height
+            );
+            update.save_final_head(&new_final_head)?;
+            final_head = Some(new_final_head);
+        } else {
+            inconsistencies.push(Inconsistency::InvalidFinalHead {
+                final_head: final_head.as_ref().map(|tip| tip.last_block_hash),
+                head: head.last_block_hash,
+            });
+        }
+    }
+
+    let tail = update.tail()?;
+    if let Some(final_head) = final_head.filter(|final_head| final_head.height < tail) {
+        inconsistencies
+            .push(Inconsistency::TailAboveFinalHead { tail, final_head: final_head.height });
+    }
+
+    if repair {
+        chain_store_update.commit()?;
+    }
+    if inconsistencies.is_empty() {
+        info!(target: "near", "Chain heads are consistent, head at {}", head.height);
+    }
+    Ok(inconsistencies)
+}
+
+/// Describes the data of the block missing to build on it, if any.
+fn missing_block_data(
+    update: &mut ChainStoreUpdate,
+    runtime: &dyn RuntimeAdapter,
+    me: Option<&AccountId>,
+    block_hash: &CryptoHash,
+) -> anyhow::Result<Option<String>> {
+    if !update.block_exists(block_hash)? {
+        return Ok(Some("block".to_string()));
+    }
+    let header = update.get_block_header(block_hash)?.clone();
+    for shard_uid in runtime.get_shard_layout(header.epoch_id())?.get_shard_uids() {
+        let shard_id = shard_uid.shard_id as ShardId;
+        if !runtime.cares_about_shard(me, header.prev_hash(), shard_id, true) {
+            continue;
+        }
+        match update.get_chunk_extra(block_hash, &shard_uid) {
+            Ok(_) => {}
+            Err(err) if matches!(err.kind(), ErrorKind::DBNotFoundErr(_)) => {
+                return Ok(Some(format!("chunk extra of shard {}", shard_id)));
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(None)
+}
+
+/// Finds the closest ancestor of the head, at or above the tail, whose data is stored.
+fn last_block_with_data(
+    update: &mut ChainStoreUpdate,
+    runtime: &dyn RuntimeAdapter,
+    me: Option<&AccountId>,
+    head: &Tip,
+) -> anyhow::Result<Option<Tip>> {
+    let tail = update.tail()?;
+    let mut block_hash = head.prev_block_hash;
+    loop {
+        let header = match update.get_block_header(&block_hash) {
+            Ok(header) if header.height() >= tail => header.clone(),
+            _ => return Ok(None),
+        };
+        if missing_block_data(update, runtime, me, &block_hash)?.is_none() {
+            return Ok(Some(Tip::from_header(&header)));
+        }
+        block_hash = *header.prev_hash();
+    }
+}
+
+/// Whether the block of `ancestor` is the block of `tip` or one of its ancestors.
+fn is_ancestor(update: &mut ChainStoreUpdate, ancestor: &Tip, tip: &Tip) -> bool {
+    let mut block_hash = tip.last_block_hash;
+    loop {
+        let header = match update.get_block_header(&block_hash) {
+            Ok(header) => header,
+            Err(_) => return false,
+        };
+        if header.height() <= ancestor.height {
+            return header.hash() == &ancestor.last_block_hash;
+        }
+        block_hash = *header.prev_hash();
+    }
+}
+
+/// The tip of the last final block as seen from the head.
+fn last_final_tip(
+    update: &mut ChainStoreUpdate,
+    head: &Tip,
+    genesis_height: BlockHeight,
+) -> anyhow::Result<Tip> {
+    let last_final_block = *update.get_block_header(&head.last_block_hash)?.last_final_block();
+    let last_final_block = if last_final_block == CryptoHash::default() {
+        update.get_block_hash_by_height(genesis_height)?
+    } else {
+        last_final_block
+    };
+    Ok(Tip::from_header(update.get_block_header(&last_final_block)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use near_chain::test_utils::setup;
+    use near_chain::{Block, Provenance};
+    use near_primitives::utils::MaybeValidated;
+    use near_primitives::validator_signer::ValidatorSigner;
+    use near_store::DBCol;
+
+    use super::*;
+
+    #[test]
+    fn test_check_store() {
+        let (mut chain, runtime, signer) = setup();
+        for _ in 0..3 {
+            let prev = chain.get_block(&chain.head().unwrap().last_block_hash).unwrap().clone();
+            let block = Block::empty(&prev, &*signer);
+            chain
+                .process_block(
+                    &None,
+                    MaybeValidated::from(block),
+                    Provenance::PRODUCED,
+                    &mut |_| {},
+                    &mut |_| {},
+                    &mut |_| {},
+                    &mut |_| {},
+                )
+                .unwrap();
+        }
+        let store = chain.store().store().clone();
+        let me = Some(signer.validator_id());
+        let check = |repair| check_store(&store, &*runtime, 0, me, false, repair).unwrap();
+        assert_eq!(check(false), vec![]);
+
+        // A crash lost the head block.
+        let head = chain.head().unwrap();
+        let mut store_update = store.store_update();
+        store_update.delete(DBCol::ColBlock, head.last_block_hash.as_ref());
+        store_update.commit().unwrap();
+        assert_eq!(
+            check(false),
+            vec![Inconsistency::MissingHeadData {
+                head: head.last_block_hash,
+                missing: "block".to_string()
+            }]
+        );
+        // Header-only nodes don't need it.
+        assert_eq!(check_store(&store, &*runtime, 0, me, true, false).unwrap(), vec![]);
+
+        assert_eq!(check(true), vec![]);
+        assert_eq!(check(false), vec![]);
+        let new_head = ChainStore::new(store.clone(), 0).head().unwrap();
+        assert_eq!(new_head.last_block_hash, head.prev_block_hash);
+    }
+}
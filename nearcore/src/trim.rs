@@ -0,0 +1,292 @@
+//! Copying the data a non-archival node needs out of an archival database.
+//!
+//! The resulting database looks like the one of a node which state synced at the start of the
+//! epoch containing the requested height and kept up with the chain since: it contains the blocks
+//! from the last block before that epoch up to the head, the chunks created since then, the state
+//! of the tracked shards as of each of those blocks and everything which is never garbage
+//! collected.
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use borsh::BorshSerialize;
+use near_chain::{ChainStore, ChainStoreAccess, RuntimeAdapter};
+use near_primitives::errors::StorageError;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
+use near_primitives::types::{BlockHeight, ShardId, StateRoot};
+use near_primitives::utils::{get_block_shard_id, index_to_bytes};
+use near_store::{
+    create_store, create_store_with_config, DBCol, KeyForStateChanges, Store, StoreConfig,
+    StoreUpdate, Trie, CHUNK_TAIL_KEY, FORK_TAIL_KEY, SHOULD_COL_GC, TAIL_KEY,
+};
+use strum::IntoEnumIterator;
+use tracing::info;
+
+use crate::{get_store_path, store_path_exists, NearConfig, NightshadeRuntime};
+
+const BATCH_SIZE_BYTES: u64 = 150_000_000;
+
+/// Copies the data needed to run a non-archival node from the block at `from_height` (or the
+/// first one after it) from the database in `home_dir` into a new database in `dst_dir`.
+pub fn trim_database(
+    home_dir: &Path,
+    near_config: &NearConfig,
+    from_height: BlockHeight,
+    dst_dir: &Path,
+) -> anyhow::Result<()> {
+    let src_dir = get_store_path(home_dir);
+    anyhow::ensure!(
+        store_path_exists(&src_dir),
+        "{}: source storage doesn’t exist",
+        src_dir.display()
+    );
+    anyhow::ensure!(
+        !store_path_exists(&dst_dir),
+        "{}: directory already exists",
+        dst_dir.display()
+    );
+
+    let src_store =
+        create_store_with_config(&src_dir, StoreConfig { read_only: true, ..Default::default() });
+    let runtime =
+        NightshadeRuntime::with_config(home_dir, src_store.clone(), near_config, None, None);
+    let genesis_height = near_config.genesis.config.genesis_height;
+    let mut chain_store = ChainStore::new(src_store.clone(), genesis_height);
+
+    let head = chain_store.head()?;
+    anyhow::ensure!(
+        from_height <= head.height,
+        "height {} is above the head of the chain at {}",
+        from_height,
+        head.height
+    );
+    let mut from_hash = None;
+    for height in from_height..=head.height {
+        if let Ok(hash) = chain_store.get_block_hash_by_height(height) {
+            from_hash = Some(hash);
+            break;
+        }
+    }
+    let from_hash = from_hash.expect("the head is on the canonical chain");
+    let sync_height = runtime.get_epoch_start_height(&from_hash)?;
+    let sync_hash = chain_store.get_block_hash_by_height(sync_height)?;
+    let tail_hash = *chain_store.get_block_header(&sync_hash)?.prev_hash();
+    let tail_block = chain_store.get_block(&tail_hash)?.clone();
+    let tail = tail_block.header().height();
+    let chunk_tail = tail_block.chunks().iter().map(|chunk| chunk.height_created()).min().unwrap();
+    anyhow::ensure!(
+        chain_store.tail()? <= tail && chain_store.chunk_tail()? <= chunk_tail,
+        "the database doesn’t contain the blocks from height {}",
+        tail
+    );
+
+    info!(
+        "Copying data from height {} up to {} from {} into {}",
+        tail,
+        head.height,
+        src_dir.display(),
+        dst_dir.display()
+    );
+    let dst_store = create_store(&dst_dir);
+    let mut copier = Copier::new(src_store.clone(), dst_store.clone());
+
+    for column in DBCol::iter() {
+        // The epoch manager looks up the first and last blocks of past epochs too.
+        if SHOULD_COL_GC[column as usize] && column != DBCol::ColBlockInfo {
+            continue;
+        }
+        info!("Copying col{} ‘{}’", column as usize, column);
+        for (key, _) in src_store.iter_without_rc_logic(column) {
+            copier.copy(column, &key)?;
+        }
+    }
+
+    info!("Copying blocks");
+    let tries = runtime.get_tries();
+    let mut num_blocks: u64 = 0;
+    let mut block_hash = tail_hash;
+    loop {
+        let block = chain_store.get_block(&block_hash)?.clone();
+        let height = block.header().height();
+        let block_hash_key = block_hash.as_ref();
+        for column in [
+            DBCol::ColBlock,
+            DBCol::ColBlockExtra,
+            DBCol::ColNextBlockHashes,
+            DBCol::ColBlockRefCount,
+        ] {
+            copier.copy(column, block_hash_key)?;
+        }
+        let height_key = index_to_bytes(height);
+        copier.copy(DBCol::ColProcessedBlockHeights, &height_key)?;
+        // Blocks on forks aren't copied.
+        let epoch_to_hashes =
+            HashMap::from([(block.header().epoch_id().clone(), HashSet::from([block_hash]))]);
+        copier.set(DBCol::ColBlockPerHeight, &height_key, &epoch_to_hashes.try_to_vec()?)?;
+        let state_changes_prefix = KeyForStateChanges::get_prefix(&block_hash);
+        for (key, _) in src_store.iter_prefix(DBCol::ColStateChanges, state_changes_prefix.as_ref())
+        {
+            copier.copy(DBCol::ColStateChanges, &key)?;
+        }
+
+        for shard_id in 0..block.chunks().len() as ShardId {
+            let block_shard_id = get_block_shard_id(&block_hash, shard_id);
+            copier.copy(DBCol::ColIncomingReceipts, &block_shard_id)?;
+            copier.copy(DBCol::ColChunkPerHeightShard, &block_shard_id)?;
+            if copier.copy(DBCol::ColOutgoingReceipts, &block_shard_id)? {
+                for receipt in chain_store.get_outgoing_receipts(&block_hash, shard_id)? {
+                    copier.copy(DBCol::ColReceiptIdToShardId, receipt.receipt_id.as_ref())?;
+                }
+            }
+            if copier.copy(DBCol::ColOutcomeIds, &block_shard_id)? {
+                for outcome_id in
+                    chain_store.get_outcomes_by_block_hash_and_shard_id(&block_hash, shard_id)?
+                {
+                    copier.copy(DBCol::ColTransactionResult, outcome_id.as_ref())?;
+                }
+            }
+        }
+
+        let shard_layout = runtime.get_shard_layout(block.header().epoch_id())?;
+        for shard_uid in shard_layout.get_shard_uids() {
+            if !runtime.cares_about_shard(
+                None,
+                block.header().prev_hash(),
+                shard_uid.shard_id as ShardId,
+                false,
+            ) {
+                continue;
+            }
+            let block_shard_uid = get_block_shard_uid(&block_hash, &shard_uid);
+            if !copier.copy(DBCol::ColChunkExtra, &block_shard_uid)? {
+                continue;
+            }
+            // The tail block's changes are never applied to the state, same as after state sync.
+            if block_hash != tail_hash {
+                copier.copy(DBCol::ColTrieChanges, &block_shard_uid)?;
+            }
+            let state_root = *chain_store.get_chunk_extra(&block_hash, &shard_uid)?.state_root();
+            copier.copy_state(&tries.get_trie_for_shard(shard_uid), shard_uid, &state_root)?;
+        }
+
+        num_blocks += 1;
+        if num_blocks % 1000 == 0 {
+            info!("Copied blocks up to height {} of {}", height, head.height);
+        }
+        if block_hash == head.last_block_hash {
+            break;
+        }
+        block_hash = *chain_store.get_next_block_hash(&block_hash)?;
+    }
+
+    info!("Copying chunks");
+    // The largest height of chunk we have in storage is head.height + 1.
+    for height in chunk_tail..=head.height + 1 {
+        let height_key = index_to_bytes(height);
+        copier.copy(DBCol::ColHeaderHashesByHeight, &height_key)?;
+        if !copier.copy(DBCol::ColChunkHashesByHeight, &height_key)? {
+            continue;
+        }
+        for chunk_hash in chain_store.get_all_chunk_hashes_by_height(height)? {
+            let chunk_hash_key = chunk_hash.as_ref();
+            copier.copy(DBCol::ColPartialChunks, chunk_hash_key)?;
+            copier.copy(DBCol::ColInvalidChunks, chunk_hash_key)?;
+            if !copier.copy(DBCol::ColChunks, chunk_hash_key)? {
+                continue;
+            }
+            let chunk = chain_store.get_chunk(&chunk_hash)?.clone();
+            for transaction in chunk.transactions() {
+                copier.copy(DBCol::ColTransactions, transaction.get_hash().as_ref())?;
+            }
+            for receipt in chunk.receipts() {
+                copier.copy(DBCol::ColReceipts, receipt.get_hash().as_ref())?;
+            }
+        }
+    }
+
+    copier.set(DBCol::ColBlockMisc, TAIL_KEY, &tail.try_to_vec()?)?;
+    copier.set(DBCol::ColBlockMisc, CHUNK_TAIL_KEY, &chunk_tail.try_to_vec()?)?;
+    copier.set(DBCol::ColBlockMisc, FORK_TAIL_KEY, &tail.try_to_vec()?)?;
+    copier.commit()?;
+
+    core::mem::drop(copier);
+    core::mem::drop(dst_store);
+    core::mem::drop(src_store);
+
+    info!("Done; trimmed database at {}", dst_dir.display());
+    Ok(())
+}
+
+/// Writes the copied data in batches.
+struct Copier {
+    src: Store,
+    dst: Store,
+    update: StoreUpdate,
+    batch_size: u64,
+    /// `ColState` keys written in the current batch.
+    state_keys: HashSet<Vec<u8>>,
+}
+
+impl Copier {
+    fn new(src: Store, dst: Store) -> Self {
+        let update = dst.store_update();
+        Self { src, dst, update, batch_size: 0, state_keys: HashSet::new() }
+    }
+
+    /// Copies the value as stored, so that the reference counts of reference counted columns are
+    /// kept.  Returns whether the key exists.
+    fn copy(&mut self, column: DBCol, key: &[u8]) -> io::Result<bool> {
+        match self.src.get_without_rc_logic(column, key)? {
+            Some(value) => {
+                self.set(column, key, &value)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn set(&mut self, column: DBCol, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.update.set(column, key, value);
+        self.batch_size += (key.len() + value.len()) as u64;
+        if self.batch_size >= BATCH_SIZE_BYTES {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> io::Result<()> {
+        std::mem::replace(&mut self.update, self.dst.store_update()).commit()?;
+        self.batch_size = 0;
+        self.state_keys.clear();
+        Ok(())
+    }
+
+    /// Copies the nodes and values of the state with the given root, skipping the subtrees which
+    /// were copied already.
+    ///
+    /// The reference counts come from the archival database so they're larger than the ones of a
+    /// non-archival node, which means that some nodes are never garbage collected.
+    fn copy_state(
+        &mut self,
+        trie: &Trie,
+        shard_uid: ShardUId,
+        state_root: &StateRoot,
+    ) -> anyhow::Result<()> {
+        trie.visit_nodes(state_root, |hash: &CryptoHash| {
+            let key = [&shard_uid.to_bytes()[..], hash.as_ref()].concat();
+            let copied = self.state_keys.contains(&key)
+                || self
+                    .dst
+                    .exists(DBCol::ColState, &key)
+                    .map_err(|_| StorageError::StorageInternalError)?;
+            if copied {
+                return Ok(false);
+            }
+            self.copy(DBCol::ColState, &key).map_err(|_| StorageError::StorageInternalError)?;
+            self.state_keys.insert(key);
+            Ok(true)
+        })?;
+        Ok(())
+    }
+}
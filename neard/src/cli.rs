@@ -1,10 +1,14 @@
+use anyhow::Context;
 use clap::{Args, Parser};
 use futures::future::FutureExt;
 use near_chain_configs::GenesisValidationMode;
-use near_o11y::{default_subscriber, EnvFilterBuilder};
-use near_primitives::types::{Gas, NumSeats, NumShards};
-use near_state_viewer::StateViewerSubCommand;
+use near_o11y::{default_subscriber, default_subscriber_with_opentelemetry, EnvFilterBuilder};
+use near_primitives::shard_layout::ShardLayout;
+use near_primitives::types::{AccountId, BlockHeight, BlockHeightDelta, Gas, NumSeats, NumShards};
+use near_state_viewer::{ReplayBlocksCmd, StateViewerSubCommand};
 use near_store::db::RocksDB;
+use nearcore::chain_params::ChainParams;
+use nearcore::genesis_spec::GenesisSpec;
 use nearcore::get_store_path;
 use std::fs;
 use std::net::SocketAddr;
@@ -35,7 +39,23 @@ impl NeardCmd {
         } else {
             env_filter
         };
-        let _subscriber = default_subscriber(env_filter);
+        let _subscriber = match &neard_cmd.opts.otlp_endpoint {
+            Some(otlp_endpoint) => {
+                let otlp_filter =
+                    EnvFilterBuilder::new(neard_cmd.opts.otlp_filter.as_str()).finish();
+                default_subscriber_with_opentelemetry(
+                    env_filter,
+                    otlp_filter,
+                    "neard",
+                    otlp_endpoint,
+                )
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to set up OpenTelemetry export: {}", err);
+                    std::process::exit(1);
+                })
+            }
+            None => default_subscriber(env_filter),
+        };
 
         info!(
             target: "neard",
@@ -90,9 +110,44 @@ impl NeardCmd {
                 cmd.run(&home_dir, genesis_validation);
             }
 
+            NeardSubCommand::Replay(cmd) => {
+                if let Err(err) = cmd.run(&home_dir, genesis_validation) {
+                    error!(target: "neard", "Replay failed: {:#}", err);
+                    std::process::exit(1);
+                }
+            }
+
             NeardSubCommand::RecompressStorage(cmd) => {
                 cmd.run(&home_dir);
             }
+
+            NeardSubCommand::MaintenanceWindow(cmd) => {
+                if let Err(err) = cmd.run(&home_dir) {
+                    error!(target: "neard", "{:#}", err);
+                    std::process::exit(1);
+                }
+            }
+
+            NeardSubCommand::ValidateConfig(cmd) => {
+                if let Err(err) = cmd.run(&home_dir) {
+                    error!(target: "neard", "{:#}", err);
+                    std::process::exit(1);
+                }
+            }
+
+            NeardSubCommand::Peers(cmd) => {
+                if let Err(err) = cmd.run() {
+                    error!(target: "neard", "{:#}", err);
+                    std::process::exit(1);
+                }
+            }
+
+            NeardSubCommand::Database(cmd) => {
+                if let Err(err) = cmd.run(&home_dir, genesis_validation) {
+                    error!(target: "neard", "{:#}", err);
+                    std::process::exit(1);
+                }
+            }
         }
     }
 }
@@ -112,6 +167,15 @@ struct NeardOpts {
     /// if "debug" is given.
     #[clap(long, name = "target")]
     verbose: Option<String>,
+    /// Exports the spans passing the log filter, such as those of the block
+    /// and chunk processing, to the OpenTelemetry collector at the given
+    /// OTLP/gRPC endpoint, e.g. http://localhost:4317.
+    #[clap(long)]
+    otlp_endpoint: Option<String>,
+    /// Filter of the spans exported to the OpenTelemetry collector, in the
+    /// `RUST_LOG` format.  It is independent of the log filter.
+    #[clap(long, default_value = "info")]
+    otlp_filter: String,
     /// Directory for config and data.
     #[clap(long, parse(from_os_str), default_value_os = crate::DEFAULT_HOME.as_os_str())]
     home: PathBuf,
@@ -150,6 +214,13 @@ pub(super) enum NeardSubCommand {
     /// View DB state.
     #[clap(subcommand, name = "view_state")]
     StateViewer(StateViewerSubCommand),
+    /// Re-applies already stored blocks in the range `--from`..=`--to` against the stored
+    /// state and verifies that the resulting outcome roots and state roots match the stored
+    /// ones.  Shards are applied in parallel and the progress is reported periodically.
+    ///
+    /// Exits with a non-zero code if any mismatch is found.
+    #[clap(name = "replay")]
+    Replay(ReplayBlocksCmd),
     /// Recompresses the entire storage.  This is a slow operation which reads
     /// all the data from the database and writes them down to a new copy of the
     /// database.
@@ -178,6 +249,25 @@ pub(super) enum NeardSubCommand {
     /// tool, it is planned to be removed by the end of 2022.
     #[clap(name = "recompress_storage")]
     RecompressStorage(RecompressStorageSubCommand),
+    /// Database maintenance commands.
+    #[clap(subcommand, name = "database")]
+    Database(DatabaseSubCommand),
+    /// Shows the upcoming heights of the current epoch at which the validator
+    /// produces neither blocks nor chunks, as reported by the running node, so
+    /// that it can be restarted or upgraded without missing any.
+    #[clap(name = "maintenance-window")]
+    MaintenanceWindow(MaintenanceWindowCmd),
+    /// Shows the peers the running node is connected to along with the
+    /// protocol and client versions they run, e.g. to see how much of the
+    /// network upgraded.
+    #[clap(name = "peers")]
+    Peers(PeersCmd),
+    /// Checks `config.json` in the home directory for invalid or inconsistent
+    /// settings against itself and the genesis, the same way as the node does
+    /// on startup, and lists all the problems found.  Doesn't need the node to
+    /// be stopped.
+    #[clap(name = "validate-config")]
+    ValidateConfig(ValidateConfigCmd),
 }
 
 #[derive(Parser)]
@@ -203,6 +293,20 @@ pub(super) struct InitCmd {
     /// Specify a custom download URL for the config file.
     #[clap(long)]
     download_config_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis file.
+    #[clap(long)]
+    download_genesis_sha256: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded config file.
+    #[clap(long)]
+    download_config_sha256: Option<String>,
+    /// Download genesis records, kept in a separate file, from the given URL.
+    /// Only for testnet and betanet, whose downloaded genesis then mustn't
+    /// contain records.
+    #[clap(long)]
+    download_records_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis records file.
+    #[clap(long)]
+    download_records_sha256: Option<String>,
     /// Genesis file to use when initializing testnet (including downloading).
     #[clap(long)]
     genesis: Option<String>,
@@ -220,6 +324,29 @@ pub(super) struct InitCmd {
     /// from genesis configuration will be taken.
     #[clap(long)]
     max_gas_burnt_view: Option<Gas>,
+    /// YAML file with the parameters of a new chain, see the flags below.
+    /// The flags take precedence over the file.
+    #[clap(long)]
+    chain_params: Option<PathBuf>,
+    /// Epoch length of a new chain, in blocks.
+    #[clap(long)]
+    epoch_length: Option<BlockHeightDelta>,
+    /// Minimum delay between blocks of a new chain, in milliseconds.
+    #[clap(long)]
+    min_block_production_delay_ms: Option<u64>,
+    /// Maximum wait for approvals before producing a block on a new chain,
+    /// in milliseconds.
+    #[clap(long)]
+    max_block_production_delay_ms: Option<u64>,
+    /// Number of block producer seats of a new chain.
+    #[clap(long)]
+    num_block_producer_seats: Option<NumSeats>,
+    /// Number of chunk producer seats per shard of a new chain.
+    #[clap(long)]
+    num_chunk_producer_seats_per_shard: Option<NumSeats>,
+    /// Gas limit of the chunks of a new chain.
+    #[clap(long)]
+    gas_limit: Option<Gas>,
 }
 
 /// Warns if unsupported build of the executable is used on mainnet or testnet.
@@ -268,6 +395,13 @@ impl InitCmd {
 
         self.chain_id.as_ref().map(|chain| check_release_build(chain));
 
+        let chain_params = match self.chain_params() {
+            Ok(chain_params) => chain_params,
+            Err(err) => {
+                error!("Failed to initialize configs: {:#}", err);
+                return;
+            }
+        };
         if let Err(e) = nearcore::init_configs(
             home_dir,
             self.chain_id.as_deref(),
@@ -278,14 +412,38 @@ impl InitCmd {
             self.genesis.as_deref(),
             self.download_genesis,
             self.download_genesis_url.as_deref(),
+            self.download_genesis_sha256.as_deref(),
+            self.download_records_url.as_deref(),
+            self.download_records_sha256.as_deref(),
             self.download_config,
             self.download_config_url.as_deref(),
+            self.download_config_sha256.as_deref(),
             self.boot_nodes.as_deref(),
             self.max_gas_burnt_view,
+            &chain_params,
         ) {
             error!("Failed to initialize configs: {:#}", e);
         }
     }
+
+    fn chain_params(&self) -> anyhow::Result<ChainParams> {
+        let mut chain_params = match &self.chain_params {
+            Some(path) => ChainParams::from_file(path)?,
+            None => ChainParams::default(),
+        };
+        chain_params.epoch_length = self.epoch_length.or(chain_params.epoch_length);
+        chain_params.min_block_production_delay_ms =
+            self.min_block_production_delay_ms.or(chain_params.min_block_production_delay_ms);
+        chain_params.max_block_production_delay_ms =
+            self.max_block_production_delay_ms.or(chain_params.max_block_production_delay_ms);
+        chain_params.num_block_producer_seats =
+            self.num_block_producer_seats.or(chain_params.num_block_producer_seats);
+        chain_params.num_chunk_producer_seats_per_shard = self
+            .num_chunk_producer_seats_per_shard
+            .or(chain_params.num_chunk_producer_seats_per_shard);
+        chain_params.gas_limit = self.gas_limit.or(chain_params.gas_limit);
+        Ok(chain_params)
+    }
 }
 
 #[derive(Parser)]
@@ -329,6 +487,11 @@ pub(super) struct RunCmd {
     /// configuration will be taken.
     #[clap(long)]
     max_gas_burnt_view: Option<Gas>,
+    /// Check the chain heads on startup and move them back to consistent blocks if the store is
+    /// inconsistent, e.g. after a crash.  Inconsistencies which can't be repaired still prevent
+    /// the node from starting.
+    #[clap(long)]
+    repair_store: bool,
 }
 
 impl RunCmd {
@@ -338,6 +501,11 @@ impl RunCmd {
 
         check_release_build(&near_config.client_config.chain_id);
 
+        #[cfg(feature = "jemalloc")]
+        near_performance_metrics::allocator_stats::set_allocator(Box::new(
+            crate::jemalloc_stats::JemallocIntrospection::new(home_dir.join("heap_profiles")),
+        ));
+
         // Set current version in client config.
         near_config.client_config.version = crate::neard_version();
         // Override some parameters from command line.
@@ -381,6 +549,9 @@ impl RunCmd {
         if self.max_gas_burnt_view.is_some() {
             near_config.client_config.max_gas_burnt_view = self.max_gas_burnt_view;
         }
+        if self.repair_store {
+            near_config.config.startup_check = nearcore::StartupCheck::Repair;
+        }
 
         #[cfg(feature = "sandbox")]
         {
@@ -395,10 +566,11 @@ impl RunCmd {
             }
         }
 
+        let shutdown_timeout = near_config.config.graceful_shutdown_timeout;
         let (tx, rx) = oneshot::channel::<()>();
         let sys = actix::System::new();
         sys.block_on(async move {
-            let nearcore::NearNode { rpc_servers, .. } =
+            let nearcore::NearNode { client, network, rpc_servers, .. } =
                 nearcore::start_with_config_and_synchronization(home_dir, near_config, Some(tx))
                     .expect("start_with_config");
 
@@ -417,11 +589,28 @@ impl RunCmd {
                 "Ctrl+C"
             };
             info!(target: "neard", "Got '{}', stopping...", sig);
-            futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
-                server.stop(true).await;
-                debug!(target: "neard", "{} server stopped", name);
-            }))
-            .await;
+            let shutdown = async {
+                futures::future::join_all(rpc_servers.iter().map(|(name, server)| async move {
+                    server.stop(true).await;
+                    debug!(target: "neard", "{} server stopped", name);
+                }))
+                .await;
+                // Waits for the block being applied to be persisted.
+                match client.send(near_client::StopProcessingBlocks).await {
+                    Ok(Ok(())) => debug!(target: "neard", "Client stopped processing blocks"),
+                    Ok(Err(err)) => error!(target: "neard", "Failed to flush the store: {}", err),
+                    Err(_) => debug!(target: "neard", "Client is already stopped"),
+                }
+                let _ = network.send(near_network::types::PeerManagerMessageRequest::Stop).await;
+                debug!(target: "neard", "Disconnected from peers");
+            };
+            if tokio::time::timeout(shutdown_timeout, shutdown).await.is_err() {
+                warn!(
+                    target: "neard",
+                    "Graceful shutdown didn’t finish within {:?}, stopping anyway",
+                    shutdown_timeout
+                );
+            }
             actix::System::current().stop();
         });
         sys.run().unwrap();
@@ -441,21 +630,94 @@ pub(super) struct LocalnetCmd {
     /// Number of shards to initialize the localnet with.
     #[clap(long, default_value = "1")]
     shards: NumShards,
+    /// Comma-separated, sorted accounts at the boundaries of the shards: shard 0 has the accounts
+    /// before the first one, shard 1 the accounts from the first one up to the second one, etc.
+    /// Without them, accounts are assigned to the shards by hash.
+    #[clap(long, use_value_delimiter = true)]
+    boundary_accounts: Vec<AccountId>,
     /// Number of validators to initialize the localnet with.
     #[clap(long = "v", default_value = "4")]
     validators: NumSeats,
+    /// YAML file describing additional accounts, contracts and state records to put into the
+    /// genesis.  See `nearcore::genesis_spec` for the format.
+    #[clap(long)]
+    genesis_spec: Option<PathBuf>,
+    /// After initializing the localnet with local ports, run all its nodes in this process until
+    /// interrupted.
+    #[clap(long)]
+    in_process: bool,
 }
 
 impl LocalnetCmd {
     pub(super) fn run(self, home_dir: &Path) {
+        let genesis_spec = self.genesis_spec.map(|path| {
+            GenesisSpec::from_file(&path).unwrap_or_else(|err| {
+                error!(target: "neard", "{:#}", err);
+                std::process::exit(1);
+            })
+        });
+        let shard_layout = self.shard_layout().unwrap_or_else(|err| {
+            error!(target: "neard", "{:#}", err);
+            std::process::exit(1);
+        });
         nearcore::config::init_testnet_configs(
             home_dir,
-            self.shards,
+            shard_layout,
             self.validators,
             self.non_validators,
             &self.prefix,
+            self.in_process,
             false,
+            genesis_spec.as_ref(),
+        );
+        if self.in_process {
+            self.run_in_process(home_dir);
+        }
+    }
+
+    fn run_in_process(&self, home_dir: &Path) {
+        let home_dirs = (0..self.validators + self.non_validators)
+            .map(|i| home_dir.join(format!("{}{}", self.prefix, i)))
+            .collect::<Vec<_>>();
+        let sys = actix::System::new();
+        sys.block_on(async move {
+            let nodes = nearcore::start_localnet(&home_dirs).unwrap_or_else(|err| {
+                error!(target: "neard", "{:#}", err);
+                std::process::exit(1);
+            });
+            info!(target: "neard", "Started {} nodes in {}", nodes.len(), home_dir.display());
+            tokio::signal::ctrl_c().await.unwrap();
+            info!(target: "neard", "Got Ctrl+C, stopping...");
+            for node in &nodes {
+                for (_, server) in &node.rpc_servers {
+                    server.stop(true).await;
+                }
+                // Waits for the block being applied to be persisted.
+                let _ = node.client.send(near_client::StopProcessingBlocks).await;
+            }
+            actix::System::current().stop();
+        });
+        sys.run().unwrap();
+        info!(target: "neard", "Waiting for RocksDB to gracefully shutdown");
+        RocksDB::block_until_all_instances_are_dropped();
+    }
+
+    fn shard_layout(&self) -> anyhow::Result<ShardLayout> {
+        if self.boundary_accounts.is_empty() {
+            return Ok(ShardLayout::v0(self.shards, 0));
+        }
+        let num_shards = self.boundary_accounts.len() as NumShards + 1;
+        anyhow::ensure!(
+            self.shards == 1 || self.shards == num_shards,
+            "--shards {} doesn't match the {} shards given by --boundary-accounts",
+            self.shards,
+            num_shards
+        );
+        anyhow::ensure!(
+            self.boundary_accounts.windows(2).all(|w| w[0] < w[1]),
+            "--boundary-accounts must be sorted and unique"
         );
+        Ok(ShardLayout::v1(vec![], self.boundary_accounts.clone(), None, 1))
     }
 }
 
@@ -475,6 +737,315 @@ impl RecompressStorageSubCommand {
     }
 }
 
+#[derive(Parser)]
+pub(super) struct MaintenanceWindowCmd {
+    /// JSON RPC endpoint of the node.
+    #[clap(long, default_value = "http://127.0.0.1:3030")]
+    rpc_url: String,
+    /// Validator account, by default the one of the validator key in the
+    /// home directory.
+    #[clap(long)]
+    account_id: Option<AccountId>,
+    /// Waits until the node reaches the largest window and exits then, when
+    /// the node can be stopped, e.g. `neard maintenance-window --wait &&
+    /// systemctl restart neard`.  Fails if the window is missed.
+    #[clap(long)]
+    wait: bool,
+}
+
+impl MaintenanceWindowCmd {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let account_id = match self.account_id {
+            Some(account_id) => account_id,
+            None => {
+                let config = nearcore::config::Config::from_file(
+                    &home_dir.join(nearcore::config::CONFIG_FILENAME),
+                )?;
+                near_crypto::KeyFile::from_file(&home_dir.join(config.validator_key_file))
+                    .account_id
+            }
+        };
+        let rpc_url = self.rpc_url;
+        let wait = self.wait;
+        actix::System::new().block_on(async move {
+            let client = near_jsonrpc_client::new_client(&rpc_url);
+            let view = client
+                .EXPERIMENTAL_maintenance_windows(
+                    near_jsonrpc_primitives::types::validator::RpcMaintenanceWindowsRequest {
+                        account_id,
+                    },
+                )
+                .await
+                .map_err(|err| anyhow::anyhow!("{:?}", err))?
+                .maintenance_windows;
+            println!(
+                "Maintenance windows of {} at head {} until the next epoch starting at {}:",
+                view.account_id, view.head_height, view.next_epoch_start_height
+            );
+            for window in &view.windows {
+                println!(
+                    "  {}..{} ({} blocks)",
+                    window.start,
+                    window.end,
+                    window.end - window.start
+                );
+            }
+            // The earliest of the longest windows.
+            let largest = view
+                .windows
+                .iter()
+                .max_by_key(|window| (window.end - window.start, std::cmp::Reverse(window.start)))
+                .ok_or_else(|| anyhow::anyhow!("No maintenance window in the current epoch"))?;
+            println!("Largest window: {}..{}", largest.start, largest.end);
+            if !wait {
+                return Ok(());
+            }
+
+            loop {
+                let status = client.status().await.map_err(|err| anyhow::anyhow!("{:?}", err))?;
+                let next_height = status.sync_info.latest_block_height + 1;
+                if next_height >= largest.end {
+                    anyhow::bail!("Missed the window, the node is at height {}", next_height - 1);
+                }
+                if next_height >= largest.start {
+                    println!(
+                        "The node can be stopped for {} blocks, until height {}",
+                        largest.end - next_height,
+                        largest.end
+                    );
+                    return Ok(());
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        })
+    }
+}
+
+#[derive(Parser)]
+pub(super) struct ValidateConfigCmd {}
+
+impl ValidateConfigCmd {
+    pub(super) fn run(self, home_dir: &Path) -> anyhow::Result<()> {
+        let config =
+            nearcore::config::Config::from_file(&home_dir.join(nearcore::config::CONFIG_FILENAME))?;
+        let genesis_file = home_dir.join(&config.genesis_file);
+        let genesis_config = near_chain_configs::GenesisConfig::from_file(&genesis_file)
+            .with_context(|| format!("Failed to read genesis from {}", genesis_file.display()))?;
+        nearcore::config_validate::validate_config(home_dir, &config, &genesis_config)?;
+        println!("config.json is valid");
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub(super) struct PeersCmd {
+    /// JSON RPC endpoint of the node.
+    #[clap(long, default_value = "http://127.0.0.1:3030")]
+    rpc_url: String,
+}
+
+impl PeersCmd {
+    pub(super) fn run(self) -> anyhow::Result<()> {
+        let rpc_url = self.rpc_url;
+        actix::System::new().block_on(async move {
+            let client = near_jsonrpc_client::new_client(&rpc_url);
+            let network_info =
+                client.network_info().await.map_err(|err| anyhow::anyhow!("{:?}", err))?;
+            println!(
+                "{:<54} {:<22} {:<28} {:>8} {:>10} {:>10}",
+                "PEER", "ADDRESS", "CLIENT VERSION", "PROTOCOL", "HEIGHT", "CONNECTED"
+            );
+            let unknown = || "?".to_string();
+            for peer in &network_info.active_peers {
+                println!(
+                    "{:<54} {:<22} {:<28} {:>8} {:>10} {:>10}",
+                    peer.id.to_string(),
+                    peer.addr.map_or_else(unknown, |addr| addr.to_string()),
+                    peer.client_version.as_ref().map_or_else(unknown, |client_version| format!(
+                        "{} ({})",
+                        client_version.version, client_version.build
+                    )),
+                    peer.protocol_version.map_or_else(unknown, |version| version.to_string()),
+                    peer.height.map_or_else(unknown, |height| height.to_string()),
+                    peer.connection_age_secs.map_or_else(unknown, |secs| format!("{}s", secs)),
+                );
+            }
+            println!("{} peers connected", network_info.num_active_peers);
+            Ok(())
+        })
+    }
+}
+
+#[derive(Parser)]
+pub(super) enum DatabaseSubCommand {
+    /// Restores the database from the snapshot which is created before a
+    /// database migration (unless `use_db_migration_snapshot` is disabled in
+    /// `config.json`) and left behind if the migration fails or gets
+    /// interrupted.  The node must be stopped.  The migration is attempted
+    /// again on the next start of the node.
+    #[clap(name = "rollback-migration")]
+    RollbackMigration,
+    /// Copies the data needed to run a non-archival node from the given
+    /// height onwards out of an archival database into a new database.  The
+    /// copy starts at the beginning of the epoch containing the height and
+    /// includes the state of the shards tracked according to `config.json`.
+    /// The node must be stopped.  Run the node with the new database with
+    /// `archive` disabled.
+    #[clap(name = "trim")]
+    Trim(TrimDatabaseCmd),
+    /// Checks that the chunks of the tracked shards included in the blocks
+    /// of the given range of heights decode, match their headers and that
+    /// the outcomes of applying them are stored.  The ranges of heights with
+    /// problems are reported.  The node must be stopped.
+    #[clap(name = "check-chunks")]
+    CheckChunks(CheckChunksCmd),
+    /// Adds the accounts with contracts in the state of the tracked shards
+    /// to the index of accounts by contract code hash queried by
+    /// `EXPERIMENTAL_accounts_by_code_hash`.  The node indexes the contracts
+    /// deployed by the chunks it applies, so this is only needed once for
+    /// the contracts deployed before, e.g. after state sync or an upgrade.
+    /// The node must be stopped.
+    #[clap(name = "index-contracts")]
+    IndexContracts,
+    /// Recomputes the reference counts of the trie nodes and values in the
+    /// State column by walking the state of the tracked shards as of the
+    /// head of the chain and the trie changes of the blocks which aren't
+    /// garbage collected yet, and reports those which differ from the
+    /// stored ones.  Needs memory proportional to the number of nodes in
+    /// the state.  The node must be stopped.
+    #[clap(name = "rc-audit")]
+    RcAudit(RcAuditCmd),
+}
+
+#[derive(Args)]
+pub(super) struct TrimDatabaseCmd {
+    /// Height from which to keep the data.
+    #[clap(long)]
+    from_height: BlockHeight,
+    /// Directory where to save the new database.
+    #[clap(long)]
+    output_dir: PathBuf,
+}
+
+#[derive(Args)]
+pub(super) struct CheckChunksCmd {
+    /// Height of the first block to check.
+    #[clap(long = "from")]
+    from_height: BlockHeight,
+    /// Height of the last block to check.
+    #[clap(long = "to")]
+    to_height: BlockHeight,
+    /// Deletes the chunks which can't be decoded or don't match their
+    /// headers, so that the node stops serving them.
+    #[clap(long)]
+    delete: bool,
+}
+
+#[derive(Args)]
+pub(super) struct RcAuditCmd {
+    /// Replaces the stored reference counts which differ by the expected
+    /// ones.  Missing nodes and values can only be restored if the trie
+    /// changes of some block contain them.
+    #[clap(long)]
+    repair: bool,
+}
+
+/// Maximum number of mismatching reference counts logged one by one.
+const MAX_LOGGED_RC_MISMATCHES: usize = 100;
+
+/// Merges the sorted heights into ranges of consecutive heights.
+fn height_ranges(
+    heights: impl IntoIterator<Item = BlockHeight>,
+) -> Vec<(BlockHeight, BlockHeight)> {
+    let mut ranges: Vec<(BlockHeight, BlockHeight)> = vec![];
+    for height in heights {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == height || *end + 1 == height => *end = height,
+            _ => ranges.push((height, height)),
+        }
+    }
+    ranges
+}
+
+impl DatabaseSubCommand {
+    pub(super) fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        match self {
+            DatabaseSubCommand::RollbackMigration => {
+                let config = nearcore::config::Config::from_file(
+                    &home_dir.join(nearcore::config::CONFIG_FILENAME),
+                )?;
+                let db_version = nearcore::rollback_store_migration(home_dir, &config)?;
+                info!(target: "neard", "Restored the database with DB version {}", db_version);
+            }
+            DatabaseSubCommand::Trim(cmd) => {
+                let near_config = nearcore::config::load_config(home_dir, genesis_validation);
+                nearcore::trim_database(home_dir, &near_config, cmd.from_height, &cmd.output_dir)?;
+            }
+            DatabaseSubCommand::CheckChunks(cmd) => {
+                let near_config = nearcore::config::load_config(home_dir, genesis_validation);
+                let bad_chunks = nearcore::check_chunks(
+                    home_dir,
+                    &near_config,
+                    cmd.from_height,
+                    cmd.to_height,
+                    cmd.delete,
+                )?;
+                if bad_chunks.is_empty() {
+                    info!(target: "neard", "All chunks are fine");
+                    return Ok(());
+                }
+                let ranges = height_ranges(bad_chunks.iter().map(|bad_chunk| bad_chunk.height));
+                for (start, end) in ranges.iter() {
+                    warn!(target: "neard", "Chunks with problems at heights {}..={}", start, end);
+                }
+                anyhow::bail!(
+                    "found {} chunks with problems in {} ranges of heights",
+                    bad_chunks.len(),
+                    ranges.len()
+                );
+            }
+            DatabaseSubCommand::IndexContracts => {
+                let near_config = nearcore::config::load_config(home_dir, genesis_validation);
+                nearcore::index_contracts(home_dir, &near_config)?;
+            }
+            DatabaseSubCommand::RcAudit(cmd) => {
+                let near_config = nearcore::config::load_config(home_dir, genesis_validation);
+                let mismatches = nearcore::rc_audit(home_dir, &near_config, cmd.repair)?;
+                if mismatches.is_empty() {
+                    info!(target: "neard", "All reference counts are fine");
+                    return Ok(());
+                }
+                for mismatch in mismatches.iter().take(MAX_LOGGED_RC_MISMATCHES) {
+                    warn!(
+                        target: "neard",
+                        "Reference count of {} in shard {} is {}, expected {}{}",
+                        mismatch.hash,
+                        mismatch.shard_uid.shard_id,
+                        mismatch.stored,
+                        mismatch.expected,
+                        if mismatch.repaired { " (repaired)" } else { "" }
+                    );
+                }
+                let missing = mismatches.iter().filter(|mismatch| mismatch.stored <= 0).count();
+                let unrepaired = mismatches.iter().filter(|mismatch| !mismatch.repaired).count();
+                anyhow::ensure!(
+                    unrepaired == 0,
+                    "found {} mismatching reference counts ({} nodes or values missing), {} not repaired",
+                    mismatches.len(),
+                    missing,
+                    unrepaired
+                );
+                info!(target: "neard", "Repaired {} reference counts", mismatches.len());
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -490,6 +1061,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn replay_range() {
+        let cmd = NeardCmd::parse_from(&["test", "replay", "--from=10", "--to=20"]);
+        assert!(matches!(cmd.subcmd, NeardSubCommand::Replay(_)));
+        assert!(NeardCmd::try_parse_from(&["test", "replay", "--from=10"]).is_err());
+    }
+
+    #[test]
+    fn database_trim() {
+        let cmd = NeardCmd::parse_from(&[
+            "test",
+            "database",
+            "trim",
+            "--from-height=100",
+            "--output-dir=/tmp/trimmed",
+        ]);
+        if let NeardSubCommand::Database(DatabaseSubCommand::Trim(scmd)) = cmd.subcmd {
+            assert_eq!(scmd.from_height, 100);
+            assert_eq!(scmd.output_dir, PathBuf::from("/tmp/trimmed"));
+        } else {
+            panic!("incorrect subcommand");
+        }
+        assert!(
+            NeardCmd::try_parse_from(&["test", "database", "trim", "--from-height=100"]).is_err()
+        );
+    }
+
+    #[test]
+    fn database_check_chunks() {
+        let cmd = NeardCmd::parse_from(&[
+            "test",
+            "database",
+            "check-chunks",
+            "--from=10",
+            "--to=20",
+            "--delete",
+        ]);
+        if let NeardSubCommand::Database(DatabaseSubCommand::CheckChunks(scmd)) = cmd.subcmd {
+            assert_eq!((scmd.from_height, scmd.to_height), (10, 20));
+            assert!(scmd.delete);
+        } else {
+            panic!("incorrect subcommand");
+        }
+        assert_eq!(height_ranges([3, 3, 4, 5, 8, 10, 11]), vec![(3, 5), (8, 8), (10, 11)]);
+    }
+
     #[test]
     fn equal_no_value_syntax() {
         assert!(NeardCmd::try_parse_from(&[
@@ -0,0 +1,98 @@
+//! Statistics and heap profiles of jemalloc, the global allocator of neard.
+use near_performance_metrics::allocator_stats::{
+    fragmentation, AllocatorIntrospection, AllocatorStats, AllocatorStatsError, ArenaStats,
+};
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tikv_jemalloc_ctl::{epoch, raw, stats};
+
+pub(crate) struct JemallocIntrospection {
+    /// Directory the heap profiles are dumped to.
+    heap_profile_dir: PathBuf,
+}
+
+impl JemallocIntrospection {
+    pub(crate) fn new(heap_profile_dir: PathBuf) -> Self {
+        Self { heap_profile_dir }
+    }
+}
+
+fn allocator_error(err: tikv_jemalloc_ctl::Error) -> AllocatorStatsError {
+    AllocatorStatsError::Allocator(err.to_string())
+}
+
+/// Reads a statistic whose name is only known at runtime, e.g. one of an arena.
+fn read_stat<T: Copy>(name: &str) -> Result<T, AllocatorStatsError> {
+    let name = format!("{}\0", name);
+    unsafe { raw::read::<T>(name.as_bytes()) }.map_err(allocator_error)
+}
+
+impl AllocatorIntrospection for JemallocIntrospection {
+    fn stats(&self) -> Result<AllocatorStats, AllocatorStatsError> {
+        // The statistics are cached by jemalloc until the epoch is advanced.
+        epoch::advance().map_err(allocator_error)?;
+        let allocated = stats::allocated::read().map_err(allocator_error)? as u64;
+        let active = stats::active::read().map_err(allocator_error)? as u64;
+        let page_size = unsafe { raw::read::<usize>(b"arenas.page\0") }.map_err(allocator_error)?;
+        let num_arenas =
+            unsafe { raw::read::<u32>(b"arenas.narenas\0") }.map_err(allocator_error)?;
+        let mut arenas = vec![];
+        for index in 0..num_arenas {
+            if !read_stat::<bool>(&format!("arena.{}.initialized", index))? {
+                continue;
+            }
+            let pages = |name: &str| -> Result<u64, AllocatorStatsError> {
+                let pages = read_stat::<usize>(&format!("stats.arenas.{}.{}", index, name))?;
+                Ok((pages * page_size) as u64)
+            };
+            let bytes = |name: &str| -> Result<u64, AllocatorStatsError> {
+                Ok(read_stat::<usize>(&format!("stats.arenas.{}.{}", index, name))? as u64)
+            };
+            arenas.push(ArenaStats {
+                index,
+                threads: read_stat::<u32>(&format!("stats.arenas.{}.nthreads", index))?,
+                active: pages("pactive")?,
+                dirty: pages("pdirty")?,
+                muzzy: pages("pmuzzy")?,
+                resident: bytes("resident")?,
+                mapped: bytes("mapped")?,
+            });
+        }
+        Ok(AllocatorStats {
+            allocator: "jemalloc".to_string(),
+            allocated,
+            active,
+            metadata: stats::metadata::read().map_err(allocator_error)? as u64,
+            resident: stats::resident::read().map_err(allocator_error)? as u64,
+            mapped: stats::mapped::read().map_err(allocator_error)? as u64,
+            retained: stats::retained::read().map_err(allocator_error)? as u64,
+            fragmentation: fragmentation(allocated, active),
+            arenas,
+        })
+    }
+
+    fn dump_heap_profile(&self) -> Result<PathBuf, AllocatorStatsError> {
+        // `opt.prof` exists only if jemalloc is built with profiling.
+        let enabled = unsafe { raw::read::<bool>(b"opt.prof\0") }.unwrap_or(false);
+        if !enabled {
+            return Err(AllocatorStatsError::ProfilingDisabled(
+                "build neard with the jemalloc_profiling feature and start it with \
+                 _RJEM_MALLOC_CONF=prof:true"
+                    .to_string(),
+            ));
+        }
+        std::fs::create_dir_all(&self.heap_profile_dir)
+            .map_err(|err| AllocatorStatsError::Allocator(err.to_string()))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = self.heap_profile_dir.join(format!("heap-{}.prof", timestamp.as_millis()));
+        let c_path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| AllocatorStatsError::Allocator(err.to_string()))?;
+        // jemalloc copies the path before the call returns.
+        unsafe { raw::write::<*const c_char>(b"prof.dump\0", c_path.as_ptr()) }
+            .map_err(allocator_error)?;
+        Ok(path)
+    }
+}
@@ -1,4 +1,6 @@
 mod cli;
+#[cfg(feature = "jemalloc")]
+mod jemalloc_stats;
 
 use near_primitives::version::{Version, DB_VERSION, PROTOCOL_VERSION};
 
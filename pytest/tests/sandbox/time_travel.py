@@ -0,0 +1,37 @@
+#!/usr/bin/env python3
+# test moving the block timestamp forward and overriding the gas price within a
+# sandbox node. This will fail if blocks produced afterwards don't reflect the
+# requested timestamp and gas price.
+
+import sys, time
+import pathlib
+
+sys.path.append(str(pathlib.Path(__file__).resolve().parents[2] / 'lib'))
+
+from cluster import start_cluster
+from utils import figure_out_sandbox_binary
+
+# startup a RPC node
+ONE_YEAR_NS = 365 * 24 * 60 * 60 * 10**9
+GAS_PRICE = 123 * 10**9
+CONFIG = figure_out_sandbox_binary()
+nodes = start_cluster(1, 0, 1, CONFIG, [["epoch_length", 10]], {})
+
+block = nodes[0].get_block(nodes[0].get_latest_block().hash)['result']
+target_timestamp = int(block['header']['timestamp_nanosec']) + ONE_YEAR_NS
+
+res = nodes[0].json_rpc('sandbox_set_timestamp', {
+    "timestamp": str(target_timestamp),
+})
+assert 'error' not in res, res
+res = nodes[0].json_rpc('sandbox_set_gas_price', {
+    "gas_price": str(GAS_PRICE),
+})
+assert 'error' not in res, res
+
+# wait a little for new blocks to be produced
+time.sleep(3)
+
+block = nodes[0].get_block(nodes[0].get_latest_block().hash)['result']
+assert int(block['header']['timestamp_nanosec']) >= target_timestamp, block
+assert int(block['header']['gas_price']) == GAS_PRICE, block
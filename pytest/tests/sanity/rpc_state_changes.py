@@ -154,6 +154,11 @@ def test_changes_with_new_account_with_access_key():
                 new_key.account_id + '_non_existing2'
             ],
         },
+        {
+            "block_id": block_hash,
+            "changes_type": "access_key_changes_by_public_key",
+            "public_keys": [new_key.pk],
+        },
     ]:
         assert_changes_response(request=request,
                                 expected_response=expected_response)
@@ -2515,6 +2515,17 @@ impl<'a> VMLogic<'a> {
         Ok(())
     }
 
+    /// Marks the start of a metered block of wasm code.  Only available when the runner is built
+    /// with its profiler, which injects the calls into the contracts and records them.
+    ///
+    /// # Cost
+    ///
+    /// 0
+    #[cfg(feature = "profiler")]
+    pub fn profile_block(&mut self, _block: u32) -> Result<()> {
+        Ok(())
+    }
+
     /// DEPRECATED
     /// Creates an iterator object inside the host. Returns the identifier that uniquely
     /// differentiates the given iterator from other iterators that can be simultaneously created.
@@ -10,9 +10,9 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", feature = "wasmtime_vm"))]
 use crate::prepare;
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", feature = "wasmtime_vm"))]
 use near_vm_errors::{FunctionCallError, VMError};
 
 #[derive(Debug, Clone, BorshSerialize)]
@@ -26,6 +26,10 @@ enum ContractCacheKey {
         vm_kind: VMKind,
         vm_hash: u64,
     },
+    /// Contracts prepared by the profiler import `profile_block`, so they can't be shared with
+    /// the nodes built without it.
+    #[cfg(feature = "profiler")]
+    Profiled(Box<ContractCacheKey>),
 }
 
 #[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
@@ -63,10 +67,12 @@ pub fn get_contract_cache_key(
         vm_kind,
         vm_hash: vm_hash(vm_kind),
     };
+    #[cfg(feature = "profiler")]
+    let key = ContractCacheKey::Profiled(Box::new(key));
     near_primitives::hash::hash(&key.try_to_vec().unwrap())
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", feature = "wasmtime_vm"))]
 fn cache_error(
     error: &CompilationError,
     key: &CryptoHash,
@@ -78,7 +84,7 @@ fn cache_error(
     Ok(())
 }
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(any(target_arch = "x86_64", feature = "wasmtime_vm"))]
 pub fn into_vm_result<T>(
     res: Result<Result<T, CompilationError>, CacheError>,
 ) -> Result<T, VMError> {
@@ -120,7 +126,7 @@ impl fmt::Debug for MockCompiledContractCache {
     }
 }
 
-#[cfg(all(not(feature = "no_cache"), target_arch = "x86_64"))]
+#[cfg(all(not(feature = "no_cache"), any(target_arch = "x86_64", feature = "wasmtime_vm")))]
 const CACHE_SIZE: usize = 128;
 
 #[cfg(all(feature = "wasmer0_vm", not(feature = "no_cache"), target_arch = "x86_64"))]
@@ -136,6 +142,11 @@ static WASMER2_CACHE: once_cell::sync::Lazy<
     >,
 > = once_cell::sync::Lazy::new(|| near_cache::SyncLruCache::new(CACHE_SIZE));
 
+#[cfg(all(feature = "wasmtime_vm", not(feature = "no_cache")))]
+static WASMTIME_CACHE: once_cell::sync::Lazy<
+    near_cache::SyncLruCache<CryptoHash, Result<wasmtime::Module, CompilationError>>,
+> = once_cell::sync::Lazy::new(|| near_cache::SyncLruCache::new(CACHE_SIZE));
+
 #[cfg(all(feature = "wasmer0_vm", target_arch = "x86_64"))]
 pub mod wasmer0_cache {
     use super::*;
@@ -149,6 +160,7 @@ pub mod wasmer0_cache {
         config: &VMConfig,
     ) -> Result<wasmer_runtime::Module, CompilationError> {
         let _span = tracing::debug_span!(target: "vm", "compile_module").entered();
+        let _timer = crate::metrics::COMPILATION_TIME.with_label_values(&["wasmer0"]).start_timer();
 
         let prepared_code =
             prepare::prepare_contract(code, config).map_err(CompilationError::PrepareError)?;
@@ -261,6 +273,7 @@ pub mod wasmer2_cache {
         config: &VMConfig,
     ) -> Result<VMArtifact, CompilationError> {
         let _span = tracing::debug_span!(target: "vm", "compile_module_wasmer2").entered();
+        let _timer = crate::metrics::COMPILATION_TIME.with_label_values(&["wasmer2"]).start_timer();
         let prepared_code =
             prepare::prepare_contract(code, config).map_err(CompilationError::PrepareError)?;
         Wasmer2VM::new(config.clone()).compile_uncached(&prepared_code)
@@ -355,6 +368,106 @@ pub mod wasmer2_cache {
     }
 }
 
+#[cfg(feature = "wasmtime_vm")]
+pub mod wasmtime_cache {
+    use crate::wasmtime_runner::engine;
+    use near_primitives::contract::ContractCode;
+    use wasmtime::Module;
+
+    use super::*;
+
+    pub(crate) fn compile_module_wasmtime(
+        code: &[u8],
+        config: &VMConfig,
+    ) -> Result<Module, CompilationError> {
+        let _span = tracing::debug_span!(target: "vm", "compile_module_wasmtime").entered();
+        let _timer =
+            crate::metrics::COMPILATION_TIME.with_label_values(&["wasmtime"]).start_timer();
+        let prepared_code =
+            prepare::prepare_contract(code, config).map_err(CompilationError::PrepareError)?;
+        Module::new(engine(), prepared_code)
+            .map_err(|e| CompilationError::WasmerCompileError { msg: e.to_string() })
+    }
+
+    pub(crate) fn compile_and_serialize_wasmtime(
+        wasm_code: &[u8],
+        key: &CryptoHash,
+        config: &VMConfig,
+        cache: &dyn CompiledContractCache,
+    ) -> Result<Result<Module, CompilationError>, CacheError> {
+        let _span = tracing::debug_span!(target: "vm", "compile_and_serialize_wasmtime").entered();
+
+        let module = match compile_module_wasmtime(wasm_code, config) {
+            Ok(module) => module,
+            Err(err) => {
+                cache_error(&err, key, cache)?;
+                return Ok(Err(err));
+            }
+        };
+
+        let code =
+            module.serialize().map_err(|_e| CacheError::SerializationError { hash: key.0 })?;
+        let serialized = CacheRecord::Code(code).try_to_vec().unwrap();
+        cache.put(key.as_ref(), &serialized).map_err(|_io_err| CacheError::WriteError)?;
+        Ok(Ok(module))
+    }
+
+    fn deserialize_wasmtime(
+        serialized: &[u8],
+    ) -> Result<Result<Module, CompilationError>, CacheError> {
+        let _span = tracing::debug_span!(target: "vm", "deserialize_wasmtime").entered();
+
+        let record = CacheRecord::try_from_slice(serialized)
+            .map_err(|_e| CacheError::DeserializationError)?;
+        let serialized_module = match record {
+            CacheRecord::CompileModuleError(err) => return Ok(Err(err)),
+            CacheRecord::Code(code) => code,
+        };
+        unsafe {
+            // (UN-)SAFETY: the `serialized_module` must have been produced by a prior call to
+            // `serialize`.  Wasmtime checks that the module was serialized by the same version
+            // and with the same configuration, but not that the data is intact.
+            let module = Module::deserialize(engine(), serialized_module)
+                .map_err(|_| CacheError::DeserializationError)?;
+            Ok(Ok(module))
+        }
+    }
+
+    fn compile_module_cached_wasmtime_impl(
+        key: CryptoHash,
+        code: &ContractCode,
+        config: &VMConfig,
+        cache: Option<&dyn CompiledContractCache>,
+    ) -> Result<Result<Module, CompilationError>, CacheError> {
+        match cache {
+            None => Ok(compile_module_wasmtime(code.code(), config)),
+            Some(cache) => {
+                let serialized = cache.get(&key.0).map_err(|_io_err| CacheError::ReadError)?;
+                match serialized {
+                    Some(serialized) => deserialize_wasmtime(serialized.as_slice()),
+                    None => compile_and_serialize_wasmtime(code.code(), &key, config, cache),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn compile_module_cached_wasmtime(
+        code: &ContractCode,
+        config: &VMConfig,
+        cache: Option<&dyn CompiledContractCache>,
+    ) -> Result<Result<Module, CompilationError>, CacheError> {
+        let key = get_contract_cache_key(code, VMKind::Wasmtime, config);
+
+        #[cfg(not(feature = "no_cache"))]
+        return WASMTIME_CACHE.get_or_try_put(key, |key| {
+            compile_module_cached_wasmtime_impl(*key, code, config, cache)
+        });
+
+        #[cfg(feature = "no_cache")]
+        return compile_module_cached_wasmtime_impl(key, code, config, cache);
+    }
+}
+
 pub fn precompile_contract_vm(
     vm_kind: VMKind,
     wasm_code: &ContractCode,
@@ -387,7 +500,16 @@ pub fn precompile_contract_vm(
         }
         #[cfg(not(all(feature = "wasmer2_vm", target_arch = "x86_64")))]
         VMKind::Wasmer2 => panic!("Wasmer2 is not enabled!"),
-        VMKind::Wasmtime => panic!("Not yet supported"),
+        #[cfg(feature = "wasmtime_vm")]
+        VMKind::Wasmtime => Ok(wasmtime_cache::compile_and_serialize_wasmtime(
+            wasm_code.code(),
+            &key,
+            config,
+            cache,
+        )?
+        .map(|_| ContractPrecompilatonResult::ContractCompiled)),
+        #[cfg(not(feature = "wasmtime_vm"))]
+        VMKind::Wasmtime => panic!("Wasmtime is not enabled!"),
     }
 }
 
@@ -0,0 +1,367 @@
+//! Differential execution of contracts on a second VM, to catch divergences between VM backends.
+//!
+//! When enabled with [`crate::set_differential_vm_kind`], every contract call made through
+//! [`crate::run`] is first executed on the differential VM and then on the regular one, and the
+//! results are compared.  Divergences are logged and counted in the
+//! `near_vm_differential_divergences_total` metric.  Only the result of the regular VM is used.
+//!
+//! The differential execution reads the state through the regular external, but its writes and
+//! created receipts are kept in a [`ShadowExternal`] and discarded afterwards, so it never affects
+//! the state.
+use std::collections::HashMap;
+
+use near_primitives::config::VMConfig;
+use near_primitives::contract::ContractCode;
+use near_primitives::runtime::fees::RuntimeFeesConfig;
+use near_primitives::types::{AccountId, Balance, CompiledContractCache, Gas, StorageUsage};
+#[cfg(feature = "protocol_feature_function_call_weight")]
+use near_primitives::types::{GasDistribution, GasWeight};
+use near_primitives::version::ProtocolVersion;
+use near_vm_errors::{VMError, VMLogicError};
+use near_vm_logic::types::{PromiseResult, PublicKey, ReceiptIndex};
+use near_vm_logic::{External, ReturnData, VMContext, VMOutcome, ValuePtr};
+
+use crate::metrics;
+use crate::vm_kind::VMKind;
+
+/// Executes the call on the given VM without modifying the state of `ext`.
+pub(crate) fn run_shadow(
+    vm_kind: VMKind,
+    code: &ContractCode,
+    method_name: &str,
+    ext: &mut dyn External,
+    context: VMContext,
+    wasm_config: &VMConfig,
+    fees_config: &RuntimeFeesConfig,
+    promise_results: &[PromiseResult],
+    current_protocol_version: ProtocolVersion,
+    cache: Option<&dyn CompiledContractCache>,
+) -> (Option<VMOutcome>, Option<VMError>) {
+    let _span = tracing::debug_span!(target: "vm", "run_shadow", ?vm_kind).entered();
+    let runtime = match vm_kind.runtime(wasm_config.clone()) {
+        Some(runtime) => runtime,
+        None => panic!("the {:?} runtime has not been enabled at compile time", vm_kind),
+    };
+    let mut shadow_ext = ShadowExternal::new(ext);
+    runtime.run(
+        code,
+        method_name,
+        &mut shadow_ext,
+        context,
+        fees_config,
+        promise_results,
+        current_protocol_version,
+        cache,
+    )
+}
+
+/// Logs a divergence between the results of the regular and the differential execution.
+pub(crate) fn compare(
+    code: &ContractCode,
+    method_name: &str,
+    vm_kind: VMKind,
+    result: &(Option<VMOutcome>, Option<VMError>),
+    differential_vm_kind: VMKind,
+    differential_result: &(Option<VMOutcome>, Option<VMError>),
+) {
+    let summary = CallSummary::new(result);
+    let differential_summary = CallSummary::new(differential_result);
+    if summary == differential_summary {
+        return;
+    }
+    metrics::DIFFERENTIAL_DIVERGENCES
+        .with_label_values(&[&format!("{:?}", vm_kind), &format!("{:?}", differential_vm_kind)])
+        .inc();
+    tracing::warn!(
+        target: "vm",
+        code_hash = %code.hash(),
+        %method_name,
+        ?vm_kind,
+        ?summary,
+        ?differential_vm_kind,
+        ?differential_summary,
+        "Contract call diverged between VMs"
+    );
+}
+
+/// The parts of a call result which are expected to be the same on every VM.
+#[derive(Debug, PartialEq)]
+struct CallSummary {
+    outcome: Option<OutcomeSummary>,
+    error: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+struct OutcomeSummary {
+    balance: Balance,
+    storage_usage: StorageUsage,
+    return_data: ReturnData,
+    burnt_gas: Gas,
+    used_gas: Gas,
+    logs: Vec<String>,
+}
+
+impl CallSummary {
+    fn new((outcome, error): &(Option<VMOutcome>, Option<VMError>)) -> Self {
+        Self {
+            outcome: outcome.as_ref().map(|outcome| OutcomeSummary {
+                balance: outcome.balance,
+                storage_usage: outcome.storage_usage,
+                return_data: outcome.return_data.clone(),
+                burnt_gas: outcome.burnt_gas,
+                used_gas: outcome.used_gas,
+                logs: outcome.logs.clone(),
+            }),
+            error: error.as_ref().map(|error| format!("{:?}", error)),
+        }
+    }
+}
+
+struct ShadowValuePtr(Vec<u8>);
+
+impl ValuePtr for ShadowValuePtr {
+    fn len(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    fn deref(&self) -> Result<Vec<u8>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// External which reads through to another one but keeps all modifications to itself.
+struct ShadowExternal<'a> {
+    ext: &'a mut dyn External,
+    /// Values written by the call, `None` for removed keys.
+    writes: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Prefixes removed by the call.  Keys written after the removal are in `writes`.
+    removed_prefixes: Vec<Vec<u8>>,
+    num_receipts: ReceiptIndex,
+    #[cfg(feature = "protocol_feature_function_call_weight")]
+    gas_weight_sum: u128,
+}
+
+impl<'a> ShadowExternal<'a> {
+    fn new(ext: &'a mut dyn External) -> Self {
+        Self {
+            ext,
+            writes: HashMap::new(),
+            removed_prefixes: vec![],
+            num_receipts: 0,
+            #[cfg(feature = "protocol_feature_function_call_weight")]
+            gas_weight_sum: 0,
+        }
+    }
+
+    /// Returns the value of the key if it has been modified by the call.
+    fn modified_value(&self, key: &[u8]) -> Option<Option<&Vec<u8>>> {
+        if let Some(value) = self.writes.get(key) {
+            return Some(value.as_ref());
+        }
+        if self.removed_prefixes.iter().any(|prefix| key.starts_with(prefix)) {
+            return Some(None);
+        }
+        None
+    }
+
+    fn check_receipt_index(&self, receipt_index: ReceiptIndex) {
+        assert!(receipt_index < self.num_receipts, "unknown receipt index {}", receipt_index);
+    }
+}
+
+type Result<T> = std::result::Result<T, VMLogicError>;
+
+impl External for ShadowExternal<'_> {
+    fn storage_set(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.writes.insert(key.to_vec(), Some(value.to_vec()));
+        Ok(())
+    }
+
+    fn storage_get<'b>(&'b self, key: &[u8]) -> Result<Option<Box<dyn ValuePtr + 'b>>> {
+        match self.modified_value(key) {
+            Some(Some(value)) => Ok(Some(Box::new(ShadowValuePtr(value.clone())))),
+            Some(None) => Ok(None),
+            None => self.ext.storage_get(key),
+        }
+    }
+
+    fn storage_remove(&mut self, key: &[u8]) -> Result<()> {
+        self.writes.insert(key.to_vec(), None);
+        Ok(())
+    }
+
+    fn storage_remove_subtree(&mut self, prefix: &[u8]) -> Result<()> {
+        self.writes.retain(|key, _| !key.starts_with(prefix));
+        self.removed_prefixes.push(prefix.to_vec());
+        Ok(())
+    }
+
+    fn storage_has_key(&mut self, key: &[u8]) -> Result<bool> {
+        match self.modified_value(key) {
+            Some(value) => Ok(value.is_some()),
+            None => self.ext.storage_has_key(key),
+        }
+    }
+
+    fn create_receipt(
+        &mut self,
+        receipt_indices: Vec<ReceiptIndex>,
+        _receiver_id: AccountId,
+    ) -> Result<ReceiptIndex> {
+        for receipt_index in receipt_indices {
+            self.check_receipt_index(receipt_index);
+        }
+        self.num_receipts += 1;
+        Ok(self.num_receipts - 1)
+    }
+
+    fn append_action_create_account(&mut self, receipt_index: ReceiptIndex) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_deploy_contract(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _code: Vec<u8>,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_function_call(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _method_name: Vec<u8>,
+        _arguments: Vec<u8>,
+        _attached_deposit: Balance,
+        _prepaid_gas: Gas,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    #[cfg(feature = "protocol_feature_function_call_weight")]
+    fn append_action_function_call_weight(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _method_name: Vec<u8>,
+        _arguments: Vec<u8>,
+        _attached_deposit: Balance,
+        _prepaid_gas: Gas,
+        gas_weight: GasWeight,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        self.gas_weight_sum += gas_weight.0 as u128;
+        Ok(())
+    }
+
+    fn append_action_transfer(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _amount: Balance,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_stake(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _stake: Balance,
+        _public_key: PublicKey,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_add_key_with_full_access(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _public_key: PublicKey,
+        _nonce: u64,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_add_key_with_function_call(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _public_key: PublicKey,
+        _nonce: u64,
+        _allowance: Option<Balance>,
+        _receiver_id: AccountId,
+        _method_names: Vec<Vec<u8>>,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_delete_key(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _public_key: PublicKey,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn append_action_delete_account(
+        &mut self,
+        receipt_index: ReceiptIndex,
+        _beneficiary_id: AccountId,
+    ) -> Result<()> {
+        self.check_receipt_index(receipt_index);
+        Ok(())
+    }
+
+    fn get_touched_nodes_count(&self) -> u64 {
+        self.ext.get_touched_nodes_count()
+    }
+
+    fn validator_stake(&self, account_id: &AccountId) -> Result<Option<Balance>> {
+        self.ext.validator_stake(account_id)
+    }
+
+    fn validator_total_stake(&self) -> Result<Balance> {
+        self.ext.validator_total_stake()
+    }
+
+    #[cfg(feature = "protocol_feature_function_call_weight")]
+    fn distribute_unused_gas(&mut self, _gas: Gas) -> GasDistribution {
+        if self.gas_weight_sum != 0 {
+            GasDistribution::All
+        } else {
+            GasDistribution::NoRatios
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_vm_logic::mocks::mock_external::MockedExternal;
+
+    #[test]
+    fn test_shadow_external_keeps_writes() {
+        let mut ext = MockedExternal::new();
+        ext.storage_set(b"a", b"1").unwrap();
+        ext.storage_set(b"b1", b"2").unwrap();
+        ext.storage_set(b"b2", b"3").unwrap();
+        {
+            let mut shadow = ShadowExternal::new(&mut ext);
+            shadow.storage_set(b"a", b"4").unwrap();
+            shadow.storage_remove_subtree(b"b").unwrap();
+            shadow.storage_set(b"b2", b"5").unwrap();
+            assert_eq!(shadow.storage_get(b"a").unwrap().unwrap().deref().unwrap(), b"4");
+            assert!(!shadow.storage_has_key(b"b1").unwrap());
+            assert_eq!(shadow.storage_get(b"b2").unwrap().unwrap().deref().unwrap(), b"5");
+            let receipt_index = shadow.create_receipt(vec![], "bob".parse().unwrap()).unwrap();
+            shadow.append_action_transfer(receipt_index, 1).unwrap();
+        }
+        assert_eq!(ext.storage_get(b"a").unwrap().unwrap().deref().unwrap(), b"1");
+        assert!(ext.storage_has_key(b"b1").unwrap());
+        assert!(ext.get_receipt_create_calls().is_empty());
+    }
+}
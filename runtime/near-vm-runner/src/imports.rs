@@ -232,6 +232,11 @@ imports! {
     // #  Sandbox  #
     // #############
     ##["sandbox"] sandbox_debug_log<[len: u64, ptr: u64] -> []>,
+    // #############
+    // # Profiler  #
+    // #############
+    // Function injected by the profiler next to the gas counter, see `crate::profiler`.
+    ##["profiler"] profile_block<[block: u32] -> []>,
 }
 
 #[cfg(all(feature = "wasmer0_vm", target_arch = "x86_64"))]
@@ -267,12 +272,26 @@ pub(crate) mod wasmer {
             ) => {
                 #[allow(unused_parens)]
                 fn $func( ctx: &mut wasmer_runtime::Ctx, $( $arg_name: $arg_type ),* ) -> Result<($( $returns ),*), VMLogicError> {
-                    const IS_GAS: bool = str_eq(stringify!($func), "gas");
+                    const IS_GAS: bool = str_eq(stringify!($func), "gas")
+                        || str_eq(stringify!($func), "profile_block");
                     let _span = if IS_GAS {
                         None
                     } else {
                         Some(tracing::trace_span!(target: "host-function", stringify!($func)).entered())
                     };
+                    #[cfg(feature = "profiler")]
+                    crate::profiler::record_host_function_call(
+                        stringify!($func),
+                        &[$( $arg_name as u64 ),*],
+                    );
+                    #[cfg(feature = "receipt_trace")]
+                    if !IS_GAS {
+                        near_primitives::receipt_trace::record(|| {
+                            near_primitives::views::ReceiptTraceEvent::HostFunctionCall {
+                                name: stringify!($func).to_string(),
+                            }
+                        });
+                    }
                     let logic: &mut VMLogic<'_> = unsafe { &mut *(ctx.data as *mut VMLogic<'_>) };
                     logic.$func( $( $arg_name, )* )
                 }
@@ -360,7 +379,8 @@ pub(crate) mod wasmer2 {
                     extern "C" fn $func(env: *mut VMLogic<'_>, $( $arg_name: $arg_type ),* )
                     -> Ret {
                         let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                            const IS_GAS: bool = str_eq(stringify!($func), "gas");
+                            const IS_GAS: bool = str_eq(stringify!($func), "gas")
+                                || str_eq(stringify!($func), "profile_block");
                             let _span = if IS_GAS {
                                 None
                             } else {
@@ -369,6 +389,19 @@ pub(crate) mod wasmer2 {
                                     stringify!($func)
                                 ).entered())
                             };
+                            #[cfg(feature = "profiler")]
+                            crate::profiler::record_host_function_call(
+                                stringify!($func),
+                                &[$( $arg_name as u64 ),*],
+                            );
+                            #[cfg(feature = "receipt_trace")]
+                            if !IS_GAS {
+                                near_primitives::receipt_trace::record(|| {
+                                    near_primitives::views::ReceiptTraceEvent::HostFunctionCall {
+                                        name: stringify!($func).to_string(),
+                                    }
+                                });
+                            }
 
                             // SAFETY: This code should only be executable within `'vmlogic`
                             // lifetime and so it is safe to dereference the `env` pointer which is
@@ -468,12 +501,26 @@ pub(crate) mod wasmtime {
             ) => {
                 #[allow(unused_parens)]
                 fn $func(caller: wasmtime::Caller<'_, ()>, $( $arg_name: $arg_type ),* ) -> Result<($( $returns ),*), wasmtime::Trap> {
-                    const IS_GAS: bool = str_eq(stringify!($func), "gas");
+                    const IS_GAS: bool = str_eq(stringify!($func), "gas")
+                        || str_eq(stringify!($func), "profile_block");
                     let _span = if IS_GAS {
                         None
                     } else {
                         Some(tracing::trace_span!(target: "host-function", stringify!($func)).entered())
                     };
+                    #[cfg(feature = "profiler")]
+                    crate::profiler::record_host_function_call(
+                        stringify!($func),
+                        &[$( $arg_name as u64 ),*],
+                    );
+                    #[cfg(feature = "receipt_trace")]
+                    if !IS_GAS {
+                        near_primitives::receipt_trace::record(|| {
+                            near_primitives::views::ReceiptTraceEvent::HostFunctionCall {
+                                name: stringify!($func).to_string(),
+                            }
+                        });
+                    }
                     // the below is bad. don't do this at home. it probably works thanks to the exact way the system is setup.
                     // Thanksfully, this doesn't run in production, and hopefully should be possible to remove before we even
                     // consider doing so.
@@ -1,13 +1,19 @@
 #![doc = include_str!("../README.md")]
 
 mod cache;
+mod differential;
 mod errors;
 mod imports;
 #[cfg(all(feature = "wasmer0_vm", target_arch = "x86_64"))]
 mod memory;
+mod metrics;
+#[cfg(feature = "protocol_feature_precompiles")]
+pub mod precompiles;
 #[cfg(target_arch = "x86_64")]
 mod preload;
 pub mod prepare;
+#[cfg(feature = "profiler")]
+pub mod profiler;
 mod runner;
 #[cfg(test)]
 mod tests;
@@ -28,6 +34,7 @@ pub use cache::{
 #[cfg(target_arch = "x86_64")]
 pub use preload::{ContractCallPrepareRequest, ContractCallPrepareResult, ContractCaller};
 pub use runner::{run, VM};
+pub use vm_kind::{set_differential_vm_kind, set_vm_kind_override, VMKind};
 
 /// This is public for internal experimentation use only, and should otherwise be considered an
 /// implementation detail of `near-vm-runner`.
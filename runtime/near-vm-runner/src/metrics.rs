@@ -0,0 +1,24 @@
+use near_metrics::{
+    try_create_histogram_vec, try_create_int_counter_vec, HistogramVec, IntCounterVec,
+};
+use once_cell::sync::Lazy;
+
+#[cfg(any(target_arch = "x86_64", feature = "wasmtime_vm"))]
+pub(crate) static COMPILATION_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_vm_compilation_time",
+        "Time spent compiling contracts which were not found in the compiled contract cache",
+        &["vm_kind"],
+        None,
+    )
+    .unwrap()
+});
+
+pub(crate) static DIFFERENTIAL_DIVERGENCES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_vm_differential_divergences_total",
+        "Contract calls whose outcome on the differential VM differs from the regular one",
+        &["vm_kind", "differential_vm_kind"],
+    )
+    .unwrap()
+});
@@ -0,0 +1,112 @@
+//! Registry of native precompiles, enabled by the `protocol_feature_precompiles` feature.
+//!
+//! A precompile is a function implemented natively by the node instead of by a contract (not to
+//! be confused with ahead-of-time compilation of contracts done by [`crate::precompile_contract`]).
+//! It is called like a regular contract method on a designated system account: the arguments of
+//! the function call are its input and its output becomes the return value of the call.  Calls to
+//! a registered method never reach the code deployed on the account.
+//!
+//! Every precompile is gated by a protocol feature and charges `base + byte * input.len()` gas,
+//! with both costs being `ExtCosts` in the runtime parameters, as estimated by the runtime
+//! parameters estimator.
+use near_primitives::config::{ExtCosts, ExtCostsConfig};
+use near_primitives::profile::ProfileData;
+use near_primitives::types::{AccountId, Gas};
+use near_primitives::version::{ProtocolFeature, ProtocolVersion};
+use near_vm_errors::{FunctionCallError, HostError, VMError};
+use near_vm_logic::{ReturnData, VMContext, VMOutcome};
+use sha2::Digest;
+
+/// System account hosting the precompiles.  Top-level accounts this short can only be created by
+/// the registrar, so the account cannot be taken over by a user.
+pub const PRECOMPILES_ACCOUNT_ID: &str = "precompiles";
+
+pub struct Precompile {
+    pub account_id: &'static str,
+    pub method_name: &'static str,
+    /// Protocol feature which enables the precompile.
+    pub feature: ProtocolFeature,
+    pub base_cost: ExtCosts,
+    /// Cost per byte of input.
+    pub byte_cost: ExtCosts,
+    /// Computes the output from the input.  An error fails the function call with the given
+    /// message.
+    pub execute: fn(&[u8]) -> Result<Vec<u8>, String>,
+}
+
+static PRECOMPILES: &[Precompile] = &[Precompile {
+    account_id: PRECOMPILES_ACCOUNT_ID,
+    method_name: "sha512",
+    feature: ProtocolFeature::Precompiles,
+    base_cost: ExtCosts::sha512_base,
+    byte_cost: ExtCosts::sha512_byte,
+    execute: sha512,
+}];
+
+impl Precompile {
+    /// Gas charged for a call with an input of the given length, `None` on overflow.
+    pub fn gas(&self, ext_costs: &ExtCostsConfig, input_len: usize) -> Option<Gas> {
+        self.byte_cost
+            .value(ext_costs)
+            .checked_mul(input_len as Gas)?
+            .checked_add(self.base_cost.value(ext_costs))
+    }
+}
+
+pub fn sha512(input: &[u8]) -> Result<Vec<u8>, String> {
+    Ok(sha2::Sha512::digest(input).to_vec())
+}
+
+/// Returns the precompile registered for the method of the account, if it is enabled in the given
+/// protocol version.
+pub fn find_precompile(
+    account_id: &AccountId,
+    method_name: &str,
+    current_protocol_version: ProtocolVersion,
+) -> Option<&'static Precompile> {
+    PRECOMPILES.iter().find(|precompile| {
+        precompile.account_id == account_id.as_str()
+            && precompile.method_name == method_name
+            && precompile.feature.protocol_version() <= current_protocol_version
+    })
+}
+
+/// Executes the precompile as a function call with the given context.
+pub fn run_precompile(
+    precompile: &Precompile,
+    context: &VMContext,
+    ext_costs: &ExtCostsConfig,
+) -> (Option<VMOutcome>, Option<VMError>) {
+    let outcome = |burnt_gas, return_data| {
+        let mut profile = ProfileData::new();
+        let base = precompile.base_cost.value(ext_costs).min(burnt_gas);
+        profile.add_ext_cost(precompile.base_cost, base);
+        profile.add_ext_cost(precompile.byte_cost, burnt_gas - base);
+        VMOutcome {
+            balance: context.account_balance + context.attached_deposit,
+            storage_usage: context.storage_usage,
+            return_data,
+            burnt_gas,
+            used_gas: burnt_gas,
+            logs: vec![],
+            profile,
+        }
+    };
+    let gas = match precompile.gas(ext_costs, context.input.len()) {
+        Some(gas) if gas <= context.prepaid_gas => gas,
+        _ => {
+            let error = FunctionCallError::HostError(HostError::GasExceeded);
+            return (
+                Some(outcome(context.prepaid_gas, ReturnData::None)),
+                Some(VMError::FunctionCallError(error)),
+            );
+        }
+    };
+    match (precompile.execute)(&context.input) {
+        Ok(output) => (Some(outcome(gas, ReturnData::Value(output))), None),
+        Err(panic_msg) => {
+            let error = FunctionCallError::HostError(HostError::GuestPanic { panic_msg });
+            (Some(outcome(gas, ReturnData::None)), Some(VMError::FunctionCallError(error)))
+        }
+    }
+}
@@ -49,12 +49,18 @@ pub fn prepare_contract(original_code: &[u8], config: &VMConfig) -> Result<Vec<u
             .standardize_mem()
             .ensure_no_internal_memory()?
             .inject_gas_metering()?
+            .inject_block_profiling()?
             .inject_stack_height_metering()?
             .scan_imports()?
             .into_wasm_code(),
     }
 }
 
+/// Rules of the gas metering: every instruction costs one opcode.
+pub(crate) fn gas_rules(config: &VMConfig) -> pwasm_utils::rules::Set {
+    pwasm_utils::rules::Set::new(1, Default::default()).with_grow_cost(config.grow_mem_cost)
+}
+
 struct ContractModule<'a> {
     module: elements::Module,
     config: &'a VMConfig,
@@ -108,13 +114,28 @@ impl<'a> ContractModule<'a> {
         if config.regular_op_cost == 0 {
             return Ok(Self { module, config });
         }
-        let gas_rules = pwasm_utils::rules::Set::new(1, Default::default())
-            .with_grow_cost(config.grow_mem_cost);
-        let module = pwasm_utils::inject_gas_counter(module, &gas_rules, "env")
+        let module = pwasm_utils::inject_gas_counter(module, &gas_rules(config), "env")
             .map_err(|_| PrepareError::GasInstrumentation)?;
         Ok(Self { module, config })
     }
 
+    /// Marks the metered blocks for the profiler, see [`crate::profiler`].
+    #[cfg(feature = "profiler")]
+    fn inject_block_profiling(self) -> Result<Self, PrepareError> {
+        let Self { module, config } = self;
+        if config.regular_op_cost == 0 {
+            return Ok(Self { module, config });
+        }
+        let module = crate::profiler::inject_block_profiling(module)
+            .map_err(|_| PrepareError::GasInstrumentation)?;
+        Ok(Self { module, config })
+    }
+
+    #[cfg(not(feature = "profiler"))]
+    fn inject_block_profiling(self) -> Result<Self, PrepareError> {
+        Ok(self)
+    }
+
     fn inject_stack_height_metering(self) -> Result<Self, PrepareError> {
         let Self { module, config } = self;
         let module =
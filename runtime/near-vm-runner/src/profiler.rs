@@ -0,0 +1,212 @@
+//! Opt-in profiler of contract execution, enabled by the `profiler` feature.
+//!
+//! For every contract call made through [`crate::run`] the profiler records the wall time of the
+//! call, how many times each host function was called, how the burnt gas splits between the
+//! host functions, actions and wasm instructions, and how the gas of the wasm instructions splits
+//! between their opcodes.  Apart from the wall time the results are deterministic.
+//!
+//! The gas of the wasm instructions is attributed to the opcodes by marking the metered blocks of
+//! the contracts, see the `instrument` submodule.  Contracts prepared by the old stack limiter aren't marked,
+//! so their calls come without the opcodes.  The opcodes don't include the gas charged for the
+//! pages grown by `memory.grow`.
+//!
+//! Profiles of the most recent calls are kept in memory and can be retrieved with
+//! [`take_profiles`].  If the `NEAR_VM_PROFILE_FILE` environment variable is set, every profile is
+//! also appended to the file it points to.
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use near_primitives::contract::ContractCode;
+use near_primitives::hash::CryptoHash;
+use near_primitives::profile::ProfileData;
+use near_primitives::types::Gas;
+use near_vm_logic::{VMConfig, VMOutcome};
+use once_cell::sync::Lazy;
+
+mod instrument;
+
+pub(crate) use instrument::{analyze, inject_block_profiling, MeteredBlocks};
+
+/// Number of the most recent profiles kept in memory.
+const MAX_PROFILES: usize = 1000;
+
+/// Number of the contracts whose metered blocks are kept in memory.
+const METERED_BLOCKS_CACHE_SIZE: usize = 128;
+
+const PROFILE_FILE_ENV_VAR: &str = "NEAR_VM_PROFILE_FILE";
+
+#[derive(Clone)]
+pub struct ContractCallProfile {
+    pub code_hash: CryptoHash,
+    pub method_name: String,
+    pub wall_time: Duration,
+    pub burnt_gas: Gas,
+    /// Number of calls of every host function, including the `gas` function injected into the
+    /// contract to charge for wasm instructions.
+    pub host_function_calls: BTreeMap<&'static str, u64>,
+    pub gas_profile: ProfileData,
+    /// Executed instructions and their gas by opcode.
+    pub opcodes: BTreeMap<String, OpcodeProfile>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpcodeProfile {
+    pub count: u64,
+    pub gas: Gas,
+}
+
+impl fmt::Display for ContractCallProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Contract {} method {}: wall time {:?}, burnt gas {}",
+            self.code_hash, self.method_name, self.wall_time, self.burnt_gas
+        )?;
+        writeln!(f, "------ Host function calls --------")?;
+        for (name, count) in &self.host_function_calls {
+            writeln!(f, "{} -> {}", name, count)?;
+        }
+        writeln!(f, "------ Opcodes --------")?;
+        for (name, opcode) in &self.opcodes {
+            writeln!(f, "{} -> {} [{} gas]", name, opcode.count, opcode.gas)?;
+        }
+        write!(f, "{:?}", self.gas_profile)
+    }
+}
+
+thread_local! {
+    static HOST_FUNCTION_CALLS: RefCell<BTreeMap<&'static str, u64>> = Default::default();
+    /// Number of times every metered block was charged for, indexed by the number of the block.
+    static BLOCK_CALLS: RefCell<Vec<u64>> = Default::default();
+}
+
+static PROFILES: Lazy<Mutex<VecDeque<ContractCallProfile>>> = Lazy::new(Default::default);
+
+/// Metered blocks of the recently called contracts, by the hash of the code and of the config.
+static METERED_BLOCKS: Lazy<
+    near_cache::SyncLruCache<(CryptoHash, u64), Option<Arc<MeteredBlocks>>>,
+> = Lazy::new(|| near_cache::SyncLruCache::new(METERED_BLOCKS_CACHE_SIZE));
+
+static PROFILE_FILE: Lazy<Option<Mutex<std::fs::File>>> = Lazy::new(|| {
+    let path = std::env::var_os(PROFILE_FILE_ENV_VAR)?;
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => Some(Mutex::new(file)),
+        Err(err) => {
+            tracing::warn!(target: "vm", "Failed to open profile file {:?}: {}", path, err);
+            None
+        }
+    }
+});
+
+/// Called by every host function with its arguments.  The calls of `profile_block` injected by
+/// [`inject_block_profiling`] are recorded as charges of the metered blocks.
+pub(crate) fn record_host_function_call(name: &'static str, args: &[u64]) {
+    if name == "profile_block" {
+        BLOCK_CALLS.with(|calls| {
+            // Blocks the contract doesn't have can only come from calls the contract made itself.
+            if let Some(calls) = calls.borrow_mut().get_mut(args[0] as usize) {
+                *calls += 1;
+            }
+        });
+    } else {
+        HOST_FUNCTION_CALLS.with(|calls| *calls.borrow_mut().entry(name).or_default() += 1);
+    }
+}
+
+/// Measures a single contract call.  Contract calls on the same thread must not overlap.
+pub(crate) struct CallProfiler {
+    code_hash: CryptoHash,
+    method_name: String,
+    metered_blocks: Option<Arc<MeteredBlocks>>,
+    regular_op_cost: u32,
+    start: Instant,
+}
+
+impl CallProfiler {
+    pub(crate) fn start(code: &ContractCode, method_name: &str, config: &VMConfig) -> Self {
+        let metered_blocks = METERED_BLOCKS
+            .get_or_put((*code.hash(), config.non_crypto_hash()), |_| {
+                analyze(code.code(), config).map(Arc::new)
+            });
+        let blocks =
+            metered_blocks.as_ref().map_or(0, |metered_blocks| metered_blocks.blocks.len());
+        HOST_FUNCTION_CALLS.with(|calls| calls.borrow_mut().clear());
+        BLOCK_CALLS.with(|calls| *calls.borrow_mut() = vec![0; blocks]);
+        Self {
+            code_hash: *code.hash(),
+            method_name: method_name.to_string(),
+            metered_blocks,
+            regular_op_cost: config.regular_op_cost,
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self, outcome: Option<&VMOutcome>) {
+        let wall_time = self.start.elapsed();
+        let host_function_calls = HOST_FUNCTION_CALLS.with(|calls| calls.take());
+        let block_calls = BLOCK_CALLS.with(|calls| calls.take());
+        let opcodes = match &self.metered_blocks {
+            Some(metered_blocks) => {
+                attribute_opcodes(metered_blocks, &block_calls, self.regular_op_cost)
+            }
+            None => BTreeMap::new(),
+        };
+        let profile = ContractCallProfile {
+            code_hash: self.code_hash,
+            method_name: self.method_name,
+            wall_time,
+            burnt_gas: outcome.map_or(0, |outcome| outcome.burnt_gas),
+            host_function_calls,
+            gas_profile: outcome.map(|outcome| outcome.profile.clone()).unwrap_or_default(),
+            opcodes,
+        };
+        if let Some(file) = &*PROFILE_FILE {
+            if let Err(err) = writeln!(file.lock().unwrap(), "{}", profile) {
+                tracing::warn!(target: "vm", "Failed to write contract profile: {}", err);
+            }
+        }
+        let mut profiles = PROFILES.lock().unwrap();
+        if profiles.len() == MAX_PROFILES {
+            profiles.pop_front();
+        }
+        profiles.push_back(profile);
+    }
+}
+
+/// Counts the instructions of the metered blocks by opcode.  A block is charged for as a whole
+/// before it's executed, so a trap in the middle of a block still counts all its instructions,
+/// just like the gas does.
+fn attribute_opcodes(
+    metered_blocks: &MeteredBlocks,
+    block_calls: &[u64],
+    regular_op_cost: u32,
+) -> BTreeMap<String, OpcodeProfile> {
+    let mut counts = vec![0u64; metered_blocks.opcodes.len()];
+    for (block, &calls) in block_calls.iter().enumerate() {
+        if calls == 0 {
+            continue;
+        }
+        for &(opcode, count) in metered_blocks.blocks.get(block).map_or(&[][..], Vec::as_slice) {
+            counts[opcode] = counts[opcode].saturating_add(calls.saturating_mul(count as u64));
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count > 0)
+        .map(|(opcode, count)| {
+            let gas = count.saturating_mul(regular_op_cost as u64);
+            (metered_blocks.opcodes[opcode].clone(), OpcodeProfile { count, gas })
+        })
+        .collect()
+}
+
+/// Returns profiles of the recent contract calls, oldest first, and forgets them.
+pub fn take_profiles() -> Vec<ContractCallProfile> {
+    PROFILES.lock().unwrap().drain(..).collect()
+}
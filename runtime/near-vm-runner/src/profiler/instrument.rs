@@ -0,0 +1,319 @@
+//! Attribution of the wasm gas to opcodes.
+//!
+//! The gas metering injected by `pwasm-utils` charges for the instructions of a whole metered
+//! block with a single call of the `gas` function at the start of the block.  When profiling, a
+//! call of the `profile_block` function with the number of the block is injected right before
+//! every such call, so the profiler knows how many times every block was charged for.  The
+//! opcodes of every block come from [`analyze`], which splits the code into metered blocks the
+//! same way as `pwasm-utils` does.
+//!
+//! The injected calls don't change the gas burnt by the contract, as they are injected after the
+//! gas metering, nor the stack height it's limited to, as they push their argument onto the stack
+//! at the same height as the call of `gas` which follows them.
+use std::collections::{BTreeMap, HashMap};
+
+use near_vm_logic::{StackLimiterVersion, VMConfig};
+use pwasm_utils::parity_wasm::elements::{
+    self, External, ImportCountType, ImportEntry, Instruction, Internal,
+};
+
+/// Opcodes of the metered blocks of a contract.
+pub(crate) struct MeteredBlocks {
+    /// Names of the opcodes found in the contract.
+    pub(crate) opcodes: Vec<String>,
+    /// Number of instructions of every opcode in every metered block, indexed by the number of
+    /// the block passed to `profile_block`.
+    pub(crate) blocks: Vec<Vec<(usize, u32)>>,
+}
+
+/// Injects a call of `profile_block` before every call of `gas` charging for a metered block.
+/// Blocks are numbered in the order of the functions and of the blocks within them.  Must be
+/// called right after the gas metering was injected.
+pub(crate) fn inject_block_profiling(mut module: elements::Module) -> Result<elements::Module, ()> {
+    // The gas metering imports `gas` after all the other functions.
+    let gas_func =
+        (module.import_count(ImportCountType::Function) as u32).checked_sub(1).ok_or(())?;
+    let imports = module.import_section_mut().ok_or(())?.entries_mut();
+    let gas_type = match imports.iter().rev().find_map(|import| match import.external() {
+        External::Function(type_idx) => Some((import.field(), *type_idx)),
+        _ => None,
+    }) {
+        Some(("gas", type_idx)) => type_idx,
+        _ => return Err(()),
+    };
+    imports.push(ImportEntry::new(
+        "env".to_string(),
+        "profile_block".to_string(),
+        External::Function(gas_type),
+    ));
+    let profile_func = gas_func + 1;
+
+    let mut block = 0u32;
+    for section in module.sections_mut() {
+        match section {
+            elements::Section::Code(code_section) => {
+                for func_body in code_section.bodies_mut() {
+                    let code = func_body.code_mut().elements_mut();
+                    let mut instrumented = Vec::with_capacity(code.len());
+                    for mut instruction in std::mem::take(code) {
+                        match &mut instruction {
+                            Instruction::Call(func) if *func >= profile_func => *func += 1,
+                            Instruction::Call(func) if *func == gas_func => {
+                                if let Some(Instruction::I32Const(_)) = instrumented.last() {
+                                    let charge = instrumented.pop().unwrap();
+                                    instrumented.push(Instruction::I32Const(block as i32));
+                                    instrumented.push(Instruction::Call(profile_func));
+                                    instrumented.push(charge);
+                                    block += 1;
+                                }
+                            }
+                            _ => {}
+                        }
+                        instrumented.push(instruction);
+                    }
+                    *code = instrumented;
+                }
+            }
+            elements::Section::Export(export_section) => {
+                for export in export_section.entries_mut() {
+                    if let Internal::Function(func) = export.internal_mut() {
+                        if *func >= profile_func {
+                            *func += 1;
+                        }
+                    }
+                }
+            }
+            elements::Section::Element(elements_section) => {
+                for segment in elements_section.entries_mut() {
+                    for func in segment.members_mut() {
+                        if *func >= profile_func {
+                            *func += 1;
+                        }
+                    }
+                }
+            }
+            elements::Section::Start(func) => {
+                if *func >= profile_func {
+                    *func += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(module)
+}
+
+/// Returns the opcodes of the metered blocks of the contract, or `None` if its code isn't
+/// instrumented for profiling under the config.  The blocks are checked against the gas metering
+/// of `pwasm-utils`, so that a mismatch can't attribute the gas wrongly.
+pub(crate) fn analyze(original_code: &[u8], config: &VMConfig) -> Option<MeteredBlocks> {
+    if config.regular_op_cost == 0
+        || config.limit_config.stack_limiter_version != StackLimiterVersion::V1
+    {
+        return None;
+    }
+    let module: elements::Module =
+        pwasm_utils::parity_wasm::deserialize_buffer(original_code).ok()?;
+    let mut opcodes = Opcodes::default();
+    let mut blocks = vec![];
+    for func_body in module.code_section().map_or(&[][..], |code| code.bodies()) {
+        let instructions = func_body.code().elements();
+        for block in determine_metered_blocks(instructions, &mut opcodes)? {
+            blocks.push((block.cost, block.opcodes.into_iter().collect::<Vec<_>>()));
+        }
+    }
+
+    let metered =
+        pwasm_utils::inject_gas_counter(module, &crate::prepare::gas_rules(config), "env").ok()?;
+    let gas_func = metered.import_count(ImportCountType::Function) as u32 - 1;
+    let mut costs = vec![];
+    for func_body in metered.code_section().map_or(&[][..], |code| code.bodies()) {
+        for pair in func_body.code().elements().windows(2) {
+            if let [Instruction::I32Const(cost), Instruction::Call(func)] = pair {
+                if *func == gas_func {
+                    costs.push(*cost as u32);
+                }
+            }
+        }
+    }
+    if costs.len() != blocks.len() || blocks.iter().zip(&costs).any(|((cost, _), c)| cost != c) {
+        tracing::warn!(target: "vm", "Metered blocks don't match the gas metering of the contract");
+        return None;
+    }
+    Some(MeteredBlocks {
+        opcodes: opcodes.names,
+        blocks: blocks.into_iter().map(|(_, opcodes)| opcodes).collect(),
+    })
+}
+
+#[derive(Default)]
+struct Opcodes {
+    names: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl Opcodes {
+    fn index(&mut self, instruction: &Instruction) -> usize {
+        // Debug representation is the name of the opcode followed by its immediates, if any.
+        let debug = format!("{:?}", instruction);
+        let name = debug.split('(').next().unwrap_or_default();
+        if let Some(index) = self.indices.get(name) {
+            return *index;
+        }
+        self.names.push(name.to_string());
+        self.indices.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+}
+
+// What follows is `pwasm_utils::gas::determine_metered_blocks` of `pwasm-utils` 0.18, which isn't
+// public, with the instructions of every metered block counted per opcode next to its cost.  All
+// instructions cost 1, as in the rules of `prepare::gas_rules`.
+
+struct MeteredBlock {
+    start_pos: usize,
+    cost: u32,
+    opcodes: BTreeMap<usize, u32>,
+}
+
+impl MeteredBlock {
+    fn new(start_pos: usize) -> Self {
+        Self { start_pos, cost: 0, opcodes: BTreeMap::new() }
+    }
+}
+
+struct ControlBlock {
+    lowest_forward_br_target: usize,
+    active_metered_block: MeteredBlock,
+    is_loop: bool,
+}
+
+#[derive(Default)]
+struct Counter {
+    stack: Vec<ControlBlock>,
+    finalized_blocks: Vec<MeteredBlock>,
+}
+
+impl Counter {
+    fn begin_control_block(&mut self, cursor: usize, is_loop: bool) {
+        let index = self.stack.len();
+        self.stack.push(ControlBlock {
+            lowest_forward_br_target: index,
+            active_metered_block: MeteredBlock::new(cursor),
+            is_loop,
+        })
+    }
+
+    fn finalize_control_block(&mut self, cursor: usize) -> Option<()> {
+        self.finalize_metered_block(cursor)?;
+        let closing_control_block = self.stack.pop()?;
+        let closing_control_index = self.stack.len();
+        if self.stack.is_empty() {
+            return Some(());
+        }
+        let control_block = self.stack.last_mut()?;
+        control_block.lowest_forward_br_target = std::cmp::min(
+            control_block.lowest_forward_br_target,
+            closing_control_block.lowest_forward_br_target,
+        );
+        if closing_control_block.lowest_forward_br_target < closing_control_index {
+            self.finalize_metered_block(cursor)?;
+        }
+        Some(())
+    }
+
+    fn finalize_metered_block(&mut self, cursor: usize) -> Option<()> {
+        let closing_metered_block = std::mem::replace(
+            &mut self.stack.last_mut()?.active_metered_block,
+            MeteredBlock::new(cursor + 1),
+        );
+        let last_index = self.stack.len() - 1;
+        if last_index > 0 {
+            let prev_metered_block = &mut self.stack[last_index - 1].active_metered_block;
+            if closing_metered_block.start_pos == prev_metered_block.start_pos {
+                prev_metered_block.cost += closing_metered_block.cost;
+                for (opcode, count) in closing_metered_block.opcodes {
+                    *prev_metered_block.opcodes.entry(opcode).or_default() += count;
+                }
+                return Some(());
+            }
+        }
+        if closing_metered_block.cost > 0 {
+            self.finalized_blocks.push(closing_metered_block);
+        }
+        Some(())
+    }
+
+    fn branch(&mut self, cursor: usize, indices: &[usize]) -> Option<()> {
+        self.finalize_metered_block(cursor)?;
+        for &index in indices {
+            if self.stack.get(index)?.is_loop {
+                continue;
+            }
+            let control_block = self.stack.last_mut()?;
+            control_block.lowest_forward_br_target =
+                std::cmp::min(control_block.lowest_forward_br_target, index);
+        }
+        Some(())
+    }
+
+    fn active_control_block_index(&self) -> Option<usize> {
+        self.stack.len().checked_sub(1)
+    }
+
+    fn increment(&mut self, opcode: usize) -> Option<()> {
+        let block = &mut self.stack.last_mut()?.active_metered_block;
+        block.cost = block.cost.checked_add(1)?;
+        *block.opcodes.entry(opcode).or_default() += 1;
+        Some(())
+    }
+}
+
+fn determine_metered_blocks(
+    instructions: &[Instruction],
+    opcodes: &mut Opcodes,
+) -> Option<Vec<MeteredBlock>> {
+    let mut counter = Counter::default();
+    counter.begin_control_block(0, false);
+    for (cursor, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::Block(_) => {
+                counter.increment(opcodes.index(instruction))?;
+                let top_block_start_pos = counter.stack.last()?.active_metered_block.start_pos;
+                counter.begin_control_block(top_block_start_pos, false);
+            }
+            Instruction::If(_) => {
+                counter.increment(opcodes.index(instruction))?;
+                counter.begin_control_block(cursor + 1, false);
+            }
+            Instruction::Loop(_) => {
+                counter.increment(opcodes.index(instruction))?;
+                counter.begin_control_block(cursor + 1, true);
+            }
+            Instruction::End => counter.finalize_control_block(cursor)?,
+            Instruction::Else => counter.finalize_metered_block(cursor)?,
+            Instruction::Br(label) | Instruction::BrIf(label) => {
+                counter.increment(opcodes.index(instruction))?;
+                let active_index = counter.active_control_block_index()?;
+                let target_index = active_index.checked_sub(*label as usize)?;
+                counter.branch(cursor, &[target_index])?;
+            }
+            Instruction::BrTable(br_table_data) => {
+                counter.increment(opcodes.index(instruction))?;
+                let active_index = counter.active_control_block_index()?;
+                let target_indices = std::iter::once(&br_table_data.default)
+                    .chain(br_table_data.table.iter())
+                    .map(|label| active_index.checked_sub(*label as usize))
+                    .collect::<Option<Vec<_>>>()?;
+                counter.branch(cursor, &target_indices)?;
+            }
+            Instruction::Return => {
+                counter.increment(opcodes.index(instruction))?;
+                counter.branch(cursor, &[0])?;
+            }
+            _ => counter.increment(opcodes.index(instruction))?,
+        }
+    }
+    counter.finalized_blocks.sort_unstable_by_key(|block| block.start_pos);
+    Some(counter.finalized_blocks)
+}
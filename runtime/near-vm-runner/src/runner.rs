@@ -35,7 +35,25 @@ pub fn run(
     cache: Option<&dyn CompiledContractCache>,
 ) -> (Option<VMOutcome>, Option<VMError>) {
     let vm_kind = VMKind::for_protocol_version(current_protocol_version);
-    if let Some(runtime) = vm_kind.runtime(wasm_config.clone()) {
+    let differential_vm_kind =
+        crate::vm_kind::differential_vm_kind().filter(|differential| *differential != vm_kind);
+    let differential_result = differential_vm_kind.map(|differential_vm_kind| {
+        crate::differential::run_shadow(
+            differential_vm_kind,
+            code,
+            method_name,
+            ext,
+            context.clone(),
+            wasm_config,
+            fees_config,
+            promise_results,
+            current_protocol_version,
+            cache,
+        )
+    });
+    #[cfg(feature = "profiler")]
+    let profiler = crate::profiler::CallProfiler::start(code, method_name, wasm_config);
+    let result = if let Some(runtime) = vm_kind.runtime(wasm_config.clone()) {
         runtime.run(
             code,
             method_name,
@@ -48,7 +66,22 @@ pub fn run(
         )
     } else {
         panic!("the {:?} runtime has not been enabled at compile time", vm_kind);
+    };
+    #[cfg(feature = "profiler")]
+    profiler.finish(result.0.as_ref());
+    if let (Some(differential_vm_kind), Some(differential_result)) =
+        (differential_vm_kind, differential_result)
+    {
+        crate::differential::compare(
+            code,
+            method_name,
+            vm_kind,
+            &result,
+            differential_vm_kind,
+            &differential_result,
+        );
     }
+    result
 }
 
 pub trait VM {
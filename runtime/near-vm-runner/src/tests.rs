@@ -1,6 +1,10 @@
 mod cache;
 mod compile_errors;
 mod contract_preload;
+#[cfg(feature = "protocol_feature_precompiles")]
+mod precompiles;
+#[cfg(feature = "profiler")]
+mod profiler;
 mod rs_contract;
 mod runtime_errors;
 mod ts_contract;
@@ -1,4 +1,4 @@
-//! Tests that `CompiledContractCache` is working correctly. Currently testing only wasmer code, so disabled outside of x86_64
+//! Tests that `CompiledContractCache` is working correctly. Some tests check wasmer-specific code, so disabled outside of x86_64
 #![cfg(target_arch = "x86_64")]
 
 use super::{create_context, with_vm_variants, LATEST_PROTOCOL_VERSION};
@@ -21,10 +21,6 @@ use wasmer_compiler::{CpuFeature, Target};
 #[test]
 fn test_caches_compilation_error() {
     with_vm_variants(|vm_kind: VMKind| {
-        match vm_kind {
-            VMKind::Wasmer0 | VMKind::Wasmer2 => {}
-            VMKind::Wasmtime => return,
-        }
         let cache = MockCompiledContractCache::default();
         let code = [42; 1000];
         let terragas = 1000000000000u64;
@@ -40,11 +36,6 @@ fn test_caches_compilation_error() {
 #[test]
 fn test_does_not_cache_io_error() {
     with_vm_variants(|vm_kind: VMKind| {
-        match vm_kind {
-            VMKind::Wasmer0 | VMKind::Wasmer2 => {}
-            VMKind::Wasmtime => return,
-        }
-
         let code = near_test_contracts::trivial_contract();
         let prepaid_gas = 10u64.pow(12);
         let mut cache = FaultingCompiledContractCache::default();
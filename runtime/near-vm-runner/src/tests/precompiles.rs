@@ -0,0 +1,50 @@
+use super::{create_context, LATEST_PROTOCOL_VERSION};
+use crate::precompiles::{find_precompile, run_precompile, PRECOMPILES_ACCOUNT_ID};
+use near_primitives::config::ExtCostsConfig;
+use near_primitives::version::ProtocolFeature;
+use near_vm_errors::{FunctionCallError, HostError, VMError};
+use near_vm_logic::ReturnData;
+
+#[test]
+fn test_find_precompile() {
+    let account_id = PRECOMPILES_ACCOUNT_ID.parse().unwrap();
+    let version = ProtocolFeature::Precompiles.protocol_version();
+    assert!(find_precompile(&account_id, "sha512", version).is_some());
+    assert!(find_precompile(&account_id, "sha512", version - 1).is_none());
+    assert!(find_precompile(&account_id, "unknown", version).is_none());
+    assert!(find_precompile(&"alice".parse().unwrap(), "sha512", version).is_none());
+}
+
+#[test]
+fn test_run_sha512() {
+    let account_id = PRECOMPILES_ACCOUNT_ID.parse().unwrap();
+    let precompile = find_precompile(&account_id, "sha512", LATEST_PROTOCOL_VERSION).unwrap();
+    let context = create_context(b"tesseract".to_vec());
+    let ext_costs = ExtCostsConfig::test();
+    let (outcome, err) = run_precompile(precompile, &context, &ext_costs);
+    assert_eq!(err, None);
+    let outcome = outcome.unwrap();
+    assert_eq!(
+        outcome.burnt_gas,
+        ext_costs.sha512_base + ext_costs.sha512_byte * b"tesseract".len() as u64
+    );
+    match outcome.return_data {
+        ReturnData::Value(value) => assert_eq!(value, (precompile.execute)(b"tesseract").unwrap()),
+        _ => panic!("expected a value"),
+    }
+}
+
+#[test]
+fn test_run_precompile_gas_exceeded() {
+    let account_id = PRECOMPILES_ACCOUNT_ID.parse().unwrap();
+    let precompile = find_precompile(&account_id, "sha512", LATEST_PROTOCOL_VERSION).unwrap();
+    let ext_costs = ExtCostsConfig::test();
+    let mut context = create_context(vec![0; 100]);
+    context.prepaid_gas = ext_costs.sha512_base;
+    let (outcome, err) = run_precompile(precompile, &context, &ext_costs);
+    assert_eq!(outcome.unwrap().burnt_gas, ext_costs.sha512_base);
+    assert_eq!(
+        err,
+        Some(VMError::FunctionCallError(FunctionCallError::HostError(HostError::GasExceeded)))
+    );
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use near_primitives::contract::ContractCode;
+use near_primitives::profile::Cost;
+use near_primitives::runtime::fees::RuntimeFeesConfig;
+use near_vm_logic::mocks::mock_external::MockedExternal;
+use near_vm_logic::VMConfig;
+
+use super::{create_context, LATEST_PROTOCOL_VERSION};
+use crate::profiler::{analyze, take_profiles, OpcodeProfile};
+
+/// Counts down from 3 in a loop, which executes 3 instructions before the loop and 6 instructions
+/// in each of its 3 iterations.
+fn loop_contract() -> Vec<u8> {
+    wat::parse_str(
+        r#"
+            (module
+              (func (export "main")
+                (local i32)
+                (local.set 0 (i32.const 3))
+                (loop
+                  (local.set 0 (i32.sub (local.get 0) (i32.const 1)))
+                  (br_if 0 (local.get 0))))
+            )"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_opcodes_attribution() {
+    let code = ContractCode::new(loop_contract(), None);
+    let config = VMConfig::test();
+    let (outcome, err) = crate::run(
+        &code,
+        "main",
+        &mut MockedExternal::new(),
+        create_context(vec![]),
+        &config,
+        &RuntimeFeesConfig::test(),
+        &[],
+        LATEST_PROTOCOL_VERSION,
+        None,
+    );
+    assert_eq!(err, None);
+    let outcome = outcome.unwrap();
+
+    let profile = take_profiles()
+        .into_iter()
+        .find(|profile| profile.code_hash == *code.hash())
+        .expect("the call should be profiled");
+    let op_cost = config.regular_op_cost as u64;
+    let opcode = |count: u64| OpcodeProfile { count, gas: count * op_cost };
+    let expected: BTreeMap<String, OpcodeProfile> = [
+        ("BrIf", opcode(3)),
+        ("GetLocal", opcode(6)),
+        ("I32Const", opcode(4)),
+        ("I32Sub", opcode(3)),
+        ("Loop", opcode(1)),
+        ("SetLocal", opcode(4)),
+    ]
+    .into_iter()
+    .map(|(name, opcode)| (name.to_string(), opcode))
+    .collect();
+    assert_eq!(profile.opcodes, expected);
+    // The injected calls of `profile_block` neither cost gas nor count as host function calls.
+    assert_eq!(outcome.profile[Cost::WasmInstruction], 21 * op_cost);
+    assert_eq!(profile.host_function_calls.get("profile_block"), None);
+    assert_eq!(profile.host_function_calls.get("gas"), Some(&4));
+}
+
+#[test]
+fn test_metered_blocks_match_gas_metering() {
+    let config = VMConfig::test();
+    let blocks = analyze(&loop_contract(), &config).unwrap();
+    assert_eq!(blocks.blocks.len(), 2);
+    let total: u32 = blocks.blocks.iter().flatten().map(|(_, count)| count).sum();
+    assert_eq!(total, 9);
+
+    // Contracts compiled from Rust exercise all the control flow the gas metering handles.
+    let blocks = analyze(near_test_contracts::rs_contract(), &config);
+    assert!(blocks.is_some());
+}
@@ -1,14 +1,17 @@
 use borsh::BorshSerialize;
 use near_primitives::checked_feature;
 use near_vm_logic::ProtocolVersion;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
 use std::hash::Hash;
 
-#[derive(Clone, Copy, Debug, Hash, BorshSerialize)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, BorshSerialize, Serialize, Deserialize)]
 // Note, that VMKind is part of serialization protocol, so we cannor remove entries
 // from this list if particular VM reached publically visible networks.
 //
-// Additionally, this is public only for the purposes of the standalone VM runner. This API should
-// otherwise be considered a private implementation detail of the `near-vm-runner` crate.
+// Additionally, this is public only for the purposes of the standalone VM runner and of the node
+// configuration (see [`set_vm_kind_override`]). This API should otherwise be considered a private
+// implementation detail of the `near-vm-runner` crate.
 pub enum VMKind {
     /// Wasmer 0.17.x VM.
     Wasmer0,
@@ -38,6 +41,9 @@ impl VMKind {
         if cfg!(feature = "force_wasmer2") {
             return VMKind::Wasmer2;
         }
+        if let Some(vm_kind) = VM_KIND_OVERRIDE.get() {
+            return *vm_kind;
+        }
 
         if cfg!(target_arch = "x86_64") {
             if checked_feature!("stable", Wasmer2, protocol_version) {
@@ -49,4 +55,35 @@ impl VMKind {
             VMKind::Wasmtime
         }
     }
+
+    /// Whether this VM has been enabled at compile time and supports the current platform.
+    pub fn is_available(self) -> bool {
+        match self {
+            VMKind::Wasmer0 => cfg!(all(feature = "wasmer0_vm", target_arch = "x86_64")),
+            VMKind::Wasmtime => cfg!(feature = "wasmtime_vm"),
+            VMKind::Wasmer2 => cfg!(all(feature = "wasmer2_vm", target_arch = "x86_64")),
+        }
+    }
+}
+
+static VM_KIND_OVERRIDE: OnceCell<VMKind> = OnceCell::new();
+static DIFFERENTIAL_VM_KIND: OnceCell<VMKind> = OnceCell::new();
+
+/// Makes contracts execute on the given VM regardless of the protocol version (`force_*` features
+/// still take precedence).  Different VMs are not guaranteed to produce identical outcomes, so
+/// this is meant for non-validating nodes on platforms which cannot run the default VM reliably.
+///
+/// Must be called before any contract is executed.  Returns `Err` if the override is already set.
+pub fn set_vm_kind_override(vm_kind: VMKind) -> Result<(), VMKind> {
+    VM_KIND_OVERRIDE.set(vm_kind)
+}
+
+/// Makes every contract call also execute on the given VM and log divergences from the regular
+/// execution.  Returns `Err` if the VM is already set.
+pub fn set_differential_vm_kind(vm_kind: VMKind) -> Result<(), VMKind> {
+    DIFFERENTIAL_VM_KIND.set(vm_kind)
+}
+
+pub(crate) fn differential_vm_kind() -> Option<VMKind> {
+    DIFFERENTIAL_VM_KIND.get().copied()
 }
@@ -1,6 +1,7 @@
+use crate::cache::{self, into_vm_result};
 use crate::errors::IntoVMError;
+use crate::imports;
 use crate::prepare::WASM_FEATURES;
-use crate::{imports, prepare};
 use near_primitives::config::VMConfig;
 use near_primitives::contract::ContractCode;
 use near_primitives::hash::CryptoHash;
@@ -8,11 +9,11 @@ use near_primitives::runtime::fees::RuntimeFeesConfig;
 use near_primitives::types::CompiledContractCache;
 use near_primitives::version::ProtocolVersion;
 use near_vm_errors::{
-    CompilationError, FunctionCallError, MethodResolveError, PrepareError, VMError, VMLogicError,
-    WasmTrap,
+    FunctionCallError, MethodResolveError, PrepareError, VMError, VMLogicError, WasmTrap,
 };
 use near_vm_logic::types::PromiseResult;
 use near_vm_logic::{External, MemoryLike, VMContext, VMLogic, VMOutcome};
+use once_cell::sync::Lazy;
 use std::cell::RefCell;
 use std::ffi::c_void;
 use std::str;
@@ -50,9 +51,7 @@ impl MemoryLike for WasmtimeMemory {
             let offset = offset as usize;
             let mut caller = caller.borrow_mut();
             let caller = caller.as_mut().unwrap();
-            for i in 0..buffer.len() {
-                buffer[i] = self.0.data(&mut *caller)[i + offset];
-            }
+            buffer.copy_from_slice(&self.0.data(&mut *caller)[offset..offset + buffer.len()]);
         })
     }
 
@@ -65,9 +64,7 @@ impl MemoryLike for WasmtimeMemory {
             let offset = offset as usize;
             let mut caller = caller.borrow_mut();
             let caller = caller.as_mut().unwrap();
-            for i in 0..buffer.len() {
-                self.0.data_mut(&mut *caller)[i + offset] = buffer[i];
-            }
+            self.0.data_mut(&mut *caller)[offset..offset + buffer.len()].copy_from_slice(buffer);
         })
     }
 }
@@ -90,6 +87,9 @@ fn trap_to_error(trap: &wasmtime::Trap) -> VMError {
             Some(TrapCode::MemoryOutOfBounds) => {
                 VMError::FunctionCallError(FunctionCallError::WasmTrap(WasmTrap::MemoryOutOfBounds))
             }
+            Some(TrapCode::HeapMisaligned) => VMError::FunctionCallError(
+                FunctionCallError::WasmTrap(WasmTrap::MisalignedAtomicAccess),
+            ),
             Some(TrapCode::TableOutOfBounds) => {
                 VMError::FunctionCallError(FunctionCallError::WasmTrap(WasmTrap::MemoryOutOfBounds))
             }
@@ -161,8 +161,17 @@ pub(super) fn default_config() -> wasmtime::Config {
     config
 }
 
+/// Engine shared by all contract calls, so that compiled modules can be reused between them.
+static ENGINE: Lazy<Engine> = Lazy::new(|| get_engine(&mut default_config()));
+
+pub(crate) fn engine() -> &'static Engine {
+    &ENGINE
+}
+
 pub(crate) fn wasmtime_vm_hash() -> u64 {
     // TODO: take into account compiler and engine used to compile the contract.
+    // Must be changed when upgrading wasmtime, as serialized modules are not portable across
+    // versions.
     64
 }
 
@@ -186,7 +195,7 @@ impl crate::runner::VM for WasmtimeVM {
         fees_config: &RuntimeFeesConfig,
         promise_results: &[PromiseResult],
         current_protocol_version: ProtocolVersion,
-        _cache: Option<&dyn CompiledContractCache>,
+        cache: Option<&dyn CompiledContractCache>,
     ) -> (Option<VMOutcome>, Option<VMError>) {
         let _span = tracing::debug_span!(
             target: "vm",
@@ -195,24 +204,29 @@ impl crate::runner::VM for WasmtimeVM {
             %method_name
         )
         .entered();
-        let mut config = default_config();
-        let engine = get_engine(&mut config);
-        let mut store = Store::new(&engine, ());
+        if method_name.is_empty() {
+            return (
+                None,
+                Some(VMError::FunctionCallError(FunctionCallError::MethodResolveError(
+                    MethodResolveError::MethodEmptyName,
+                ))),
+            );
+        }
+        let module =
+            cache::wasmtime_cache::compile_module_cached_wasmtime(code, &self.config, cache);
+        let module = match into_vm_result(module) {
+            Ok(module) => module,
+            Err(err) => return (None, Some(err)),
+        };
+        let engine = engine();
+        let mut store = Store::new(engine, ());
         let mut memory = WasmtimeMemory::new(
             &mut store,
             self.config.limit_config.initial_memory_pages,
             self.config.limit_config.max_memory_pages,
         )
         .unwrap();
-        let prepared_code = match prepare::prepare_contract(code.code(), &self.config) {
-            Ok(code) => code,
-            Err(err) => return (None, Some(VMError::from(err))),
-        };
-        let module = match Module::new(&engine, prepared_code) {
-            Ok(module) => module,
-            Err(err) => return (None, Some(err.into_vm_error())),
-        };
-        let mut linker = Linker::new(&engine);
+        let mut linker = Linker::new(engine);
         let memory_copy = memory.0;
         let mut logic = VMLogic::new_with_protocol_version(
             ext,
@@ -238,14 +252,6 @@ impl crate::runner::VM for WasmtimeVM {
         // lifetimes of the logic instance and pass raw pointers here.
         let raw_logic = &mut logic as *mut _ as *mut c_void;
         imports::wasmtime::link(&mut linker, memory_copy, raw_logic, current_protocol_version);
-        if method_name.is_empty() {
-            return (
-                None,
-                Some(VMError::FunctionCallError(FunctionCallError::MethodResolveError(
-                    MethodResolveError::MethodEmptyName,
-                ))),
-            );
-        }
         match module.get_export(method_name) {
             Some(export) => match export {
                 Func(func_type) => {
@@ -308,20 +314,20 @@ impl crate::runner::VM for WasmtimeVM {
 
     fn precompile(
         &self,
-        _code: &[u8],
-        _code_hash: &CryptoHash,
-        _cache: &dyn CompiledContractCache,
+        code: &[u8],
+        code_hash: &CryptoHash,
+        cache: &dyn CompiledContractCache,
     ) -> Option<VMError> {
-        Some(VMError::FunctionCallError(FunctionCallError::CompilationError(
-            CompilationError::UnsupportedCompiler {
-                msg: "Precompilation not supported in Wasmtime yet".to_string(),
-            },
-        )))
+        let result = cache::wasmtime_cache::compile_and_serialize_wasmtime(
+            code,
+            code_hash,
+            &self.config,
+            cache,
+        );
+        into_vm_result(result).err()
     }
 
     fn check_compile(&self, code: &Vec<u8>) -> bool {
-        let mut config = default_config();
-        let engine = get_engine(&mut config);
-        Module::new(&engine, code).is_ok()
+        Module::new(engine(), code).is_ok()
     }
 }
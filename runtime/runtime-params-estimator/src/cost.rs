@@ -415,6 +415,14 @@ pub enum Cost {
     AltBn128G1SumBase,
     AltBn128G1SumByte,
 
+    /// Estimates the base and per input byte costs of the `sha512` precompile, see
+    /// `near_vm_runner::precompiles`.
+    ///
+    /// Estimation: Measure the native implementation outside of the runtime on an empty input
+    /// and on a 10 KiB input.
+    PrecompileSha512Base,
+    PrecompileSha512Byte,
+
     // Costs used only in estimator
     //
     /// Costs associated with applying an empty block. This overhead is not
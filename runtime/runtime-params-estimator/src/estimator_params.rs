@@ -30,6 +30,26 @@ pub(crate) fn sha256_cost(metric: GasMetric, repeats: u64) -> GasCost {
     cpu_per_rep
 }
 
+/// Measure the cost of a single call of a native precompile on an input of the given length.
+#[cfg(feature = "protocol_feature_precompiles")]
+pub(crate) fn precompile_cost(
+    metric: GasMetric,
+    precompile: fn(&[u8]) -> Result<Vec<u8>, String>,
+    input_len: usize,
+) -> GasCost {
+    const REPEATS: u64 = 10_000;
+    let input = vec![0xab; input_len];
+    let cpu = measure_operation(REPEATS, metric, |repeats| {
+        let mut result = 0;
+        for _ in 0..repeats {
+            let output = precompile(&input).expect("precompile failed");
+            result += output.first().copied().unwrap_or_default() as i64;
+        }
+        result
+    });
+    cpu / REPEATS
+}
+
 fn exec_sha256(repeats: u64) -> i64 {
     use sha256::digest;
     let mut result = 0;
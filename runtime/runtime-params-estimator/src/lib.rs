@@ -166,6 +166,8 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::AltBn128G1SumByte, alt_bn128g1_sum_byte),
     (Cost::AltBn128PairingCheckBase, alt_bn128_pairing_check_base),
     (Cost::AltBn128PairingCheckByte, alt_bn128_pairing_check_byte),
+    (Cost::PrecompileSha512Base, precompile_sha512_base),
+    (Cost::PrecompileSha512Byte, precompile_sha512_byte),
     (Cost::StorageHasKeyBase, storage_has_key_base),
     (Cost::StorageHasKeyByte, storage_has_key_byte),
     (Cost::StorageReadBase, storage_read_base),
@@ -1240,6 +1242,30 @@ fn gas_metering(ctx: &mut EstimatorContext) -> (GasCost, GasCost) {
     (base, byte)
 }
 
+fn precompile_sha512_base(ctx: &mut EstimatorContext) -> GasCost {
+    #[cfg(feature = "protocol_feature_precompiles")]
+    return estimator_params::precompile_cost(
+        ctx.config.metric,
+        near_vm_runner::precompiles::sha512,
+        0,
+    );
+    #[cfg(not(feature = "protocol_feature_precompiles"))]
+    return GasCost::zero(ctx.config.metric);
+}
+
+fn precompile_sha512_byte(ctx: &mut EstimatorContext) -> GasCost {
+    #[cfg(feature = "protocol_feature_precompiles")]
+    {
+        const INPUT_LEN: usize = 10 * 1024;
+        let sha512 = near_vm_runner::precompiles::sha512;
+        let base = estimator_params::precompile_cost(ctx.config.metric, sha512, 0);
+        let total = estimator_params::precompile_cost(ctx.config.metric, sha512, INPUT_LEN);
+        return total.saturating_sub(&base, &NonNegativeTolerance::PER_MILLE) / INPUT_LEN as u64;
+    }
+    #[cfg(not(feature = "protocol_feature_precompiles"))]
+    return GasCost::zero(ctx.config.metric);
+}
+
 fn cpu_benchmark_sha256(ctx: &mut EstimatorContext) -> GasCost {
     const REPEATS: u64 = 1_000_000;
     sha256_cost(ctx.config.metric, REPEATS)
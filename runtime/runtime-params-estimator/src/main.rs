@@ -136,10 +136,15 @@ fn main() -> anyhow::Result<()> {
             None,
             false,
             None,
+            None,
+            None,
+            None,
             false,
             None,
             None,
             None,
+            None,
+            &Default::default(),
         )
         .expect("failed to init config");
 
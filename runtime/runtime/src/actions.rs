@@ -51,21 +51,6 @@ pub(crate) fn execute_function_call(
     view_config: Option<ViewConfig>,
 ) -> (Option<VMOutcome>, Option<VMError>) {
     let account_id = runtime_ext.account_id();
-    let code = match runtime_ext.get_code(account.code_hash()) {
-        Ok(Some(code)) => code,
-        Ok(None) => {
-            let error = FunctionCallError::CompilationError(CompilationError::CodeDoesNotExist {
-                account_id: account_id.clone(),
-            });
-            return (None, Some(VMError::FunctionCallError(error)));
-        }
-        Err(e) => {
-            return (
-                None,
-                Some(VMError::ExternalError(AnyError::new(ExternalError::StorageError(e)))),
-            );
-        }
-    };
     // Output data receipts are ignored if the function call is not the last action in the batch.
     let output_data_receivers: Vec<_> = if is_last_action {
         action_receipt.output_data_receivers.iter().map(|r| r.receiver_id.clone()).collect()
@@ -99,6 +84,35 @@ pub(crate) fn execute_function_call(
         output_data_receivers,
     };
 
+    #[cfg(feature = "protocol_feature_precompiles")]
+    if let Some(precompile) = near_vm_runner::precompiles::find_precompile(
+        account_id,
+        &function_call.method_name,
+        apply_state.current_protocol_version,
+    ) {
+        return near_vm_runner::precompiles::run_precompile(
+            precompile,
+            &context,
+            &config.wasm_config.ext_costs,
+        );
+    }
+
+    let code = match runtime_ext.get_code(account.code_hash()) {
+        Ok(Some(code)) => code,
+        Ok(None) => {
+            let error = FunctionCallError::CompilationError(CompilationError::CodeDoesNotExist {
+                account_id: account_id.clone(),
+            });
+            return (None, Some(VMError::FunctionCallError(error)));
+        }
+        Err(e) => {
+            return (
+                None,
+                Some(VMError::ExternalError(AnyError::new(ExternalError::StorageError(e)))),
+            );
+        }
+    };
+
     // TODO (#5920): enable chunk caching in the protocol. Also consider using RAII for switching the state back
     // runtime_ext.set_trie_cache_mode(TrieCacheMode::CachingChunk);
     let result = near_vm_runner::run(
@@ -388,6 +388,10 @@ mod tests {
                 gas_deficit_amount: 0,
                 other_burnt_amount: 0,
                 slashed_burnt_amount: 0,
+                state_witness_size: 0,
+                delayed_receipts_count: 0,
+                delayed_receipts_gas_added: 0,
+                delayed_receipts_gas_removed: 0,
             },
             PROTOCOL_VERSION,
         )
@@ -6,6 +6,7 @@ use num_traits::pow::Pow;
 
 use near_primitives::account::AccessKeyPermission;
 use near_primitives::errors::IntegerOverflowError;
+use near_primitives::receipt::{Receipt, ReceiptEnum};
 // Just re-exporting RuntimeConfig for backwards compatibility.
 pub use near_primitives::num_rational::Rational;
 pub use near_primitives::runtime::config::RuntimeConfig;
@@ -262,6 +263,14 @@ pub fn total_prepaid_gas(actions: &[Action]) -> Result<Gas, IntegerOverflowError
     actions.iter().try_fold(0, |acc, action| safe_add_gas(acc, action.get_prepaid_gas()))
 }
 
+/// Get the total sum of prepaid gas for the actions of the given receipt.
+pub fn total_receipt_prepaid_gas(receipt: &Receipt) -> Result<Gas, IntegerOverflowError> {
+    match &receipt.receipt {
+        ReceiptEnum::Action(action_receipt) => total_prepaid_gas(&action_receipt.actions),
+        ReceiptEnum::Data(_) => Ok(0),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
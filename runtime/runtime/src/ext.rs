@@ -9,6 +9,8 @@ use near_primitives::contract::ContractCode;
 use near_primitives::errors::{EpochError, StorageError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::{ActionReceipt, DataReceiver, Receipt, ReceiptEnum};
+#[cfg(feature = "receipt_trace")]
+use near_primitives::receipt_trace;
 use near_primitives::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
     DeployContractAction, FunctionCallAction, StakeAction, TransferAction,
@@ -19,6 +21,8 @@ use near_primitives::types::{AccountId, Balance, EpochId, EpochInfoProvider, Gas
 use near_primitives::types::{GasDistribution, GasWeight};
 use near_primitives::utils::create_data_id;
 use near_primitives::version::ProtocolVersion;
+#[cfg(feature = "receipt_trace")]
+use near_primitives::views::ReceiptTraceEvent;
 use near_store::{get_code, TrieUpdate, TrieUpdateValuePtr};
 use near_vm_errors::{AnyError, HostError, VMLogicError};
 use near_vm_logic::{External, ValuePtr};
@@ -187,6 +191,11 @@ type ExtResult<T> = ::std::result::Result<T, VMLogicError>;
 
 impl<'a> External for RuntimeExt<'a> {
     fn storage_set(&mut self, key: &[u8], value: &[u8]) -> ExtResult<()> {
+        #[cfg(feature = "receipt_trace")]
+        receipt_trace::record(|| ReceiptTraceEvent::StorageWrite {
+            key: key.to_vec(),
+            value_len: value.len() as u64,
+        });
         let storage_key = self.create_storage_key(key);
         self.trie_update.set(storage_key, Vec::from(value));
         Ok(())
@@ -194,13 +203,24 @@ impl<'a> External for RuntimeExt<'a> {
 
     fn storage_get<'b>(&'b self, key: &[u8]) -> ExtResult<Option<Box<dyn ValuePtr + 'b>>> {
         let storage_key = self.create_storage_key(key);
-        self.trie_update
+        let result = self
+            .trie_update
             .get_ref(&storage_key)
             .map_err(wrap_storage_error)
-            .map(|option| option.map(|ptr| Box::new(RuntimeExtValuePtr(ptr)) as Box<_>))
+            .map(|option| option.map(|ptr| Box::new(RuntimeExtValuePtr(ptr)) as Box<_>));
+        #[cfg(feature = "receipt_trace")]
+        if let Ok(value) = &result {
+            receipt_trace::record(|| ReceiptTraceEvent::StorageRead {
+                key: key.to_vec(),
+                value_len: value.as_ref().map(|ptr| ptr.len() as u64),
+            });
+        }
+        result
     }
 
     fn storage_remove(&mut self, key: &[u8]) -> ExtResult<()> {
+        #[cfg(feature = "receipt_trace")]
+        receipt_trace::record(|| ReceiptTraceEvent::StorageRemove { key: key.to_vec() });
         let storage_key = self.create_storage_key(key);
         self.trie_update.remove(storage_key);
         Ok(())
@@ -208,7 +228,13 @@ impl<'a> External for RuntimeExt<'a> {
 
     fn storage_has_key(&mut self, key: &[u8]) -> ExtResult<bool> {
         let storage_key = self.create_storage_key(key);
-        self.trie_update.get_ref(&storage_key).map(|x| x.is_some()).map_err(wrap_storage_error)
+        let result =
+            self.trie_update.get_ref(&storage_key).map(|x| x.is_some()).map_err(wrap_storage_error);
+        #[cfg(feature = "receipt_trace")]
+        if let Ok(found) = result {
+            receipt_trace::record(|| ReceiptTraceEvent::StorageHasKey { key: key.to_vec(), found });
+        }
+        result
     }
 
     fn storage_remove_subtree(&mut self, prefix: &[u8]) -> ExtResult<()> {
@@ -12,6 +12,8 @@ pub use near_primitives;
 #[cfg(feature = "sandbox")]
 use near_primitives::contract::ContractCode;
 use near_primitives::profile::ProfileData;
+#[cfg(feature = "receipt_trace")]
+use near_primitives::receipt_trace;
 pub use near_primitives::runtime::apply_state::ApplyState;
 use near_primitives::runtime::fees::RuntimeFeesConfig;
 use near_primitives::runtime::get_insufficient_storage_stake;
@@ -57,7 +59,7 @@ use crate::actions::*;
 use crate::balance_checker::check_balance;
 use crate::config::{
     exec_fee, safe_add_balance, safe_add_gas, safe_gas_to_balance, total_deposit,
-    total_prepaid_exec_fees, total_prepaid_gas, RuntimeConfig,
+    total_prepaid_exec_fees, total_prepaid_gas, total_receipt_prepaid_gas, RuntimeConfig,
 };
 use crate::genesis::{GenesisStateApplier, StorageComputer};
 use crate::verifier::validate_receipt;
@@ -111,6 +113,15 @@ pub struct ApplyStats {
     /// This is a negative amount. This amount was not charged from the account that issued
     /// the transaction. It's likely due to the delayed queue of the receipts.
     pub gas_deficit_amount: Balance,
+    /// Total size of the distinct trie nodes and values touched while applying the chunk, i.e.
+    /// the size of the state witness needed to validate the chunk without the state.
+    pub state_witness_size: u64,
+    /// Number of receipts in the delayed receipts queue after applying the chunk.
+    pub delayed_receipts_count: u64,
+    /// Total prepaid gas of the receipts added to the delayed receipts queue.
+    pub delayed_receipts_gas_added: Gas,
+    /// Total prepaid gas of the receipts removed from the delayed receipts queue.
+    pub delayed_receipts_gas_removed: Gas,
 }
 
 pub struct ApplyResult {
@@ -185,6 +196,12 @@ impl Default for ActionResult {
     }
 }
 
+/// Prepaid gas of the receipt for the apply statistics, which saturates instead of failing the
+/// apply on an overflow.
+fn receipt_prepaid_gas_stat(receipt: &Receipt) -> Gas {
+    total_receipt_prepaid_gas(receipt).unwrap_or(Gas::MAX)
+}
+
 pub struct Runtime {}
 
 impl Runtime {
@@ -843,6 +860,8 @@ impl Runtime {
         stats: &mut ApplyStats,
         epoch_info_provider: &dyn EpochInfoProvider,
     ) -> Result<Option<ExecutionOutcomeWithId>, RuntimeError> {
+        #[cfg(feature = "receipt_trace")]
+        let _trace_guard = receipt_trace::ReceiptTraceGuard::enter(&receipt.receipt_id);
         let account_id = &receipt.receiver_id;
         match receipt.receipt {
             ReceiptEnum::Data(ref data_receipt) => {
@@ -1186,6 +1205,11 @@ impl Runtime {
         let trie = Rc::new(trie);
         let initial_state = TrieUpdate::new(trie.clone(), root);
         let mut state_update = TrieUpdate::new(trie.clone(), root);
+        state_update.set_inline_small_values(checked_feature!(
+            "protocol_feature_inline_small_values",
+            InlineSmallValues,
+            apply_state.current_protocol_version
+        ));
 
         let mut stats = ApplyStats::default();
 
@@ -1218,6 +1242,7 @@ impl Runtime {
                 >= ProtocolFeature::FixApplyChunks.protocol_version()
         {
             let (trie_changes, state_changes) = state_update.finalize()?;
+            stats.state_witness_size = trie.get_state_witness_size();
             let proof = trie.recorded_storage();
             return Ok(ApplyResult {
                 state_root: trie_changes.new_root,
@@ -1263,12 +1288,13 @@ impl Runtime {
         let mut delayed_receipts_indices: DelayedReceiptIndices =
             get(&state_update, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
         let initial_delayed_receipt_indices = delayed_receipts_indices.clone();
+        let mut delayed_receipts_gas_added: Gas = 0;
 
         let mut process_receipt = |receipt: &Receipt,
                                    state_update: &mut TrieUpdate,
                                    total_gas_burnt: &mut Gas|
          -> Result<_, RuntimeError> {
-            let _span = tracing::debug_span!(target: "runtime", "Runtime::process_receipt", receipt_id = %receipt.receipt_id, node_counter = state_update.trie.get_touched_nodes_count()).entered();
+            let _span = tracing::debug_span!(target: "runtime", "Runtime::process_receipt", receipt_id = %receipt.receipt_id, receiver_id = %receipt.receiver_id, node_counter = state_update.trie.get_touched_nodes_count(), witness_size = state_update.trie.get_state_witness_size()).entered();
             let result = self.process_receipt(
                 state_update,
                 apply_state,
@@ -1278,7 +1304,7 @@ impl Runtime {
                 &mut stats,
                 epoch_info_provider,
             );
-            tracing::debug!(target: "runtime", node_counter = state_update.trie.get_touched_nodes_count());
+            tracing::debug!(target: "runtime", node_counter = state_update.trie.get_touched_nodes_count(), witness_size = state_update.trie.get_state_witness_size());
             result?.into_iter().try_for_each(
                 |outcome_with_id: ExecutionOutcomeWithId| -> Result<(), RuntimeError> {
                     *total_gas_burnt =
@@ -1300,6 +1326,8 @@ impl Runtime {
                 process_receipt(receipt, &mut state_update, &mut total_gas_burnt)?;
             } else {
                 Self::delay_receipt(&mut state_update, &mut delayed_receipts_indices, receipt)?;
+                delayed_receipts_gas_added =
+                    delayed_receipts_gas_added.saturating_add(receipt_prepaid_gas_stat(receipt));
             }
         }
 
@@ -1343,12 +1371,20 @@ impl Runtime {
                 process_receipt(receipt, &mut state_update, &mut total_gas_burnt)?;
             } else {
                 Self::delay_receipt(&mut state_update, &mut delayed_receipts_indices, receipt)?;
+                delayed_receipts_gas_added =
+                    delayed_receipts_gas_added.saturating_add(receipt_prepaid_gas_stat(receipt));
             }
         }
 
         if delayed_receipts_indices != initial_delayed_receipt_indices {
             set(&mut state_update, TrieKey::DelayedReceiptIndices, &delayed_receipts_indices);
         }
+        stats.delayed_receipts_count =
+            delayed_receipts_indices.next_available_index - delayed_receipts_indices.first_index;
+        stats.delayed_receipts_gas_added = delayed_receipts_gas_added;
+        stats.delayed_receipts_gas_removed = processed_delayed_receipts
+            .iter()
+            .fold(0, |acc: Gas, receipt| acc.saturating_add(receipt_prepaid_gas_stat(receipt)));
 
         check_balance(
             &apply_state.config.transaction_costs,
@@ -1384,6 +1420,7 @@ impl Runtime {
         }
 
         let state_root = trie_changes.new_root;
+        stats.state_witness_size = trie.get_state_witness_size();
         let proof = trie.recorded_storage();
         Ok(ApplyResult {
             state_root,
@@ -1743,6 +1780,7 @@ mod tests {
                     + small_transfer * Balance::from(capped_i)
                     + Balance::from(capped_i * (capped_i - 1) / 2)
             );
+            assert_eq!(apply_result.stats.delayed_receipts_count, n - capped_i);
         }
     }
 
@@ -20,6 +20,26 @@ pub enum ViewContractCodeError {
     InternalError { error_message: String },
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum ViewAbiError {
+    #[error("Account ID \"{requested_account_id}\" is invalid")]
+    InvalidAccountId { requested_account_id: near_primitives::types::AccountId },
+    #[error("Account ID #{requested_account_id} does not exist")]
+    AccountDoesNotExist { requested_account_id: near_primitives::types::AccountId },
+    #[error("Contract code for contract ID #{contract_account_id} does not exist")]
+    NoContractCode { contract_account_id: near_primitives::types::AccountId },
+    #[error("ABI of contract ID #{contract_account_id} is not available")]
+    NoContractAbi { contract_account_id: near_primitives::types::AccountId },
+    #[error("ABI of contract ID #{contract_account_id} has hash {abi_hash}, but the contract code declares {declared_hash}")]
+    AbiHashMismatch {
+        contract_account_id: near_primitives::types::AccountId,
+        abi_hash: near_primitives::hash::CryptoHash,
+        declared_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("Internal error: #{error_message}")]
+    InternalError { error_message: String },
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ViewAccessKeyError {
     #[error("Account ID \"{requested_account_id}\" is invalid")]
@@ -38,6 +58,8 @@ pub enum ViewStateError {
     AccountDoesNotExist { requested_account_id: near_primitives::types::AccountId },
     #[error("The state of {requested_account_id} is too large")]
     AccountStateTooLarge { requested_account_id: near_primitives::types::AccountId },
+    #[error("Viewing the state of {requested_account_id} took too long")]
+    Timeout { requested_account_id: near_primitives::types::AccountId },
     #[error("Internal error: #{error_message}")]
     InternalError { error_message: String },
 }
@@ -70,6 +92,25 @@ impl From<ViewAccountError> for ViewContractCodeError {
     }
 }
 
+impl From<ViewContractCodeError> for ViewAbiError {
+    fn from(view_contract_code_error: ViewContractCodeError) -> Self {
+        match view_contract_code_error {
+            ViewContractCodeError::InvalidAccountId { requested_account_id } => {
+                Self::InvalidAccountId { requested_account_id }
+            }
+            ViewContractCodeError::AccountDoesNotExist { requested_account_id } => {
+                Self::AccountDoesNotExist { requested_account_id }
+            }
+            ViewContractCodeError::NoContractCode { contract_account_id } => {
+                Self::NoContractCode { contract_account_id }
+            }
+            ViewContractCodeError::InternalError { error_message } => {
+                Self::InternalError { error_message }
+            }
+        }
+    }
+}
+
 impl From<near_primitives::errors::StorageError> for ViewAccountError {
     fn from(storage_error: near_primitives::errors::StorageError) -> Self {
         Self::InternalError { error_message: storage_error.to_string() }
@@ -82,6 +123,12 @@ impl From<near_primitives::errors::StorageError> for ViewContractCodeError {
     }
 }
 
+impl From<near_primitives::errors::StorageError> for ViewAbiError {
+    fn from(storage_error: near_primitives::errors::StorageError) -> Self {
+        Self::InternalError { error_message: storage_error.to_string() }
+    }
+}
+
 impl From<near_primitives::errors::StorageError> for ViewAccessKeyError {
     fn from(storage_error: near_primitives::errors::StorageError) -> Self {
         Self::InternalError { error_message: storage_error.to_string() }
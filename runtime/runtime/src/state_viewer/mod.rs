@@ -15,21 +15,38 @@ use near_primitives::{
     serialize::to_base64,
     transaction::FunctionCallAction,
     trie_key::trie_key_parsers,
+    trie_key::TrieKey,
     types::{AccountId, EpochInfoProvider, Gas},
-    views::{StateItem, ViewApplyState, ViewStateResult},
+    views::{ContractAbiSource, ContractAbiView, StateItem, ViewApplyState, ViewStateResult},
 };
 use near_store::{get_access_key, get_account, get_code, TrieUpdate};
 use near_vm_logic::{ReturnData, ViewConfig};
-use std::{str, sync::Arc, time::Instant};
+use std::{
+    path::PathBuf,
+    str,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::debug;
 
 pub mod errors;
 
+/// Key of the contract data under which a contract may publish its ABI.
+pub const CONTRACT_ABI_KEY: &[u8] = b"__abi";
+/// Name of the custom section of the wasm code declaring the hash of the contract's ABI.
+pub const ABI_HASH_SECTION: &str = "near_abi_hash";
+
 pub struct TrieViewer {
     /// Upper bound of the byte size of contract state that is still viewable. None is no limit
     state_size_limit: Option<u64>,
     /// Gas limit used when when handling call_function queries.
     max_gas_burnt_view: Gas,
+    /// Maximum time spent iterating over the state of a contract in view_state queries. None is
+    /// no limit
+    view_state_timeout: Option<Duration>,
+    /// Directory with the ABIs of contracts which don't publish them, named
+    /// `<code hash>.json`.
+    abi_dir: Option<PathBuf>,
 }
 
 impl Default for TrieViewer {
@@ -37,7 +54,12 @@ impl Default for TrieViewer {
         let config_store = RuntimeConfigStore::new(None);
         let latest_runtime_config = config_store.get_config(PROTOCOL_VERSION);
         let max_gas_burnt = latest_runtime_config.wasm_config.limit_config.max_gas_burnt;
-        Self { state_size_limit: None, max_gas_burnt_view: max_gas_burnt }
+        Self {
+            state_size_limit: None,
+            max_gas_burnt_view: max_gas_burnt,
+            view_state_timeout: None,
+            abi_dir: None,
+        }
     }
 }
 
@@ -45,7 +67,15 @@ impl TrieViewer {
     pub fn new(state_size_limit: Option<u64>, max_gas_burnt_view: Option<Gas>) -> Self {
         let max_gas_burnt_view =
             max_gas_burnt_view.unwrap_or_else(|| TrieViewer::default().max_gas_burnt_view);
-        Self { state_size_limit, max_gas_burnt_view }
+        Self { state_size_limit, max_gas_burnt_view, view_state_timeout: None, abi_dir: None }
+    }
+
+    pub fn set_view_state_timeout(&mut self, view_state_timeout: Option<Duration>) {
+        self.view_state_timeout = view_state_timeout;
+    }
+
+    pub fn set_abi_dir(&mut self, abi_dir: Option<PathBuf>) {
+        self.abi_dir = abi_dir;
     }
 
     pub fn view_account(
@@ -73,6 +103,67 @@ impl TrieViewer {
         })
     }
 
+    /// Returns the ABI of the contract deployed to the account, published by the contract under
+    /// [`CONTRACT_ABI_KEY`] or else found in the ABI directory.  If the code declares the hash of
+    /// its ABI in the [`ABI_HASH_SECTION`] custom section, only an ABI with that hash is returned.
+    pub fn view_abi(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+    ) -> Result<ContractAbiView, errors::ViewAbiError> {
+        let code = self.view_contract_code(state_update, account_id)?;
+        let code_hash = *code.hash();
+        let declared_hash = abi_hash_from_code(code.code());
+        let mut candidates = vec![];
+        let key = TrieKey::ContractData {
+            account_id: account_id.clone(),
+            key: CONTRACT_ABI_KEY.to_vec(),
+        };
+        if let Some(abi) = state_update.get(&key)? {
+            candidates.push((abi, ContractAbiSource::State));
+        }
+        if let Some(abi_dir) = &self.abi_dir {
+            let path = abi_dir.join(format!("{}.json", code_hash));
+            match std::fs::read(&path) {
+                Ok(abi) => candidates.push((abi, ContractAbiSource::Directory)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(errors::ViewAbiError::InternalError {
+                        error_message: format!("Failed to read {}: {}", path.display(), err),
+                    })
+                }
+            }
+        }
+        let mut mismatched_hash = None;
+        for (abi, source) in candidates {
+            let abi_hash = CryptoHash::hash_bytes(&abi);
+            match declared_hash {
+                Some(declared_hash) if declared_hash != abi_hash => {
+                    mismatched_hash.get_or_insert(abi_hash);
+                }
+                _ => {
+                    return Ok(ContractAbiView {
+                        abi,
+                        abi_hash,
+                        code_hash,
+                        source,
+                        verified: declared_hash.is_some(),
+                    })
+                }
+            }
+        }
+        match (mismatched_hash, declared_hash) {
+            (Some(abi_hash), Some(declared_hash)) => Err(errors::ViewAbiError::AbiHashMismatch {
+                contract_account_id: account_id.clone(),
+                abi_hash,
+                declared_hash,
+            }),
+            _ => {
+                Err(errors::ViewAbiError::NoContractAbi { contract_account_id: account_id.clone() })
+            }
+        }
+    }
+
     pub fn view_access_key(
         &self,
         state_update: &TrieUpdate,
@@ -140,6 +231,7 @@ impl TrieViewer {
             }
         };
 
+        let deadline = self.view_state_timeout.map(|timeout| Instant::now() + timeout);
         let mut values = vec![];
         let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
         let acc_sep_len = query.len() - prefix.len();
@@ -150,6 +242,11 @@ impl TrieViewer {
             if !key.starts_with(query.as_ref()) {
                 break;
             }
+            if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                return Err(errors::ViewStateError::Timeout {
+                    requested_account_id: account_id.clone(),
+                });
+            }
             values.push(StateItem {
                 key: to_base64(&key[acc_sep_len..]),
                 value: to_base64(&value),
@@ -265,3 +362,58 @@ impl TrieViewer {
         }
     }
 }
+
+/// Returns the hash in the [`ABI_HASH_SECTION`] custom section of the wasm code, if it has one.
+fn abi_hash_from_code(code: &[u8]) -> Option<CryptoHash> {
+    // Magic number and version.
+    let mut rest = code.get(8..)?;
+    while let Some((&section_id, tail)) = rest.split_first() {
+        let (size, tail) = read_leb128_u32(tail)?;
+        let section = tail.get(..size as usize)?;
+        rest = &tail[size as usize..];
+        if section_id != 0 {
+            continue;
+        }
+        let (name_len, section) = read_leb128_u32(section)?;
+        let name = section.get(..name_len as usize)?;
+        if name == ABI_HASH_SECTION.as_bytes() {
+            return CryptoHash::try_from(&section[name_len as usize..]).ok();
+        }
+    }
+    None
+}
+
+fn read_leb128_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let mut value = 0u32;
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use near_primitives::hash::CryptoHash;
+
+    use super::{abi_hash_from_code, ABI_HASH_SECTION};
+
+    #[test]
+    fn test_abi_hash_from_code() {
+        let abi_hash = CryptoHash::hash_bytes(b"{}");
+        let mut section = vec![ABI_HASH_SECTION.len() as u8];
+        section.extend_from_slice(ABI_HASH_SECTION.as_bytes());
+        section.extend_from_slice(abi_hash.as_ref());
+        let mut code = b"\0asm\x01\0\0\0".to_vec();
+        // An empty type section and another custom section come first.
+        code.extend_from_slice(&[1, 1, 0, 0, 2, 1, b'x']);
+        code.push(0);
+        code.push(section.len() as u8);
+        code.extend_from_slice(&section);
+        assert_eq!(abi_hash_from_code(&code), Some(abi_hash));
+        assert_eq!(abi_hash_from_code(&code[..code.len() - 1]), None);
+        assert_eq!(abi_hash_from_code(b"\0asm\x01\0\0\0"), None);
+    }
+}
@@ -8,6 +8,7 @@ use std::sync::atomic::Ordering;
 use tokio::time;
 
 use near_primitives::hash::CryptoHash;
+use near_primitives::types::ShardId;
 
 // run() fetches the chain (headers,blocks and chunks)
 // starting with block having hash = <start_block_hash> and
@@ -64,11 +65,12 @@ pub async fn run(
                         let network = network.clone();
                         |ctx, s| async move {
                             let block = network.fetch_block(&ctx, h.hash()).await?;
+                            let num_shards = block.chunks().len() as ShardId;
                             for ch in block.chunks().iter() {
                                 let ch = ch.clone();
                                 let network = network.clone();
-                                s.spawn(|ctx, _s| async move {
-                                    network.fetch_chunk(&ctx, &ch).await?;
+                                s.spawn(move |ctx, _s| async move {
+                                    network.fetch_chunk(&ctx, &ch, num_shards).await?;
                                     anyhow::Ok(())
                                 });
                             }
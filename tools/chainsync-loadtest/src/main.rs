@@ -1,40 +1,52 @@
 mod concurrency;
 mod fetch_chain;
 mod network;
+mod recording;
+mod replay;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use actix::{Actor, Arbiter};
+use actix::dev::ToEnvelope;
+use actix::{Actor, Addr, Arbiter, Handler};
 use anyhow::{anyhow, Context};
 use clap::Parser;
 use openssl_probe;
 
 use concurrency::{Ctx, Scope};
 use network::{FakeClientActor, Network};
+use recording::{read_recording, Recorder};
+use replay::{Recording, Replay, ReplayClientActor};
 
 use near_chain_configs::Genesis;
 use near_network::routing::start_routing_table_actor;
 use near_network::test_utils::NetworkRecipient;
+use near_network::types::NetworkClientMessages;
 use near_network::PeerManagerActor;
+use near_network_primitives::types::{NetworkViewClientMessages, PeerInfo};
 use near_o11y::tracing::{error, info};
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
+use near_primitives::types::AccountId;
 use near_store::{db, Store};
 use nearcore::config;
 use nearcore::config::NearConfig;
 
-pub fn start_with_config(config: NearConfig, qps_limit: u32) -> anyhow::Result<Arc<Network>> {
-    config.network_config.verify().context("start_with_config")?;
+// start_peer_manager() starts a PeerManager which passes the messages received from the network
+// to <client_actor> and connects it to <network_adapter>.
+fn start_peer_manager<A>(
+    config: NearConfig,
+    network_adapter: &NetworkRecipient,
+    client_actor: Addr<A>,
+) -> anyhow::Result<()>
+where
+    A: Actor + Handler<NetworkClientMessages> + Handler<NetworkViewClientMessages>,
+    A::Context: ToEnvelope<A, NetworkClientMessages> + ToEnvelope<A, NetworkViewClientMessages>,
+{
+    config.network_config.verify().context("start_peer_manager")?;
     let node_id = PeerId::new(config.network_config.public_key.clone());
     let store = Store::new(Arc::new(db::TestDB::new()));
 
-    let network_adapter = Arc::new(NetworkRecipient::default());
-    let network = Network::new(&config, network_adapter.clone(), qps_limit);
-    let client_actor = FakeClientActor::start_in_arbiter(&Arbiter::new().handle(), {
-        let network = network.clone();
-        move |_| FakeClientActor::new(network)
-    });
-
     let routing_table_addr = start_routing_table_actor(node_id, store.clone());
     let network_actor = PeerManagerActor::start_in_arbiter(&Arbiter::new().handle(), move |_ctx| {
         PeerManagerActor::new(
@@ -48,15 +60,50 @@ pub fn start_with_config(config: NearConfig, qps_limit: u32) -> anyhow::Result<A
     })
     .recipient();
     network_adapter.set_recipient(network_actor);
+    return Ok(());
+}
+
+pub fn start_with_config(
+    config: NearConfig,
+    qps_limit: u32,
+    recorder: Option<Recorder>,
+) -> anyhow::Result<Arc<Network>> {
+    let network_adapter = Arc::new(NetworkRecipient::default());
+    let network = Network::new(&config, network_adapter.clone(), qps_limit, recorder);
+    let client_actor = FakeClientActor::start_in_arbiter(&Arbiter::new().handle(), {
+        let network = network.clone();
+        move |_| FakeClientActor::new(network)
+    });
+    start_peer_manager(config, &network_adapter, client_actor)?;
     return Ok(network);
 }
 
+pub fn start_replay(
+    config: NearConfig,
+    recording: Recording,
+    forward_tx_to: Option<AccountId>,
+) -> anyhow::Result<Arc<Replay>> {
+    let network_adapter = Arc::new(NetworkRecipient::default());
+    let replay = Replay::new(
+        recording,
+        &config.client_config.chain_id,
+        network_adapter.clone(),
+        forward_tx_to,
+    );
+    let client_actor = ReplayClientActor::start_in_arbiter(&Arbiter::new().handle(), {
+        let replay = replay.clone();
+        move |_| ReplayClientActor::new(replay)
+    });
+    start_peer_manager(config, &network_adapter, client_actor)?;
+    return Ok(replay);
+}
+
 fn download_configs(chain_id: &str, dir: &std::path::Path) -> anyhow::Result<NearConfig> {
     // Always fetch the config.
     std::fs::create_dir_all(dir)?;
     let url = config::get_config_url(chain_id);
     let config_path = &dir.join(config::CONFIG_FILENAME);
-    config::download_config(&url, config_path)?;
+    config::download_config(&url, config_path, None)?;
     let config = config::Config::from_file(config_path)?;
 
     // Generate node key.
@@ -72,19 +119,50 @@ fn download_configs(chain_id: &str, dir: &std::path::Path) -> anyhow::Result<Nea
 struct Cmd {
     #[clap(long)]
     pub chain_id: String,
-    #[clap(long)]
-    pub start_block_hash: String,
     #[clap(long, default_value = "200")]
     pub qps_limit: u32,
+    #[clap(subcommand)]
+    pub subcmd: SubCmd,
+}
+
+#[derive(Parser, Debug)]
+enum SubCmd {
+    /// Fetches the chain from the network, optionally recording the received messages.
+    Fetch(FetchCmd),
+    /// Replays a recording against a node under test.
+    Replay(ReplayCmd),
+}
+
+#[derive(Parser, Debug)]
+struct FetchCmd {
+    #[clap(long)]
+    pub start_block_hash: String,
     #[clap(long, default_value = "2000")]
     pub block_limit: u64,
+    /// File to record the received blocks, chunks and transactions to.
+    #[clap(long)]
+    pub record: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ReplayCmd {
+    /// File with a recording made by the fetch command.
+    #[clap(long)]
+    pub recording: PathBuf,
+    /// Multiplier of the pace at which the recorded blocks are released.
+    #[clap(long, default_value = "1.0")]
+    pub speed: f64,
+    /// Node under test, in the <peer id>@<address> format.
+    #[clap(long)]
+    pub node: String,
+    /// If set, the recorded transactions are forwarded to this account.
+    #[clap(long)]
+    pub forward_tx_to: Option<AccountId>,
 }
 
 impl Cmd {
     fn parse_and_run() -> anyhow::Result<()> {
         let cmd = Self::parse();
-        let start_block_hash =
-            cmd.start_block_hash.parse::<CryptoHash>().map_err(|x| anyhow!(x.to_string()))?;
 
         let mut cache_dir = dirs::cache_dir().context("dirs::cache_dir() = None")?;
         cache_dir.push("near_configs");
@@ -92,10 +170,9 @@ impl Cmd {
 
         info!("downloading configs for chain {}", cmd.chain_id);
         let home_dir = cache_dir.as_path();
-        let near_config =
+        let mut near_config =
             download_configs(&cmd.chain_id, home_dir).context("Failed to initialize configs")?;
 
-        info!("#boot nodes = {}", near_config.network_config.boot_nodes.len());
         // Dropping Runtime is blocking, while futures should never be blocking.
         // Tokio has a runtime check which panics if you drop tokio Runtime from a future executed
         // on another Tokio runtime.
@@ -103,29 +180,75 @@ impl Cmd {
         // inside of it.
         let rt_ = Arc::new(tokio::runtime::Runtime::new()?);
         let rt = rt_.clone();
-        return actix::System::new().block_on(async move {
-            let network =
-                start_with_config(near_config, cmd.qps_limit).context("start_with_config")?;
-
-            // We execute the chain_sync on a totally separate set of system threads to minimize
-            // the interaction with actix.
-            rt.spawn(async move {
-                Scope::run(&Ctx::background(), move |ctx, s| async move {
-                    s.spawn_weak(|ctx| async move {
-                        ctx.wrap(tokio::signal::ctrl_c()).await?.unwrap();
-                        info!("Got CTRL+C, stopping...");
-                        return Err(anyhow!("Got CTRL+C"));
-                    });
-                    fetch_chain::run(ctx.clone(), network, start_block_hash, cmd.block_limit)
-                        .await?;
-                    info!("Fetch completed");
-                    anyhow::Ok(())
-                })
-                .await
-            })
-            .await??;
-            return Ok(());
-        });
+        match cmd.subcmd {
+            SubCmd::Fetch(fetch) => {
+                let start_block_hash = fetch
+                    .start_block_hash
+                    .parse::<CryptoHash>()
+                    .map_err(|x| anyhow!(x.to_string()))?;
+                let recorder = match &fetch.record {
+                    Some(path) => Some(Recorder::create(path)?),
+                    None => None,
+                };
+                info!("#boot nodes = {}", near_config.network_config.boot_nodes.len());
+                return actix::System::new().block_on(async move {
+                    let network = start_with_config(near_config, cmd.qps_limit, recorder)
+                        .context("start_with_config")?;
+
+                    // We execute the chain_sync on a totally separate set of system threads to minimize
+                    // the interaction with actix.
+                    rt.spawn(async move {
+                        Scope::run(&Ctx::background(), move |ctx, s| async move {
+                            s.spawn_weak(|ctx| async move {
+                                ctx.wrap(tokio::signal::ctrl_c()).await?.unwrap();
+                                info!("Got CTRL+C, stopping...");
+                                return Err(anyhow!("Got CTRL+C"));
+                            });
+                            fetch_chain::run(
+                                ctx.clone(),
+                                network.clone(),
+                                start_block_hash,
+                                fetch.block_limit,
+                            )
+                            .await?;
+                            info!("Fetch completed");
+                            if let Some(recorder) = &network.recorder {
+                                recorder.flush()?;
+                            }
+                            anyhow::Ok(())
+                        })
+                        .await
+                    })
+                    .await??;
+                    return Ok(());
+                });
+            }
+            SubCmd::Replay(replay) => {
+                let recording = Recording::new(read_recording(&replay.recording)?)?;
+                // The only peer of the replay is the node under test.
+                near_config.network_config.boot_nodes =
+                    vec![replay.node.parse::<PeerInfo>().map_err(|x| anyhow!(x.to_string()))?];
+                return actix::System::new().block_on(async move {
+                    let r = start_replay(near_config, recording, replay.forward_tx_to)
+                        .context("start_replay")?;
+                    rt.spawn(async move {
+                        Scope::run(&Ctx::background(), move |ctx, s| async move {
+                            s.spawn_weak(|ctx| async move {
+                                ctx.wrap(tokio::signal::ctrl_c()).await?.unwrap();
+                                info!("Got CTRL+C, stopping...");
+                                return Err(anyhow!("Got CTRL+C"));
+                            });
+                            r.run(&ctx, replay.speed).await?;
+                            info!("Replay completed");
+                            anyhow::Ok(())
+                        })
+                        .await
+                    })
+                    .await??;
+                    return Ok(());
+                });
+            }
+        }
     }
 }
 
@@ -1,6 +1,8 @@
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use crate::concurrency::{Ctx, Once, RateLimiter, Scope, WeakMap};
+use crate::recording::{RecordedMessage, Recorder};
 
 use near_network_primitives::types::{
     AccountIdOrPeerTrackingShard, NetworkViewClientMessages, NetworkViewClientResponses,
@@ -17,6 +19,7 @@ use near_primitives::block::{Block, BlockHeader, GenesisId};
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
 use near_primitives::time::Clock;
+use near_primitives::types::ShardId;
 use nearcore::config::NearConfig;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
@@ -25,7 +28,7 @@ use std::sync::{Arc, Mutex};
 use tokio::sync::oneshot;
 use tokio::time;
 
-fn genesis_hash(chain_id: &str) -> CryptoHash {
+pub fn genesis_hash(chain_id: &str) -> CryptoHash {
     return match chain_id {
         "mainnet" => "EPnLgE7iEq9s7yTkos96M3cWymH5avBAPm3qx3NXqR8H",
         "testnet" => "FWJ9kR6KFWoyMoNjpLXXGHeuiy7tEY6GmoFeCA5yuc6b",
@@ -78,6 +81,8 @@ pub struct Network {
 
     request_timeout: tokio::time::Duration,
     rate_limiter: RateLimiter,
+    // If set, the received blocks, chunks and transactions are recorded.
+    pub recorder: Option<Recorder>,
 }
 
 impl Network {
@@ -85,6 +90,7 @@ impl Network {
         config: &NearConfig,
         network_adapter: Arc<dyn PeerManagerAdapter>,
         qps_limit: u32,
+        recorder: Option<Recorder>,
     ) -> Arc<Network> {
         Arc::new(Network {
             stats: Default::default(),
@@ -99,6 +105,7 @@ impl Network {
                     received_bytes_per_sec: 0,
                     known_producers: vec![],
                     peer_counter: 0,
+                    port_mapping: None,
                 }),
                 info_futures: Default::default(),
             }),
@@ -114,6 +121,7 @@ impl Network {
                 qps_limit as u64,
             ),
             request_timeout: time::Duration::from_secs(2),
+            recorder,
         })
     }
 
@@ -220,10 +228,13 @@ impl Network {
     }
 
     // fetch_chunk fetches a chunk for the given chunk header.
+    // When recording, receipts for all the <num_shards> shards are requested as well,
+    // so that the chunk can be later served to a node tracking any shard.
     pub async fn fetch_chunk(
         self: &Arc<Self>,
         ctx: &Ctx,
         ch: &ShardChunkHeader,
+        num_shards: ShardId,
     ) -> anyhow::Result<PartialEncodedChunkResponseMsg> {
         Scope::run(ctx, {
             let self_ = self.clone();
@@ -235,6 +246,10 @@ impl Network {
                 s.spawn_weak(|ctx| {
                     self_.keep_sending(&ctx, {
                         let ppc = self_.parts_per_chunk;
+                        let tracking_shards: HashSet<ShardId> = match self_.recorder {
+                            Some(_) => (0..num_shards).collect(),
+                            None => Default::default(),
+                        };
                         move |peer| NetworkRequests::PartialEncodedChunkRequest {
                             target: AccountIdOrPeerTrackingShard {
                                 account_id: peer.peer_info.account_id,
@@ -246,7 +261,7 @@ impl Network {
                             request: PartialEncodedChunkRequestMsg {
                                 chunk_hash: ch.chunk_hash(),
                                 part_ords: (0..ppc).collect(),
-                                tracking_shards: Default::default(),
+                                tracking_shards: tracking_shards.clone(),
                             },
                             create_time: WrappedInstant(Clock::instant()),
                         }
@@ -275,6 +290,9 @@ impl Network {
                 }
             }
             NetworkClientMessages::Block(block, _, _) => {
+                if let Some(recorder) = &self.recorder {
+                    recorder.record(RecordedMessage::Block(block.clone()));
+                }
                 self.blocks.get(&block.hash().clone()).map(|p| p.set(block));
             }
             NetworkClientMessages::BlockHeaders(headers, _) => {
@@ -284,8 +302,16 @@ impl Network {
                 }
             }
             NetworkClientMessages::PartialEncodedChunkResponse(resp, _) => {
+                if let Some(recorder) = &self.recorder {
+                    recorder.record(RecordedMessage::Chunk(resp.clone()));
+                }
                 self.chunks.get(&resp.chunk_hash.clone()).map(|p| p.set(resp));
             }
+            NetworkClientMessages::Transaction { transaction, .. } => {
+                if let Some(recorder) = &self.recorder {
+                    recorder.record(RecordedMessage::Transaction(transaction));
+                }
+            }
             _ => {}
         }
     }
@@ -335,6 +361,9 @@ impl Handler<NetworkViewClientMessages> for FakeClientActor {
             NetworkViewClientMessages::AnnounceAccount(_) => {
                 return NetworkViewClientResponses::NoResponse;
             }
+            NetworkViewClientMessages::GetServableState => {
+                return NetworkViewClientResponses::ServableState(vec![]);
+            }
             #[allow(unreachable_patterns)]
             _ => "unknown",
         };
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use borsh::{BorshDeserialize, BorshSerialize};
+use log::warn;
+use tokio::time;
+
+use near_network_primitives::types::PartialEncodedChunkResponseMsg;
+use near_primitives::block::Block;
+use near_primitives::transaction::SignedTransaction;
+
+// RecordedMessage is a network message received from a peer which is
+// kept in a recording, so that it can be replayed against a node under test.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum RecordedMessage {
+    Block(Block),
+    Chunk(PartialEncodedChunkResponseMsg),
+    Transaction(SignedTransaction),
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RecordedEntry {
+    // Time since the start of the recording.
+    pub offset_ms: u64,
+    pub message: RecordedMessage,
+}
+
+// Recorder appends received messages to a file. Every entry is stored as
+// a borsh-serialized RecordedEntry prefixed by its length (u32, little endian).
+pub struct Recorder {
+    start: time::Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> anyhow::Result<Recorder> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create recording {}", path.display()))?;
+        Ok(Recorder { start: time::Instant::now(), writer: Mutex::new(BufWriter::new(file)) })
+    }
+
+    pub fn record(&self, message: RecordedMessage) {
+        let entry = RecordedEntry { offset_ms: self.start.elapsed().as_millis() as u64, message };
+        let bytes = entry.try_to_vec().unwrap();
+        let mut writer = self.writer.lock().unwrap();
+        let res = writer
+            .write_all(&(bytes.len() as u32).to_le_bytes())
+            .and_then(|_| writer.write_all(&bytes));
+        if let Err(err) = res {
+            warn!("failed to record a message: {}", err);
+        }
+    }
+
+    pub fn flush(&self) -> anyhow::Result<()> {
+        Ok(self.writer.lock().unwrap().flush()?)
+    }
+}
+
+// read_recording() reads all the entries of a recording written by Recorder.
+pub fn read_recording(path: &Path) -> anyhow::Result<Vec<RecordedEntry>> {
+    let file =
+        File::open(path).with_context(|| format!("failed to open recording {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut entries = vec![];
+    loop {
+        let mut len = [0u8; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        reader.read_exact(&mut bytes).context("truncated recording")?;
+        entries.push(RecordedEntry::try_from_slice(&bytes).context("malformed recording")?);
+    }
+    Ok(entries)
+}
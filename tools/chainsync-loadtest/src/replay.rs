@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use actix::{Actor, Context, Handler};
+use anyhow::Context as _;
+use log::info;
+use tokio::time;
+
+use crate::concurrency::{Ctx, Scope};
+use crate::network::genesis_hash;
+use crate::recording::{RecordedEntry, RecordedMessage};
+
+use near_network::types::{
+    NetworkClientMessages, NetworkClientResponses, NetworkRequests, PeerManagerAdapter,
+    PeerManagerMessageRequest,
+};
+use near_network_primitives::types::{
+    NetworkViewClientMessages, NetworkViewClientResponses, PartialEncodedChunkRequestMsg,
+    PartialEncodedChunkResponseMsg,
+};
+use near_primitives::block::{Block, BlockHeader, GenesisId};
+use near_primitives::hash::CryptoHash;
+use near_primitives::sharding::ChunkHash;
+use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::{AccountId, BlockHeight};
+
+// Maximal number of headers returned in a single BlockHeaders response,
+// same as sync::MAX_BLOCK_HEADERS.
+const MAX_BLOCK_HEADERS: usize = 512;
+
+// Recording contains the messages of a recording, indexed so that they
+// can be served to a node under test.
+pub struct Recording {
+    // Blocks sorted by height.
+    blocks: Vec<Block>,
+    block_index: HashMap<CryptoHash, usize>,
+    chunks: HashMap<ChunkHash, PartialEncodedChunkResponseMsg>,
+    // Transactions with the time (since the start of the recording) they were received at.
+    transactions: Vec<(u64, SignedTransaction)>,
+}
+
+impl Recording {
+    pub fn new(entries: Vec<RecordedEntry>) -> anyhow::Result<Recording> {
+        let mut blocks: HashMap<CryptoHash, Block> = HashMap::new();
+        let mut chunks: HashMap<ChunkHash, PartialEncodedChunkResponseMsg> = HashMap::new();
+        let mut transactions = vec![];
+        for entry in entries {
+            match entry.message {
+                RecordedMessage::Block(block) => {
+                    blocks.insert(block.hash().clone(), block);
+                }
+                RecordedMessage::Chunk(resp) => {
+                    // A chunk might have been received multiple times, keep the most complete response.
+                    let keep = match chunks.get(&resp.chunk_hash) {
+                        Some(prev) => prev.parts.len() < resp.parts.len(),
+                        None => true,
+                    };
+                    if keep {
+                        chunks.insert(resp.chunk_hash.clone(), resp);
+                    }
+                }
+                RecordedMessage::Transaction(tx) => transactions.push((entry.offset_ms, tx)),
+            }
+        }
+        let mut blocks: Vec<Block> = blocks.into_values().collect();
+        blocks.sort_by_key(|b| b.header().height());
+        anyhow::ensure!(!blocks.is_empty(), "recording doesn't contain any blocks");
+        let block_index = blocks.iter().enumerate().map(|(i, b)| (b.hash().clone(), i)).collect();
+        return Ok(Recording { blocks, block_index, chunks, transactions });
+    }
+}
+
+// Replay serves a recorded chain to a node under test, pretending to be a peer
+// which receives the recorded blocks in real time.
+pub struct Replay {
+    recording: Recording,
+    genesis_id: GenesisId,
+    network_adapter: Arc<dyn PeerManagerAdapter>,
+    forward_tx_to: Option<AccountId>,
+    // Number of blocks released so far.
+    released: AtomicUsize,
+    // The highest height reported by the connected peers.
+    peer_height: AtomicU64,
+}
+
+impl Replay {
+    pub fn new(
+        recording: Recording,
+        chain_id: &str,
+        network_adapter: Arc<dyn PeerManagerAdapter>,
+        forward_tx_to: Option<AccountId>,
+    ) -> Arc<Replay> {
+        Arc::new(Replay {
+            recording,
+            genesis_id: GenesisId { chain_id: chain_id.to_string(), hash: genesis_hash(chain_id) },
+            network_adapter,
+            forward_tx_to,
+            released: AtomicUsize::new(0),
+            peer_height: AtomicU64::new(0),
+        })
+    }
+
+    fn released_blocks(&self) -> &[Block] {
+        return &self.recording.blocks[..self.released.load(Ordering::Relaxed)];
+    }
+
+    // height() is the height of the newest released block. Before any block is released
+    // it is the height of the block the node under test is expected to start from.
+    fn height(&self) -> BlockHeight {
+        return match self.released_blocks().last() {
+            Some(b) => b.header().height(),
+            None => self.recording.blocks[0].header().height() - 1,
+        };
+    }
+
+    fn block(&self, hash: &CryptoHash) -> Option<Block> {
+        let i = *self.recording.block_index.get(hash)?;
+        return self.released_blocks().get(i).cloned();
+    }
+
+    // headers() returns the released headers following the newest known block of the locator.
+    fn headers(&self, locator: &[CryptoHash]) -> Vec<BlockHeader> {
+        let blocks = self.released_blocks();
+        let first_prev_hash = self.recording.blocks[0].header().prev_hash();
+        let start = locator
+            .iter()
+            .filter_map(|h| match self.recording.block_index.get(h) {
+                Some(i) => Some(i + 1),
+                None if h == first_prev_hash => Some(0),
+                None => None,
+            })
+            .max();
+        return match start {
+            Some(start) if start < blocks.len() => blocks
+                [start..std::cmp::min(blocks.len(), start + MAX_BLOCK_HEADERS)]
+                .iter()
+                .map(|b| b.header().clone())
+                .collect(),
+            _ => vec![],
+        };
+    }
+
+    // chunk() returns the requested parts and receipts of the recorded chunk.
+    fn chunk(&self, req: &PartialEncodedChunkRequestMsg) -> Option<PartialEncodedChunkResponseMsg> {
+        let resp = self.recording.chunks.get(&req.chunk_hash)?;
+        return Some(PartialEncodedChunkResponseMsg {
+            chunk_hash: resp.chunk_hash.clone(),
+            parts: resp
+                .parts
+                .iter()
+                .filter(|p| req.part_ords.contains(&p.part_ord))
+                .cloned()
+                .collect(),
+            receipts: resp
+                .receipts
+                .iter()
+                .filter(|r| req.tracking_shards.contains(&r.1.to_shard_id))
+                .cloned()
+                .collect(),
+        });
+    }
+
+    // run() releases the recorded blocks (and forwards the recorded transactions)
+    // at the pace they were originally produced, multiplied by <speed>.
+    // Then it waits until the node under test catches up and reports the
+    // throughput at which it has applied the blocks.
+    pub async fn run(self: &Arc<Self>, ctx: &Ctx, speed: f64) -> anyhow::Result<()> {
+        anyhow::ensure!(speed > 0., "speed has to be positive");
+        let blocks = &self.recording.blocks;
+        let first_height = blocks[0].header().height();
+        let last_height = blocks.last().unwrap().header().height();
+        info!(
+            "REPLAY {} blocks ({}..={}), {} chunks, {} transactions",
+            blocks.len(),
+            first_height,
+            last_height,
+            self.recording.chunks.len(),
+            self.recording.transactions.len()
+        );
+        let start_time = time::Instant::now();
+        Scope::run(ctx, {
+            let self_ = self.clone();
+            move |ctx, s| async move {
+                s.spawn_weak({
+                    let self_ = self_.clone();
+                    |ctx| async move {
+                        let ctx = ctx.with_label("stats");
+                        let interval = time::Duration::from_secs(5);
+                        let mut prev_height = self_.peer_height.load(Ordering::Relaxed);
+                        loop {
+                            ctx.wait(interval).await?;
+                            let peer_height = self_.peer_height.load(Ordering::Relaxed);
+                            info!(
+                                "REPLAY released_height = {}, peer_height = {}, {:.2} blocks/s",
+                                self_.height(),
+                                peer_height,
+                                peer_height.saturating_sub(prev_height) as f64
+                                    / interval.as_secs_f64()
+                            );
+                            prev_height = peer_height;
+                        }
+                    }
+                });
+                if let Some(account_id) = self_.forward_tx_to.clone() {
+                    let self_ = self_.clone();
+                    s.spawn(|ctx, _s| async move {
+                        for (offset_ms, tx) in &self_.recording.transactions {
+                            let offset = time::Duration::from_millis(*offset_ms).div_f64(speed);
+                            ctx.wait_until(start_time + offset).await?;
+                            self_.network_adapter.do_send(
+                                PeerManagerMessageRequest::NetworkRequests(
+                                    NetworkRequests::ForwardTx(account_id.clone(), tx.clone()),
+                                ),
+                            );
+                        }
+                        anyhow::Ok(())
+                    });
+                }
+
+                let first_timestamp = self_.recording.blocks[0].header().raw_timestamp();
+                for (i, block) in self_.recording.blocks.iter().enumerate() {
+                    let offset = time::Duration::from_nanos(
+                        block.header().raw_timestamp().saturating_sub(first_timestamp),
+                    )
+                    .div_f64(speed);
+                    ctx.wait_until(start_time + offset).await?;
+                    self_.released.store(i + 1, Ordering::Relaxed);
+                    self_.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
+                        NetworkRequests::Block { block: block.clone() },
+                    ));
+                }
+                info!("REPLAY all blocks released, waiting for the peer to catch up");
+                while self_.peer_height.load(Ordering::Relaxed) < last_height {
+                    ctx.wait(time::Duration::from_millis(100)).await?;
+                }
+                anyhow::Ok(())
+            }
+        })
+        .await
+        .context("replay")?;
+        let took = start_time.elapsed();
+        info!(
+            "REPLAY done: {} blocks applied in {:.2}s, {:.2} blocks/s",
+            blocks.len(),
+            took.as_secs_f64(),
+            blocks.len() as f64 / took.as_secs_f64()
+        );
+        return Ok(());
+    }
+}
+
+// ReplayClientActor answers the requests of the node under test from the recording.
+pub struct ReplayClientActor {
+    replay: Arc<Replay>,
+}
+
+impl ReplayClientActor {
+    pub fn new(replay: Arc<Replay>) -> Self {
+        ReplayClientActor { replay }
+    }
+}
+
+impl Actor for ReplayClientActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<NetworkViewClientMessages> for ReplayClientActor {
+    type Result = NetworkViewClientResponses;
+    fn handle(&mut self, msg: NetworkViewClientMessages, _ctx: &mut Self::Context) -> Self::Result {
+        return match msg {
+            NetworkViewClientMessages::GetChainInfo => NetworkViewClientResponses::ChainInfo {
+                genesis_id: self.replay.genesis_id.clone(),
+                height: self.replay.height(),
+                tracked_shards: Default::default(),
+                archival: false,
+            },
+            NetworkViewClientMessages::BlockRequest(hash) => match self.replay.block(&hash) {
+                Some(block) => NetworkViewClientResponses::Block(Box::new(block)),
+                None => NetworkViewClientResponses::NoResponse,
+            },
+            NetworkViewClientMessages::BlockHeadersRequest(hashes) => {
+                NetworkViewClientResponses::BlockHeaders(self.replay.headers(&hashes))
+            }
+            _ => NetworkViewClientResponses::NoResponse,
+        };
+    }
+}
+
+impl Handler<NetworkClientMessages> for ReplayClientActor {
+    type Result = NetworkClientResponses;
+    fn handle(&mut self, msg: NetworkClientMessages, _ctx: &mut Context<Self>) -> Self::Result {
+        match msg {
+            NetworkClientMessages::NetworkInfo(info) => {
+                if let Some(height) = info.connected_peers.iter().map(|p| p.chain_info.height).max()
+                {
+                    self.replay.peer_height.store(height, Ordering::Relaxed);
+                }
+            }
+            NetworkClientMessages::PartialEncodedChunkRequest(req, route_back) => {
+                if let Some(response) = self.replay.chunk(&req) {
+                    self.replay.network_adapter.do_send(
+                        PeerManagerMessageRequest::NetworkRequests(
+                            NetworkRequests::PartialEncodedChunkResponse { route_back, response },
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+        return NetworkClientResponses::NoResponse;
+    }
+}
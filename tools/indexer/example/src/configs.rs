@@ -55,6 +55,18 @@ pub(crate) struct InitConfigArgs {
     /// Specify a custom download URL for the config file.
     #[clap(long)]
     pub download_config_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis file.
+    #[clap(long)]
+    pub download_genesis_sha256: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded config file.
+    #[clap(long)]
+    pub download_config_sha256: Option<String>,
+    /// Specify a download URL for the genesis records file.
+    #[clap(long)]
+    pub download_records_url: Option<String>,
+    /// Expected SHA-256 checksum (hex) of the downloaded genesis records file.
+    #[clap(long)]
+    pub download_records_sha256: Option<String>,
     /// Specify the boot nodes to bootstrap the network
     pub boot_nodes: Option<String>,
     /// Specify a custom max_gas_burnt_view limit.
@@ -75,6 +87,10 @@ impl From<InitConfigArgs> for near_indexer::InitConfigArgs {
             download_genesis_url: config_args.download_genesis_url,
             download_config: config_args.download_config,
             download_config_url: config_args.download_config_url,
+            download_genesis_sha256: config_args.download_genesis_sha256,
+            download_config_sha256: config_args.download_config_sha256,
+            download_records_url: config_args.download_records_url,
+            download_records_sha256: config_args.download_records_sha256,
             boot_nodes: config_args.boot_nodes,
             max_gas_burnt_view: config_args.max_gas_burnt_view,
         }
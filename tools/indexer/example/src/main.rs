@@ -276,6 +276,7 @@ fn main() -> Result<()> {
                 home_dir,
                 sync_mode: near_indexer::SyncModeEnum::FromInterruption,
                 await_for_node_synced: near_indexer::AwaitForNodeSyncedEnum::WaitForFullSync,
+                contract_data_filter: None,
             };
             let system = actix::System::new();
             system.block_on(async move {
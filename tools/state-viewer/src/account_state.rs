@@ -0,0 +1,146 @@
+use anyhow::Context;
+use near_chain_configs::Genesis;
+use near_primitives::account::id::AccountId;
+use near_primitives::state_record::{state_record_to_account_id, StateRecord};
+use near_primitives::trie_key::{trie_key_parsers, TrieKey};
+use near_primitives::types::StateRoot;
+use near_store::{StorageError, Trie};
+
+/// Chains whose genesis must never be modified by `apply_account_state`.
+const PROTECTED_CHAINS: &[&str] = &["mainnet", "testnet", "betanet", "shardnet"];
+
+/// Collects all the records of the account: the account itself, its contract code, access keys
+/// and contract data.  The result can be put into genesis records or passed to the
+/// `sandbox_patch_state` RPC method.
+pub(crate) fn account_records(
+    trie: &Trie,
+    state_root: &StateRoot,
+    account_id: &AccountId,
+) -> Result<Vec<StateRecord>, StorageError> {
+    let mut records = vec![];
+    for key in [
+        TrieKey::Account { account_id: account_id.clone() },
+        TrieKey::ContractCode { account_id: account_id.clone() },
+    ] {
+        let key = key.to_vec();
+        if let Some(value) = trie.get(state_root, &key)? {
+            records.extend(StateRecord::from_raw_key_value(key, value));
+        }
+    }
+    for prefix in [
+        trie_key_parsers::get_raw_prefix_for_access_keys(account_id),
+        trie_key_parsers::get_raw_prefix_for_contract_data(account_id, &[]),
+    ] {
+        let mut iter = trie.iter(state_root)?;
+        iter.seek(&prefix)?;
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            records.extend(StateRecord::from_raw_key_value(key, value));
+        }
+    }
+    Ok(records)
+}
+
+/// Replaces all the records of the account in the genesis with the given ones and adjusts the
+/// total supply.  The imported account can't stake, so its locked balance becomes liquid.
+pub(crate) fn replace_account_records(
+    genesis: &mut Genesis,
+    account_id: &AccountId,
+    records: Vec<StateRecord>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !PROTECTED_CHAINS.contains(&genesis.config.chain_id.as_str()),
+        "refusing to modify genesis of {}, only localnet and sandbox chains are supported",
+        genesis.config.chain_id
+    );
+    anyhow::ensure!(
+        genesis.config.validators.iter().all(|validator| &validator.account_id != account_id),
+        "{} is a genesis validator and can't be replaced",
+        account_id
+    );
+    anyhow::ensure!(
+        records.iter().all(|record| state_record_to_account_id(record) == account_id),
+        "records don't belong to {}",
+        account_id
+    );
+    anyhow::ensure!(
+        records.iter().any(|record| matches!(record, StateRecord::Account { .. })),
+        "records of {} don't contain the account",
+        account_id
+    );
+
+    let mut total_supply = genesis.config.total_supply;
+    genesis.records.0.retain(|record| match record {
+        StateRecord::Account { account_id: id, account } if id == account_id => {
+            total_supply -= account.amount() + account.locked();
+            false
+        }
+        StateRecord::AccessKey { account_id: id, .. }
+        | StateRecord::Contract { account_id: id, .. }
+        | StateRecord::Data { account_id: id, .. } => id != account_id,
+        _ => true,
+    });
+    for mut record in records {
+        if let StateRecord::Account { account, .. } = &mut record {
+            account.set_amount(account.amount() + account.locked());
+            account.set_locked(0);
+            total_supply =
+                total_supply.checked_add(account.amount()).context("total supply overflow")?;
+        }
+        genesis.records.0.push(record);
+    }
+    genesis.config.total_supply = total_supply;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use near_chain_configs::Genesis;
+    use near_primitives::account::Account;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::state_record::StateRecord;
+    use nearcore::config::GenesisExt;
+
+    use crate::account_state::replace_account_records;
+
+    #[test]
+    fn test_replace_account_records() {
+        let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+        let total_supply = genesis.config.total_supply;
+        let account_id = "contract.near".parse().unwrap();
+        let records = vec![
+            StateRecord::Account {
+                account_id: "contract.near".parse().unwrap(),
+                account: Account::new(100, 50, CryptoHash::default(), 0),
+            },
+            StateRecord::Data {
+                account_id: "contract.near".parse().unwrap(),
+                data_key: b"key".to_vec(),
+                value: b"value".to_vec(),
+            },
+        ];
+        replace_account_records(&mut genesis, &account_id, records.clone()).unwrap();
+        // Replacing again must not duplicate the records.
+        replace_account_records(&mut genesis, &account_id, records).unwrap();
+
+        assert_eq!(genesis.config.total_supply, total_supply + 150);
+        let imported: Vec<_> = genesis
+            .records
+            .0
+            .iter()
+            .filter(|record| match record {
+                StateRecord::Account { account_id: id, .. }
+                | StateRecord::Data { account_id: id, .. } => id == &account_id,
+                _ => false,
+            })
+            .collect();
+        assert_eq!(imported.len(), 2);
+        near_chain_configs::genesis_validate::validate_genesis(&genesis);
+
+        let validator = "test0".parse().unwrap();
+        assert!(replace_account_records(&mut genesis, &validator, vec![]).is_err());
+    }
+}
@@ -22,11 +22,6 @@ use near_primitives::types::{BlockHeight, ShardId};
 use near_store::{get, DBCol, Store};
 use nearcore::NightshadeRuntime;
 
-fn timestamp() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
-}
-
 fn timestamp_ms() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
@@ -46,6 +41,18 @@ struct ProgressReporter {
 }
 
 impl ProgressReporter {
+    fn new(all: u64) -> Self {
+        ProgressReporter {
+            cnt: AtomicU64::new(0),
+            ts: AtomicU64::new(timestamp_ms()),
+            all,
+            skipped: AtomicU64::new(0),
+            empty_blocks: AtomicU64::new(0),
+            non_empty_blocks: AtomicU64::new(0),
+            tgas_burned: AtomicU64::new(0),
+        }
+    }
+
     pub fn inc_and_report_progress(&self, gas_burnt: u64) {
         let ProgressReporter { cnt, ts, all, skipped, empty_blocks, non_empty_blocks, tgas_burned } =
             self;
@@ -63,7 +70,7 @@ impl ProgressReporter {
             let new_ts = timestamp_ms();
             let per_second = (PRINT_PER as f64 / (new_ts - prev_ts) as f64) as f64 * 1000.0;
             ts.store(new_ts, Ordering::Relaxed);
-            let secs_remaining = (all - prev) as f64 / per_second;
+            let secs_remaining = all.saturating_sub(prev) as f64 / per_second;
             let avg_gas = if non_empty_blocks.load(Ordering::Relaxed) == 0 {
                 0.0
             } else {
@@ -114,6 +121,8 @@ fn maybe_add_to_csv(csv_file_mutex: &Mutex<Option<&mut File>>, s: &str) {
     }
 }
 
+/// Applies the chunk of the given shard in the block at the given height and compares the result
+/// with the stored `ChunkExtra`.  Returns a description of the difference if they don't match.
 fn apply_block_from_range(
     height: BlockHeight,
     shard_id: ShardId,
@@ -124,14 +133,14 @@ fn apply_block_from_range(
     verbose_output: bool,
     csv_file_mutex: &Arc<Mutex<Option<&mut File>>>,
     only_contracts: bool,
-) {
+) -> Result<(), String> {
     let mut chain_store = ChainStore::new(store.clone(), genesis.config.genesis_height);
     let block_hash = match chain_store.get_block_hash_by_height(height) {
         Ok(block_hash) => block_hash,
         Err(_) => {
             // Skipping block because it's not available in ChainStore.
             progress_reporter.inc_and_report_progress(0);
-            return;
+            return Ok(());
         }
     };
     let block = chain_store.get_block(&block_hash).unwrap().clone();
@@ -152,7 +161,7 @@ fn apply_block_from_range(
             println!("Skipping the genesis block #{}.", height);
         }
         progress_reporter.inc_and_report_progress(0);
-        return;
+        return Ok(());
     } else if block.chunks()[shard_id as usize].height_included() == height {
         chunk_present = true;
         let res_existing_chunk_extra = chain_store.get_chunk_extra(&block_hash, &shard_uid);
@@ -183,7 +192,7 @@ fn apply_block_from_range(
                     ),
                 );
                 progress_reporter.inc_and_report_progress(0);
-                return;
+                return Ok(());
             }
         };
 
@@ -221,7 +230,7 @@ fn apply_block_from_range(
             }
             if !has_contracts {
                 progress_reporter.skipped.fetch_add(1, Ordering::Relaxed);
-                return;
+                return Ok(());
             }
         }
         runtime_adapter
@@ -293,7 +302,8 @@ fn apply_block_from_range(
                 println!("block_height: {}, block_hash: {}\nchunk_extra: {:#?}\nexisting_chunk_extra: {:#?}\noutcomes: {:#?}", height, block_hash, chunk_extra, existing_chunk_extra, apply_result.outcomes);
             }
             if !smart_equals(&existing_chunk_extra, &chunk_extra) {
-                assert!(false, "Got a different ChunkExtra:\nblock_height: {}, block_hash: {}\nchunk_extra: {:#?}\nexisting_chunk_extra: {:#?}\nnew outcomes: {:#?}\n\nold outcomes: {:#?}\n", height, block_hash, chunk_extra, existing_chunk_extra, apply_result.outcomes, old_outcomes(store, &apply_result.outcomes));
+                progress_reporter.inc_and_report_progress(apply_result.total_gas_burnt);
+                return Err(format!("Got a different ChunkExtra:\nblock_height: {}, block_hash: {}\nchunk_extra: {:#?}\nexisting_chunk_extra: {:#?}\nnew outcomes: {:#?}\n\nold outcomes: {:#?}\n", height, block_hash, chunk_extra, existing_chunk_extra, apply_result.outcomes, old_outcomes(store, &apply_result.outcomes)));
             }
         }
         None => {
@@ -321,6 +331,7 @@ fn apply_block_from_range(
         ),
     );
     progress_reporter.inc_and_report_progress(apply_result.total_gas_burnt);
+    Ok(())
 }
 
 pub fn apply_chain_range(
@@ -350,17 +361,9 @@ pub fn apply_chain_range(
     maybe_add_to_csv(&csv_file_mutex, "Height,Hash,Author,#Tx,#Receipt,Timestamp,GasUsed,ChunkPresent,#ProcessedDelayedReceipts,#DelayedReceipts");
 
     let range = start_height..=end_height;
-    let progress_reporter = ProgressReporter {
-        cnt: AtomicU64::new(0),
-        ts: AtomicU64::new(timestamp()),
-        all: end_height - start_height,
-        skipped: AtomicU64::new(0),
-        empty_blocks: AtomicU64::new(0),
-        non_empty_blocks: AtomicU64::new(0),
-        tgas_burned: AtomicU64::new(0),
-    };
+    let progress_reporter = ProgressReporter::new(end_height - start_height + 1);
     let process_height = |height| {
-        apply_block_from_range(
+        if let Err(err) = apply_block_from_range(
             height,
             shard_id,
             store.clone(),
@@ -370,7 +373,9 @@ pub fn apply_chain_range(
             verbose_output,
             &csv_file_mutex,
             only_contracts,
-        );
+        ) {
+            panic!("{}", err);
+        }
     };
 
     if sequential {
@@ -385,6 +390,96 @@ pub fn apply_chain_range(
     );
 }
 
+/// Re-applies the blocks at heights `start_height..=end_height` against the stored state and
+/// checks that the resulting `ChunkExtra`s, including the outcome and state roots, match the
+/// stored ones.  All shards, or only `shard_ids` if given, are applied in parallel unless
+/// `sequential` is set.  Returns descriptions of all found mismatches.
+pub fn replay_blocks(
+    store: Store,
+    genesis: &Genesis,
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+    shard_ids: Option<Vec<ShardId>>,
+    runtime: NightshadeRuntime,
+    sequential: bool,
+) -> Vec<String> {
+    let runtime_adapter: Arc<dyn RuntimeAdapter> = Arc::new(runtime);
+    let shards_at_height = |height| -> Option<Vec<ShardId>> {
+        let mut chain_store = ChainStore::new(store.clone(), genesis.config.genesis_height);
+        let block_hash = chain_store.get_block_hash_by_height(height).ok()?;
+        let epoch_id = chain_store.get_block_header(&block_hash).ok()?.epoch_id().clone();
+        let num_shards = runtime_adapter.num_shards(&epoch_id).ok()?;
+        Some(match &shard_ids {
+            Some(shard_ids) => {
+                shard_ids.iter().copied().filter(|shard_id| *shard_id < num_shards).collect()
+            }
+            None => (0..num_shards).collect(),
+        })
+    };
+    // The shard layout may change within the range, so the estimate of the remaining time
+    // assumes the shards of the last available block.
+    let num_shards = (start_height..=end_height)
+        .rev()
+        .find_map(|height| shards_at_height(height))
+        .map_or(1, |shards| shards.len() as u64);
+
+    println!(
+        "Replaying blocks in the range {}..={} for {} shards",
+        start_height, end_height, num_shards
+    );
+
+    let csv_file_mutex = Arc::new(Mutex::new(None));
+    let progress_reporter = ProgressReporter::new((end_height - start_height + 1) * num_shards);
+    let mismatches = Mutex::new(vec![]);
+    let process_shard = |height, shard_id| {
+        if let Err(err) = apply_block_from_range(
+            height,
+            shard_id,
+            store.clone(),
+            genesis,
+            runtime_adapter.clone(),
+            &progress_reporter,
+            false,
+            &csv_file_mutex,
+            false,
+        ) {
+            println!("{}", err);
+            mismatches.lock().unwrap().push(err);
+        }
+    };
+    let process_height = |height| {
+        let shards = match shards_at_height(height) {
+            Some(shards) => shards,
+            None => {
+                // Skipping height because there's no block at it in ChainStore.
+                progress_reporter.skipped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+        if sequential {
+            shards.into_iter().for_each(|shard_id| process_shard(height, shard_id));
+        } else {
+            shards.into_par_iter().for_each(|shard_id| process_shard(height, shard_id));
+        }
+    };
+
+    let range = start_height..=end_height;
+    if sequential {
+        range.into_iter().for_each(process_height);
+    } else {
+        range.into_par_iter().for_each(process_height);
+    }
+
+    let mismatches = mismatches.into_inner().unwrap();
+    println!(
+        "Found {} mismatches after replaying blocks in the range {}..={}",
+        mismatches.len(),
+        start_height,
+        end_height
+    );
+    mismatches
+}
+
 /**
  * With the database migration we can get into the situation where there are different
  * ChunkExtra versions in database and produced by `neard` playback. Consider them equal as
@@ -433,7 +528,7 @@ mod test {
     use nearcore::config::TESTING_INIT_STAKE;
     use nearcore::NightshadeRuntime;
 
-    use crate::apply_chain_range::apply_chain_range;
+    use crate::apply_chain_range::{apply_chain_range, replay_blocks};
 
     fn setup(epoch_length: NumBlocks) -> (Store, Genesis, TestEnv) {
         let mut genesis =
@@ -559,4 +654,27 @@ mod test {
         assert_eq!(has_tx, 1, "{:#?}", lines);
         assert_eq!(no_tx, 8, "{:#?}", lines);
     }
+    #[test]
+    fn test_replay_blocks() {
+        let epoch_length = 4;
+        let (store, genesis, mut env) = setup(epoch_length);
+        let genesis_hash = *env.clients[0].chain.genesis().hash();
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::stake(
+            1,
+            "test1".parse().unwrap(),
+            &signer,
+            TESTING_INIT_STAKE,
+            signer.public_key.clone(),
+            genesis_hash,
+        );
+        env.clients[0].process_tx(tx, false, false);
+
+        safe_produce_blocks(&mut env, 1, epoch_length * 2 + 1, Some(5));
+
+        let runtime = NightshadeRuntime::test(Path::new("."), store.clone(), &genesis);
+        let head_height = env.clients[0].chain.head().unwrap().height;
+        let mismatches = replay_blocks(store, &genesis, 1, head_height, None, runtime, false);
+        assert!(mismatches.is_empty(), "{:#?}", mismatches);
+    }
 }
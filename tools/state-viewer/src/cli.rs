@@ -1,5 +1,6 @@
 use crate::commands::*;
 use crate::epoch_info;
+use crate::export_parquet::export_parquet;
 use crate::rocksdb_stats::get_rocksdb_stats;
 use clap::{Args, Parser, Subcommand};
 use near_chain_configs::GenesisValidationMode;
@@ -7,9 +8,9 @@ use near_logger_utils::init_integration_logger;
 use near_primitives::account::id::AccountId;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::ChunkHash;
-use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::types::{Balance, BlockHeight, ShardId, StorageUsage};
 use near_primitives::version::{DB_VERSION, PROTOCOL_VERSION};
-use near_store::{create_store_with_config, Store, StoreConfig};
+use near_store::{create_store_with_config, AccountFilter, Store, StoreConfig};
 use nearcore::{get_default_home, get_store_path, load_config, NearConfig};
 use once_cell::sync::Lazy;
 use std::path::{Path, PathBuf};
@@ -91,6 +92,18 @@ pub enum StateViewerSubCommand {
     /// Dump deployed contract code of given account to wasm file.
     #[clap(name = "dump_code")]
     DumpCode(DumpCodeCmd),
+    /// Print accounts matching the filters as JSON lines.
+    #[clap(name = "accounts")]
+    Accounts(AccountsCmd),
+    /// Dump all records of the given account (account, contract code, access keys and contract
+    /// data) to a JSON file.
+    #[clap(name = "dump_account_state")]
+    DumpAccountState(DumpAccountStateCmd),
+    /// Replace the records of an account in the genesis of a localnet or sandbox chain with the
+    /// ones dumped by `dump_account_state`.  Must be run before the node is started for the first
+    /// time.
+    #[clap(name = "apply_account_state")]
+    ApplyAccountState(ApplyAccountStateCmd),
     /// Dump contract data in storage of given account to binary file.
     #[clap(name = "dump_account_storage")]
     DumpAccountStorage(DumpAccountStorageCmd),
@@ -109,16 +122,30 @@ pub enum StateViewerSubCommand {
     /// Apply a chunk, even if it's not included in any block on disk
     #[clap(name = "apply_chunk")]
     ApplyChunk(ApplyChunkCmd),
+    /// Check how the state of every shard would be split into the shards of a new shard layout,
+    /// without writing anything.
+    #[clap(name = "check_resharding")]
+    CheckResharding(CheckReshardingCmd),
+    /// Export blocks, transactions, receipts and execution outcomes of a range of heights to
+    /// Parquet files partitioned by height.
+    #[clap(name = "export_parquet")]
+    ExportParquet(ExportParquetCmd),
 }
 
 impl StateViewerSubCommand {
     pub fn run(self, home_dir: &Path, genesis_validation: GenesisValidationMode) {
+        // Modifies the genesis of a chain which hasn't been started yet, so there is neither a
+        // valid config nor a storage to load.
+        let cmd = match self {
+            StateViewerSubCommand::ApplyAccountState(cmd) => return cmd.run(home_dir),
+            cmd => cmd,
+        };
         let near_config = load_config(home_dir, genesis_validation);
         let store = create_store_with_config(
             &get_store_path(home_dir),
-            StoreConfig { read_only: true, enable_statistics: false },
+            StoreConfig { read_only: true, ..Default::default() },
         );
-        match self {
+        match cmd {
             StateViewerSubCommand::Peers => peers(store),
             StateViewerSubCommand::State => state(home_dir, near_config, store),
             StateViewerSubCommand::DumpState(cmd) => cmd.run(home_dir, near_config, store),
@@ -130,6 +157,9 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::ViewChain(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::CheckBlock => check_block_chunk_existence(store, near_config),
             StateViewerSubCommand::DumpCode(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::Accounts(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::DumpAccountState(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::ApplyAccountState(_) => unreachable!(),
             StateViewerSubCommand::DumpAccountStorage(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::EpochInfo(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::RocksDBStats(cmd) => cmd.run(home_dir),
@@ -137,6 +167,8 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::Chunks(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::PartialChunks(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::ApplyChunk(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::CheckResharding(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::ExportParquet(cmd) => cmd.run(near_config, store),
         }
     }
 }
@@ -206,6 +238,54 @@ impl ReplayCmd {
     }
 }
 
+/// Re-applies stored blocks against the stored state and verifies that the outcome and state roots
+/// match the stored ones.
+#[derive(Args)]
+pub struct ReplayBlocksCmd {
+    /// Height of the first block to replay.
+    #[clap(long = "from")]
+    from_height: BlockHeight,
+    /// Height of the last block to replay.
+    #[clap(long = "to")]
+    to_height: BlockHeight,
+    /// Shards to replay.  All shards are replayed if not given.
+    #[clap(long, multiple_occurrences = true)]
+    shard_id: Vec<ShardId>,
+    /// Applies blocks and shards one at a time instead of in parallel.
+    #[clap(long)]
+    sequential: bool,
+}
+
+impl ReplayBlocksCmd {
+    pub fn run(
+        self,
+        home_dir: &Path,
+        genesis_validation: GenesisValidationMode,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.from_height <= self.to_height,
+            "--from {} is greater than --to {}",
+            self.from_height,
+            self.to_height
+        );
+        let near_config = load_config(home_dir, genesis_validation);
+        let store = create_store_with_config(
+            &get_store_path(home_dir),
+            StoreConfig { read_only: true, ..Default::default() },
+        );
+        let shard_ids = if self.shard_id.is_empty() { None } else { Some(self.shard_id) };
+        replay_blocks(
+            self.from_height,
+            self.to_height,
+            shard_ids,
+            home_dir,
+            near_config,
+            store,
+            self.sequential,
+        )
+    }
+}
+
 #[derive(Parser)]
 pub struct ApplyRangeCmd {
     #[clap(long)]
@@ -222,6 +302,10 @@ pub struct ApplyRangeCmd {
     only_contracts: bool,
     #[clap(long)]
     sequential: bool,
+    /// Records the trie accesses to this file, for the `trie_trace` benchmarks of near-store.
+    /// Use with `--sequential` to keep the accesses in order.
+    #[clap(long, parse(from_os_str))]
+    record_trie_trace: Option<PathBuf>,
 }
 
 impl ApplyRangeCmd {
@@ -237,6 +321,7 @@ impl ApplyRangeCmd {
             store,
             self.only_contracts,
             self.sequential,
+            self.record_trie_trace,
         );
     }
 }
@@ -285,6 +370,82 @@ impl DumpCodeCmd {
     }
 }
 
+#[derive(Parser)]
+pub struct AccountsCmd {
+    /// Height of the block whose state is read.  The latest state is read if not given.
+    #[clap(long)]
+    height: Option<BlockHeight>,
+    /// Shard to read.  All shards are read if not given.
+    #[clap(long)]
+    shard_id: Option<ShardId>,
+    /// Print only accounts with (`true`) or without (`false`) a deployed contract.
+    #[clap(long)]
+    has_contract: Option<bool>,
+    /// Minimal liquid balance in yoctoNEAR.
+    #[clap(long)]
+    min_balance: Option<Balance>,
+    /// Maximal liquid balance in yoctoNEAR.
+    #[clap(long)]
+    max_balance: Option<Balance>,
+    #[clap(long)]
+    min_storage_usage: Option<StorageUsage>,
+    #[clap(long)]
+    max_storage_usage: Option<StorageUsage>,
+}
+
+impl AccountsCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        let filter = AccountFilter {
+            has_contract: self.has_contract,
+            min_balance: self.min_balance,
+            max_balance: self.max_balance,
+            min_storage_usage: self.min_storage_usage,
+            max_storage_usage: self.max_storage_usage,
+        };
+        print_accounts(self.height, self.shard_id, filter, home_dir, near_config, store);
+    }
+}
+
+#[derive(Parser)]
+pub struct DumpAccountStateCmd {
+    #[clap(long)]
+    account_id: AccountId,
+    /// Height of the block whose state is read.  The latest state is read if not given.
+    #[clap(long)]
+    height: Option<BlockHeight>,
+    #[clap(long, parse(from_os_str))]
+    output: PathBuf,
+}
+
+impl DumpAccountStateCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        dump_account_state(
+            self.account_id,
+            self.height,
+            &self.output,
+            home_dir,
+            near_config,
+            store,
+        );
+    }
+}
+
+#[derive(Parser)]
+pub struct ApplyAccountStateCmd {
+    /// File written by `dump_account_state`.
+    #[clap(long, parse(from_os_str))]
+    input: PathBuf,
+}
+
+impl ApplyAccountStateCmd {
+    pub fn run(self, home_dir: &Path) {
+        if let Err(err) = apply_account_state(&self.input, home_dir) {
+            eprintln!("Failed to apply account state: {:#}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
 #[derive(Parser)]
 pub struct DumpAccountStorageCmd {
     #[clap(long)]
@@ -406,3 +567,50 @@ impl ApplyChunkCmd {
         apply_chunk(home_dir, near_config, store, hash, self.target_height, txs, receipts).unwrap()
     }
 }
+
+#[derive(Parser)]
+pub struct CheckReshardingCmd {
+    /// Height of the block whose state is split.  The latest state is split if not given.
+    #[clap(long)]
+    height: Option<BlockHeight>,
+    /// JSON file with the shard layout to split the state into.  The simple nightshade shard
+    /// layout from the genesis config is used if not given.
+    #[clap(long, parse(from_os_str))]
+    shard_layout_file: Option<PathBuf>,
+}
+
+impl CheckReshardingCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        check_resharding(self.height, self.shard_layout_file, home_dir, near_config, store);
+    }
+}
+
+#[derive(Parser)]
+pub struct ExportParquetCmd {
+    #[clap(long)]
+    start_height: BlockHeight,
+    #[clap(long)]
+    end_height: BlockHeight,
+    /// Directory to write the tables to.
+    #[clap(long, parse(from_os_str))]
+    output: PathBuf,
+    /// Number of heights per partition of every table.
+    #[clap(long, default_value = "10000")]
+    partition_size: BlockHeight,
+}
+
+impl ExportParquetCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        if let Err(err) = export_parquet(
+            self.start_height,
+            self.end_height,
+            self.partition_size,
+            &self.output,
+            near_config,
+            store,
+        ) {
+            eprintln!("Failed to export to Parquet: {:#}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,27 +1,34 @@
-use crate::apply_chain_range::apply_chain_range;
+use crate::apply_chain_range::{self, apply_chain_range};
 use crate::state_dump::state_dump;
 use crate::state_dump::state_dump_redis;
-use crate::{apply_chunk, epoch_info};
+use crate::{account_state, apply_chunk, epoch_info};
 use ansi_term::Color::Red;
+use anyhow::Context;
 use near_chain::chain::collect_receipts_from_response;
 use near_chain::migrations::check_if_block_is_first_with_chunk_of_version;
 use near_chain::types::{ApplyTransactionResult, BlockHeaderInfo};
 use near_chain::{ChainStore, ChainStoreAccess, ChainStoreUpdate, RuntimeAdapter};
+use near_chain_configs::genesis_validate::validate_genesis;
+use near_chain_configs::{Genesis, GenesisValidationMode};
 use near_epoch_manager::EpochManager;
 use near_network::iter_peers_from_store;
 use near_primitives::account::id::AccountId;
 use near_primitives::block::BlockHeader;
 use near_primitives::hash::CryptoHash;
 use near_primitives::serialize::to_base;
-use near_primitives::shard_layout::ShardUId;
+use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::sharding::ChunkHash;
 use near_primitives::state_record::StateRecord;
+use near_primitives::syncing::get_num_state_parts;
+use near_primitives::trie_key::trie_key_parsers::parse_account_id_from_raw_key;
 use near_primitives::trie_key::TrieKey;
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, ShardId, StateRoot};
+use near_primitives::views::AccountView;
 use near_primitives_core::types::Gas;
 use near_store::test_utils::create_test_store;
-use near_store::{Store, TrieIterator};
+use near_store::{access_trace, AccountFilter, AccountIterator, Store, TrieIterator};
+use nearcore::config::{Config, CONFIG_FILENAME};
 use nearcore::{NearConfig, NightshadeRuntime};
 use node_runtime::adapter::ViewRuntimeAdapter;
 use std::collections::{HashMap, HashSet};
@@ -111,8 +118,12 @@ pub(crate) fn apply_range(
     store: Store,
     only_contracts: bool,
     sequential: bool,
+    record_trie_trace: Option<PathBuf>,
 ) {
     let mut csv_file = csv_file.map(|filename| std::fs::File::create(filename).unwrap());
+    if let Some(path) = &record_trie_trace {
+        access_trace::start_recording(path).unwrap();
+    }
 
     let runtime = NightshadeRuntime::with_config(
         home_dir,
@@ -133,6 +144,42 @@ pub(crate) fn apply_range(
         only_contracts,
         sequential,
     );
+    if record_trie_trace.is_some() {
+        access_trace::stop_recording().unwrap();
+    }
+}
+
+pub(crate) fn replay_blocks(
+    from_height: BlockHeight,
+    to_height: BlockHeight,
+    shard_ids: Option<Vec<ShardId>>,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+    sequential: bool,
+) -> anyhow::Result<()> {
+    let runtime = NightshadeRuntime::with_config(
+        home_dir,
+        store.clone(),
+        &near_config,
+        None,
+        near_config.client_config.max_gas_burnt_view,
+    );
+    let mismatches = apply_chain_range::replay_blocks(
+        store,
+        &near_config.genesis,
+        from_height,
+        to_height,
+        shard_ids,
+        runtime,
+        sequential,
+    );
+    anyhow::ensure!(
+        mismatches.is_empty(),
+        "{} chunks produced results different from the stored ones",
+        mismatches.len()
+    );
+    Ok(())
 }
 
 pub(crate) fn dump_code(
@@ -163,6 +210,98 @@ pub(crate) fn dump_code(
     );
 }
 
+pub(crate) fn print_accounts(
+    height: Option<BlockHeight>,
+    shard_id: Option<ShardId>,
+    filter: AccountFilter,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let mode = match height {
+        Some(height) => LoadTrieMode::Height(height),
+        None => LoadTrieMode::Latest,
+    };
+    let (runtime, state_roots, header) =
+        load_trie_stop_at_height(store, home_dir, &near_config, mode);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for (current_shard_id, state_root) in state_roots.iter().enumerate() {
+        let current_shard_id = current_shard_id as ShardId;
+        if shard_id.map_or(false, |shard_id| shard_id != current_shard_id) {
+            continue;
+        }
+        let trie = runtime.get_trie_for_shard(current_shard_id, header.prev_hash()).unwrap();
+        for item in AccountIterator::new(&trie, state_root, filter.clone()).unwrap() {
+            let (account_id, account) = item.unwrap();
+            let mut record = serde_json::to_value(AccountView::from(account)).unwrap();
+            record["account_id"] = serde_json::json!(account_id);
+            record["shard_id"] = serde_json::json!(current_shard_id);
+            writeln!(out, "{}", record).unwrap();
+        }
+    }
+}
+
+pub(crate) fn dump_account_state(
+    account_id: AccountId,
+    height: Option<BlockHeight>,
+    output: &Path,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let mode = match height {
+        Some(height) => LoadTrieMode::Height(height),
+        None => LoadTrieMode::Latest,
+    };
+    let (runtime, state_roots, header) =
+        load_trie_stop_at_height(store, home_dir, &near_config, mode);
+    let shard_id = runtime.account_id_to_shard_id(&account_id, header.epoch_id()).unwrap();
+    let trie = runtime.get_trie_for_shard(shard_id, header.prev_hash()).unwrap();
+    let records =
+        account_state::account_records(&trie, &state_roots[shard_id as usize], &account_id)
+            .unwrap();
+    if records.is_empty() {
+        println!("Account {} not found", account_id);
+        std::process::exit(1);
+    }
+    serde_json::to_writer_pretty(File::create(output).unwrap(), &records).unwrap();
+    println!(
+        "Dumped {} records of account {} at height {} into file {}",
+        records.len(),
+        account_id,
+        header.height(),
+        output.display()
+    );
+}
+
+pub(crate) fn apply_account_state(input: &Path, home_dir: &Path) -> anyhow::Result<()> {
+    let records: Vec<StateRecord> = serde_json::from_reader(std::io::BufReader::new(
+        File::open(input).with_context(|| format!("failed to open {}", input.display()))?,
+    ))
+    .with_context(|| format!("failed to parse {}", input.display()))?;
+    let account_id = records
+        .iter()
+        .find_map(|record| match record {
+            StateRecord::Account { account_id, .. } => Some(account_id.clone()),
+            _ => None,
+        })
+        .context("no account record found")?;
+
+    let config = Config::from_file(&home_dir.join(CONFIG_FILENAME))?;
+    anyhow::ensure!(
+        config.genesis_records_file.is_none(),
+        "genesis with a separate records file is not supported"
+    );
+    let genesis_path = home_dir.join(&config.genesis_file);
+    let mut genesis = Genesis::from_file(&genesis_path, GenesisValidationMode::Full);
+    account_state::replace_account_records(&mut genesis, &account_id, records)?;
+    validate_genesis(&genesis);
+    genesis.to_file(&genesis_path);
+    println!("Replaced records of account {} in {}", account_id, genesis_path.display());
+    Ok(())
+}
+
 pub(crate) fn dump_account_storage(
     account_id: String,
     storage_key: String,
@@ -605,6 +744,106 @@ enum LoadTrieMode {
     LastFinalFromHeight(BlockHeight),
 }
 
+/// Keys and bytes of the state of a shard which go to one of the shards it is split into.
+#[derive(Default)]
+struct SplitStateSize {
+    num_keys: u64,
+    num_bytes: u64,
+}
+
+impl SplitStateSize {
+    fn add(&mut self, key: &[u8], value: &[u8]) {
+        self.num_keys += 1;
+        self.num_bytes += (key.len() + value.len()) as u64;
+    }
+}
+
+pub(crate) fn check_resharding(
+    height: Option<BlockHeight>,
+    shard_layout_file: Option<PathBuf>,
+    home_dir: &Path,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let shard_layout = match shard_layout_file {
+        Some(path) => {
+            let file = File::open(&path).unwrap();
+            serde_json::from_reader::<_, ShardLayout>(file).unwrap()
+        }
+        None => near_config.genesis.config.simple_nightshade_shard_layout.clone().expect(
+            "no --shard-layout-file given and no simple nightshade shard layout in genesis",
+        ),
+    };
+    let mode = match height {
+        Some(height) => LoadTrieMode::Height(height),
+        None => LoadTrieMode::Latest,
+    };
+    let (runtime, state_roots, header) =
+        load_trie_stop_at_height(store, home_dir, &near_config, mode);
+    println!("Splitting the state at height {} into {:?}", header.height(), shard_layout);
+    let start_time = std::time::Instant::now();
+    for (shard_id, state_root) in state_roots.iter().enumerate() {
+        let shard_id = shard_id as ShardId;
+        let shard_start_time = std::time::Instant::now();
+        let children = match shard_layout.get_split_shard_uids(shard_id) {
+            Some(children) => children,
+            None => {
+                println!("{}", Red.paint(format!("Shard {} has no children", shard_id)));
+                continue;
+            }
+        };
+        let trie = runtime.get_trie_for_shard(shard_id, header.prev_hash()).unwrap();
+        let num_parts =
+            get_num_state_parts(trie.retrieve_root_node(state_root).unwrap().memory_usage);
+        let mut child_sizes: HashMap<ShardUId, SplitStateSize> = HashMap::new();
+        let mut unmapped: HashMap<AccountId, ShardUId> = HashMap::new();
+        // Delayed receipts are not split by account, they are rebuilt in the children at the end.
+        let mut delayed_receipts = SplitStateSize::default();
+        for item in TrieIterator::new(&trie, state_root).unwrap() {
+            let (key, value) = item.unwrap();
+            match parse_account_id_from_raw_key(&key).unwrap() {
+                Some(account_id) => {
+                    let child = account_id_to_shard_uid(&account_id, &shard_layout);
+                    if !children.contains(&child) {
+                        unmapped.insert(account_id, child);
+                        continue;
+                    }
+                    child_sizes.entry(child).or_default().add(&key, &value);
+                }
+                None => delayed_receipts.add(&key, &value),
+            }
+        }
+        println!(
+            "Shard {} with state root {} in {} parts, read in {:?}:",
+            shard_id,
+            state_root,
+            num_parts,
+            shard_start_time.elapsed()
+        );
+        for child in children {
+            let size = child_sizes.remove(&child).unwrap_or_default();
+            println!(
+                "  child shard {}: {} keys, {} bytes",
+                child.shard_id, size.num_keys, size.num_bytes
+            );
+        }
+        println!(
+            "  delayed receipts: {} keys, {} bytes",
+            delayed_receipts.num_keys, delayed_receipts.num_bytes
+        );
+        for (account_id, child) in unmapped {
+            println!(
+                "{}",
+                Red.paint(format!(
+                    "  account {} maps to shard {} which is not a child of shard {}",
+                    account_id, child.shard_id, shard_id
+                ))
+            );
+        }
+    }
+    println!("Checked all shards in {:?}", start_time.elapsed());
+}
+
 fn load_trie(
     store: Store,
     home_dir: &Path,
@@ -0,0 +1,506 @@
+//! Export of the blocks, transactions, receipts and execution outcomes of a range of heights to
+//! Parquet files, for analytics which would otherwise need to scrape the JSON RPC.
+//!
+//! Every table is written to its own directory, partitioned by the height of the blocks:
+//! `<output>/<table>/height_bucket=<first height of the bucket>/part-<first>-<last>.parquet`,
+//! where `first` and `last` are the heights of the range exported into the bucket.  Exporting
+//! the same range again overwrites the same files.
+//!
+//! The schema of every table is listed in [`TABLES`].  Columns are never renamed, removed or
+//! changed; adding columns bumps [`SCHEMA_VERSION`], which is stored in the metadata of every
+//! file under the `near.schema_version` key.  Hashes and public keys are base58 strings as in the
+//! RPC, balances are decimal strings since Parquet has no unsigned 128-bit integers, and nested
+//! values like actions are the JSON of their RPC views.
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_primitives::block::Block;
+use near_primitives::transaction::ExecutionStatus;
+use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::views::{ReceiptEnumView, ReceiptView, SignedTransactionView};
+use near_store::Store;
+use nearcore::NearConfig;
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+use parquet::format::KeyValue;
+use parquet::schema::parser::parse_message_type;
+use tracing::info;
+
+/// Version of the schema of the exported tables.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_VERSION_KEY: &str = "near.schema_version";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    UInt64,
+    Utf8,
+    OptionalUtf8,
+}
+
+struct Table {
+    name: &'static str,
+    columns: &'static [(&'static str, ColumnType)],
+}
+
+const BLOCKS: Table = Table {
+    name: "blocks",
+    columns: &[
+        ("height", ColumnType::UInt64),
+        ("hash", ColumnType::Utf8),
+        ("prev_hash", ColumnType::Utf8),
+        ("epoch_id", ColumnType::Utf8),
+        ("timestamp_nanos", ColumnType::UInt64),
+        ("gas_price", ColumnType::Utf8),
+        ("total_supply", ColumnType::Utf8),
+        ("protocol_version", ColumnType::UInt64),
+    ],
+};
+
+const TRANSACTIONS: Table = Table {
+    name: "transactions",
+    columns: &[
+        ("block_height", ColumnType::UInt64),
+        ("block_hash", ColumnType::Utf8),
+        ("shard_id", ColumnType::UInt64),
+        ("chunk_hash", ColumnType::Utf8),
+        ("hash", ColumnType::Utf8),
+        ("signer_id", ColumnType::Utf8),
+        ("public_key", ColumnType::Utf8),
+        ("nonce", ColumnType::UInt64),
+        ("receiver_id", ColumnType::Utf8),
+        ("actions", ColumnType::Utf8),
+    ],
+};
+
+/// The receipts included in the chunks, i.e. the ones executed in the block.
+const RECEIPTS: Table = Table {
+    name: "receipts",
+    columns: &[
+        ("block_height", ColumnType::UInt64),
+        ("block_hash", ColumnType::Utf8),
+        ("shard_id", ColumnType::UInt64),
+        ("chunk_hash", ColumnType::Utf8),
+        ("receipt_id", ColumnType::Utf8),
+        ("predecessor_id", ColumnType::Utf8),
+        ("receiver_id", ColumnType::Utf8),
+        ("kind", ColumnType::Utf8),
+        ("receipt", ColumnType::Utf8),
+    ],
+};
+
+/// The outcomes of the transactions and receipts executed in the block, `id` being the hash of
+/// the transaction or the id of the receipt.
+const EXECUTION_OUTCOMES: Table = Table {
+    name: "execution_outcomes",
+    columns: &[
+        ("block_height", ColumnType::UInt64),
+        ("block_hash", ColumnType::Utf8),
+        ("shard_id", ColumnType::UInt64),
+        ("id", ColumnType::Utf8),
+        ("executor_id", ColumnType::Utf8),
+        ("gas_burnt", ColumnType::UInt64),
+        ("tokens_burnt", ColumnType::Utf8),
+        ("receipt_ids", ColumnType::Utf8),
+        ("logs", ColumnType::Utf8),
+        ("status", ColumnType::Utf8),
+        ("status_value", ColumnType::OptionalUtf8),
+    ],
+};
+
+const TABLES: [&Table; 4] = [&BLOCKS, &TRANSACTIONS, &RECEIPTS, &EXECUTION_OUTCOMES];
+
+impl Table {
+    /// The schema in the Parquet message type syntax.
+    fn schema(&self) -> String {
+        let mut schema = format!("message {} {{\n", self.name);
+        for (name, column_type) in self.columns {
+            let column = match column_type {
+                ColumnType::UInt64 => format!("required int64 {} (UINT_64);", name),
+                ColumnType::Utf8 => format!("required binary {} (UTF8);", name),
+                ColumnType::OptionalUtf8 => format!("optional binary {} (UTF8);", name),
+            };
+            schema.push_str(&format!("  {}\n", column));
+        }
+        schema.push('}');
+        schema
+    }
+}
+
+enum Value {
+    UInt64(u64),
+    Utf8(String),
+    OptionalUtf8(Option<String>),
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::UInt64(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Utf8(value)
+    }
+}
+
+impl From<Option<String>> for Value {
+    fn from(value: Option<String>) -> Self {
+        Value::OptionalUtf8(value)
+    }
+}
+
+enum Column {
+    UInt64(Vec<i64>),
+    Utf8(Vec<ByteArray>),
+    /// The non-null values and the definition level of every row: 1 if set, 0 if null.
+    OptionalUtf8(Vec<ByteArray>, Vec<i16>),
+}
+
+/// Rows of a table buffered in columns until they're written to a partition.
+struct TableBuffer {
+    table: &'static Table,
+    columns: Vec<Column>,
+    num_rows: usize,
+}
+
+impl TableBuffer {
+    fn new(table: &'static Table) -> Self {
+        let columns = table
+            .columns
+            .iter()
+            .map(|(_, column_type)| match column_type {
+                ColumnType::UInt64 => Column::UInt64(vec![]),
+                ColumnType::Utf8 => Column::Utf8(vec![]),
+                ColumnType::OptionalUtf8 => Column::OptionalUtf8(vec![], vec![]),
+            })
+            .collect();
+        TableBuffer { table, columns, num_rows: 0 }
+    }
+
+    fn push(&mut self, row: Vec<Value>) {
+        assert_eq!(row.len(), self.columns.len(), "row doesn't match table {}", self.table.name);
+        for (column, value) in self.columns.iter_mut().zip(row) {
+            match (column, value) {
+                // Unsigned values keep their bits, the UINT_64 annotation tells the readers.
+                (Column::UInt64(values), Value::UInt64(value)) => values.push(value as i64),
+                (Column::Utf8(values), Value::Utf8(value)) => values.push(value.into()),
+                (Column::OptionalUtf8(values, def_levels), Value::OptionalUtf8(value)) => {
+                    match value {
+                        Some(value) => {
+                            values.push(value.into());
+                            def_levels.push(1);
+                        }
+                        None => def_levels.push(0),
+                    }
+                }
+                _ => panic!("row doesn't match the schema of table {}", self.table.name),
+            }
+        }
+        self.num_rows += 1;
+    }
+
+    /// Writes the buffered rows to a single row group of a new file and clears the buffer.
+    fn write(&mut self, path: &Path) -> anyhow::Result<()> {
+        let schema = Arc::new(parse_message_type(&self.table.schema())?);
+        let properties = Arc::new(
+            WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .set_key_value_metadata(Some(vec![KeyValue::new(
+                    SCHEMA_VERSION_KEY.to_string(),
+                    SCHEMA_VERSION.to_string(),
+                )]))
+                .build(),
+        );
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = SerializedFileWriter::new(file, schema, properties)?;
+        let mut row_group = writer.next_row_group()?;
+        let table = self.table;
+        let columns = std::mem::replace(self, TableBuffer::new(table)).columns;
+        for column in columns {
+            let mut column_writer =
+                row_group.next_column()?.context("schema has fewer columns than the table")?;
+            match (&mut column_writer, &column) {
+                (ColumnWriter::Int64ColumnWriter(writer), Column::UInt64(values)) => {
+                    writer.write_batch(values, None, None)?;
+                }
+                (ColumnWriter::ByteArrayColumnWriter(writer), Column::Utf8(values)) => {
+                    writer.write_batch(values, None, None)?;
+                }
+                (
+                    ColumnWriter::ByteArrayColumnWriter(writer),
+                    Column::OptionalUtf8(values, def_levels),
+                ) => {
+                    writer.write_batch(values, Some(def_levels), None)?;
+                }
+                _ => unreachable!("the schema is generated from the table"),
+            }
+            row_group.close_column(column_writer)?;
+        }
+        writer.close_row_group(row_group)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+/// First height of the bucket the height belongs to.
+fn partition_start(height: BlockHeight, partition_size: BlockHeight) -> BlockHeight {
+    height - height % partition_size
+}
+
+fn partition_path(
+    output: &Path,
+    table: &Table,
+    partition_size: BlockHeight,
+    first_height: BlockHeight,
+    last_height: BlockHeight,
+) -> PathBuf {
+    output
+        .join(table.name)
+        .join(format!("height_bucket={}", partition_start(first_height, partition_size)))
+        .join(format!("part-{}-{}.parquet", first_height, last_height))
+}
+
+struct Exporter {
+    output: PathBuf,
+    partition_size: BlockHeight,
+    buffers: Vec<TableBuffer>,
+}
+
+impl Exporter {
+    fn buffer(&mut self, table: &Table) -> &mut TableBuffer {
+        self.buffers.iter_mut().find(|buffer| buffer.table.name == table.name).unwrap()
+    }
+
+    /// Writes every table buffered for the heights from `first_height` to `last_height`, which
+    /// all belong to the same bucket.
+    fn flush(&mut self, first_height: BlockHeight, last_height: BlockHeight) -> anyhow::Result<()> {
+        for buffer in &mut self.buffers {
+            let path = partition_path(
+                &self.output,
+                buffer.table,
+                self.partition_size,
+                first_height,
+                last_height,
+            );
+            fs::create_dir_all(path.parent().unwrap())
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let num_rows = buffer.num_rows;
+            buffer.write(&path).with_context(|| format!("Failed to write {}", path.display()))?;
+            info!(target: "state_viewer", "Wrote {} rows to {}", num_rows, path.display());
+        }
+        Ok(())
+    }
+
+    fn export_block(&mut self, chain_store: &mut ChainStore, block: &Block) -> anyhow::Result<()> {
+        let header = block.header();
+        let block_height = header.height();
+        let block_hash = *header.hash();
+        self.buffer(&BLOCKS).push(vec![
+            block_height.into(),
+            block_hash.to_string().into(),
+            header.prev_hash().to_string().into(),
+            header.epoch_id().0.to_string().into(),
+            header.raw_timestamp().into(),
+            header.gas_price().to_string().into(),
+            header.total_supply().to_string().into(),
+            u64::from(header.latest_protocol_version()).into(),
+        ]);
+
+        for (shard_id, chunk_header) in block.chunks().iter().enumerate() {
+            let shard_id = shard_id as ShardId;
+            if header.chunk_mask()[shard_id as usize] {
+                let chunk_hash = chunk_header.chunk_hash();
+                let chunk = chain_store.get_chunk(&chunk_hash).with_context(|| {
+                    format!("Chunk {:?} of block {} isn't available", chunk_hash, block_hash)
+                })?;
+                let chunk_hash = chunk_hash.0.to_string();
+                let transactions = chunk.transactions().clone();
+                let receipts = chunk.receipts().clone();
+                for transaction in transactions {
+                    let transaction = SignedTransactionView::from(transaction);
+                    self.buffer(&TRANSACTIONS).push(vec![
+                        block_height.into(),
+                        block_hash.to_string().into(),
+                        shard_id.into(),
+                        chunk_hash.clone().into(),
+                        transaction.hash.to_string().into(),
+                        transaction.signer_id.to_string().into(),
+                        transaction.public_key.to_string().into(),
+                        transaction.nonce.into(),
+                        transaction.receiver_id.to_string().into(),
+                        serde_json::to_string(&transaction.actions)?.into(),
+                    ]);
+                }
+                for receipt in receipts {
+                    let receipt = ReceiptView::from(receipt);
+                    let kind = match receipt.receipt {
+                        ReceiptEnumView::Action { .. } => "action",
+                        ReceiptEnumView::Data { .. } => "data",
+                    };
+                    self.buffer(&RECEIPTS).push(vec![
+                        block_height.into(),
+                        block_hash.to_string().into(),
+                        shard_id.into(),
+                        chunk_hash.clone().into(),
+                        receipt.receipt_id.to_string().into(),
+                        receipt.predecessor_id.to_string().into(),
+                        receipt.receiver_id.to_string().into(),
+                        kind.to_string().into(),
+                        serde_json::to_string(&receipt.receipt)?.into(),
+                    ]);
+                }
+            }
+
+            // The outcomes are stored by id for every block they were executed in, which may be
+            // several blocks on different forks.
+            for id in chain_store.get_outcomes_by_block_hash_and_shard_id(&block_hash, shard_id)? {
+                for outcome in chain_store.get_outcomes_by_id(&id)? {
+                    if outcome.block_hash != block_hash {
+                        continue;
+                    }
+                    let outcome = outcome.outcome_with_id.outcome;
+                    let (status, status_value) = match outcome.status {
+                        ExecutionStatus::Unknown => ("Unknown", None),
+                        ExecutionStatus::Failure(err) => {
+                            ("Failure", Some(serde_json::to_string(&err)?))
+                        }
+                        ExecutionStatus::SuccessValue(value) => {
+                            ("SuccessValue", Some(near_primitives::serialize::to_base64(&value)))
+                        }
+                        ExecutionStatus::SuccessReceiptId(receipt_id) => {
+                            ("SuccessReceiptId", Some(receipt_id.to_string()))
+                        }
+                    };
+                    self.buffer(&EXECUTION_OUTCOMES).push(vec![
+                        block_height.into(),
+                        block_hash.to_string().into(),
+                        shard_id.into(),
+                        id.to_string().into(),
+                        outcome.executor_id.to_string().into(),
+                        outcome.gas_burnt.into(),
+                        outcome.tokens_burnt.to_string().into(),
+                        serde_json::to_string(&outcome.receipt_ids)?.into(),
+                        serde_json::to_string(&outcome.logs)?.into(),
+                        status.to_string().into(),
+                        status_value.into(),
+                    ]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn export_parquet(
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+    partition_size: BlockHeight,
+    output: &Path,
+    near_config: NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        start_height <= end_height,
+        "--start-height {} is greater than --end-height {}",
+        start_height,
+        end_height
+    );
+    anyhow::ensure!(partition_size > 0, "--partition-size must be positive");
+    let mut chain_store = ChainStore::new(store, near_config.genesis.config.genesis_height);
+    let tail = chain_store.tail()?;
+    let head = chain_store.head()?.height;
+    anyhow::ensure!(
+        tail <= start_height && end_height <= head,
+        "the node has blocks from {} to {}, the blocks before {} are only kept by archival nodes",
+        tail,
+        head,
+        tail
+    );
+
+    let mut exporter = Exporter {
+        output: output.to_path_buf(),
+        partition_size,
+        buffers: TABLES.iter().map(|table| TableBuffer::new(*table)).collect(),
+    };
+    let mut first_height = start_height;
+    for height in start_height..=end_height {
+        // Heights may be skipped, in which case there's no block.
+        if let Ok(block_hash) = chain_store.get_block_hash_by_height(height) {
+            let block = chain_store.get_block(&block_hash)?.clone();
+            exporter.export_block(&mut chain_store, &block)?;
+        }
+        let bucket_end = partition_start(height, partition_size) + partition_size - 1;
+        if height == bucket_end || height == end_height {
+            exporter.flush(first_height, height)?;
+            first_height = height + 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::Path;
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::schema::parser::parse_message_type;
+
+    use super::{partition_path, TableBuffer, EXECUTION_OUTCOMES, SCHEMA_VERSION, TABLES};
+
+    #[test]
+    fn test_schemas() {
+        for table in TABLES {
+            parse_message_type(&table.schema()).unwrap();
+        }
+        assert_eq!(
+            partition_path(Path::new("out"), &EXECUTION_OUTCOMES, 1000, 12345, 12999),
+            Path::new("out/execution_outcomes/height_bucket=12000/part-12345-12999.parquet")
+        );
+    }
+
+    #[test]
+    fn test_write_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outcomes.parquet");
+        let mut buffer = TableBuffer::new(&EXECUTION_OUTCOMES);
+        for status_value in [Some("AQ==".to_string()), None] {
+            buffer.push(vec![
+                u64::MAX.into(),
+                "hash".to_string().into(),
+                0u64.into(),
+                "id".to_string().into(),
+                "test.near".to_string().into(),
+                2428000000000u64.into(),
+                "242800000000000000000".to_string().into(),
+                "[]".to_string().into(),
+                "[]".to_string().into(),
+                "SuccessValue".to_string().into(),
+                status_value.into(),
+            ]);
+        }
+        buffer.write(&path).unwrap();
+        assert_eq!(buffer.num_rows, 0);
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+        assert_eq!(metadata.schema_descr().num_columns(), EXECUTION_OUTCOMES.columns.len());
+        let schema_version = metadata
+            .key_value_metadata()
+            .unwrap()
+            .iter()
+            .find(|key_value| key_value.key == "near.schema_version")
+            .and_then(|key_value| key_value.value.clone());
+        assert_eq!(schema_version, Some(SCHEMA_VERSION.to_string()));
+    }
+}
@@ -1,11 +1,13 @@
 #![doc = include_str!("../README.md")]
 
+mod account_state;
 mod apply_chain_range;
 mod apply_chunk;
 pub mod cli;
 mod commands;
 mod epoch_info;
+mod export_parquet;
 mod rocksdb_stats;
 mod state_dump;
 
-pub use cli::StateViewerSubCommand;
+pub use cli::{ReplayBlocksCmd, StateViewerSubCommand};
@@ -9,7 +9,8 @@ use quote::quote;
 /// This derive can be used to provide performance metrics to method calls with Actors. Currently
 /// we print performance stats per thread every minute, and we print a warning whenever a function
 /// call exceeds took more than given time limit. It should have no performance impact unless
-/// `performance_stats` feature is enabled.
+/// `performance_stats` feature is enabled. Regardless of the feature, the time taken per message
+/// type is exported via `near_performance_metrics::actor_metrics`.
 ///
 /// This function assumes it wraps around a method with `&mut self, msg: NetworkClientMessages,
 /// ctx: &mut Self::Context<Self>` as arguments. There is currently a requirement that the second
@@ -84,14 +85,23 @@ fn perf_internal(_attr: TokenStream, item: TokenStream, debug: bool) -> TokenStr
         let new_body: TokenStream = if debug {
             quote! (
                 {
+                    let _handler_timer = near_performance_metrics::actor_metrics::HandlerTimer::new(
+                        std::any::type_name::<Self>(),
+                        near_performance_metrics::actor_metrics::variant_name(&msg),
+                    );
                     near_performance_metrics::stats::measure_performance_with_debug(std::any::type_name::<Self>(), msg, move |msg| {
                         #function_body
                     })
                 }
             ).into()
         } else {
+            let message = message_type_name(&func.sig);
             quote! (
                 {
+                    let _handler_timer = near_performance_metrics::actor_metrics::HandlerTimer::new(
+                        std::any::type_name::<Self>(),
+                        #message,
+                    );
                     near_performance_metrics::stats::measure_performance(std::any::type_name::<Self>(), (), move |_| {
                         #function_body
                     })
@@ -105,3 +115,14 @@ fn perf_internal(_attr: TokenStream, item: TokenStream, debug: bool) -> TokenStr
         panic!("not a function");
     }
 }
+
+/// Name of the type of the message handled by the method, taken from its second argument.
+fn message_type_name(sig: &syn::Signature) -> String {
+    match sig.inputs.iter().nth(1) {
+        Some(syn::FnArg::Typed(arg)) => match &*arg.ty {
+            syn::Type::Path(path) => path.path.segments.last().unwrap().ident.to_string(),
+            ty => quote!(#ty).to_string(),
+        },
+        _ => panic!("expected the message as the second argument"),
+    }
+}
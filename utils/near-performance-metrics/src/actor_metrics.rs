@@ -0,0 +1,137 @@
+//! Prometheus metrics of actors, exported regardless of the `performance_stats` feature: how long
+//! handlers of every message type take and how long messages wait in the mailbox.
+//!
+//! Handler times are recorded by the `perf` and `perf_with_debug` macros.  The time messages wait
+//! in the mailbox is measured by periodically sending a [`MailboxProbe`] to the actor, see
+//! [`spawn_mailbox_probes`].
+use std::time::{Duration, Instant};
+
+use actix::dev::ToEnvelope;
+use actix::{Actor, Addr, Handler, Message};
+use near_metrics::{
+    try_create_histogram_vec, try_create_int_counter_vec, try_create_int_gauge_vec, HistogramVec,
+    IntCounterVec, IntGaugeVec,
+};
+use once_cell::sync::Lazy;
+use strum::AsStaticRef;
+
+/// How often the mailbox of an actor is probed.
+const MAILBOX_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+static HANDLER_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_actor_handler_time",
+        "Time taken by an actor to handle a message, per message type",
+        &["actor", "message"],
+        None,
+    )
+    .unwrap()
+});
+static MESSAGES_HANDLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_actor_messages_handled_total",
+        "Number of messages handled by an actor",
+        &["actor"],
+    )
+    .unwrap()
+});
+static MAILBOX_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_actor_mailbox_delay",
+        "Time a message waited in the mailbox of an actor before being handled, measured by probes",
+        &["actor"],
+        None,
+    )
+    .unwrap()
+});
+static MAILBOX_DEPTH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_actor_mailbox_depth",
+        "Number of messages handled by an actor while the last probe waited in its mailbox, i.e. the number of messages ahead of it",
+        &["actor"],
+    )
+    .unwrap()
+});
+
+/// Name of the actor without the module path.
+fn actor_name(type_name: &'static str) -> &'static str {
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
+
+/// Name of the variant of a message enum, used as the message type.
+pub fn variant_name<M: AsStaticRef<str>>(msg: &M) -> &'static str {
+    msg.as_static()
+}
+
+/// Records the time taken by a handler when dropped.
+pub struct HandlerTimer {
+    actor: &'static str,
+    message: &'static str,
+    start: Instant,
+}
+
+impl HandlerTimer {
+    pub fn new(actor_type_name: &'static str, message: &'static str) -> Self {
+        Self { actor: actor_name(actor_type_name), message, start: Instant::now() }
+    }
+}
+
+impl Drop for HandlerTimer {
+    fn drop(&mut self) {
+        HANDLER_TIME
+            .with_label_values(&[self.actor, self.message])
+            .observe(self.start.elapsed().as_secs_f64());
+        MESSAGES_HANDLED.with_label_values(&[self.actor]).inc();
+    }
+}
+
+/// Message sent to an actor to measure how long messages wait in its mailbox.  The actor only
+/// has to call [`MailboxProbe::observe`] when handling it.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct MailboxProbe {
+    actor: &'static str,
+    sent_at: Instant,
+    handled_before: u64,
+}
+
+impl MailboxProbe {
+    fn new(actor: &'static str) -> Self {
+        Self {
+            actor,
+            sent_at: Instant::now(),
+            handled_before: MESSAGES_HANDLED.with_label_values(&[actor]).get(),
+        }
+    }
+
+    pub fn observe(self) {
+        MAILBOX_DELAY
+            .with_label_values(&[self.actor])
+            .observe(self.sent_at.elapsed().as_secs_f64());
+        let handled = MESSAGES_HANDLED.with_label_values(&[self.actor]).get();
+        MAILBOX_DEPTH
+            .with_label_values(&[self.actor])
+            .set(handled.saturating_sub(self.handled_before) as i64);
+    }
+}
+
+/// Periodically sends a [`MailboxProbe`] to the actor until it stops.  Must be called from within
+/// an actix system.
+pub fn spawn_mailbox_probes<A>(addr: Addr<A>)
+where
+    A: Actor + Handler<MailboxProbe>,
+    A::Context: ToEnvelope<A, MailboxProbe>,
+{
+    let actor = actor_name(std::any::type_name::<A>());
+    // Doesn't keep the actor alive.
+    let addr = addr.downgrade();
+    actix::spawn(async move {
+        loop {
+            actix::clock::sleep(MAILBOX_PROBE_INTERVAL).await;
+            match addr.upgrade() {
+                Some(addr) => addr.do_send(MailboxProbe::new(actor)),
+                None => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,141 @@
+//! Statistics of the memory allocator of the node, exported as metrics and returned by
+//! [`snapshot`], and dumps of its heap profile.
+//!
+//! The crate doesn't know which allocator the binary uses, so the binary registers one via
+//! [`set_allocator`].  Without a registered allocator the statistics aren't available.
+use std::path::PathBuf;
+
+use near_metrics::{try_create_int_gauge, IntGauge};
+use once_cell::sync::{Lazy, OnceCell};
+
+static ALLOCATED_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_allocator_allocated_bytes", "Bytes allocated by the node").unwrap()
+});
+static ACTIVE_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_allocator_active_bytes",
+        "Bytes in the pages of the allocator which hold allocations",
+    )
+    .unwrap()
+});
+static RESIDENT_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_allocator_resident_bytes",
+        "Bytes in the physically resident pages mapped by the allocator",
+    )
+    .unwrap()
+});
+static METADATA_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_allocator_metadata_bytes", "Bytes used by the allocator metadata")
+        .unwrap()
+});
+static MAPPED_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge("near_allocator_mapped_bytes", "Bytes mapped by the allocator").unwrap()
+});
+static RETAINED_BYTES: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_allocator_retained_bytes",
+        "Bytes of virtual memory which the allocator retained instead of returning to the OS",
+    )
+    .unwrap()
+});
+
+static ALLOCATOR: OnceCell<Box<dyn AllocatorIntrospection>> = OnceCell::new();
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocatorStats {
+    pub allocator: String,
+    pub allocated: u64,
+    pub active: u64,
+    pub metadata: u64,
+    pub resident: u64,
+    pub mapped: u64,
+    pub retained: u64,
+    /// Share of the active bytes which aren't allocated, i.e. lost to fragmentation.
+    pub fragmentation: f64,
+    pub arenas: Vec<ArenaStats>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArenaStats {
+    pub index: u32,
+    /// Number of threads assigned to the arena.
+    pub threads: u32,
+    pub active: u64,
+    /// Bytes in the unused pages which weren't returned to the OS yet.
+    pub dirty: u64,
+    pub muzzy: u64,
+    pub resident: u64,
+    pub mapped: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AllocatorStatsError {
+    #[error("the allocator of the node doesn't provide statistics")]
+    NotAvailable,
+    #[error("heap profiling is disabled: {0}")]
+    ProfilingDisabled(String),
+    #[error("the allocator failed: {0}")]
+    Allocator(String),
+}
+
+/// Statistics and heap profiles of the global allocator of the binary.
+pub trait AllocatorIntrospection: Send + Sync {
+    fn stats(&self) -> Result<AllocatorStats, AllocatorStatsError>;
+
+    /// Dumps the heap profile to a new file and returns its path.
+    fn dump_heap_profile(&self) -> Result<PathBuf, AllocatorStatsError>;
+}
+
+/// Registers the global allocator of the binary.  Only the first call has an effect.
+pub fn set_allocator(allocator: Box<dyn AllocatorIntrospection>) {
+    let _ = ALLOCATOR.set(allocator);
+}
+
+/// Current statistics of the allocator.  Also updates the metrics.
+pub fn snapshot() -> Result<AllocatorStats, AllocatorStatsError> {
+    let stats = ALLOCATOR.get().ok_or(AllocatorStatsError::NotAvailable)?.stats()?;
+    ALLOCATED_BYTES.set(stats.allocated as i64);
+    ACTIVE_BYTES.set(stats.active as i64);
+    RESIDENT_BYTES.set(stats.resident as i64);
+    METADATA_BYTES.set(stats.metadata as i64);
+    MAPPED_BYTES.set(stats.mapped as i64);
+    RETAINED_BYTES.set(stats.retained as i64);
+    Ok(stats)
+}
+
+pub fn dump_heap_profile() -> Result<PathBuf, AllocatorStatsError> {
+    ALLOCATOR.get().ok_or(AllocatorStatsError::NotAvailable)?.dump_heap_profile()
+}
+
+/// Updates the metrics if an allocator is registered.
+pub(crate) fn update_metrics() {
+    if ALLOCATOR.get().is_some() {
+        if let Err(err) = snapshot() {
+            tracing::debug!("failed to update the allocator metrics: {}", err);
+        }
+    }
+}
+
+/// Share of the active bytes which aren't allocated.
+pub fn fragmentation(allocated: u64, active: u64) -> f64 {
+    if active == 0 {
+        0.0
+    } else {
+        active.saturating_sub(allocated) as f64 / active as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fragmentation;
+
+    #[test]
+    fn test_fragmentation() {
+        assert_eq!(fragmentation(0, 0), 0.0);
+        assert_eq!(fragmentation(3 << 20, 4 << 20), 0.25);
+        assert_eq!(fragmentation(4 << 20, 4 << 20), 0.0);
+        // The counters are read one after another, so allocated may overtake active.
+        assert_eq!(fragmentation(5 << 20, 4 << 20), 0.0);
+    }
+}
@@ -1,8 +1,11 @@
 pub mod actix_disabled;
 #[cfg(feature = "performance_stats")]
 pub mod actix_enabled;
+pub mod actor_metrics;
+pub mod allocator_stats;
 pub mod framed_write;
 pub mod process;
+pub mod runtime_stats;
 pub mod stats_disabled;
 #[cfg(feature = "performance_stats")]
 pub mod stats_enabled;
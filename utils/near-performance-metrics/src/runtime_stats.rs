@@ -0,0 +1,306 @@
+//! Statistics of the threads, async runtimes and thread pools of the node, exported as metrics
+//! and returned by [`snapshot`].  Meant to tell which thread is busy and whether a blocking call
+//! holds up an async runtime.
+//!
+//! Runtimes are monitored by spawning [`runtime_heartbeat`] on them: a runtime whose heartbeat
+//! is late is blocked.  Thread pools count their tasks via [`ThreadPoolCounters`].  Threads are
+//! sampled from `/proc`, so their CPU usage is only available on Linux.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant};
+
+use near_metrics::{
+    try_create_int_counter_vec, try_create_int_gauge_vec, IntCounterVec, IntGaugeVec,
+};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tracing::{error, warn};
+
+/// How often runtimes tick their heartbeat.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// A runtime whose heartbeat is late by more than this is considered blocked.
+const BLOCKED_THRESHOLD: Duration = Duration::from_secs(5);
+/// How often the watchdog samples the threads and checks the heartbeats.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+static RUNTIME_HEARTBEAT_LAG: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_runtime_heartbeat_lag_ms",
+        "How late the heartbeat of an async runtime is, a lag of seconds means that a task blocks the runtime",
+        &["runtime"],
+    )
+    .unwrap()
+});
+static RUNTIME_BLOCKED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_runtime_blocked_total",
+        "Number of times an async runtime was blocked for longer than 5 seconds",
+        &["runtime"],
+    )
+    .unwrap()
+});
+static THREAD_POOL_QUEUED: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_thread_pool_queued_tasks",
+        "Number of tasks waiting for a thread of a thread pool",
+        &["pool"],
+    )
+    .unwrap()
+});
+static THREAD_POOL_RUNNING: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_thread_pool_running_tasks",
+        "Number of tasks running on the threads of a thread pool",
+        &["pool"],
+    )
+    .unwrap()
+});
+static THREAD_CPU_USAGE: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_thread_cpu_usage",
+        "CPU usage of the threads with the given name in percent of one core",
+        &["thread"],
+    )
+    .unwrap()
+});
+
+static START: Lazy<Instant> = Lazy::new(Instant::now);
+static HEARTBEATS: Lazy<Mutex<Vec<Arc<Heartbeat>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static THREAD_POOLS: Lazy<Mutex<Vec<Arc<ThreadPoolCounters>>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+static THREADS: Lazy<Mutex<Vec<ThreadStats>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ThreadStats {
+    pub tid: u64,
+    pub name: String,
+    /// CPU usage since the previous sample, in percent of one core.
+    pub cpu_usage: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RuntimeHeartbeatStats {
+    pub name: &'static str,
+    pub heartbeat_lag_ms: u64,
+    pub blocked: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ThreadPoolStats {
+    pub name: &'static str,
+    pub queued_tasks: i64,
+    pub running_tasks: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RuntimeStats {
+    pub threads: Vec<ThreadStats>,
+    pub runtimes: Vec<RuntimeHeartbeatStats>,
+    pub thread_pools: Vec<ThreadPoolStats>,
+}
+
+struct Heartbeat {
+    name: &'static str,
+    /// Milliseconds since `START` of the last tick.
+    last_tick: AtomicU64,
+    blocked: AtomicBool,
+}
+
+impl Heartbeat {
+    fn lag(&self) -> Duration {
+        let since_tick = START
+            .elapsed()
+            .saturating_sub(Duration::from_millis(self.last_tick.load(Ordering::Relaxed)));
+        since_tick.saturating_sub(HEARTBEAT_INTERVAL)
+    }
+
+    fn stats(&self) -> RuntimeHeartbeatStats {
+        RuntimeHeartbeatStats {
+            name: self.name,
+            heartbeat_lag_ms: self.lag().as_millis() as u64,
+            blocked: self.blocked.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Ticks a heartbeat of the runtime it is spawned on, forever.
+pub async fn runtime_heartbeat(name: &'static str) {
+    let heartbeat = Arc::new(Heartbeat {
+        name,
+        last_tick: AtomicU64::new(START.elapsed().as_millis() as u64),
+        blocked: AtomicBool::new(false),
+    });
+    HEARTBEATS.lock().unwrap().push(heartbeat.clone());
+    loop {
+        heartbeat.last_tick.store(START.elapsed().as_millis() as u64, Ordering::Relaxed);
+        actix::clock::sleep(HEARTBEAT_INTERVAL).await;
+    }
+}
+
+/// Counts the tasks of a thread pool.
+pub struct ThreadPoolCounters {
+    name: &'static str,
+    queued: AtomicI64,
+    running: AtomicI64,
+}
+
+impl ThreadPoolCounters {
+    pub fn new(name: &'static str) -> Arc<Self> {
+        let counters =
+            Arc::new(Self { name, queued: AtomicI64::new(0), running: AtomicI64::new(0) });
+        THREAD_POOLS.lock().unwrap().push(counters.clone());
+        counters
+    }
+
+    pub fn add_queued(&self, num_tasks: usize) {
+        self.queued.fetch_add(num_tasks as i64, Ordering::Relaxed);
+    }
+
+    /// Marks a queued task as running until the returned guard is dropped.
+    pub fn start_task(&self) -> RunningTask<'_> {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        self.running.fetch_add(1, Ordering::Relaxed);
+        RunningTask(self)
+    }
+
+    fn stats(&self) -> ThreadPoolStats {
+        ThreadPoolStats {
+            name: self.name,
+            queued_tasks: self.queued.load(Ordering::Relaxed),
+            running_tasks: self.running.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub struct RunningTask<'a>(&'a ThreadPoolCounters);
+
+impl Drop for RunningTask<'_> {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Latest statistics: threads as of the last sample of the watchdog, runtimes and thread pools as
+/// of now.
+pub fn snapshot() -> RuntimeStats {
+    RuntimeStats {
+        threads: THREADS.lock().unwrap().clone(),
+        runtimes: HEARTBEATS.lock().unwrap().iter().map(|heartbeat| heartbeat.stats()).collect(),
+        thread_pools: THREAD_POOLS.lock().unwrap().iter().map(|pool| pool.stats()).collect(),
+    }
+}
+
+/// Starts a thread which periodically samples the threads, checks the heartbeats of the runtimes
+/// and exports the statistics, along with those of the allocator, as metrics.  Only the first call starts it.
+pub fn start_watchdog() {
+    static WATCHDOG: Once = Once::new();
+    WATCHDOG.call_once(spawn_watchdog);
+}
+
+fn spawn_watchdog() {
+    Lazy::force(&START);
+    if let Err(err) = std::thread::Builder::new().name("RuntimeWatchdog".to_string()).spawn(|| {
+        let mut prev_cpu_ticks = HashMap::new();
+        loop {
+            std::thread::sleep(WATCHDOG_INTERVAL);
+            check_heartbeats();
+            update_thread_pool_metrics();
+            crate::allocator_stats::update_metrics();
+            let threads = sample_threads(&mut prev_cpu_ticks);
+            *THREADS.lock().unwrap() = threads;
+        }
+    }) {
+        error!("failed to spawn the thread: {}", err);
+    }
+}
+
+fn check_heartbeats() {
+    for heartbeat in HEARTBEATS.lock().unwrap().iter() {
+        let lag = heartbeat.lag();
+        RUNTIME_HEARTBEAT_LAG.with_label_values(&[heartbeat.name]).set(lag.as_millis() as i64);
+        let blocked = lag > BLOCKED_THRESHOLD;
+        if blocked && !heartbeat.blocked.load(Ordering::Relaxed) {
+            warn!("Runtime {} is blocked for {:?}", heartbeat.name, lag);
+            RUNTIME_BLOCKED_TOTAL.with_label_values(&[heartbeat.name]).inc();
+        }
+        heartbeat.blocked.store(blocked, Ordering::Relaxed);
+    }
+}
+
+fn update_thread_pool_metrics() {
+    for pool in THREAD_POOLS.lock().unwrap().iter() {
+        let stats = pool.stats();
+        THREAD_POOL_QUEUED.with_label_values(&[stats.name]).set(stats.queued_tasks);
+        THREAD_POOL_RUNNING.with_label_values(&[stats.name]).set(stats.running_tasks);
+    }
+}
+
+/// Reads the CPU time of every thread of the process and computes its usage since the previous
+/// sample, which is kept in `prev_cpu_ticks`.
+fn sample_threads(prev_cpu_ticks: &mut HashMap<u64, u64>) -> Vec<ThreadStats> {
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    let entries = match std::fs::read_dir("/proc/self/task") {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let interval_ticks = WATCHDOG_INTERVAL.as_secs_f64() * ticks_per_sec as f64;
+    let mut cpu_ticks = HashMap::new();
+    let mut threads = vec![];
+    for entry in entries.flatten() {
+        let tid = match entry.file_name().to_str().and_then(|tid| tid.parse::<u64>().ok()) {
+            Some(tid) => tid,
+            None => continue,
+        };
+        let (name, ticks) = match std::fs::read_to_string(entry.path().join("stat"))
+            .ok()
+            .and_then(|stat| parse_thread_stat(&stat))
+        {
+            Some(stat) => stat,
+            None => continue,
+        };
+        let cpu_usage = prev_cpu_ticks.get(&tid).map_or(0, |prev| {
+            (ticks.saturating_sub(*prev) as f64 * 100.0 / interval_ticks).round() as u64
+        });
+        cpu_ticks.insert(tid, ticks);
+        threads.push(ThreadStats { tid, name, cpu_usage });
+    }
+    *prev_cpu_ticks = cpu_ticks;
+
+    let mut usage_per_name: HashMap<&str, u64> = HashMap::new();
+    for thread in threads.iter() {
+        *usage_per_name.entry(&thread.name).or_default() += thread.cpu_usage;
+    }
+    for (name, usage) in usage_per_name {
+        THREAD_CPU_USAGE.with_label_values(&[name]).set(usage as i64);
+    }
+    threads.sort_by(|a, b| b.cpu_usage.cmp(&a.cpu_usage).then(a.tid.cmp(&b.tid)));
+    threads
+}
+
+/// Parses the name and the user and system CPU time in ticks from `/proc/<pid>/task/<tid>/stat`.
+fn parse_thread_stat(stat: &str) -> Option<(String, u64)> {
+    // The name is in parentheses and may contain spaces and parentheses itself.
+    let name_start = stat.find('(')?;
+    let name_end = stat.rfind(')')?;
+    let name = stat.get(name_start + 1..name_end)?.to_string();
+    let fields: Vec<&str> = stat.get(name_end + 1..)?.split_whitespace().collect();
+    // Fields 14 and 15 of the stat, counting from the pid.
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_thread_stat;
+
+    #[test]
+    fn test_parse_thread_stat() {
+        let stat = "1234 (actix-rt|system) S 1 1234 1234 0 -1 4194368 1200 0 0 0 150 25 0 0 20 0 30 0 100 0 0";
+        assert_eq!(parse_thread_stat(stat), Some(("actix-rt|system".to_string(), 175)));
+        let stat = "1235 (a (b) c) R 1 1234 1234 0 -1 4194368 1200 0 0 0 7 3 0 0";
+        assert_eq!(parse_thread_stat(stat), Some(("a (b) c".to_string(), 10)));
+        assert_eq!(parse_thread_stat("1236 (broken"), None);
+    }
+}